@@ -0,0 +1,506 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Versioned archive format: the upgrade pipeline and strict validation for the
+//! JSON documents inside an export archive.
+//!
+//! The format version is the `version` field of `notes.json`; `workspace.json`
+//! carries the same number. Archives written by older builds are upgraded one
+//! version at a time by [`upgrade_archive`], then [`validate_archive`] checks them
+//! against the current format, so the typed structs in [`crate::core::export`]
+//! only ever see current-format documents.
+//!
+//! The format is specified in `docs/archive-format.md`. JSON Schemas for each
+//! document are published under `krillnotes-core/schemas/archive/` and exposed as
+//! [`NOTES_JSON_SCHEMA`], [`WORKSPACE_JSON_SCHEMA`] and [`SCRIPTS_JSON_SCHEMA`].
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use chrono::NaiveDate;
+use serde_json::{Map, Value};
+
+use crate::core::export::ExportError;
+
+/// Archive format version written by [`crate::export_workspace`].
+pub const ARCHIVE_FORMAT_VERSION: u32 = 2;
+
+/// JSON Schema (draft 2020-12) for `notes.json` in the current format.
+pub const NOTES_JSON_SCHEMA: &str = include_str!("../../schemas/archive/v2/notes.schema.json");
+
+/// JSON Schema (draft 2020-12) for `workspace.json` in the current format.
+pub const WORKSPACE_JSON_SCHEMA: &str =
+    include_str!("../../schemas/archive/v2/workspace.schema.json");
+
+/// JSON Schema (draft 2020-12) for `scripts/scripts.json` in the current format.
+pub const SCRIPTS_JSON_SCHEMA: &str = include_str!("../../schemas/archive/v2/scripts.schema.json");
+
+pub(crate) const NOTES_FILE: &str = "notes.json";
+pub(crate) const WORKSPACE_FILE: &str = "workspace.json";
+pub(crate) const SCRIPTS_FILE: &str = "scripts/scripts.json";
+
+/// The JSON documents of an archive, read but not yet deserialized.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveDocuments {
+    /// `notes.json`.
+    pub notes: Value,
+    /// `workspace.json`; absent in archives from before workspace metadata.
+    pub workspace: Option<Value>,
+    /// `scripts/scripts.json`; absent in archives without user scripts.
+    pub scripts: Option<Value>,
+}
+
+/// A value in an archive document that does not match the format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveValidationError {
+    /// Archive entry the problem is in, e.g. `notes.json`.
+    pub file: String,
+    /// ID of the offending note, when the problem is inside one.
+    pub note_id: Option<String>,
+    /// JSON Pointer (RFC 6901) to the offending value; empty for the document root.
+    pub pointer: String,
+    pub message: String,
+}
+
+impl fmt::Display for ArchiveValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.file)?;
+        if let Some(id) = &self.note_id {
+            write!(f, ", note '{id}'")?;
+        }
+        let pointer = if self.pointer.is_empty() {
+            "/"
+        } else {
+            &self.pointer
+        };
+        write!(f, " at {pointer}: {}", self.message)
+    }
+}
+
+impl std::error::Error for ArchiveValidationError {}
+
+/// One upgrade step; `MIGRATIONS[n - 1]` turns a version `n` archive into version
+/// `n + 1`. The array length ties the chain to [`ARCHIVE_FORMAT_VERSION`], so
+/// bumping the version without adding a step does not compile.
+type Migration = fn(&mut ArchiveDocuments);
+
+const MIGRATIONS: [Migration; ARCHIVE_FORMAT_VERSION as usize - 1] = [migrate_v1_to_v2];
+
+/// Reads the format version from a `notes.json` document.
+///
+/// # Errors
+///
+/// Returns an [`ArchiveValidationError`] if `version` is missing or not a
+/// positive integer.
+pub fn archive_version(notes: &Value) -> Result<u32, ArchiveValidationError> {
+    notes
+        .get("version")
+        .and_then(Value::as_u64)
+        .and_then(|v| u32::try_from(v).ok())
+        .filter(|v| *v >= 1)
+        .ok_or_else(|| {
+            Location::root(NOTES_FILE)
+                .child("version")
+                .error("expected a positive integer format version")
+        })
+}
+
+/// Upgrades `docs` in place to [`ARCHIVE_FORMAT_VERSION`], one version at a time,
+/// and returns the version the archive was written in.
+///
+/// # Errors
+///
+/// Returns [`ExportError::Validation`] if `notes.json` has no usable `version`,
+/// and [`ExportError::InvalidFormat`] if the archive was written by a newer build.
+pub fn upgrade_archive(docs: &mut ArchiveDocuments) -> Result<u32, ExportError> {
+    let source = archive_version(&docs.notes)?;
+    if source > ARCHIVE_FORMAT_VERSION {
+        return Err(ExportError::InvalidFormat(format!(
+            "Unsupported export format version: {source} (this build reads versions up to {ARCHIVE_FORMAT_VERSION})"
+        )));
+    }
+    for (from, migration) in (source..).zip(&MIGRATIONS[source as usize - 1..]) {
+        migration(docs);
+        set_version(docs, from + 1);
+    }
+    Ok(source)
+}
+
+fn set_version(docs: &mut ArchiveDocuments, version: u32) {
+    if let Some(notes) = docs.notes.as_object_mut() {
+        notes.insert("version".into(), version.into());
+    }
+    if let Some(workspace) = docs.workspace.as_mut().and_then(Value::as_object_mut) {
+        workspace.insert("version".into(), version.into());
+    }
+}
+
+/// Version 1 → 2: makes explicit the legacy shapes that 1.x builds tolerated
+/// while reading.
+///
+/// - Notes: `nodeType` is renamed to `schema`; `createdBy` / `modifiedBy` written
+///   as the integer `0` (before identities existed) become `""`; missing
+///   `createdBy`, `modifiedBy`, `tags`, `schemaVersion` and `isChecked` get their
+///   defaults.
+/// - Scripts: a missing `category` becomes `"schema"` and the retired
+///   `"presentation"` category becomes `"library"`.
+fn migrate_v1_to_v2(docs: &mut ArchiveDocuments) {
+    if let Some(notes) = docs.notes.get_mut("notes").and_then(Value::as_array_mut) {
+        for note in notes.iter_mut().filter_map(Value::as_object_mut) {
+            let node_type = note.remove("nodeType");
+            if let (false, Some(schema)) = (note.contains_key("schema"), node_type) {
+                note.insert("schema".into(), schema);
+            }
+            for key in ["createdBy", "modifiedBy"] {
+                if matches!(note.get(key), None | Some(Value::Number(_))) {
+                    note.insert(key.into(), "".into());
+                }
+            }
+            note.entry("tags")
+                .or_insert_with(|| Value::Array(Vec::new()));
+            note.entry("schemaVersion").or_insert_with(|| 1.into());
+            note.entry("isChecked").or_insert(Value::Bool(false));
+        }
+    }
+
+    let entries = docs
+        .scripts
+        .as_mut()
+        .and_then(|s| s.get_mut("scripts"))
+        .and_then(Value::as_array_mut);
+    for entry in entries
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut)
+    {
+        let category = match entry.get("category") {
+            None | Some(Value::Null) => "schema",
+            Some(Value::String(c)) if c == "presentation" => "library",
+            _ => continue,
+        };
+        entry.insert("category".into(), category.into());
+    }
+}
+
+/// Checks upgraded documents against the current format.
+///
+/// Unknown properties are rejected: any change to the documents' shape needs a
+/// new format version and a migration step.
+///
+/// # Errors
+///
+/// Returns the first [`ArchiveValidationError`] found.
+pub fn validate_archive(docs: &ArchiveDocuments) -> Result<(), ArchiveValidationError> {
+    validate_notes(&docs.notes)?;
+    if let Some(workspace) = &docs.workspace {
+        let at = Location::root(WORKSPACE_FILE);
+        let map = check_object(workspace, &at, WORKSPACE_PROPS)?;
+        check_version(map, &at)?;
+    }
+    if let Some(scripts) = &docs.scripts {
+        validate_scripts(scripts)?;
+    }
+    Ok(())
+}
+
+/// Where a value sits in an archive document.
+struct Location {
+    file: &'static str,
+    note_id: Option<String>,
+    pointer: String,
+}
+
+impl Location {
+    fn root(file: &'static str) -> Self {
+        Self {
+            file,
+            note_id: None,
+            pointer: String::new(),
+        }
+    }
+
+    fn child(&self, key: impl fmt::Display) -> Self {
+        let key = key.to_string().replace('~', "~0").replace('/', "~1");
+        Self {
+            file: self.file,
+            note_id: self.note_id.clone(),
+            pointer: format!("{}/{key}", self.pointer),
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> ArchiveValidationError {
+        ArchiveValidationError {
+            file: self.file.to_string(),
+            note_id: self.note_id.clone(),
+            pointer: self.pointer.clone(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Expected JSON type of a property.
+#[derive(Clone, Copy)]
+enum Kind {
+    String,
+    NonEmptyString,
+    StringOrNull,
+    Integer,
+    PositiveInteger,
+    Number,
+    Bool,
+    Array,
+    StringArray,
+    Object,
+    OneOf(&'static [&'static str]),
+}
+
+impl Kind {
+    /// Returns a description of the expected value if `value` does not match.
+    fn check(self, value: &Value) -> Result<(), String> {
+        let ok = match self {
+            Kind::String => value.is_string(),
+            Kind::NonEmptyString => value.as_str().is_some_and(|s| !s.is_empty()),
+            Kind::StringOrNull => value.is_string() || value.is_null(),
+            Kind::Integer => value.is_i64(),
+            Kind::PositiveInteger => value
+                .as_u64()
+                .is_some_and(|v| v >= 1 && v <= u64::from(u32::MAX)),
+            Kind::Number => value.is_number(),
+            Kind::Bool => value.is_boolean(),
+            Kind::Array => value.is_array(),
+            Kind::StringArray => value
+                .as_array()
+                .is_some_and(|items| items.iter().all(Value::is_string)),
+            Kind::Object => value.is_object(),
+            Kind::OneOf(allowed) => value.as_str().is_some_and(|s| allowed.contains(&s)),
+        };
+        if ok {
+            return Ok(());
+        }
+        Err(match self {
+            Kind::String => "expected a string".to_string(),
+            Kind::NonEmptyString => "expected a non-empty string".to_string(),
+            Kind::StringOrNull => "expected a string or null".to_string(),
+            Kind::Integer => "expected an integer".to_string(),
+            Kind::PositiveInteger => "expected a positive integer".to_string(),
+            Kind::Number => "expected a number".to_string(),
+            Kind::Bool => "expected true or false".to_string(),
+            Kind::Array => "expected an array".to_string(),
+            Kind::StringArray => "expected an array of strings".to_string(),
+            Kind::Object => "expected an object".to_string(),
+            Kind::OneOf(allowed) => format!("expected one of {}", allowed.join(", ")),
+        })
+    }
+}
+
+/// A property of an object: name, expected type, and whether it is required.
+type Prop = (&'static str, Kind, bool);
+
+const NOTES_DOC_PROPS: &[Prop] = &[
+    ("version", Kind::PositiveInteger, true),
+    ("appVersion", Kind::String, true),
+    ("notes", Kind::Array, true),
+    ("subtree", Kind::Object, false),
+];
+
+const SUBTREE_PROPS: &[Prop] = &[
+    ("rootId", Kind::NonEmptyString, true),
+    ("rootTitle", Kind::String, true),
+    (
+        "referencedNotes",
+        Kind::OneOf(&["exclude", "stubs", "fullCopies"]),
+        true,
+    ),
+    ("referencedNoteIds", Kind::StringArray, false),
+];
+
+const NOTE_PROPS: &[Prop] = &[
+    ("id", Kind::NonEmptyString, true),
+    ("title", Kind::String, true),
+    ("schema", Kind::NonEmptyString, true),
+    ("parentId", Kind::StringOrNull, true),
+    ("position", Kind::Number, true),
+    ("createdAt", Kind::Integer, true),
+    ("modifiedAt", Kind::Integer, true),
+    ("createdBy", Kind::String, true),
+    ("modifiedBy", Kind::String, true),
+    ("fields", Kind::Object, true),
+    ("isExpanded", Kind::Bool, true),
+    ("tags", Kind::StringArray, true),
+    ("schemaVersion", Kind::PositiveInteger, true),
+    ("isChecked", Kind::Bool, true),
+];
+
+const WORKSPACE_PROPS: &[Prop] = &[
+    ("version", Kind::PositiveInteger, true),
+    ("authorName", Kind::StringOrNull, false),
+    ("authorOrg", Kind::StringOrNull, false),
+    ("homepageUrl", Kind::StringOrNull, false),
+    ("description", Kind::StringOrNull, false),
+    ("license", Kind::StringOrNull, false),
+    ("licenseUrl", Kind::StringOrNull, false),
+    ("language", Kind::StringOrNull, false),
+    ("tags", Kind::StringArray, false),
+    ("ownerPubkey", Kind::StringOrNull, false),
+];
+
+const SCRIPTS_DOC_PROPS: &[Prop] = &[("scripts", Kind::Array, true)];
+
+const SCRIPT_ENTRY_PROPS: &[Prop] = &[
+    ("filename", Kind::NonEmptyString, true),
+    ("loadOrder", Kind::Integer, true),
+    ("enabled", Kind::Bool, true),
+    ("category", Kind::OneOf(&["schema", "library"]), true),
+];
+
+fn check_object<'v>(
+    value: &'v Value,
+    at: &Location,
+    props: &[Prop],
+) -> Result<&'v Map<String, Value>, ArchiveValidationError> {
+    let map = value
+        .as_object()
+        .ok_or_else(|| at.error("expected an object"))?;
+    if let Some(unknown) = map
+        .keys()
+        .find(|k| !props.iter().any(|(name, ..)| name == k))
+    {
+        return Err(at
+            .child(unknown)
+            .error(format!("unknown property '{unknown}'")));
+    }
+    for &(name, kind, required) in props {
+        match map.get(name) {
+            Some(value) => kind.check(value).map_err(|m| at.child(name).error(m))?,
+            None if required => {
+                return Err(at.error(format!("missing required property '{name}'")));
+            }
+            None => {}
+        }
+    }
+    Ok(map)
+}
+
+fn check_version(map: &Map<String, Value>, at: &Location) -> Result<(), ArchiveValidationError> {
+    match map.get("version").and_then(Value::as_u64) {
+        Some(v) if v == u64::from(ARCHIVE_FORMAT_VERSION) => Ok(()),
+        _ => Err(at
+            .child("version")
+            .error(format!("expected format version {ARCHIVE_FORMAT_VERSION}"))),
+    }
+}
+
+/// Checks one entry of a note's `fields` map, e.g. `{"Date": "2026-02-19"}`.
+fn check_field_value(value: &Value) -> Result<(), String> {
+    let Some((variant, inner)) = value
+        .as_object()
+        .filter(|m| m.len() == 1)
+        .and_then(|m| m.iter().next())
+    else {
+        return Err("expected an object with a single type key, e.g. {\"Text\": \"...\"}".into());
+    };
+    let (ok, expected) = match variant.as_str() {
+        "Text" | "Email" => (inner.is_string(), "a string"),
+        "Number" => (inner.is_number(), "a number"),
+        "Boolean" => (inner.is_boolean(), "true or false"),
+        "Date" => (
+            inner.is_null()
+                || inner
+                    .as_str()
+                    .is_some_and(|s| NaiveDate::parse_from_str(s, "%Y-%m-%d").is_ok()),
+            "a \"YYYY-MM-DD\" date or null",
+        ),
+        "NoteLink" | "File" => (inner.is_null() || inner.is_string(), "a string or null"),
+        other => return Err(format!("unknown field type '{other}'")),
+    };
+    if ok {
+        Ok(())
+    } else {
+        Err(format!("{variant} value must be {expected}"))
+    }
+}
+
+fn validate_notes(doc: &Value) -> Result<(), ArchiveValidationError> {
+    let root = Location::root(NOTES_FILE);
+    let map = check_object(doc, &root, NOTES_DOC_PROPS)?;
+    check_version(map, &root)?;
+    if let Some(subtree) = map.get("subtree") {
+        check_object(subtree, &root.child("subtree"), SUBTREE_PROPS)?;
+    }
+
+    let notes = map["notes"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let mut parents: HashMap<&str, Option<&str>> = HashMap::with_capacity(notes.len());
+    for (index, note) in notes.iter().enumerate() {
+        let mut at = root.child("notes").child(index);
+        let id = note.get("id").and_then(Value::as_str);
+        at.note_id = id.map(str::to_string);
+        let note = check_object(note, &at, NOTE_PROPS)?;
+        let fields = note["fields"].as_object().into_iter().flatten();
+        for (name, value) in fields {
+            check_field_value(value).map_err(|m| at.child("fields").child(name).error(m))?;
+        }
+        let id = id.unwrap_or_default();
+        let parent_id = note["parentId"].as_str();
+        if parents.insert(id, parent_id).is_some() {
+            return Err(at.child("id").error("duplicate note id"));
+        }
+    }
+
+    for (index, note) in notes.iter().enumerate() {
+        let id = note["id"].as_str().unwrap_or_default();
+        let Some(parent_id) = parents[id] else {
+            continue;
+        };
+        let mut at = root.child("notes").child(index).child("parentId");
+        at.note_id = Some(id.to_string());
+        if !parents.contains_key(parent_id) {
+            return Err(at.error(format!("parent note '{parent_id}' is not in the archive")));
+        }
+        // Walk up at most `notes.len()` steps; reaching the note again means a cycle.
+        let mut ancestor = Some(parent_id);
+        for _ in 0..notes.len() {
+            match ancestor {
+                Some(a) if a == id => {
+                    return Err(at.error("note is its own ancestor"));
+                }
+                Some(a) => ancestor = parents.get(a).copied().flatten(),
+                None => break,
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validate_scripts(doc: &Value) -> Result<(), ArchiveValidationError> {
+    let root = Location::root(SCRIPTS_FILE);
+    let map = check_object(doc, &root, SCRIPTS_DOC_PROPS)?;
+    let entries = map["scripts"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let mut filenames = HashSet::new();
+    for (index, entry) in entries.iter().enumerate() {
+        let at = root.child("scripts").child(index);
+        let entry = check_object(entry, &at, SCRIPT_ENTRY_PROPS)?;
+        let filename = entry["filename"].as_str().unwrap_or_default();
+        if filename.contains(['/', '\\']) || filename == "." || filename == ".." {
+            return Err(at
+                .child("filename")
+                .error("expected a file name inside scripts/"));
+        }
+        if !filenames.insert(filename) {
+            return Err(at.child("filename").error("duplicate script filename"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[path = "archive_format_tests.rs"]
+mod tests;
@@ -0,0 +1,313 @@
+use super::*;
+use crate::core::export::{import_workspace, peek_import};
+use crate::core::workspace::Workspace;
+use crate::FieldValue;
+use serde_json::json;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+/// Golden fixtures, one per historical archive shape, oldest first. Each has an
+/// `archive/` directory with the files as they appear in the zip and an
+/// `expected/` directory with the documents after upgrading.
+const FIXTURES: &[(&str, u32)] = &[
+    ("v1-node-type", 1),
+    ("v1-tags", 1),
+    ("v1-metadata", 1),
+    ("v2-subtree", 2),
+];
+
+fn fixture_dir(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/archives")
+        .join(name)
+}
+
+fn read_json(path: &Path) -> Option<Value> {
+    let bytes = std::fs::read(path).ok()?;
+    Some(serde_json::from_slice(&bytes).unwrap())
+}
+
+fn fixture_documents(name: &str) -> (ArchiveDocuments, ArchiveDocuments) {
+    let dir = fixture_dir(name);
+    let load = |base: &Path, scripts: &str| ArchiveDocuments {
+        notes: read_json(&base.join(NOTES_FILE)).unwrap(),
+        workspace: read_json(&base.join(WORKSPACE_FILE)),
+        scripts: read_json(&base.join(scripts)),
+    };
+    (
+        load(&dir.join("archive"), SCRIPTS_FILE),
+        load(&dir.join("expected"), "scripts.json"),
+    )
+}
+
+fn add_dir_to_zip(zip: &mut zip::ZipWriter<Cursor<&mut Vec<u8>>>, root: &Path, dir: &Path) {
+    let mut entries: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            add_dir_to_zip(zip, root, &path);
+        } else {
+            let name = path.strip_prefix(root).unwrap().to_string_lossy();
+            zip.start_file(
+                name.replace('\\', "/"),
+                zip::write::SimpleFileOptions::default(),
+            )
+            .unwrap();
+            zip.write_all(&std::fs::read(&path).unwrap()).unwrap();
+        }
+    }
+}
+
+fn fixture_zip(name: &str) -> Vec<u8> {
+    let root = fixture_dir(name).join("archive");
+    let mut buf = Vec::new();
+    let mut zip = zip::ZipWriter::new(Cursor::new(&mut buf));
+    add_dir_to_zip(&mut zip, &root, &root);
+    zip.finish().unwrap();
+    buf
+}
+
+fn current_notes(notes: Value) -> ArchiveDocuments {
+    ArchiveDocuments {
+        notes: json!({ "version": ARCHIVE_FORMAT_VERSION, "appVersion": "1.1.0", "notes": notes }),
+        workspace: None,
+        scripts: None,
+    }
+}
+
+fn note(id: &str, parent_id: Option<&str>) -> Value {
+    json!({
+        "id": id, "title": id, "schema": "TextNote", "parentId": parent_id, "position": 0.0,
+        "createdAt": 0, "modifiedAt": 0, "createdBy": "", "modifiedBy": "",
+        "fields": {}, "isExpanded": false, "tags": [], "schemaVersion": 1, "isChecked": false
+    })
+}
+
+#[test]
+fn test_golden_archives_upgrade_to_expected_documents() {
+    for &(name, version) in FIXTURES {
+        let (mut docs, expected) = fixture_documents(name);
+        assert_eq!(upgrade_archive(&mut docs).unwrap(), version, "{name}");
+        validate_archive(&docs).unwrap_or_else(|e| panic!("{name}: {e}"));
+        assert_eq!(docs.notes, expected.notes, "{name}: notes.json");
+        assert_eq!(docs.workspace, expected.workspace, "{name}: workspace.json");
+        assert_eq!(docs.scripts, expected.scripts, "{name}: scripts.json");
+    }
+}
+
+#[test]
+fn test_golden_archives_import() {
+    for &(name, version) in FIXTURES {
+        let zip = fixture_zip(name);
+        let (_, expected) = fixture_documents(name);
+        let expected_notes = expected.notes["notes"].as_array().unwrap();
+
+        let peeked = peek_import(Cursor::new(&zip), None).unwrap();
+        assert_eq!(peeked.format_version, version, "{name}");
+        assert_eq!(peeked.note_count, expected_notes.len(), "{name}");
+
+        let db = NamedTempFile::new().unwrap();
+        let result = import_workspace(
+            Cursor::new(&zip),
+            db.path(),
+            None,
+            "",
+            "test-identity",
+            ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]),
+        )
+        .unwrap_or_else(|e| panic!("{name}: {e}"));
+        assert_eq!(result.format_version, version, "{name}");
+
+        let ws = Workspace::open(
+            db.path(),
+            "",
+            "test-identity",
+            ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]),
+            Box::new(crate::core::permission::AllowAllGate::new("test")),
+            None,
+        )
+        .unwrap();
+        for expected_note in expected_notes {
+            let id = expected_note["id"].as_str().unwrap();
+            let imported = ws.get_note(id).unwrap();
+            assert_eq!(imported.title, expected_note["title"], "{name}");
+            assert_eq!(imported.schema, expected_note["schema"], "{name}");
+            assert_eq!(json!(imported.tags), expected_note["tags"], "{name}");
+            assert_eq!(json!(imported.fields), expected_note["fields"], "{name}");
+        }
+        let categories: Vec<String> = ws
+            .list_user_scripts()
+            .unwrap()
+            .into_iter()
+            .map(|s| s.category)
+            .collect();
+        let expected_categories: Vec<&str> = expected
+            .scripts
+            .as_ref()
+            .map(|s| s["scripts"].as_array().unwrap().as_slice())
+            .unwrap_or_default()
+            .iter()
+            .map(|e| e["category"].as_str().unwrap())
+            .collect();
+        assert_eq!(categories, expected_categories, "{name}");
+    }
+}
+
+#[test]
+fn test_legacy_node_type_and_integer_authors_are_upgraded() {
+    let (docs, _) = fixture_documents("v1-node-type");
+    let mut docs = docs;
+    upgrade_archive(&mut docs).unwrap();
+    let first = &docs.notes["notes"][0];
+    assert_eq!(first["schema"], "TextNote");
+    assert!(first.get("nodeType").is_none());
+    assert_eq!(first["createdBy"], "");
+    assert_eq!(first["isChecked"], false);
+
+    let notes: crate::ExportNotes = serde_json::from_value(docs.notes).unwrap();
+    assert!(matches!(
+        notes.notes[1].fields.get("done"),
+        Some(FieldValue::Boolean(false))
+    ));
+}
+
+#[test]
+fn test_upgrade_rejects_newer_and_missing_versions() {
+    let mut docs = current_notes(json!([]));
+    docs.notes["version"] = json!(ARCHIVE_FORMAT_VERSION + 1);
+    assert!(matches!(
+        upgrade_archive(&mut docs),
+        Err(ExportError::InvalidFormat(_))
+    ));
+
+    docs.notes["version"] = json!(0);
+    match upgrade_archive(&mut docs) {
+        Err(ExportError::Validation(e)) => assert_eq!(e.pointer, "/version"),
+        other => panic!("expected a validation error, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_validation_error_points_at_offending_note() {
+    let mut bad = note("n2", Some("n1"));
+    bad["fields"] = json!({ "due/date": { "Date": "next week" } });
+    let docs = current_notes(json!([note("n1", None), bad]));
+
+    let err = validate_archive(&docs).unwrap_err();
+    assert_eq!(err.file, "notes.json");
+    assert_eq!(err.note_id.as_deref(), Some("n2"));
+    assert_eq!(err.pointer, "/notes/1/fields/due~1date");
+    assert_eq!(
+        err.to_string(),
+        "notes.json, note 'n2' at /notes/1/fields/due~1date: Date value must be a \"YYYY-MM-DD\" date or null"
+    );
+
+    let mut unknown = note("n3", None);
+    unknown["nodeType"] = json!("TextNote");
+    let err = validate_archive(&current_notes(json!([unknown]))).unwrap_err();
+    assert_eq!(err.pointer, "/notes/0/nodeType");
+    assert_eq!(err.note_id.as_deref(), Some("n3"));
+}
+
+#[test]
+fn test_validation_rejects_broken_tree() {
+    let duplicate = current_notes(json!([note("a", None), note("a", None)]));
+    assert_eq!(
+        validate_archive(&duplicate).unwrap_err().pointer,
+        "/notes/1/id"
+    );
+
+    let dangling = current_notes(json!([note("a", None), note("b", Some("missing"))]));
+    let err = validate_archive(&dangling).unwrap_err();
+    assert_eq!(err.note_id.as_deref(), Some("b"));
+    assert!(err.message.contains("'missing'"), "{err}");
+
+    let cycle = current_notes(json!([note("a", Some("b")), note("b", Some("a"))]));
+    let err = validate_archive(&cycle).unwrap_err();
+    assert!(err.message.contains("own ancestor"), "{err}");
+}
+
+#[test]
+fn test_validation_checks_scripts_and_workspace() {
+    let mut docs = current_notes(json!([]));
+    docs.scripts = Some(json!({ "scripts": [
+        { "filename": "a.rhai", "loadOrder": 0, "enabled": true, "category": "schema" },
+        { "filename": "../a.rhai", "loadOrder": 1, "enabled": true, "category": "schema" }
+    ]}));
+    let err = validate_archive(&docs).unwrap_err();
+    assert_eq!(err.file, "scripts/scripts.json");
+    assert_eq!(err.pointer, "/scripts/1/filename");
+
+    docs.scripts = None;
+    docs.workspace = Some(json!({ "version": 1 }));
+    let err = validate_archive(&docs).unwrap_err();
+    assert_eq!(err.file, "workspace.json");
+    assert_eq!(err.pointer, "/version");
+}
+
+/// The hand-written validator and the published JSON Schemas must describe the
+/// same documents.
+#[test]
+fn test_published_schemas_match_validator() {
+    fn schema_props(object: &Value) -> (Vec<String>, Vec<String>) {
+        let mut props: Vec<String> = object["properties"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        let mut required: Vec<String> = object["required"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v.as_str().unwrap().to_string())
+            .collect();
+        props.sort();
+        required.sort();
+        (props, required)
+    }
+    fn validator_props(props: &[Prop]) -> (Vec<String>, Vec<String>) {
+        let mut names: Vec<String> = props.iter().map(|(n, ..)| n.to_string()).collect();
+        let mut required: Vec<String> = props
+            .iter()
+            .filter(|(.., r)| *r)
+            .map(|(n, ..)| n.to_string())
+            .collect();
+        names.sort();
+        required.sort();
+        (names, required)
+    }
+
+    let notes: Value = serde_json::from_str(NOTES_JSON_SCHEMA).unwrap();
+    let workspace: Value = serde_json::from_str(WORKSPACE_JSON_SCHEMA).unwrap();
+    let scripts: Value = serde_json::from_str(SCRIPTS_JSON_SCHEMA).unwrap();
+
+    assert_eq!(
+        notes["properties"]["version"]["const"],
+        ARCHIVE_FORMAT_VERSION
+    );
+    assert_eq!(
+        workspace["properties"]["version"]["const"],
+        ARCHIVE_FORMAT_VERSION
+    );
+
+    assert_eq!(schema_props(&notes), validator_props(NOTES_DOC_PROPS));
+    assert_eq!(
+        schema_props(&notes["$defs"]["note"]),
+        validator_props(NOTE_PROPS)
+    );
+    assert_eq!(
+        schema_props(&notes["$defs"]["subtree"]),
+        validator_props(SUBTREE_PROPS)
+    );
+    assert_eq!(schema_props(&workspace), validator_props(WORKSPACE_PROPS));
+    assert_eq!(schema_props(&scripts), validator_props(SCRIPTS_DOC_PROPS));
+    assert_eq!(
+        schema_props(&scripts["properties"]["scripts"]["items"]),
+        validator_props(SCRIPT_ENTRY_PROPS)
+    );
+}
@@ -8,13 +8,14 @@
 
 use crate::{KrillnotesError, Result};
 use chacha20poly1305::{
-    aead::{Aead, KeyInit},
+    aead::{Aead, KeyInit, Payload},
     ChaCha20Poly1305, Key, Nonce,
 };
 use hkdf::Hkdf;
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
-use sha2::Sha256;
+use sha2::{Digest, Sha256};
+use std::io::{Read, Seek, SeekFrom, Write};
 
 use super::timestamp::UnixSecs;
 
@@ -53,65 +54,382 @@ fn derive_file_key(attachment_key: &[u8; 32], file_salt: &[u8; 32]) -> [u8; 32]
     key
 }
 
-/// Encrypts `plaintext` using ChaCha20-Poly1305.
+/// Magic bytes at the start of a framed (streaming) attachment file.
+const FRAMED_MAGIC: [u8; 4] = *b"KNAT";
+
+/// Format version written after [`FRAMED_MAGIC`]. Version 1 is the original
+/// single-shot `[12-byte nonce][ciphertext+tag]` layout, which has no header.
+const FRAMED_VERSION: u8 = 2;
+
+/// Length of the framed header: magic, version, chunk size (u32 LE), nonce prefix.
+const FRAMED_HEADER_LEN: usize = 4 + 1 + 4 + 7;
+
+/// Plaintext bytes per frame when encrypting. Each frame on disk is this many
+/// bytes plus a 16-byte Poly1305 tag (the last frame may be shorter).
+pub const ATTACHMENT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Upper bound accepted for the chunk size recorded in a framed header, so a
+/// corrupt header cannot make the decoder allocate an unbounded buffer.
+const MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+const TAG_LEN: usize = 16;
+
+/// Summary of an attachment written by [`encrypt_attachment_stream`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamedAttachment {
+    /// Per-file HKDF salt (all zeroes for unencrypted workspaces).
+    pub salt: [u8; 32],
+    /// Number of plaintext bytes read from the source.
+    pub size_bytes: u64,
+    /// Hex-encoded SHA-256 of the plaintext.
+    pub hash_sha256: String,
+}
+
+/// Builds the per-frame nonce: `[7-byte prefix][u32 BE counter][last-frame flag]`.
 ///
-/// If `key` is `None` (unencrypted workspace), bytes are returned unchanged.
-/// Otherwise the output format is: `[12-byte nonce][ciphertext+16-byte tag]`.
-/// Returns `(encrypted_bytes, file_salt)`.
-pub fn encrypt_attachment(plaintext: &[u8], key: Option<&[u8; 32]>) -> Result<(Vec<u8>, [u8; 32])> {
+/// Binding the counter and the last-frame flag into the nonce means frames cannot
+/// be reordered, dropped or truncated without failing authentication.
+fn frame_nonce(prefix: &[u8; 7], counter: u32, last: bool) -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    nonce[..7].copy_from_slice(prefix);
+    nonce[7..11].copy_from_slice(&counter.to_be_bytes());
+    nonce[11] = last as u8;
+    nonce
+}
+
+/// Reads until `buf` is full or the reader is exhausted. Returns the byte count.
+fn read_full<R: Read + ?Sized>(reader: &mut R, buf: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(filled)
+}
+
+/// Encrypts everything read from `reader` into `writer` in bounded memory.
+///
+/// If `key` is `None` (unencrypted workspace), bytes are copied unchanged.
+/// Otherwise the output is a framed stream:
+/// `[header][frame 0]...[frame n]`, where the header is `b"KNAT"`, a version
+/// byte (`2`), the plaintext chunk size as a little-endian `u32` and a random
+/// 7-byte nonce prefix. Each frame is a ChaCha20-Poly1305 ciphertext of one
+/// chunk, authenticated with the header as associated data.
+pub fn encrypt_attachment_stream<R: Read + ?Sized, W: Write + ?Sized>(
+    reader: &mut R,
+    writer: &mut W,
+    key: Option<&[u8; 32]>,
+) -> Result<StreamedAttachment> {
+    let mut hasher = Sha256::new();
+    let mut size_bytes = 0u64;
+
     let Some(attachment_key) = key else {
-        // Unencrypted workspace — store plaintext, return zero salt
-        return Ok((plaintext.to_vec(), [0u8; 32]));
+        // Unencrypted workspace — copy plaintext, return zero salt
+        let mut buf = vec![0u8; ATTACHMENT_CHUNK_SIZE];
+        loop {
+            let n = read_full(reader, &mut buf)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+            writer.write_all(&buf[..n])?;
+            size_bytes += n as u64;
+        }
+        return Ok(StreamedAttachment {
+            salt: [0u8; 32],
+            size_bytes,
+            hash_sha256: format!("{:x}", hasher.finalize()),
+        });
     };
 
-    let mut nonce_bytes = [0u8; 12];
     let mut file_salt = [0u8; 32];
-    rand::rng().fill_bytes(&mut nonce_bytes);
+    let mut nonce_prefix = [0u8; 7];
     rand::rng().fill_bytes(&mut file_salt);
+    rand::rng().fill_bytes(&mut nonce_prefix);
 
     let file_key = derive_file_key(attachment_key, &file_salt);
     let cipher = ChaCha20Poly1305::new(Key::from_slice(&file_key));
-    let nonce = Nonce::from_slice(&nonce_bytes);
-    let ciphertext = cipher
-        .encrypt(nonce, plaintext)
-        .map_err(|e| KrillnotesError::AttachmentEncryption(e.to_string()))?;
 
-    // Format: [12-byte nonce][ciphertext+16-byte tag]
-    let mut output = Vec::with_capacity(12 + ciphertext.len());
-    output.extend_from_slice(&nonce_bytes);
-    output.extend_from_slice(&ciphertext);
+    let mut header = [0u8; FRAMED_HEADER_LEN];
+    header[..4].copy_from_slice(&FRAMED_MAGIC);
+    header[4] = FRAMED_VERSION;
+    header[5..9].copy_from_slice(&(ATTACHMENT_CHUNK_SIZE as u32).to_le_bytes());
+    header[9..].copy_from_slice(&nonce_prefix);
+    writer.write_all(&header)?;
+
+    // One chunk of look-ahead: a chunk is only known to be the last one once the
+    // following read comes back empty.
+    let mut current = vec![0u8; ATTACHMENT_CHUNK_SIZE];
+    let mut next = vec![0u8; ATTACHMENT_CHUNK_SIZE];
+    let mut current_len = read_full(reader, &mut current)?;
+    let mut counter = 0u32;
+    loop {
+        let next_len = if current_len == ATTACHMENT_CHUNK_SIZE {
+            read_full(reader, &mut next)?
+        } else {
+            0
+        };
+        let last = next_len == 0;
+
+        let chunk = &current[..current_len];
+        hasher.update(chunk);
+        size_bytes += current_len as u64;
+        let nonce = frame_nonce(&nonce_prefix, counter, last);
+        let ciphertext = cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: chunk,
+                    aad: &header,
+                },
+            )
+            .map_err(|e| KrillnotesError::AttachmentEncryption(e.to_string()))?;
+        writer.write_all(&ciphertext)?;
+
+        if last {
+            break;
+        }
+        counter = counter.checked_add(1).ok_or_else(|| {
+            KrillnotesError::AttachmentEncryption("Attachment too large to encrypt".to_string())
+        })?;
+        std::mem::swap(&mut current, &mut next);
+        current_len = next_len;
+    }
+
+    Ok(StreamedAttachment {
+        salt: file_salt,
+        size_bytes,
+        hash_sha256: format!("{:x}", hasher.finalize()),
+    })
+}
+
+/// Builds the cipher for one file from the workspace key and its per-file salt.
+fn file_cipher(attachment_key: &[u8; 32], salt: &[u8]) -> Result<ChaCha20Poly1305> {
+    let salt_array: [u8; 32] = salt
+        .try_into()
+        .map_err(|_| KrillnotesError::AttachmentEncryption("Invalid salt length".to_string()))?;
+    let file_key = derive_file_key(attachment_key, &salt_array);
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&file_key)))
+}
 
-    Ok((output, file_salt))
+/// Reads the plaintext chunk size from a framed header, rejecting values a
+/// corrupt file could use to force a huge allocation.
+fn framed_chunk_size(header: &[u8; FRAMED_HEADER_LEN]) -> Result<usize> {
+    let chunk_size = u32::from_le_bytes(header[5..9].try_into().expect("4-byte slice")) as usize;
+    if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+        return Err(KrillnotesError::AttachmentEncryption(format!(
+            "Invalid attachment chunk size {chunk_size}"
+        )));
+    }
+    Ok(chunk_size)
 }
 
-/// Decrypts bytes previously encrypted by `encrypt_attachment`.
+/// Decrypts an attachment from `reader` into `writer`, returning the number of
+/// plaintext bytes written.
 ///
-/// If `key` is `None`, bytes are returned unchanged (unencrypted workspace).
-pub fn decrypt_attachment(data: &[u8], key: Option<&[u8; 32]>, salt: &[u8]) -> Result<Vec<u8>> {
+/// Framed files (see [`encrypt_attachment_stream`]) are decrypted one frame at a
+/// time in bounded memory. Files without the framed header are treated as the
+/// legacy single-shot format and decrypted in one piece. If `key` is `None`,
+/// bytes are copied unchanged (unencrypted workspace).
+///
+/// Plaintext from frames that have already been authenticated may reach `writer`
+/// before a later frame fails, so callers writing to a file should discard it on
+/// error.
+pub fn decrypt_attachment_stream<R: Read + ?Sized, W: Write + ?Sized>(
+    reader: &mut R,
+    writer: &mut W,
+    key: Option<&[u8; 32]>,
+    salt: &[u8],
+) -> Result<u64> {
     let Some(attachment_key) = key else {
-        return Ok(data.to_vec());
+        return Ok(std::io::copy(reader, writer)?);
     };
 
-    if data.len() < 12 {
-        return Err(KrillnotesError::AttachmentEncryption(
-            "File too short to contain nonce".to_string(),
-        ));
+    let cipher = file_cipher(attachment_key, salt)?;
+
+    let mut header = [0u8; FRAMED_HEADER_LEN];
+    let header_len = read_full(reader, &mut header)?;
+    let is_framed = header_len == FRAMED_HEADER_LEN
+        && header[..4] == FRAMED_MAGIC
+        && header[4] == FRAMED_VERSION;
+
+    if !is_framed {
+        // Legacy format: [12-byte nonce][ciphertext+16-byte tag], decrypted whole.
+        let mut data = header[..header_len].to_vec();
+        reader.read_to_end(&mut data)?;
+        if data.len() < 12 {
+            return Err(KrillnotesError::AttachmentEncryption(
+                "File too short to contain nonce".to_string(),
+            ));
+        }
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(&data[..12]), &data[12..])
+            .map_err(|e| KrillnotesError::AttachmentEncryption(e.to_string()))?;
+        writer.write_all(&plaintext)?;
+        return Ok(plaintext.len() as u64);
     }
 
-    let nonce_bytes = &data[..12];
-    let ciphertext = &data[12..];
+    let chunk_size = framed_chunk_size(&header)?;
+    let nonce_prefix: [u8; 7] = header[9..].try_into().expect("7-byte slice");
 
-    let salt_array: [u8; 32] = salt
-        .try_into()
-        .map_err(|_| KrillnotesError::AttachmentEncryption("Invalid salt length".to_string()))?;
+    // Each frame is read together with one extra byte of look-ahead so the final
+    // frame can be recognised without a trailing length field.
+    let frame_len = chunk_size + TAG_LEN;
+    let mut frame = vec![0u8; frame_len + 1];
+    let mut carried = 0usize;
+    let mut counter = 0u32;
+    let mut written = 0u64;
+    loop {
+        let n = carried + read_full(reader, &mut frame[carried..])?;
+        let last = n <= frame_len;
+        let this_len = n.min(frame_len);
+        if this_len < TAG_LEN {
+            return Err(KrillnotesError::AttachmentEncryption(
+                "Attachment frame truncated".to_string(),
+            ));
+        }
+        let nonce = frame_nonce(&nonce_prefix, counter, last);
+        let plaintext = cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &frame[..this_len],
+                    aad: &header,
+                },
+            )
+            .map_err(|e| KrillnotesError::AttachmentEncryption(e.to_string()))?;
+        writer.write_all(&plaintext)?;
+        written += plaintext.len() as u64;
 
-    let file_key = derive_file_key(attachment_key, &salt_array);
-    let cipher = ChaCha20Poly1305::new(Key::from_slice(&file_key));
-    let nonce = Nonce::from_slice(nonce_bytes);
+        if last {
+            break;
+        }
+        frame[0] = frame[frame_len];
+        carried = 1;
+        counter = counter.checked_add(1).ok_or_else(|| {
+            KrillnotesError::AttachmentEncryption("Too many attachment frames".to_string())
+        })?;
+    }
+    Ok(written)
+}
+
+/// Decrypts up to `len` plaintext bytes starting at plaintext `offset`.
+///
+/// Framed files are read from the first frame covering `offset`, so a range
+/// near the end of a large attachment costs no more than one near the start.
+/// Legacy single-shot files are decrypted whole. Fewer than `len` bytes are
+/// returned only when the range runs past the end of the attachment.
+pub fn decrypt_attachment_range<R: Read + Seek + ?Sized>(
+    reader: &mut R,
+    key: Option<&[u8; 32]>,
+    salt: &[u8],
+    offset: u64,
+    len: usize,
+) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let Some(attachment_key) = key else {
+        reader.seek(SeekFrom::Start(offset))?;
+        reader.take(len as u64).read_to_end(&mut out)?;
+        return Ok(out);
+    };
+    let cipher = file_cipher(attachment_key, salt)?;
+
+    reader.seek(SeekFrom::Start(0))?;
+    let mut header = [0u8; FRAMED_HEADER_LEN];
+    let header_len = read_full(reader, &mut header)?;
+    let is_framed = header_len == FRAMED_HEADER_LEN
+        && header[..4] == FRAMED_MAGIC
+        && header[4] == FRAMED_VERSION;
+
+    if !is_framed {
+        reader.seek(SeekFrom::Start(0))?;
+        let mut whole = Vec::new();
+        decrypt_attachment_stream(reader, &mut whole, key, salt)?;
+        let start = usize::try_from(offset)
+            .unwrap_or(usize::MAX)
+            .min(whole.len());
+        let end = start.saturating_add(len).min(whole.len());
+        out.extend_from_slice(&whole[start..end]);
+        return Ok(out);
+    }
+
+    let chunk_size = framed_chunk_size(&header)?;
+    let nonce_prefix: [u8; 7] = header[9..].try_into().expect("7-byte slice");
+    let frame_len = chunk_size + TAG_LEN;
+    let first_frame = offset / chunk_size as u64;
+    let Ok(mut counter) = u32::try_from(first_frame) else {
+        return Ok(out);
+    };
+    reader.seek(SeekFrom::Start(
+        FRAMED_HEADER_LEN as u64 + first_frame * frame_len as u64,
+    ))?;
+
+    let mut skip = (offset % chunk_size as u64) as usize;
+    let mut frame = vec![0u8; frame_len + 1];
+    let mut carried = 0usize;
+    while out.len() < len {
+        let n = carried + read_full(reader, &mut frame[carried..])?;
+        if n == 0 {
+            // `offset` is at or past the end of the attachment.
+            break;
+        }
+        let last = n <= frame_len;
+        let this_len = n.min(frame_len);
+        if this_len < TAG_LEN {
+            return Err(KrillnotesError::AttachmentEncryption(
+                "Attachment frame truncated".to_string(),
+            ));
+        }
+        let nonce = frame_nonce(&nonce_prefix, counter, last);
+        let plaintext = cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &frame[..this_len],
+                    aad: &header,
+                },
+            )
+            .map_err(|e| KrillnotesError::AttachmentEncryption(e.to_string()))?;
+        let available = &plaintext[skip.min(plaintext.len())..];
+        let wanted = len - out.len();
+        out.extend_from_slice(&available[..wanted.min(available.len())]);
+        skip = 0;
 
-    cipher
-        .decrypt(nonce, ciphertext)
-        .map_err(|e| KrillnotesError::AttachmentEncryption(e.to_string()))
+        if last {
+            break;
+        }
+        frame[0] = frame[frame_len];
+        carried = 1;
+        counter = counter.checked_add(1).ok_or_else(|| {
+            KrillnotesError::AttachmentEncryption("Too many attachment frames".to_string())
+        })?;
+    }
+    Ok(out)
+}
+
+/// Encrypts `plaintext` using ChaCha20-Poly1305.
+///
+/// If `key` is `None` (unencrypted workspace), bytes are returned unchanged.
+/// Otherwise the output uses the framed format written by
+/// [`encrypt_attachment_stream`]. Returns `(encrypted_bytes, file_salt)`.
+pub fn encrypt_attachment(plaintext: &[u8], key: Option<&[u8; 32]>) -> Result<(Vec<u8>, [u8; 32])> {
+    let mut output = Vec::with_capacity(plaintext.len() + FRAMED_HEADER_LEN + TAG_LEN);
+    let streamed = encrypt_attachment_stream(&mut &plaintext[..], &mut output, key)?;
+    Ok((output, streamed.salt))
+}
+
+/// Decrypts bytes previously encrypted by `encrypt_attachment`, in either the
+/// framed or the legacy single-shot format.
+///
+/// If `key` is `None`, bytes are returned unchanged (unencrypted workspace).
+pub fn decrypt_attachment(data: &[u8], key: Option<&[u8; 32]>, salt: &[u8]) -> Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(data.len());
+    decrypt_attachment_stream(&mut &data[..], &mut output, key, salt)?;
+    Ok(output)
 }
 
 #[cfg(test)]
@@ -176,4 +494,124 @@ mod tests {
         let result = decrypt_attachment(&ciphertext, Some(&wrong_key), &salt);
         assert!(result.is_err());
     }
+
+    /// Encrypts with the pre-framing single-shot layout, as older versions did.
+    fn legacy_encrypt(plaintext: &[u8], key: &[u8; 32]) -> (Vec<u8>, [u8; 32]) {
+        let mut nonce_bytes = [0u8; 12];
+        let mut file_salt = [0u8; 32];
+        rand::rng().fill_bytes(&mut nonce_bytes);
+        rand::rng().fill_bytes(&mut file_salt);
+        let file_key = derive_file_key(key, &file_salt);
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&file_key));
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce_bytes), plaintext)
+            .unwrap();
+        let mut output = nonce_bytes.to_vec();
+        output.extend_from_slice(&ciphertext);
+        (output, file_salt)
+    }
+
+    #[test]
+    fn test_decrypt_legacy_single_shot_format() {
+        let key = derive_attachment_key("testpass", "test-uuid");
+        let (ciphertext, salt) = legacy_encrypt(b"written by 1.0", &key);
+        let recovered = decrypt_attachment(&ciphertext, Some(&key), &salt).unwrap();
+        assert_eq!(recovered, b"written by 1.0");
+    }
+
+    #[test]
+    fn test_stream_round_trip_across_frame_boundaries() {
+        let key = derive_attachment_key("testpass", "test-uuid");
+        for len in [
+            0,
+            1,
+            ATTACHMENT_CHUNK_SIZE - 1,
+            ATTACHMENT_CHUNK_SIZE,
+            ATTACHMENT_CHUNK_SIZE + 1,
+            3 * ATTACHMENT_CHUNK_SIZE,
+        ] {
+            let plaintext: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+            let mut encrypted = Vec::new();
+            let streamed =
+                encrypt_attachment_stream(&mut &plaintext[..], &mut encrypted, Some(&key)).unwrap();
+            assert_eq!(streamed.size_bytes, len as u64);
+            assert_eq!(&encrypted[..4], b"KNAT");
+
+            let mut recovered = Vec::new();
+            let written = decrypt_attachment_stream(
+                &mut &encrypted[..],
+                &mut recovered,
+                Some(&key),
+                &streamed.salt,
+            )
+            .unwrap();
+            assert_eq!(written, len as u64, "length {len}");
+            assert_eq!(recovered, plaintext, "length {len}");
+        }
+    }
+
+    #[test]
+    fn test_stream_rejects_truncated_and_tampered_frames() {
+        let key = derive_attachment_key("testpass", "test-uuid");
+        let plaintext = vec![7u8; 2 * ATTACHMENT_CHUNK_SIZE + 10];
+        let (encrypted, salt) = encrypt_attachment(&plaintext, Some(&key)).unwrap();
+
+        // Dropping the final frame must not decrypt as a shorter valid file.
+        let frame = ATTACHMENT_CHUNK_SIZE + TAG_LEN;
+        let truncated = &encrypted[..FRAMED_HEADER_LEN + 2 * frame];
+        assert!(decrypt_attachment(truncated, Some(&key), &salt).is_err());
+
+        let mut tampered = encrypted.clone();
+        tampered[FRAMED_HEADER_LEN + frame + 3] ^= 0x01;
+        assert!(decrypt_attachment(&tampered, Some(&key), &salt).is_err());
+    }
+
+    #[test]
+    fn test_range_matches_plaintext_slices() {
+        let key = derive_attachment_key("testpass", "test-uuid");
+        let plaintext: Vec<u8> = (0..3 * ATTACHMENT_CHUNK_SIZE + 100)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let (framed, salt) = encrypt_attachment(&plaintext, Some(&key)).unwrap();
+        let (legacy, legacy_salt) = legacy_encrypt(&plaintext, &key);
+
+        let ranges = [
+            (0, 10),
+            (ATTACHMENT_CHUNK_SIZE - 5, 10),
+            (ATTACHMENT_CHUNK_SIZE, 2 * ATTACHMENT_CHUNK_SIZE),
+            (3 * ATTACHMENT_CHUNK_SIZE + 90, 50),
+            (plaintext.len(), 10),
+        ];
+        for (offset, len) in ranges {
+            let end = (offset + len).min(plaintext.len());
+            let expected = &plaintext[offset..end];
+            for (data, key, salt) in [
+                (&framed, Some(&key), &salt[..]),
+                (&legacy, Some(&key), &legacy_salt[..]),
+                (&plaintext, None, &[][..]),
+            ] {
+                let got = decrypt_attachment_range(
+                    &mut std::io::Cursor::new(data),
+                    key,
+                    salt,
+                    offset as u64,
+                    len,
+                )
+                .unwrap();
+                assert_eq!(got, expected, "range {offset}+{len}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_stream_hash_matches_plaintext() {
+        let plaintext = b"hash me";
+        let mut out = Vec::new();
+        let streamed = encrypt_attachment_stream(&mut &plaintext[..], &mut out, None).unwrap();
+        assert_eq!(out, plaintext);
+        assert_eq!(
+            streamed.hash_sha256,
+            format!("{:x}", Sha256::digest(plaintext))
+        );
+    }
 }
@@ -0,0 +1,258 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Signed audit export of a workspace's operation log.
+//!
+//! `Workspace::export_audit_log` writes the log as JSON Lines — one
+//! [`AuditRecord`] per operation, carrying the signed operation itself — and
+//! a detached [`AuditManifest`] signed by the exporting identity over both
+//! files (see [`crate::core::swarm::signature`]). [`verify_audit_export`]
+//! checks an export offline: the manifest signature, then every operation's
+//! own signature, without access to the workspace.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+use crate::core::error::{KrillnotesError, Result};
+use crate::core::hlc::HlcTimestamp;
+use crate::core::operation::Operation;
+use crate::core::swarm::signature::{sign_manifest, verify_manifest};
+
+/// Current audit export format version.
+pub const AUDIT_FORMAT_VERSION: u32 = 1;
+
+/// Name the records file is signed under, whatever it is saved as.
+pub const AUDIT_RECORDS_FILE: &str = "audit.jsonl";
+
+/// Name the manifest is signed under, whatever it is saved as.
+pub const AUDIT_MANIFEST_FILE: &str = "audit-manifest.json";
+
+/// Which part of the log to export.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditExportOptions {
+    /// Export only operations on this note and its descendants, including
+    /// descendants deleted or moved away since.
+    #[serde(default)]
+    pub subtree_root: Option<String>,
+    /// Earliest HLC wall time to include (Unix ms, inclusive).
+    #[serde(default)]
+    pub from_ms: Option<u64>,
+    /// Latest HLC wall time to include (Unix ms, inclusive).
+    #[serde(default)]
+    pub to_ms: Option<u64>,
+}
+
+/// One line of the records file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditRecord {
+    pub operation_id: String,
+    pub operation_type: String,
+    pub timestamp: HlcTimestamp,
+    pub device_id: String,
+    /// Base64 public key of the author; empty for unsigned operations.
+    pub author_key: String,
+    /// The author's contact name, as known to the exporting identity.
+    pub author_name: Option<String>,
+    /// Base64 public key of the identity that vouched for the operation
+    /// when it reached the exporting device: the author, a relaying peer,
+    /// or the exporting identity for its own operations.
+    pub verified_by: String,
+    pub verified_by_name: Option<String>,
+    /// Device the operation was received from; `None` if authored locally.
+    pub received_from_peer: Option<String>,
+    /// Hash of the author's previous operation from the same device.
+    pub prev_hash: Option<String>,
+    /// Base64 Ed25519 signature of `operation` by `author_key`.
+    pub signature: String,
+    /// The operation exactly as signed.
+    pub operation: Operation,
+}
+
+/// Detached manifest describing and signing an audit export.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditManifest {
+    pub format_version: u32,
+    pub workspace_id: String,
+    /// Unix seconds.
+    pub exported_at: i64,
+    /// Base64 Ed25519 public key of the exporting identity.
+    pub exported_by: String,
+    pub exported_by_name: String,
+    pub subtree_root: Option<String>,
+    pub from_ms: Option<u64>,
+    pub to_ms: Option<u64>,
+    pub record_count: u64,
+    /// Base64 Ed25519 signature by `exported_by` over the manifest hash of
+    /// the records and this manifest with `signature = ""`.
+    pub signature: String,
+}
+
+/// An audit export ready to be written out.
+#[derive(Debug, Clone)]
+pub struct AuditExport {
+    /// JSON Lines, one [`AuditRecord`] per line.
+    pub records: Vec<u8>,
+    pub manifest: AuditManifest,
+}
+
+/// Result of [`verify_audit_export`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditVerification {
+    pub record_count: u64,
+    /// Records whose operation signature does not verify, or whose
+    /// metadata disagrees with the signed operation.
+    pub invalid_records: Vec<String>,
+    /// Unsigned operations (vouched retractions), which cannot be checked.
+    pub unsigned_records: Vec<String>,
+}
+
+impl AuditVerification {
+    /// True if every signed record verified.
+    pub fn is_valid(&self) -> bool {
+        self.invalid_records.is_empty()
+    }
+}
+
+impl AuditManifest {
+    /// Signs the manifest together with `records`, setting `exported_by`
+    /// and `signature`.
+    pub fn sign(&mut self, records: &[u8], key: &SigningKey) {
+        self.exported_by = BASE64.encode(key.verifying_key().as_bytes());
+        self.signature = String::new();
+        let unsigned = serde_json::to_vec(self).expect("AuditManifest must be serializable");
+        let sig = sign_manifest(
+            &[
+                (AUDIT_RECORDS_FILE, records),
+                (AUDIT_MANIFEST_FILE, &unsigned),
+            ],
+            key,
+        );
+        self.signature = BASE64.encode(sig);
+    }
+
+    /// Verifies the signature over `records` and this manifest.
+    pub fn verify(&self, records: &[u8]) -> Result<()> {
+        let invalid = |msg: &str| KrillnotesError::Swarm(format!("audit manifest: {msg}"));
+        let vk =
+            decode_verifying_key(&self.exported_by).ok_or_else(|| invalid("bad exporter key"))?;
+        let sig = BASE64
+            .decode(&self.signature)
+            .map_err(|_| invalid("bad signature encoding"))?;
+        let mut unsigned = self.clone();
+        unsigned.signature = String::new();
+        let unsigned = serde_json::to_vec(&unsigned)?;
+        verify_manifest(
+            &[
+                (AUDIT_RECORDS_FILE, records),
+                (AUDIT_MANIFEST_FILE, &unsigned),
+            ],
+            &sig,
+            &vk,
+        )
+    }
+}
+
+/// Checks an audit export offline: the manifest must be signed by its
+/// exporter over exactly these records, and each record's operation by its
+/// author.
+///
+/// Returns an error if the manifest does not verify or the records file is
+/// malformed; records that fail are listed in the result.
+pub fn verify_audit_export(records: &[u8], manifest: &AuditManifest) -> Result<AuditVerification> {
+    if manifest.format_version > AUDIT_FORMAT_VERSION {
+        return Err(KrillnotesError::Swarm(format!(
+            "audit export format {} is newer than supported ({AUDIT_FORMAT_VERSION})",
+            manifest.format_version
+        )));
+    }
+    manifest.verify(records)?;
+
+    let mut verification = AuditVerification {
+        record_count: 0,
+        invalid_records: Vec::new(),
+        unsigned_records: Vec::new(),
+    };
+    for line in records.split(|b| *b == b'\n').filter(|l| !l.is_empty()) {
+        let record: AuditRecord = serde_json::from_slice(line)?;
+        verification.record_count += 1;
+        let op = &record.operation;
+        let consistent = op.operation_id() == record.operation_id
+            && op.author_key() == record.author_key
+            && op.device_id() == record.device_id
+            && op.timestamp() == record.timestamp
+            && op.prev_hash() == record.prev_hash.as_deref()
+            && op.get_signature() == record.signature;
+        if record.author_key.is_empty() && consistent {
+            verification.unsigned_records.push(record.operation_id);
+            continue;
+        }
+        let signed = decode_verifying_key(&record.author_key).is_some_and(|vk| op.verify(&vk));
+        if !(consistent && signed) {
+            verification.invalid_records.push(record.operation_id);
+        }
+    }
+    if verification.record_count != manifest.record_count {
+        return Err(KrillnotesError::Swarm(format!(
+            "audit manifest lists {} records, found {}",
+            manifest.record_count, verification.record_count
+        )));
+    }
+    Ok(verification)
+}
+
+fn decode_verifying_key(key: &str) -> Option<VerifyingKey> {
+    BASE64
+        .decode(key)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .and_then(|arr| VerifyingKey::from_bytes(&arr).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_signs_records_and_itself() {
+        let key = SigningKey::from_bytes(&[9u8; 32]);
+        let records = b"{\"not\":\"checked here\"}\n".to_vec();
+        let mut manifest = AuditManifest {
+            format_version: AUDIT_FORMAT_VERSION,
+            workspace_id: "ws-1".into(),
+            exported_at: 1,
+            exported_by: String::new(),
+            exported_by_name: "Alice".into(),
+            subtree_root: None,
+            from_ms: Some(10),
+            to_ms: None,
+            record_count: 1,
+            signature: String::new(),
+        };
+        manifest.sign(&records, &key);
+        assert!(manifest.verify(&records).is_ok());
+
+        let mut trimmed = records.clone();
+        trimmed.pop();
+        assert!(manifest.verify(&trimmed).is_err());
+
+        let mut widened = manifest.clone();
+        widened.from_ms = None;
+        assert!(widened.verify(&records).is_err());
+
+        let mut misattributed = manifest.clone();
+        misattributed.exported_by = BASE64.encode(
+            SigningKey::from_bytes(&[8u8; 32])
+                .verifying_key()
+                .as_bytes(),
+        );
+        assert!(verify_audit_export(&records, &misattributed).is_err());
+    }
+}
@@ -0,0 +1,163 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Signed checkpoints standing in for compacted operation log history.
+//!
+//! Compaction (`Workspace::compact_operation_log`) removes old operations
+//! from the log and records a [`Checkpoint`]: the HLC frontier of the
+//! newest operation folded in, plus the SHA-256 of the workspace state at
+//! that point ([`CheckpointState`]). A peer whose watermark predates the
+//! compacted range receives the checkpoint, its state and the operations
+//! after the frontier instead of the history that no longer exists.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::core::hlc::HlcTimestamp;
+use crate::core::workspace::WorkspaceSnapshot;
+
+/// A signed summary of the operations removed from the log by compaction.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Checkpoint {
+    pub checkpoint_id: String,
+    pub workspace_id: String,
+    /// Timestamp of the newest operation folded into the state.
+    pub frontier: HlcTimestamp,
+    /// ID of that operation. Serves as the watermark of a peer that has
+    /// received this checkpoint and nothing after it.
+    pub frontier_operation_id: String,
+    /// Operations older than this wall clock time (Unix ms) were removed,
+    /// apart from membership and permission operations.
+    pub compacted_before_ms: u64,
+    /// Number of operations removed.
+    pub compacted_count: u64,
+    /// Hex SHA-256 of the serialized [`CheckpointState`].
+    pub state_hash: String,
+    /// Unix seconds.
+    pub created_at: i64,
+    /// Base64 Ed25519 public key of the identity that compacted its log.
+    pub author_key: String,
+    /// Base64 Ed25519 signature over the checkpoint with `signature = ""`.
+    pub signature: String,
+}
+
+/// Workspace state as of a checkpoint's frontier.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointState {
+    pub snapshot: WorkspaceSnapshot,
+    /// Notes deleted at or before the frontier, compacted or not, carried
+    /// over from earlier checkpoints, so lagging peers remove them too.
+    #[serde(default)]
+    pub deleted_note_ids: Vec<String>,
+    /// The last link of every hash chain at the frontier. The operations
+    /// after the frontier continue from these, though a peer served the
+    /// checkpoint never receives the operations they belong to.
+    #[serde(default)]
+    pub chain_tails: Vec<ChainTail>,
+}
+
+/// The hash of the last operation in one author's chain for one device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainTail {
+    pub author_key: String,
+    pub device_id: String,
+    pub hash: String,
+}
+
+impl Checkpoint {
+    /// Signs the checkpoint in place, setting `author_key` and `signature`.
+    pub fn sign(&mut self, key: &ed25519_dalek::SigningKey) {
+        use ed25519_dalek::Signer;
+
+        self.author_key = BASE64.encode(key.verifying_key().as_bytes());
+        self.signature = String::new();
+        let payload = serde_json::to_string(self).expect("Checkpoint must be serializable");
+        self.signature = BASE64.encode(key.sign(payload.as_bytes()).to_bytes());
+    }
+
+    /// Verifies the signature against `author_key`.
+    pub fn verify(&self) -> bool {
+        use ed25519_dalek::Verifier;
+
+        let Some(vk) = BASE64
+            .decode(&self.author_key)
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .and_then(|arr| ed25519_dalek::VerifyingKey::from_bytes(&arr).ok())
+        else {
+            return false;
+        };
+        let Some(sig) = BASE64
+            .decode(&self.signature)
+            .ok()
+            .and_then(|bytes| <[u8; 64]>::try_from(bytes).ok())
+            .map(|arr| ed25519_dalek::Signature::from_bytes(&arr))
+        else {
+            return false;
+        };
+        let mut unsigned = self.clone();
+        unsigned.signature = String::new();
+        let payload = serde_json::to_string(&unsigned).expect("Checkpoint must be serializable");
+        vk.verify(payload.as_bytes(), &sig).is_ok()
+    }
+
+    /// True if `state` is the state this checkpoint was taken of.
+    pub fn matches_state(&self, state: &[u8]) -> bool {
+        hash_state(state) == self.state_hash
+    }
+}
+
+/// Hex SHA-256 of serialized checkpoint state.
+pub fn hash_state(state: &[u8]) -> String {
+    hex::encode(Sha256::digest(state))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checkpoint() -> Checkpoint {
+        Checkpoint {
+            checkpoint_id: "cp-1".into(),
+            workspace_id: "ws-1".into(),
+            frontier: HlcTimestamp {
+                wall_ms: 1_000,
+                counter: 2,
+                node_id: 3,
+            },
+            frontier_operation_id: "op-9".into(),
+            compacted_before_ms: 500,
+            compacted_count: 9,
+            state_hash: hash_state(b"state"),
+            created_at: 1,
+            author_key: String::new(),
+            signature: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_sign_verify_and_tamper() {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[4u8; 32]);
+        let mut cp = checkpoint();
+        cp.sign(&key);
+        assert!(cp.verify());
+        assert!(cp.matches_state(b"state"));
+        assert!(!cp.matches_state(b"other"));
+
+        let mut moved = cp.clone();
+        moved.frontier.wall_ms += 1;
+        assert!(!moved.verify(), "frontier is covered by the signature");
+
+        let mut reattributed = cp.clone();
+        let other = ed25519_dalek::SigningKey::from_bytes(&[5u8; 32]);
+        reattributed.author_key = BASE64.encode(other.verifying_key().as_bytes());
+        assert!(!reattributed.verify());
+    }
+}
@@ -22,6 +22,12 @@ use std::path::PathBuf;
 use std::sync::RwLock;
 use uuid::Uuid;
 
+use crate::core::succession::SuccessionRecord;
+use crate::core::verification::{
+    ContactVerification, QrVerificationPayload, SasCode, SasMessage, SasSessionInfo, SasSessions,
+    VerificationMethod, QR_PAYLOAD_MAX_AGE_SECS,
+};
+use crate::core::vouch::VouchStatement;
 use crate::Result;
 
 /// How much the local user trusts this contact's claimed identity.
@@ -38,6 +44,18 @@ pub enum TrustLevel {
     Tofu,
 }
 
+impl TrustLevel {
+    /// Orders trust levels from `Tofu` (0) to `VerifiedInPerson` (3).
+    fn rank(&self) -> u8 {
+        match self {
+            Self::Tofu => 0,
+            Self::Vouched => 1,
+            Self::CodeVerified => 2,
+            Self::VerifiedInPerson => 3,
+        }
+    }
+}
+
 /// A contact in the local address book.
 ///
 /// Stored at `<contacts_dir>/<contact_id>.json` (encrypted).
@@ -58,6 +76,13 @@ pub struct Contact {
     pub vouched_by: Option<Uuid>,
     pub first_seen: DateTime<Utc>,
     pub notes: Option<String>,
+    /// How and when the contact was last verified, if at all.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verification: Option<ContactVerification>,
+    /// The latest vouch statement about this contact from each voucher,
+    /// including the local identity's own.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vouches: Vec<VouchStatement>,
 }
 
 impl Contact {
@@ -87,6 +112,8 @@ pub struct ContactManager {
     /// `None` in legacy unencrypted mode (`ContactManager::new`).
     encryption_key: Option<[u8; 32]>,
     cache: RwLock<HashMap<Uuid, Contact>>,
+    /// SAS verifications in progress with contacts.
+    sas: SasSessions,
 }
 
 impl ContactManager {
@@ -102,6 +129,7 @@ impl ContactManager {
             contacts_dir,
             encryption_key: None,
             cache: RwLock::new(HashMap::new()),
+            sas: SasSessions::default(),
         })
     }
 
@@ -116,6 +144,7 @@ impl ContactManager {
             contacts_dir,
             encryption_key: Some(key),
             cache: RwLock::new(HashMap::new()),
+            sas: SasSessions::default(),
         };
         mgr.load_all_into_cache()?;
         Ok(mgr)
@@ -218,6 +247,8 @@ impl ContactManager {
             vouched_by: None,
             first_seen: Utc::now(),
             notes: None,
+            verification: None,
+            vouches: Vec::new(),
         };
         self.save_contact(&contact)?;
         Ok(contact)
@@ -283,6 +314,335 @@ impl ContactManager {
         self.create_contact(declared_name, public_key, trust_level)
     }
 
+    /// Moves the contact holding `record.old_key` to the successor key, keeping
+    /// its name, trust level and notes.
+    ///
+    /// A contact created for the new key before the succession was known
+    /// (e.g. by TOFU) is merged away. Returns the updated contact, or `None`
+    /// if the old key is not a contact.
+    pub fn apply_succession(&self, record: &SuccessionRecord) -> Result<Option<Contact>> {
+        if !record.verify() {
+            return Err(crate::KrillnotesError::InvalidSuccession(
+                "signature does not verify".into(),
+            ));
+        }
+        let Some(mut contact) = self.find_by_public_key(&record.old_key)? else {
+            return Ok(None);
+        };
+        if let Some(duplicate) = self.find_by_public_key(&record.new_key)? {
+            self.delete_contact(duplicate.contact_id)?;
+        }
+        contact.public_key = record.new_key.clone();
+        contact.fingerprint = generate_fingerprint(&record.new_key)?;
+        self.save_contact(&contact)?;
+        Ok(Some(contact))
+    }
+
+    /// Starts a SAS verification with a contact. The commit message is
+    /// returned and queued for [`take_sas_messages_for`](Self::take_sas_messages_for).
+    pub fn start_sas_verification(
+        &self,
+        signing_key: &ed25519_dalek::SigningKey,
+        contact_id: Uuid,
+    ) -> Result<SasMessage> {
+        let contact = self
+            .get_contact(contact_id)?
+            .ok_or_else(|| crate::KrillnotesError::InvalidVerification("unknown contact".into()))?;
+        Ok(self.sas.start(signing_key, &contact.public_key))
+    }
+
+    /// Processes a SAS message from a contact, queuing any reply. Returns
+    /// the code to compare once the exchange has produced one.
+    pub fn receive_sas_message(
+        &self,
+        signing_key: &ed25519_dalek::SigningKey,
+        msg: &SasMessage,
+    ) -> Result<Option<SasCode>> {
+        if self.find_by_public_key(&msg.sender_key)?.is_none() {
+            return Err(crate::KrillnotesError::InvalidVerification(
+                "the sender is not a contact".into(),
+            ));
+        }
+        self.sas.receive(signing_key, msg)
+    }
+
+    /// Removes and returns the SAS messages waiting to be sent to `public_key`.
+    pub fn take_sas_messages_for(&self, public_key: &str) -> Vec<SasMessage> {
+        self.sas.take_outgoing(public_key)
+    }
+
+    /// Queues SAS messages again after a failed send.
+    pub fn requeue_sas_messages(&self, messages: Vec<SasMessage>) {
+        self.sas.requeue(messages);
+    }
+
+    /// Lists SAS verifications in progress.
+    pub fn pending_sas_verifications(&self) -> Vec<SasSessionInfo> {
+        self.sas.list()
+    }
+
+    /// Abandons a SAS verification.
+    pub fn cancel_sas_verification(&self, session_id: &str) {
+        self.sas.cancel(session_id);
+    }
+
+    /// Records that the user confirmed both sides show the same code,
+    /// upgrading the contact to `CodeVerified`.
+    pub fn confirm_sas_verification(&self, session_id: &str) -> Result<Contact> {
+        let session = self.sas.finish(session_id)?;
+        self.record_verification(
+            session.their_key(),
+            TrustLevel::CodeVerified,
+            ContactVerification {
+                method: VerificationMethod::Sas,
+                verified_at: Utc::now(),
+                session_id: Some(session_id.to_string()),
+            },
+        )
+    }
+
+    /// Records a scanned QR verification payload, upgrading its contact —
+    /// created if unknown — to `VerifiedInPerson`.
+    pub fn verify_in_person(&self, payload: &QrVerificationPayload) -> Result<Contact> {
+        let invalid = |msg: &str| crate::KrillnotesError::InvalidVerification(msg.to_string());
+        if !payload.verify() {
+            return Err(invalid("the code is not signed by its key"));
+        }
+        if (Utc::now().timestamp() - payload.created_at).abs() > QR_PAYLOAD_MAX_AGE_SECS {
+            return Err(invalid("the code has expired"));
+        }
+        self.find_or_create_by_public_key(
+            &payload.declared_name,
+            &payload.public_key,
+            TrustLevel::Tofu,
+        )?;
+        self.record_verification(
+            &payload.public_key,
+            TrustLevel::VerifiedInPerson,
+            ContactVerification {
+                method: VerificationMethod::QrCode,
+                verified_at: Utc::now(),
+                session_id: None,
+            },
+        )
+    }
+
+    /// Records a verification on the contact holding `public_key`, raising
+    /// its trust level to `trust_level` unless it is already higher.
+    fn record_verification(
+        &self,
+        public_key: &str,
+        trust_level: TrustLevel,
+        verification: ContactVerification,
+    ) -> Result<Contact> {
+        let mut contact = self.find_by_public_key(public_key)?.ok_or_else(|| {
+            crate::KrillnotesError::InvalidVerification("the contact no longer exists".into())
+        })?;
+        if trust_level.rank() > contact.trust_level.rank() {
+            contact.trust_level = trust_level;
+            contact.vouched_by = None;
+        }
+        contact.verification = Some(verification);
+        self.save_contact(&contact)?;
+        self.refresh_vouched_trust()?;
+        Ok(contact)
+    }
+
+    /// Vouches for a contact the user has verified themselves, recording the
+    /// statement on the contact for [`issued_vouches`](Self::issued_vouches).
+    pub fn issue_vouch(
+        &self,
+        signing_key: &ed25519_dalek::SigningKey,
+        contact_id: Uuid,
+    ) -> Result<VouchStatement> {
+        let contact = self
+            .get_contact(contact_id)?
+            .ok_or_else(|| crate::KrillnotesError::InvalidVouch("unknown contact".into()))?;
+        if contact.trust_level.rank() < TrustLevel::CodeVerified.rank() {
+            return Err(crate::KrillnotesError::InvalidVouch(
+                "only contacts you verified yourself can be vouched for".into(),
+            ));
+        }
+        self.issue_statement(signing_key, contact, false)
+    }
+
+    /// Withdraws the local identity's vouch for a contact.
+    pub fn revoke_vouch(
+        &self,
+        signing_key: &ed25519_dalek::SigningKey,
+        contact_id: Uuid,
+    ) -> Result<VouchStatement> {
+        let contact = self
+            .get_contact(contact_id)?
+            .ok_or_else(|| crate::KrillnotesError::InvalidVouch("unknown contact".into()))?;
+        let own_key = BASE64.encode(signing_key.verifying_key().as_bytes());
+        if !contact
+            .vouches
+            .iter()
+            .any(|v| v.voucher_key == own_key && !v.revoked)
+        {
+            return Err(crate::KrillnotesError::InvalidVouch(
+                "you have not vouched for this contact".into(),
+            ));
+        }
+        self.issue_statement(signing_key, contact, true)
+    }
+
+    fn issue_statement(
+        &self,
+        signing_key: &ed25519_dalek::SigningKey,
+        mut contact: Contact,
+        revoked: bool,
+    ) -> Result<VouchStatement> {
+        let own_key = BASE64.encode(signing_key.verifying_key().as_bytes());
+        // Strictly after our previous statement, so it always supersedes it.
+        let after_previous = contact
+            .vouches
+            .iter()
+            .filter(|v| v.voucher_key == own_key)
+            .map(|v| v.issued_at_ms + 1)
+            .max()
+            .unwrap_or(0);
+        let statement = VouchStatement::new(
+            signing_key,
+            &contact.public_key,
+            &contact.declared_name,
+            revoked,
+            (Utc::now().timestamp_millis() as u64).max(after_previous),
+        );
+        contact.vouches.retain(|v| v.voucher_key != own_key);
+        contact.vouches.push(statement.clone());
+        self.save_contact(&contact)?;
+        Ok(statement)
+    }
+
+    /// The vouches and revocations issued by `voucher_key`, for sharing in a
+    /// vouch bundle.
+    pub fn issued_vouches(&self, voucher_key: &str) -> Vec<VouchStatement> {
+        self.cache
+            .read()
+            .unwrap()
+            .values()
+            .flat_map(|c| c.vouches.iter())
+            .filter(|v| v.voucher_key == voucher_key)
+            .cloned()
+            .collect()
+    }
+
+    /// Records a vouch statement issued by a contact, replacing that
+    /// contact's earlier statement about the same subject.
+    ///
+    /// A vouch from a contact verified in person raises a TOFU subject to
+    /// `Vouched`, creating the subject if unknown; its revocation drops the
+    /// subject back to `Tofu` unless another such vouch remains. Returns the
+    /// subject, or `None` if it is not a contact.
+    pub fn receive_vouch(&self, statement: &VouchStatement) -> Result<Option<Contact>> {
+        if !statement.verify() {
+            return Err(crate::KrillnotesError::InvalidVouch(
+                "signature does not verify".into(),
+            ));
+        }
+        let voucher = self
+            .find_by_public_key(&statement.voucher_key)?
+            .ok_or_else(|| {
+                crate::KrillnotesError::InvalidVouch("the voucher is not a contact".into())
+            })?;
+        let mut subject = match self.find_by_public_key(&statement.subject_key)? {
+            Some(subject) => subject,
+            None if !statement.revoked && voucher.trust_level == TrustLevel::VerifiedInPerson => {
+                self.create_contact(
+                    &statement.subject_name,
+                    &statement.subject_key,
+                    TrustLevel::Tofu,
+                )?
+            }
+            None => return Ok(None),
+        };
+        if subject
+            .vouches
+            .iter()
+            .any(|v| v.voucher_key == statement.voucher_key && !statement.supersedes(v))
+        {
+            return Ok(Some(subject));
+        }
+        subject
+            .vouches
+            .retain(|v| v.voucher_key != statement.voucher_key);
+        subject.vouches.push(statement.clone());
+        self.save_contact(&subject)?;
+        self.refresh_vouched_trust()?;
+        self.get_contact(subject.contact_id)
+    }
+
+    /// The chain of contacts through which `contact_id` is trusted: from a
+    /// contact trusted directly, through each voucher, to the contact itself.
+    /// A contact not trusted through a vouch is its own path.
+    pub fn trust_path(&self, contact_id: Uuid) -> Result<Vec<Contact>> {
+        let mut path: Vec<Contact> = Vec::new();
+        let mut next = Some(contact_id);
+        while let Some(id) = next {
+            if path.iter().any(|c| c.contact_id == id) {
+                break;
+            }
+            let Some(contact) = self.get_contact(id)? else {
+                break;
+            };
+            next = contact
+                .vouched_by
+                .filter(|_| contact.trust_level == TrustLevel::Vouched);
+            path.push(contact);
+        }
+        if path.is_empty() {
+            return Err(crate::KrillnotesError::InvalidVouch(
+                "unknown contact".into(),
+            ));
+        }
+        path.reverse();
+        Ok(path)
+    }
+
+    /// Sets TOFU contacts holding a vouch from a contact verified in person
+    /// to `Vouched`, and returns contacts vouched for that way to `Tofu` once
+    /// no such vouch remains.
+    fn refresh_vouched_trust(&self) -> Result<()> {
+        let contacts = self.list_contacts()?;
+        let in_person: HashMap<&str, Uuid> = contacts
+            .iter()
+            .filter(|c| c.trust_level == TrustLevel::VerifiedInPerson)
+            .map(|c| (c.public_key.as_str(), c.contact_id))
+            .collect();
+        for contact in &contacts {
+            if !matches!(contact.trust_level, TrustLevel::Tofu | TrustLevel::Vouched)
+                || contact.vouches.is_empty()
+            {
+                continue;
+            }
+            let voucher = contact
+                .vouches
+                .iter()
+                .filter(|v| !v.revoked)
+                .filter_map(|v| {
+                    in_person
+                        .get(v.voucher_key.as_str())
+                        .map(|id| (v.issued_at_ms, *id))
+                })
+                .min()
+                .map(|(_, id)| id);
+            let trust_level = if voucher.is_some() {
+                TrustLevel::Vouched
+            } else {
+                TrustLevel::Tofu
+            };
+            if contact.trust_level != trust_level || contact.vouched_by != voucher {
+                let mut updated = contact.clone();
+                updated.trust_level = trust_level;
+                updated.vouched_by = voucher;
+                self.save_contact(&updated)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Delete a contact from disk and the in-memory cache.
     pub fn delete_contact(&self, id: Uuid) -> Result<()> {
         let path = self.path_for(id);
@@ -290,14 +650,15 @@ impl ContactManager {
             std::fs::remove_file(&path)?;
         }
         self.cache.write().unwrap().remove(&id);
-        Ok(())
+        self.refresh_vouched_trust()
     }
 }
 
 impl Drop for ContactManager {
     fn drop(&mut self) {
+        use zeroize::Zeroize;
         if let Some(key) = self.encryption_key.as_mut() {
-            key.fill(0);
+            key.zeroize();
         }
     }
 }
@@ -506,4 +867,117 @@ mod tests {
         mgr.delete_contact(alice.contact_id).unwrap();
         assert_eq!(mgr.list_contacts().unwrap().len(), 1);
     }
+
+    #[test]
+    fn test_sas_confirmation_upgrades_contact_and_qr_verifies_in_person() {
+        let alice_key = ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]);
+        let bob_key = ed25519_dalek::SigningKey::from_bytes(&[2u8; 32]);
+        let b64 = |k: &ed25519_dalek::SigningKey| BASE64.encode(k.verifying_key().as_bytes());
+        let (alice_dir, bob_dir) = (tempdir().unwrap(), tempdir().unwrap());
+        let alice =
+            ContactManager::for_identity(alice_dir.path().to_path_buf(), test_key()).unwrap();
+        let bob = ContactManager::for_identity(bob_dir.path().to_path_buf(), test_key()).unwrap();
+        let bob_contact = alice
+            .create_contact("Bob", &b64(&bob_key), TrustLevel::Tofu)
+            .unwrap();
+
+        // Bob only answers contacts.
+        let commit = alice
+            .start_sas_verification(&alice_key, bob_contact.contact_id)
+            .unwrap();
+        assert!(bob.receive_sas_message(&bob_key, &commit).is_err());
+        bob.create_contact("Alice", &b64(&alice_key), TrustLevel::Tofu)
+            .unwrap();
+        bob.receive_sas_message(&bob_key, &commit).unwrap();
+
+        for msg in bob.take_sas_messages_for(&b64(&alice_key)) {
+            alice.receive_sas_message(&alice_key, &msg).unwrap();
+        }
+        assert!(alice.confirm_sas_verification(&commit.session_id).is_ok());
+        let mut bob_code = None;
+        for msg in alice.take_sas_messages_for(&b64(&bob_key)) {
+            bob_code = bob.receive_sas_message(&bob_key, &msg).unwrap();
+        }
+        assert!(bob_code.is_some());
+        let upgraded = bob.confirm_sas_verification(&commit.session_id).unwrap();
+        assert_eq!(upgraded.trust_level, TrustLevel::CodeVerified);
+        assert_eq!(
+            upgraded.verification.as_ref().unwrap().method,
+            VerificationMethod::Sas
+        );
+
+        // In person beats the code; the code never downgrades it.
+        let qr = QrVerificationPayload::new(&alice_key, "Alice");
+        let scanned = QrVerificationPayload::from_uri(&qr.to_uri()).unwrap();
+        let in_person = bob.verify_in_person(&scanned).unwrap();
+        assert_eq!(in_person.trust_level, TrustLevel::VerifiedInPerson);
+        let reloaded = ContactManager::for_identity(bob_dir.path().to_path_buf(), test_key())
+            .unwrap()
+            .find_by_public_key(&b64(&alice_key))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            reloaded.verification.unwrap().method,
+            VerificationMethod::QrCode
+        );
+    }
+
+    #[test]
+    fn test_vouch_from_in_person_contact_upgrades_and_revokes() {
+        let alice_key = ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]);
+        let carol_key = ed25519_dalek::SigningKey::from_bytes(&[3u8; 32]);
+        let b64 = |k: &ed25519_dalek::SigningKey| BASE64.encode(k.verifying_key().as_bytes());
+        let (alice_dir, bob_dir) = (tempdir().unwrap(), tempdir().unwrap());
+        let alice =
+            ContactManager::for_identity(alice_dir.path().to_path_buf(), test_key()).unwrap();
+        let bob = ContactManager::for_identity(bob_dir.path().to_path_buf(), test_key()).unwrap();
+
+        // Alice can only vouch for someone she verified herself.
+        let carol = alice
+            .create_contact("Carol", &b64(&carol_key), TrustLevel::Tofu)
+            .unwrap();
+        assert!(alice.issue_vouch(&alice_key, carol.contact_id).is_err());
+        let mut carol = carol;
+        carol.trust_level = TrustLevel::CodeVerified;
+        alice.save_contact(&carol).unwrap();
+        let vouch = alice.issue_vouch(&alice_key, carol.contact_id).unwrap();
+        assert_eq!(alice.issued_vouches(&b64(&alice_key)), vec![vouch.clone()]);
+
+        // Bob only accepts vouches from contacts; from a TOFU contact the
+        // vouch is kept but does not raise Carol's trust.
+        assert!(bob.receive_vouch(&vouch).is_err());
+        let bob_alice = bob
+            .create_contact("Alice", &b64(&alice_key), TrustLevel::Tofu)
+            .unwrap();
+        assert!(bob.receive_vouch(&vouch).unwrap().is_none());
+        bob.create_contact("Carol", &b64(&carol_key), TrustLevel::Tofu)
+            .unwrap();
+        let bob_carol = bob.receive_vouch(&vouch).unwrap().unwrap();
+        assert_eq!(bob_carol.trust_level, TrustLevel::Tofu);
+
+        // Once Bob meets Alice in person, her vouch counts.
+        let qr = QrVerificationPayload::new(&alice_key, "Alice");
+        bob.verify_in_person(&qr).unwrap();
+        let vouched = bob.get_contact(bob_carol.contact_id).unwrap().unwrap();
+        assert_eq!(vouched.trust_level, TrustLevel::Vouched);
+        assert_eq!(vouched.vouched_by, Some(bob_alice.contact_id));
+        let path: Vec<Uuid> = bob
+            .trust_path(vouched.contact_id)
+            .unwrap()
+            .iter()
+            .map(|c| c.contact_id)
+            .collect();
+        assert_eq!(path, vec![bob_alice.contact_id, vouched.contact_id]);
+
+        // A stale statement is ignored; the revocation drops Carol to TOFU.
+        let revocation = alice.revoke_vouch(&alice_key, carol.contact_id).unwrap();
+        assert!(revocation.revoked && revocation.supersedes(&vouch));
+        assert!(alice.revoke_vouch(&alice_key, carol.contact_id).is_err());
+        let revoked = bob.receive_vouch(&revocation).unwrap().unwrap();
+        assert_eq!(revoked.trust_level, TrustLevel::Tofu);
+        assert_eq!(revoked.vouched_by, None);
+        let replayed = bob.receive_vouch(&vouch).unwrap().unwrap();
+        assert_eq!(replayed.trust_level, TrustLevel::Tofu);
+        assert_eq!(bob.trust_path(revoked.contact_id).unwrap().len(), 1);
+    }
 }
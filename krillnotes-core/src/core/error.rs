@@ -88,6 +88,21 @@ pub enum KrillnotesError {
     #[error("Cannot delete identity with bound workspaces: {0}")]
     IdentityHasBoundWorkspaces(String),
 
+    #[error("Invalid recovery phrase: {0}")]
+    InvalidRecoveryPhrase(String),
+
+    #[error("Invalid recovery shares: {0}")]
+    InvalidRecoveryShares(String),
+
+    #[error("Invalid key succession: {0}")]
+    InvalidSuccession(String),
+
+    #[error("Contact verification failed: {0}")]
+    InvalidVerification(String),
+
+    #[error("Invalid vouch: {0}")]
+    InvalidVouch(String),
+
     #[error("Workspace not bound to any identity: {0}")]
     WorkspaceNotBound(String),
 
@@ -134,6 +149,22 @@ pub enum KrillnotesError {
     #[error("Relay unavailable: {0}")]
     RelayUnavailable(String),
 
+    /// A WebDAV request failed, or WebDAV account storage could not be read.
+    #[error("WebDAV error: {0}")]
+    WebDav(String),
+
+    /// An S3 request failed, or S3 account storage could not be read.
+    #[error("S3 error: {0}")]
+    S3(String),
+
+    /// A git command run by the git sync channel failed.
+    #[error("Git error: {0}")]
+    Git(String),
+
+    /// A direct LAN transfer or the LAN listener failed.
+    #[error("LAN sync error: {0}")]
+    Lan(String),
+
     /// A permission check failed (from a [`PermissionGate`] implementation).
     #[error("permission denied: {0}")]
     Permission(#[from] crate::core::permission::PermissionError),
@@ -209,6 +240,20 @@ impl KrillnotesError {
             Self::IdentityHasBoundWorkspaces(id) => {
                 format!("Cannot delete identity {id} — it still has workspaces bound to it. Unbind all workspaces first.")
             }
+            Self::InvalidRecoveryPhrase(_) => {
+                "The recovery phrase is not valid. Check that all 24 words are spelled correctly and in order.".to_string()
+            }
+            Self::InvalidRecoveryShares(_) => {
+                "These recovery shares cannot restore ownership of this workspace.".to_string()
+            }
+            Self::InvalidSuccession(_) => {
+                "The identity key rotation record is not valid.".to_string()
+            }
+            Self::InvalidVerification(_) => {
+                "The verification could not be completed. Start it again with your contact."
+                    .to_string()
+            }
+            Self::InvalidVouch(_) => "This vouch is not valid.".to_string(),
             Self::WorkspaceNotBound(id) => {
                 format!("Workspace {id} is not bound to any identity.")
             }
@@ -228,6 +273,10 @@ impl KrillnotesError {
             Self::RelayRateLimited(_) => "Relay is rate limiting requests. Please try again later.".to_string(),
             Self::RelayNotFound(_) => "The requested relay resource was not found or has expired.".to_string(),
             Self::RelayUnavailable(msg) => format!("Relay server unavailable: {msg}"),
+            Self::WebDav(msg) => format!("WebDAV sync failed: {msg}"),
+            Self::S3(msg) => format!("S3 sync failed: {msg}"),
+            Self::Git(msg) => format!("Git sync failed: {msg}"),
+            Self::Lan(msg) => format!("LAN sync failed: {msg}"),
             Self::Permission(e) => format!("Permission denied: {e}"),
             Self::ProtocolMismatch { expected, found } =>
                 format!("Incompatible swarm protocol: expected {}, found {}", expected, found),
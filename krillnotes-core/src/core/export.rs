@@ -6,21 +6,26 @@
 
 //! Workspace export and import as `.zip` archives.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::io::{Cursor, Read, Seek, Write};
 use std::path::Path;
 
 use ed25519_dalek;
 use serde::{Deserialize, Serialize};
+use zip::read::ZipFile;
 use zip::write::SimpleFileOptions;
 use zip::AesMode;
 use zip::{ZipArchive, ZipWriter};
 
-use crate::core::attachment::AttachmentMeta;
+use crate::core::archive_format::{
+    self, ArchiveDocuments, ArchiveValidationError, ARCHIVE_FORMAT_VERSION, NOTES_FILE,
+    SCRIPTS_FILE, WORKSPACE_FILE,
+};
+use crate::core::attachment::{AttachmentMeta, StreamedAttachment};
 use crate::core::note::Note;
 use crate::core::timestamp::UnixSecs;
 use crate::core::user_script;
-use crate::core::workspace::Workspace;
+use crate::core::workspace::{GraftIdStrategy, GraftOutcome, Workspace};
 use crate::get_device_id;
 use crate::Storage;
 
@@ -28,12 +33,17 @@ use crate::Storage;
 pub const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /// Top-level JSON structure in `notes.json`.
+///
+/// `version` is the archive format version (see [`crate::core::archive_format`]).
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ExportNotes {
     pub version: u32,
     pub app_version: String,
     pub notes: Vec<Note>,
+    /// Present when the archive holds a single subtree rather than a whole workspace.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subtree: Option<SubtreeExportInfo>,
 }
 
 /// One entry in `scripts/scripts.json`.
@@ -43,8 +53,8 @@ pub struct ScriptManifestEntry {
     pub filename: String,
     pub load_order: i32,
     pub enabled: bool,
-    #[serde(default)]
-    pub category: Option<String>,
+    /// `"schema"` or `"library"`.
+    pub category: String,
 }
 
 /// The `scripts/scripts.json` manifest.
@@ -57,8 +67,8 @@ pub struct ScriptManifest {
 ///
 /// This is written on export and read back on import to carry authorship,
 /// licensing, and gallery-discovery metadata for template distribution.
-/// All fields except `version` are optional so old archives without them
-/// deserialise successfully (all missing fields default to `None` / empty).
+/// `version` matches the archive format version in `notes.json`; all other
+/// fields are optional.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct WorkspaceMetadata {
@@ -89,10 +99,23 @@ pub struct WorkspaceMetadata {
 #[serde(rename_all = "camelCase")]
 pub struct ImportResult {
     pub app_version: String,
+    /// Archive format version the archive was written in. Older formats are
+    /// upgraded on read, so this can be lower than [`ARCHIVE_FORMAT_VERSION`].
+    #[serde(default)]
+    pub format_version: u32,
     pub note_count: usize,
     pub script_count: usize,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub metadata: Option<WorkspaceMetadata>,
+    /// Number of attachments listed in `attachments.json`.
+    #[serde(default)]
+    pub attachment_count: usize,
+    /// Distinct note schemas used in the archive, sorted.
+    #[serde(default)]
+    pub schemas: Vec<String>,
+    /// Present when the archive is a subtree export.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subtree: Option<SubtreeExportInfo>,
 }
 
 /// Errors specific to export/import operations.
@@ -110,6 +133,9 @@ pub enum ExportError {
     #[error("Invalid export format: {0}")]
     InvalidFormat(String),
 
+    #[error("Invalid archive: {0}")]
+    Validation(#[from] ArchiveValidationError),
+
     #[error("Database error: {0}")]
     Database(String),
 
@@ -186,25 +212,294 @@ fn try_read_entry<R: Read + Seek>(
     Some(Cursor::new(content))
 }
 
+/// The JSON documents of an archive, upgraded to the current format and validated.
+struct ArchiveContents {
+    /// Format version the archive was written in.
+    format_version: u32,
+    notes: ExportNotes,
+    workspace: Option<WorkspaceMetadata>,
+    scripts: Option<ScriptManifest>,
+}
+
+/// Detects an encrypted archive without a password, then reads the JSON documents
+/// and runs them through [`archive_format::upgrade_archive`] and
+/// [`archive_format::validate_archive`].
+fn read_archive_contents<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    password: Option<&str>,
+) -> Result<ArchiveContents, ExportError> {
+    // by_index_raw reads metadata without decrypting, so .encrypted() is safe to call
+    // without a password.
+    {
+        let index = archive.index_for_name(NOTES_FILE).ok_or_else(|| {
+            ExportError::InvalidFormat("Missing notes.json in archive".to_string())
+        })?;
+        let check = archive.by_index_raw(index).map_err(ExportError::Zip)?;
+        if check.encrypted() && password.is_none() {
+            return Err(ExportError::EncryptedArchive);
+        }
+    }
+
+    let mut docs = ArchiveDocuments {
+        notes: serde_json::from_reader(read_entry(archive, NOTES_FILE, password)?)?,
+        workspace: try_read_entry(archive, WORKSPACE_FILE, password)
+            .map(serde_json::from_reader)
+            .transpose()?,
+        scripts: try_read_entry(archive, SCRIPTS_FILE, password)
+            .map(serde_json::from_reader)
+            .transpose()?,
+    };
+    let format_version = archive_format::upgrade_archive(&mut docs)?;
+    archive_format::validate_archive(&docs)?;
+
+    Ok(ArchiveContents {
+        format_version,
+        notes: serde_json::from_value(docs.notes)?,
+        workspace: docs.workspace.map(serde_json::from_value).transpose()?,
+        scripts: docs.scripts.map(serde_json::from_value).transpose()?,
+    })
+}
+
+/// Reads every `.rhai` file referenced by the `scripts/scripts.json` manifest.
+///
+/// Returns `(source_code, manifest_entry)` pairs in manifest order; an archive
+/// without a manifest yields an empty list.
+fn read_script_sources<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    manifest: Option<ScriptManifest>,
+    password: Option<&str>,
+) -> Result<Vec<(String, ScriptManifestEntry)>, ExportError> {
+    let Some(manifest) = manifest else {
+        return Ok(Vec::new());
+    };
+    let mut sources = Vec::with_capacity(manifest.scripts.len());
+    for entry in manifest.scripts {
+        let path = format!("scripts/{}", entry.filename);
+        let mut rhai_cursor = read_entry(archive, &path, password).map_err(|e| {
+            ExportError::InvalidFormat(format!(
+                "Script file '{}' referenced in manifest but missing from archive: {}",
+                path, e
+            ))
+        })?;
+        let mut source = String::new();
+        rhai_cursor.read_to_string(&mut source)?;
+        sources.push((source, entry));
+    }
+    Ok(sources)
+}
+
+/// Opens a named entry for streaming reads, decrypting with `password` if provided.
+/// Returns `None` when the entry is absent or cannot be opened.
+fn open_entry<'a, R: Read + Seek>(
+    archive: &'a mut ZipArchive<R>,
+    name: &str,
+    password: Option<&str>,
+) -> Option<ZipFile<'a, R>> {
+    match password {
+        Some(pwd) => archive.by_name_decrypt(name, pwd.as_bytes()).ok(),
+        None => archive.by_name(name).ok(),
+    }
+}
+
+/// Reads `attachments.json`, returning an empty list when it is absent or unreadable.
+fn read_attachment_metas<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    password: Option<&str>,
+) -> Vec<AttachmentMeta> {
+    try_read_entry(archive, "attachments.json", password)
+        .and_then(|cursor| serde_json::from_reader(cursor).ok())
+        .unwrap_or_default()
+}
+
+/// Distinct schema names used by `notes`, sorted.
+fn schemas_used(notes: &[Note]) -> Vec<String> {
+    let mut schemas: Vec<String> = notes.iter().map(|n| n.schema.clone()).collect();
+    schemas.sort();
+    schemas.dedup();
+    schemas
+}
+
+/// How notes outside an exported subtree that are targets of `note_link` fields
+/// are handled by [`export_workspace_with_options`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReferencedNotes {
+    /// Links to notes outside the subtree are cleared.
+    #[default]
+    Exclude,
+    /// Referenced notes are included as top-level stubs: same ID, schema and
+    /// title, but no fields, tags or attachments.
+    Stubs,
+    /// Referenced notes are included as top-level copies with their fields,
+    /// tags and attachments (but not their descendants).
+    FullCopies,
+}
+
+/// Options for [`export_workspace_with_options`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportOptions {
+    /// Export only this note and its descendants. `None` exports the whole workspace.
+    #[serde(default)]
+    pub subtree_root: Option<String>,
+    /// Handling of notes referenced from the subtree. Ignored for whole-workspace exports.
+    #[serde(default)]
+    pub referenced_notes: ReferencedNotes,
+}
+
+/// Describes a subtree export; stored in `notes.json` and reported by [`peek_import`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubtreeExportInfo {
+    pub root_id: String,
+    pub root_title: String,
+    pub referenced_notes: ReferencedNotes,
+    /// IDs of notes included only because they are referenced from the subtree.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub referenced_note_ids: Vec<String>,
+}
+
 /// Exports the workspace contents as a zip archive.
 ///
+/// Equivalent to [`export_workspace_with_options`] with default options
+/// (whole workspace).
+pub fn export_workspace<W: Write + Seek>(
+    workspace: &Workspace,
+    writer: W,
+    password: Option<&str>,
+) -> Result<(), ExportError> {
+    export_workspace_with_options(workspace, writer, password, &ExportOptions::default())
+}
+
+/// Selects the notes for a subtree export and rewrites links that leave it.
+///
+/// The subtree root is detached (`parent_id = None`) so the archive is self-contained.
+/// Returns the notes plus the IDs of notes pulled in as references.
+fn collect_subtree_export(
+    workspace: &Workspace,
+    root_id: &str,
+    mode: ReferencedNotes,
+) -> Result<(Vec<Note>, Vec<String>), ExportError> {
+    let db_err = |e: crate::KrillnotesError| ExportError::Database(e.to_string());
+    let mut notes = workspace.collect_subtree_notes(root_id).map_err(db_err)?;
+    if notes.is_empty() {
+        return Err(ExportError::Database(format!("Note not found: {root_id}")));
+    }
+    notes[0].parent_id = None;
+
+    let mut included: HashSet<String> = notes.iter().map(|n| n.id.clone()).collect();
+    let mut referenced_ids = Vec::new();
+    if mode != ReferencedNotes::Exclude {
+        let targets: Vec<String> = notes
+            .iter()
+            .flat_map(|n| n.fields.values())
+            .filter_map(|v| match v {
+                crate::FieldValue::NoteLink(Some(target)) => Some(target.clone()),
+                _ => None,
+            })
+            .collect();
+        for target in targets {
+            if included.contains(&target) {
+                continue;
+            }
+            let Ok(mut referenced) = workspace.get_note(&target) else {
+                continue;
+            };
+            referenced.parent_id = None;
+            if mode == ReferencedNotes::Stubs {
+                referenced.fields.clear();
+                referenced.tags.clear();
+                referenced.is_checked = false;
+            }
+            included.insert(target.clone());
+            referenced_ids.push(target);
+            notes.push(referenced);
+        }
+    }
+
+    // Links that still point outside the archive would dangle on import.
+    for note in &mut notes {
+        for value in note.fields.values_mut() {
+            if let crate::FieldValue::NoteLink(Some(target)) = value {
+                if !included.contains(target.as_str()) {
+                    *value = crate::FieldValue::NoteLink(None);
+                }
+            }
+        }
+    }
+    Ok((notes, referenced_ids))
+}
+
+/// Exports workspace contents as a zip archive, optionally limited to a subtree.
+///
 /// The archive contains:
-/// - `notes.json` -- all notes with format version and app version
+/// - `notes.json` -- the exported notes with format version and app version
 /// - `scripts/scripts.json` -- script metadata (filename, load_order, enabled)
 /// - `scripts/<name>.rhai` -- each user script's source code
+/// - `workspace.json` -- workspace metadata
+/// - `attachments.json` and `attachments/<id>/<filename>` -- attachments, if any
+///
+/// For a subtree export (`options.subtree_root`), only attachments belonging to
+/// included notes are written (stubs have none), and only library scripts plus the
+/// schema scripts that declare a schema used by an included note.
 ///
 /// The `operations` table and `workspace_meta` are excluded.
-pub fn export_workspace<W: Write + Seek>(
+pub fn export_workspace_with_options<W: Write + Seek>(
     workspace: &Workspace,
     writer: W,
     password: Option<&str>,
+    options: &ExportOptions,
 ) -> Result<(), ExportError> {
-    let notes = workspace
-        .list_all_notes()
-        .map_err(|e| ExportError::Database(e.to_string()))?;
-    let scripts = workspace
+    let (notes, subtree) = match &options.subtree_root {
+        Some(root_id) => {
+            let (notes, referenced_note_ids) =
+                collect_subtree_export(workspace, root_id, options.referenced_notes)?;
+            let info = SubtreeExportInfo {
+                root_id: root_id.clone(),
+                root_title: notes[0].title.clone(),
+                referenced_notes: options.referenced_notes,
+                referenced_note_ids,
+            };
+            (notes, Some(info))
+        }
+        None => (
+            workspace
+                .list_all_notes()
+                .map_err(|e| ExportError::Database(e.to_string()))?,
+            None,
+        ),
+    };
+    let mut scripts = workspace
         .list_user_scripts()
         .map_err(|e| ExportError::Database(e.to_string()))?;
+    if subtree.is_some() {
+        let used_schemas: HashSet<&str> = notes.iter().map(|n| n.schema.as_str()).collect();
+        scripts.retain(|script| {
+            script.category != "schema"
+                || user_script::declared_schema_names(&script.source_code)
+                    .iter()
+                    .any(|name| used_schemas.contains(name.as_str()))
+        });
+    }
+
+    // Attachments of stubs are never exported.
+    let stub_ids: HashSet<&str> = match &subtree {
+        Some(info) if info.referenced_notes == ReferencedNotes::Stubs => info
+            .referenced_note_ids
+            .iter()
+            .map(String::as_str)
+            .collect(),
+        _ => HashSet::new(),
+    };
+    let note_ids: HashSet<&str> = notes
+        .iter()
+        .map(|n| n.id.as_str())
+        .filter(|id| !stub_ids.contains(id))
+        .collect();
+    let mut all_attachments = workspace
+        .list_all_attachments()
+        .map_err(|e| ExportError::Database(e.to_string()))?;
+    all_attachments.retain(|meta| note_ids.contains(meta.note_id.as_str()));
 
     let mut zip = ZipWriter::new(writer);
     let options = match password {
@@ -224,9 +519,10 @@ pub fn export_workspace<W: Write + Seek>(
         })
         .collect();
     let export_notes = ExportNotes {
-        version: 1,
+        version: ARCHIVE_FORMAT_VERSION,
         app_version: APP_VERSION.to_string(),
         notes,
+        subtree,
     };
     zip.start_file("notes.json", options)?;
     serde_json::to_writer_pretty(&mut zip, &export_notes)?;
@@ -251,7 +547,7 @@ pub fn export_workspace<W: Write + Seek>(
             filename: filename.clone(),
             load_order: script.load_order,
             enabled: script.enabled,
-            category: Some(script.category.clone()),
+            category: script.category.clone(),
         });
 
         zip.start_file(format!("scripts/{filename}"), options)?;
@@ -269,32 +565,29 @@ pub fn export_workspace<W: Write + Seek>(
     let mut ws_meta = workspace
         .get_workspace_metadata()
         .map_err(|e| ExportError::Database(e.to_string()))?;
-    ws_meta.version = 1;
+    ws_meta.version = ARCHIVE_FORMAT_VERSION;
     ws_meta.owner_pubkey = None;
     zip.start_file("workspace.json", options)?;
     serde_json::to_writer_pretty(&mut zip, &ws_meta)?;
 
     // Write attachments
-    let all_attachments = workspace
-        .list_all_attachments()
-        .map_err(|e| ExportError::Database(e.to_string()))?;
-
     if !all_attachments.is_empty() {
         // Write attachments.json manifest
         zip.start_file("attachments.json", options)?;
         serde_json::to_writer(&mut zip, &all_attachments)
             .map_err(|e| ExportError::Database(e.to_string()))?;
 
-        // Write each attachment file (plaintext — zip password protects them at rest)
+        // Write each attachment file (plaintext — zip password protects them at rest).
+        // Attachments are decrypted straight into the zip entry so memory use stays
+        // bounded regardless of file size.
         for meta in &all_attachments {
-            let plaintext = workspace
-                .get_attachment_bytes(&meta.id)
-                .map_err(|e| ExportError::Database(e.to_string()))?;
             zip.start_file(
                 format!("attachments/{}/{}", meta.id, meta.filename),
-                options,
+                options.large_file(meta.size_bytes >= u32::MAX as i64),
             )?;
-            zip.write_all(&plaintext)?;
+            workspace
+                .copy_attachment_to(&meta.id, &mut zip)
+                .map_err(|e| ExportError::Database(e.to_string()))?;
         }
     }
 
@@ -311,55 +604,28 @@ pub fn export_workspace<W: Write + Seek>(
 ///
 /// Returns [`ExportError::EncryptedArchive`] if the archive is encrypted and no
 /// password is provided. Returns [`ExportError::InvalidPassword`] if the password
-/// is wrong. Returns [`ExportError::InvalidFormat`] if the format version is not
-/// `1` or `notes.json` is missing. Returns other `ExportError` variants for I/O,
-/// zip, or JSON failures.
+/// is wrong. Returns [`ExportError::InvalidFormat`] if the format version is newer
+/// than [`ARCHIVE_FORMAT_VERSION`] or `notes.json` is missing, and
+/// [`ExportError::Validation`] if a document does not match the format. Returns
+/// other `ExportError` variants for I/O, zip, or JSON failures.
 pub fn peek_import<R: Read + Seek>(
     reader: R,
     password: Option<&str>,
 ) -> Result<ImportResult, ExportError> {
     let mut archive = ZipArchive::new(reader)?;
-
-    // Detect encryption before trying to read data.
-    // by_index_raw reads metadata without decrypting, so .encrypted() is safe to call
-    // without a password.
-    {
-        let index = archive.index_for_name("notes.json").ok_or_else(|| {
-            ExportError::InvalidFormat("Missing notes.json in archive".to_string())
-        })?;
-        let check = archive.by_index_raw(index).map_err(ExportError::Zip)?;
-        if check.encrypted() && password.is_none() {
-            return Err(ExportError::EncryptedArchive);
-        }
-    }
-
-    let notes_cursor = read_entry(&mut archive, "notes.json", password)?;
-    let export_notes: ExportNotes = serde_json::from_reader(notes_cursor)?;
-
-    if export_notes.version != 1 {
-        return Err(ExportError::InvalidFormat(format!(
-            "Unsupported export format version: {}",
-            export_notes.version
-        )));
-    }
-
-    let script_count = match try_read_entry(&mut archive, "scripts/scripts.json", password) {
-        Some(manifest_cursor) => {
-            let manifest: ScriptManifest = serde_json::from_reader(manifest_cursor)?;
-            manifest.scripts.len()
-        }
-        None => 0,
-    };
-
-    let metadata: Option<WorkspaceMetadata> =
-        try_read_entry(&mut archive, "workspace.json", password)
-            .and_then(|cursor| serde_json::from_reader(cursor).ok());
+    let contents = read_archive_contents(&mut archive, password)?;
+    let export_notes = contents.notes;
+    let attachment_count = read_attachment_metas(&mut archive, password).len();
 
     Ok(ImportResult {
         app_version: export_notes.app_version,
+        format_version: contents.format_version,
         note_count: export_notes.notes.len(),
-        script_count,
-        metadata,
+        script_count: contents.scripts.map_or(0, |m| m.scripts.len()),
+        metadata: contents.workspace,
+        attachment_count,
+        schemas: schemas_used(&export_notes.notes),
+        subtree: export_notes.subtree,
     })
 }
 
@@ -374,8 +640,10 @@ pub fn peek_import<R: Read + Seek>(
 /// # Errors
 ///
 /// Returns [`ExportError::InvalidFormat`] if `notes.json` is missing or the format
-/// version is not `1`. Returns [`ExportError::Database`] for any storage or SQL
-/// failure. Returns other `ExportError` variants for I/O, zip, or JSON errors.
+/// version is newer than [`ARCHIVE_FORMAT_VERSION`], and [`ExportError::Validation`]
+/// if a document does not match the format after upgrading. Returns
+/// [`ExportError::Database`] for any storage or SQL failure. Returns other
+/// `ExportError` variants for I/O, zip, or JSON errors.
 pub fn import_workspace<R: Read + Seek>(
     reader: R,
     db_path: &Path,
@@ -385,58 +653,15 @@ pub fn import_workspace<R: Read + Seek>(
     signing_key: ed25519_dalek::SigningKey,
 ) -> Result<ImportResult, ExportError> {
     let mut archive = ZipArchive::new(reader)?;
-
-    // Detect encryption (same pattern as peek_import)
-    {
-        let index = archive.index_for_name("notes.json").ok_or_else(|| {
-            ExportError::InvalidFormat("Missing notes.json in archive".to_string())
-        })?;
-        let check = archive.by_index_raw(index).map_err(ExportError::Zip)?;
-        if check.encrypted() && zip_password.is_none() {
-            return Err(ExportError::EncryptedArchive);
-        }
-    }
-
-    let notes_cursor = read_entry(&mut archive, "notes.json", zip_password)?;
-    let export_notes: ExportNotes = serde_json::from_reader(notes_cursor)?;
-
-    // Validate format version
-    if export_notes.version != 1 {
-        return Err(ExportError::InvalidFormat(format!(
-            "Unsupported export format version: {}",
-            export_notes.version
-        )));
-    }
-
-    // Read script manifest and source files
-    let manifest = match try_read_entry(&mut archive, "scripts/scripts.json", zip_password) {
-        Some(manifest_cursor) => {
-            let m: ScriptManifest = serde_json::from_reader(manifest_cursor)?;
-            Some(m)
-        }
-        None => None,
-    };
+    let contents = read_archive_contents(&mut archive, zip_password)?;
+    let export_notes = contents.notes;
 
     // Read each .rhai script source from the archive
-    let mut script_sources: Vec<(String, i32, bool, String)> = Vec::new(); // (source_code, load_order, enabled, category)
-    if let Some(ref manifest) = manifest {
-        for entry in &manifest.scripts {
-            let path = format!("scripts/{}", entry.filename);
-            let mut rhai_cursor = read_entry(&mut archive, &path, zip_password).map_err(|e| {
-                ExportError::InvalidFormat(format!(
-                    "Script file '{}' referenced in manifest but missing from archive: {}",
-                    path, e
-                ))
-            })?;
-            let mut source = String::new();
-            rhai_cursor.read_to_string(&mut source)?;
-            let category = entry
-                .category
-                .clone()
-                .unwrap_or_else(|| "schema".to_string());
-            script_sources.push((source, entry.load_order, entry.enabled, category));
-        }
-    }
+    let script_sources: Vec<(String, i32, bool, String)> = // (source_code, load_order, enabled, category)
+        read_script_sources(&mut archive, contents.scripts, zip_password)?
+            .into_iter()
+            .map(|(source, entry)| (source, entry.load_order, entry.enabled, entry.category))
+            .collect();
 
     // Create the database
     let mut storage = Storage::create(db_path, workspace_password)
@@ -527,11 +752,6 @@ pub fn import_workspace<R: Read + Seek>(
             .map_err(|e| ExportError::Database(e.to_string()))?;
     }
 
-    // Read workspace metadata before dropping storage (archive must still be alive).
-    let workspace_metadata: Option<WorkspaceMetadata> =
-        try_read_entry(&mut archive, "workspace.json", zip_password)
-            .and_then(|cursor| serde_json::from_reader(cursor).ok());
-
     // Drop storage before opening via Workspace::open (avoids double-locking the file).
     drop(storage);
 
@@ -550,30 +770,23 @@ pub fn import_workspace<R: Read + Seek>(
         .map_err(|e| ExportError::Database(e.to_string()))?;
 
     // Restore attachments if the archive contains them.
-    // Use try_read_entry so the borrow on archive is fully released before
-    // we call by_name again for each individual attachment file.
-    if let Some(att_cursor) = try_read_entry(&mut archive, "attachments.json", zip_password) {
-        let att_json = String::from_utf8(att_cursor.into_inner()).unwrap_or_default();
-        let attachment_metas: Vec<AttachmentMeta> =
-            serde_json::from_str(&att_json).unwrap_or_default();
-
-        for meta in attachment_metas {
-            let zip_path = format!("attachments/{}/{}", meta.id, meta.filename);
-            if let Some(file_cursor) = try_read_entry(&mut archive, &zip_path, zip_password) {
-                let plaintext = file_cursor.into_inner();
-                let _ = workspace.attach_file_with_id(
-                    &meta.id,
-                    &meta.note_id,
-                    &meta.filename,
-                    meta.mime_type.as_deref(),
-                    &plaintext,
-                );
-            }
+    let attachment_metas = read_attachment_metas(&mut archive, zip_password);
+    let attachment_count = attachment_metas.len();
+    for meta in attachment_metas {
+        let zip_path = format!("attachments/{}/{}", meta.id, meta.filename);
+        if let Some(entry) = open_entry(&mut archive, &zip_path, zip_password) {
+            let _ = workspace.attach_file_with_id_from_reader(
+                &meta.id,
+                &meta.note_id,
+                &meta.filename,
+                meta.mime_type.as_deref(),
+                entry,
+            );
         }
     }
 
     // Restore workspace metadata if the archive contained it.
-    if let Some(ref meta) = workspace_metadata {
+    if let Some(ref meta) = contents.workspace {
         workspace
             .set_workspace_metadata(meta)
             .map_err(|e| ExportError::Database(e.to_string()))?;
@@ -590,12 +803,273 @@ pub fn import_workspace<R: Read + Seek>(
 
     Ok(ImportResult {
         app_version: export_notes.app_version,
+        format_version: contents.format_version,
         note_count: export_notes.notes.len(),
         script_count,
-        metadata: workspace_metadata,
+        metadata: contents.workspace,
+        attachment_count,
+        schemas: schemas_used(&export_notes.notes),
+        subtree: export_notes.subtree,
+    })
+}
+
+/// How a script in the archive that shares its `@name` with an existing user script
+/// (but has different source) is handled by [`merge_import_workspace`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ScriptConflictPolicy {
+    /// Leave the existing script untouched and report the conflict.
+    #[default]
+    KeepExisting,
+    /// Overwrite the existing script's source with the archive's version.
+    ReplaceExisting,
+}
+
+/// Options for [`merge_import_workspace`].
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeImportOptions {
+    #[serde(default)]
+    pub id_strategy: GraftIdStrategy,
+    #[serde(default)]
+    pub script_conflicts: ScriptConflictPolicy,
+}
+
+/// A script from the archive whose name matches an existing user script with different source.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptConflict {
+    pub name: String,
+    pub existing_script_id: String,
+    /// `true` if the existing script was overwritten ([`ScriptConflictPolicy::ReplaceExisting`]).
+    pub replaced: bool,
+}
+
+/// Result of merging an archive into an existing workspace.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeImportResult {
+    pub app_version: String,
+    /// IDs of the grafted top-level notes in the target workspace.
+    pub root_note_ids: Vec<String>,
+    pub notes_created: usize,
+    pub notes_updated: usize,
+    pub scripts_created: usize,
+    pub script_conflicts: Vec<ScriptConflict>,
+    /// Names of archive scripts that were not applied because the importer
+    /// does not own the target workspace.
+    pub scripts_skipped: Vec<String>,
+    pub attachments_imported: usize,
+}
+
+/// Merges an export archive into an already-open workspace, grafting its notes under
+/// `parent_id`.
+///
+/// Unlike [`import_workspace`], nothing is written directly to the database: scripts,
+/// notes and attachments all go through [`Workspace`] methods, so every change is
+/// logged as a signed operation and reaches peers on the next sync. Attachments are
+/// re-encrypted with the target workspace's attachment key.
+///
+/// Scripts are matched against existing user scripts by `@name`. Identical scripts
+/// are skipped; scripts with different source are reported as [`ScriptConflict`]s and
+/// resolved per [`MergeImportOptions::script_conflicts`]. Scripts are only written
+/// when the importer owns the workspace — otherwise they are listed in
+/// `scripts_skipped` and notes whose schema is missing cause an error.
+///
+/// Scripts and notes are validated, and attachments are read from the archive and
+/// encrypted into the workspace unrecorded, before the first write, so an archive
+/// that fails validation or has an unreadable or oversized attachment leaves the
+/// workspace unchanged. The writes themselves are not one transaction: scripts,
+/// the grafted notes and each attachment row are committed in turn, and a
+/// database error partway through (e.g. a full disk) leaves the earlier ones
+/// applied. Staged attachment content that was not recorded is removed.
+///
+/// # Errors
+///
+/// Returns [`ExportError::InvalidFormat`] if `notes.json` is missing or the format
+/// version is newer than [`ARCHIVE_FORMAT_VERSION`], [`ExportError::Validation`] if
+/// a document does not match the format, and [`ExportError::Database`] if a script,
+/// note or attachment cannot be applied to the workspace (including permission
+/// denials).
+pub fn merge_import_workspace<R: Read + Seek>(
+    reader: R,
+    workspace: &mut Workspace,
+    parent_id: &str,
+    zip_password: Option<&str>,
+    options: MergeImportOptions,
+) -> Result<MergeImportResult, ExportError> {
+    let mut archive = ZipArchive::new(reader)?;
+    let contents = read_archive_contents(&mut archive, zip_password)?;
+    let export_notes = contents.notes;
+    let script_sources = read_script_sources(&mut archive, contents.scripts, zip_password)?;
+
+    // Plan the script changes, then validate them together with the notes and
+    // attachments before anything is written, so a bad archive leaves the
+    // workspace untouched.
+    let existing_scripts = workspace
+        .list_user_scripts()
+        .map_err(|e| ExportError::Database(e.to_string()))?;
+    // (ID of the script to replace, or `None` to create one; source; category)
+    let mut script_writes: Vec<(Option<String>, &str, String)> = Vec::new();
+    let mut script_conflicts = Vec::new();
+    let mut scripts_skipped = Vec::new();
+    for (source, entry) in &script_sources {
+        let fm = user_script::parse_front_matter(source);
+        let existing = existing_scripts.iter().find(|s| s.name == fm.name);
+        if existing.is_some_and(|s| s.source_code == *source) {
+            continue;
+        }
+        if !workspace.is_owner() {
+            scripts_skipped.push(fm.name);
+            continue;
+        }
+        match existing {
+            Some(script) => {
+                let replaced = options.script_conflicts == ScriptConflictPolicy::ReplaceExisting;
+                if replaced {
+                    script_writes.push((Some(script.id.clone()), source, script.category.clone()));
+                }
+                script_conflicts.push(ScriptConflict {
+                    name: fm.name,
+                    existing_script_id: script.id.clone(),
+                    replaced,
+                });
+            }
+            None => script_writes.push((None, source, entry.category.clone())),
+        }
+    }
+    let pending_scripts: Vec<(&str, &str)> = script_writes
+        .iter()
+        .map(|(_, source, category)| (*source, category.as_str()))
+        .collect();
+    workspace
+        .check_graft(
+            &pending_scripts,
+            &export_notes.notes,
+            parent_id,
+            options.id_strategy,
+        )
+        .map_err(|e| ExportError::Database(e.to_string()))?;
+
+    let attachment_metas = read_attachment_metas(&mut archive, zip_password);
+    let attachment_id_map: HashMap<String, String> = attachment_metas
+        .iter()
+        .map(|meta| {
+            let new_id = match options.id_strategy {
+                GraftIdStrategy::Remap => uuid::Uuid::new_v4().to_string(),
+                GraftIdStrategy::Preserve => meta.id.clone(),
+            };
+            (meta.id.clone(), new_id)
+        })
+        .collect();
+
+    // Encrypt the attachments into the workspace, unrecorded, so that archive
+    // content that cannot be read or is too large fails before the first write.
+    let incoming_ids: HashSet<&str> = export_notes.notes.iter().map(|n| n.id.as_str()).collect();
+    let mut staged: Vec<(&AttachmentMeta, &str, StreamedAttachment)> = Vec::new();
+    let discard = |workspace: &Workspace,
+                   staged: &[(&AttachmentMeta, &str, StreamedAttachment)]| {
+        for (_, new_id, _) in staged {
+            workspace.discard_staged_file(new_id);
+        }
+    };
+    for meta in &attachment_metas {
+        if !incoming_ids.contains(meta.note_id.as_str()) {
+            continue;
+        }
+        let new_id = attachment_id_map[&meta.id].as_str();
+        if options.id_strategy == GraftIdStrategy::Preserve
+            && workspace.get_attachment_meta(new_id).is_ok()
+        {
+            continue;
+        }
+        let zip_path = format!("attachments/{}/{}", meta.id, meta.filename);
+        let Some(mut entry) = open_entry(&mut archive, &zip_path, zip_password) else {
+            log::warn!("merge import: attachment '{zip_path}' missing from archive");
+            continue;
+        };
+        match workspace.stage_attachment_file(new_id, &mut entry) {
+            Ok(streamed) => staged.push((meta, new_id, streamed)),
+            Err(e) => {
+                discard(workspace, &staged);
+                return Err(ExportError::Database(e.to_string()));
+            }
+        }
+    }
+
+    let written = apply_merge_writes(
+        workspace,
+        &script_writes,
+        &export_notes.notes,
+        parent_id,
+        options.id_strategy,
+        &attachment_id_map,
+    );
+    let (scripts_created, outcome) = match written {
+        Ok(written) => written,
+        Err(e) => {
+            discard(workspace, &staged);
+            return Err(e);
+        }
+    };
+
+    let mut attachments_imported = 0;
+    let mut staged = staged.into_iter();
+    while let Some((meta, new_id, streamed)) = staged.next() {
+        let note_id = &outcome.id_map[&meta.note_id];
+        if let Err(e) = workspace.attach_staged_file(
+            new_id,
+            note_id,
+            &meta.filename,
+            meta.mime_type.as_deref(),
+            streamed,
+        ) {
+            workspace.discard_staged_file(new_id);
+            discard(workspace, staged.as_slice());
+            return Err(ExportError::Database(e.to_string()));
+        }
+        attachments_imported += 1;
+    }
+
+    Ok(MergeImportResult {
+        app_version: export_notes.app_version,
+        root_note_ids: outcome.root_ids,
+        notes_created: outcome.created,
+        notes_updated: outcome.updated,
+        scripts_created,
+        script_conflicts,
+        scripts_skipped,
+        attachments_imported,
     })
 }
 
+/// Writes the scripts and grafts the notes of a merge import, returning the
+/// number of scripts created and the graft outcome.
+fn apply_merge_writes(
+    workspace: &mut Workspace,
+    script_writes: &[(Option<String>, &str, String)],
+    notes: &[Note],
+    parent_id: &str,
+    id_strategy: GraftIdStrategy,
+    attachment_id_map: &HashMap<String, String>,
+) -> Result<(usize, GraftOutcome), ExportError> {
+    let mut scripts_created = 0;
+    for (script_id, source, category) in script_writes {
+        match script_id {
+            Some(id) => workspace.update_user_script(id, source),
+            None => {
+                scripts_created += 1;
+                workspace.create_user_script_with_category(source, category)
+            }
+        }
+        .map_err(|e| ExportError::Database(e.to_string()))?;
+    }
+    let outcome = workspace
+        .graft_notes(notes, parent_id, id_strategy, attachment_id_map)
+        .map_err(|e| ExportError::Database(e.to_string()))?;
+    Ok((scripts_created, outcome))
+}
+
 #[cfg(test)]
 #[path = "export_tests.rs"]
 mod tests;
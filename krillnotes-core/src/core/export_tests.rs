@@ -11,6 +11,7 @@ fn test_export_notes_serialization() {
         version: 1,
         app_version: "0.1.0".to_string(),
         notes: vec![],
+        subtree: None,
     };
     let json = serde_json::to_string(&export).unwrap();
     assert!(json.contains("\"version\":1"));
@@ -29,7 +30,7 @@ fn test_script_manifest_serialization() {
             filename: "contacts.rhai".to_string(),
             load_order: 0,
             enabled: true,
-            category: Some("schema".to_string()),
+            category: "schema".to_string(),
         }],
     };
     let json = serde_json::to_string(&manifest).unwrap();
@@ -81,7 +82,7 @@ fn test_export_workspace_creates_valid_zip() {
     // Must contain notes.json
     let notes_file = archive.by_name("notes.json").unwrap();
     let notes_data: ExportNotes = serde_json::from_reader(notes_file).unwrap();
-    assert_eq!(notes_data.version, 1);
+    assert_eq!(notes_data.version, crate::ARCHIVE_FORMAT_VERSION);
     assert!(!notes_data.app_version.is_empty());
     assert!(!notes_data.notes.is_empty()); // at least the root note
 
@@ -374,7 +375,7 @@ fn test_export_includes_workspace_json() {
     let mut archive = zip::ZipArchive::new(Cursor::new(&buf)).unwrap();
     let ws_file = archive.by_name("workspace.json").unwrap();
     let ws_meta: WorkspaceMetadata = serde_json::from_reader(ws_file).unwrap();
-    assert_eq!(ws_meta.version, 1);
+    assert_eq!(ws_meta.version, crate::ARCHIVE_FORMAT_VERSION);
     assert!(
         ws_meta.owner_pubkey.is_none(),
         "exported workspace.json must not contain owner_pubkey"
@@ -979,6 +980,55 @@ fn test_import_restores_attachments() {
     assert_eq!(recovered, b"attachment content" as &[u8]);
 }
 
+#[test]
+fn test_export_import_streams_multi_frame_attachment() {
+    let dir_src = tempfile::tempdir().unwrap();
+    let db_src = dir_src.path().join("notes.db");
+    let mut ws = Workspace::create(
+        &db_src,
+        "pass",
+        "test-identity",
+        ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]),
+        test_gate(),
+        None,
+    )
+    .unwrap();
+    let root_id = ws.list_all_notes().unwrap()[0].id.clone();
+
+    let data: Vec<u8> = (0..300_000u32).map(|i| (i % 241) as u8).collect();
+    ws.attach_file_from_reader(&root_id, "clip.bin", None, &data[..], None)
+        .unwrap();
+
+    let mut buf = Vec::new();
+    export_workspace(&ws, Cursor::new(&mut buf), Some("zip-pw")).unwrap();
+
+    let dir_dst = tempfile::tempdir().unwrap();
+    let db_dst = dir_dst.path().join("notes.db");
+    import_workspace(
+        Cursor::new(&buf),
+        &db_dst,
+        Some("zip-pw"),
+        "newpass",
+        "test-identity",
+        ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]),
+    )
+    .unwrap();
+
+    let ws2 = Workspace::open(
+        &db_dst,
+        "newpass",
+        "test-identity",
+        ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]),
+        test_gate(),
+        None,
+    )
+    .unwrap();
+    let attachments = ws2.list_all_attachments().unwrap();
+    assert_eq!(attachments.len(), 1);
+    assert_eq!(attachments[0].size_bytes, data.len() as i64);
+    assert_eq!(ws2.get_attachment_bytes(&attachments[0].id).unwrap(), data);
+}
+
 #[test]
 fn test_workspace_metadata_absent_in_old_archive() {
     // Old archive: workspace.json only has version + tags (old format), no metadata fields.
@@ -1148,3 +1198,351 @@ fn test_import_makes_importer_the_owner() {
         "importer should be recognized as owner"
     );
 }
+
+fn merge_test_workspace(dir: &std::path::Path, seed: u8) -> Workspace {
+    Workspace::create(
+        dir.join("notes.db"),
+        "pass",
+        "test-identity",
+        ed25519_dalek::SigningKey::from_bytes(&[seed; 32]),
+        test_gate(),
+        None,
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_merge_import_grafts_subtree_with_remapped_ids() {
+    let dir_src = tempfile::tempdir().unwrap();
+    let mut src = merge_test_workspace(dir_src.path(), 1);
+    let src_root = src.list_all_notes().unwrap()[0].id.clone();
+    let trip = src
+        .create_note(&src_root, AddPosition::AsChild, "TextNote")
+        .unwrap();
+    src.update_note_title(&trip, "Travel 2026".to_string())
+        .unwrap();
+    src.update_note_tags(&trip, vec!["travel".to_string()])
+        .unwrap();
+    src.attach_file(&trip, "map.txt", Some("text/plain"), b"route", None)
+        .unwrap();
+    src.create_user_script(
+        "// @name: Custom Widget\n// @description: Widget cards\nschema(\"Widget\", #{ version: 1, fields: [] });",
+    )
+    .unwrap();
+
+    let mut buf = Vec::new();
+    export_workspace(&src, Cursor::new(&mut buf), None).unwrap();
+
+    let dir_dst = tempfile::tempdir().unwrap();
+    let mut dst = merge_test_workspace(dir_dst.path(), 2);
+    let dst_root = dst.list_all_notes().unwrap()[0].id.clone();
+    let ops_before = dst.list_operations(None, None, None).unwrap().len();
+
+    let result = merge_import_workspace(
+        Cursor::new(&buf),
+        &mut dst,
+        &dst_root,
+        None,
+        MergeImportOptions::default(),
+    )
+    .unwrap();
+
+    assert_eq!(result.notes_created, 2);
+    assert_eq!(
+        result.scripts_created, 1,
+        "only the new Widget script is created"
+    );
+    assert!(result.script_conflicts.is_empty());
+    assert_eq!(result.attachments_imported, 1);
+    assert_eq!(result.root_note_ids.len(), 1);
+
+    let grafted_root = dst.get_note(&result.root_note_ids[0]).unwrap();
+    assert_eq!(grafted_root.parent_id.as_deref(), Some(dst_root.as_str()));
+    assert_ne!(grafted_root.id, src_root, "IDs are remapped");
+
+    let children = dst.get_children(&grafted_root.id).unwrap();
+    assert_eq!(children.len(), 1);
+    assert_eq!(children[0].title, "Travel 2026");
+    assert_ne!(children[0].id, trip);
+    assert_eq!(children[0].tags, vec!["travel".to_string()]);
+
+    let attachments = dst.get_attachments(&children[0].id).unwrap();
+    assert_eq!(attachments.len(), 1);
+    assert_eq!(
+        dst.get_attachment_bytes(&attachments[0].id).unwrap(),
+        b"route" as &[u8]
+    );
+
+    // Every change is logged as an operation so peers receive it.
+    let ops_after = dst.list_operations(None, None, None).unwrap().len();
+    assert!(
+        ops_after >= ops_before + 5,
+        "script + 2 creates + tags + attachment must be logged, got {} new ops",
+        ops_after - ops_before
+    );
+}
+
+#[test]
+fn test_merge_import_preserve_ids_upserts_existing_notes() {
+    let dir_src = tempfile::tempdir().unwrap();
+    let mut src = merge_test_workspace(dir_src.path(), 1);
+    let src_root = src.list_all_notes().unwrap()[0].id.clone();
+    let child = src
+        .create_note(&src_root, AddPosition::AsChild, "TextNote")
+        .unwrap();
+    src.update_note_title(&child, "Original".to_string())
+        .unwrap();
+
+    let mut buf = Vec::new();
+    export_workspace(&src, Cursor::new(&mut buf), None).unwrap();
+
+    let dir_dst = tempfile::tempdir().unwrap();
+    let mut dst = merge_test_workspace(dir_dst.path(), 2);
+    let dst_root = dst.list_all_notes().unwrap()[0].id.clone();
+    let options = MergeImportOptions {
+        id_strategy: GraftIdStrategy::Preserve,
+        ..Default::default()
+    };
+    let first =
+        merge_import_workspace(Cursor::new(&buf), &mut dst, &dst_root, None, options).unwrap();
+    assert_eq!(first.notes_created, 2);
+    assert_eq!(dst.get_note(&child).unwrap().title, "Original");
+
+    // Re-export with a changed title and merge again: the same note is updated in place.
+    src.update_note_title(&child, "Renamed".to_string())
+        .unwrap();
+    let mut buf2 = Vec::new();
+    export_workspace(&src, Cursor::new(&mut buf2), None).unwrap();
+    let second =
+        merge_import_workspace(Cursor::new(&buf2), &mut dst, &dst_root, None, options).unwrap();
+    assert_eq!(second.notes_created, 0);
+    assert_eq!(second.notes_updated, 1);
+    assert_eq!(dst.get_note(&child).unwrap().title, "Renamed");
+}
+
+#[test]
+fn test_merge_import_reports_script_conflicts() {
+    let dir_src = tempfile::tempdir().unwrap();
+    let mut src = merge_test_workspace(dir_src.path(), 1);
+    src.create_user_script(
+        "// @name: Custom Widget\n// @description: v2\nschema(\"Widget\", #{ version: 1, fields: [] });",
+    )
+    .unwrap();
+    let mut buf = Vec::new();
+    export_workspace(&src, Cursor::new(&mut buf), None).unwrap();
+
+    let dir_dst = tempfile::tempdir().unwrap();
+    let mut dst = merge_test_workspace(dir_dst.path(), 2);
+    let dst_root = dst.list_all_notes().unwrap()[0].id.clone();
+    let (existing, _) = dst
+        .create_user_script(
+            "// @name: Custom Widget\n// @description: v1\nschema(\"Widget\", #{ version: 1, fields: [] });",
+        )
+        .unwrap();
+
+    let kept = merge_import_workspace(
+        Cursor::new(&buf),
+        &mut dst,
+        &dst_root,
+        None,
+        MergeImportOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(kept.script_conflicts.len(), 1);
+    assert_eq!(kept.script_conflicts[0].existing_script_id, existing.id);
+    assert!(!kept.script_conflicts[0].replaced);
+    assert_eq!(dst.get_user_script(&existing.id).unwrap().description, "v1");
+
+    let replaced = merge_import_workspace(
+        Cursor::new(&buf),
+        &mut dst,
+        &dst_root,
+        None,
+        MergeImportOptions {
+            script_conflicts: ScriptConflictPolicy::ReplaceExisting,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert!(replaced.script_conflicts[0].replaced);
+    assert_eq!(dst.get_user_script(&existing.id).unwrap().description, "v2");
+}
+
+#[test]
+fn test_merge_import_failure_writes_nothing() {
+    let dir_src = tempfile::tempdir().unwrap();
+    let mut src = merge_test_workspace(dir_src.path(), 1);
+    let src_root = src.list_all_notes().unwrap()[0].id.clone();
+    src.attach_file(&src_root, "map.txt", Some("text/plain"), b"route", None)
+        .unwrap();
+    src.create_user_script(
+        "// @name: Custom Widget\n// @description: Widget cards\nschema(\"Widget\", #{ version: 1, fields: [] });",
+    )
+    .unwrap();
+    let mut buf = Vec::new();
+    export_workspace(&src, Cursor::new(&mut buf), None).unwrap();
+
+    let dir_dst = tempfile::tempdir().unwrap();
+    let mut dst = merge_test_workspace(dir_dst.path(), 2);
+    let dst_root = dst.list_all_notes().unwrap()[0].id.clone();
+    dst.create_user_script(
+        "// @name: LeafSchema\nschema(\"LeafType\", #{ version: 1, is_leaf: true, fields: [] });",
+    )
+    .unwrap();
+    let leaf = dst
+        .create_note(&dst_root, AddPosition::AsChild, "LeafType")
+        .unwrap();
+    let scripts_before = dst.list_user_scripts().unwrap().len();
+    let notes_before = dst.list_all_notes().unwrap().len();
+    let ops_before = dst.list_operations(None, None, None).unwrap().len();
+
+    // The notes cannot go under a leaf note, so the Widget script is not created either.
+    let under_leaf = merge_import_workspace(
+        Cursor::new(&buf),
+        &mut dst,
+        &leaf,
+        None,
+        MergeImportOptions::default(),
+    );
+    assert!(under_leaf.is_err());
+
+    // The attachment exceeds the limit, so neither the script nor the notes are written.
+    dst.set_attachment_max_size_bytes(Some(2)).unwrap();
+    let too_large = merge_import_workspace(
+        Cursor::new(&buf),
+        &mut dst,
+        &dst_root,
+        None,
+        MergeImportOptions::default(),
+    );
+    assert!(matches!(too_large, Err(ExportError::Database(msg)) if msg.contains("too large")));
+    let attachment_files = std::fs::read_dir(dir_dst.path().join("attachments"))
+        .unwrap()
+        .count();
+    assert_eq!(attachment_files, 0, "no staged attachment is left behind");
+
+    assert_eq!(dst.list_user_scripts().unwrap().len(), scripts_before);
+    assert_eq!(dst.list_all_notes().unwrap().len(), notes_before);
+    assert_eq!(
+        dst.list_operations(None, None, None).unwrap().len(),
+        ops_before
+    );
+    assert!(dst.script_registry().get_schema("Widget").is_err());
+}
+
+#[test]
+fn test_subtree_export_with_referenced_stubs() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut ws = merge_test_workspace(dir.path(), 1);
+    ws.create_user_script(
+        "// @name: Trip\n// @description: Trip schema\nschema(\"Trip\", #{ version: 1, fields: [ #{ name: \"place\", type: \"note_link\" } ] });",
+    )
+    .unwrap();
+    ws.create_user_script(
+        "// @name: Unused\n// @description: Not referenced\nschema(\"Unused\", #{ version: 1, fields: [] });",
+    )
+    .unwrap();
+    let root = ws.list_all_notes().unwrap()[0].id.clone();
+
+    let places = ws
+        .create_note(&root, AddPosition::AsChild, "TextNote")
+        .unwrap();
+    ws.update_note_title(&places, "Kyoto".to_string()).unwrap();
+    ws.attach_file(&places, "kyoto.jpg", None, b"outside", None)
+        .unwrap();
+
+    let travel = ws
+        .create_note(&root, AddPosition::AsChild, "TextNote")
+        .unwrap();
+    ws.update_note_title(&travel, "Travel 2026".to_string())
+        .unwrap();
+    ws.attach_file(&travel, "itinerary.txt", None, b"inside", None)
+        .unwrap();
+    let trip = ws
+        .create_note(&travel, AddPosition::AsChild, "Trip")
+        .unwrap();
+    let mut fields = ws.get_note(&trip).unwrap().fields;
+    fields.insert(
+        "place".to_string(),
+        crate::FieldValue::NoteLink(Some(places.clone())),
+    );
+    ws.update_note(&trip, "Japan".to_string(), fields).unwrap();
+
+    let options = ExportOptions {
+        subtree_root: Some(travel.clone()),
+        referenced_notes: ReferencedNotes::Stubs,
+    };
+    let mut buf = Vec::new();
+    export_workspace_with_options(&ws, Cursor::new(&mut buf), None, &options).unwrap();
+
+    let peeked = peek_import(Cursor::new(&buf), None).unwrap();
+    assert_eq!(peeked.note_count, 3, "subtree (2) + one referenced stub");
+    assert_eq!(peeked.attachment_count, 1, "only the subtree's attachment");
+    assert_eq!(
+        peeked.schemas,
+        vec!["TextNote".to_string(), "Trip".to_string()]
+    );
+    let subtree = peeked.subtree.expect("subtree info");
+    assert_eq!(subtree.root_id, travel);
+    assert_eq!(subtree.root_title, "Travel 2026");
+    assert_eq!(subtree.referenced_note_ids, vec![places.clone()]);
+
+    let mut archive = zip::ZipArchive::new(Cursor::new(&buf)).unwrap();
+    let manifest: ScriptManifest =
+        serde_json::from_reader(archive.by_name("scripts/scripts.json").unwrap()).unwrap();
+    let names: Vec<&str> = manifest
+        .scripts
+        .iter()
+        .map(|s| s.filename.as_str())
+        .collect();
+    assert!(names.contains(&"trip.rhai"));
+    assert!(
+        !names.contains(&"unused.rhai"),
+        "unused schema scripts are omitted"
+    );
+
+    let notes: ExportNotes =
+        serde_json::from_reader(archive.by_name("notes.json").unwrap()).unwrap();
+    let root_note = notes.notes.iter().find(|n| n.id == travel).unwrap();
+    assert!(root_note.parent_id.is_none(), "subtree root is detached");
+    let stub = notes.notes.iter().find(|n| n.id == places).unwrap();
+    assert_eq!(stub.title, "Kyoto");
+    assert!(stub.fields.is_empty(), "stubs carry no field values");
+}
+
+#[test]
+fn test_subtree_export_excluding_references_clears_links() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut ws = merge_test_workspace(dir.path(), 1);
+    ws.create_user_script(
+        "// @name: Trip\n// @description: Trip schema\nschema(\"Trip\", #{ version: 1, fields: [ #{ name: \"place\", type: \"note_link\" } ] });",
+    )
+    .unwrap();
+    let root = ws.list_all_notes().unwrap()[0].id.clone();
+    let outside = ws
+        .create_note(&root, AddPosition::AsChild, "TextNote")
+        .unwrap();
+    let trip = ws.create_note(&root, AddPosition::AsChild, "Trip").unwrap();
+    let mut fields = ws.get_note(&trip).unwrap().fields;
+    fields.insert(
+        "place".to_string(),
+        crate::FieldValue::NoteLink(Some(outside.clone())),
+    );
+    ws.update_note(&trip, "Japan".to_string(), fields).unwrap();
+
+    let options = ExportOptions {
+        subtree_root: Some(trip.clone()),
+        referenced_notes: ReferencedNotes::Exclude,
+    };
+    let mut buf = Vec::new();
+    export_workspace_with_options(&ws, Cursor::new(&mut buf), None, &options).unwrap();
+
+    let mut archive = zip::ZipArchive::new(Cursor::new(&buf)).unwrap();
+    let notes: ExportNotes =
+        serde_json::from_reader(archive.by_name("notes.json").unwrap()).unwrap();
+    assert_eq!(notes.notes.len(), 1);
+    assert_eq!(
+        notes.notes[0].fields.get("place"),
+        Some(&crate::FieldValue::NoteLink(None))
+    );
+}
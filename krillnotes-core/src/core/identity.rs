@@ -14,7 +14,6 @@ use aes_gcm::{
     aead::{Aead, KeyInit},
     Aes256Gcm, Nonce,
 };
-use argon2::Argon2;
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use chrono::{DateTime, Utc};
 use ed25519_dalek::SigningKey;
@@ -24,24 +23,10 @@ use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
+use crate::core::kdf::{derive_key, KdfCost};
+use crate::core::succession::SuccessionRecord;
 use crate::Result;
 
-// ---------------------------------------------------------------------------
-// Argon2id parameters
-// ---------------------------------------------------------------------------
-
-#[cfg(test)]
-const ARGON2_M_COST: u32 = 1024; // 1 MiB — fast for tests
-#[cfg(test)]
-const ARGON2_T_COST: u32 = 1;
-
-#[cfg(not(test))]
-const ARGON2_M_COST: u32 = 65536; // 64 MiB — production
-#[cfg(not(test))]
-const ARGON2_T_COST: u32 = 3;
-
-const ARGON2_P_COST: u32 = 1;
-
 // ---------------------------------------------------------------------------
 // Identity file format (on-disk JSON)
 // ---------------------------------------------------------------------------
@@ -73,6 +58,17 @@ pub struct KdfParams {
     pub p_cost: u32,
 }
 
+impl KdfParams {
+    /// The Argon2id cost these parameters were written with.
+    pub fn cost(&self) -> KdfCost {
+        KdfCost {
+            m_cost: self.m_cost,
+            t_cost: self.t_cost,
+            p_cost: self.p_cost,
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Identity references
 // ---------------------------------------------------------------------------
@@ -109,9 +105,16 @@ pub struct IdentityManager {
     home_dir: PathBuf,
     /// UUID -> folder name (the display-name folder as it appears on disk).
     folder_cache: HashMap<Uuid, String>,
+    /// Argon2id cost new and rehashed identity files are written with.
+    kdf_policy: KdfCost,
 }
 
 /// Returned after successful unlock -- caller holds this and wipes on lock.
+///
+/// Dropping it zeroizes the signing key. The keys derived from it
+/// ([`contacts_key`](Self::contacts_key) and friends) are plain copies; the
+/// managers they are handed to zeroize theirs on drop, and callers should
+/// zeroize any other copy once done with it.
 #[derive(Debug)]
 pub struct UnlockedIdentity {
     pub identity_uuid: Uuid,
@@ -120,7 +123,21 @@ pub struct UnlockedIdentity {
     pub verifying_key: ed25519_dalek::VerifyingKey,
 }
 
+/// Returned by [`IdentityManager::recover_identity_from_mnemonic`].
+#[derive(Debug)]
+pub struct RecoveredIdentity {
+    /// The recovered identity, already unlocked.
+    pub identity: UnlockedIdentity,
+    /// Workspaces on disk bound to it, as `(workspace_folder, binding)`.
+    pub workspaces: Vec<(PathBuf, WorkspaceBinding)>,
+}
+
 impl UnlockedIdentity {
+    /// The 24-word BIP-39 recovery phrase encoding this identity's seed.
+    pub fn recovery_phrase(&self) -> String {
+        recovery_phrase_for_seed(self.signing_key.as_bytes())
+    }
+
     /// Derives a 32-byte encryption key for this identity's contact book.
     /// Uses HKDF-SHA256 with the Ed25519 seed as IKM.
     pub fn contacts_key(&self) -> [u8; 32] {
@@ -141,6 +158,26 @@ impl UnlockedIdentity {
         okm
     }
 
+    /// Derives a 32-byte encryption key for this identity's WebDAV accounts.
+    /// Uses HKDF-SHA256 with the Ed25519 seed as IKM.
+    pub fn webdav_key(&self) -> [u8; 32] {
+        let hk = hkdf::Hkdf::<sha2::Sha256>::new(None, self.signing_key.as_bytes());
+        let mut okm = [0u8; 32];
+        hk.expand(b"krillnotes-webdav-v1", &mut okm)
+            .expect("HKDF expand failed — output length is valid");
+        okm
+    }
+
+    /// Derives a 32-byte encryption key for this identity's S3 accounts.
+    /// Uses HKDF-SHA256 with the Ed25519 seed as IKM.
+    pub fn s3_key(&self) -> [u8; 32] {
+        let hk = hkdf::Hkdf::<sha2::Sha256>::new(None, self.signing_key.as_bytes());
+        let mut okm = [0u8; 32];
+        hk.expand(b"krillnotes-s3-v1", &mut okm)
+            .expect("HKDF expand failed — output length is valid");
+        okm
+    }
+
     /// Derives a per-device Ed25519 signing key for relay device registration.
     ///
     /// Each device + identity combination produces a unique keypair, derived via
@@ -197,9 +234,23 @@ impl IdentityManager {
         Ok(Self {
             home_dir,
             folder_cache,
+            kdf_policy: KdfCost::MINIMUM,
         })
     }
 
+    /// The Argon2id cost identity files are held to.
+    pub fn kdf_policy(&self) -> KdfCost {
+        self.kdf_policy
+    }
+
+    /// Raises the Argon2id cost policy, e.g. to the result of
+    /// [`crate::core::kdf::benchmark_kdf_cost`]. Costs below
+    /// [`KdfCost::MINIMUM`] are raised to it. Identity files written with a
+    /// weaker cost are rehashed the next time they are unlocked.
+    pub fn set_kdf_policy(&mut self, policy: KdfCost) {
+        self.kdf_policy = policy.max(KdfCost::MINIMUM);
+    }
+
     /// Scans `home_dir` for display-name folders that contain `.identity/identity.json`.
     fn scan_identities(home_dir: &Path) -> HashMap<Uuid, String> {
         let mut cache = HashMap::new();
@@ -288,64 +339,60 @@ impl IdentityManager {
         display_name: &str,
         passphrase: &str,
     ) -> Result<IdentityFile> {
-        // Generate Ed25519 keypair
+        self.create_identity_with_recovery_phrase(display_name, passphrase)
+            .map(|(file, _)| file)
+    }
+
+    /// Like [`Self::create_identity`], also returning the identity's 24-word
+    /// recovery phrase for the user to write down.
+    pub fn create_identity_with_recovery_phrase(
+        &mut self,
+        display_name: &str,
+        passphrase: &str,
+    ) -> Result<(IdentityFile, String)> {
         let mut seed = [0u8; 32];
         OsRng.fill_bytes(&mut seed);
-        let signing_key = SigningKey::from_bytes(&seed);
-        let verifying_key = signing_key.verifying_key();
-
-        // Argon2id: derive encryption key from passphrase
-        let mut salt = [0u8; 16];
-        OsRng.fill_bytes(&mut salt);
-
-        let mut derived_key = [0u8; 32];
-        let argon2 = Argon2::new(
-            argon2::Algorithm::Argon2id,
-            argon2::Version::V0x13,
-            argon2::Params::new(ARGON2_M_COST, ARGON2_T_COST, ARGON2_P_COST, Some(32)).map_err(
-                |e| crate::KrillnotesError::IdentityCorrupt(format!("Argon2 params: {e}")),
-            )?,
-        );
-        argon2
-            .hash_password_into(passphrase.as_bytes(), &salt, &mut derived_key)
-            .map_err(|e| crate::KrillnotesError::IdentityCorrupt(format!("Argon2 hash: {e}")))?;
-
-        // AES-256-GCM: encrypt seed
-        let cipher = Aes256Gcm::new_from_slice(&derived_key)
-            .map_err(|e| crate::KrillnotesError::IdentityCorrupt(format!("AES key: {e}")))?;
-        let mut nonce_bytes = [0u8; 12];
-        OsRng.fill_bytes(&mut nonce_bytes);
-        let nonce = Nonce::from_slice(&nonce_bytes);
-        let ciphertext = cipher
-            .encrypt(nonce, seed.as_ref())
-            .map_err(|e| crate::KrillnotesError::IdentityCorrupt(format!("AES encrypt: {e}")))?;
-
-        // Zero the seed from stack
+        let phrase = recovery_phrase_for_seed(&seed);
+        let file =
+            self.write_identity_from_seed(display_name, passphrase, &seed, Uuid::new_v4(), None);
         seed.fill(0);
-        derived_key.fill(0);
+        Ok((file?, phrase))
+    }
+
+    /// Encrypts `seed` under `passphrase` and writes a new identity file for
+    /// it, in `folder_name` if given (and free of another identity) or a
+    /// fresh display-name folder.
+    fn write_identity_from_seed(
+        &mut self,
+        display_name: &str,
+        passphrase: &str,
+        seed: &[u8; 32],
+        identity_uuid: Uuid,
+        folder_name: Option<String>,
+    ) -> Result<IdentityFile> {
+        let signing_key = SigningKey::from_bytes(seed);
+        let verifying_key = signing_key.verifying_key();
 
         // Build identity file
-        let identity_uuid = Uuid::new_v4();
         let identity_file = IdentityFile {
             identity_uuid,
             display_name: display_name.to_string(),
             public_key: BASE64.encode(verifying_key.as_bytes()),
-            private_key_enc: EncryptedKey {
-                ciphertext: BASE64.encode(&ciphertext),
-                nonce: BASE64.encode(nonce_bytes),
-                kdf: "argon2id".to_string(),
-                kdf_params: KdfParams {
-                    salt: BASE64.encode(salt),
-                    m_cost: ARGON2_M_COST,
-                    t_cost: ARGON2_T_COST,
-                    p_cost: ARGON2_P_COST,
-                },
-            },
+            private_key_enc: self.encrypt_seed(passphrase, seed, &self.kdf_policy)?,
             last_used: Some(Utc::now()),
         };
 
         // Create display-name folder with .identity/ subdirs
-        let folder_name = self.pick_folder_name(display_name);
+        let folder_name = folder_name
+            .filter(|name| {
+                !self
+                    .home_dir
+                    .join(name)
+                    .join(".identity")
+                    .join("identity.json")
+                    .exists()
+            })
+            .unwrap_or_else(|| self.pick_folder_name(display_name));
         let identity_dir = self.home_dir.join(&folder_name).join(".identity");
         Self::create_identity_subdirs(&identity_dir)?;
 
@@ -359,6 +406,101 @@ impl IdentityManager {
         Ok(identity_file)
     }
 
+    /// Rebuilds an identity from its 24-word recovery phrase, protected by a
+    /// new `passphrase`.
+    ///
+    /// Workspaces still on disk whose `binding.json` was written with this
+    /// key are found by decrypting their DB password; the identity takes
+    /// over their identity UUID and display-name folder so the bindings
+    /// resolve again. Keys derived from the seed (`contacts_key`,
+    /// `relay_key`, …) are the same as before, so the identity's encrypted
+    /// contacts and accounts open again if their folder survived.
+    ///
+    /// Returns `IdentityAlreadyExists` if an identity with the same key is
+    /// already registered.
+    pub fn recover_identity_from_mnemonic(
+        &mut self,
+        phrase: &str,
+        display_name: &str,
+        passphrase: &str,
+    ) -> Result<RecoveredIdentity> {
+        let mut seed = seed_from_recovery_phrase(phrase)?;
+        let result = self.recover_identity_from_seed(&seed, display_name, passphrase);
+        seed.fill(0);
+        result
+    }
+
+    fn recover_identity_from_seed(
+        &mut self,
+        seed: &[u8; 32],
+        display_name: &str,
+        passphrase: &str,
+    ) -> Result<RecoveredIdentity> {
+        let signing_key = SigningKey::from_bytes(seed);
+        let public_key = BASE64.encode(signing_key.verifying_key().as_bytes());
+        for uuid in self.folder_cache.keys() {
+            let Ok(data) = std::fs::read_to_string(self.identity_file_path(uuid)) else {
+                continue;
+            };
+            if let Ok(file) = serde_json::from_str::<IdentityFile>(&data) {
+                if file.public_key == public_key {
+                    return Err(crate::KrillnotesError::IdentityAlreadyExists(
+                        file.display_name,
+                    ));
+                }
+            }
+        }
+
+        // A workspace is ours if its DB password decrypts with this seed.
+        let mut previous: Option<(Uuid, String)> = None;
+        for base in std::fs::read_dir(&self.home_dir)?.flatten() {
+            let Ok(workspaces) = std::fs::read_dir(base.path()) else {
+                continue;
+            };
+            for workspace in workspaces.flatten() {
+                let dir = workspace.path();
+                let Ok(Some(binding)) = self.get_workspace_binding(&dir) else {
+                    continue;
+                };
+                if self.decrypt_db_password(&dir, seed).is_ok() {
+                    previous = Some((
+                        binding.identity_uuid,
+                        base.file_name().to_string_lossy().to_string(),
+                    ));
+                    break;
+                }
+            }
+            if previous.is_some() {
+                break;
+            }
+        }
+        let (identity_uuid, folder_name) = match previous {
+            Some((uuid, folder)) => (uuid, Some(folder)),
+            None => (Uuid::new_v4(), None),
+        };
+        if self.folder_cache.contains_key(&identity_uuid) {
+            return Err(crate::KrillnotesError::IdentityAlreadyExists(
+                identity_uuid.to_string(),
+            ));
+        }
+
+        self.write_identity_from_seed(display_name, passphrase, seed, identity_uuid, folder_name)?;
+        let workspaces = self.get_workspaces_for_identity(&identity_uuid)?;
+        log::info!(
+            "recovered identity {identity_uuid} from recovery phrase ({} bound workspaces)",
+            workspaces.len()
+        );
+        Ok(RecoveredIdentity {
+            identity: UnlockedIdentity {
+                identity_uuid,
+                display_name: display_name.to_string(),
+                verifying_key: signing_key.verifying_key(),
+                signing_key,
+            },
+            workspaces,
+        })
+    }
+
     /// Unlock an identity by decrypting its Ed25519 seed with the given passphrase.
     pub fn unlock_identity(
         &mut self,
@@ -390,18 +532,8 @@ impl IdentityManager {
             })?;
 
         // Argon2id: derive decryption key
-        let params = &identity_file.private_key_enc.kdf_params;
-        let mut derived_key = [0u8; 32];
-        let argon2 = Argon2::new(
-            argon2::Algorithm::Argon2id,
-            argon2::Version::V0x13,
-            argon2::Params::new(params.m_cost, params.t_cost, params.p_cost, Some(32)).map_err(
-                |e| crate::KrillnotesError::IdentityCorrupt(format!("Argon2 params: {e}")),
-            )?,
-        );
-        argon2
-            .hash_password_into(passphrase.as_bytes(), &salt, &mut derived_key)
-            .map_err(|e| crate::KrillnotesError::IdentityCorrupt(format!("Argon2 hash: {e}")))?;
+        let stored_cost = identity_file.private_key_enc.kdf_params.cost();
+        let mut derived_key = derive_key(passphrase, &salt, &stored_cost)?;
 
         // AES-256-GCM: decrypt seed
         let cipher = Aes256Gcm::new_from_slice(&derived_key)
@@ -419,6 +551,14 @@ impl IdentityManager {
         let signing_key = SigningKey::from_bytes(&seed);
         let verifying_key = signing_key.verifying_key();
 
+        // Rehash if the stored cost has fallen behind the policy in any
+        // parameter, keeping those in which it is stronger
+        if !stored_cost.meets(&self.kdf_policy) {
+            let cost = stored_cost.max(self.kdf_policy);
+            identity_file.private_key_enc = self.encrypt_seed(passphrase, &seed, &cost)?;
+            log::info!("Rehashed identity {identity_uuid} from {stored_cost:?} to {cost:?}");
+        }
+
         // Update last_used timestamp directly in identity.json
         identity_file.last_used = Some(Utc::now());
         let json = serde_json::to_string_pretty(&identity_file)?;
@@ -508,51 +648,14 @@ impl IdentityManager {
         let unlocked = self.unlock_identity(identity_uuid, old_passphrase)?;
         let seed = unlocked.signing_key.to_bytes();
 
-        // Generate new Argon2id salt and derive new key
-        let mut new_salt = [0u8; 16];
-        OsRng.fill_bytes(&mut new_salt);
-
-        let mut new_derived_key = [0u8; 32];
-        let argon2 = Argon2::new(
-            argon2::Algorithm::Argon2id,
-            argon2::Version::V0x13,
-            argon2::Params::new(ARGON2_M_COST, ARGON2_T_COST, ARGON2_P_COST, Some(32)).map_err(
-                |e| crate::KrillnotesError::IdentityCorrupt(format!("Argon2 params: {e}")),
-            )?,
-        );
-        argon2
-            .hash_password_into(new_passphrase.as_bytes(), &new_salt, &mut new_derived_key)
-            .map_err(|e| crate::KrillnotesError::IdentityCorrupt(format!("Argon2 hash: {e}")))?;
-
-        // Re-encrypt seed with new key
-        let cipher = Aes256Gcm::new_from_slice(&new_derived_key)
-            .map_err(|e| crate::KrillnotesError::IdentityCorrupt(format!("AES key: {e}")))?;
-        new_derived_key.fill(0);
-
-        let mut nonce_bytes = [0u8; 12];
-        OsRng.fill_bytes(&mut nonce_bytes);
-        let nonce = Nonce::from_slice(&nonce_bytes);
-        let ciphertext = cipher
-            .encrypt(nonce, seed.as_ref())
-            .map_err(|e| crate::KrillnotesError::IdentityCorrupt(format!("AES encrypt: {e}")))?;
-
         // Load and update identity file (preserves last_used)
         let file_path = self.identity_file_path(identity_uuid);
         let data = std::fs::read_to_string(&file_path)?;
         let mut identity_file: IdentityFile = serde_json::from_str(&data)
             .map_err(|e| crate::KrillnotesError::IdentityCorrupt(format!("JSON parse: {e}")))?;
 
-        identity_file.private_key_enc = EncryptedKey {
-            ciphertext: BASE64.encode(&ciphertext),
-            nonce: BASE64.encode(nonce_bytes),
-            kdf: "argon2id".to_string(),
-            kdf_params: KdfParams {
-                salt: BASE64.encode(new_salt),
-                m_cost: ARGON2_M_COST,
-                t_cost: ARGON2_T_COST,
-                p_cost: ARGON2_P_COST,
-            },
-        };
+        let cost = identity_file.private_key_enc.kdf_params.cost();
+        identity_file.private_key_enc = self.encrypt_seed(new_passphrase, &seed, &cost)?;
 
         let json = serde_json::to_string_pretty(&identity_file)?;
         std::fs::write(&file_path, json)?;
@@ -560,6 +663,95 @@ impl IdentityManager {
         Ok(())
     }
 
+    /// Replaces an identity's Ed25519 key with a freshly generated one.
+    ///
+    /// The old key signs a [`SuccessionRecord`] naming the new key. The
+    /// record is kept in `.identity/successions.json` and must be applied
+    /// to each workspace with `Workspace::rotate_identity_key` so peers
+    /// learn of it. Workspace bindings and the contacts, relay, WebDAV and
+    /// S3 stores are re-encrypted under keys derived from the new seed.
+    /// Relay servers know the old device keys, so relay accounts have to
+    /// log in again.
+    pub fn rotate_identity_key(
+        &mut self,
+        identity_uuid: &Uuid,
+        passphrase: &str,
+    ) -> Result<(UnlockedIdentity, SuccessionRecord)> {
+        let old = self.unlock_identity(identity_uuid, passphrase)?;
+        let mut seed = [0u8; 32];
+        OsRng.fill_bytes(&mut seed);
+        let signing_key = SigningKey::from_bytes(&seed);
+        let new = UnlockedIdentity {
+            identity_uuid: *identity_uuid,
+            display_name: old.display_name.clone(),
+            verifying_key: signing_key.verifying_key(),
+            signing_key,
+        };
+        let record = SuccessionRecord::new(
+            &old.signing_key,
+            &new.verifying_key,
+            Utc::now().timestamp_millis() as u64,
+        );
+
+        // Decrypt everything under the old keys before writing anything.
+        let mut bindings = Vec::new();
+        for (dir, binding) in self.get_workspaces_for_identity(identity_uuid)? {
+            let password = self.decrypt_db_password(&dir, old.signing_key.as_bytes())?;
+            bindings.push((dir, binding.workspace_uuid, password));
+        }
+        let identity_dir = self.identity_dir(identity_uuid);
+        let mut store_files = Vec::new();
+        for (store, old_key, new_key) in [
+            ("contacts", old.contacts_key(), new.contacts_key()),
+            ("relays", old.relay_key(), new.relay_key()),
+            ("webdav", old.webdav_key(), new.webdav_key()),
+            ("s3", old.s3_key(), new.s3_key()),
+        ] {
+            store_files.extend(reencrypt_store_files(
+                &identity_dir.join(store),
+                &old_key,
+                &new_key,
+            )?);
+        }
+
+        let file_path = self.identity_file_path(identity_uuid);
+        let data = std::fs::read_to_string(&file_path)?;
+        let mut identity_file: IdentityFile = serde_json::from_str(&data)
+            .map_err(|e| crate::KrillnotesError::IdentityCorrupt(format!("JSON parse: {e}")))?;
+        identity_file.public_key = BASE64.encode(new.verifying_key.as_bytes());
+        let cost = identity_file.private_key_enc.kdf_params.cost();
+        identity_file.private_key_enc = self.encrypt_seed(passphrase, &seed, &cost)?;
+        std::fs::write(&file_path, serde_json::to_string_pretty(&identity_file)?)?;
+
+        let mut successions = self.key_successions(identity_uuid)?;
+        successions.push(record.clone());
+        std::fs::write(
+            identity_dir.join("successions.json"),
+            serde_json::to_string_pretty(&successions)?,
+        )?;
+
+        for (dir, workspace_uuid, password) in bindings {
+            self.bind_workspace(identity_uuid, &workspace_uuid, &dir, &password, &seed)?;
+        }
+        for (path, json) in store_files {
+            std::fs::write(path, json)?;
+        }
+        seed.fill(0);
+
+        Ok((new, record))
+    }
+
+    /// The succession records of an identity's past key rotations, oldest first.
+    pub fn key_successions(&self, identity_uuid: &Uuid) -> Result<Vec<SuccessionRecord>> {
+        let path = self.identity_dir(identity_uuid).join("successions.json");
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let raw = std::fs::read_to_string(&path)?;
+        serde_json::from_str(&raw)
+            .map_err(|e| crate::KrillnotesError::IdentityCorrupt(format!("successions.json: {e}")))
+    }
+
     /// Renames an identity's display name and moves its folder.
     pub fn rename_identity(&mut self, identity_uuid: &Uuid, new_name: &str) -> Result<()> {
         // Update identity file
@@ -830,6 +1022,43 @@ impl IdentityManager {
 
     // --- private helpers ---
 
+    /// Encrypts `seed` under `passphrase` (Argon2id at `cost` with a fresh
+    /// salt, then AES-256-GCM).
+    fn encrypt_seed(
+        &self,
+        passphrase: &str,
+        seed: &[u8; 32],
+        cost: &KdfCost,
+    ) -> Result<EncryptedKey> {
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+
+        let mut derived_key = derive_key(passphrase, &salt, cost)?;
+
+        let cipher = Aes256Gcm::new_from_slice(&derived_key)
+            .map_err(|e| crate::KrillnotesError::IdentityCorrupt(format!("AES key: {e}")))?;
+        derived_key.fill(0);
+
+        let mut nonce_bytes = [0u8; 12];
+        OsRng.fill_bytes(&mut nonce_bytes);
+        let nonce = Nonce::from_slice(&nonce_bytes);
+        let ciphertext = cipher
+            .encrypt(nonce, seed.as_ref())
+            .map_err(|e| crate::KrillnotesError::IdentityCorrupt(format!("AES encrypt: {e}")))?;
+
+        Ok(EncryptedKey {
+            ciphertext: BASE64.encode(&ciphertext),
+            nonce: BASE64.encode(nonce_bytes),
+            kdf: "argon2id".to_string(),
+            kdf_params: KdfParams {
+                salt: BASE64.encode(salt),
+                m_cost: cost.m_cost,
+                t_cost: cost.t_cost,
+                p_cost: cost.p_cost,
+            },
+        })
+    }
+
     fn derive_db_password_key(&self, seed: &[u8; 32], workspace_uuid: &str) -> Result<[u8; 32]> {
         use hkdf::Hkdf;
         use sha2::Sha256;
@@ -866,6 +1095,96 @@ impl IdentityManager {
     }
 }
 
+/// Re-encrypts the `{ nonce, ciphertext }` JSON files of a per-identity store
+/// (contacts, relay, WebDAV or S3 accounts) from `old_key` to `new_key`.
+///
+/// Returns the rewritten `(path, json)` pairs without writing them. Legacy
+/// plaintext files are left alone.
+fn reencrypt_store_files(
+    dir: &Path,
+    old_key: &[u8; 32],
+    new_key: &[u8; 32],
+) -> Result<Vec<(PathBuf, String)>> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Ok(Vec::new());
+    };
+    let old_cipher = Aes256Gcm::new_from_slice(old_key)
+        .map_err(|e| crate::KrillnotesError::IdentityCorrupt(format!("AES key: {e}")))?;
+    let new_cipher = Aes256Gcm::new_from_slice(new_key)
+        .map_err(|e| crate::KrillnotesError::IdentityCorrupt(format!("AES key: {e}")))?;
+    let mut rewritten = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let mut envelope: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        let (Some(nonce), Some(ciphertext)) = (
+            envelope.get("nonce").and_then(|v| v.as_str()),
+            envelope.get("ciphertext").and_then(|v| v.as_str()),
+        ) else {
+            continue;
+        };
+        let corrupt = |what: &str| {
+            crate::KrillnotesError::IdentityCorrupt(format!("{what} in {}", path.display()))
+        };
+        let nonce = BASE64.decode(nonce).map_err(|_| corrupt("bad nonce"))?;
+        if nonce.len() != 12 {
+            return Err(corrupt("bad nonce"));
+        }
+        let ciphertext = BASE64
+            .decode(ciphertext)
+            .map_err(|_| corrupt("bad ciphertext"))?;
+        let plaintext = old_cipher
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+            .map_err(|_| corrupt("decrypt failed"))?;
+
+        let mut nonce_bytes = [0u8; 12];
+        OsRng.fill_bytes(&mut nonce_bytes);
+        let ciphertext = new_cipher
+            .encrypt(Nonce::from_slice(&nonce_bytes), plaintext.as_ref())
+            .map_err(|e| crate::KrillnotesError::IdentityCorrupt(format!("AES encrypt: {e}")))?;
+        envelope["nonce"] = BASE64.encode(nonce_bytes).into();
+        envelope["ciphertext"] = BASE64.encode(&ciphertext).into();
+        rewritten.push((path, serde_json::to_string_pretty(&envelope)?));
+    }
+    Ok(rewritten)
+}
+
+// ---------------------------------------------------------------------------
+// Recovery phrase
+// ---------------------------------------------------------------------------
+
+/// Encodes a 32-byte Ed25519 seed as a 24-word BIP-39 English phrase.
+fn recovery_phrase_for_seed(seed: &[u8; 32]) -> String {
+    bip39::Mnemonic::from_entropy_in(bip39::Language::English, seed)
+        .expect("32 bytes is a valid BIP-39 entropy length")
+        .to_string()
+}
+
+/// Decodes a recovery phrase back into the seed, checking its checksum.
+/// Case and surrounding whitespace are ignored.
+pub fn seed_from_recovery_phrase(phrase: &str) -> Result<[u8; 32]> {
+    let normalized = phrase
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mnemonic = bip39::Mnemonic::parse_in_normalized(bip39::Language::English, &normalized)
+        .map_err(|e| crate::KrillnotesError::InvalidRecoveryPhrase(e.to_string()))?;
+    let (entropy, len) = mnemonic.to_entropy_array();
+    if len != 32 {
+        return Err(crate::KrillnotesError::InvalidRecoveryPhrase(format!(
+            "expected 24 words, got {}",
+            mnemonic.word_count()
+        )));
+    }
+    let mut seed = [0u8; 32];
+    seed.copy_from_slice(&entropy[..32]);
+    Ok(seed)
+}
+
 // ---------------------------------------------------------------------------
 // Per-machine device UUID helpers
 // ---------------------------------------------------------------------------
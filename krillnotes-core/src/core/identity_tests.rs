@@ -167,6 +167,96 @@ fn test_change_passphrase() {
     assert_eq!(*unlocked_after.verifying_key.as_bytes(), pk_before);
 }
 
+#[test]
+fn unlock_rehashes_identity_below_kdf_policy() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut mgr = IdentityManager::new(dir.path().to_path_buf()).unwrap();
+    let identity = mgr.create_identity("Ivy", "pass").unwrap();
+    let stored = |mgr: &IdentityManager| {
+        let data =
+            std::fs::read_to_string(mgr.identity_file_path(&identity.identity_uuid)).unwrap();
+        serde_json::from_str::<IdentityFile>(&data)
+            .unwrap()
+            .private_key_enc
+    };
+    let before = stored(&mgr);
+    assert_eq!(before.kdf_params.t_cost, KdfCost::MINIMUM.t_cost);
+
+    // A policy below the minimum is raised to it, so nothing changes
+    mgr.set_kdf_policy(KdfCost {
+        m_cost: 8,
+        t_cost: 1,
+        p_cost: 1,
+    });
+    assert_eq!(mgr.kdf_policy(), KdfCost::MINIMUM);
+    mgr.unlock_identity(&identity.identity_uuid, "pass")
+        .unwrap();
+    assert_eq!(stored(&mgr).ciphertext, before.ciphertext);
+
+    // A stronger policy rehashes on the next successful unlock
+    let policy = KdfCost {
+        t_cost: KdfCost::MINIMUM.t_cost + 1,
+        ..KdfCost::MINIMUM
+    };
+    mgr.set_kdf_policy(policy);
+    assert!(mgr
+        .unlock_identity(&identity.identity_uuid, "wrong")
+        .is_err());
+    assert_eq!(stored(&mgr).kdf_params.t_cost, before.kdf_params.t_cost);
+
+    let unlocked = mgr
+        .unlock_identity(&identity.identity_uuid, "pass")
+        .unwrap();
+    let after = stored(&mgr);
+    assert_eq!(after.kdf_params.t_cost, policy.t_cost);
+    assert_ne!(after.kdf_params.salt, before.kdf_params.salt);
+    assert_eq!(
+        BASE64.encode(unlocked.verifying_key.as_bytes()),
+        identity.public_key
+    );
+
+    // The rehashed file still unlocks with the same passphrase
+    mgr.unlock_identity(&identity.identity_uuid, "pass")
+        .unwrap();
+    assert_eq!(stored(&mgr).ciphertext, after.ciphertext);
+}
+
+#[test]
+fn unlock_rehash_keeps_stronger_stored_parameters() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut mgr = IdentityManager::new(dir.path().to_path_buf()).unwrap();
+    let stored_cost = KdfCost {
+        m_cost: KdfCost::MINIMUM.m_cost * 2,
+        ..KdfCost::MINIMUM
+    };
+    mgr.set_kdf_policy(stored_cost);
+    let identity = mgr.create_identity("Ivy", "pass").unwrap();
+
+    // More iterations but less memory than the identity was written with
+    let policy = KdfCost {
+        t_cost: KdfCost::MINIMUM.t_cost + 1,
+        ..KdfCost::MINIMUM
+    };
+    mgr.set_kdf_policy(policy);
+    mgr.unlock_identity(&identity.identity_uuid, "pass")
+        .unwrap();
+
+    let data = std::fs::read_to_string(mgr.identity_file_path(&identity.identity_uuid)).unwrap();
+    let rehashed = serde_json::from_str::<IdentityFile>(&data)
+        .unwrap()
+        .private_key_enc
+        .kdf_params
+        .cost();
+    assert_eq!(
+        rehashed,
+        KdfCost {
+            m_cost: stored_cost.m_cost,
+            t_cost: policy.t_cost,
+            p_cost: policy.p_cost,
+        }
+    );
+}
+
 #[test]
 fn bind_and_get_workspace_binding_round_trips() {
     let tmp = tempfile::tempdir().unwrap();
@@ -623,6 +713,22 @@ fn test_relay_key_differs_from_contacts_key() {
     );
 }
 
+#[test]
+fn test_webdav_key_differs_from_relay_key() {
+    let signing_key = SigningKey::from_bytes(&[0x22u8; 32]);
+    let verifying_key = signing_key.verifying_key();
+    let unlocked = UnlockedIdentity {
+        identity_uuid: Uuid::new_v4(),
+        display_name: "Test".to_string(),
+        signing_key,
+        verifying_key,
+    };
+    assert_ne!(unlocked.webdav_key(), unlocked.relay_key());
+    assert_ne!(unlocked.webdav_key(), unlocked.contacts_key());
+    assert_ne!(unlocked.s3_key(), unlocked.webdav_key());
+    assert_ne!(unlocked.s3_key(), unlocked.relay_key());
+}
+
 #[test]
 fn test_relay_key_deterministic() {
     let seed = [0x11u8; 32];
@@ -729,3 +835,128 @@ fn new_discovers_existing_identities() {
     assert!(names.contains(&"Alice"));
     assert!(names.contains(&"Bob"));
 }
+
+#[test]
+fn recovery_phrase_encodes_the_seed() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mut mgr = IdentityManager::new(tmp.path().to_path_buf()).unwrap();
+    let (file, phrase) = mgr
+        .create_identity_with_recovery_phrase("Alice", "pass")
+        .unwrap();
+    assert_eq!(phrase.split_whitespace().count(), 24);
+
+    let unlocked = mgr.unlock_identity(&file.identity_uuid, "pass").unwrap();
+    assert_eq!(unlocked.recovery_phrase(), phrase);
+    let seed = seed_from_recovery_phrase(&format!("  {}\n", phrase.to_uppercase())).unwrap();
+    assert_eq!(&seed, unlocked.signing_key.as_bytes());
+}
+
+#[test]
+fn recover_identity_rediscovers_bound_workspaces() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mut mgr = IdentityManager::new(tmp.path().to_path_buf()).unwrap();
+    let (file, phrase) = mgr
+        .create_identity_with_recovery_phrase("Alice", "old-pass")
+        .unwrap();
+    let unlocked = mgr
+        .unlock_identity(&file.identity_uuid, "old-pass")
+        .unwrap();
+    let base = mgr.identity_base_dir(&file.identity_uuid).unwrap();
+    let ws_dir = base.join("Notes");
+    std::fs::create_dir_all(&ws_dir).unwrap();
+    let seed = unlocked.signing_key.to_bytes();
+    mgr.bind_workspace(&file.identity_uuid, "ws-uuid", &ws_dir, "db-pass", &seed)
+        .unwrap();
+
+    // The key file is lost; the workspace folder survives.
+    std::fs::remove_dir_all(base.join(".identity")).unwrap();
+    let mut mgr = IdentityManager::new(tmp.path().to_path_buf()).unwrap();
+    let recovered = mgr
+        .recover_identity_from_mnemonic(&phrase, "Alice", "new-pass")
+        .unwrap();
+
+    assert_eq!(recovered.identity.identity_uuid, file.identity_uuid);
+    assert_eq!(recovered.identity.verifying_key, unlocked.verifying_key);
+    assert_eq!(recovered.identity.contacts_key(), unlocked.contacts_key());
+    assert_eq!(recovered.identity.relay_key(), unlocked.relay_key());
+    assert_eq!(recovered.workspaces.len(), 1);
+    assert_eq!(recovered.workspaces[0].0, ws_dir);
+    assert_eq!(
+        mgr.decrypt_db_password(&ws_dir, &recovered.identity.signing_key.to_bytes())
+            .unwrap(),
+        "db-pass"
+    );
+    assert_eq!(mgr.identity_base_dir(&file.identity_uuid).unwrap(), base);
+    mgr.unlock_identity(&file.identity_uuid, "new-pass")
+        .unwrap();
+}
+
+#[test]
+fn recover_identity_rejects_bad_or_duplicate_phrases() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mut mgr = IdentityManager::new(tmp.path().to_path_buf()).unwrap();
+    let (_, phrase) = mgr
+        .create_identity_with_recovery_phrase("Alice", "pass")
+        .unwrap();
+
+    // All-zero entropy ends in "art"; this one fails the checksum.
+    let bad_checksum = vec!["abandon"; 24].join(" ");
+    assert!(matches!(
+        mgr.recover_identity_from_mnemonic(&bad_checksum, "Alice", "pass"),
+        Err(crate::KrillnotesError::InvalidRecoveryPhrase(_))
+    ));
+    let twelve = bip39::Mnemonic::from_entropy(&[7u8; 16])
+        .unwrap()
+        .to_string();
+    assert!(matches!(
+        mgr.recover_identity_from_mnemonic(&twelve, "Alice", "pass"),
+        Err(crate::KrillnotesError::InvalidRecoveryPhrase(_))
+    ));
+    assert!(matches!(
+        mgr.recover_identity_from_mnemonic(&phrase, "Alice again", "pass"),
+        Err(crate::KrillnotesError::IdentityAlreadyExists(_))
+    ));
+}
+
+#[test]
+fn rotate_identity_key_rekeys_bindings_and_stores() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mut mgr = IdentityManager::new(tmp.path().to_path_buf()).unwrap();
+    let file = mgr.create_identity("Alice", "pass").unwrap();
+    let uuid = file.identity_uuid;
+    let old = mgr.unlock_identity(&uuid, "pass").unwrap();
+    let ws_dir = mgr.identity_base_dir(&uuid).unwrap().join("Notes");
+    std::fs::create_dir_all(&ws_dir).unwrap();
+    mgr.bind_workspace(
+        &uuid,
+        "ws-uuid",
+        &ws_dir,
+        "db-pass",
+        &old.signing_key.to_bytes(),
+    )
+    .unwrap();
+    let contacts_dir = mgr.identity_dir(&uuid).join("contacts");
+    let bob_key = BASE64.encode([7u8; 32]);
+    crate::core::contact::ContactManager::for_identity(contacts_dir.clone(), old.contacts_key())
+        .unwrap()
+        .create_contact("Bob", &bob_key, crate::core::contact::TrustLevel::Tofu)
+        .unwrap();
+
+    let (new, record) = mgr.rotate_identity_key(&uuid, "pass").unwrap();
+    assert!(record.verify());
+    assert_eq!(record.old_key, BASE64.encode(old.verifying_key.as_bytes()));
+    assert_eq!(record.new_key, BASE64.encode(new.verifying_key.as_bytes()));
+    assert_eq!(mgr.key_successions(&uuid).unwrap(), vec![record]);
+
+    let unlocked = mgr.unlock_identity(&uuid, "pass").unwrap();
+    assert_eq!(unlocked.verifying_key, new.verifying_key);
+    assert_eq!(
+        mgr.decrypt_db_password(&ws_dir, &new.signing_key.to_bytes())
+            .unwrap(),
+        "db-pass"
+    );
+    let contacts =
+        crate::core::contact::ContactManager::for_identity(contacts_dir, new.contacts_key())
+            .unwrap();
+    assert!(contacts.find_by_public_key(&bob_key).unwrap().is_some());
+}
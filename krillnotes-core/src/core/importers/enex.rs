@@ -0,0 +1,759 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Evernote `.enex` importer.
+//!
+//! Each `<note>` becomes a `TextNote` whose `body` is the ENML content converted
+//! to markdown. `<tag>`s become tags and `<resource>`s become attachments; inline
+//! `<en-media>` references are rewritten to `{{image: attach:...}}` blocks.
+//! Notes are grafted one at a time, so memory use is bounded by the largest note.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::BufRead;
+
+use base64::Engine as _;
+use md5::{Digest, Md5};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use super::{
+    attach, attachment_markdown, graft_into, mime_from_filename, new_note, unique_filename,
+    ForeignImportReport, PendingAttachment,
+};
+use crate::core::export::ExportError;
+use crate::core::note::FieldValue;
+use crate::core::timestamp::UnixSecs;
+use crate::core::workspace::Workspace;
+
+/// A `<resource>` inside the note currently being parsed.
+#[derive(Default)]
+struct EnexResource {
+    data: String,
+    mime: String,
+    file_name: String,
+}
+
+/// Fields collected for the `<note>` currently being parsed.
+#[derive(Default)]
+struct EnexNote {
+    title: String,
+    content: String,
+    created: String,
+    updated: String,
+    tags: Vec<String>,
+    resources: Vec<EnexResource>,
+}
+
+/// A resource that `<en-media hash="...">` can refer to.
+pub(crate) struct MediaRef {
+    pub filename: String,
+    pub mime_type: Option<String>,
+}
+
+/// Imports every note in an Evernote export under `parent_id`.
+///
+/// # Errors
+///
+/// Returns [`ExportError::InvalidFormat`] if the input is not well-formed ENEX, and
+/// [`ExportError::Database`] if a note cannot be grafted. Notes grafted before the
+/// failure are kept.
+pub fn import_enex<R: BufRead>(
+    reader: R,
+    workspace: &mut Workspace,
+    parent_id: &str,
+) -> Result<ForeignImportReport, ExportError> {
+    let mut xml = Reader::from_reader(reader);
+    xml.config_mut().expand_empty_elements = true;
+
+    let mut report = ForeignImportReport::default();
+    let mut buf = Vec::new();
+    let mut path: Vec<String> = Vec::new();
+    let mut note: Option<EnexNote> = None;
+    let mut saw_export_root = false;
+
+    loop {
+        let event = xml
+            .read_event_into(&mut buf)
+            .map_err(|e| ExportError::InvalidFormat(format!("Invalid ENEX: {e}")))?;
+        match event {
+            Event::Start(e) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                match name.as_str() {
+                    "en-export" => saw_export_root = true,
+                    "note" => note = Some(EnexNote::default()),
+                    "resource" => {
+                        if let Some(n) = note.as_mut() {
+                            n.resources.push(EnexResource::default());
+                        }
+                    }
+                    _ => {}
+                }
+                path.push(name);
+            }
+            Event::End(_) => {
+                let closed = path.pop();
+                if closed.as_deref() == Some("note") {
+                    if let Some(n) = note.take() {
+                        import_note(n, workspace, parent_id, &mut report)?;
+                    }
+                }
+            }
+            Event::Text(t) => {
+                let text = t
+                    .unescape()
+                    .map(|s| s.into_owned())
+                    .unwrap_or_else(|_| String::from_utf8_lossy(&t).into_owned());
+                append_text(&path, note.as_mut(), &text);
+            }
+            Event::CData(c) => {
+                let text = String::from_utf8_lossy(&c).into_owned();
+                append_text(&path, note.as_mut(), &text);
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    if !saw_export_root {
+        return Err(ExportError::InvalidFormat(
+            "Not an Evernote export: missing <en-export> element".to_string(),
+        ));
+    }
+    Ok(report)
+}
+
+/// Routes character data to the field of `note` selected by the element path.
+fn append_text(path: &[String], note: Option<&mut EnexNote>, text: &str) {
+    let Some(note) = note else { return };
+    let n = path.len();
+    let (parent, leaf) = match n {
+        0 => return,
+        1 => ("", path[0].as_str()),
+        _ => (path[n - 2].as_str(), path[n - 1].as_str()),
+    };
+    match (parent, leaf) {
+        ("note", "title") => note.title.push_str(text),
+        ("note", "content") => note.content.push_str(text),
+        ("note", "created") => note.created.push_str(text),
+        ("note", "updated") => note.updated.push_str(text),
+        ("note", "tag") => note.tags.push(text.trim().to_string()),
+        ("resource", "data") => {
+            if let Some(r) = note.resources.last_mut() {
+                r.data.push_str(text);
+            }
+        }
+        ("resource", "mime") => {
+            if let Some(r) = note.resources.last_mut() {
+                r.mime.push_str(text.trim());
+            }
+        }
+        ("resource-attributes", "file-name") => {
+            if let Some(r) = note.resources.last_mut() {
+                r.file_name.push_str(text.trim());
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Parses an ENEX timestamp such as `20240131T094500Z`.
+fn parse_enex_time(s: &str) -> Option<UnixSecs> {
+    chrono::NaiveDateTime::parse_from_str(s.trim(), "%Y%m%dT%H%M%SZ")
+        .ok()
+        .map(|dt| UnixSecs::from_secs(dt.and_utc().timestamp()))
+}
+
+/// Picks a file extension for a resource that has no `<file-name>`.
+fn extension_for_mime(mime: &str) -> &'static str {
+    match mime {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "application/pdf" => "pdf",
+        "text/plain" => "txt",
+        _ => "bin",
+    }
+}
+
+/// Converts one parsed `<note>` and grafts it with its resources.
+fn import_note(
+    note: EnexNote,
+    workspace: &mut Workspace,
+    parent_id: &str,
+    report: &mut ForeignImportReport,
+) -> Result<(), ExportError> {
+    let title = match note.title.trim() {
+        "" => "Untitled".to_string(),
+        t => t.to_string(),
+    };
+
+    // Decode resources up front: en-media elements refer to them by MD5.
+    let mut used_names = HashSet::new();
+    let mut media: HashMap<String, MediaRef> = HashMap::new();
+    let mut decoded: Vec<(Vec<u8>, String, Option<String>)> = Vec::new();
+    for (i, res) in note.resources.into_iter().enumerate() {
+        let cleaned: String = res.data.chars().filter(|c| !c.is_whitespace()).collect();
+        let bytes = match base64::engine::general_purpose::STANDARD.decode(cleaned) {
+            Ok(b) => b,
+            Err(e) => {
+                report.issue(
+                    &title,
+                    format!("resource {} could not be decoded: {e}", i + 1),
+                );
+                continue;
+            }
+        };
+        let mime_type = if res.mime.is_empty() {
+            mime_from_filename(&res.file_name)
+        } else {
+            Some(res.mime)
+        };
+        let base_name = if res.file_name.is_empty() {
+            let ext = extension_for_mime(mime_type.as_deref().unwrap_or_default());
+            format!("attachment-{}.{ext}", i + 1)
+        } else {
+            res.file_name
+        };
+        let filename = unique_filename(&base_name, &mut used_names);
+        let hash = format!("{:x}", Md5::digest(&bytes));
+        media.insert(
+            hash,
+            MediaRef {
+                filename: filename.clone(),
+                mime_type: mime_type.clone(),
+            },
+        );
+        decoded.push((bytes, filename, mime_type));
+    }
+
+    let mut lossy = Vec::new();
+    let body = enml_to_markdown_with_media(&note.content, &media, &mut lossy);
+    for message in lossy {
+        report.issue(&title, message);
+    }
+
+    let mut fields = BTreeMap::new();
+    fields.insert("body".to_string(), FieldValue::Text(body));
+    let mut n = new_note(&title, "TextNote", None, 0, fields);
+    n.tags = note.tags.into_iter().filter(|t| !t.is_empty()).collect();
+    if let Some(created) = parse_enex_time(&note.created) {
+        n.created_at = created;
+    }
+    if let Some(updated) = parse_enex_time(&note.updated) {
+        n.modified_at = updated;
+    }
+    let note_id = n.id.clone();
+    graft_into(workspace, parent_id, &[n], report)?;
+
+    for (bytes, filename, mime_type) in decoded {
+        let pending = PendingAttachment {
+            note_id: note_id.clone(),
+            filename,
+            mime_type,
+            source: (),
+        };
+        attach(workspace, &pending, &mut &bytes[..], &title, report);
+    }
+    Ok(())
+}
+
+/// Converts ENML (Evernote's XHTML dialect) to markdown.
+///
+/// `<en-media>` elements are dropped; use [`import_enex`] to keep resources.
+pub fn enml_to_markdown(enml: &str) -> String {
+    enml_to_markdown_with_media(enml, &HashMap::new(), &mut Vec::new())
+}
+
+/// Converts ENML to markdown, resolving `<en-media>` against `media` and
+/// appending a description of anything converted lossily to `lossy`.
+pub(crate) fn enml_to_markdown_with_media(
+    enml: &str,
+    media: &HashMap<String, MediaRef>,
+    lossy: &mut Vec<String>,
+) -> String {
+    let mut reader = Reader::from_str(enml);
+    let config = reader.config_mut();
+    config.expand_empty_elements = true;
+    config.check_end_names = false;
+    config.allow_unmatched_ends = true;
+
+    let mut w = MarkdownWriter::default();
+    let mut unsupported: BTreeSet<String> = BTreeSet::new();
+    let mut missing_media = 0usize;
+    let mut encrypted = 0usize;
+    // Depth of an element whose content is being skipped (e.g. `<en-crypt>`).
+    let mut skip_depth = 0usize;
+
+    loop {
+        let event = match reader.read_event() {
+            Ok(Event::Eof) => break,
+            Ok(event) => event,
+            Err(e) => {
+                lossy.push(format!("note content is malformed and was truncated: {e}"));
+                break;
+            }
+        };
+        match event {
+            Event::Start(e) => {
+                if skip_depth > 0 {
+                    skip_depth += 1;
+                    continue;
+                }
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).to_ascii_lowercase();
+                match name.as_str() {
+                    "en-crypt" => {
+                        encrypted += 1;
+                        w.text("*[encrypted content not imported]*");
+                        skip_depth = 1;
+                    }
+                    "en-media" => {
+                        let hash = attr(&e, b"hash").unwrap_or_default().to_ascii_lowercase();
+                        match media.get(&hash) {
+                            Some(m) => {
+                                w.raw(&attachment_markdown(&m.filename, m.mime_type.as_deref()))
+                            }
+                            None => missing_media += 1,
+                        }
+                        w.open(Tag::Ignored);
+                    }
+                    "img" => {
+                        match attr(&e, b"src") {
+                            Some(src) if src.starts_with("http") => {
+                                let alt = attr(&e, b"alt").unwrap_or_default();
+                                w.raw(&format!("![{alt}]({src})"));
+                            }
+                            _ => {
+                                unsupported.insert("img".to_string());
+                            }
+                        }
+                        w.open(Tag::Ignored);
+                    }
+                    "en-todo" => {
+                        let checked = attr(&e, b"checked").is_some_and(|v| v == "true");
+                        w.todo(checked);
+                        w.open(Tag::Ignored);
+                    }
+                    "div"
+                        if attr(&e, b"style")
+                            .is_some_and(|s| s.contains("--en-codeblock:true")) =>
+                    {
+                        w.open(Tag::Pre)
+                    }
+                    "div" | "p" | "blockquote" | "section" | "article" | "header" | "footer"
+                    | "dl" | "dt" | "dd" | "address" | "center" => w.open(Tag::Block),
+                    "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                        let level = name.as_bytes()[1] - b'0';
+                        w.open(Tag::Heading(level));
+                    }
+                    "br" => {
+                        w.line_break();
+                        w.open(Tag::Ignored);
+                    }
+                    "hr" => {
+                        w.rule();
+                        w.open(Tag::Ignored);
+                    }
+                    "b" | "strong" => w.open(Tag::Wrap("**")),
+                    "i" | "em" => w.open(Tag::Wrap("*")),
+                    "s" | "strike" | "del" => w.open(Tag::Wrap("~~")),
+                    "code" => w.open(Tag::Wrap("`")),
+                    "pre" => w.open(Tag::Pre),
+                    "a" => w.open(Tag::Link(attr(&e, b"href"))),
+                    "ul" => w.open(Tag::List { ordered: false }),
+                    "ol" => w.open(Tag::List { ordered: true }),
+                    "li" => w.open(Tag::Item),
+                    "table" => {
+                        if w.in_table() {
+                            lossy.push("nested table flattened into its cell".to_string());
+                        }
+                        w.open(Tag::Table)
+                    }
+                    "tr" => w.open(Tag::Row),
+                    "td" | "th" => w.open(Tag::Cell),
+                    "en-note" | "span" | "font" | "u" | "sup" | "sub" | "small" | "big"
+                    | "abbr" | "cite" | "dfn" | "kbd" | "q" | "samp" | "var" | "tt" | "ins"
+                    | "thead" | "tbody" | "tfoot" | "colgroup" | "col" | "caption" | "bdo" => {
+                        w.open(Tag::Transparent)
+                    }
+                    other => {
+                        unsupported.insert(other.to_string());
+                        w.open(Tag::Transparent);
+                    }
+                }
+            }
+            Event::End(_) => {
+                if skip_depth > 0 {
+                    skip_depth -= 1;
+                    continue;
+                }
+                w.close();
+            }
+            Event::Text(t) if skip_depth == 0 => {
+                let raw = String::from_utf8_lossy(&t);
+                let text = quick_xml::escape::unescape_with(&raw, resolve_html_entity)
+                    .map(|s| s.into_owned())
+                    .unwrap_or_else(|_| raw.clone().into_owned());
+                w.text(&text);
+            }
+            Event::CData(c) if skip_depth == 0 => {
+                w.text(&String::from_utf8_lossy(&c));
+            }
+            _ => {}
+        }
+    }
+
+    if encrypted > 0 {
+        lossy.push(format!(
+            "{encrypted} encrypted section(s) could not be decrypted and were skipped"
+        ));
+    }
+    if missing_media > 0 {
+        lossy.push(format!(
+            "{missing_media} embedded resource(s) missing from the export"
+        ));
+    }
+    if !unsupported.is_empty() {
+        let names: Vec<String> = unsupported.into_iter().map(|n| format!("<{n}>")).collect();
+        lossy.push(format!(
+            "unsupported elements imported as plain text: {}",
+            names.join(", ")
+        ));
+    }
+    w.finish()
+}
+
+/// Reads an attribute value by name, unescaping it where possible.
+fn attr(e: &BytesStart, name: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key.as_ref() == name)
+        .map(|a| {
+            a.unescape_value()
+                .map(|v| v.into_owned())
+                .unwrap_or_else(|_| String::from_utf8_lossy(&a.value).into_owned())
+        })
+}
+
+/// Resolves the XML predefined entities plus the HTML entities common in ENML.
+fn resolve_html_entity(entity: &str) -> Option<&'static str> {
+    quick_xml::escape::resolve_predefined_entity(entity).or(match entity {
+        "nbsp" => Some(" "),
+        "ndash" => Some("–"),
+        "mdash" => Some("—"),
+        "hellip" => Some("…"),
+        "lsquo" => Some("‘"),
+        "rsquo" => Some("’"),
+        "ldquo" => Some("“"),
+        "rdquo" => Some("”"),
+        "laquo" => Some("«"),
+        "raquo" => Some("»"),
+        "bull" => Some("•"),
+        "middot" => Some("·"),
+        "copy" => Some("©"),
+        "reg" => Some("®"),
+        "trade" => Some("™"),
+        "deg" => Some("°"),
+        "times" => Some("×"),
+        "euro" => Some("€"),
+        _ => None,
+    })
+}
+
+/// An open ENML element, as far as markdown output is concerned.
+enum Tag {
+    Transparent,
+    Ignored,
+    Block,
+    Heading(u8),
+    Wrap(&'static str),
+    Pre,
+    Link(Option<String>),
+    List { ordered: bool },
+    Item,
+    Table,
+    Row,
+    Cell,
+}
+
+/// An open `<ul>`/`<ol>` and the number of items emitted so far.
+struct ListState {
+    ordered: bool,
+    count: usize,
+}
+
+/// Rows collected for an open `<table>`.
+#[derive(Default)]
+struct TableState {
+    rows: Vec<Vec<String>>,
+    /// Output stashed while a cell is being written.
+    stash: Option<String>,
+}
+
+/// Incremental markdown builder driven by ENML start/end/text events.
+#[derive(Default)]
+struct MarkdownWriter {
+    out: String,
+    stack: Vec<Tag>,
+    lists: Vec<ListState>,
+    tables: Vec<TableState>,
+    link_starts: Vec<usize>,
+    pre_depth: usize,
+    /// A `<br>` was seen; emit a hard break before the next text.
+    pending_break: bool,
+}
+
+impl MarkdownWriter {
+    fn in_table(&self) -> bool {
+        !self.tables.is_empty()
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.out.is_empty() || self.out.ends_with('\n')
+    }
+
+    fn trim_trailing_spaces(&mut self) {
+        let trimmed = self.out.trim_end_matches([' ', '\t']).len();
+        self.out.truncate(trimmed);
+    }
+
+    fn ensure_newline(&mut self) {
+        self.pending_break = false;
+        self.trim_trailing_spaces();
+        if !self.at_line_start() {
+            self.out.push('\n');
+        }
+    }
+
+    fn ensure_blank_line(&mut self) {
+        self.ensure_newline();
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    /// Starts a block element. Inside list items and table cells blocks run
+    /// together, since markdown cannot nest paragraphs there without indentation.
+    fn block_break(&mut self) {
+        if self.pre_depth > 0 {
+            self.ensure_newline();
+        } else if self.lists.is_empty() && self.tables.last().is_none_or(|t| t.stash.is_none()) {
+            self.ensure_blank_line();
+        } else if !self.at_line_start() && !self.out.ends_with(' ') {
+            self.out.push(' ');
+        }
+    }
+
+    fn open(&mut self, tag: Tag) {
+        match &tag {
+            Tag::Block => self.block_break(),
+            Tag::Heading(level) => {
+                self.ensure_blank_line();
+                self.out.push_str(&"#".repeat(*level as usize));
+                self.out.push(' ');
+            }
+            Tag::Wrap(marker) if self.pre_depth == 0 => self.raw(marker),
+            Tag::Pre => {
+                self.ensure_blank_line();
+                self.out.push_str("```\n");
+                self.pre_depth += 1;
+            }
+            Tag::Link(_) => {
+                self.raw("[");
+                self.link_starts.push(self.out.len());
+            }
+            Tag::List { ordered } => {
+                if self.lists.is_empty() {
+                    self.ensure_blank_line();
+                } else {
+                    self.ensure_newline();
+                }
+                self.lists.push(ListState {
+                    ordered: *ordered,
+                    count: 0,
+                });
+            }
+            Tag::Item => {
+                self.ensure_newline();
+                let indent: usize = self.lists[..self.lists.len().saturating_sub(1)]
+                    .iter()
+                    .map(|l| if l.ordered { 3 } else { 2 })
+                    .sum();
+                self.out.push_str(&" ".repeat(indent));
+                if let Some(list) = self.lists.last_mut() {
+                    list.count += 1;
+                    if list.ordered {
+                        let n = list.count;
+                        self.out.push_str(&format!("{n}. "));
+                    } else {
+                        self.out.push_str("- ");
+                    }
+                } else {
+                    self.out.push_str("- ");
+                }
+            }
+            Tag::Table => {
+                self.ensure_blank_line();
+                self.tables.push(TableState::default());
+            }
+            Tag::Row => {
+                if let Some(t) = self.tables.last_mut() {
+                    t.rows.push(Vec::new());
+                }
+            }
+            Tag::Cell => {
+                let stash = std::mem::take(&mut self.out);
+                if let Some(t) = self.tables.last_mut() {
+                    t.stash = Some(stash);
+                }
+            }
+            _ => {}
+        }
+        self.stack.push(tag);
+    }
+
+    fn close(&mut self) {
+        let Some(tag) = self.stack.pop() else { return };
+        match tag {
+            Tag::Block => self.block_break(),
+            Tag::Heading(_) => self.ensure_blank_line(),
+            Tag::Wrap(marker) if self.pre_depth == 0 => self.raw(marker),
+            Tag::Pre => {
+                self.pre_depth = self.pre_depth.saturating_sub(1);
+                self.ensure_newline();
+                self.out.push_str("```");
+                self.ensure_blank_line();
+            }
+            Tag::Link(href) => {
+                let start = self.link_starts.pop().unwrap_or(self.out.len());
+                match href {
+                    Some(href) => {
+                        if self.out.len() == start {
+                            self.out.push_str(&href);
+                        }
+                        self.out.push_str(&format!("]({href})"));
+                    }
+                    None => self.out.push(']'),
+                }
+            }
+            Tag::List { .. } => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.ensure_blank_line();
+                } else {
+                    self.ensure_newline();
+                }
+            }
+            Tag::Item => self.ensure_newline(),
+            Tag::Table => {
+                if let Some(table) = self.tables.pop() {
+                    self.render_table(table.rows);
+                }
+                self.ensure_blank_line();
+            }
+            Tag::Cell => {
+                let cell = std::mem::take(&mut self.out);
+                let cell = cell
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+                    .replace('|', "\\|");
+                if let Some(t) = self.tables.last_mut() {
+                    self.out = t.stash.take().unwrap_or_default();
+                    if let Some(row) = t.rows.last_mut() {
+                        row.push(cell);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn render_table(&mut self, rows: Vec<Vec<String>>) {
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if width == 0 {
+            return;
+        }
+        for (i, mut row) in rows.into_iter().enumerate() {
+            row.resize(width, String::new());
+            self.out.push_str(&format!("| {} |\n", row.join(" | ")));
+            if i == 0 {
+                self.out.push_str(&format!("|{}\n", " --- |".repeat(width)));
+            }
+        }
+    }
+
+    /// Appends markup verbatim.
+    fn raw(&mut self, s: &str) {
+        self.flush_break();
+        self.out.push_str(s);
+    }
+
+    fn flush_break(&mut self) {
+        if self.pending_break {
+            self.pending_break = false;
+            if !self.at_line_start() {
+                self.trim_trailing_spaces();
+                self.out.push_str("\\\n");
+            }
+        }
+    }
+
+    /// Appends character data, collapsing whitespace outside code blocks.
+    fn text(&mut self, text: &str) {
+        if self.pre_depth > 0 {
+            self.out.push_str(text);
+            return;
+        }
+        let starts_ws = text.starts_with(char::is_whitespace);
+        let ends_ws = text.ends_with(char::is_whitespace);
+        let words: Vec<&str> = text.split_whitespace().collect();
+        if words.is_empty() {
+            if starts_ws && !self.at_line_start() && !self.out.ends_with(' ') {
+                self.out.push(' ');
+            }
+            return;
+        }
+        self.flush_break();
+        if starts_ws && !self.at_line_start() && !self.out.ends_with(' ') {
+            self.out.push(' ');
+        }
+        self.out.push_str(&words.join(" "));
+        if ends_ws {
+            self.out.push(' ');
+        }
+    }
+
+    fn line_break(&mut self) {
+        if self.pre_depth > 0 {
+            self.out.push('\n');
+        } else if self.in_table() {
+            self.out.push(' ');
+        } else {
+            self.pending_break = true;
+        }
+    }
+
+    fn rule(&mut self) {
+        self.ensure_blank_line();
+        self.out.push_str("---");
+        self.ensure_blank_line();
+    }
+
+    fn todo(&mut self, checked: bool) {
+        if self.at_line_start() && self.lists.is_empty() {
+            self.out.push_str("- ");
+        }
+        self.raw(if checked { "[x] " } else { "[ ] " });
+    }
+
+    fn finish(mut self) -> String {
+        self.ensure_newline();
+        self.out.trim().to_string()
+    }
+}
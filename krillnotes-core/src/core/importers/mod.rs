@@ -0,0 +1,262 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Importers for notes exported from other applications.
+//!
+//! Each importer converts its source format into ordinary notes, attachments and
+//! (where needed) generated schema scripts, then grafts them under a parent note
+//! with [`Workspace::graft_notes`] so every change is a signed operation.
+//! [`import_foreign`] imports into an open workspace; [`import_foreign_workspace`]
+//! creates a new one and imports under its root note.
+//!
+//! Anything that could not be converted faithfully is listed in the returned
+//! [`ForeignImportReport`] rather than failing the whole import.
+
+mod enex;
+mod notion;
+
+pub use enex::{enml_to_markdown, import_enex};
+pub use notion::import_notion_export;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{BufReader, Read, Seek};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::export::ExportError;
+use crate::core::note::{FieldValue, Note};
+use crate::core::permission::PermissionGate;
+use crate::core::timestamp::UnixSecs;
+use crate::core::workspace::{GraftIdStrategy, Workspace};
+
+/// Source formats understood by [`import_foreign`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ForeignFormat {
+    /// Evernote `.enex` export (XML).
+    Enex,
+    /// Notion "Markdown & CSV" export (`.zip`).
+    Notion,
+}
+
+/// Something in the source that was skipped or only partially converted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportIssue {
+    /// Where the problem was found: a note title or a path inside the export.
+    pub location: String,
+    /// Human-readable description of what was not converted.
+    pub message: String,
+}
+
+/// Result of a foreign import.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForeignImportReport {
+    /// IDs of the notes created directly under the chosen parent.
+    pub root_note_ids: Vec<String>,
+    /// Total number of notes created.
+    pub notes_created: usize,
+    /// Number of attachments stored.
+    pub attachments_imported: usize,
+    /// Schema names registered by generated scripts (Notion databases).
+    pub schemas_created: Vec<String>,
+    /// Content that was skipped or converted lossily.
+    pub issues: Vec<ImportIssue>,
+}
+
+impl ForeignImportReport {
+    pub(crate) fn issue(&mut self, location: impl Into<String>, message: impl Into<String>) {
+        self.issues.push(ImportIssue {
+            location: location.into(),
+            message: message.into(),
+        });
+    }
+}
+
+/// Imports `reader` in the given `format` under `parent_id` in `workspace`.
+///
+/// # Errors
+///
+/// Returns [`ExportError::InvalidFormat`] if the source cannot be parsed at all,
+/// and [`ExportError::Database`] if grafting notes or storing attachments fails.
+pub fn import_foreign<R: Read + Seek>(
+    format: ForeignFormat,
+    reader: R,
+    workspace: &mut Workspace,
+    parent_id: &str,
+) -> Result<ForeignImportReport, ExportError> {
+    match format {
+        ForeignFormat::Enex => import_enex(BufReader::new(reader), workspace, parent_id),
+        ForeignFormat::Notion => import_notion_export(reader, workspace, parent_id),
+    }
+}
+
+/// Creates a new workspace at `db_path` (see [`Workspace::create`]) and imports
+/// `reader` in the given `format` under its root note.
+///
+/// # Errors
+///
+/// As [`import_foreign`], plus [`ExportError::Database`] if the workspace cannot
+/// be created.
+#[allow(clippy::too_many_arguments)]
+pub fn import_foreign_workspace<R: Read + Seek>(
+    format: ForeignFormat,
+    reader: R,
+    db_path: &Path,
+    workspace_password: &str,
+    identity_uuid: &str,
+    signing_key: ed25519_dalek::SigningKey,
+    permission_gate: Box<dyn PermissionGate>,
+    identity_dir: Option<&Path>,
+) -> Result<(Workspace, ForeignImportReport), ExportError> {
+    let mut workspace = Workspace::create(
+        db_path,
+        workspace_password,
+        identity_uuid,
+        signing_key,
+        permission_gate,
+        identity_dir,
+    )
+    .map_err(|e| ExportError::Database(e.to_string()))?;
+    let root_id = workspace
+        .list_all_notes()
+        .map_err(|e| ExportError::Database(e.to_string()))?
+        .into_iter()
+        .find(|n| n.parent_id.is_none())
+        .map(|n| n.id)
+        .ok_or_else(|| ExportError::Database("new workspace has no root note".to_string()))?;
+    let report = import_foreign(format, reader, &mut workspace, &root_id)?;
+    Ok((workspace, report))
+}
+
+/// A file to attach once its note has been grafted.
+pub(crate) struct PendingAttachment<S> {
+    pub note_id: String,
+    pub filename: String,
+    pub mime_type: Option<String>,
+    /// Importer-specific handle to the bytes (inline data or a path in an archive).
+    pub source: S,
+}
+
+/// Builds a note with fresh ID and timestamps, ready for grafting.
+pub(crate) fn new_note(
+    title: &str,
+    schema: &str,
+    parent_id: Option<&str>,
+    position: usize,
+    fields: BTreeMap<String, FieldValue>,
+) -> Note {
+    let now = UnixSecs::now();
+    Note {
+        id: uuid::Uuid::new_v4().to_string(),
+        title: title.to_string(),
+        schema: schema.to_string(),
+        parent_id: parent_id.map(str::to_string),
+        position: position as f64,
+        created_at: now,
+        modified_at: now,
+        created_by: String::new(),
+        modified_by: String::new(),
+        fields,
+        is_expanded: false,
+        tags: Vec::new(),
+        schema_version: 1,
+        is_checked: false,
+    }
+}
+
+/// Grafts freshly built notes (whose IDs are new UUIDs) and records the outcome
+/// in `report`.
+pub(crate) fn graft_into(
+    workspace: &mut Workspace,
+    parent_id: &str,
+    notes: &[Note],
+    report: &mut ForeignImportReport,
+) -> Result<(), ExportError> {
+    if notes.is_empty() {
+        return Ok(());
+    }
+    let outcome = workspace
+        .graft_notes(notes, parent_id, GraftIdStrategy::Preserve, &HashMap::new())
+        .map_err(|e| ExportError::Database(e.to_string()))?;
+    report.root_note_ids.extend(outcome.root_ids);
+    report.notes_created += outcome.created;
+    Ok(())
+}
+
+/// Stores an attachment read from `reader`, recording failures as issues.
+pub(crate) fn attach<S>(
+    workspace: &mut Workspace,
+    pending: &PendingAttachment<S>,
+    reader: &mut dyn Read,
+    location: &str,
+    report: &mut ForeignImportReport,
+) {
+    let id = uuid::Uuid::new_v4().to_string();
+    match workspace.attach_file_signed_with_id(
+        &id,
+        &pending.note_id,
+        &pending.filename,
+        pending.mime_type.as_deref(),
+        reader,
+    ) {
+        Ok(_) => report.attachments_imported += 1,
+        Err(e) => report.issue(
+            location,
+            format!("attachment '{}' was not imported: {e}", pending.filename),
+        ),
+    }
+}
+
+/// Returns `name`, or `name (2)`, `name (3)`, ... if it is already in `used`.
+/// Attachment references in markdown resolve by filename, so names must be
+/// unique within a note.
+pub(crate) fn unique_filename(name: &str, used: &mut HashSet<String>) -> String {
+    let mut candidate = name.to_string();
+    let mut counter = 1;
+    while used.contains(&candidate) {
+        counter += 1;
+        candidate = match name.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => format!("{stem} ({counter}).{ext}"),
+            _ => format!("{name} ({counter})"),
+        };
+    }
+    used.insert(candidate.clone());
+    candidate
+}
+
+/// Guesses a MIME type from a filename extension for the formats the viewer can
+/// display inline. Returns `None` for anything else.
+pub(crate) fn mime_from_filename(filename: &str) -> Option<String> {
+    let ext = filename.rsplit_once('.')?.1.to_ascii_lowercase();
+    let mime = match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "pdf" => "application/pdf",
+        "txt" => "text/plain",
+        _ => return None,
+    };
+    Some(mime.to_string())
+}
+
+/// Markdown that references an attachment of the same note: an inline image
+/// block for images, a paperclip label for anything else.
+pub(crate) fn attachment_markdown(filename: &str, mime_type: Option<&str>) -> String {
+    if mime_type.is_some_and(|m| m.starts_with("image/")) {
+        format!("{{{{image: attach:{filename}}}}}")
+    } else {
+        format!("📎 {filename}")
+    }
+}
+
+#[cfg(test)]
+#[path = "tests.rs"]
+mod tests;
@@ -0,0 +1,671 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Notion "Markdown & CSV" export importer.
+//!
+//! Notion names every exported item `<Title> <32-hex id>`; a page's sub-pages live
+//! in a folder with the same name. Pages become `TextNote`s with their markdown as
+//! the body. Each database CSV gets a generated schema script with one field per
+//! column (types inferred from the values) plus a `body` field, and its rows become
+//! notes of that schema; a row's own page, if exported, supplies the body.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{Read, Seek};
+use std::sync::OnceLock;
+
+use zip::ZipArchive;
+
+use super::{
+    attach, attachment_markdown, graft_into, mime_from_filename, new_note, unique_filename,
+    ForeignImportReport, PendingAttachment,
+};
+use crate::core::export::ExportError;
+use crate::core::note::{FieldValue, Note};
+use crate::core::workspace::Workspace;
+
+/// What an exported path turns into.
+enum NodeSource {
+    /// A `.md` page.
+    Page(String),
+    /// A database `.csv`.
+    Database(String),
+    /// A folder with no page of its own.
+    Folder,
+}
+
+/// Column type inferred from a database CSV.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnType {
+    Text,
+    Number,
+    Boolean,
+    Date,
+    Email,
+}
+
+impl ColumnType {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Number => "number",
+            Self::Boolean => "boolean",
+            Self::Date => "date",
+            Self::Email => "email",
+        }
+    }
+}
+
+/// A database column mapped to a schema field.
+struct Column {
+    field: String,
+    kind: ColumnType,
+}
+
+/// Imports a Notion "Markdown & CSV" export (`.zip`) under `parent_id`.
+///
+/// # Errors
+///
+/// Returns [`ExportError::Zip`] if the input is not a zip archive,
+/// [`ExportError::InvalidFormat`] if it contains no pages or databases, and
+/// [`ExportError::Database`] if the notes cannot be grafted.
+pub fn import_notion_export<R: Read + Seek>(
+    reader: R,
+    workspace: &mut Workspace,
+    parent_id: &str,
+) -> Result<ForeignImportReport, ExportError> {
+    let mut archive = ZipArchive::new(reader)?;
+    let mut report = ForeignImportReport::default();
+
+    let files: HashSet<String> = archive
+        .file_names()
+        .filter(|n| !n.ends_with('/'))
+        .map(str::to_string)
+        .collect();
+
+    // Map each item key (path without extension) to its source. Notion may export a
+    // database both as `X.csv` and `X_all.csv`; the `_all` variant has every row.
+    let mut sources: BTreeMap<String, NodeSource> = BTreeMap::new();
+    for name in &files {
+        if let Some(key) = name.strip_suffix(".md") {
+            sources.insert(key.to_string(), NodeSource::Page(name.clone()));
+        } else if let Some(stem) = name.strip_suffix(".csv") {
+            match stem.strip_suffix("_all") {
+                Some(key) => {
+                    sources.insert(key.to_string(), NodeSource::Database(name.clone()));
+                }
+                None if !files.contains(&format!("{stem}_all.csv")) => {
+                    sources.insert(stem.to_string(), NodeSource::Database(name.clone()));
+                }
+                None => {}
+            }
+        } else if name.ends_with(".zip") {
+            report.issue(
+                name.as_str(),
+                "nested archive not imported; extract it and import its contents separately",
+            );
+        }
+    }
+    if sources.is_empty() {
+        return Err(ExportError::InvalidFormat(
+            "No Notion pages or databases found in archive".to_string(),
+        ));
+    }
+
+    // Folders that hold sub-pages but have no page of their own.
+    let mut folders = Vec::new();
+    for key in sources.keys() {
+        let mut dir = parent_dir(key);
+        while let Some(d) = dir {
+            if !sources.contains_key(d) {
+                folders.push(d.to_string());
+            }
+            dir = parent_dir(d);
+        }
+    }
+    for folder in folders {
+        sources.entry(folder).or_insert(NodeSource::Folder);
+    }
+
+    // Parents before children; siblings alphabetically (Notion exports carry no order).
+    let mut keys: Vec<&String> = sources.keys().collect();
+    keys.sort_by_key(|k| (k.matches('/').count(), k.to_lowercase()));
+
+    let mut note_ids: HashMap<String, String> = HashMap::new();
+    let mut notes: Vec<Note> = Vec::new();
+    let mut positions: HashMap<Option<String>, usize> = HashMap::new();
+    let mut pending: Vec<PendingAttachment<String>> = Vec::new();
+    // Row pages already consumed by a database row: key → (row note index, headers).
+    let mut claimed_rows: HashMap<String, (usize, HashSet<String>)> = HashMap::new();
+
+    for key in keys {
+        let parent = parent_dir(key).and_then(|d| note_ids.get(d)).cloned();
+        let location = key.as_str();
+
+        if let Some((index, headers)) = claimed_rows.remove(key.as_str()) {
+            let NodeSource::Page(path) = &sources[key] else {
+                continue;
+            };
+            let markdown = read_text(&mut archive, path)?;
+            let (_, body) = split_title(&markdown);
+            let body = strip_properties(body, &headers);
+            let mut used = HashSet::new();
+            let note_id = notes[index].id.clone();
+            let body = rewrite_links(
+                &body,
+                path,
+                &note_id,
+                &files,
+                &mut used,
+                &mut pending,
+                location,
+                &mut report,
+            );
+            notes[index]
+                .fields
+                .insert("body".to_string(), FieldValue::Text(body));
+            continue;
+        }
+
+        let position = positions.entry(parent.clone()).or_insert(0);
+        let here = *position;
+        *position += 1;
+
+        let note = match &sources[key] {
+            NodeSource::Folder => {
+                text_note(&clean_name(last_segment(key)), parent.as_deref(), here, "")
+            }
+            NodeSource::Page(path) => {
+                let markdown = read_text(&mut archive, path)?;
+                let (title, body) = split_title(&markdown);
+                let title = title.unwrap_or_else(|| clean_name(last_segment(key)));
+                let mut note = text_note(&title, parent.as_deref(), here, "");
+                let mut used = HashSet::new();
+                let body = rewrite_links(
+                    body,
+                    path,
+                    &note.id,
+                    &files,
+                    &mut used,
+                    &mut pending,
+                    location,
+                    &mut report,
+                );
+                note.fields
+                    .insert("body".to_string(), FieldValue::Text(body));
+                note
+            }
+            NodeSource::Database(path) => {
+                let title = clean_name(last_segment(key));
+                let container = text_note(&title, parent.as_deref(), here, "");
+                let csv_text = read_text(&mut archive, path)?;
+                let rows = import_database(
+                    workspace,
+                    &title,
+                    &csv_text,
+                    &container.id,
+                    key,
+                    &sources,
+                    &mut claimed_rows,
+                    notes.len() + 1,
+                    location,
+                    &mut report,
+                );
+                note_ids.insert(key.clone(), container.id.clone());
+                notes.push(container);
+                for (row_key, row) in rows {
+                    if let Some(row_key) = row_key {
+                        note_ids.insert(row_key, row.id.clone());
+                    }
+                    notes.push(row);
+                }
+                continue;
+            }
+        };
+        note_ids.insert(key.clone(), note.id.clone());
+        notes.push(note);
+    }
+
+    graft_into(workspace, parent_id, &notes, &mut report)?;
+
+    for item in &pending {
+        match archive.by_name(&item.source) {
+            Ok(mut entry) => attach(workspace, item, &mut entry, &item.source, &mut report),
+            Err(e) => report.issue(item.source.as_str(), format!("could not read file: {e}")),
+        }
+    }
+    Ok(report)
+}
+
+/// Converts one database CSV into a generated schema and one note per row.
+///
+/// Returns the row notes (each paired with the key of its row page, if one was
+/// exported). Rows whose page is found register it in `claimed_rows` so the page
+/// body is merged into the row instead of creating a separate note; `first_index`
+/// is the index the first row will have in the importer's note list.
+#[allow(clippy::too_many_arguments)]
+fn import_database(
+    workspace: &mut Workspace,
+    title: &str,
+    csv_text: &str,
+    container_id: &str,
+    key: &str,
+    sources: &BTreeMap<String, NodeSource>,
+    claimed_rows: &mut HashMap<String, (usize, HashSet<String>)>,
+    first_index: usize,
+    location: &str,
+    report: &mut ForeignImportReport,
+) -> Vec<(Option<String>, Note)> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(csv_text.trim_start_matches('\u{feff}').as_bytes());
+    let headers: Vec<String> = match reader.headers() {
+        Ok(h) => h.iter().map(|s| s.trim().to_string()).collect(),
+        Err(e) => {
+            report.issue(location, format!("database could not be read: {e}"));
+            return Vec::new();
+        }
+    };
+    let mut records: Vec<Vec<String>> = Vec::new();
+    for record in reader.records() {
+        match record {
+            Ok(r) => records.push(r.iter().map(str::to_string).collect()),
+            Err(e) => report.issue(location, format!("database row skipped: {e}")),
+        }
+    }
+    if headers.is_empty() {
+        return Vec::new();
+    }
+
+    // The first column is the title property; the rest become fields.
+    let mut used_fields: HashSet<String> = HashSet::from(["body".to_string()]);
+    let columns: Vec<Column> = headers[1..]
+        .iter()
+        .enumerate()
+        .map(|(i, header)| {
+            let values = records
+                .iter()
+                .filter_map(|r| r.get(i + 1))
+                .map(String::as_str);
+            Column {
+                field: unique_field_name(header, &mut used_fields),
+                kind: infer_column_type(values),
+            }
+        })
+        .collect();
+
+    let schema = generate_schema(workspace, title, &columns, location, report);
+
+    // Row pages live in the folder named after the database.
+    let mut row_pages: HashMap<String, Vec<String>> = HashMap::new();
+    let prefix = format!("{key}/");
+    for (page_key, source) in sources {
+        if let (Some(rest), NodeSource::Page(_)) = (page_key.strip_prefix(&prefix), source) {
+            if !rest.contains('/') {
+                row_pages
+                    .entry(clean_name(rest))
+                    .or_default()
+                    .push(page_key.clone());
+            }
+        }
+    }
+    let header_set: HashSet<String> = headers.iter().cloned().collect();
+
+    let mut rows = Vec::with_capacity(records.len());
+    for (position, record) in records.iter().enumerate() {
+        let row_title = match record.first().map(|s| s.trim()) {
+            Some("") | None => "Untitled".to_string(),
+            Some(t) => t.to_string(),
+        };
+        let note = match &schema {
+            Some(schema) => {
+                let mut fields = BTreeMap::new();
+                for (i, column) in columns.iter().enumerate() {
+                    let raw = record.get(i + 1).map(|s| s.trim()).unwrap_or_default();
+                    fields.insert(column.field.clone(), field_value(column.kind, raw));
+                }
+                fields.insert("body".to_string(), FieldValue::Text(String::new()));
+                new_note(&row_title, schema, Some(container_id), position, fields)
+            }
+            None => {
+                // No schema could be created: keep the properties as markdown.
+                let body = headers[1..]
+                    .iter()
+                    .zip(record.iter().skip(1))
+                    .filter(|(_, v)| !v.trim().is_empty())
+                    .map(|(h, v)| format!("**{h}:** {}", v.trim()))
+                    .collect::<Vec<_>>()
+                    .join("\\\n");
+                text_note(&row_title, Some(container_id), position, &body)
+            }
+        };
+        let row_key = row_pages
+            .get_mut(&row_title)
+            .and_then(|keys| (!keys.is_empty()).then(|| keys.remove(0)));
+        if let Some(k) = &row_key {
+            claimed_rows.insert(k.clone(), (first_index + rows.len(), header_set.clone()));
+        }
+        rows.push((row_key, note));
+    }
+    rows
+}
+
+/// Creates the schema script for a database and returns the schema name, or
+/// `None` (with an issue recorded) if it could not be registered.
+fn generate_schema(
+    workspace: &mut Workspace,
+    title: &str,
+    columns: &[Column],
+    location: &str,
+    report: &mut ForeignImportReport,
+) -> Option<String> {
+    let base: String = title
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| {
+            let mut chars = w.chars();
+            let first = chars.next().map(|c| c.to_ascii_uppercase());
+            first.into_iter().chain(chars).collect::<String>()
+        })
+        .collect();
+    let base = format!("Notion{base}");
+    let mut schema = base.clone();
+    let mut counter = 1;
+    while workspace.script_registry().schema_exists(&schema)
+        || report.schemas_created.contains(&schema)
+    {
+        counter += 1;
+        schema = format!("{base}{counter}");
+    }
+
+    let one_line = title.replace(['\n', '\r'], " ");
+    let mut source = format!(
+        "// @name: Notion: {one_line}\n\
+         // @description: Generated by the Notion importer for the \"{one_line}\" database.\n\n\
+         schema(\"{schema}\", #{{\n    version: 1,\n    allow_attachments: true,\n    fields: [\n"
+    );
+    for column in columns {
+        source.push_str(&format!(
+            "        #{{ name: \"{}\", type: \"{}\", required: false }},\n",
+            column.field,
+            column.kind.as_str()
+        ));
+    }
+    source.push_str(
+        "        #{ name: \"body\", type: \"textarea\", required: false },\n    ]\n});\n",
+    );
+
+    match workspace.create_user_script_with_category(&source, "schema") {
+        Ok(_) if workspace.script_registry().schema_exists(&schema) => {
+            report.schemas_created.push(schema.clone());
+            Some(schema)
+        }
+        Ok((_, errors)) => {
+            let detail = errors
+                .first()
+                .map(|e| e.message.clone())
+                .unwrap_or_else(|| "schema was not registered".to_string());
+            report.issue(
+                location,
+                format!("database rows imported as text notes: {detail}"),
+            );
+            None
+        }
+        Err(e) => {
+            report.issue(
+                location,
+                format!("database rows imported as text notes: {e}"),
+            );
+            None
+        }
+    }
+}
+
+fn text_note(title: &str, parent_id: Option<&str>, position: usize, body: &str) -> Note {
+    let mut fields = BTreeMap::new();
+    fields.insert("body".to_string(), FieldValue::Text(body.to_string()));
+    new_note(title, "TextNote", parent_id, position, fields)
+}
+
+fn read_text<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    path: &str,
+) -> Result<String, ExportError> {
+    let mut bytes = Vec::new();
+    archive.by_name(path)?.read_to_end(&mut bytes)?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn parent_dir(key: &str) -> Option<&str> {
+    key.rsplit_once('/').map(|(dir, _)| dir)
+}
+
+fn last_segment(key: &str) -> &str {
+    key.rsplit_once('/').map_or(key, |(_, name)| name)
+}
+
+/// Strips Notion's trailing 32-hex-digit ID from an exported file or folder name.
+fn clean_name(name: &str) -> String {
+    static ID_RE: OnceLock<regex::Regex> = OnceLock::new();
+    let re =
+        ID_RE.get_or_init(|| regex::Regex::new(r"^(.*?)\s+[0-9a-f]{32}$").expect("valid regex"));
+    let cleaned = re
+        .captures(name)
+        .map_or(name, |c| c.get(1).map_or(name, |m| m.as_str()))
+        .trim();
+    if cleaned.is_empty() {
+        "Untitled".to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
+
+/// Splits a leading `# Title` line from a page's markdown.
+fn split_title(markdown: &str) -> (Option<String>, &str) {
+    let trimmed = markdown.trim_start_matches('\u{feff}').trim_start();
+    match trimmed.strip_prefix("# ") {
+        Some(rest) => {
+            let (title, body) = rest.split_once('\n').unwrap_or((rest, ""));
+            (
+                Some(title.trim().to_string()),
+                body.trim_start_matches(['\r', '\n']),
+            )
+        }
+        None => (None, trimmed),
+    }
+}
+
+/// Removes the `Property: value` lines Notion writes at the top of a database row
+/// page; those values are already imported as fields.
+fn strip_properties(body: &str, headers: &HashSet<String>) -> String {
+    let mut lines = body.lines().peekable();
+    while let Some(line) = lines.peek() {
+        let is_property = line
+            .split_once(": ")
+            .is_some_and(|(k, _)| headers.contains(k.trim()));
+        if is_property || line.trim().is_empty() {
+            lines.next();
+        } else {
+            break;
+        }
+    }
+    lines.collect::<Vec<_>>().join("\n")
+}
+
+/// Rewrites relative links in a page body. Links to files in the export become
+/// attachments of the page; links to other pages keep only their text.
+#[allow(clippy::too_many_arguments)]
+fn rewrite_links(
+    body: &str,
+    page_path: &str,
+    note_id: &str,
+    files: &HashSet<String>,
+    used_names: &mut HashSet<String>,
+    pending: &mut Vec<PendingAttachment<String>>,
+    location: &str,
+    report: &mut ForeignImportReport,
+) -> String {
+    static LINK_RE: OnceLock<regex::Regex> = OnceLock::new();
+    let re = LINK_RE
+        .get_or_init(|| regex::Regex::new(r"(!?)\[([^\]]*)\]\(([^)\s]+)\)").expect("valid regex"));
+
+    let base_dir = parent_dir(page_path);
+    let mut page_links = 0usize;
+    let mut missing = Vec::new();
+    let out = re.replace_all(body, |caps: &regex::Captures| {
+        let whole = caps[0].to_string();
+        let text = &caps[2];
+        let target = &caps[3];
+        if target.contains("://") || target.starts_with("mailto:") || target.starts_with('#') {
+            return whole;
+        }
+        let Some(path) = resolve_relative(base_dir, &percent_decode(target)) else {
+            return whole;
+        };
+        if path.ends_with(".md") || path.ends_with(".csv") {
+            page_links += 1;
+            return if text.is_empty() {
+                clean_name(last_segment(
+                    path.trim_end_matches(".md").trim_end_matches(".csv"),
+                ))
+            } else {
+                text.to_string()
+            };
+        }
+        if !files.contains(&path) {
+            missing.push(path);
+            return whole;
+        }
+        let filename = unique_filename(last_segment(&path), used_names);
+        let mime_type = mime_from_filename(&filename);
+        let markdown = attachment_markdown(&filename, mime_type.as_deref());
+        pending.push(PendingAttachment {
+            note_id: note_id.to_string(),
+            filename,
+            mime_type,
+            source: path,
+        });
+        markdown
+    });
+
+    if page_links > 0 {
+        report.issue(
+            location,
+            format!("{page_links} link(s) to other Notion pages kept as plain text"),
+        );
+    }
+    for path in missing {
+        report.issue(
+            location,
+            format!("linked file '{path}' is not in the export"),
+        );
+    }
+    out.trim().to_string()
+}
+
+/// Joins a relative link onto the directory of the page that contains it.
+fn resolve_relative(base_dir: Option<&str>, target: &str) -> Option<String> {
+    let mut parts: Vec<&str> = base_dir.map(|d| d.split('/').collect()).unwrap_or_default();
+    for segment in target.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            s => parts.push(s),
+        }
+    }
+    Some(parts.join("/"))
+}
+
+/// Decodes `%XX` escapes (Notion percent-encodes spaces and punctuation in links).
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(b) = s
+                .get(i + 1..i + 3)
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+            {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Turns a column header into a unique snake_case field name.
+fn unique_field_name(header: &str, used: &mut HashSet<String>) -> String {
+    let mut name = String::new();
+    for c in header.chars() {
+        if c.is_ascii_alphanumeric() {
+            name.push(c.to_ascii_lowercase());
+        } else if !name.ends_with('_') {
+            name.push('_');
+        }
+    }
+    let mut name = name.trim_matches('_').to_string();
+    if name.is_empty() {
+        name = "field".to_string();
+    }
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name = format!("f_{name}");
+    }
+    let base = name.clone();
+    let mut counter = 1;
+    while used.contains(&name) {
+        counter += 1;
+        name = format!("{base}_{counter}");
+    }
+    used.insert(name.clone());
+    name
+}
+
+fn parse_date(s: &str) -> Option<chrono::NaiveDate> {
+    ["%B %d, %Y", "%Y-%m-%d", "%Y/%m/%d"]
+        .iter()
+        .find_map(|fmt| chrono::NaiveDate::parse_from_str(s, fmt).ok())
+}
+
+fn is_email(s: &str) -> bool {
+    !s.contains(char::is_whitespace)
+        && s.split_once('@')
+            .is_some_and(|(user, domain)| !user.is_empty() && domain.contains('.'))
+}
+
+/// Picks the narrowest type that every non-empty value fits.
+fn infer_column_type<'a>(values: impl Iterator<Item = &'a str>) -> ColumnType {
+    let values: Vec<&str> = values.map(str::trim).filter(|v| !v.is_empty()).collect();
+    if values.is_empty() {
+        return ColumnType::Text;
+    }
+    if values.iter().all(|v| *v == "Yes" || *v == "No") {
+        ColumnType::Boolean
+    } else if values.iter().all(|v| v.parse::<f64>().is_ok()) {
+        ColumnType::Number
+    } else if values.iter().all(|v| parse_date(v).is_some()) {
+        ColumnType::Date
+    } else if values.iter().all(|v| is_email(v)) {
+        ColumnType::Email
+    } else {
+        ColumnType::Text
+    }
+}
+
+fn field_value(kind: ColumnType, raw: &str) -> FieldValue {
+    match kind {
+        ColumnType::Text => FieldValue::Text(raw.to_string()),
+        ColumnType::Number => FieldValue::Number(raw.parse().unwrap_or(0.0)),
+        ColumnType::Boolean => FieldValue::Boolean(raw == "Yes"),
+        ColumnType::Date => FieldValue::Date(parse_date(raw)),
+        ColumnType::Email => FieldValue::Email(raw.to_string()),
+    }
+}
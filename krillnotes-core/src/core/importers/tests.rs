@@ -0,0 +1,276 @@
+use super::*;
+use crate::core::operation::Operation;
+use crate::core::permission::{AllowAllGate, PermissionGate};
+use std::io::{Cursor, Write};
+
+fn test_gate() -> Box<dyn PermissionGate> {
+    Box::new(AllowAllGate::new("test"))
+}
+
+fn test_workspace(dir: &std::path::Path) -> (Workspace, String) {
+    let ws = Workspace::create(
+        dir.join("notes.db"),
+        "pass",
+        "test-identity",
+        ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]),
+        test_gate(),
+        None,
+    )
+    .unwrap();
+    let root_id = ws.list_all_notes().unwrap()[0].id.clone();
+    (ws, root_id)
+}
+
+fn body(note: &Note) -> &str {
+    match note.fields.get("body") {
+        Some(FieldValue::Text(s)) => s,
+        other => panic!("expected text body, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_enml_to_markdown_converts_common_formatting() {
+    let enml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE en-note SYSTEM "http://xml.evernote.com/pub/enml2.dtd">
+<en-note><h1>Plan</h1><div>Some <b>bold</b> and <i>italic</i>&nbsp;text.</div>
+<div><br/></div>
+<ul><li><div>first</div></li><li>second<ol><li>nested</li></ol></li></ul>
+<div><en-todo checked="true"/>done</div><div><en-todo/>open</div>
+<div>See <a href="https://example.com">the site</a></div>
+<table><tr><td>a</td><td>b</td></tr><tr><td>1</td><td>2</td></tr></table>
+<div style="--en-codeblock:true;"><div>let x = 1;</div></div></en-note>"#;
+    let md = enex::enml_to_markdown(enml);
+    assert!(
+        md.starts_with("# Plan\n\nSome **bold** and *italic* text."),
+        "{md}"
+    );
+    assert!(md.contains("- first\n- second\n  1. nested"), "{md}");
+    assert!(md.contains("- [x] done"), "{md}");
+    assert!(md.contains("- [ ] open"), "{md}");
+    assert!(md.contains("[the site](https://example.com)"), "{md}");
+    assert!(md.contains("| a | b |\n| --- | --- |\n| 1 | 2 |"), "{md}");
+    assert!(md.contains("```\nlet x = 1;\n```"), "{md}");
+}
+
+#[test]
+fn test_import_enex_creates_notes_tags_and_attachments() {
+    use base64::Engine as _;
+    use md5::{Digest, Md5};
+
+    let dir = tempfile::tempdir().unwrap();
+    let (mut ws, root_id) = test_workspace(dir.path());
+
+    let image = b"\x89PNG fake image bytes";
+    let hash = format!("{:x}", Md5::digest(image));
+    let data = base64::engine::general_purpose::STANDARD.encode(image);
+    let enex = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<en-export export-date="20240101T000000Z" application="Evernote">
+  <note>
+    <title>Trip notes</title>
+    <content><![CDATA[<?xml version="1.0" encoding="UTF-8"?><en-note><div>Photo:</div><en-media hash="{hash}" type="image/png"/><en-crypt>c2VjcmV0</en-crypt></en-note>]]></content>
+    <created>20230405T101500Z</created>
+    <tag>Travel</tag>
+    <tag>2023</tag>
+    <resource>
+      <data encoding="base64">{data}</data>
+      <mime>image/png</mime>
+      <resource-attributes><file-name>beach.png</file-name></resource-attributes>
+    </resource>
+  </note>
+  <note>
+    <title>Second</title>
+    <content><![CDATA[<en-note>Plain</en-note>]]></content>
+  </note>
+</en-export>"#
+    );
+
+    let report = import_enex(enex.as_bytes(), &mut ws, &root_id).unwrap();
+    assert_eq!(report.notes_created, 2);
+    assert_eq!(report.root_note_ids.len(), 2);
+    assert_eq!(report.attachments_imported, 1);
+    assert!(report
+        .issues
+        .iter()
+        .any(|i| i.location == "Trip notes" && i.message.contains("encrypted")));
+
+    let trip = ws.get_note(&report.root_note_ids[0]).unwrap();
+    assert_eq!(trip.title, "Trip notes");
+    assert_eq!(trip.parent_id.as_deref(), Some(root_id.as_str()));
+    assert_eq!(trip.tags, vec!["2023".to_string(), "travel".to_string()]);
+    assert!(body(&trip).contains("{{image: attach:beach.png}}"));
+
+    let attachments = ws.get_attachments(&trip.id).unwrap();
+    assert_eq!(attachments.len(), 1);
+    assert_eq!(attachments[0].filename, "beach.png");
+    assert_eq!(ws.get_attachment_bytes(&attachments[0].id).unwrap(), image);
+
+    let second = ws.get_note(&report.root_note_ids[1]).unwrap();
+    assert_eq!(body(&second), "Plain");
+}
+
+#[test]
+fn test_import_enex_rejects_non_enex_input() {
+    let dir = tempfile::tempdir().unwrap();
+    let (mut ws, root_id) = test_workspace(dir.path());
+    let result = import_enex(&b"<html><body>hi</body></html>"[..], &mut ws, &root_id);
+    assert!(matches!(result, Err(ExportError::InvalidFormat(_))));
+}
+
+#[test]
+fn test_import_foreign_workspace_keeps_enex_dates() {
+    let dir = tempfile::tempdir().unwrap();
+    let enex = r#"<?xml version="1.0" encoding="UTF-8"?>
+<en-export export-date="20240101T000000Z" application="Evernote">
+  <note>
+    <title>Old note</title>
+    <content><![CDATA[<en-note>From 2023</en-note>]]></content>
+    <created>20230405T101500Z</created>
+    <updated>20240102T030405Z</updated>
+  </note>
+</en-export>"#;
+
+    let (ws, report) = import_foreign_workspace(
+        ForeignFormat::Enex,
+        Cursor::new(enex.as_bytes()),
+        &dir.path().join("notes.db"),
+        "pass",
+        "test-identity",
+        ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]),
+        test_gate(),
+        None,
+    )
+    .unwrap();
+    assert_eq!(report.notes_created, 1);
+
+    let note = ws.get_note(&report.root_note_ids[0]).unwrap();
+    assert_eq!(note.title, "Old note");
+    assert!(ws.get_note(note.parent_id.as_deref().unwrap()).is_ok());
+    assert_eq!(note.created_at.as_i64(), 1_680_689_700);
+    assert_eq!(note.modified_at.as_i64(), 1_704_164_645);
+
+    // Peers build the note from its CreateNote operation, so the dates travel with it.
+    let create = ws
+        .operations_since_with_verified_by(None, "")
+        .unwrap()
+        .into_iter()
+        .find_map(|(op, _)| match op {
+            Operation::CreateNote {
+                note_id,
+                created_at,
+                modified_at,
+                ..
+            } if note_id == note.id => Some((created_at, modified_at)),
+            _ => None,
+        })
+        .expect("CreateNote for the imported note");
+    assert_eq!(create, (Some(note.created_at), Some(note.modified_at)));
+}
+
+fn notion_zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    {
+        let mut zip = zip::ZipWriter::new(Cursor::new(&mut buf));
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, content) in files {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+    buf
+}
+
+#[test]
+fn test_import_notion_pages_databases_and_assets() {
+    let dir = tempfile::tempdir().unwrap();
+    let (mut ws, root_id) = test_workspace(dir.path());
+
+    let id1 = "0123456789abcdef0123456789abcdef";
+    let id2 = "fedcba9876543210fedcba9876543210";
+    let id3 = "00000000000000000000000000000001";
+    let home_md = format!(
+        "# Home\n\nWelcome ![](Home%20{id1}/photo.png)\n\nSee [Tasks](Home%20{id1}/Tasks%20{id2}.csv)\n"
+    );
+    let csv = "\u{feff}Name,Done,Estimate,Due,Status\n\
+               Write spec,Yes,3,\"January 5, 2024\",In progress\n\
+               Review,No,1.5,,Todo\n";
+    let row_md =
+        "# Write spec\n\nDone: Yes\nEstimate: 3\nStatus: In progress\n\nDraft the outline first.\n";
+    let zip = notion_zip(&[
+        (&format!("Home {id1}.md"), home_md.as_bytes()),
+        (&format!("Home {id1}/photo.png"), b"png-bytes"),
+        (&format!("Home {id1}/Tasks {id2}.csv"), csv.as_bytes()),
+        (
+            &format!("Home {id1}/Tasks {id2}/Write spec {id3}.md"),
+            row_md.as_bytes(),
+        ),
+    ]);
+
+    let report = import_notion_export(Cursor::new(zip), &mut ws, &root_id).unwrap();
+    assert_eq!(report.root_note_ids.len(), 1);
+    assert_eq!(report.notes_created, 4); // Home, Tasks, two rows
+    assert_eq!(report.attachments_imported, 1);
+    assert_eq!(report.schemas_created, vec!["NotionTasks".to_string()]);
+    assert!(report
+        .issues
+        .iter()
+        .any(|i| i.message.contains("link(s) to other Notion pages")));
+
+    let home = ws.get_note(&report.root_note_ids[0]).unwrap();
+    assert_eq!(home.title, "Home");
+    assert!(body(&home).contains("{{image: attach:photo.png}}"));
+    assert!(body(&home).contains("See Tasks"));
+    assert_eq!(ws.get_attachments(&home.id).unwrap().len(), 1);
+
+    let children = ws.get_children(&home.id).unwrap();
+    assert_eq!(children.len(), 1);
+    let tasks = &children[0];
+    assert_eq!(tasks.title, "Tasks");
+
+    let schema = ws.script_registry().get_schema("NotionTasks").unwrap();
+    let types: Vec<(&str, &str)> = schema
+        .fields
+        .iter()
+        .map(|f| (f.name.as_str(), f.field_type.as_str()))
+        .collect();
+    assert_eq!(
+        types,
+        vec![
+            ("done", "boolean"),
+            ("estimate", "number"),
+            ("due", "date"),
+            ("status", "text"),
+            ("body", "textarea"),
+        ]
+    );
+
+    let rows = ws.get_children(&tasks.id).unwrap();
+    assert_eq!(rows.len(), 2);
+    let spec = rows.iter().find(|n| n.title == "Write spec").unwrap();
+    assert_eq!(spec.schema, "NotionTasks");
+    assert!(matches!(
+        spec.fields.get("done"),
+        Some(FieldValue::Boolean(true))
+    ));
+    assert!(matches!(spec.fields.get("estimate"), Some(FieldValue::Number(n)) if *n == 3.0));
+    assert!(matches!(
+        spec.fields.get("due"),
+        Some(FieldValue::Date(Some(d))) if d.to_string() == "2024-01-05"
+    ));
+    assert_eq!(body(spec), "Draft the outline first.");
+    let review = rows.iter().find(|n| n.title == "Review").unwrap();
+    assert!(matches!(
+        review.fields.get("due"),
+        Some(FieldValue::Date(None))
+    ));
+}
+
+#[test]
+fn test_unique_filename_suffixes_duplicates() {
+    let mut used = HashSet::new();
+    assert_eq!(unique_filename("a.png", &mut used), "a.png");
+    assert_eq!(unique_filename("a.png", &mut used), "a (2).png");
+    assert_eq!(unique_filename("README", &mut used), "README");
+    assert_eq!(unique_filename("README", &mut used), "README (2)");
+}
@@ -0,0 +1,175 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Tamper evidence for the operation log.
+//!
+//! Every signed operation carries the hash of its author's previous operation
+//! from the same device (`prev_hash`), so each author's operations form a hash
+//! chain per device. Removing, reordering or substituting operations — for
+//! instance forging a purged range — leaves an operation whose predecessor is
+//! unknown (a *gap*) or two operations claiming the same predecessor (a
+//! *fork*). `Workspace::audit_log_integrity` checks the whole log and
+//! returns a signed [`IntegrityReport`].
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{Deserialize, Serialize};
+
+/// How an operation breaks its author's hash chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChainBreakKind {
+    /// The operation's predecessor is not in the log.
+    Gap,
+    /// Another operation claims the same predecessor.
+    Fork,
+}
+
+/// One operation that breaks its author's hash chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainBreak {
+    pub kind: ChainBreakKind,
+    pub operation_id: String,
+    pub author_key: String,
+    pub device_id: String,
+    /// The `prev_hash` the operation carries.
+    pub prev_hash: String,
+}
+
+impl ChainBreak {
+    /// The `sync_events` event type reporting this break.
+    pub fn event_type(&self) -> &'static str {
+        match self.kind {
+            ChainBreakKind::Gap => "chain_gap",
+            ChainBreakKind::Fork => "chain_fork",
+        }
+    }
+
+    /// Human-readable description for the `sync_events` detail column.
+    pub fn describe(&self) -> String {
+        let author = &self.author_key[..8.min(self.author_key.len())];
+        let prev = &self.prev_hash[..12.min(self.prev_hash.len())];
+        match self.kind {
+            ChainBreakKind::Gap => format!(
+                "operation {} by {author}… on {} follows unknown operation {prev}…",
+                self.operation_id, self.device_id
+            ),
+            ChainBreakKind::Fork => format!(
+                "operation {} by {author}… on {} shares predecessor {prev}… with another operation",
+                self.operation_id, self.device_id
+            ),
+        }
+    }
+}
+
+/// Signed result of auditing a workspace's operation log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityReport {
+    pub workspace_id: String,
+    /// Unix seconds.
+    pub generated_at: i64,
+    /// Operations in the log when it was audited.
+    pub operation_count: u64,
+    /// Distinct (author, device) hash chains seen, including purged history.
+    pub chain_count: u64,
+    /// Hex SHA-256 over the chain hashes of all logged operations in HLC
+    /// order. Peers holding the same operations compute the same digest.
+    pub log_digest: String,
+    /// Operations whose signature does not verify against their author key.
+    pub invalid_signatures: Vec<String>,
+    /// Operations whose content no longer matches the hash recorded when
+    /// they were logged.
+    pub altered_operations: Vec<String>,
+    pub chain_breaks: Vec<ChainBreak>,
+    /// Base64 Ed25519 public key of the auditing identity.
+    pub author_key: String,
+    /// Base64 Ed25519 signature over the report with `signature = ""`.
+    pub signature: String,
+}
+
+impl IntegrityReport {
+    /// True if the audit found nothing wrong.
+    pub fn is_intact(&self) -> bool {
+        self.invalid_signatures.is_empty()
+            && self.altered_operations.is_empty()
+            && self.chain_breaks.is_empty()
+    }
+
+    /// Signs the report in place, setting `author_key` and `signature`.
+    pub fn sign(&mut self, key: &ed25519_dalek::SigningKey) {
+        use ed25519_dalek::Signer;
+
+        self.author_key = BASE64.encode(key.verifying_key().as_bytes());
+        self.signature = String::new();
+        let payload = serde_json::to_string(self).expect("IntegrityReport must be serializable");
+        self.signature = BASE64.encode(key.sign(payload.as_bytes()).to_bytes());
+    }
+
+    /// Verifies the signature against `author_key`.
+    pub fn verify(&self) -> bool {
+        use ed25519_dalek::Verifier;
+
+        let Some(vk) = BASE64
+            .decode(&self.author_key)
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .and_then(|arr| ed25519_dalek::VerifyingKey::from_bytes(&arr).ok())
+        else {
+            return false;
+        };
+        let Some(sig) = BASE64
+            .decode(&self.signature)
+            .ok()
+            .and_then(|bytes| <[u8; 64]>::try_from(bytes).ok())
+            .map(|arr| ed25519_dalek::Signature::from_bytes(&arr))
+        else {
+            return false;
+        };
+        let mut unsigned = self.clone();
+        unsigned.signature = String::new();
+        let payload =
+            serde_json::to_string(&unsigned).expect("IntegrityReport must be serializable");
+        vk.verify(payload.as_bytes(), &sig).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_sign_verify_and_tamper() {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[5u8; 32]);
+        let mut report = IntegrityReport {
+            workspace_id: "ws-1".into(),
+            generated_at: 1,
+            operation_count: 3,
+            chain_count: 1,
+            log_digest: "00".into(),
+            invalid_signatures: vec![],
+            altered_operations: vec![],
+            chain_breaks: vec![],
+            author_key: String::new(),
+            signature: String::new(),
+        };
+        report.sign(&key);
+        assert!(report.verify());
+        assert!(report.is_intact());
+
+        let mut hidden = report.clone();
+        hidden.chain_breaks.push(ChainBreak {
+            kind: ChainBreakKind::Gap,
+            operation_id: "op-2".into(),
+            author_key: "author".into(),
+            device_id: "dev".into(),
+            prev_hash: "ab".into(),
+        });
+        assert!(!hidden.verify());
+        assert!(!hidden.is_intact());
+        assert_eq!(hidden.chain_breaks[0].event_type(), "chain_gap");
+    }
+}
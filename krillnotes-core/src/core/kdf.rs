@@ -0,0 +1,181 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Argon2id cost parameters for identity files.
+//!
+//! An identity's seed is encrypted under a key derived from its passphrase
+//! with the [`KdfCost`] current when it was written. [`KdfCost::MINIMUM`] is
+//! the floor; a machine can raise its policy with [`benchmark_kdf_cost`], and
+//! `IdentityManager::unlock_identity` rehashes any identity file whose stored
+//! cost falls below the policy.
+
+use std::time::{Duration, Instant};
+
+use argon2::Argon2;
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::Result;
+
+#[cfg(test)]
+const ARGON2_M_COST: u32 = 1024; // 1 MiB — fast for tests
+#[cfg(test)]
+const ARGON2_T_COST: u32 = 1;
+
+#[cfg(not(test))]
+const ARGON2_M_COST: u32 = 65536; // 64 MiB — production
+#[cfg(not(test))]
+const ARGON2_T_COST: u32 = 3;
+
+const ARGON2_P_COST: u32 = 1;
+
+/// Largest memory cost [`benchmark_kdf_cost`] will pick, in KiB (1 GiB).
+const MAX_BENCHMARK_M_COST: u32 = 1024 * 1024;
+
+/// Largest time cost [`benchmark_kdf_cost`] will pick.
+const MAX_BENCHMARK_T_COST: u32 = 16;
+
+/// Argon2id cost parameters: memory in KiB, iterations and lanes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KdfCost {
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+impl KdfCost {
+    /// The weakest cost new identity files are written with.
+    pub const MINIMUM: KdfCost = KdfCost {
+        m_cost: ARGON2_M_COST,
+        t_cost: ARGON2_T_COST,
+        p_cost: ARGON2_P_COST,
+    };
+
+    /// True if every parameter is at least as strong as `policy`'s.
+    pub fn meets(&self, policy: &KdfCost) -> bool {
+        self.m_cost >= policy.m_cost && self.t_cost >= policy.t_cost && self.p_cost >= policy.p_cost
+    }
+
+    /// The parameter-wise maximum of `self` and `other`.
+    pub fn max(self, other: KdfCost) -> KdfCost {
+        KdfCost {
+            m_cost: self.m_cost.max(other.m_cost),
+            t_cost: self.t_cost.max(other.t_cost),
+            p_cost: self.p_cost.max(other.p_cost),
+        }
+    }
+}
+
+impl Default for KdfCost {
+    fn default() -> Self {
+        Self::MINIMUM
+    }
+}
+
+/// Derives a 32-byte key from `passphrase` and `salt` with Argon2id at `cost`.
+pub fn derive_key(passphrase: &str, salt: &[u8], cost: &KdfCost) -> Result<[u8; 32]> {
+    let params = argon2::Params::new(cost.m_cost, cost.t_cost, cost.p_cost, Some(32))
+        .map_err(|e| crate::KrillnotesError::IdentityCorrupt(format!("Argon2 params: {e}")))?;
+    let argon2 = Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+    let mut key = [0u8; 32];
+    argon2
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| crate::KrillnotesError::IdentityCorrupt(format!("Argon2 hash: {e}")))?;
+    Ok(key)
+}
+
+/// Picks the strongest cost this machine can derive a key at within about
+/// `target`, never weaker than [`KdfCost::MINIMUM`].
+///
+/// Memory is doubled first while a single pass stays under a quarter of
+/// `target`, since memory hardness is what slows down GPU attacks; the
+/// remaining budget goes to iterations.
+pub fn benchmark_kdf_cost(target: Duration) -> Result<KdfCost> {
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let time = |cost: &KdfCost| -> Result<Duration> {
+        let start = Instant::now();
+        let mut key = derive_key("krillnotes-kdf-benchmark", &salt, cost)?;
+        key.fill(0);
+        Ok(start.elapsed())
+    };
+
+    let mut cost = KdfCost {
+        t_cost: 1,
+        ..KdfCost::MINIMUM
+    };
+    let mut elapsed = time(&cost)?;
+    while elapsed * 4 < target && cost.m_cost * 2 <= MAX_BENCHMARK_M_COST {
+        cost.m_cost *= 2;
+        elapsed = time(&cost)?;
+    }
+
+    let passes = target.as_secs_f64() / elapsed.as_secs_f64().max(f64::EPSILON);
+    cost.t_cost = (passes as u32).clamp(1, MAX_BENCHMARK_T_COST);
+    Ok(cost.max(KdfCost::MINIMUM))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kdf_cost_policy_comparison() {
+        let policy = KdfCost::MINIMUM;
+        assert!(policy.meets(&policy));
+
+        let weaker = KdfCost {
+            t_cost: policy.t_cost,
+            m_cost: policy.m_cost / 2,
+            p_cost: policy.p_cost,
+        };
+        assert!(!weaker.meets(&policy));
+        assert_eq!(weaker.max(policy), policy);
+
+        let stronger = KdfCost {
+            t_cost: policy.t_cost + 1,
+            ..policy
+        };
+        assert!(stronger.meets(&policy) && !policy.meets(&stronger));
+    }
+
+    #[test]
+    fn kdf_cost_max_keeps_the_stronger_parameters() {
+        let stored = KdfCost {
+            m_cost: 256 * 1024,
+            t_cost: 2,
+            p_cost: 1,
+        };
+        let policy = KdfCost {
+            m_cost: 64 * 1024,
+            t_cost: 3,
+            p_cost: 1,
+        };
+        assert!(!stored.meets(&policy) && !policy.meets(&stored));
+
+        let target = stored.max(policy);
+        assert_eq!(
+            target,
+            KdfCost {
+                m_cost: 256 * 1024,
+                t_cost: 3,
+                p_cost: 1,
+            }
+        );
+        assert!(target.meets(&stored) && target.meets(&policy));
+    }
+
+    #[test]
+    fn benchmark_never_goes_below_minimum() {
+        let cost = benchmark_kdf_cost(Duration::ZERO).unwrap();
+        assert_eq!(cost, KdfCost::MINIMUM);
+
+        let cost = benchmark_kdf_cost(Duration::from_millis(50)).unwrap();
+        assert!(cost.meets(&KdfCost::MINIMUM));
+        assert!(cost.m_cost <= MAX_BENCHMARK_M_COST && cost.t_cost <= MAX_BENCHMARK_T_COST);
+    }
+}
@@ -10,7 +10,10 @@
 //! with `#[doc(inline)]`; import from there in preference to this module.
 
 pub mod accepted_invite;
+pub mod archive_format;
 pub mod attachment;
+pub mod audit_export;
+pub mod checkpoint;
 pub mod contact;
 pub mod delete;
 pub mod device;
@@ -18,26 +21,46 @@ pub mod error;
 pub mod export;
 pub mod hlc;
 pub mod identity;
+pub mod importers;
+pub mod integrity;
 pub mod invite;
+pub mod kdf;
 pub mod note;
 pub mod operation;
 pub mod operation_log;
+pub mod passphrase;
 pub mod peer_registry;
 pub mod permission;
 pub mod received_response;
+pub mod root_recovery;
 pub mod save_transaction;
 pub mod scripting;
 pub mod storage;
+pub mod succession;
 pub mod swarm;
 pub mod sync;
 pub mod timestamp;
 pub mod undo;
 pub mod user_script;
+pub mod verification;
+pub mod vouch;
 pub mod workspace;
 
+#[doc(inline)]
+pub use archive_format::{
+    upgrade_archive, validate_archive, ArchiveDocuments, ArchiveValidationError,
+    ARCHIVE_FORMAT_VERSION, NOTES_JSON_SCHEMA, SCRIPTS_JSON_SCHEMA, WORKSPACE_JSON_SCHEMA,
+};
 #[doc(inline)]
 pub use attachment::AttachmentMeta;
 #[doc(inline)]
+pub use audit_export::{
+    verify_audit_export, AuditExport, AuditExportOptions, AuditManifest, AuditRecord,
+    AuditVerification,
+};
+#[doc(inline)]
+pub use checkpoint::{Checkpoint, CheckpointState};
+#[doc(inline)]
 pub use contact::{generate_fingerprint, Contact, ContactManager, TrustLevel};
 #[doc(inline)]
 pub use delete::{DeleteResult, DeleteStrategy};
@@ -47,28 +70,55 @@ pub use device::get_device_id;
 pub use error::{KrillnotesError, Result};
 #[doc(inline)]
 pub use export::{
-    export_workspace, import_workspace, peek_import, ExportError, ExportNotes, ImportResult,
-    ScriptManifest, ScriptManifestEntry, APP_VERSION,
+    export_workspace, export_workspace_with_options, import_workspace, merge_import_workspace,
+    peek_import, ExportError, ExportNotes, ExportOptions, ImportResult, MergeImportOptions,
+    MergeImportResult, ReferencedNotes, ScriptConflict, ScriptConflictPolicy, ScriptManifest,
+    ScriptManifestEntry, SubtreeExportInfo, APP_VERSION,
+};
+#[doc(inline)]
+pub use importers::{
+    import_enex, import_foreign, import_foreign_workspace, import_notion_export, ForeignFormat,
+    ForeignImportReport, ImportIssue,
 };
 #[doc(inline)]
+pub use integrity::{ChainBreak, ChainBreakKind, IntegrityReport};
+#[doc(inline)]
+pub use kdf::{benchmark_kdf_cost, KdfCost};
+#[doc(inline)]
 pub use note::{FieldValue, Note};
 #[doc(inline)]
 pub use operation::Operation;
 #[doc(inline)]
-pub use operation_log::{OperationLog, OperationSummary, PurgeStrategy};
+pub use operation_log::{CommitListener, OperationLog, OperationSummary, PurgeStrategy};
+#[doc(inline)]
+pub use passphrase::{
+    estimate_passphrase_strength, PassphraseEstimate, PassphraseHint, PassphraseStrength,
+    MIN_PASSPHRASE_STRENGTH,
+};
 #[doc(inline)]
 pub use peer_registry::{PeerRegistry, SyncPeer};
 #[doc(inline)]
 pub use permission::{PermissionError, PermissionGate};
 #[doc(inline)]
+pub use root_recovery::RecoveryShare;
+#[doc(inline)]
 pub use scripting::{FieldDefinition, Schema, ScriptRegistry};
 #[doc(inline)]
 pub use storage::Storage;
 #[doc(inline)]
+pub use succession::SuccessionRecord;
+#[doc(inline)]
 pub use swarm::header::{RecipientEntry, SwarmHeader, SwarmMode};
 #[doc(inline)]
 pub use undo::{RetractInverse, UndoResult};
 #[doc(inline)]
 pub use user_script::UserScript;
 #[doc(inline)]
-pub use workspace::{AddPosition, NoteSearchResult, Workspace};
+pub use verification::{
+    ContactVerification, QrVerificationPayload, SasCode, SasMessage, SasSessionInfo,
+    VerificationMethod,
+};
+#[doc(inline)]
+pub use vouch::VouchStatement;
+#[doc(inline)]
+pub use workspace::{AddPosition, GraftIdStrategy, NoteSearchResult, Workspace};
@@ -7,6 +7,7 @@
 //! CRDT-style operation types for the Krillnotes operation log.
 
 use crate::core::hlc::HlcTimestamp;
+use crate::core::timestamp::UnixSecs;
 use crate::FieldValue;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
@@ -42,6 +43,23 @@ pub enum Operation {
         fields: BTreeMap<String, FieldValue>,
         /// Public key (base64) of the identity that created this note.
         created_by: String,
+        /// Creation time the note keeps from an external source (e.g. an
+        /// importer); `None` stamps it with the operation's timestamp.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        created_at: Option<UnixSecs>,
+        /// Modification time the note keeps from an external source;
+        /// `None` stamps it with the operation's timestamp.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        modified_at: Option<UnixSecs>,
+        /// For a note rebuilt across a recipient's read-scope boundary: the
+        /// public key of its original author, recorded by the recipient in
+        /// place of `created_by`, which names the signer.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        original_author: Option<String>,
+        /// Hex SHA-256 of the author's previous operation from this device,
+        /// chaining their operations; `None` at the start of a chain.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prev_hash: Option<String>,
         /// Ed25519 signature over the canonical JSON payload (base64).
         signature: String,
     },
@@ -59,6 +77,10 @@ pub enum Operation {
         title: String,
         /// Public key (base64) of the identity that modified this note.
         modified_by: String,
+        /// Hex SHA-256 of the author's previous operation from this device,
+        /// chaining their operations; `None` at the start of a chain.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prev_hash: Option<String>,
         /// Ed25519 signature over the canonical JSON payload (base64).
         signature: String,
     },
@@ -78,6 +100,10 @@ pub enum Operation {
         value: FieldValue,
         /// Public key (base64) of the identity that modified this note.
         modified_by: String,
+        /// Hex SHA-256 of the author's previous operation from this device,
+        /// chaining their operations; `None` at the start of a chain.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prev_hash: Option<String>,
         /// Ed25519 signature over the canonical JSON payload (base64).
         signature: String,
     },
@@ -93,6 +119,10 @@ pub enum Operation {
         note_id: String,
         /// Public key (base64) of the identity that deleted this note.
         deleted_by: String,
+        /// Hex SHA-256 of the author's previous operation from this device,
+        /// chaining their operations; `None` at the start of a chain.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prev_hash: Option<String>,
         /// Ed25519 signature over the canonical JSON payload (base64).
         signature: String,
     },
@@ -112,6 +142,10 @@ pub enum Operation {
         new_position: f64,
         /// Public key (base64) of the identity that moved this note.
         moved_by: String,
+        /// Hex SHA-256 of the author's previous operation from this device,
+        /// chaining their operations; `None` at the start of a chain.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prev_hash: Option<String>,
         /// Ed25519 signature over the canonical JSON payload (base64).
         signature: String,
     },
@@ -129,6 +163,10 @@ pub enum Operation {
         tags: Vec<String>,
         /// Public key (base64) of the identity that modified this note.
         modified_by: String,
+        /// Hex SHA-256 of the author's previous operation from this device,
+        /// chaining their operations; `None` at the start of a chain.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prev_hash: Option<String>,
         /// Ed25519 signature over the canonical JSON payload (base64).
         signature: String,
     },
@@ -154,6 +192,10 @@ pub enum Operation {
         enabled: bool,
         /// Public key (base64) of the identity that created this script.
         created_by: String,
+        /// Hex SHA-256 of the author's previous operation from this device,
+        /// chaining their operations; `None` at the start of a chain.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prev_hash: Option<String>,
         /// Ed25519 signature over the canonical JSON payload (base64).
         signature: String,
     },
@@ -179,6 +221,10 @@ pub enum Operation {
         enabled: bool,
         /// Public key (base64) of the identity that modified this script.
         modified_by: String,
+        /// Hex SHA-256 of the author's previous operation from this device,
+        /// chaining their operations; `None` at the start of a chain.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prev_hash: Option<String>,
         /// Ed25519 signature over the canonical JSON payload (base64).
         signature: String,
     },
@@ -194,6 +240,10 @@ pub enum Operation {
         script_id: String,
         /// Public key (base64) of the identity that deleted this script.
         deleted_by: String,
+        /// Hex SHA-256 of the author's previous operation from this device,
+        /// chaining their operations; `None` at the start of a chain.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prev_hash: Option<String>,
         /// Ed25519 signature over the canonical JSON payload (base64).
         signature: String,
     },
@@ -215,6 +265,10 @@ pub enum Operation {
         notes_migrated: u32,
         /// Public key (base64) of the identity that ran the migration.
         updated_by: String,
+        /// Hex SHA-256 of the author's previous operation from this device,
+        /// chaining their operations; `None` at the start of a chain.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prev_hash: Option<String>,
         /// Ed25519 signature over the canonical JSON payload (base64).
         signature: String,
     },
@@ -251,6 +305,10 @@ pub enum Operation {
         role: String,
         /// Public key (base64) of the identity issuing this grant.
         granted_by: String,
+        /// Hex SHA-256 of the author's previous operation from this device,
+        /// chaining their operations; `None` at the start of a chain.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prev_hash: Option<String>,
         /// Ed25519 signature over the canonical JSON payload (base64).
         signature: String,
     },
@@ -265,6 +323,10 @@ pub enum Operation {
         user_id: String,
         /// Public key (base64) of the identity performing the revocation.
         revoked_by: String,
+        /// Hex SHA-256 of the author's previous operation from this device,
+        /// chaining their operations; `None` at the start of a chain.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prev_hash: Option<String>,
         /// Ed25519 signature over the canonical JSON payload (base64).
         signature: String,
     },
@@ -279,6 +341,10 @@ pub enum Operation {
         declared_name: String,
         /// Reference to the pairing token from the corresponding invite.swarm.
         pairing_token: String,
+        /// Hex SHA-256 of the author's previous operation from this device,
+        /// chaining their operations; `None` at the start of a chain.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prev_hash: Option<String>,
         /// Ed25519 signature over the canonical JSON payload (base64).
         signature: String,
     },
@@ -292,6 +358,10 @@ pub enum Operation {
         user_id: String,
         /// Public key of the Root Owner performing the removal.
         removed_by: String,
+        /// Hex SHA-256 of the author's previous operation from this device,
+        /// chaining their operations; `None` at the start of a chain.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prev_hash: Option<String>,
         signature: String,
     },
     /// Transfer root ownership to another peer.
@@ -302,8 +372,17 @@ pub enum Operation {
         device_id: String,
         /// Public key of the new Root Owner.
         new_owner: String,
-        /// Public key of the current (outgoing) Root Owner.
+        /// Public key of the author: the outgoing Root Owner, or `new_owner`
+        /// when the transfer is authorised by `recovery_signature`.
         transferred_by: String,
+        /// Signature by the workspace recovery key rebuilt from recovery
+        /// shares (base64), standing in for the outgoing owner's consent.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        recovery_signature: Option<String>,
+        /// Hex SHA-256 of the author's previous operation from this device,
+        /// chaining their operations; `None` at the start of a chain.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prev_hash: Option<String>,
         signature: String,
     },
     /// A file attachment was added to a note.
@@ -328,6 +407,10 @@ pub enum Operation {
         hash_sha256: String,
         /// Public key (base64) of the identity that added this attachment.
         added_by: String,
+        /// Hex SHA-256 of the author's previous operation from this device,
+        /// chaining their operations; `None` at the start of a chain.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prev_hash: Option<String>,
         /// Ed25519 signature over the canonical JSON payload (base64).
         signature: String,
     },
@@ -345,6 +428,10 @@ pub enum Operation {
         note_id: String,
         /// Public key (base64) of the identity that removed this attachment.
         removed_by: String,
+        /// Hex SHA-256 of the author's previous operation from this device,
+        /// chaining their operations; `None` at the start of a chain.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prev_hash: Option<String>,
         /// Ed25519 signature over the canonical JSON payload (base64).
         signature: String,
     },
@@ -363,6 +450,10 @@ pub enum Operation {
         device_name: String,
         /// Base64-encoded Ed25519 public key of the registering identity.
         identity_public_key: String,
+        /// Hex SHA-256 of the author's previous operation from this device,
+        /// chaining their operations; `None` at the start of a chain.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prev_hash: Option<String>,
         /// Ed25519 signature over the canonical JSON payload (base64).
         signature: String,
     },
@@ -380,6 +471,79 @@ pub enum Operation {
         checked: bool,
         /// Public key (base64) of the identity that modified this note.
         modified_by: String,
+        /// Hex SHA-256 of the author's previous operation from this device,
+        /// chaining their operations; `None` at the start of a chain.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prev_hash: Option<String>,
+        /// Ed25519 signature over the canonical JSON payload (base64).
+        signature: String,
+    },
+    /// The author's identity key was replaced by a successor key.
+    ///
+    /// Signed by the new key; `succession_signature` is the retiring key's
+    /// signature over the matching [`SuccessionRecord`](crate::SuccessionRecord),
+    /// so peers can link both keys to the same principal.
+    RotateIdentityKey {
+        /// Stable UUID for this operation.
+        operation_id: String,
+        /// HLC timestamp when the operation was created.
+        timestamp: HlcTimestamp,
+        /// ID of the device that performed this operation.
+        device_id: String,
+        /// Base64-encoded Ed25519 public key being retired.
+        old_key: String,
+        /// Base64-encoded Ed25519 public key of the successor (the author).
+        new_key: String,
+        /// Unix milliseconds after which signatures by `old_key` are rejected.
+        rotated_at_ms: u64,
+        /// Ed25519 signature by `old_key` over the succession record (base64).
+        succession_signature: String,
+        /// Hex SHA-256 of the author's previous operation from this device,
+        /// chaining their operations; `None` at the start of a chain.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prev_hash: Option<String>,
+        /// Ed25519 signature over the canonical JSON payload (base64).
+        signature: String,
+    },
+    /// The Root Owner set up social recovery: any `threshold` of the shares
+    /// handed to trusted contacts rebuild the private half of `recovery_key`.
+    SetRootRecovery {
+        /// Stable UUID for this operation.
+        operation_id: String,
+        /// HLC timestamp when the operation was created.
+        timestamp: HlcTimestamp,
+        /// ID of the device that performed this operation.
+        device_id: String,
+        /// Base64-encoded Ed25519 public recovery key.
+        recovery_key: String,
+        /// Number of shares needed to rebuild the recovery key.
+        threshold: u8,
+        /// Public key (base64) of the Root Owner who set it up.
+        set_by: String,
+        /// Hex SHA-256 of the author's previous operation from this device,
+        /// chaining their operations; `None` at the start of a chain.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prev_hash: Option<String>,
+        /// Ed25519 signature over the canonical JSON payload (base64).
+        signature: String,
+    },
+    /// One of the author's devices was revoked; operations it creates after
+    /// this timestamp are rejected.
+    RevokeDevice {
+        /// Stable UUID for this operation.
+        operation_id: String,
+        /// HLC timestamp when the operation was created.
+        timestamp: HlcTimestamp,
+        /// ID of the device that performed this operation.
+        device_id: String,
+        /// Composite device ID (as carried by operations) being revoked.
+        revoked_device_id: String,
+        /// Public key (base64) of the identity that owns the revoked device.
+        revoked_by: String,
+        /// Hex SHA-256 of the author's previous operation from this device,
+        /// chaining their operations; `None` at the start of a chain.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prev_hash: Option<String>,
         /// Ed25519 signature over the canonical JSON payload (base64).
         signature: String,
     },
@@ -409,7 +573,10 @@ impl Operation {
             | Self::AddAttachment { operation_id, .. }
             | Self::RemoveAttachment { operation_id, .. }
             | Self::RegisterDevice { operation_id, .. }
-            | Self::SetChecked { operation_id, .. } => operation_id,
+            | Self::SetChecked { operation_id, .. }
+            | Self::RotateIdentityKey { operation_id, .. }
+            | Self::RevokeDevice { operation_id, .. }
+            | Self::SetRootRecovery { operation_id, .. } => operation_id,
         }
     }
 
@@ -436,7 +603,40 @@ impl Operation {
             | Self::AddAttachment { timestamp, .. }
             | Self::RemoveAttachment { timestamp, .. }
             | Self::RegisterDevice { timestamp, .. }
-            | Self::SetChecked { timestamp, .. } => *timestamp,
+            | Self::SetChecked { timestamp, .. }
+            | Self::RotateIdentityKey { timestamp, .. }
+            | Self::RevokeDevice { timestamp, .. }
+            | Self::SetRootRecovery { timestamp, .. } => *timestamp,
+        }
+    }
+
+    /// Stamps the operation with `ts`. Must be called before
+    /// [`sign`](Self::sign), since the timestamp is part of the signed payload.
+    pub fn set_timestamp(&mut self, ts: HlcTimestamp) {
+        match self {
+            Self::CreateNote { timestamp, .. }
+            | Self::UpdateNote { timestamp, .. }
+            | Self::UpdateField { timestamp, .. }
+            | Self::DeleteNote { timestamp, .. }
+            | Self::MoveNote { timestamp, .. }
+            | Self::SetTags { timestamp, .. }
+            | Self::CreateUserScript { timestamp, .. }
+            | Self::UpdateUserScript { timestamp, .. }
+            | Self::DeleteUserScript { timestamp, .. }
+            | Self::UpdateSchema { timestamp, .. }
+            | Self::RetractOperation { timestamp, .. }
+            | Self::SetPermission { timestamp, .. }
+            | Self::RevokePermission { timestamp, .. }
+            | Self::JoinWorkspace { timestamp, .. }
+            | Self::RemovePeer { timestamp, .. }
+            | Self::TransferRootOwnership { timestamp, .. }
+            | Self::AddAttachment { timestamp, .. }
+            | Self::RemoveAttachment { timestamp, .. }
+            | Self::RegisterDevice { timestamp, .. }
+            | Self::SetChecked { timestamp, .. }
+            | Self::RotateIdentityKey { timestamp, .. }
+            | Self::RevokeDevice { timestamp, .. }
+            | Self::SetRootRecovery { timestamp, .. } => *timestamp = ts,
         }
     }
 
@@ -463,7 +663,10 @@ impl Operation {
             | Self::AddAttachment { device_id, .. }
             | Self::RemoveAttachment { device_id, .. }
             | Self::RegisterDevice { device_id, .. }
-            | Self::SetChecked { device_id, .. } => device_id,
+            | Self::SetChecked { device_id, .. }
+            | Self::RotateIdentityKey { device_id, .. }
+            | Self::RevokeDevice { device_id, .. }
+            | Self::SetRootRecovery { device_id, .. } => device_id,
         }
     }
 
@@ -499,9 +702,81 @@ impl Operation {
                 ..
             } => identity_public_key,
             Self::SetChecked { modified_by, .. } => modified_by,
+            Self::RotateIdentityKey { new_key, .. } => new_key,
+            Self::RevokeDevice { revoked_by, .. } => revoked_by,
+            Self::SetRootRecovery { set_by, .. } => set_by,
         }
     }
 
+    /// Hash of the author's previous operation from the same device, if this
+    /// operation is chained to one. `RetractOperation` is never chained.
+    pub fn prev_hash(&self) -> Option<&str> {
+        match self {
+            Self::CreateNote { prev_hash, .. }
+            | Self::UpdateNote { prev_hash, .. }
+            | Self::UpdateField { prev_hash, .. }
+            | Self::DeleteNote { prev_hash, .. }
+            | Self::MoveNote { prev_hash, .. }
+            | Self::SetTags { prev_hash, .. }
+            | Self::CreateUserScript { prev_hash, .. }
+            | Self::UpdateUserScript { prev_hash, .. }
+            | Self::DeleteUserScript { prev_hash, .. }
+            | Self::UpdateSchema { prev_hash, .. }
+            | Self::SetPermission { prev_hash, .. }
+            | Self::RevokePermission { prev_hash, .. }
+            | Self::JoinWorkspace { prev_hash, .. }
+            | Self::RemovePeer { prev_hash, .. }
+            | Self::TransferRootOwnership { prev_hash, .. }
+            | Self::AddAttachment { prev_hash, .. }
+            | Self::RemoveAttachment { prev_hash, .. }
+            | Self::RegisterDevice { prev_hash, .. }
+            | Self::SetChecked { prev_hash, .. }
+            | Self::RotateIdentityKey { prev_hash, .. }
+            | Self::RevokeDevice { prev_hash, .. }
+            | Self::SetRootRecovery { prev_hash, .. } => prev_hash.as_deref(),
+            Self::RetractOperation { .. } => None,
+        }
+    }
+
+    /// Chains this operation to the author's previous one. Must be called
+    /// before [`sign`](Self::sign), since the link is part of the signed payload.
+    pub fn set_prev_hash(&mut self, hash: Option<String>) {
+        match self {
+            Self::CreateNote { prev_hash, .. }
+            | Self::UpdateNote { prev_hash, .. }
+            | Self::UpdateField { prev_hash, .. }
+            | Self::DeleteNote { prev_hash, .. }
+            | Self::MoveNote { prev_hash, .. }
+            | Self::SetTags { prev_hash, .. }
+            | Self::CreateUserScript { prev_hash, .. }
+            | Self::UpdateUserScript { prev_hash, .. }
+            | Self::DeleteUserScript { prev_hash, .. }
+            | Self::UpdateSchema { prev_hash, .. }
+            | Self::SetPermission { prev_hash, .. }
+            | Self::RevokePermission { prev_hash, .. }
+            | Self::JoinWorkspace { prev_hash, .. }
+            | Self::RemovePeer { prev_hash, .. }
+            | Self::TransferRootOwnership { prev_hash, .. }
+            | Self::AddAttachment { prev_hash, .. }
+            | Self::RemoveAttachment { prev_hash, .. }
+            | Self::RegisterDevice { prev_hash, .. }
+            | Self::SetChecked { prev_hash, .. }
+            | Self::RotateIdentityKey { prev_hash, .. }
+            | Self::RevokeDevice { prev_hash, .. }
+            | Self::SetRootRecovery { prev_hash, .. } => *prev_hash = hash,
+            Self::RetractOperation { .. } => {}
+        }
+    }
+
+    /// Hex SHA-256 of the serialized operation, signature included — what the
+    /// author's next operation carries as its `prev_hash`.
+    pub fn chain_hash(&self) -> String {
+        use sha2::{Digest, Sha256};
+
+        let json = serde_json::to_string(self).expect("Operation must be serializable");
+        hex::encode(Sha256::digest(json.as_bytes()))
+    }
+
     // ── Private helpers for sign/verify ────────────────────────────────────
 
     fn set_author_key(&mut self, key: String) {
@@ -532,6 +807,9 @@ impl Operation {
                 ..
             } => *identity_public_key = key,
             Self::SetChecked { modified_by, .. } => *modified_by = key,
+            Self::RotateIdentityKey { new_key, .. } => *new_key = key,
+            Self::RevokeDevice { revoked_by, .. } => *revoked_by = key,
+            Self::SetRootRecovery { set_by, .. } => *set_by = key,
         }
     }
 
@@ -555,12 +833,15 @@ impl Operation {
             | Self::AddAttachment { signature, .. }
             | Self::RemoveAttachment { signature, .. }
             | Self::RegisterDevice { signature, .. }
-            | Self::SetChecked { signature, .. } => *signature = sig,
+            | Self::SetChecked { signature, .. }
+            | Self::RotateIdentityKey { signature, .. }
+            | Self::RevokeDevice { signature, .. }
+            | Self::SetRootRecovery { signature, .. } => *signature = sig,
             Self::RetractOperation { .. } => {}
         }
     }
 
-    fn get_signature(&self) -> &str {
+    pub(crate) fn get_signature(&self) -> &str {
         match self {
             Self::CreateNote { signature, .. }
             | Self::UpdateNote { signature, .. }
@@ -580,7 +861,10 @@ impl Operation {
             | Self::AddAttachment { signature, .. }
             | Self::RemoveAttachment { signature, .. }
             | Self::RegisterDevice { signature, .. }
-            | Self::SetChecked { signature, .. } => signature,
+            | Self::SetChecked { signature, .. }
+            | Self::RotateIdentityKey { signature, .. }
+            | Self::RevokeDevice { signature, .. }
+            | Self::SetRootRecovery { signature, .. } => signature,
             Self::RetractOperation { .. } => "",
         }
     }
@@ -635,6 +919,20 @@ impl Operation {
 
         pubkey.verify(payload.as_bytes(), &sig).is_ok()
     }
+
+    /// Verify the signature against the operation's own author key.
+    ///
+    /// Returns `false` for operations without an author key.
+    pub fn verify_author(&self) -> bool {
+        use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+        STANDARD
+            .decode(self.author_key())
+            .ok()
+            .and_then(|b| <[u8; 32]>::try_from(b).ok())
+            .and_then(|arr| ed25519_dalek::VerifyingKey::from_bytes(&arr).ok())
+            .is_some_and(|vk| self.verify(&vk))
+    }
 }
 
 #[cfg(test)]
@@ -6,9 +6,11 @@
 
 //! Durable operation log and purge strategies for the Krillnotes workspace.
 
+use std::sync::Arc;
+
 use crate::{Operation, Result};
-use rusqlite::Connection;
 use rusqlite::Transaction;
+use rusqlite::{Connection, OptionalExtension};
 
 /// Seconds in one day; used to convert `retention_days` to a Unix timestamp cutoff.
 const SECONDS_PER_DAY: i64 = 86_400;
@@ -44,6 +46,49 @@ pub struct OperationSummary {
     pub verified_by: String,
 }
 
+/// Returns the chain hash of the newest operation `author_key` logged from
+/// `device_id` — the `prev_hash` of their next operation from that device.
+///
+/// Every author keeps one hash chain per device: devices cannot see each
+/// other's latest operation before signing, so a single chain would fork.
+pub(crate) fn chain_head(
+    conn: &Connection,
+    author_key: &str,
+    device_id: &str,
+) -> Result<Option<String>> {
+    Ok(conn
+        .query_row(
+            "SELECT hash FROM op_chain WHERE author_key = ?1 AND device_id = ?2 \
+             ORDER BY rowid DESC LIMIT 1",
+            rusqlite::params![author_key, device_id],
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+/// Records the chain link of a logged operation in `op_chain`. Unsigned
+/// operations are not chained.
+pub(crate) fn record_chain_link(conn: &Connection, op: &Operation) -> Result<()> {
+    if op.author_key().is_empty() {
+        return Ok(());
+    }
+    conn.execute(
+        "INSERT OR IGNORE INTO op_chain (operation_id, author_key, device_id, hash, prev_hash) \
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        rusqlite::params![
+            op.operation_id(),
+            op.author_key(),
+            op.device_id(),
+            op.chain_hash(),
+            op.prev_hash(),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Called after a locally authored operation is logged.
+pub type CommitListener = Arc<dyn Fn() + Send + Sync>;
+
 /// Records document mutations to the `operations` table and purges stale entries.
 pub struct OperationLog {
     strategy: PurgeStrategy,
@@ -51,6 +96,8 @@ pub struct OperationLog {
     /// Stamped into `verified_by` on every INSERT so self-authored ops
     /// are immediately marked as verified.
     identity_pubkey: String,
+    /// Notified after each [`log`](Self::log); see [`set_listener`](Self::set_listener).
+    listener: Option<CommitListener>,
 }
 
 impl OperationLog {
@@ -59,9 +106,18 @@ impl OperationLog {
         Self {
             strategy,
             identity_pubkey,
+            listener: None,
         }
     }
 
+    /// Sets the callback notified whenever a local operation is logged.
+    ///
+    /// The callback runs inside the caller's transaction, before commit, so
+    /// it should only schedule work (e.g. wake a sync scheduler).
+    pub fn set_listener(&mut self, listener: Option<CommitListener>) {
+        self.listener = listener;
+    }
+
     pub fn purge_strategy(&self) -> &PurgeStrategy {
         &self.strategy
     }
@@ -90,7 +146,11 @@ impl OperationLog {
                 &self.identity_pubkey,
             ],
         )?;
+        record_chain_link(tx, op)?;
 
+        if let Some(listener) = &self.listener {
+            listener();
+        }
         Ok(())
     }
 
@@ -257,6 +317,9 @@ impl OperationLog {
             Operation::RemoveAttachment { .. } => "RemoveAttachment",
             Operation::RegisterDevice { .. } => "RegisterDevice",
             Operation::SetChecked { .. } => "SetChecked",
+            Operation::RotateIdentityKey { .. } => "RotateIdentityKey",
+            Operation::RevokeDevice { .. } => "RevokeDevice",
+            Operation::SetRootRecovery { .. } => "SetRootRecovery",
         }
     }
 
@@ -352,6 +415,10 @@ mod tests {
                 title: format!("Note {}", i),
                 fields: BTreeMap::new(),
                 created_by: String::new(),
+                created_at: None,
+                modified_at: None,
+                original_author: None,
+                prev_hash: None,
                 signature: String::new(),
             };
             log.log(&tx, &op).unwrap();
@@ -389,6 +456,10 @@ mod tests {
                 title: "My Note".to_string(),
                 fields: BTreeMap::new(),
                 created_by: String::new(),
+                created_at: None,
+                modified_at: None,
+                original_author: None,
+                prev_hash: None,
                 signature: String::new(),
             };
             log.log(&tx, &op1).unwrap();
@@ -404,6 +475,7 @@ mod tests {
                 load_order: 0,
                 enabled: true,
                 created_by: String::new(),
+                prev_hash: None,
                 signature: String::new(),
             };
             log.log(&tx, &op2).unwrap();
@@ -457,6 +529,10 @@ mod tests {
                     title: format!("Note {}", i),
                     fields: BTreeMap::new(),
                     created_by: String::new(),
+                    created_at: None,
+                    modified_at: None,
+                    original_author: None,
+                    prev_hash: None,
                     signature: String::new(),
                 };
                 log.log(&tx, &op).unwrap();
@@ -493,6 +569,10 @@ mod tests {
             title: "Verified Note".to_string(),
             fields: BTreeMap::new(),
             created_by: String::new(),
+            created_at: None,
+            modified_at: None,
+            original_author: None,
+            prev_hash: None,
             signature: String::new(),
         };
         log.log(&tx, &op).unwrap();
@@ -527,6 +607,10 @@ mod tests {
             title: "Empty Identity Note".to_string(),
             fields: BTreeMap::new(),
             created_by: String::new(),
+            created_at: None,
+            modified_at: None,
+            original_author: None,
+            prev_hash: None,
             signature: String::new(),
         };
         log.log(&tx, &op).unwrap();
@@ -18,6 +18,7 @@ fn test_set_permission_roundtrip() {
         user_id: "pubkey_b64".to_string(),
         role: "writer".to_string(),
         granted_by: "grantor_b64".to_string(),
+        prev_hash: None,
         signature: "sig_b64".to_string(),
     };
     let json = serde_json::to_string(&op).unwrap();
@@ -34,6 +35,7 @@ fn test_revoke_permission_roundtrip() {
         note_id: Some("note1".to_string()),
         user_id: "pubkey_b64".to_string(),
         revoked_by: "revoker_b64".to_string(),
+        prev_hash: None,
         signature: "sig_b64".to_string(),
     };
     let json = serde_json::to_string(&op).unwrap();
@@ -49,6 +51,7 @@ fn test_remove_peer_roundtrip() {
         device_id: "dev1".to_string(),
         user_id: "bob_pubkey".to_string(),
         removed_by: "alice_pubkey".to_string(),
+        prev_hash: None,
         signature: "sig".to_string(),
     };
     let json = serde_json::to_string(&op).unwrap();
@@ -65,9 +68,13 @@ fn test_transfer_root_ownership_roundtrip() {
         device_id: "dev1".to_string(),
         new_owner: "bob_pubkey".to_string(),
         transferred_by: "alice_pubkey".to_string(),
+        recovery_signature: None,
+        prev_hash: None,
         signature: "sig".to_string(),
     };
     let json = serde_json::to_string(&op).unwrap();
+    // Owner-signed transfers serialise (and so sign) as before recovery existed.
+    assert!(!json.contains("recovery_signature"));
     let back: Operation = serde_json::from_str(&json).unwrap();
     assert_eq!(back.operation_id(), "op-tro1");
     assert_eq!(back.author_key(), "alice_pubkey");
@@ -82,6 +89,7 @@ fn test_join_workspace_roundtrip() {
         identity_public_key: "pubkey_b64".to_string(),
         declared_name: "Alice".to_string(),
         pairing_token: "token_b64".to_string(),
+        prev_hash: None,
         signature: "sig_b64".to_string(),
     };
     let json = serde_json::to_string(&op).unwrap();
@@ -133,6 +141,10 @@ fn test_operation_serialization() {
         title: "Test".to_string(),
         fields: BTreeMap::new(),
         created_by: String::new(),
+        created_at: None,
+        modified_at: None,
+        original_author: None,
+        prev_hash: None,
         signature: String::new(),
     };
 
@@ -157,6 +169,7 @@ fn test_sign_and_verify() {
         field: "body".to_string(),
         value: crate::FieldValue::Text("hello".to_string()),
         modified_by: String::new(),
+        prev_hash: None,
         signature: String::new(),
     };
 
@@ -212,6 +225,10 @@ fn test_create_note_sign_and_verify_multi_field() {
         title: "Multi-field note".to_string(),
         fields,
         created_by: String::new(),
+        created_at: None,
+        modified_at: None,
+        original_author: None,
+        prev_hash: None,
         signature: String::new(),
     };
 
@@ -245,6 +262,7 @@ fn test_update_note_variant() {
         note_id: "note-42".to_string(),
         title: "New Title".to_string(),
         modified_by: String::new(),
+        prev_hash: None,
         signature: String::new(),
     };
 
@@ -263,6 +281,7 @@ fn test_set_tags_variant() {
         note_id: "note-7".to_string(),
         tags: vec!["rust".to_string(), "crdt".to_string()],
         modified_by: String::new(),
+        prev_hash: None,
         signature: String::new(),
     };
 
@@ -294,6 +313,7 @@ fn test_add_attachment_sign_and_verify() {
         size_bytes: 1024,
         hash_sha256: "abc123".to_string(),
         added_by: String::new(),
+        prev_hash: None,
         signature: String::new(),
     };
 
@@ -326,6 +346,7 @@ fn test_remove_attachment_sign_and_verify() {
         attachment_id: "att-uuid-1".to_string(),
         note_id: "note-1".to_string(),
         removed_by: String::new(),
+        prev_hash: None,
         signature: String::new(),
     };
 
@@ -357,6 +378,7 @@ fn test_attachment_op_accessors() {
         size_bytes: 100,
         hash_sha256: "hash".to_string(),
         added_by: "key123".to_string(),
+        prev_hash: None,
         signature: String::new(),
     };
 
@@ -372,6 +394,7 @@ fn test_attachment_op_accessors() {
         attachment_id: "att-2".to_string(),
         note_id: "note-2".to_string(),
         removed_by: "remkey456".to_string(),
+        prev_hash: None,
         signature: String::new(),
     };
 
@@ -379,3 +402,34 @@ fn test_attachment_op_accessors() {
     assert_eq!(op2.device_id(), "dev-rem");
     assert_eq!(op2.author_key(), "remkey456");
 }
+
+#[test]
+fn test_prev_hash_is_signed_and_optional() {
+    use ed25519_dalek::SigningKey;
+
+    let signing_key = SigningKey::generate(&mut rand_core::OsRng);
+    let verifying_key = signing_key.verifying_key();
+    let mut first = Operation::SetTags {
+        operation_id: "op-tags-1".to_string(),
+        timestamp: dummy_timestamp(),
+        device_id: "dev-1".to_string(),
+        note_id: "note-1".to_string(),
+        tags: vec!["a".to_string()],
+        modified_by: String::new(),
+        prev_hash: None,
+        signature: String::new(),
+    };
+    first.sign(&signing_key);
+    // Unchained operations serialise (and so sign) as before hash chains existed.
+    assert!(!serde_json::to_string(&first).unwrap().contains("prev_hash"));
+
+    let mut second = first.clone();
+    second.set_prev_hash(Some(first.chain_hash()));
+    second.sign(&signing_key);
+    assert_eq!(second.prev_hash(), Some(first.chain_hash().as_str()));
+    assert_ne!(second.chain_hash(), first.chain_hash());
+    assert!(second.verify(&verifying_key));
+
+    second.set_prev_hash(Some("0".repeat(64)));
+    assert!(!second.verify(&verifying_key));
+}
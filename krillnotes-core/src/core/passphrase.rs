@@ -0,0 +1,246 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Entropy-based passphrase strength estimation.
+//!
+//! [`estimate_passphrase_strength`] guesses how many bits of entropy an
+//! attacker has to search through. Words from the BIP-39 English list count
+//! 11 bits each, since a word-list attack guesses whole words; other text
+//! counts the bits of its character pool per character, with repeated and
+//! sequential characters counting one bit. The core library does not reject
+//! weak passphrases; apps compare the estimate against
+//! [`MIN_PASSPHRASE_STRENGTH`].
+
+use serde::Serialize;
+
+/// Bits per word of the 2048-word BIP-39 list.
+const WORD_BITS: f64 = 11.0;
+
+/// Bits counted for a repeated or sequential character.
+const PATTERN_BITS: f64 = 1.0;
+
+/// Passphrases shorter than this get a [`PassphraseHint::TooShort`] hint.
+const RECOMMENDED_LENGTH: usize = 12;
+
+/// Passphrases attackers try first. Matching one (ignoring case) scores zero.
+const COMMON_PASSPHRASES: &[&str] = &[
+    "password",
+    "passw0rd",
+    "password1",
+    "123456",
+    "12345678",
+    "123456789",
+    "1234567890",
+    "qwerty",
+    "qwertyuiop",
+    "letmein",
+    "welcome",
+    "iloveyou",
+    "admin",
+    "monkey",
+    "dragon",
+    "sunshine",
+    "princess",
+    "football",
+    "baseball",
+    "trustno1",
+    "krillnotes",
+];
+
+/// The weakest passphrase apps should accept for an identity.
+pub const MIN_PASSPHRASE_STRENGTH: PassphraseStrength = PassphraseStrength::Fair;
+
+/// Strength band of a passphrase, weakest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PassphraseStrength {
+    VeryWeak,
+    Weak,
+    Fair,
+    Strong,
+    VeryStrong,
+}
+
+impl PassphraseStrength {
+    fn from_bits(bits: f64) -> Self {
+        match bits {
+            b if b < 28.0 => Self::VeryWeak,
+            b if b < 36.0 => Self::Weak,
+            b if b < 60.0 => Self::Fair,
+            b if b < 80.0 => Self::Strong,
+            _ => Self::VeryStrong,
+        }
+    }
+}
+
+/// A suggestion for making a passphrase stronger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PassphraseHint {
+    /// Shorter than [`RECOMMENDED_LENGTH`] characters.
+    TooShort,
+    /// Uses fewer than three of lowercase, uppercase, digits and symbols,
+    /// and is not made of words.
+    AddCharacterClasses,
+    /// Contains runs of the same character.
+    AvoidRepeats,
+    /// Contains runs like `abc` or `321`.
+    AvoidSequences,
+    /// Is one of the passphrases attackers try first.
+    Common,
+}
+
+/// Result of [`estimate_passphrase_strength`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PassphraseEstimate {
+    pub entropy_bits: f64,
+    pub strength: PassphraseStrength,
+    /// True if `strength` is at least [`MIN_PASSPHRASE_STRENGTH`].
+    pub acceptable: bool,
+    pub hints: Vec<PassphraseHint>,
+}
+
+/// Estimates the entropy of `passphrase`.
+pub fn estimate_passphrase_strength(passphrase: &str) -> PassphraseEstimate {
+    let mut hints = Vec::new();
+    let entropy_bits = if COMMON_PASSPHRASES.contains(&passphrase.to_lowercase().as_str()) {
+        hints.push(PassphraseHint::Common);
+        0.0
+    } else {
+        let pool_bits = f64::from(pool_size(passphrase)).log2();
+        let mut bits = 0.0;
+        let mut all_words = true;
+        for token in passphrase.split_whitespace() {
+            if bip39::Language::English
+                .find_word(&token.to_lowercase())
+                .is_some()
+            {
+                bits += WORD_BITS;
+            } else {
+                all_words = false;
+                bits += token_bits(token, pool_bits, &mut hints);
+            }
+        }
+        if !all_words && char_classes(passphrase) < 3 {
+            hints.push(PassphraseHint::AddCharacterClasses);
+        }
+        bits
+    };
+    if passphrase.chars().count() < RECOMMENDED_LENGTH {
+        hints.push(PassphraseHint::TooShort);
+    }
+
+    let strength = PassphraseStrength::from_bits(entropy_bits);
+    PassphraseEstimate {
+        entropy_bits,
+        strength,
+        acceptable: strength >= MIN_PASSPHRASE_STRENGTH,
+        hints,
+    }
+}
+
+/// Bits of one non-word token, noting repeat and sequence hints.
+fn token_bits(token: &str, pool_bits: f64, hints: &mut Vec<PassphraseHint>) -> f64 {
+    let chars: Vec<u32> = token.chars().map(u32::from).collect();
+    let mut bits = 0.0;
+    for (i, &c) in chars.iter().enumerate() {
+        let repeat = i >= 1 && chars[i - 1] == c;
+        let sequence = i >= 2 && {
+            let step = i64::from(c) - i64::from(chars[i - 1]);
+            step.abs() == 1 && i64::from(chars[i - 1]) - i64::from(chars[i - 2]) == step
+        };
+        if repeat {
+            push_once(hints, PassphraseHint::AvoidRepeats);
+        }
+        if sequence {
+            push_once(hints, PassphraseHint::AvoidSequences);
+        }
+        bits += if repeat || sequence {
+            PATTERN_BITS
+        } else {
+            pool_bits
+        };
+    }
+    bits
+}
+
+fn push_once(hints: &mut Vec<PassphraseHint>, hint: PassphraseHint) {
+    if !hints.contains(&hint) {
+        hints.push(hint);
+    }
+}
+
+/// Size of the character pool an attacker would brute-force `passphrase` in.
+fn pool_size(passphrase: &str) -> u32 {
+    let mut pool = 0;
+    if passphrase.chars().any(|c| c.is_ascii_lowercase()) {
+        pool += 26;
+    }
+    if passphrase.chars().any(|c| c.is_ascii_uppercase()) {
+        pool += 26;
+    }
+    if passphrase.chars().any(|c| c.is_ascii_digit()) {
+        pool += 10;
+    }
+    if passphrase
+        .chars()
+        .any(|c| c.is_ascii_punctuation() || c == ' ')
+    {
+        pool += 33;
+    }
+    if !passphrase.is_ascii() {
+        pool += 100;
+    }
+    pool.max(1)
+}
+
+/// How many of lowercase, uppercase, digits and symbols appear.
+fn char_classes(passphrase: &str) -> usize {
+    [
+        passphrase.chars().any(|c| c.is_ascii_lowercase()),
+        passphrase.chars().any(|c| c.is_ascii_uppercase()),
+        passphrase.chars().any(|c| c.is_ascii_digit()),
+        passphrase.chars().any(|c| !c.is_ascii_alphanumeric()),
+    ]
+    .iter()
+    .filter(|&&present| present)
+    .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_rank_passphrases_sensibly() {
+        let common = estimate_passphrase_strength("Password");
+        assert_eq!(common.strength, PassphraseStrength::VeryWeak);
+        assert!(!common.acceptable);
+        assert!(common.hints.contains(&PassphraseHint::Common));
+
+        let patterned = estimate_passphrase_strength("aaaaaa123456");
+        assert_eq!(patterned.strength, PassphraseStrength::VeryWeak);
+        assert!(patterned.hints.contains(&PassphraseHint::AvoidRepeats));
+        assert!(patterned.hints.contains(&PassphraseHint::AvoidSequences));
+
+        let short = estimate_passphrase_strength("k7#Qp");
+        assert!(short.hints.contains(&PassphraseHint::TooShort));
+        assert!(!short.acceptable);
+
+        let words = estimate_passphrase_strength("orbit canal tissue velvet");
+        assert_eq!(words.entropy_bits, 4.0 * WORD_BITS);
+        assert_eq!(words.strength, PassphraseStrength::Fair);
+        assert!(words.acceptable && words.hints.is_empty());
+
+        let six_words = estimate_passphrase_strength("orbit canal tissue velvet admit ripple");
+        assert_eq!(six_words.strength, PassphraseStrength::Strong);
+
+        let random = estimate_passphrase_strength("v9#Lq2!xTz$8wKp@");
+        assert_eq!(random.strength, PassphraseStrength::VeryStrong);
+        assert!(random.hints.is_empty());
+    }
+}
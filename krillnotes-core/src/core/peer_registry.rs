@@ -11,7 +11,7 @@
 //! (matching `peer_identity_id` to a `Contact.public_key`).
 
 use chrono::Utc;
-use rusqlite::Connection;
+use rusqlite::{Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
 
 use crate::Result;
@@ -328,6 +328,20 @@ impl<'a> PeerRegistry<'a> {
         let (channel_type, channel_params) =
             existing_channel.unwrap_or_else(|| ("manual".to_string(), "{}".to_string()));
 
+        // Carry forward the scope last delivered to this identity.
+        let existing_sent_scope: Option<String> = {
+            let mut stmt = self.conn.prepare(
+                "SELECT sent_scope FROM sync_peers \
+                 WHERE peer_identity_id = ?1 AND sent_scope IS NOT NULL \
+                 LIMIT 1",
+            )?;
+            match stmt.query_row([peer_identity_id], |row| row.get::<_, String>(0)) {
+                Ok(v) => Some(v),
+                Err(rusqlite::Error::QueryReturnedNoRows) => None,
+                Err(e) => return Err(crate::KrillnotesError::Database(e)),
+            }
+        };
+
         // Drop all placeholder / stale rows for this identity, then insert one clean row.
         self.conn.execute(
             "DELETE FROM sync_peers WHERE peer_identity_id = ?1",
@@ -336,8 +350,8 @@ impl<'a> PeerRegistry<'a> {
         self.conn.execute(
             "INSERT INTO sync_peers \
                  (peer_device_id, peer_identity_id, last_sent_op, last_received_op, last_sync, \
-                  channel_type, channel_params) \
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                  channel_type, channel_params, sent_scope) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
             rusqlite::params![
                 real_device_id,
                 peer_identity_id,
@@ -346,6 +360,7 @@ impl<'a> PeerRegistry<'a> {
                 now,
                 channel_type,
                 channel_params,
+                existing_sent_scope,
             ],
         )?;
         Ok(())
@@ -405,6 +420,43 @@ impl<'a> PeerRegistry<'a> {
         Ok(())
     }
 
+    /// The read scope (JSON) last delivered to a peer, or `None` if the peer
+    /// has only ever received unfiltered bundles.
+    pub fn get_sent_scope(&self, peer_device_id: &str) -> Result<Option<String>> {
+        let scope = self
+            .conn
+            .query_row(
+                "SELECT sent_scope FROM sync_peers WHERE peer_device_id = ?1",
+                [peer_device_id],
+                |row| row.get::<_, Option<String>>(0),
+            )
+            .optional()?
+            .flatten();
+        Ok(scope)
+    }
+
+    /// Record the read scope (JSON) delivered to a peer; `None` clears it.
+    pub fn set_sent_scope(&self, peer_device_id: &str, scope_json: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE sync_peers SET sent_scope = ?1 WHERE peer_device_id = ?2",
+            rusqlite::params![scope_json, peer_device_id],
+        )?;
+        Ok(())
+    }
+
+    /// Record the read scope (JSON) delivered to every device of an identity.
+    pub fn set_sent_scope_by_identity(
+        &self,
+        peer_identity_id: &str,
+        scope_json: Option<&str>,
+    ) -> Result<()> {
+        self.conn.execute(
+            "UPDATE sync_peers SET sent_scope = ?1 WHERE peer_identity_id = ?2",
+            rusqlite::params![scope_json, peer_identity_id],
+        )?;
+        Ok(())
+    }
+
     /// Remove a peer from the registry.
     pub fn remove_peer(&self, peer_device_id: &str) -> Result<()> {
         self.conn.execute(
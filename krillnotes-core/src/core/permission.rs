@@ -33,13 +33,16 @@ pub trait PermissionGate: Send + Sync {
     /// to the database.
     ///
     /// `actor` is the base64-encoded Ed25519 public key of the identity
-    /// performing the operation.
+    /// performing the operation. `actor_predecessors` are the keys the
+    /// identity held before rotating to `actor`; what
+    /// they authored counts as the actor's own.
     ///
     /// Returns `Ok(())` if permitted, `Err(PermissionError)` if denied.
     fn authorize(
         &self,
         conn: &Connection,
         actor: &str,
+        actor_predecessors: &[String],
         operation: &Operation,
     ) -> Result<(), PermissionError>;
 
@@ -115,6 +118,7 @@ impl PermissionGate for AllowAllGate {
         &self,
         _conn: &Connection,
         _actor: &str,
+        _actor_predecessors: &[String],
         _operation: &Operation,
     ) -> Result<(), PermissionError> {
         Ok(())
@@ -0,0 +1,230 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Social recovery of workspace Root Ownership with Shamir secret sharing.
+//!
+//! The Root Owner generates a recovery key, announces its public half in an
+//! `Operation::SetRootRecovery` and splits its seed into
+//! [`RecoveryShare`]s for trusted contacts, any `threshold` of which rebuild
+//! it. The rebuilt key signs a `TransferRootOwnership` to a new owner key,
+//! which peers accept in place of the outgoing owner's own signature.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use ed25519_dalek::{Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sharks::{Share, Sharks};
+use zeroize::Zeroize;
+
+use crate::core::error::{KrillnotesError, Result};
+
+/// One contact's share of a workspace recovery key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryShare {
+    /// Workspace whose Root Ownership the share helps recover.
+    pub workspace_id: String,
+    /// Base64 Ed25519 public key of the Root Owner who split the key.
+    pub owner_key: String,
+    /// Base64 Ed25519 public key the shares rebuild the private half of.
+    pub recovery_key: String,
+    /// Number of shares needed to rebuild the key.
+    pub threshold: u8,
+    /// Number of shares handed out.
+    pub share_count: u8,
+    /// Base64 Shamir share (x coordinate followed by the y bytes).
+    pub share: String,
+    /// Base64 Ed25519 signature by `owner_key` over the share with `signature = ""`.
+    pub signature: String,
+}
+
+impl RecoveryShare {
+    /// Verifies the owner's signature.
+    pub fn verify(&self) -> bool {
+        let Some(vk) = decode_verifying_key(&self.owner_key) else {
+            return false;
+        };
+        let mut unsigned = self.clone();
+        unsigned.signature = String::new();
+        let payload = serde_json::to_string(&unsigned).expect("RecoveryShare must be serializable");
+        verify_signature(&vk, payload.as_bytes(), &self.signature)
+    }
+}
+
+/// Generates a recovery key for `workspace_id` and splits its seed into
+/// `share_count` shares signed by `owner`, any `threshold` of which rebuild it.
+///
+/// Returns the base64 public recovery key and the shares.
+pub fn split_recovery_key(
+    owner: &SigningKey,
+    workspace_id: &str,
+    threshold: u8,
+    share_count: u8,
+) -> Result<(String, Vec<RecoveryShare>)> {
+    if threshold < 2 || threshold > share_count {
+        return Err(KrillnotesError::InvalidRecoveryShares(format!(
+            "threshold must be between 2 and the number of shares ({share_count}), got {threshold}"
+        )));
+    }
+    let recovery = SigningKey::generate(&mut rand_core::OsRng);
+    let recovery_key = BASE64.encode(recovery.verifying_key().as_bytes());
+    let owner_key = BASE64.encode(owner.verifying_key().as_bytes());
+    let mut seed = recovery.to_bytes();
+    let shares = Sharks(threshold)
+        .dealer(&seed)
+        .take(share_count as usize)
+        .map(|share| {
+            let mut share = RecoveryShare {
+                workspace_id: workspace_id.to_string(),
+                owner_key: owner_key.clone(),
+                recovery_key: recovery_key.clone(),
+                threshold,
+                share_count,
+                share: BASE64.encode(Vec::from(&share)),
+                signature: String::new(),
+            };
+            let payload =
+                serde_json::to_string(&share).expect("RecoveryShare must be serializable");
+            share.signature = BASE64.encode(owner.sign(payload.as_bytes()).to_bytes());
+            share
+        })
+        .collect();
+    seed.zeroize();
+    Ok((recovery_key, shares))
+}
+
+/// Rebuilds the recovery key from at least `threshold` shares of the same split.
+pub fn combine_recovery_shares(shares: &[RecoveryShare]) -> Result<SigningKey> {
+    let invalid = |msg: &str| KrillnotesError::InvalidRecoveryShares(msg.to_string());
+    let first = shares.first().ok_or_else(|| invalid("no shares given"))?;
+    if shares.iter().any(|s| !s.verify()) {
+        return Err(invalid("a share is not signed by its Root Owner"));
+    }
+    if shares.iter().any(|s| {
+        s.workspace_id != first.workspace_id
+            || s.owner_key != first.owner_key
+            || s.recovery_key != first.recovery_key
+            || s.threshold != first.threshold
+    }) {
+        return Err(invalid("shares come from different recovery setups"));
+    }
+    let parsed = shares
+        .iter()
+        .map(|s| {
+            BASE64
+                .decode(&s.share)
+                .ok()
+                .and_then(|bytes| Share::try_from(bytes.as_slice()).ok())
+                .ok_or_else(|| invalid("malformed share"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut seed = Sharks(first.threshold)
+        .recover(&parsed)
+        .map_err(|_| invalid("not enough distinct shares"))?;
+    let key = <[u8; 32]>::try_from(seed.as_slice())
+        .map(|arr| SigningKey::from_bytes(&arr))
+        .map_err(|_| invalid("recovered secret has the wrong length"));
+    seed.zeroize();
+    let key = key?;
+    if BASE64.encode(key.verifying_key().as_bytes()) != first.recovery_key {
+        return Err(invalid("shares do not rebuild the recovery key"));
+    }
+    Ok(key)
+}
+
+/// Signs the transfer of `workspace_id` Root Ownership from `old_owner` to
+/// `new_owner` with the rebuilt recovery key.
+pub fn sign_recovery_transfer(
+    recovery: &SigningKey,
+    workspace_id: &str,
+    old_owner: &str,
+    new_owner: &str,
+) -> String {
+    let message = transfer_message(workspace_id, old_owner, new_owner);
+    BASE64.encode(recovery.sign(message.as_bytes()).to_bytes())
+}
+
+/// Verifies a [`sign_recovery_transfer`] signature against the base64
+/// public `recovery_key`.
+pub fn verify_recovery_transfer(
+    recovery_key: &str,
+    workspace_id: &str,
+    old_owner: &str,
+    new_owner: &str,
+    signature: &str,
+) -> bool {
+    let Some(vk) = decode_verifying_key(recovery_key) else {
+        return false;
+    };
+    let message = transfer_message(workspace_id, old_owner, new_owner);
+    verify_signature(&vk, message.as_bytes(), signature)
+}
+
+/// Binding the outgoing owner makes a signature useless once ownership has
+/// moved on, even if the same shares are combined again.
+fn transfer_message(workspace_id: &str, old_owner: &str, new_owner: &str) -> String {
+    format!("krillnotes-root-recovery-v1:{workspace_id}:{old_owner}:{new_owner}")
+}
+
+fn decode_verifying_key(key: &str) -> Option<VerifyingKey> {
+    BASE64
+        .decode(key)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .and_then(|arr| VerifyingKey::from_bytes(&arr).ok())
+}
+
+fn verify_signature(vk: &VerifyingKey, message: &[u8], signature: &str) -> bool {
+    BASE64
+        .decode(signature)
+        .ok()
+        .and_then(|bytes| <[u8; 64]>::try_from(bytes).ok())
+        .map(|arr| ed25519_dalek::Signature::from_bytes(&arr))
+        .is_some_and(|sig| vk.verify(message, &sig).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_threshold_of_shares_rebuilds_the_key() {
+        let owner = SigningKey::from_bytes(&[1u8; 32]);
+        let (recovery_key, shares) = split_recovery_key(&owner, "ws-1", 2, 3).unwrap();
+        assert_eq!(shares.len(), 3);
+        assert!(shares.iter().all(RecoveryShare::verify));
+
+        let key = combine_recovery_shares(&shares[1..]).unwrap();
+        assert_eq!(BASE64.encode(key.verifying_key().as_bytes()), recovery_key);
+        assert!(matches!(
+            combine_recovery_shares(&shares[..1]),
+            Err(KrillnotesError::InvalidRecoveryShares(_))
+        ));
+
+        let sig = sign_recovery_transfer(&key, "ws-1", "old", "new");
+        assert!(verify_recovery_transfer(
+            &recovery_key,
+            "ws-1",
+            "old",
+            "new",
+            &sig
+        ));
+        assert!(!verify_recovery_transfer(
+            &recovery_key,
+            "ws-1",
+            "old",
+            "thief",
+            &sig
+        ));
+
+        // Shares from another split, or tampered ones, do not combine.
+        let (_, other) = split_recovery_key(&owner, "ws-1", 2, 3).unwrap();
+        assert!(combine_recovery_shares(&[shares[0].clone(), other[1].clone()]).is_err());
+        let mut tampered = shares[0].clone();
+        tampered.threshold = 1;
+        assert!(!tampered.verify());
+        assert!(split_recovery_key(&owner, "ws-1", 4, 3).is_err());
+    }
+}
@@ -92,6 +92,85 @@ CREATE TABLE IF NOT EXISTS attachments (
 );
 CREATE INDEX IF NOT EXISTS idx_attachments_note_id ON attachments(note_id);
 
+-- Attachments announced by sync whose content has not arrived yet.
+CREATE TABLE IF NOT EXISTS pending_attachments (
+    id          TEXT PRIMARY KEY,
+    note_id     TEXT NOT NULL,
+    filename    TEXT NOT NULL,
+    mime_type   TEXT,
+    size_bytes  INTEGER NOT NULL,
+    hash_sha256 TEXT NOT NULL,
+    created_at  INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_pending_attachments_hash ON pending_attachments(hash_sha256);
+
+-- Download progress per content hash; chunks live in attachments/incoming/<hash>/.
+-- requested_at: when peers were last asked for it; NULL = not yet
+CREATE TABLE IF NOT EXISTS blob_downloads (
+    hash_sha256    TEXT PRIMARY KEY,
+    size_bytes     INTEGER NOT NULL,
+    received_bytes INTEGER NOT NULL DEFAULT 0,
+    requested_at   INTEGER
+);
+
+-- Content peers have asked us for, and how much of it they have been sent.
+CREATE TABLE IF NOT EXISTS blob_requests (
+    peer_device_id TEXT NOT NULL,
+    hash_sha256    TEXT NOT NULL,
+    sent_bytes     INTEGER NOT NULL,
+    PRIMARY KEY (peer_device_id, hash_sha256)
+);
+
+-- Latest signed checkpoint of compacted operation log history, with the
+-- workspace state it stands for (CheckpointState JSON).
+CREATE TABLE IF NOT EXISTS checkpoints (
+    checkpoint_id   TEXT PRIMARY KEY,
+    checkpoint_json TEXT NOT NULL,
+    state           BLOB NOT NULL,
+    created_at      INTEGER NOT NULL
+);
+
+-- Identity keys rotated out, each with the key that succeeded it
+-- (from RotateIdentityKey operations; the first rotation of a key wins).
+CREATE TABLE IF NOT EXISTS key_successions (
+    old_key       TEXT PRIMARY KEY,
+    new_key       TEXT NOT NULL,
+    rotated_at_ms INTEGER NOT NULL,
+    operation_id  TEXT NOT NULL
+);
+
+-- Devices revoked by their identity (from RevokeDevice operations).
+-- Operations from a revoked device stamped after the revocation are rejected.
+CREATE TABLE IF NOT EXISTS revoked_devices (
+    device_id          TEXT NOT NULL,
+    revoked_by         TEXT NOT NULL,
+    revoked_at_wall_ms INTEGER NOT NULL,
+    revoked_at_counter INTEGER NOT NULL,
+    revoked_at_node_id INTEGER NOT NULL,
+    operation_id       TEXT NOT NULL,
+    PRIMARY KEY (device_id, revoked_by)
+);
+
+-- Hash chain links of signed operations, one chain per author and device.
+-- Outlives log purges so later operations can still be linked.
+CREATE TABLE IF NOT EXISTS op_chain (
+    operation_id TEXT PRIMARY KEY,
+    author_key   TEXT NOT NULL,
+    device_id    TEXT NOT NULL,
+    hash         TEXT NOT NULL,
+    prev_hash    TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_op_chain_author ON op_chain(author_key, device_id);
+
+-- Chain tails of applied checkpoints: hashes of compacted operations that
+-- later operations continue from, though they never reached this peer.
+CREATE TABLE IF NOT EXISTS checkpoint_chain_tails (
+    author_key TEXT NOT NULL,
+    device_id  TEXT NOT NULL,
+    hash       TEXT NOT NULL,
+    PRIMARY KEY (author_key, device_id, hash)
+);
+
 -- Sync peers: devices we directly exchange .swarm bundles with.
 -- Display name is resolved via the contact record (peer_identity_id = public key).
 CREATE TABLE IF NOT EXISTS sync_peers (
@@ -104,7 +183,9 @@ CREATE TABLE IF NOT EXISTS sync_peers (
     channel_params   TEXT NOT NULL DEFAULT '{}',
     sync_status      TEXT NOT NULL DEFAULT 'idle',
     sync_status_detail TEXT,
-    last_sync_error  TEXT
+    last_sync_error  TEXT,
+    -- JSON ReadScope last delivered to this peer; NULL = unfiltered / unknown
+    sent_scope       TEXT
 );
 
 -- HLC covering index for delta generation and operations_since queries
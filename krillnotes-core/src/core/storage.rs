@@ -480,6 +480,98 @@ impl Storage {
             )?;
         }
 
+        // Migration: add sent_scope column to sync_peers (scope-filtered deltas).
+        let sent_scope_exists: bool = conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('sync_peers') WHERE name='sent_scope'",
+            [],
+            |row| row.get::<_, i64>(0).map(|c| c > 0),
+        )?;
+        if !sent_scope_exists {
+            conn.execute("ALTER TABLE sync_peers ADD COLUMN sent_scope TEXT", [])?;
+        }
+
+        // Migration: attachment content transfer by hash.
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS pending_attachments (
+                id TEXT PRIMARY KEY,
+                note_id TEXT NOT NULL,
+                filename TEXT NOT NULL,
+                mime_type TEXT,
+                size_bytes INTEGER NOT NULL,
+                hash_sha256 TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_pending_attachments_hash
+                ON pending_attachments(hash_sha256);
+            CREATE TABLE IF NOT EXISTS blob_downloads (
+                hash_sha256 TEXT PRIMARY KEY,
+                size_bytes INTEGER NOT NULL,
+                received_bytes INTEGER NOT NULL DEFAULT 0,
+                requested_at INTEGER
+            );
+            CREATE TABLE IF NOT EXISTS blob_requests (
+                peer_device_id TEXT NOT NULL,
+                hash_sha256 TEXT NOT NULL,
+                sent_bytes INTEGER NOT NULL,
+                PRIMARY KEY (peer_device_id, hash_sha256)
+            )",
+        )?;
+
+        // Migration: signed checkpoints for operation log compaction.
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS checkpoints (
+                checkpoint_id TEXT PRIMARY KEY,
+                checkpoint_json TEXT NOT NULL,
+                state BLOB NOT NULL,
+                created_at INTEGER NOT NULL
+            )",
+        )?;
+
+        // Migration: identity key successions (RotateIdentityKey).
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS key_successions (
+                old_key TEXT PRIMARY KEY,
+                new_key TEXT NOT NULL,
+                rotated_at_ms INTEGER NOT NULL,
+                operation_id TEXT NOT NULL
+            )",
+        )?;
+
+        // Migration: revoked devices (RevokeDevice).
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS revoked_devices (
+                device_id TEXT NOT NULL,
+                revoked_by TEXT NOT NULL,
+                revoked_at_wall_ms INTEGER NOT NULL,
+                revoked_at_counter INTEGER NOT NULL,
+                revoked_at_node_id INTEGER NOT NULL,
+                operation_id TEXT NOT NULL,
+                PRIMARY KEY (device_id, revoked_by)
+            )",
+        )?;
+
+        // Migration: operation hash chains.
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS op_chain (
+                operation_id TEXT PRIMARY KEY,
+                author_key TEXT NOT NULL,
+                device_id TEXT NOT NULL,
+                hash TEXT NOT NULL,
+                prev_hash TEXT
+            );
+            CREATE INDEX IF NOT EXISTS idx_op_chain_author ON op_chain(author_key, device_id);",
+        )?;
+
+        // Migration: chain tails of applied checkpoints.
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS checkpoint_chain_tails (
+                author_key TEXT NOT NULL,
+                device_id TEXT NOT NULL,
+                hash TEXT NOT NULL,
+                PRIMARY KEY (author_key, device_id, hash)
+            )",
+        )?;
+
         // Migration: create sync_events table for persistent audit trail.
         conn.execute_batch(
             "CREATE TABLE IF NOT EXISTS sync_events (
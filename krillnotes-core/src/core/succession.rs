@@ -0,0 +1,127 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Signed succession statements linking a retired identity key to its successor.
+//!
+//! When an identity rotates its Ed25519 key, the old key signs a
+//! [`SuccessionRecord`] naming the new key. The record travels inside an
+//! `Operation::RotateIdentityKey` (signed by the new key), so peers can treat
+//! both keys as the same principal and stop accepting the old key's
+//! signatures after `rotated_at_ms`.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{Deserialize, Serialize};
+
+use crate::core::operation::Operation;
+
+/// A statement by `old_key` that `new_key` succeeds it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuccessionRecord {
+    /// Base64 Ed25519 public key being retired.
+    pub old_key: String,
+    /// Base64 Ed25519 public key replacing it.
+    pub new_key: String,
+    /// Unix milliseconds after which `old_key` signatures are not accepted.
+    pub rotated_at_ms: u64,
+    /// Base64 Ed25519 signature by `old_key` over the record with `signature = ""`.
+    pub signature: String,
+}
+
+impl SuccessionRecord {
+    /// Creates a record retiring `old` in favour of `new_key`, signed by `old`.
+    pub fn new(
+        old: &ed25519_dalek::SigningKey,
+        new_key: &ed25519_dalek::VerifyingKey,
+        rotated_at_ms: u64,
+    ) -> Self {
+        use ed25519_dalek::Signer;
+
+        let mut record = Self {
+            old_key: BASE64.encode(old.verifying_key().as_bytes()),
+            new_key: BASE64.encode(new_key.as_bytes()),
+            rotated_at_ms,
+            signature: String::new(),
+        };
+        let payload =
+            serde_json::to_string(&record).expect("SuccessionRecord must be serializable");
+        record.signature = BASE64.encode(old.sign(payload.as_bytes()).to_bytes());
+        record
+    }
+
+    /// Extracts the record carried by a `RotateIdentityKey` operation.
+    pub fn from_operation(op: &Operation) -> Option<Self> {
+        match op {
+            Operation::RotateIdentityKey {
+                old_key,
+                new_key,
+                rotated_at_ms,
+                succession_signature,
+                ..
+            } => Some(Self {
+                old_key: old_key.clone(),
+                new_key: new_key.clone(),
+                rotated_at_ms: *rotated_at_ms,
+                signature: succession_signature.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Verifies the signature against `old_key`.
+    pub fn verify(&self) -> bool {
+        use ed25519_dalek::Verifier;
+
+        let Some(vk) = BASE64
+            .decode(&self.old_key)
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .and_then(|arr| ed25519_dalek::VerifyingKey::from_bytes(&arr).ok())
+        else {
+            return false;
+        };
+        let Some(sig) = BASE64
+            .decode(&self.signature)
+            .ok()
+            .and_then(|bytes| <[u8; 64]>::try_from(bytes).ok())
+            .map(|arr| ed25519_dalek::Signature::from_bytes(&arr))
+        else {
+            return false;
+        };
+        let mut unsigned = self.clone();
+        unsigned.signature = String::new();
+        let payload =
+            serde_json::to_string(&unsigned).expect("SuccessionRecord must be serializable");
+        vk.verify(payload.as_bytes(), &sig).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_verify_and_tamper() {
+        let old = ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]);
+        let new = ed25519_dalek::SigningKey::from_bytes(&[2u8; 32]);
+        let record = SuccessionRecord::new(&old, &new.verifying_key(), 1_000);
+        assert!(record.verify());
+
+        let mut redirected = record.clone();
+        redirected.new_key = BASE64.encode([3u8; 32]);
+        assert!(!redirected.verify());
+
+        let mut backdated = record.clone();
+        backdated.rotated_at_ms = 1;
+        assert!(!backdated.verify());
+
+        // A record "signed" by the new key claiming to retire the old one is rejected.
+        let forged = SuccessionRecord::new(&new, &new.verifying_key(), 1_000);
+        let mut forged_for_old = forged.clone();
+        forged_for_old.old_key = record.old_key.clone();
+        assert!(!forged_for_old.verify());
+    }
+}
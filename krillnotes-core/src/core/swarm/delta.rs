@@ -15,6 +15,7 @@ use ed25519_dalek::{SigningKey, VerifyingKey};
 use std::io::{Cursor, Read, Write};
 use zip::{write::SimpleFileOptions, ZipArchive, ZipWriter};
 
+use crate::core::checkpoint::Checkpoint;
 use crate::core::operation::Operation;
 use crate::core::swarm::crypto::{
     decrypt_blob, decrypt_payload_with_key, encrypt_blob, encrypt_for_recipients_with_key,
@@ -34,8 +35,58 @@ pub struct DeltaOperation {
     pub op: Operation,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub verified_by: Option<String>,
+    /// Synthetic operation moving a note across the recipient's read scope
+    /// (see `Workspace::scope_operations`). Applied to the working tables
+    /// only — never logged, relayed or acknowledged.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub scope_boundary: bool,
 }
 
+/// A request for attachment content the sender does not hold yet, by the
+/// SHA-256 of its plaintext. `offset` is how many bytes have already arrived,
+/// so an interrupted transfer resumes instead of restarting.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlobWant {
+    pub hash_sha256: String,
+    pub offset: u64,
+}
+
+/// A slice of attachment content answering a [`BlobWant`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlobChunk {
+    pub hash_sha256: String,
+    pub offset: u64,
+    /// Plaintext size of the whole attachment.
+    pub total_size: u64,
+    pub data: Vec<u8>,
+}
+
+/// Encrypted `blobs/index.enc` entry: the wants plus the metadata of each
+/// `blobs/<n>.enc` chunk, in order.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlobIndex {
+    #[serde(default)]
+    wants: Vec<BlobWant>,
+    #[serde(default)]
+    chunks: Vec<BlobChunkInfo>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlobChunkInfo {
+    hash_sha256: String,
+    offset: u64,
+    total_size: u64,
+}
+
+const BLOB_INDEX: &str = "blobs/index.enc";
+const BLOB_SIGNATURE: &str = "blobs/signature.bin";
+const CHECKPOINT_RECORD: &str = "checkpoint/record.enc";
+const CHECKPOINT_STATE: &str = "checkpoint/state.enc";
+const CHECKPOINT_SIGNATURE: &str = "checkpoint/signature.bin";
+
 pub struct DeltaParams<'a> {
     pub protocol: String,
     pub workspace_id: String,
@@ -58,6 +109,13 @@ pub struct DeltaParams<'a> {
     /// Plaintext attachment bytes keyed by attachment_id.
     /// Each blob corresponds to an AddAttachment operation in the batch.
     pub attachment_blobs: Vec<(String, Vec<u8>)>,
+    /// Attachment content we are asking the recipient for.
+    pub blob_wants: Vec<BlobWant>,
+    /// Attachment content the recipient asked us for.
+    pub blob_chunks: Vec<BlobChunk>,
+    /// Checkpoint and serialized state standing in for compacted history
+    /// the recipient has not seen.
+    pub checkpoint: Option<(Checkpoint, Vec<u8>)>,
 }
 
 pub struct ParsedDelta {
@@ -73,6 +131,10 @@ pub struct ParsedDelta {
     pub ack_operation_id: Option<String>,
     /// Decrypted attachment blobs from the delta bundle sidecar files.
     pub attachment_blobs: Vec<(String, Vec<u8>)>,
+    pub blob_wants: Vec<BlobWant>,
+    pub blob_chunks: Vec<BlobChunk>,
+    /// Checkpoint and serialized state, not yet verified against each other.
+    pub checkpoint: Option<(Checkpoint, Vec<u8>)>,
 }
 
 /// Generate a delta.swarm bundle.
@@ -110,7 +172,7 @@ pub fn create_delta_bundle(params: DeltaParams<'_>) -> Result<Vec<u8>> {
         target_peer: Some(params.recipient_identity_id),
         ack_operation_id: params.ack_operation_id.clone(),
         recipients: Some(entries),
-        has_attachments: !params.attachment_blobs.is_empty(),
+        has_attachments: !params.attachment_blobs.is_empty() || !params.blob_chunks.is_empty(),
         owner_pubkey: Some(params.owner_pubkey.clone()),
     };
     header.validate()?;
@@ -139,6 +201,41 @@ pub fn create_delta_bundle(params: DeltaParams<'_>) -> Result<Vec<u8>> {
     }
     let sig = sign_manifest(&files, params.sender_key);
 
+    // Blob traffic travels in `blobs/` entries under a second signature, so
+    // readers that predate it still verify the bundle and simply skip them.
+    let blob_entries = encrypt_blob_entries(&sym_key, &params.blob_wants, &params.blob_chunks)?;
+    let blob_sig = if blob_entries.is_empty() {
+        None
+    } else {
+        let mut blob_files: Vec<(&str, &[u8])> =
+            vec![("header.json", &header_bytes), ("payload.enc", &ciphertext)];
+        blob_files.extend(
+            blob_entries
+                .iter()
+                .map(|(n, ct)| (n.as_str(), ct.as_slice())),
+        );
+        Some(sign_manifest(&blob_files, params.sender_key))
+    };
+    // Likewise for a checkpoint.
+    let checkpoint_entries = match &params.checkpoint {
+        Some((checkpoint, state)) => vec![
+            (
+                CHECKPOINT_RECORD,
+                encrypt_blob(&sym_key, &serde_json::to_vec(checkpoint)?)?,
+            ),
+            (CHECKPOINT_STATE, encrypt_blob(&sym_key, state)?),
+        ],
+        None => Vec::new(),
+    };
+    let checkpoint_sig = if checkpoint_entries.is_empty() {
+        None
+    } else {
+        let mut checkpoint_files: Vec<(&str, &[u8])> =
+            vec![("header.json", &header_bytes), ("payload.enc", &ciphertext)];
+        checkpoint_files.extend(checkpoint_entries.iter().map(|(n, ct)| (*n, ct.as_slice())));
+        Some(sign_manifest(&checkpoint_files, params.sender_key))
+    };
+
     let mut buf = Vec::new();
     {
         let cursor = Cursor::new(&mut buf);
@@ -154,6 +251,22 @@ pub fn create_delta_bundle(params: DeltaParams<'_>) -> Result<Vec<u8>> {
             zip.start_file(format!("attachments/{att_id}.enc"), opts)?;
             zip.write_all(ct)?;
         }
+        for (name, ct) in &blob_entries {
+            zip.start_file(name.as_str(), opts)?;
+            zip.write_all(ct)?;
+        }
+        if let Some(blob_sig) = blob_sig {
+            zip.start_file(BLOB_SIGNATURE, opts)?;
+            zip.write_all(&blob_sig)?;
+        }
+        for (name, ct) in &checkpoint_entries {
+            zip.start_file(*name, opts)?;
+            zip.write_all(ct)?;
+        }
+        if let Some(checkpoint_sig) = checkpoint_sig {
+            zip.start_file(CHECKPOINT_SIGNATURE, opts)?;
+            zip.write_all(&checkpoint_sig)?;
+        }
         zip.finish()?;
     }
     Ok(buf)
@@ -175,12 +288,37 @@ pub fn parse_delta_bundle(data: &[u8], recipient_key: &SigningKey) -> Result<Par
 
     // Read sidecar ciphertext BEFORE verification so we can include it in the manifest.
     let mut sidecar_entries: Vec<(String, Vec<u8>)> = Vec::new();
+    let mut blob_entries: Vec<(String, Vec<u8>)> = Vec::new();
+    let mut blob_sig: Option<Vec<u8>> = None;
+    let mut checkpoint_entries: Vec<(String, Vec<u8>)> = Vec::new();
+    let mut checkpoint_sig: Option<Vec<u8>> = None;
     for i in 0..zip.len() {
         let mut file = zip
             .by_index(i)
             .map_err(|e| KrillnotesError::Swarm(format!("zip index {i}: {e}")))?;
         let name = file.name().to_string();
-        if let Some(att_id) = name
+        if name == BLOB_SIGNATURE || (name.starts_with("blobs/") && name.ends_with(".enc")) {
+            let mut data = Vec::new();
+            file.read_to_end(&mut data)
+                .map_err(|e| KrillnotesError::Swarm(format!("read {name}: {e}")))?;
+            if name == BLOB_SIGNATURE {
+                blob_sig = Some(data);
+            } else {
+                blob_entries.push((name, data));
+            }
+        } else if name == CHECKPOINT_SIGNATURE
+            || name == CHECKPOINT_RECORD
+            || name == CHECKPOINT_STATE
+        {
+            let mut data = Vec::new();
+            file.read_to_end(&mut data)
+                .map_err(|e| KrillnotesError::Swarm(format!("read {name}: {e}")))?;
+            if name == CHECKPOINT_SIGNATURE {
+                checkpoint_sig = Some(data);
+            } else {
+                checkpoint_entries.push((name, data));
+            }
+        } else if let Some(att_id) = name
             .strip_prefix("attachments/")
             .and_then(|n| n.strip_suffix(".enc"))
         {
@@ -213,6 +351,31 @@ pub fn parse_delta_bundle(data: &[u8], recipient_key: &SigningKey) -> Result<Par
         files.push((&sidecar_names[i], ct));
     }
     verify_manifest(&files, &sig_bytes, &vk)?;
+    if !blob_entries.is_empty() {
+        let blob_sig = blob_sig
+            .ok_or_else(|| KrillnotesError::Swarm("blob entries without signature".to_string()))?;
+        let mut blob_files: Vec<(&str, &[u8])> =
+            vec![("header.json", &header_bytes), ("payload.enc", &ciphertext)];
+        blob_files.extend(
+            blob_entries
+                .iter()
+                .map(|(n, ct)| (n.as_str(), ct.as_slice())),
+        );
+        verify_manifest(&blob_files, &blob_sig, &vk)?;
+    }
+    if !checkpoint_entries.is_empty() {
+        let checkpoint_sig = checkpoint_sig.ok_or_else(|| {
+            KrillnotesError::Swarm("checkpoint entries without signature".to_string())
+        })?;
+        let mut checkpoint_files: Vec<(&str, &[u8])> =
+            vec![("header.json", &header_bytes), ("payload.enc", &ciphertext)];
+        checkpoint_files.extend(
+            checkpoint_entries
+                .iter()
+                .map(|(n, ct)| (n.as_str(), ct.as_slice())),
+        );
+        verify_manifest(&checkpoint_files, &checkpoint_sig, &vk)?;
+    }
 
     // Decrypt.
     let recipients = header
@@ -242,6 +405,8 @@ pub fn parse_delta_bundle(data: &[u8], recipient_key: &SigningKey) -> Result<Par
         let pt = decrypt_blob(&sym_key, ct)?;
         attachment_blobs.push((att_id.clone(), pt));
     }
+    let (blob_wants, blob_chunks) = decrypt_blob_entries(&sym_key, &blob_entries)?;
+    let checkpoint = decrypt_checkpoint_entries(&sym_key, &checkpoint_entries)?;
 
     Ok(ParsedDelta {
         protocol,
@@ -253,9 +418,91 @@ pub fn parse_delta_bundle(data: &[u8], recipient_key: &SigningKey) -> Result<Par
         owner_pubkey: header.owner_pubkey,
         ack_operation_id: header.ack_operation_id,
         attachment_blobs,
+        blob_wants,
+        blob_chunks,
+        checkpoint,
     })
 }
 
+/// Encrypts the blob index and chunk data into `blobs/` zip entries. Returns
+/// nothing when there is no blob traffic, keeping such bundles unchanged.
+fn encrypt_blob_entries(
+    sym_key: &[u8; 32],
+    wants: &[BlobWant],
+    chunks: &[BlobChunk],
+) -> Result<Vec<(String, Vec<u8>)>> {
+    if wants.is_empty() && chunks.is_empty() {
+        return Ok(Vec::new());
+    }
+    let index = BlobIndex {
+        wants: wants.to_vec(),
+        chunks: chunks
+            .iter()
+            .map(|c| BlobChunkInfo {
+                hash_sha256: c.hash_sha256.clone(),
+                offset: c.offset,
+                total_size: c.total_size,
+            })
+            .collect(),
+    };
+    let mut entries = vec![(
+        BLOB_INDEX.to_string(),
+        encrypt_blob(sym_key, &serde_json::to_vec(&index)?)?,
+    )];
+    for (i, chunk) in chunks.iter().enumerate() {
+        entries.push((
+            format!("blobs/{i}.enc"),
+            encrypt_blob(sym_key, &chunk.data)?,
+        ));
+    }
+    Ok(entries)
+}
+
+fn decrypt_checkpoint_entries(
+    sym_key: &[u8; 32],
+    entries: &[(String, Vec<u8>)],
+) -> Result<Option<(Checkpoint, Vec<u8>)>> {
+    if entries.is_empty() {
+        return Ok(None);
+    }
+    let entry = |name: &str| {
+        entries
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, ct)| ct)
+            .ok_or_else(|| KrillnotesError::Swarm(format!("missing {name}")))
+    };
+    let checkpoint: Checkpoint =
+        serde_json::from_slice(&decrypt_blob(sym_key, entry(CHECKPOINT_RECORD)?)?)?;
+    let state = decrypt_blob(sym_key, entry(CHECKPOINT_STATE)?)?;
+    Ok(Some((checkpoint, state)))
+}
+
+fn decrypt_blob_entries(
+    sym_key: &[u8; 32],
+    entries: &[(String, Vec<u8>)],
+) -> Result<(Vec<BlobWant>, Vec<BlobChunk>)> {
+    let Some((_, index_ct)) = entries.iter().find(|(name, _)| name == BLOB_INDEX) else {
+        return Ok((Vec::new(), Vec::new()));
+    };
+    let index: BlobIndex = serde_json::from_slice(&decrypt_blob(sym_key, index_ct)?)?;
+    let mut chunks = Vec::with_capacity(index.chunks.len());
+    for (i, info) in index.chunks.into_iter().enumerate() {
+        let name = format!("blobs/{i}.enc");
+        let (_, ct) = entries
+            .iter()
+            .find(|(n, _)| *n == name)
+            .ok_or_else(|| KrillnotesError::Swarm(format!("missing {name}")))?;
+        chunks.push(BlobChunk {
+            hash_sha256: info.hash_sha256,
+            offset: info.offset,
+            total_size: info.total_size,
+            data: decrypt_blob(sym_key, ct)?,
+        });
+    }
+    Ok((index.wants, chunks))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -279,6 +526,7 @@ mod tests {
             note_id: "note-1".to_string(),
             title: "Updated".to_string(),
             modified_by: "pk".to_string(),
+            prev_hash: None,
             signature: "sig".to_string(),
         }
     }
@@ -299,10 +547,12 @@ mod tests {
                 DeltaOperation {
                     op: dummy_op("op-1"),
                     verified_by: None,
+                    scope_boundary: false,
                 },
                 DeltaOperation {
                     op: dummy_op("op-2"),
                     verified_by: Some("voucher-pk".to_string()),
+                    scope_boundary: false,
                 },
             ],
             sender_key: &sender_key,
@@ -312,6 +562,9 @@ mod tests {
             owner_pubkey: "owner-pk".to_string(),
             ack_operation_id: None,
             attachment_blobs: vec![],
+            blob_wants: vec![],
+            blob_chunks: vec![],
+            checkpoint: None,
         })
         .unwrap();
 
@@ -348,6 +601,9 @@ mod tests {
             owner_pubkey: "owner-pk".to_string(),
             ack_operation_id: None,
             attachment_blobs: vec![],
+            blob_wants: vec![],
+            blob_chunks: vec![],
+            checkpoint: None,
         })
         .unwrap();
 
@@ -378,6 +634,7 @@ mod tests {
             size_bytes: 4,
             hash_sha256: "fakehash".to_string(),
             added_by: String::new(),
+            prev_hash: None,
             signature: String::new(),
         };
         op.sign(&sender_key);
@@ -393,6 +650,7 @@ mod tests {
             delta_operations: vec![DeltaOperation {
                 op,
                 verified_by: None,
+                scope_boundary: false,
             }],
             sender_key: &sender_key,
             recipient_keys: vec![&recipient_vk],
@@ -401,6 +659,9 @@ mod tests {
             owner_pubkey: "owner-key".to_string(),
             ack_operation_id: None,
             attachment_blobs: vec![("att-uuid-1".to_string(), blob_data.clone())],
+            blob_wants: vec![],
+            blob_chunks: vec![],
+            checkpoint: None,
         };
 
         let bundle = create_delta_bundle(params).unwrap();
@@ -412,6 +673,69 @@ mod tests {
         assert_eq!(parsed.attachment_blobs[0].1, blob_data);
     }
 
+    #[test]
+    fn test_delta_blob_traffic_roundtrip_and_tamper() {
+        let sender_key = make_key();
+        let recipient_key = make_key();
+        let recipient_vk = recipient_key.verifying_key();
+        let wants = vec![BlobWant {
+            hash_sha256: "aa".repeat(32),
+            offset: 1024,
+        }];
+        let chunks = vec![BlobChunk {
+            hash_sha256: "bb".repeat(32),
+            offset: 0,
+            total_size: 6,
+            data: b"CHUNK!".to_vec(),
+        }];
+        let bundle = create_delta_bundle(DeltaParams {
+            protocol: "test".to_string(),
+            workspace_id: "ws-1".to_string(),
+            workspace_name: "Test".to_string(),
+            source_device_id: "dev-1".to_string(),
+            source_display_name: "Alice".to_string(),
+            since_operation_id: String::new(),
+            delta_operations: vec![],
+            sender_key: &sender_key,
+            recipient_keys: vec![&recipient_vk],
+            recipient_peer_ids: vec!["peer-1".to_string()],
+            recipient_identity_id: "recip-id".to_string(),
+            owner_pubkey: "owner-key".to_string(),
+            ack_operation_id: None,
+            attachment_blobs: vec![],
+            blob_wants: wants.clone(),
+            blob_chunks: chunks.clone(),
+            checkpoint: None,
+        })
+        .unwrap();
+
+        let parsed = parse_delta_bundle(&bundle, &recipient_key).unwrap();
+        assert_eq!(parsed.blob_wants, wants);
+        assert_eq!(parsed.blob_chunks, chunks);
+
+        // Flipping a byte of the chunk ciphertext breaks the blob signature.
+        let mut zip_in = ZipArchive::new(Cursor::new(&bundle)).unwrap();
+        let mut tampered = Vec::new();
+        {
+            let mut zip_out = ZipWriter::new(Cursor::new(&mut tampered));
+            for i in 0..zip_in.len() {
+                let mut file = zip_in.by_index(i).unwrap();
+                let name = file.name().to_string();
+                let mut data = Vec::new();
+                file.read_to_end(&mut data).unwrap();
+                if name == "blobs/0.enc" {
+                    data[14] ^= 0x01;
+                }
+                zip_out
+                    .start_file(&name, SimpleFileOptions::default())
+                    .unwrap();
+                zip_out.write_all(&data).unwrap();
+            }
+            zip_out.finish().unwrap();
+        }
+        assert!(parse_delta_bundle(&tampered, &recipient_key).is_err());
+    }
+
     /// Rebuild a ZIP without any attachments/*.enc entries.
     fn strip_sidecar_from_bundle(bundle: &[u8]) -> Vec<u8> {
         let cursor = Cursor::new(bundle);
@@ -458,6 +782,7 @@ mod tests {
             size_bytes: 5,
             hash_sha256: "fakehash".to_string(),
             added_by: String::new(),
+            prev_hash: None,
             signature: String::new(),
         };
         op.sign(&sender_key);
@@ -473,6 +798,7 @@ mod tests {
             delta_operations: vec![DeltaOperation {
                 op,
                 verified_by: None,
+                scope_boundary: false,
             }],
             sender_key: &sender_key,
             recipient_keys: vec![&recipient_vk],
@@ -481,6 +807,9 @@ mod tests {
             owner_pubkey: "owner-key".to_string(),
             ack_operation_id: None,
             attachment_blobs: vec![("att-strip-1".to_string(), blob_data)],
+            blob_wants: vec![],
+            blob_chunks: vec![],
+            checkpoint: None,
         })
         .unwrap();
 
@@ -520,6 +849,7 @@ mod tests {
             note_id: "note-1".to_string(),
             title: "Updated".to_string(),
             modified_by: String::new(),
+            prev_hash: None,
             signature: String::new(),
         };
         op.sign(&sender_key);
@@ -534,6 +864,7 @@ mod tests {
             delta_operations: vec![DeltaOperation {
                 op,
                 verified_by: None,
+                scope_boundary: false,
             }],
             sender_key: &sender_key,
             recipient_keys: vec![&recipient_vk],
@@ -542,6 +873,9 @@ mod tests {
             owner_pubkey: "owner-key".to_string(),
             ack_operation_id: None,
             attachment_blobs: vec![],
+            blob_wants: vec![],
+            blob_chunks: vec![],
+            checkpoint: None,
         };
 
         let bundle = create_delta_bundle(params).unwrap();
@@ -557,6 +891,7 @@ mod tests {
         let with_voucher = DeltaOperation {
             op: dummy_op("op-v1"),
             verified_by: Some("voucher-pk-base64".to_string()),
+            scope_boundary: false,
         };
         let json_with = serde_json::to_string(&with_voucher).unwrap();
         assert!(
@@ -574,6 +909,7 @@ mod tests {
         let without_voucher = DeltaOperation {
             op: dummy_op("op-v2"),
             verified_by: None,
+            scope_boundary: false,
         };
         let json_without = serde_json::to_string(&without_voucher).unwrap();
         assert!(
@@ -618,6 +954,7 @@ mod tests {
             delta_operations: vec![DeltaOperation {
                 op: op.clone(),
                 verified_by: None,
+                scope_boundary: false,
             }],
             sender_key: &key_a,
             recipient_keys: vec![&key_b.verifying_key()],
@@ -626,6 +963,9 @@ mod tests {
             owner_pubkey: pubkey_a.clone(),
             ack_operation_id: None,
             attachment_blobs: vec![],
+            blob_wants: vec![],
+            blob_chunks: vec![],
+            checkpoint: None,
         })
         .unwrap();
 
@@ -650,6 +990,7 @@ mod tests {
             delta_operations: vec![DeltaOperation {
                 op: op.clone(),
                 verified_by: Some(pubkey_b.clone()), // B vouches
+                scope_boundary: false,
             }],
             sender_key: &key_b,
             recipient_keys: vec![&key_c.verifying_key()],
@@ -658,6 +999,9 @@ mod tests {
             owner_pubkey: pubkey_a.clone(),
             ack_operation_id: None,
             attachment_blobs: vec![],
+            blob_wants: vec![],
+            blob_chunks: vec![],
+            checkpoint: None,
         })
         .unwrap();
 
@@ -10,7 +10,7 @@ use crate::KrillnotesError;
 use crate::Result;
 use serde::{Deserialize, Serialize};
 
-/// The four bundle modes.
+/// The bundle modes.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum SwarmMode {
@@ -18,6 +18,12 @@ pub enum SwarmMode {
     Accept,
     Snapshot,
     Delta,
+    /// One Root Ownership recovery share for one contact.
+    RecoveryShare,
+    /// Contact verification (SAS) messages for one contact.
+    Verification,
+    /// Vouch statements issued by the sender.
+    Vouch,
 }
 
 /// Encrypted payload key for one recipient.
@@ -114,6 +120,13 @@ impl SwarmHeader {
                     "delta",
                 )?;
             }
+            SwarmMode::RecoveryShare => {
+                require_field(self.recipients.as_ref(), "recipients", "recovery share")?;
+            }
+            SwarmMode::Verification => {
+                require_field(self.target_peer.as_ref(), "target_peer", "verification")?;
+            }
+            SwarmMode::Vouch => {}
         }
         Ok(())
     }
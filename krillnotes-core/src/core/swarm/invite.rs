@@ -130,6 +130,7 @@ pub fn create_invite_bundle(params: InviteParams<'_>) -> Result<Vec<u8>> {
         user_id: "PLACEHOLDER".to_string(),
         role: params.offered_role,
         granted_by: pubkey_b64,
+        prev_hash: None,
         signature: String::new(),
     };
 
@@ -274,6 +275,7 @@ pub fn create_accept_bundle(params: AcceptParams<'_>) -> Result<Vec<u8>> {
         identity_public_key: pubkey_b64,
         declared_name: params.declared_name,
         pairing_token: params.pairing_token,
+        prev_hash: None,
         signature: String::new(),
     };
 
@@ -10,9 +10,12 @@ pub mod crypto;
 pub mod delta;
 pub mod header;
 pub mod invite;
+pub mod recovery;
 pub mod signature;
 pub mod snapshot;
 pub mod sync;
+pub mod verification;
+pub mod vouch;
 
 #[cfg(test)]
 mod integration_tests {
@@ -44,6 +47,7 @@ mod integration_tests {
             note_id: note_id.to_string(),
             title: "Alice's edit".to_string(),
             modified_by: "pk_alice".to_string(),
+            prev_hash: None,
             signature: "sig".to_string(),
         }
     }
@@ -131,6 +135,7 @@ mod integration_tests {
             .map(|op| DeltaOperation {
                 op: op.clone(),
                 verified_by: None,
+                scope_boundary: false,
             })
             .collect();
         let delta_bundle = create_delta_bundle(DeltaParams {
@@ -148,6 +153,9 @@ mod integration_tests {
             owner_pubkey: "owner-pk-alice".to_string(),
             ack_operation_id: None,
             attachment_blobs: vec![],
+            blob_wants: vec![],
+            blob_chunks: vec![],
+            checkpoint: None,
         })
         .unwrap();
 
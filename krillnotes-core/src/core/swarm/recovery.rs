@@ -0,0 +1,195 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Recovery share bundle: one [`RecoveryShare`], encrypted for one contact.
+//!
+//! The Root Owner sends each trusted contact their share this way, and a
+//! contact releases it to the identity taking over the workspace the same way.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::Utc;
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use std::io::{Cursor, Write};
+use zip::{write::SimpleFileOptions, ZipArchive, ZipWriter};
+
+use crate::core::root_recovery::RecoveryShare;
+use crate::core::swarm::crypto::{decrypt_payload, encrypt_for_recipients};
+use crate::core::swarm::header::{SwarmHeader, SwarmMode};
+use crate::core::swarm::invite::read_zip_file;
+use crate::core::swarm::signature::{sign_manifest, verify_manifest};
+use crate::{KrillnotesError, Result};
+
+pub struct RecoveryShareParams<'a> {
+    pub protocol: String,
+    pub workspace_name: String,
+    pub source_device_id: String,
+    pub source_display_name: String,
+    pub share: &'a RecoveryShare,
+    pub sender_key: &'a SigningKey,
+    pub recipient_key: &'a VerifyingKey,
+}
+
+pub struct ParsedRecoveryShare {
+    pub workspace_id: String,
+    pub workspace_name: String,
+    pub sender_public_key: String,
+    pub sender_display_name: String,
+    pub share: RecoveryShare,
+}
+
+/// Generate a recovery share .swarm bundle.
+pub fn create_recovery_share_bundle(params: RecoveryShareParams<'_>) -> Result<Vec<u8>> {
+    let sender_b64 = BASE64.encode(params.sender_key.verifying_key().as_bytes());
+    let payload = serde_json::to_vec(params.share)?;
+    let (ciphertext, mut entries) = encrypt_for_recipients(&payload, &[params.recipient_key])?;
+    for entry in &mut entries {
+        entry.peer_id = BASE64.encode(params.recipient_key.as_bytes());
+    }
+
+    let header = SwarmHeader {
+        protocol: params.protocol,
+        format_version: 1,
+        mode: SwarmMode::RecoveryShare,
+        workspace_id: params.share.workspace_id.clone(),
+        workspace_name: params.workspace_name,
+        source_device_id: params.source_device_id,
+        source_identity: sender_b64,
+        source_display_name: params.source_display_name,
+        created_at: Utc::now().to_rfc3339(),
+        pairing_token: None,
+        offered_role: None,
+        offered_scope: None,
+        inviter_fingerprint: None,
+        accepted_identity: None,
+        accepted_display_name: None,
+        accepted_fingerprint: None,
+        as_of_operation_id: None,
+        since_operation_id: None,
+        target_peer: None,
+        ack_operation_id: None,
+        recipients: Some(entries),
+        has_attachments: false,
+        owner_pubkey: Some(params.share.owner_key.clone()),
+    };
+    header.validate()?;
+
+    let header_bytes = serde_json::to_vec(&header)?;
+    let files: Vec<(&str, &[u8])> =
+        vec![("header.json", &header_bytes), ("payload.enc", &ciphertext)];
+    let sig = sign_manifest(&files, params.sender_key);
+
+    let mut buf = Vec::new();
+    {
+        let mut zip = ZipWriter::new(Cursor::new(&mut buf));
+        let opts = SimpleFileOptions::default();
+        zip.start_file("header.json", opts)?;
+        zip.write_all(&header_bytes)?;
+        zip.start_file("payload.enc", opts)?;
+        zip.write_all(&ciphertext)?;
+        zip.start_file("signature.bin", opts)?;
+        zip.write_all(&sig)?;
+        zip.finish()?;
+    }
+    Ok(buf)
+}
+
+/// Parse and decrypt a recovery share .swarm bundle, checking that the share
+/// is signed by its Root Owner.
+pub fn parse_recovery_share_bundle(
+    data: &[u8],
+    recipient_key: &SigningKey,
+) -> Result<ParsedRecoveryShare> {
+    let mut zip = ZipArchive::new(Cursor::new(data))
+        .map_err(|e| KrillnotesError::Swarm(format!("zip open: {e}")))?;
+    let header_bytes = read_zip_file(&mut zip, "header.json")?;
+    let ciphertext = read_zip_file(&mut zip, "payload.enc")?;
+    let sig_bytes = read_zip_file(&mut zip, "signature.bin")?;
+
+    let header: SwarmHeader = serde_json::from_slice(&header_bytes)?;
+    header.validate()?;
+    if header.mode != SwarmMode::RecoveryShare {
+        return Err(KrillnotesError::Swarm(
+            "not a recovery share bundle".to_string(),
+        ));
+    }
+
+    let vk_arr: [u8; 32] = BASE64
+        .decode(&header.source_identity)
+        .map_err(|e| KrillnotesError::Swarm(format!("bad source_identity: {e}")))?
+        .try_into()
+        .map_err(|_| KrillnotesError::Swarm("source_identity key wrong length".to_string()))?;
+    let vk = VerifyingKey::from_bytes(&vk_arr)
+        .map_err(|e| KrillnotesError::Swarm(format!("invalid sender key: {e}")))?;
+    let files: Vec<(&str, &[u8])> =
+        vec![("header.json", &header_bytes), ("payload.enc", &ciphertext)];
+    verify_manifest(&files, &sig_bytes, &vk)?;
+
+    let plaintext = header
+        .recipients
+        .iter()
+        .flatten()
+        .find_map(|entry| decrypt_payload(&ciphertext, entry, recipient_key).ok())
+        .ok_or_else(|| KrillnotesError::Swarm("no recipient entry matched our key".to_string()))?;
+    let share: RecoveryShare = serde_json::from_slice(&plaintext)?;
+    if share.workspace_id != header.workspace_id || !share.verify() {
+        return Err(KrillnotesError::InvalidRecoveryShares(
+            "share is not signed by its Root Owner".to_string(),
+        ));
+    }
+
+    Ok(ParsedRecoveryShare {
+        workspace_id: header.workspace_id,
+        workspace_name: header.workspace_name,
+        sender_public_key: header.source_identity,
+        sender_display_name: header.source_display_name,
+        share,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::root_recovery::split_recovery_key;
+
+    fn make_key() -> SigningKey {
+        SigningKey::generate(&mut rand_core::OsRng)
+    }
+
+    #[test]
+    fn test_recovery_share_roundtrip_and_release() {
+        let owner = make_key();
+        let holder = make_key();
+        let claimant = make_key();
+        let (_, shares) = split_recovery_key(&owner, "ws-1", 2, 2).unwrap();
+        let params = |share, sender_key, recipient_key| RecoveryShareParams {
+            protocol: "test".to_string(),
+            workspace_name: "Team".to_string(),
+            source_device_id: "dev-1".to_string(),
+            source_display_name: "Someone".to_string(),
+            share,
+            sender_key,
+            recipient_key,
+        };
+
+        let (holder_vk, claimant_vk) = (holder.verifying_key(), claimant.verifying_key());
+
+        let bundle = create_recovery_share_bundle(params(&shares[0], &owner, &holder_vk)).unwrap();
+        assert!(parse_recovery_share_bundle(&bundle, &claimant).is_err());
+        let held = parse_recovery_share_bundle(&bundle, &holder).unwrap();
+        assert_eq!(held.share, shares[0]);
+        assert_eq!(held.workspace_name, "Team");
+
+        // The holder releases the share to the claimant.
+        let released =
+            create_recovery_share_bundle(params(&held.share, &holder, &claimant_vk)).unwrap();
+        let parsed = parse_recovery_share_bundle(&released, &claimant).unwrap();
+        assert_eq!(parsed.share, shares[0]);
+        assert_eq!(
+            parsed.sender_public_key,
+            BASE64.encode(holder.verifying_key().as_bytes())
+        );
+    }
+}
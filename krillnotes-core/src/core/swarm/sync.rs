@@ -14,17 +14,24 @@
 //!   - codec invocation
 //!   - watermark and peer registry updates
 
+use std::collections::{BTreeMap, HashSet};
+
 use base64::engine::general_purpose::STANDARD as BASE64;
 use base64::Engine;
 use ed25519_dalek::{SigningKey, VerifyingKey};
 use serde::Serialize;
 
+use crate::core::checkpoint::Checkpoint;
 use crate::core::contact::{ContactManager, TrustLevel};
 use crate::core::operation::Operation;
+use crate::core::peer_registry::SyncPeer;
+use crate::core::succession::SuccessionRecord;
 use crate::core::swarm::delta::{
-    create_delta_bundle, parse_delta_bundle, DeltaOperation, DeltaParams,
+    create_delta_bundle, parse_delta_bundle, BlobChunk, BlobWant, DeltaOperation, DeltaParams,
+    ParsedDelta,
 };
-use crate::core::workspace::Workspace;
+use crate::core::workspace::permissions::ReadScope;
+use crate::core::workspace::{CheckpointMerge, OpRejection, OperationsSince, Workspace};
 use crate::{KrillnotesError, Result};
 
 /// Result of generating a delta bundle.
@@ -35,8 +42,26 @@ pub struct DeltaBundle {
     /// The operation ID of the last op included, if any.
     /// The poll loop advances the watermark only after confirmed delivery.
     pub last_included_op: Option<String>,
-    /// Number of operations included.
+    /// Number of operations included, scope boundary operations among them.
     pub op_count: usize,
+    /// The recipient's read scope this bundle was filtered to (`None` =
+    /// unfiltered). Record it with `Workspace::record_peer_sent_scope` once
+    /// the bundle is delivered.
+    pub scope: Option<ReadScope>,
+    /// Attachment content this bundle asks the peer for. Once delivered,
+    /// pass the hashes to `Workspace::mark_blob_wants_sent`.
+    pub blob_wants: Vec<BlobWant>,
+    /// How far this bundle's chunks take each content transfer to the peer.
+    /// Once delivered, pass it to `Workspace::record_blob_progress`.
+    pub blobs_sent: Vec<BlobWant>,
+}
+
+impl DeltaBundle {
+    /// True if the bundle carries attachment content or requests for it, and
+    /// is worth sending even without operations.
+    pub fn has_blob_traffic(&self) -> bool {
+        !self.blob_wants.is_empty() || !self.blobs_sent.is_empty()
+    }
 }
 
 /// Result of applying a received delta bundle.
@@ -49,6 +74,8 @@ pub struct ApplyResult {
     pub sender_public_key: String,
     /// Display names of contacts auto-registered via TOFU during this apply.
     pub new_tofu_contacts: Vec<String>,
+    /// Changes made by a checkpoint carried in the bundle, if there was one.
+    pub checkpoint: Option<CheckpointMerge>,
 }
 
 /// Generate a delta `.swarm` bundle for a specific peer.
@@ -61,6 +88,11 @@ pub struct ApplyResult {
 ///
 /// When `last_sent_op` is `None` (e.g. after a force-resync reset), all operations
 /// are included in the delta so the peer can catch up from scratch.
+///
+/// Operations and attachment content are limited to the peer's RBAC read scope
+/// (`Workspace::read_scope_for`); notes that entered or left that scope since
+/// the last delivered bundle travel as scope boundary operations (see
+/// `Workspace::scope_operations`).
 pub fn generate_delta(
     workspace: &mut Workspace,
     peer_device_id: &str,
@@ -69,20 +101,80 @@ pub fn generate_delta(
     sender_display_name: &str,
     contact_manager: &ContactManager,
 ) -> Result<DeltaBundle> {
+    let prepared = prepare_delta(workspace, peer_device_id, signing_key, contact_manager)?;
+    encode_delta(
+        workspace,
+        prepared,
+        workspace_name,
+        signing_key,
+        sender_display_name,
+    )
+}
+
+/// Everything that goes into a peer's next delta, before encoding.
+struct PreparedDelta {
+    peer: SyncPeer,
+    delta_operations: Vec<DeltaOperation>,
+    last_included_op: Option<String>,
+    scope: Option<ReadScope>,
+    recipient_vk: VerifyingKey,
+    blob_wants: Vec<BlobWant>,
+    blob_chunks: Vec<BlobChunk>,
+    blobs_sent: Vec<BlobWant>,
+    checkpoint: Option<(Checkpoint, Vec<u8>)>,
+}
+
+fn prepare_delta(
+    workspace: &mut Workspace,
+    peer_device_id: &str,
+    signing_key: &SigningKey,
+    contact_manager: &ContactManager,
+) -> Result<PreparedDelta> {
     // 1. Look up peer.
     let peer = workspace.get_sync_peer(peer_device_id)?.ok_or_else(|| {
         KrillnotesError::Swarm(format!("peer {peer_device_id} not found in registry"))
     })?;
 
     // 2. Collect operations since watermark with verified_by metadata,
-    //    excluding this peer's own ops.
-    let ops_with_vb = workspace
-        .operations_since_with_verified_by(peer.last_sent_op.as_deref(), &peer.peer_device_id)?;
+    //    excluding this peer's own ops. A watermark that predates the
+    //    compacted log is answered with the latest checkpoint and the
+    //    operations after it, except for peers with a read scope: the
+    //    checkpoint state covers every note, so they are re-sent their whole
+    //    scope as boundary operations instead (see 2a).
+    let scope = workspace.read_scope_for(&peer.peer_identity_id)?;
+    let OperationsSince {
+        mut checkpoint,
+        operations: ops_with_vb,
+    } = workspace
+        .operations_since_with_checkpoint(peer.last_sent_op.as_deref(), &peer.peer_device_id)?;
+    let mut resend_frontier = None;
+    if scope.is_some() {
+        resend_frontier = checkpoint.take().map(|(cp, _)| cp.frontier_operation_id);
+    }
+
+    // 2a. Limit to what the peer may read. A peer without a recorded scope
+    //     either has nothing yet (no watermark) or may hold every note.
+    let previous = match workspace.get_peer_sent_scope(&peer.peer_device_id)? {
+        Some(previous) => Some(previous),
+        None if scope.is_some() && peer.last_sent_op.is_none() => Some(ReadScope::default()),
+        None => None,
+    };
+    let (ops_with_vb, boundary_ops) = if scope.is_none() && previous.is_none() {
+        (ops_with_vb, Vec::new())
+    } else {
+        let scoped = workspace.scope_operations(
+            ops_with_vb,
+            scope.as_ref(),
+            previous.as_ref(),
+            resend_frontier.is_some(),
+        )?;
+        (scoped.operations, scoped.boundary_operations)
+    };
 
     // 2b. Wrap each operation in a DeltaOperation with appropriate vouching.
     let my_pubkey = workspace.identity_pubkey().to_string();
 
-    let delta_operations: Vec<DeltaOperation> = ops_with_vb
+    let mut delta_operations: Vec<DeltaOperation> = ops_with_vb
         .into_iter()
         .filter_map(|(op, verified_by)| {
             if op.author_key() == my_pubkey {
@@ -90,12 +182,14 @@ pub fn generate_delta(
                 Some(DeltaOperation {
                     op,
                     verified_by: None,
+                    scope_boundary: false,
                 })
             } else if !verified_by.is_empty() {
                 // Previously verified/vouched: I re-vouch
                 Some(DeltaOperation {
                     op,
                     verified_by: Some(my_pubkey.clone()),
+                    scope_boundary: false,
                 })
             } else {
                 // Unverified op — do not include in delta
@@ -106,18 +200,39 @@ pub fn generate_delta(
         })
         .collect();
 
-    // 3. Collect plaintext attachment blobs for any AddAttachment ops in the batch.
-    let mut attachment_blobs: Vec<(String, Vec<u8>)> = Vec::new();
-    for delta_op in &delta_operations {
-        if let Operation::AddAttachment { attachment_id, .. } = &delta_op.op {
-            match workspace.get_attachment_bytes(attachment_id) {
-                Ok(bytes) => attachment_blobs.push((attachment_id.clone(), bytes)),
-                Err(e) => {
-                    log::warn!(target: "krillnotes::sync",
-                        "Could not read attachment {} for delta, skipping blob: {e}",
-                        attachment_id);
-                }
-            }
+    // The watermark covers logged operations only, or the checkpoint
+    // frontier when nothing follows it.
+    let last_included_op = delta_operations
+        .last()
+        .map(|d| d.op.operation_id().to_string())
+        .or_else(|| {
+            checkpoint
+                .as_ref()
+                .map(|(cp, _)| cp.frontier_operation_id.clone())
+        })
+        .or(resend_frontier);
+    delta_operations.extend(boundary_ops.into_iter().map(|op| DeltaOperation {
+        op,
+        verified_by: None,
+        scope_boundary: true,
+    }));
+
+    // 3. Attachment content travels by hash, outside the operations: ask for
+    //    what we are missing and answer what this peer asked us for.
+    let blob_wants = workspace.due_blob_wants()?;
+    let blob_chunks = workspace.blob_chunks_for_peer(peer_device_id, scope.as_ref())?;
+    let mut blobs_sent: Vec<BlobWant> = Vec::new();
+    for chunk in &blob_chunks {
+        let end = chunk.offset + chunk.data.len() as u64;
+        match blobs_sent
+            .iter_mut()
+            .find(|p| p.hash_sha256 == chunk.hash_sha256)
+        {
+            Some(progress) => progress.offset = end,
+            None => blobs_sent.push(BlobWant {
+                hash_sha256: chunk.hash_sha256.clone(),
+                offset: end,
+            }),
         }
     }
 
@@ -148,15 +263,44 @@ pub fn generate_delta(
             .map_err(|e| KrillnotesError::Swarm(format!("invalid recipient key: {e}")))?
     };
 
+    Ok(PreparedDelta {
+        peer,
+        delta_operations,
+        last_included_op,
+        scope,
+        recipient_vk,
+        blob_wants,
+        blob_chunks,
+        blobs_sent,
+        checkpoint,
+    })
+}
+
+fn encode_delta(
+    workspace: &Workspace,
+    prepared: PreparedDelta,
+    workspace_name: &str,
+    signing_key: &SigningKey,
+    sender_display_name: &str,
+) -> Result<DeltaBundle> {
+    let PreparedDelta {
+        peer,
+        delta_operations,
+        last_included_op,
+        scope,
+        recipient_vk,
+        blob_wants,
+        blob_chunks,
+        blobs_sent,
+        checkpoint,
+    } = prepared;
+
     // 5. Build delta bundle.
     // Use the workspace's identity-based device_id (not the hardware device ID)
     // so that multiple identities on the same machine have distinct source IDs.
     let source_device_id = workspace.device_id().to_string();
 
     let op_count = delta_operations.len();
-    let last_included_op = delta_operations
-        .last()
-        .map(|d| d.op.operation_id().to_string());
 
     let bundle_bytes = create_delta_bundle(DeltaParams {
         protocol: workspace.protocol_id().to_string(),
@@ -168,13 +312,16 @@ pub fn generate_delta(
         delta_operations,
         sender_key: signing_key,
         recipient_keys: vec![&recipient_vk],
-        recipient_peer_ids: vec![peer_device_id.to_string()],
+        recipient_peer_ids: vec![peer.peer_device_id.clone()],
         recipient_identity_id: peer.peer_identity_id.clone(),
         owner_pubkey: workspace.owner_pubkey().to_string(),
         // ACK: tell the peer the last operation we received FROM them.
         // They can compare it with their last_sent_op to detect missed deltas.
         ack_operation_id: peer.last_received_op.clone(),
-        attachment_blobs,
+        attachment_blobs: Vec::new(),
+        blob_wants: blob_wants.clone(),
+        blob_chunks,
+        checkpoint,
     })?;
 
     // NOTE: watermark is NOT advanced here.
@@ -184,6 +331,9 @@ pub fn generate_delta(
         bundle_bytes,
         last_included_op,
         op_count,
+        scope,
+        blob_wants,
+        blobs_sent,
     })
 }
 
@@ -202,68 +352,42 @@ pub fn apply_delta(
     recipient_key: &SigningKey,
     contact_manager: &mut ContactManager,
 ) -> Result<ApplyResult> {
-    // 0. Protocol isolation — reject bundles from incompatible products before decryption.
-    let header = crate::core::swarm::header::read_header(bundle_bytes)?;
-    if header.protocol != workspace.protocol_id() {
-        log::error!(
-            "Rejecting swarm bundle: protocol mismatch (expected '{}', found '{}')",
-            workspace.protocol_id(),
-            header.protocol,
-        );
-        return Err(KrillnotesError::ProtocolMismatch {
-            expected: workspace.protocol_id().to_string(),
-            found: header.protocol,
-        });
-    }
-
-    // 1. Decrypt and verify bundle-level signature.
-    let parsed = parse_delta_bundle(bundle_bytes, recipient_key)?;
-
-    // 1b. Authoritative protocol check — the encrypted protocol cannot be
-    // tampered with (unlike the cleartext header).
-    if parsed.protocol != workspace.protocol_id() {
-        log::error!(
-            "Rejecting delta: encrypted protocol mismatch (expected '{}', found '{}')",
-            workspace.protocol_id(),
-            parsed.protocol,
-        );
-        return Err(KrillnotesError::ProtocolMismatch {
-            expected: workspace.protocol_id().to_string(),
-            found: parsed.protocol,
-        });
-    }
-
-    // 2. Assert workspace_id matches.
-    if parsed.workspace_id != workspace.workspace_id() {
-        return Err(KrillnotesError::Swarm(format!(
-            "workspace_id mismatch: bundle has '{}', this workspace is '{}'",
-            parsed.workspace_id,
-            workspace.workspace_id()
-        )));
-    }
-
-    // Cross-check owner_pubkey if present in the delta
-    if let Some(ref header_owner) = parsed.owner_pubkey {
-        let local_owner = workspace.owner_pubkey();
-        if header_owner != local_owner {
-            return Err(KrillnotesError::Swarm(format!(
-                "owner_pubkey mismatch: delta header={}, local={}",
-                &header_owner[..header_owner.len().min(8)],
-                &local_owner[..local_owner.len().min(8)],
-            )));
-        }
-    }
+    let parsed = open_delta(bundle_bytes, workspace, recipient_key)?;
 
     let mut applied = 0usize;
     let mut skipped = 0usize;
     let mut new_tofu_contacts: Vec<String> = Vec::new();
 
+    // 2b. A checkpoint (verified by `open_delta`) stands in for history the
+    //     sender no longer has; merge its state before the operations after it.
+    let checkpoint = match &parsed.checkpoint {
+        Some((cp, state)) => Some(workspace.apply_checkpoint(cp, state)?),
+        None => None,
+    };
+
     // 3. Apply each operation in chronological order.
     for delta_op in &parsed.delta_operations {
         let op = &delta_op.op;
-        // TOFU: auto-register unknown authors.
+        if delta_op.scope_boundary {
+            if workspace.apply_scope_boundary_operation(
+                op,
+                &parsed.sender_public_key,
+                &parsed.attachment_blobs,
+            )? {
+                applied += 1;
+            } else {
+                skipped += 1;
+            }
+            continue;
+        }
+        // TOFU: auto-register unknown authors. A rotated key takes over its
+        // predecessor's contact instead (below).
         let author_key = op.author_key();
-        if !author_key.is_empty() && contact_manager.find_by_public_key(author_key)?.is_none() {
+        let rotation = SuccessionRecord::from_operation(op);
+        if !author_key.is_empty()
+            && rotation.is_none()
+            && contact_manager.find_by_public_key(author_key)?.is_none()
+        {
             let name = if let Operation::JoinWorkspace { declared_name, .. } = op {
                 declared_name.clone()
             } else {
@@ -282,6 +406,9 @@ pub fn apply_delta(
             &parsed.sender_public_key,
         )? {
             applied += 1;
+            if let Some(record) = rotation {
+                contact_manager.apply_succession(&record)?;
+            }
         } else {
             skipped += 1;
         }
@@ -294,8 +421,15 @@ pub fn apply_delta(
     //    the sender's watermark, triggering an infinite full-resend loop.
     let last_bundle_op_id = parsed
         .delta_operations
-        .last()
-        .map(|d| d.op.operation_id().to_string());
+        .iter()
+        .rfind(|d| !d.scope_boundary)
+        .map(|d| d.op.operation_id().to_string())
+        .or_else(|| {
+            parsed
+                .checkpoint
+                .as_ref()
+                .map(|(cp, _)| cp.frontier_operation_id.clone())
+        });
     let last_received = last_bundle_op_id.as_deref();
     workspace.upsert_peer_from_delta(
         &parsed.sender_device_id,
@@ -315,7 +449,9 @@ pub fn apply_delta(
                         sender_device_id, ack_op_id, our_last_sent
                     );
                     workspace.reset_peer_watermark(sender_device_id, Some(ack_op_id))?;
-                } else if !workspace.operation_exists(ack_op_id)? {
+                } else if !workspace.operation_exists(ack_op_id)?
+                    && !workspace.is_checkpoint_frontier(ack_op_id)?
+                {
                     // ACK references an operation we don't have (purged?) — force full resend.
                     log::warn!(target: "krillnotes::sync",
                         "peer {} ACK ({}) references unknown operation, resetting watermark",
@@ -339,15 +475,395 @@ pub fn apply_delta(
         }
     }
 
+    // 6. Blob traffic: store the sender's content requests and write the
+    //    chunks it answered ours with.
+    workspace.ingest_blob_traffic(sender_device_id, &parsed.blob_wants, &parsed.blob_chunks)?;
+
     Ok(ApplyResult {
         operations_applied: applied,
         operations_skipped: skipped,
         sender_device_id: parsed.sender_device_id,
         sender_public_key: parsed.sender_public_key,
         new_tofu_contacts,
+        checkpoint,
     })
 }
 
+/// A note touched by the operations in a preview.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewNote {
+    pub note_id: String,
+    /// Latest title carried by the operations, else the local title.
+    /// `None` if neither is known.
+    pub title: Option<String>,
+}
+
+/// What [`generate_delta`] would send to a peer right now.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeltaPreview {
+    pub peer_device_id: String,
+    /// The peer's watermark; `None` means the whole log would be sent.
+    pub since_operation_id: Option<String>,
+    /// Operations that would be sent, scope boundary operations among them.
+    pub op_count: usize,
+    pub scope_boundary_ops: usize,
+    /// Operation counts keyed by type (`"CreateNote"`, `"UpdateField"`, …).
+    pub ops_by_type: BTreeMap<String, usize>,
+    /// Notes touched, in the order the operations first touch them.
+    pub notes: Vec<PreviewNote>,
+    /// Total size of the attachments announced by `AddAttachment` operations.
+    pub attachment_bytes: u64,
+    /// Attachment content the bundle would carry for the peer's requests.
+    pub blob_bytes: u64,
+    /// Number of attachment hashes the bundle would ask the peer for.
+    pub blob_wants: usize,
+    /// Checkpoint sent in place of history compacted out of the log.
+    pub checkpoint: Option<Checkpoint>,
+    /// Size of the encoded bundle in bytes.
+    pub bundle_size: usize,
+}
+
+/// What [`apply_delta`] would do with one operation of a bundle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "status", content = "reason")]
+pub enum OpOutcome {
+    Apply,
+    /// Already in the log, or earlier in the same bundle.
+    Duplicate,
+    Rejected(OpRejection),
+}
+
+/// One operation of an inbound bundle, as listed by [`preview_bundle`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewedOperation {
+    pub operation_id: String,
+    pub op_type: String,
+    pub author_key: String,
+    pub note_ids: Vec<String>,
+    pub scope_boundary: bool,
+    pub outcome: OpOutcome,
+}
+
+/// What [`apply_delta`] would change if given an inbound bundle.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundlePreview {
+    pub sender_device_id: String,
+    pub sender_public_key: String,
+    /// The sender's name in the contact book, if known.
+    pub sender_name: Option<String>,
+    /// Every operation in the bundle, in apply order.
+    pub operations: Vec<PreviewedOperation>,
+    pub would_apply: usize,
+    pub duplicates: usize,
+    pub rejected: usize,
+    /// Counts, notes and announced attachment sizes of the operations that
+    /// would be applied.
+    pub ops_by_type: BTreeMap<String, usize>,
+    pub notes: Vec<PreviewNote>,
+    pub attachment_bytes: u64,
+    /// Attachment content carried in the bundle.
+    pub blob_bytes: u64,
+    /// Number of attachment hashes the sender asks us for.
+    pub blob_wants: usize,
+    /// Checkpoint whose state would be merged before the operations.
+    pub checkpoint: Option<Checkpoint>,
+    /// Operation authors that would be added as TOFU contacts.
+    pub new_contacts: Vec<String>,
+}
+
+/// Aggregates operations for a preview.
+#[derive(Default)]
+struct OpSummary {
+    ops_by_type: BTreeMap<String, usize>,
+    notes: Vec<PreviewNote>,
+    attachment_bytes: u64,
+}
+
+impl OpSummary {
+    fn add(&mut self, workspace: &Workspace, op: &Operation) {
+        *self
+            .ops_by_type
+            .entry(Workspace::operation_type_str(op).to_string())
+            .or_default() += 1;
+        if let Operation::AddAttachment { size_bytes, .. } = op {
+            self.attachment_bytes += (*size_bytes).max(0) as u64;
+        }
+        let carried_title = match op {
+            Operation::CreateNote { title, .. } | Operation::UpdateNote { title, .. } => {
+                Some(title)
+            }
+            _ => None,
+        };
+        for note_id in Workspace::operation_note_ids(op) {
+            match self.notes.iter_mut().find(|n| n.note_id == note_id) {
+                Some(note) => {
+                    if let Some(title) = carried_title {
+                        note.title = Some(title.clone());
+                    }
+                }
+                None => self.notes.push(PreviewNote {
+                    note_id: note_id.to_string(),
+                    title: carried_title
+                        .cloned()
+                        .or_else(|| workspace.get_note(note_id).ok().map(|n| n.title)),
+                }),
+            }
+        }
+    }
+}
+
+/// Dry run of [`generate_delta`]: reports what the next bundle for
+/// `peer_device_id` would contain.
+///
+/// The peer's watermark is not advanced and nothing is sent, so this is safe
+/// to call before the first sync with a peer or after
+/// [`Workspace::reset_peer_watermark`].
+pub fn preview_delta(
+    workspace: &mut Workspace,
+    peer_device_id: &str,
+    workspace_name: &str,
+    signing_key: &SigningKey,
+    sender_display_name: &str,
+    contact_manager: &ContactManager,
+) -> Result<DeltaPreview> {
+    let prepared = prepare_delta(workspace, peer_device_id, signing_key, contact_manager)?;
+
+    let mut summary = OpSummary::default();
+    for d in &prepared.delta_operations {
+        summary.add(workspace, &d.op);
+    }
+    let scope_boundary_ops = prepared
+        .delta_operations
+        .iter()
+        .filter(|d| d.scope_boundary)
+        .count();
+    let blob_bytes = prepared
+        .blob_chunks
+        .iter()
+        .map(|c| c.data.len() as u64)
+        .sum();
+    let since_operation_id = prepared.peer.last_sent_op.clone();
+    let blob_wants = prepared.blob_wants.len();
+    let checkpoint = prepared.checkpoint.as_ref().map(|(cp, _)| cp.clone());
+
+    let bundle = encode_delta(
+        workspace,
+        prepared,
+        workspace_name,
+        signing_key,
+        sender_display_name,
+    )?;
+
+    Ok(DeltaPreview {
+        peer_device_id: peer_device_id.to_string(),
+        since_operation_id,
+        op_count: bundle.op_count,
+        scope_boundary_ops,
+        ops_by_type: summary.ops_by_type,
+        notes: summary.notes,
+        attachment_bytes: summary.attachment_bytes,
+        blob_bytes,
+        blob_wants,
+        checkpoint,
+        bundle_size: bundle.bundle_bytes.len(),
+    })
+}
+
+/// Dry run of [`apply_delta`]: decrypts an inbound bundle and reports what
+/// applying it would change, including the operations that would be
+/// rejected by the signature and vouch checks. Nothing is written.
+pub fn preview_bundle(
+    bundle_bytes: &[u8],
+    workspace: &Workspace,
+    recipient_key: &SigningKey,
+    contact_manager: &ContactManager,
+) -> Result<BundlePreview> {
+    let parsed = open_delta(bundle_bytes, workspace, recipient_key)?;
+    let sender = parsed.sender_public_key.as_str();
+
+    let mut operations = Vec::with_capacity(parsed.delta_operations.len());
+    let mut summary = OpSummary::default();
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut new_authors: HashSet<&str> = HashSet::new();
+    let mut new_contacts: Vec<String> = Vec::new();
+    let (mut would_apply, mut duplicates, mut rejected) = (0usize, 0usize, 0usize);
+
+    for delta_op in &parsed.delta_operations {
+        let op = &delta_op.op;
+        let outcome = if delta_op.scope_boundary {
+            match workspace.check_scope_boundary_operation(op, sender)? {
+                Some(rejection) => OpOutcome::Rejected(rejection),
+                None => OpOutcome::Apply,
+            }
+        } else {
+            // Same TOFU registration as `apply_delta`, which happens before
+            // the operation is checked.
+            let author_key = op.author_key();
+            if !author_key.is_empty()
+                && !new_authors.contains(author_key)
+                && contact_manager.find_by_public_key(author_key)?.is_none()
+            {
+                new_authors.insert(author_key);
+                new_contacts.push(match op {
+                    Operation::JoinWorkspace { declared_name, .. } => declared_name.clone(),
+                    _ => format!("{}…", &author_key[..8.min(author_key.len())]),
+                });
+            }
+            match workspace.check_incoming_operation(op, delta_op.verified_by.as_deref(), sender)? {
+                Some(rejection) => OpOutcome::Rejected(rejection),
+                None if !seen.insert(op.operation_id())
+                    || workspace.operation_exists(op.operation_id())? =>
+                {
+                    OpOutcome::Duplicate
+                }
+                None => OpOutcome::Apply,
+            }
+        };
+        match outcome {
+            OpOutcome::Apply => {
+                would_apply += 1;
+                summary.add(workspace, op);
+            }
+            OpOutcome::Duplicate => duplicates += 1,
+            OpOutcome::Rejected(_) => rejected += 1,
+        }
+        operations.push(PreviewedOperation {
+            operation_id: op.operation_id().to_string(),
+            op_type: Workspace::operation_type_str(op).to_string(),
+            author_key: op.author_key().to_string(),
+            note_ids: Workspace::operation_note_ids(op)
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            scope_boundary: delta_op.scope_boundary,
+            outcome,
+        });
+    }
+
+    let blob_bytes = parsed
+        .attachment_blobs
+        .iter()
+        .map(|(_, b)| b.len() as u64)
+        .chain(parsed.blob_chunks.iter().map(|c| c.data.len() as u64))
+        .sum();
+    let sender_name = contact_manager
+        .find_by_public_key(sender)?
+        .map(|c| c.display_name().to_string());
+
+    Ok(BundlePreview {
+        sender_device_id: parsed.sender_device_id.clone(),
+        sender_public_key: parsed.sender_public_key.clone(),
+        sender_name,
+        operations,
+        would_apply,
+        duplicates,
+        rejected,
+        ops_by_type: summary.ops_by_type,
+        notes: summary.notes,
+        attachment_bytes: summary.attachment_bytes,
+        blob_bytes,
+        blob_wants: parsed.blob_wants.len(),
+        checkpoint: parsed.checkpoint.map(|(cp, _)| cp),
+        new_contacts,
+    })
+}
+
+/// Decrypts a delta bundle addressed to `recipient_key` and checks that it
+/// belongs to this workspace: protocol, workspace ID and owner.
+fn open_delta(
+    bundle_bytes: &[u8],
+    workspace: &Workspace,
+    recipient_key: &SigningKey,
+) -> Result<ParsedDelta> {
+    // 0. Protocol isolation — reject bundles from incompatible products before decryption.
+    let header = crate::core::swarm::header::read_header(bundle_bytes)?;
+    if header.protocol != workspace.protocol_id() {
+        log::error!(
+            "Rejecting swarm bundle: protocol mismatch (expected '{}', found '{}')",
+            workspace.protocol_id(),
+            header.protocol,
+        );
+        return Err(KrillnotesError::ProtocolMismatch {
+            expected: workspace.protocol_id().to_string(),
+            found: header.protocol,
+        });
+    }
+
+    // 1. Decrypt and verify bundle-level signature.
+    let parsed = parse_delta_bundle(bundle_bytes, recipient_key)?;
+
+    // 1b. Authoritative protocol check — the encrypted protocol cannot be
+    // tampered with (unlike the cleartext header).
+    if parsed.protocol != workspace.protocol_id() {
+        log::error!(
+            "Rejecting delta: encrypted protocol mismatch (expected '{}', found '{}')",
+            workspace.protocol_id(),
+            parsed.protocol,
+        );
+        return Err(KrillnotesError::ProtocolMismatch {
+            expected: workspace.protocol_id().to_string(),
+            found: parsed.protocol,
+        });
+    }
+
+    // 2. Assert workspace_id matches.
+    if parsed.workspace_id != workspace.workspace_id() {
+        return Err(KrillnotesError::Swarm(format!(
+            "workspace_id mismatch: bundle has '{}', this workspace is '{}'",
+            parsed.workspace_id,
+            workspace.workspace_id()
+        )));
+    }
+
+    // Cross-check owner_pubkey if present in the delta. A Root Owner who
+    // rotated their key, or a new owner who recovered the workspace, sends
+    // the new key along with the operation that installs it.
+    if let Some(ref header_owner) = parsed.owner_pubkey {
+        let local_owner = workspace.owner_pubkey();
+        let owner_rotated = parsed.delta_operations.iter().any(|d| {
+            SuccessionRecord::from_operation(&d.op).is_some_and(|record| {
+                record.old_key == local_owner && &record.new_key == header_owner && record.verify()
+            })
+        });
+        let owner_recovered = parsed.delta_operations.iter().any(|d| {
+            workspace
+                .recovered_owner(&d.op)
+                .ok()
+                .flatten()
+                .is_some_and(|new_owner| &new_owner == header_owner)
+        });
+        if header_owner != local_owner && !owner_rotated && !owner_recovered {
+            return Err(KrillnotesError::Swarm(format!(
+                "owner_pubkey mismatch: delta header={}, local={}",
+                &header_owner[..header_owner.len().min(8)],
+                &local_owner[..local_owner.len().min(8)],
+            )));
+        }
+    }
+
+    // 3. A checkpoint must be signed by the sender for this workspace, match
+    //    the state it came with and come from the Root Owner.
+    if let Some((checkpoint, state)) = &parsed.checkpoint {
+        if checkpoint.workspace_id != workspace.workspace_id()
+            || checkpoint.author_key != parsed.sender_public_key
+            || !checkpoint.verify()
+            || !checkpoint.matches_state(state)
+            || !workspace.accepts_checkpoint_from(checkpoint, &parsed.sender_device_id)?
+        {
+            return Err(KrillnotesError::Swarm(format!(
+                "invalid checkpoint {} in delta from {}",
+                checkpoint.checkpoint_id, parsed.sender_device_id
+            )));
+        }
+    }
+
+    Ok(parsed)
+}
+
 #[cfg(test)]
 mod tests {
     use base64::Engine;
@@ -367,6 +883,36 @@ mod tests {
         base64::engine::general_purpose::STANDARD.encode(key.verifying_key().as_bytes())
     }
 
+    /// Content round trip for attachments announced in an earlier delta: Bob's
+    /// next bundle asks for the missing content and Alice's reply carries it.
+    fn fetch_attachment_content(
+        alice_ws: &mut crate::core::workspace::Workspace,
+        alice_key: &SigningKey,
+        alice_cm: &mut crate::core::contact::ContactManager,
+        bob_ws: &mut crate::core::workspace::Workspace,
+        bob_key: &SigningKey,
+        bob_cm: &mut crate::core::contact::ContactManager,
+    ) {
+        let alice_device = alice_ws.device_id().to_string();
+        let bob_device = bob_ws.device_id().to_string();
+        let request =
+            super::generate_delta(bob_ws, &alice_device, "Test", bob_key, "Bob", bob_cm).unwrap();
+        assert!(
+            !request.blob_wants.is_empty(),
+            "Bob must ask for the missing content"
+        );
+        super::apply_delta(&request.bundle_bytes, alice_ws, alice_key, alice_cm).unwrap();
+
+        let reply =
+            super::generate_delta(alice_ws, &bob_device, "Test", alice_key, "Alice", alice_cm)
+                .unwrap();
+        assert!(
+            reply.has_blob_traffic(),
+            "Alice must answer with the content"
+        );
+        super::apply_delta(&reply.bundle_bytes, bob_ws, bob_key, bob_cm).unwrap();
+    }
+
     /// Basic smoke test: generate_delta succeeds for a registered peer that has a
     /// snapshot watermark set (last_sent_op is Some).
     #[test]
@@ -949,7 +1495,7 @@ mod tests {
 
         // Alice's contact manager knows Bob's key so the bundle can be encrypted.
         let alice_cm_dir = tempfile::tempdir().unwrap();
-        let alice_cm = crate::core::contact::ContactManager::for_identity(
+        let mut alice_cm = crate::core::contact::ContactManager::for_identity(
             alice_cm_dir.path().to_path_buf(),
             [30u8; 32],
         )
@@ -1007,19 +1553,27 @@ mod tests {
             result
         );
 
-        // Verify the bundle actually carried a blob sidecar (guards against
-        // generate_delta regressions where blob collection is silently skipped).
+        // The delta carries the metadata only; the content is fetched by hash.
         let parsed =
             crate::core::swarm::delta::parse_delta_bundle(&bundle.bundle_bytes, &bob_key).unwrap();
-        assert_eq!(
-            parsed.attachment_blobs.len(),
-            1,
-            "delta must carry exactly one blob sidecar"
+        assert!(
+            parsed.attachment_blobs.is_empty(),
+            "delta must not inline attachment content"
         );
-        assert_eq!(
-            parsed.attachment_blobs[0].0, attachment_id,
-            "blob sidecar id must match attachment_id"
+        assert!(bob_ws.get_attachments(&note_id).unwrap().is_empty());
+        let pending = bob_ws.pending_attachments().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, attachment_id);
+
+        fetch_attachment_content(
+            &mut alice_ws,
+            &alice_key,
+            &mut alice_cm,
+            &mut bob_ws,
+            &bob_key,
+            &mut bob_cm,
         );
+        assert!(bob_ws.pending_attachments().unwrap().is_empty());
 
         // ── Assertions ────────────────────────────────────────────────────────
         // Bob should see the attachment in the note's list.
@@ -1094,7 +1648,7 @@ mod tests {
 
         // Contact managers.
         let alice_cm_dir = tempfile::tempdir().unwrap();
-        let alice_cm = crate::core::contact::ContactManager::for_identity(
+        let mut alice_cm = crate::core::contact::ContactManager::for_identity(
             alice_cm_dir.path().to_path_buf(),
             [40u8; 32],
         )
@@ -1114,13 +1668,15 @@ mod tests {
         )
         .unwrap();
 
-        // First sync: Bob receives the AddAttachment op.
+        // First sync: Bob receives the AddAttachment op. Bob is registered
+        // under his real device ID so his own ops are not echoed back to him.
+        let bob_device = bob_ws.device_id().to_string();
         alice_ws
-            .upsert_sync_peer("dev-bob", &bob_pubkey_b64, None, None)
+            .upsert_sync_peer(&bob_device, &bob_pubkey_b64, None, None)
             .unwrap();
         let add_bundle = super::generate_delta(
             &mut alice_ws,
-            "dev-bob",
+            &bob_device,
             "Test",
             &alice_key,
             "Alice",
@@ -1128,6 +1684,14 @@ mod tests {
         )
         .unwrap();
         super::apply_delta(&add_bundle.bundle_bytes, &mut bob_ws, &bob_key, &mut bob_cm).unwrap();
+        fetch_attachment_content(
+            &mut alice_ws,
+            &alice_key,
+            &mut alice_cm,
+            &mut bob_ws,
+            &bob_key,
+            &mut bob_cm,
+        );
 
         // Verify Bob has the attachment after the first sync.
         assert_eq!(
@@ -1144,7 +1708,7 @@ mod tests {
         // Advance the watermark so the second delta only includes the remove op.
         alice_ws
             .upsert_sync_peer(
-                "dev-bob",
+                &bob_device,
                 &bob_pubkey_b64,
                 add_bundle.last_included_op.as_deref(),
                 None,
@@ -1154,7 +1718,7 @@ mod tests {
         // Second sync: Bob receives the RemoveAttachment op.
         let remove_bundle = super::generate_delta(
             &mut alice_ws,
-            "dev-bob",
+            &bob_device,
             "Test",
             &alice_key,
             "Alice",
@@ -1193,6 +1757,722 @@ mod tests {
         );
     }
 
+    /// Alice and Bob sharing one workspace, each with a contact for the other
+    /// and with Bob registered as Alice's peer.
+    struct SyncPeers {
+        alice_key: SigningKey,
+        bob_key: SigningKey,
+        alice_ws: crate::core::workspace::Workspace,
+        bob_ws: crate::core::workspace::Workspace,
+        alice_cm: crate::core::contact::ContactManager,
+        bob_cm: crate::core::contact::ContactManager,
+        _dirs: Vec<tempfile::TempDir>,
+    }
+
+    impl SyncPeers {
+        fn new(password: &str) -> Self {
+            let alice_key = make_key();
+            let bob_key = make_key();
+            let dirs: Vec<tempfile::TempDir> =
+                (0..4).map(|_| tempfile::tempdir().unwrap()).collect();
+            let alice_ws = crate::core::workspace::Workspace::create(
+                dirs[0].path().join("alice.db"),
+                password,
+                "alice-id",
+                SigningKey::from_bytes(&alice_key.to_bytes()),
+                test_gate(),
+                None,
+            )
+            .unwrap();
+            let mut bob_ws = crate::core::workspace::Workspace::create_empty_with_id(
+                dirs[1].path().join("bob.db"),
+                password,
+                "bob-id",
+                SigningKey::from_bytes(&bob_key.to_bytes()),
+                alice_ws.workspace_id(),
+                test_gate(),
+                None,
+            )
+            .unwrap();
+            bob_ws.set_owner_pubkey(&b64(&alice_key)).unwrap();
+            let alice_cm = crate::core::contact::ContactManager::for_identity(
+                dirs[2].path().to_path_buf(),
+                [50u8; 32],
+            )
+            .unwrap();
+            alice_cm
+                .find_or_create_by_public_key(
+                    "Bob",
+                    &b64(&bob_key),
+                    crate::core::contact::TrustLevel::Tofu,
+                )
+                .unwrap();
+            let bob_cm = crate::core::contact::ContactManager::for_identity(
+                dirs[3].path().to_path_buf(),
+                [51u8; 32],
+            )
+            .unwrap();
+            let peers = Self {
+                alice_key,
+                bob_key,
+                alice_ws,
+                bob_ws,
+                alice_cm,
+                bob_cm,
+                _dirs: dirs,
+            };
+            let bob_device = peers.bob_ws.device_id().to_string();
+            peers
+                .alice_ws
+                .upsert_sync_peer(&bob_device, &b64(&peers.bob_key), None, None)
+                .unwrap();
+            peers
+        }
+
+        /// Bob's next bundle to Alice, applied on her side.
+        fn bob_to_alice(&mut self) -> super::DeltaBundle {
+            let alice_device = self.alice_ws.device_id().to_string();
+            let bundle = super::generate_delta(
+                &mut self.bob_ws,
+                &alice_device,
+                "Test",
+                &self.bob_key,
+                "Bob",
+                &self.bob_cm,
+            )
+            .unwrap();
+            super::apply_delta(
+                &bundle.bundle_bytes,
+                &mut self.alice_ws,
+                &self.alice_key,
+                &mut self.alice_cm,
+            )
+            .unwrap();
+            bundle
+        }
+
+        /// Alice's next bundle to Bob, recorded as delivered. Applied on Bob's
+        /// side unless `lost`.
+        fn alice_to_bob(&mut self, lost: bool) -> super::DeltaBundle {
+            let bob_device = self.bob_ws.device_id().to_string();
+            let bundle = super::generate_delta(
+                &mut self.alice_ws,
+                &bob_device,
+                "Test",
+                &self.alice_key,
+                "Alice",
+                &self.alice_cm,
+            )
+            .unwrap();
+            self.alice_ws
+                .record_blob_progress(&bob_device, &bundle.blobs_sent)
+                .unwrap();
+            if let Some(ref last) = bundle.last_included_op {
+                self.alice_ws
+                    .upsert_sync_peer(&bob_device, &b64(&self.bob_key), Some(last), None)
+                    .unwrap();
+            }
+            self.alice_ws
+                .record_peer_sent_scope(&bob_device, bundle.scope.as_ref())
+                .unwrap();
+            if !lost {
+                super::apply_delta(
+                    &bundle.bundle_bytes,
+                    &mut self.bob_ws,
+                    &self.bob_key,
+                    &mut self.bob_cm,
+                )
+                .unwrap();
+            }
+            bundle
+        }
+    }
+
+    /// The same file attached twice is stored once per side and sent once.
+    #[test]
+    fn test_attachment_content_deduplicated_by_hash() {
+        let mut p = SyncPeers::new("");
+        let first = p.alice_ws.create_note_root("TextNote").unwrap();
+        let second = p.alice_ws.create_note_root("TextNote").unwrap();
+        let bytes = b"the same photo, twice";
+        let a = p
+            .alice_ws
+            .attach_file(&first, "a.jpg", None, bytes, Some(&p.alice_key))
+            .unwrap();
+        let b = p
+            .alice_ws
+            .attach_file(&second, "b.jpg", None, bytes, Some(&p.alice_key))
+            .unwrap();
+        assert_eq!(a.salt, b.salt, "second copy must reuse the stored content");
+
+        p.alice_to_bob(false);
+        assert_eq!(p.bob_ws.pending_attachments().unwrap().len(), 2);
+
+        let request = p.bob_to_alice();
+        assert_eq!(request.blob_wants.len(), 1, "one want per distinct hash");
+        let reply = p.alice_to_bob(false);
+        assert_eq!(reply.blobs_sent.len(), 1);
+
+        assert!(p.bob_ws.pending_attachments().unwrap().is_empty());
+        assert_eq!(p.bob_ws.get_attachment_bytes(&a.id).unwrap(), bytes);
+        assert_eq!(p.bob_ws.get_attachment_bytes(&b.id).unwrap(), bytes);
+
+        // A third copy arriving later is satisfied from Bob's own store.
+        let third = p.alice_ws.create_note_root("TextNote").unwrap();
+        let c = p
+            .alice_ws
+            .attach_file(&third, "c.jpg", None, bytes, Some(&p.alice_key))
+            .unwrap();
+        p.alice_to_bob(false);
+        assert!(p.bob_ws.pending_attachments().unwrap().is_empty());
+        assert_eq!(p.bob_ws.get_attachment_bytes(&c.id).unwrap(), bytes);
+    }
+
+    /// Content larger than one bundle is sent in chunks; after a lost bundle
+    /// the transfer resumes from what arrived instead of starting over.
+    #[test]
+    fn test_large_attachment_transfer_resumes_after_lost_bundle() {
+        use crate::core::workspace::MAX_BLOB_BYTES_PER_BUNDLE;
+
+        let mut p = SyncPeers::new("secret");
+        let note = p.alice_ws.create_note_root("TextNote").unwrap();
+        let bytes: Vec<u8> = (0..MAX_BLOB_BYTES_PER_BUNDLE + 1_500_000)
+            .map(|i| (i % 253) as u8)
+            .collect();
+        let meta = p
+            .alice_ws
+            .attach_file(&note, "big.bin", None, &bytes, Some(&p.alice_key))
+            .unwrap();
+
+        let announce = p.alice_to_bob(false);
+        assert!(
+            announce.bundle_bytes.len() < 64 * 1024,
+            "the delta must not carry the content"
+        );
+        p.bob_to_alice();
+
+        let first = p.alice_to_bob(false);
+        assert_eq!(first.blobs_sent[0].offset, MAX_BLOB_BYTES_PER_BUNDLE as u64);
+        let pending = p.bob_ws.pending_attachments().unwrap();
+        assert_eq!(pending[0].received_bytes, MAX_BLOB_BYTES_PER_BUNDLE as i64);
+
+        // The bundle with the rest is lost; Alice considers it delivered.
+        p.alice_to_bob(true);
+        assert!(p.alice_to_bob(false).blobs_sent.is_empty());
+
+        // Once the retry interval passes, Bob asks again from where he is
+        // and gets only the remainder.
+        assert!(p.bob_to_alice().blob_wants.is_empty());
+        p.bob_ws
+            .connection()
+            .execute("UPDATE blob_downloads SET requested_at = 0", [])
+            .unwrap();
+        let retry = p.bob_to_alice();
+        assert_eq!(retry.blob_wants[0].offset, MAX_BLOB_BYTES_PER_BUNDLE as u64);
+        let rest = p.alice_to_bob(false);
+        assert_eq!(rest.blobs_sent[0].offset, bytes.len() as u64);
+
+        assert!(p.bob_ws.pending_attachments().unwrap().is_empty());
+        assert_eq!(p.bob_ws.get_attachment_bytes(&meta.id).unwrap(), bytes);
+    }
+
+    /// A preview reports the next bundle's content without moving the peer's
+    /// watermark.
+    #[test]
+    fn test_preview_delta_reports_pending_changes() {
+        let mut p = SyncPeers::new("");
+        let note = p.alice_ws.create_note_root("TextNote").unwrap();
+        let fields = p.alice_ws.get_note(&note).unwrap().fields;
+        p.alice_ws
+            .update_note(&note, "Groceries".into(), fields)
+            .unwrap();
+        p.alice_ws
+            .attach_file(&note, "list.txt", None, &[7u8; 100], Some(&p.alice_key))
+            .unwrap();
+        let bob_device = p.bob_ws.device_id().to_string();
+
+        let preview = super::preview_delta(
+            &mut p.alice_ws,
+            &bob_device,
+            "Test",
+            &p.alice_key,
+            "Alice",
+            &p.alice_cm,
+        )
+        .unwrap();
+        assert_eq!(preview.since_operation_id, None);
+        assert_eq!(preview.ops_by_type.get("AddAttachment"), Some(&1));
+        assert_eq!(preview.ops_by_type.get("UpdateNote"), Some(&1));
+        assert_eq!(preview.attachment_bytes, 100);
+        assert_eq!(preview.blob_bytes, 0);
+        assert!(preview.notes.contains(&super::PreviewNote {
+            note_id: note.clone(),
+            title: Some("Groceries".into()),
+        }));
+        assert!(preview.bundle_size > 0);
+
+        let peer = p.alice_ws.get_sync_peer(&bob_device).unwrap().unwrap();
+        assert_eq!(
+            peer.last_sent_op, None,
+            "preview must not move the watermark"
+        );
+
+        let bundle = p.alice_to_bob(false);
+        assert_eq!(bundle.op_count, preview.op_count);
+        assert_eq!(
+            preview.ops_by_type.values().sum::<usize>(),
+            preview.op_count
+        );
+    }
+
+    /// Previewing an inbound bundle classifies each operation the way
+    /// `apply_delta` would, and writes nothing.
+    #[test]
+    fn test_preview_bundle_classifies_operations() {
+        use super::OpOutcome;
+        use crate::core::workspace::OpRejection;
+
+        let mut p = SyncPeers::new("");
+        let note = p.alice_ws.create_note_root("TextNote").unwrap();
+        let fields = p.alice_ws.get_note(&note).unwrap().fields;
+        p.alice_ws
+            .update_note(&note, "Plans".into(), fields)
+            .unwrap();
+        let bob_device = p.bob_ws.device_id().to_string();
+        let bundle = super::generate_delta(
+            &mut p.alice_ws,
+            &bob_device,
+            "Test",
+            &p.alice_key,
+            "Alice",
+            &p.alice_cm,
+        )
+        .unwrap();
+
+        let preview =
+            super::preview_bundle(&bundle.bundle_bytes, &p.bob_ws, &p.bob_key, &p.bob_cm).unwrap();
+        assert_eq!(preview.sender_public_key, b64(&p.alice_key));
+        assert_eq!(preview.would_apply, bundle.op_count);
+        assert_eq!((preview.duplicates, preview.rejected), (0, 0));
+        assert_eq!(preview.new_contacts.len(), 1);
+        assert!(preview
+            .notes
+            .iter()
+            .any(|n| n.note_id == note && n.title.as_deref() == Some("Plans")));
+        let first = &preview.operations[0].operation_id;
+        assert!(!p.bob_ws.operation_exists(first).unwrap());
+        assert!(p
+            .bob_cm
+            .find_by_public_key(&b64(&p.alice_key))
+            .unwrap()
+            .is_none());
+
+        super::apply_delta(
+            &bundle.bundle_bytes,
+            &mut p.bob_ws,
+            &p.bob_key,
+            &mut p.bob_cm,
+        )
+        .unwrap();
+        let again =
+            super::preview_bundle(&bundle.bundle_bytes, &p.bob_ws, &p.bob_key, &p.bob_cm).unwrap();
+        assert_eq!((again.would_apply, again.duplicates), (0, bundle.op_count));
+        assert!(again.new_contacts.is_empty());
+        assert_eq!(again.sender_name.as_ref(), preview.new_contacts.first());
+
+        // A bundle with a tampered op and relayed ops lacking a proper vouch.
+        let carol_key = make_key();
+        let signed_by = |key: &SigningKey, title: &str| {
+            let mut op = crate::core::operation::Operation::UpdateNote {
+                operation_id: uuid::Uuid::new_v4().to_string(),
+                timestamp: crate::core::hlc::HlcTimestamp {
+                    wall_ms: chrono::Utc::now().timestamp_millis() as u64,
+                    counter: 0,
+                    node_id: 7,
+                },
+                device_id: "dev".to_string(),
+                note_id: note.clone(),
+                title: title.to_string(),
+                modified_by: b64(key),
+                prev_hash: None,
+                signature: String::new(),
+            };
+            op.sign(key);
+            op
+        };
+        let mut tampered = signed_by(&p.alice_key, "Original");
+        if let crate::core::operation::Operation::UpdateNote { title, .. } = &mut tampered {
+            *title = "Forged".into();
+        }
+        let relayed = |verified_by: Option<String>| crate::core::swarm::delta::DeltaOperation {
+            op: signed_by(&carol_key, "Carol's"),
+            verified_by,
+            scope_boundary: false,
+        };
+        let bob_vk = p.bob_key.verifying_key();
+        let bytes = crate::core::swarm::delta::create_delta_bundle(
+            crate::core::swarm::delta::DeltaParams {
+                protocol: p.alice_ws.protocol_id().to_string(),
+                workspace_id: p.alice_ws.workspace_id().to_string(),
+                workspace_name: "Test".into(),
+                source_device_id: p.alice_ws.device_id().to_string(),
+                source_display_name: "Alice".into(),
+                since_operation_id: String::new(),
+                delta_operations: vec![
+                    crate::core::swarm::delta::DeltaOperation {
+                        op: tampered,
+                        verified_by: None,
+                        scope_boundary: false,
+                    },
+                    relayed(None),
+                    relayed(Some(b64(&carol_key))),
+                    relayed(Some(b64(&p.alice_key))),
+                ],
+                sender_key: &p.alice_key,
+                recipient_keys: vec![&bob_vk],
+                recipient_peer_ids: vec![bob_device.clone()],
+                recipient_identity_id: b64(&p.bob_key),
+                owner_pubkey: b64(&p.alice_key),
+                ack_operation_id: None,
+                attachment_blobs: vec![],
+                blob_wants: vec![],
+                blob_chunks: vec![],
+                checkpoint: None,
+            },
+        )
+        .unwrap();
+        let preview = super::preview_bundle(&bytes, &p.bob_ws, &p.bob_key, &p.bob_cm).unwrap();
+        let outcomes: Vec<OpOutcome> = preview.operations.iter().map(|o| o.outcome).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                OpOutcome::Rejected(OpRejection::BadSignature),
+                OpOutcome::Rejected(OpRejection::Unvouched),
+                OpOutcome::Rejected(OpRejection::VoucherMismatch),
+                OpOutcome::Apply,
+            ]
+        );
+        assert_eq!(preview.rejected, 3);
+        assert_eq!(preview.new_contacts.len(), 1, "Carol would be added once");
+    }
+
+    /// Moves every operation in a workspace's log `days` into the past.
+    fn age_operation_log(ws: &crate::core::workspace::Workspace, days: i64) {
+        ws.connection()
+            .execute(
+                "UPDATE operations SET timestamp_wall_ms = timestamp_wall_ms - ?1",
+                [days * 86_400_000],
+            )
+            .unwrap();
+    }
+
+    /// A peer whose watermark was compacted away receives the checkpoint and
+    /// the operations after it, and then syncs from the log as before.
+    #[test]
+    fn test_lagging_peer_receives_checkpoint_and_tail() {
+        use crate::DeleteStrategy;
+
+        let mut p = SyncPeers::new("");
+        let kept = p.alice_ws.create_note_root("TextNote").unwrap();
+        let removed = p.alice_ws.create_note_root("TextNote").unwrap();
+        p.alice_to_bob(false);
+
+        let added = p.alice_ws.create_note_root("TextNote").unwrap();
+        p.alice_ws
+            .delete_note(&removed, DeleteStrategy::DeleteAll)
+            .unwrap();
+        age_operation_log(&p.alice_ws, 100);
+        let checkpoint = p.alice_ws.compact_operation_log(90).unwrap().unwrap();
+        assert!(checkpoint.verify());
+        let note_ops: i64 = p
+            .alice_ws
+            .connection()
+            .query_row(
+                "SELECT COUNT(*) FROM operations WHERE operation_type IN ('CreateNote', 'DeleteNote')",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(note_ops, 0, "note history is compacted");
+        let tail = p.alice_ws.create_note_root("TextNote").unwrap();
+
+        let bob_device = p.bob_ws.device_id().to_string();
+        let since = p
+            .alice_ws
+            .get_sync_peer(&bob_device)
+            .unwrap()
+            .unwrap()
+            .last_sent_op;
+        let plan = p
+            .alice_ws
+            .operations_since_with_checkpoint(since.as_deref(), "")
+            .unwrap();
+        assert_eq!(
+            plan.checkpoint.map(|(cp, _)| cp.checkpoint_id),
+            Some(checkpoint.checkpoint_id.clone())
+        );
+
+        let bundle = p.alice_to_bob(false);
+        assert!(p.bob_ws.get_note(&kept).is_ok());
+        assert!(p.bob_ws.get_note(&added).is_ok());
+        assert!(p.bob_ws.get_note(&tail).is_ok());
+        assert!(
+            p.bob_ws.get_note(&removed).is_err(),
+            "deletion folded into the checkpoint reaches Bob"
+        );
+
+        // Bob's ACK now refers to the tail, which is still in Alice's log.
+        p.bob_to_alice();
+        let peer = p.alice_ws.get_sync_peer(&bob_device).unwrap().unwrap();
+        assert_eq!(peer.last_sent_op, bundle.last_included_op);
+        let plan = p
+            .alice_ws
+            .operations_since_with_checkpoint(peer.last_sent_op.as_deref(), "")
+            .unwrap();
+        assert!(plan.checkpoint.is_none());
+        assert!(plan.operations.is_empty());
+    }
+
+    /// A deletion inside the retention window sits before the checkpoint's
+    /// frontier, so a lagging peer served the checkpoint must learn of it
+    /// from the checkpoint itself.
+    #[test]
+    fn test_lagging_peer_receives_retained_deletion() {
+        use crate::DeleteStrategy;
+
+        let mut p = SyncPeers::new("");
+        let old = p.alice_ws.create_note_root("TextNote").unwrap();
+        let recent = p.alice_ws.create_note_root("TextNote").unwrap();
+        p.alice_to_bob(false);
+        age_operation_log(&p.alice_ws, 100);
+
+        p.alice_ws
+            .delete_note(&recent, DeleteStrategy::DeleteAll)
+            .unwrap();
+        let checkpoint = p.alice_ws.compact_operation_log(90).unwrap().unwrap();
+        let retained: i64 = p
+            .alice_ws
+            .connection()
+            .query_row(
+                "SELECT COUNT(*) FROM operations WHERE operation_type = 'DeleteNote'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(retained, 1, "the recent deletion is not compacted");
+
+        let bob_device = p.bob_ws.device_id().to_string();
+        let since = p
+            .alice_ws
+            .get_sync_peer(&bob_device)
+            .unwrap()
+            .unwrap()
+            .last_sent_op;
+        let plan = p
+            .alice_ws
+            .operations_since_with_checkpoint(since.as_deref(), "")
+            .unwrap();
+        assert_eq!(
+            plan.checkpoint.map(|(cp, _)| cp.checkpoint_id),
+            Some(checkpoint.checkpoint_id)
+        );
+        assert!(plan.operations.is_empty());
+
+        p.alice_to_bob(false);
+        assert!(p.bob_ws.get_note(&old).is_ok());
+        assert!(
+            p.bob_ws.get_note(&recent).is_err(),
+            "deletion before the frontier reaches Bob"
+        );
+    }
+
+    /// Operations after a checkpoint's frontier continue chains whose earlier
+    /// links the receiver never got; neither sync nor the audit sees a gap.
+    #[test]
+    fn test_checkpoint_sync_reports_no_chain_gaps() {
+        let mut p = SyncPeers::new("");
+        p.alice_ws.create_note_root("TextNote").unwrap();
+        p.alice_to_bob(false);
+
+        p.alice_ws.create_note_root("TextNote").unwrap();
+        p.alice_ws.create_note_root("TextNote").unwrap();
+        age_operation_log(&p.alice_ws, 100);
+        p.alice_ws.compact_operation_log(90).unwrap().unwrap();
+        p.alice_ws.create_note_root("TextNote").unwrap();
+        p.alice_ws.create_note_root("TextNote").unwrap();
+        p.alice_to_bob(false);
+
+        let events: Vec<String> = p
+            .bob_ws
+            .list_sync_events(50, 0)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.event_type)
+            .collect();
+        assert!(
+            !events.iter().any(|e| e == "chain_gap"),
+            "unexpected gap: {events:?}"
+        );
+        let report = p.bob_ws.audit_log_integrity().unwrap();
+        assert!(report.chain_breaks.is_empty(), "{:?}", report.chain_breaks);
+    }
+
+    /// A peer with a read scope whose watermark was compacted away is not
+    /// sent the checkpoint; its whole scope is re-sent as boundary operations,
+    /// carrying the changes the compacted log no longer holds.
+    #[test]
+    fn test_lagging_scoped_peer_receives_its_scope_again() {
+        use crate::{AddPosition, DeleteStrategy};
+
+        let mut p = SyncPeers::new("");
+        p.alice_ws
+            .connection()
+            .execute_batch(
+                "CREATE TABLE note_permissions (
+                    note_id TEXT NOT NULL, user_id TEXT NOT NULL,
+                    role TEXT NOT NULL CHECK(role IN ('owner', 'writer', 'reader')),
+                    granted_by TEXT NOT NULL, PRIMARY KEY (note_id, user_id)
+                );",
+            )
+            .unwrap();
+        let shared = p.alice_ws.create_note_root("TextNote").unwrap();
+        let edited = p
+            .alice_ws
+            .create_note(&shared, AddPosition::AsChild, "TextNote")
+            .unwrap();
+        let removed = p
+            .alice_ws
+            .create_note(&shared, AddPosition::AsChild, "TextNote")
+            .unwrap();
+        let secret = p.alice_ws.create_note_root("TextNote").unwrap();
+        p.alice_ws
+            .connection()
+            .execute(
+                "INSERT INTO note_permissions (note_id, user_id, role, granted_by)
+                 VALUES (?1, ?2, 'reader', ?3)",
+                rusqlite::params![shared, b64(&p.bob_key), b64(&p.alice_key)],
+            )
+            .unwrap();
+        p.alice_to_bob(false);
+        assert!(p.bob_ws.get_note(&removed).is_ok());
+
+        p.alice_ws
+            .update_note_title(&edited, "Edited".to_string())
+            .unwrap();
+        let added = p
+            .alice_ws
+            .create_note(&shared, AddPosition::AsChild, "TextNote")
+            .unwrap();
+        p.alice_ws
+            .delete_note(&removed, DeleteStrategy::DeleteAll)
+            .unwrap();
+        age_operation_log(&p.alice_ws, 100);
+        p.alice_ws.compact_operation_log(90).unwrap().unwrap();
+
+        let bundle = p.alice_to_bob(false);
+        assert!(bundle.last_included_op.is_some());
+        assert_eq!(p.bob_ws.get_note(&edited).unwrap().title, "Edited");
+        assert!(p.bob_ws.get_note(&added).is_ok());
+        assert!(
+            p.bob_ws.get_note(&removed).is_err(),
+            "compacted deletion reaches Bob"
+        );
+        assert!(
+            p.bob_ws.get_note(&secret).is_err(),
+            "no checkpoint state outside Bob's scope"
+        );
+    }
+
+    /// A checkpoint whose state does not hash to the signed value makes the
+    /// whole bundle fail.
+    #[test]
+    fn test_tampered_checkpoint_rejected() {
+        let mut p = SyncPeers::new("");
+        p.alice_ws.create_note_root("TextNote").unwrap();
+        age_operation_log(&p.alice_ws, 100);
+        p.alice_ws.compact_operation_log(90).unwrap().unwrap();
+        let (checkpoint, mut state) = p.alice_ws.latest_checkpoint_with_state().unwrap().unwrap();
+        state.push(b' ');
+
+        let bob_device = p.bob_ws.device_id().to_string();
+        let bob_vk = p.bob_key.verifying_key();
+        let bytes = crate::core::swarm::delta::create_delta_bundle(
+            crate::core::swarm::delta::DeltaParams {
+                protocol: p.alice_ws.protocol_id().to_string(),
+                workspace_id: p.alice_ws.workspace_id().to_string(),
+                workspace_name: "Test".into(),
+                source_device_id: p.alice_ws.device_id().to_string(),
+                source_display_name: "Alice".into(),
+                since_operation_id: String::new(),
+                delta_operations: vec![],
+                sender_key: &p.alice_key,
+                recipient_keys: vec![&bob_vk],
+                recipient_peer_ids: vec![bob_device],
+                recipient_identity_id: b64(&p.bob_key),
+                owner_pubkey: b64(&p.alice_key),
+                ack_operation_id: None,
+                attachment_blobs: vec![],
+                blob_wants: vec![],
+                blob_chunks: vec![],
+                checkpoint: Some((checkpoint, state)),
+            },
+        )
+        .unwrap();
+        let err = super::apply_delta(&bytes, &mut p.bob_ws, &p.bob_key, &mut p.bob_cm);
+        assert!(err.is_err());
+        assert!(p.bob_ws.list_all_notes().unwrap().is_empty());
+    }
+
+    /// Only the Root Owner compacts, and a checkpoint from a Writer peer
+    /// (here one deleting the owner's note) makes the whole bundle fail.
+    #[test]
+    fn test_writer_checkpoint_rejected() {
+        use crate::core::checkpoint::{hash_state, CheckpointState};
+
+        let mut p = SyncPeers::new("");
+        let owner_note = p.alice_ws.create_note_root("TextNote").unwrap();
+        p.alice_to_bob(false);
+        age_operation_log(&p.bob_ws, 100);
+        assert!(p.bob_ws.compact_if_due().unwrap().is_none());
+
+        p.bob_ws.compact_operation_log(90).unwrap().unwrap();
+        let (mut checkpoint, state) = p.bob_ws.latest_checkpoint_with_state().unwrap().unwrap();
+        let mut state: CheckpointState = serde_json::from_slice(&state).unwrap();
+        state.deleted_note_ids.push(owner_note.clone());
+        let state = serde_json::to_vec(&state).unwrap();
+        checkpoint.state_hash = hash_state(&state);
+        checkpoint.sign(&p.bob_key);
+        assert!(checkpoint.verify() && checkpoint.matches_state(&state));
+
+        let alice_device = p.alice_ws.device_id().to_string();
+        let alice_vk = p.alice_key.verifying_key();
+        let bytes = crate::core::swarm::delta::create_delta_bundle(
+            crate::core::swarm::delta::DeltaParams {
+                protocol: p.bob_ws.protocol_id().to_string(),
+                workspace_id: p.bob_ws.workspace_id().to_string(),
+                workspace_name: "Test".into(),
+                source_device_id: p.bob_ws.device_id().to_string(),
+                source_display_name: "Bob".into(),
+                since_operation_id: String::new(),
+                delta_operations: vec![],
+                sender_key: &p.bob_key,
+                recipient_keys: vec![&alice_vk],
+                recipient_peer_ids: vec![alice_device],
+                recipient_identity_id: b64(&p.alice_key),
+                owner_pubkey: b64(&p.alice_key),
+                ack_operation_id: None,
+                attachment_blobs: vec![],
+                blob_wants: vec![],
+                blob_chunks: vec![],
+                checkpoint: Some((checkpoint, state)),
+            },
+        )
+        .unwrap();
+        let err = super::apply_delta(&bytes, &mut p.alice_ws, &p.alice_key, &mut p.alice_cm);
+        assert!(err.is_err());
+        assert!(p.alice_ws.get_note(&owner_note).is_ok());
+    }
+
     /// operations_since_with_verified_by returns (op, verified_by) tuples
     /// where verified_by matches the workspace's identity pubkey for self-authored ops.
     #[test]
@@ -1321,6 +2601,7 @@ mod tests {
                 note_id: alice_ws.list_all_notes().unwrap()[0].id.clone(),
                 title: "Carol's update".to_string(),
                 modified_by: carol_pubkey.clone(),
+                prev_hash: None,
                 signature: String::new(),
             };
             op.sign(&carol_key);
@@ -1376,4 +2657,213 @@ mod tests {
             "Carol's op should be re-vouched by Alice"
         );
     }
+
+    /// A rotated identity key reaches peers as the same principal: the
+    /// owner key, contact and sync peer move to the new key, and the old key
+    /// can no longer sign operations.
+    #[test]
+    fn test_identity_key_rotation_propagates() {
+        use crate::core::hlc::HlcTimestamp;
+        use crate::core::operation::Operation;
+        use crate::core::succession::SuccessionRecord;
+        use crate::core::workspace::OpRejection;
+        use std::collections::BTreeMap;
+
+        let mut p = SyncPeers::new("");
+        p.alice_ws.create_note_root("TextNote").unwrap();
+        p.alice_to_bob(false);
+        let old_key = SigningKey::from_bytes(&p.alice_key.to_bytes());
+        let old_pubkey = b64(&old_key);
+        let alice_contact = p.bob_cm.find_by_public_key(&old_pubkey).unwrap().unwrap();
+
+        let new_key = make_key();
+        let new_pubkey = b64(&new_key);
+        let record = SuccessionRecord::new(&old_key, &new_key.verifying_key(), 1_000);
+        assert!(p
+            .alice_ws
+            .rotate_identity_key(SigningKey::from_bytes(&new_key.to_bytes()), &record)
+            .unwrap());
+        assert_eq!(p.alice_ws.owner_pubkey(), new_pubkey);
+        p.alice_key = SigningKey::from_bytes(&new_key.to_bytes());
+        let after = p.alice_ws.create_note_root("TextNote").unwrap();
+        p.alice_to_bob(false);
+
+        assert!(p.bob_ws.get_note(&after).is_ok());
+        assert_eq!(p.bob_ws.owner_pubkey(), new_pubkey);
+        assert_eq!(
+            p.bob_ws.successor_of(&old_pubkey).unwrap(),
+            Some((new_pubkey.clone(), 1_000))
+        );
+        let contact = p.bob_cm.find_by_public_key(&new_pubkey).unwrap().unwrap();
+        assert_eq!(contact.contact_id, alice_contact.contact_id);
+        assert!(p.bob_cm.find_by_public_key(&old_pubkey).unwrap().is_none());
+
+        // The retired key signing after the rotation is refused.
+        let mut late = Operation::CreateNote {
+            operation_id: uuid::Uuid::new_v4().to_string(),
+            timestamp: HlcTimestamp {
+                wall_ms: 2_000,
+                counter: 0,
+                node_id: 0,
+            },
+            device_id: "alice-old-device".into(),
+            note_id: uuid::Uuid::new_v4().to_string(),
+            parent_id: None,
+            position: 0.0,
+            schema: "TextNote".into(),
+            title: "Late".into(),
+            fields: BTreeMap::new(),
+            created_by: String::new(),
+            created_at: None,
+            modified_at: None,
+            original_author: None,
+            prev_hash: None,
+            signature: String::new(),
+        };
+        late.sign(&old_key);
+        assert_eq!(
+            p.bob_ws
+                .check_incoming_operation(&late, None, &old_pubkey)
+                .unwrap(),
+            Some(OpRejection::RotatedKey)
+        );
+        assert!(!p
+            .bob_ws
+            .apply_incoming_operation(late, "alice-old-device", &[], None, &old_pubkey)
+            .unwrap());
+
+        // A second rotation of the old key, to a key Alice never named, is refused.
+        let thief = make_key();
+        let mut forked = Operation::RotateIdentityKey {
+            operation_id: uuid::Uuid::new_v4().to_string(),
+            timestamp: HlcTimestamp {
+                wall_ms: 500,
+                counter: 0,
+                node_id: 0,
+            },
+            device_id: "thief-device".into(),
+            old_key: old_pubkey.clone(),
+            new_key: String::new(),
+            rotated_at_ms: 500,
+            succession_signature: SuccessionRecord::new(&old_key, &thief.verifying_key(), 500)
+                .signature,
+            prev_hash: None,
+            signature: String::new(),
+        };
+        forked.sign(&thief);
+        assert_eq!(
+            p.bob_ws
+                .check_incoming_operation(&forked, None, &b64(&thief))
+                .unwrap(),
+            Some(OpRejection::RotatedKey)
+        );
+        if let Operation::RotateIdentityKey {
+            succession_signature,
+            ..
+        } = &mut forked
+        {
+            *succession_signature = record.signature.clone();
+        }
+        forked.sign(&thief);
+        assert_eq!(
+            p.bob_ws
+                .check_incoming_operation(&forked, None, &b64(&thief))
+                .unwrap(),
+            Some(OpRejection::BadSuccession)
+        );
+    }
+
+    /// Any two of three recovery shares let Bob take over Root Ownership;
+    /// Alice accepts the transfer, and the used shares cannot be replayed.
+    #[test]
+    fn test_root_ownership_recovered_from_shares() {
+        use crate::core::operation::Operation;
+        use crate::core::workspace::OpRejection;
+
+        let mut p = SyncPeers::new("");
+        let shares = p.alice_ws.set_up_root_recovery(2, 3).unwrap();
+        let (recovery_key, threshold) = p.alice_ws.root_recovery().unwrap().unwrap();
+        assert_eq!(threshold, 2);
+        assert!(
+            p.bob_ws.set_up_root_recovery(2, 3).is_err(),
+            "only the owner"
+        );
+        p.alice_to_bob(false);
+        assert_eq!(p.bob_ws.root_recovery().unwrap(), Some((recovery_key, 2)));
+
+        assert!(p.bob_ws.recover_root_ownership(&shares[..1]).is_err());
+        p.bob_ws.recover_root_ownership(&shares[1..]).unwrap();
+        let bob_pubkey = b64(&p.bob_key);
+        assert_eq!(p.bob_ws.owner_pubkey(), bob_pubkey);
+        assert!(p.bob_ws.root_recovery().unwrap().is_none());
+
+        p.bob_to_alice();
+        assert_eq!(p.alice_ws.owner_pubkey(), bob_pubkey);
+        assert!(p.alice_ws.root_recovery().unwrap().is_none());
+
+        // A transfer signed with some other key is refused.
+        let mut forged = Operation::TransferRootOwnership {
+            operation_id: uuid::Uuid::new_v4().to_string(),
+            timestamp: crate::core::hlc::HlcTimestamp {
+                wall_ms: 1_000,
+                counter: 0,
+                node_id: 0,
+            },
+            device_id: "alice-device".into(),
+            new_owner: b64(&p.alice_key),
+            transferred_by: String::new(),
+            recovery_signature: Some(crate::core::root_recovery::sign_recovery_transfer(
+                &make_key(),
+                p.bob_ws.workspace_id(),
+                &bob_pubkey,
+                &b64(&p.alice_key),
+            )),
+            prev_hash: None,
+            signature: String::new(),
+        };
+        forged.sign(&p.alice_key);
+        assert_eq!(
+            p.bob_ws
+                .check_incoming_operation(&forged, None, &b64(&p.alice_key))
+                .unwrap(),
+            Some(OpRejection::BadRecovery)
+        );
+    }
+
+    /// A `SetRootRecovery` naming the Root Owner in `set_by` but not signed
+    /// by them is ignored, so a peer relaying it cannot install a recovery
+    /// key of their own.
+    #[test]
+    fn test_forged_root_recovery_is_ignored() {
+        use crate::core::operation::Operation;
+
+        let mut p = SyncPeers::new("");
+        let bob_pubkey = b64(&p.bob_key);
+        let thief = make_key();
+        let mut forged = Operation::SetRootRecovery {
+            operation_id: "op-forged-recovery".to_string(),
+            timestamp: crate::core::hlc::HlcTimestamp {
+                wall_ms: 1_000,
+                counter: 0,
+                node_id: 0,
+            },
+            device_id: "bob-device".into(),
+            recovery_key: b64(&thief),
+            threshold: 1,
+            set_by: String::new(),
+            prev_hash: None,
+            signature: String::new(),
+        };
+        forged.sign(&p.bob_key);
+        if let Operation::SetRootRecovery { set_by, .. } = &mut forged {
+            *set_by = b64(&p.alice_key);
+        }
+
+        // Bob vouches for it as a relayed operation.
+        assert!(p
+            .alice_ws
+            .apply_incoming_operation(forged, "bob-device", &[], Some(&bob_pubkey), &bob_pubkey)
+            .unwrap());
+        assert!(p.alice_ws.root_recovery().unwrap().is_none());
+    }
 }
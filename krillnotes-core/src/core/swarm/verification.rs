@@ -0,0 +1,198 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Verification bundle: SAS messages for one contact.
+//!
+//! The messages are signed individually and hold nothing secret, so the
+//! payload is not encrypted. Bundles travel through a shared workspace's sync
+//! channels like deltas, or as files.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::Utc;
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use std::io::{Cursor, Write};
+use zip::{write::SimpleFileOptions, ZipArchive, ZipWriter};
+
+use crate::core::swarm::header::{SwarmHeader, SwarmMode};
+use crate::core::swarm::invite::read_zip_file;
+use crate::core::swarm::signature::{sign_manifest, verify_manifest};
+use crate::core::verification::SasMessage;
+use crate::{KrillnotesError, Result};
+
+pub struct VerificationBundleParams<'a> {
+    pub protocol: String,
+    pub workspace_id: String,
+    pub workspace_name: String,
+    pub source_device_id: String,
+    pub source_display_name: String,
+    /// Base64 public key of the contact the messages are for.
+    pub recipient_key: String,
+    pub messages: &'a [SasMessage],
+    pub sender_key: &'a SigningKey,
+}
+
+pub struct ParsedVerificationBundle {
+    pub workspace_id: String,
+    pub sender_public_key: String,
+    pub sender_display_name: String,
+    pub source_device_id: String,
+    /// Base64 public key the bundle is addressed to.
+    pub recipient_key: String,
+    pub messages: Vec<SasMessage>,
+}
+
+/// Generate a verification .swarm bundle.
+pub fn create_verification_bundle(params: VerificationBundleParams<'_>) -> Result<Vec<u8>> {
+    let sender_b64 = BASE64.encode(params.sender_key.verifying_key().as_bytes());
+    if params
+        .messages
+        .iter()
+        .any(|m| m.sender_key != sender_b64 || m.recipient_key != params.recipient_key)
+    {
+        return Err(KrillnotesError::Swarm(
+            "verification messages do not match the bundle's sender and recipient".to_string(),
+        ));
+    }
+    let payload = serde_json::to_vec(params.messages)?;
+
+    let header = SwarmHeader {
+        protocol: params.protocol,
+        format_version: 1,
+        mode: SwarmMode::Verification,
+        workspace_id: params.workspace_id,
+        workspace_name: params.workspace_name,
+        source_device_id: params.source_device_id,
+        source_identity: sender_b64,
+        source_display_name: params.source_display_name,
+        created_at: Utc::now().to_rfc3339(),
+        pairing_token: None,
+        offered_role: None,
+        offered_scope: None,
+        inviter_fingerprint: None,
+        accepted_identity: None,
+        accepted_display_name: None,
+        accepted_fingerprint: None,
+        as_of_operation_id: None,
+        since_operation_id: None,
+        target_peer: Some(params.recipient_key),
+        ack_operation_id: None,
+        recipients: None,
+        has_attachments: false,
+        owner_pubkey: None,
+    };
+    header.validate()?;
+
+    let header_bytes = serde_json::to_vec(&header)?;
+    let files: Vec<(&str, &[u8])> =
+        vec![("header.json", &header_bytes), ("payload.json", &payload)];
+    let sig = sign_manifest(&files, params.sender_key);
+
+    let mut buf = Vec::new();
+    {
+        let mut zip = ZipWriter::new(Cursor::new(&mut buf));
+        let opts = SimpleFileOptions::default();
+        zip.start_file("header.json", opts)?;
+        zip.write_all(&header_bytes)?;
+        zip.start_file("payload.json", opts)?;
+        zip.write_all(&payload)?;
+        zip.start_file("signature.bin", opts)?;
+        zip.write_all(&sig)?;
+        zip.finish()?;
+    }
+    Ok(buf)
+}
+
+/// Parse a verification .swarm bundle, checking that it and every message
+/// in it come from its sender.
+pub fn parse_verification_bundle(data: &[u8]) -> Result<ParsedVerificationBundle> {
+    let mut zip = ZipArchive::new(Cursor::new(data))
+        .map_err(|e| KrillnotesError::Swarm(format!("zip open: {e}")))?;
+    let header_bytes = read_zip_file(&mut zip, "header.json")?;
+    let payload = read_zip_file(&mut zip, "payload.json")?;
+    let sig_bytes = read_zip_file(&mut zip, "signature.bin")?;
+
+    let header: SwarmHeader = serde_json::from_slice(&header_bytes)?;
+    header.validate()?;
+    if header.mode != SwarmMode::Verification {
+        return Err(KrillnotesError::Swarm(
+            "not a verification bundle".to_string(),
+        ));
+    }
+
+    let vk_arr: [u8; 32] = BASE64
+        .decode(&header.source_identity)
+        .map_err(|e| KrillnotesError::Swarm(format!("bad source_identity: {e}")))?
+        .try_into()
+        .map_err(|_| KrillnotesError::Swarm("source_identity key wrong length".to_string()))?;
+    let vk = VerifyingKey::from_bytes(&vk_arr)
+        .map_err(|e| KrillnotesError::Swarm(format!("invalid sender key: {e}")))?;
+    let files: Vec<(&str, &[u8])> =
+        vec![("header.json", &header_bytes), ("payload.json", &payload)];
+    verify_manifest(&files, &sig_bytes, &vk)?;
+
+    let messages: Vec<SasMessage> = serde_json::from_slice(&payload)?;
+    let recipient_key = header.target_peer.unwrap_or_default();
+    if messages
+        .iter()
+        .any(|m| m.sender_key != header.source_identity || m.recipient_key != recipient_key)
+    {
+        return Err(KrillnotesError::Swarm(
+            "verification message from another sender".to_string(),
+        ));
+    }
+
+    Ok(ParsedVerificationBundle {
+        workspace_id: header.workspace_id,
+        sender_public_key: header.source_identity,
+        sender_display_name: header.source_display_name,
+        source_device_id: header.source_device_id,
+        recipient_key,
+        messages,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::verification::SasSessions;
+
+    fn make_key() -> SigningKey {
+        SigningKey::generate(&mut rand_core::OsRng)
+    }
+
+    #[test]
+    fn test_verification_bundle_roundtrip() {
+        let alice = make_key();
+        let bob = make_key();
+        let bob_b64 = BASE64.encode(bob.verifying_key().as_bytes());
+        let sessions = SasSessions::default();
+        let commit = sessions.start(&alice, &bob_b64);
+        let params = |messages, sender_key, recipient_key: &str| VerificationBundleParams {
+            protocol: "test".to_string(),
+            workspace_id: "ws-1".to_string(),
+            workspace_name: "Team".to_string(),
+            source_device_id: "dev-1".to_string(),
+            source_display_name: "Alice".to_string(),
+            recipient_key: recipient_key.to_string(),
+            messages,
+            sender_key,
+        };
+
+        let bundle =
+            create_verification_bundle(params(std::slice::from_ref(&commit), &alice, &bob_b64))
+                .unwrap();
+        let parsed = parse_verification_bundle(&bundle).unwrap();
+        assert_eq!(parsed.messages, vec![commit.clone()]);
+        assert_eq!(parsed.recipient_key, bob_b64);
+        assert_eq!(parsed.sender_display_name, "Alice");
+
+        // Nobody can wrap someone else's messages as their own.
+        assert!(
+            create_verification_bundle(params(std::slice::from_ref(&commit), &bob, &bob_b64))
+                .is_err()
+        );
+    }
+}
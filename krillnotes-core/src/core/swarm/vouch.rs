@@ -0,0 +1,172 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Vouch bundle: the vouch statements one identity has issued.
+//!
+//! Statements are signed individually and meant to be shared, so the payload
+//! is not encrypted and the bundle is not tied to a workspace or recipient.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::Utc;
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use std::io::{Cursor, Write};
+use zip::{write::SimpleFileOptions, ZipArchive, ZipWriter};
+
+use crate::core::swarm::header::{SwarmHeader, SwarmMode};
+use crate::core::swarm::invite::read_zip_file;
+use crate::core::swarm::signature::{sign_manifest, verify_manifest};
+use crate::core::vouch::VouchStatement;
+use crate::{KrillnotesError, Result};
+
+pub struct VouchBundleParams<'a> {
+    pub protocol: String,
+    pub source_device_id: String,
+    pub source_display_name: String,
+    pub statements: &'a [VouchStatement],
+    pub sender_key: &'a SigningKey,
+}
+
+pub struct ParsedVouchBundle {
+    pub sender_public_key: String,
+    pub sender_display_name: String,
+    pub statements: Vec<VouchStatement>,
+}
+
+/// Generate a vouch .swarm bundle.
+pub fn create_vouch_bundle(params: VouchBundleParams<'_>) -> Result<Vec<u8>> {
+    let sender_b64 = BASE64.encode(params.sender_key.verifying_key().as_bytes());
+    if params
+        .statements
+        .iter()
+        .any(|v| v.voucher_key != sender_b64)
+    {
+        return Err(KrillnotesError::Swarm(
+            "vouch statements were not issued by the bundle's sender".to_string(),
+        ));
+    }
+    let payload = serde_json::to_vec(params.statements)?;
+
+    let header = SwarmHeader {
+        protocol: params.protocol,
+        format_version: 1,
+        mode: SwarmMode::Vouch,
+        workspace_id: String::new(),
+        workspace_name: String::new(),
+        source_device_id: params.source_device_id,
+        source_identity: sender_b64,
+        source_display_name: params.source_display_name,
+        created_at: Utc::now().to_rfc3339(),
+        pairing_token: None,
+        offered_role: None,
+        offered_scope: None,
+        inviter_fingerprint: None,
+        accepted_identity: None,
+        accepted_display_name: None,
+        accepted_fingerprint: None,
+        as_of_operation_id: None,
+        since_operation_id: None,
+        target_peer: None,
+        ack_operation_id: None,
+        recipients: None,
+        has_attachments: false,
+        owner_pubkey: None,
+    };
+    header.validate()?;
+
+    let header_bytes = serde_json::to_vec(&header)?;
+    let files: Vec<(&str, &[u8])> =
+        vec![("header.json", &header_bytes), ("payload.json", &payload)];
+    let sig = sign_manifest(&files, params.sender_key);
+
+    let mut buf = Vec::new();
+    {
+        let mut zip = ZipWriter::new(Cursor::new(&mut buf));
+        let opts = SimpleFileOptions::default();
+        zip.start_file("header.json", opts)?;
+        zip.write_all(&header_bytes)?;
+        zip.start_file("payload.json", opts)?;
+        zip.write_all(&payload)?;
+        zip.start_file("signature.bin", opts)?;
+        zip.write_all(&sig)?;
+        zip.finish()?;
+    }
+    Ok(buf)
+}
+
+/// Parse a vouch .swarm bundle, checking that it and every statement in it
+/// come from its sender.
+pub fn parse_vouch_bundle(data: &[u8]) -> Result<ParsedVouchBundle> {
+    let mut zip = ZipArchive::new(Cursor::new(data))
+        .map_err(|e| KrillnotesError::Swarm(format!("zip open: {e}")))?;
+    let header_bytes = read_zip_file(&mut zip, "header.json")?;
+    let payload = read_zip_file(&mut zip, "payload.json")?;
+    let sig_bytes = read_zip_file(&mut zip, "signature.bin")?;
+
+    let header: SwarmHeader = serde_json::from_slice(&header_bytes)?;
+    header.validate()?;
+    if header.mode != SwarmMode::Vouch {
+        return Err(KrillnotesError::Swarm("not a vouch bundle".to_string()));
+    }
+
+    let vk_arr: [u8; 32] = BASE64
+        .decode(&header.source_identity)
+        .map_err(|e| KrillnotesError::Swarm(format!("bad source_identity: {e}")))?
+        .try_into()
+        .map_err(|_| KrillnotesError::Swarm("source_identity key wrong length".to_string()))?;
+    let vk = VerifyingKey::from_bytes(&vk_arr)
+        .map_err(|e| KrillnotesError::Swarm(format!("invalid sender key: {e}")))?;
+    let files: Vec<(&str, &[u8])> =
+        vec![("header.json", &header_bytes), ("payload.json", &payload)];
+    verify_manifest(&files, &sig_bytes, &vk)?;
+
+    let statements: Vec<VouchStatement> = serde_json::from_slice(&payload)?;
+    if statements
+        .iter()
+        .any(|v| v.voucher_key != header.source_identity || !v.verify())
+    {
+        return Err(KrillnotesError::Swarm(
+            "vouch statement not signed by the bundle's sender".to_string(),
+        ));
+    }
+
+    Ok(ParsedVouchBundle {
+        sender_public_key: header.source_identity,
+        sender_display_name: header.source_display_name,
+        statements,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_key() -> SigningKey {
+        SigningKey::generate(&mut rand_core::OsRng)
+    }
+
+    #[test]
+    fn test_vouch_bundle_roundtrip() {
+        let alice = make_key();
+        let bob = make_key();
+        let carol_b64 = BASE64.encode(make_key().verifying_key().as_bytes());
+        let vouch = VouchStatement::new(&alice, &carol_b64, "Carol", false, 1);
+        let params = |sender_key| VouchBundleParams {
+            protocol: "test".to_string(),
+            source_device_id: "dev-1".to_string(),
+            source_display_name: "Alice".to_string(),
+            statements: std::slice::from_ref(&vouch),
+            sender_key,
+        };
+
+        let bundle = create_vouch_bundle(params(&alice)).unwrap();
+        let parsed = parse_vouch_bundle(&bundle).unwrap();
+        assert_eq!(parsed.statements, vec![vouch.clone()]);
+        assert_eq!(parsed.sender_display_name, "Alice");
+
+        // Nobody can pass off someone else's vouches as their own.
+        assert!(create_vouch_bundle(params(&bob)).is_err());
+    }
+}
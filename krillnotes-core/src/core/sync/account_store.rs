@@ -0,0 +1,178 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Encrypted per-identity account store shared by the WebDAV and S3 account
+//! managers.
+//!
+//! Each account is an AES-256-GCM encrypted JSON file `<dir>/<account_id>.json`
+//! in the same envelope as relay accounts. All accounts are decrypted into an
+//! in-memory cache when the store is opened, and writes update the cache after
+//! a successful disk write.
+
+use aes_gcm::{aead::Aead, Aes256Gcm, Key, KeyInit, Nonce};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use rand::RngCore;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use uuid::Uuid;
+
+use crate::{KrillnotesError, Result};
+
+/// An account that can be kept in an [`AccountStore`].
+pub(crate) trait StoredAccount: Clone + Serialize + DeserializeOwned {
+    /// The id the account file is named after.
+    fn account_id(&self) -> Uuid;
+}
+
+/// On-disk format for an encrypted account file.
+#[derive(Serialize, Deserialize)]
+struct EncryptedAccountFile {
+    /// base64-encoded 12-byte nonce.
+    nonce: String,
+    /// base64-encoded AES-256-GCM ciphertext (includes the 16-byte authentication tag).
+    ciphertext: String,
+}
+
+/// The encrypted account files of one directory and their decrypted cache.
+pub(crate) struct AccountStore<A> {
+    dir: PathBuf,
+    encryption_key: [u8; 32],
+    cache: RwLock<HashMap<Uuid, A>>,
+    /// Wraps a failure message in the owning channel's error variant.
+    error: fn(String) -> KrillnotesError,
+}
+
+impl<A: StoredAccount> AccountStore<A> {
+    /// Creates `dir` if needed and decrypts every account in it with `key`.
+    ///
+    /// Fails if an existing file cannot be decrypted (e.g. wrong key).
+    pub(crate) fn open(
+        dir: PathBuf,
+        key: [u8; 32],
+        error: fn(String) -> KrillnotesError,
+    ) -> Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        let store = Self {
+            dir,
+            encryption_key: key,
+            cache: RwLock::new(HashMap::new()),
+            error,
+        };
+        store.load_all_into_cache()?;
+        Ok(store)
+    }
+
+    fn load_all_into_cache(&self) -> Result<()> {
+        let mut cache = self.cache.write().unwrap();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let account = self.decrypt_file(&path)?;
+            cache.insert(account.account_id(), account);
+        }
+        Ok(())
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&self.encryption_key))
+    }
+
+    fn encrypt_account(&self, account: &A) -> Result<EncryptedAccountFile> {
+        let mut nonce_bytes = [0u8; 12];
+        rand::rng().fill_bytes(&mut nonce_bytes);
+        let plaintext = serde_json::to_vec(account)?;
+        let ciphertext = self
+            .cipher()
+            .encrypt(Nonce::from_slice(&nonce_bytes), plaintext.as_ref())
+            .map_err(|e| (self.error)(format!("account encryption failed: {e}")))?;
+        Ok(EncryptedAccountFile {
+            nonce: BASE64.encode(nonce_bytes),
+            ciphertext: BASE64.encode(&ciphertext),
+        })
+    }
+
+    fn decrypt_file(&self, path: &std::path::Path) -> Result<A> {
+        let raw = std::fs::read_to_string(path)?;
+        let enc: EncryptedAccountFile = serde_json::from_str(&raw)?;
+
+        let nonce_bytes = BASE64
+            .decode(&enc.nonce)
+            .map_err(|e| (self.error)(format!("invalid account nonce: {e}")))?;
+        if nonce_bytes.len() != 12 {
+            return Err((self.error)(format!(
+                "invalid nonce length: {} bytes",
+                nonce_bytes.len()
+            )));
+        }
+        let ciphertext = BASE64
+            .decode(&enc.ciphertext)
+            .map_err(|e| (self.error)(format!("invalid account ciphertext: {e}")))?;
+
+        let plaintext = self
+            .cipher()
+            .decrypt(Nonce::from_slice(&nonce_bytes), ciphertext.as_ref())
+            .map_err(|_| (self.error)("Account decryption failed — wrong key?".into()))?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    /// Returns the on-disk path for the given account UUID.
+    pub(crate) fn path_for(&self, id: Uuid) -> PathBuf {
+        self.dir.join(format!("{id}.json"))
+    }
+
+    /// Save (create or overwrite) an account.
+    pub(crate) fn save(&self, account: &A) -> Result<()> {
+        let enc = self.encrypt_account(account)?;
+        let json = serde_json::to_string_pretty(&enc)?;
+        std::fs::write(self.path_for(account.account_id()), json)?;
+        self.cache
+            .write()
+            .unwrap()
+            .insert(account.account_id(), account.clone());
+        Ok(())
+    }
+
+    /// Load an account by UUID from the in-memory cache.
+    pub(crate) fn get(&self, id: Uuid) -> Option<A> {
+        self.cache.read().unwrap().get(&id).cloned()
+    }
+
+    /// All accounts, in no particular order.
+    pub(crate) fn list(&self) -> Vec<A> {
+        self.cache.read().unwrap().values().cloned().collect()
+    }
+
+    /// The first account matching `predicate`.
+    pub(crate) fn find(&self, predicate: impl Fn(&A) -> bool) -> Option<A> {
+        self.cache
+            .read()
+            .unwrap()
+            .values()
+            .find(|a| predicate(a))
+            .cloned()
+    }
+
+    /// Delete an account from disk and the in-memory cache.
+    pub(crate) fn delete(&self, id: Uuid) -> Result<()> {
+        let path = self.path_for(id);
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+        self.cache.write().unwrap().remove(&id);
+        Ok(())
+    }
+}
+
+impl<A> Drop for AccountStore<A> {
+    fn drop(&mut self) {
+        use zeroize::Zeroize;
+        self.encryption_key.zeroize();
+    }
+}
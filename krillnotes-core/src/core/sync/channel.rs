@@ -22,6 +22,11 @@ pub enum SendResult {
 pub enum ChannelType {
     Relay,
     Folder,
+    #[serde(rename = "webdav")]
+    WebDav,
+    S3,
+    Git,
+    Lan,
     #[default]
     Manual,
 }
@@ -31,6 +36,10 @@ impl std::fmt::Display for ChannelType {
         match self {
             ChannelType::Relay => write!(f, "relay"),
             ChannelType::Folder => write!(f, "folder"),
+            ChannelType::WebDav => write!(f, "webdav"),
+            ChannelType::S3 => write!(f, "s3"),
+            ChannelType::Git => write!(f, "git"),
+            ChannelType::Lan => write!(f, "lan"),
             ChannelType::Manual => write!(f, "manual"),
         }
     }
@@ -61,6 +70,10 @@ pub struct BundleRef {
 /// Channel instances are constructed with their required context pre-configured:
 /// - RelayChannel: holds RelayClient (with session token) and relay URL
 /// - FolderChannel: holds local identity key + device key for header filtering
+/// - WebDavChannel: holds one WebDAV client per configured account
+/// - S3Channel: holds one S3 client per configured bucket account
+/// - GitChannel: holds local identity key + directory of local clones
+/// - LanChannel: holds the running LAN listener and discovered peer table
 ///
 /// This avoids pushing identity/device context through every trait method.
 pub trait SyncChannel: Send + Sync {
@@ -75,7 +88,7 @@ pub trait SyncChannel: Send + Sync {
     fn receive_bundles(&self, workspace_id: &str) -> Result<Vec<BundleRef>, KrillnotesError>;
 
     /// Acknowledge successful processing of a bundle.
-    /// Relay: DELETE /bundles/{id}. Folder: delete file.
+    /// Relay: DELETE /bundles/{id}. Folder: delete file. WebDAV: DELETE the resource.
     fn acknowledge(&self, bundle_ref: &BundleRef) -> Result<(), KrillnotesError>;
 
     /// Channel type identifier.
@@ -23,7 +23,7 @@ pub struct FolderChannel {
 impl FolderChannel {
     /// Compute a filesystem-safe 8-char prefix from a base64 identity string.
     /// Maps `/`→`-` and `+`→`_` (URL-safe base64) to avoid path-separator issues.
-    fn identity_short(id: &str) -> String {
+    pub(crate) fn identity_short(id: &str) -> String {
         id.chars()
             .take(8)
             .map(|c| match c {
@@ -34,6 +34,87 @@ impl FolderChannel {
             .collect()
     }
 
+    /// A fresh bundle filename addressed to `recipient_identity_id`:
+    /// `{recipient_short}_{14-digit-ts}_{8-char-uuid}.swarm`.
+    ///
+    /// Returns `None` if the recipient has no identity key.
+    pub(crate) fn bundle_filename(recipient_identity_id: &str) -> Option<String> {
+        let recipient_short = Self::identity_short(recipient_identity_id);
+        if recipient_short.is_empty() {
+            return None;
+        }
+        let timestamp = Utc::now().format("%Y%m%d%H%M%S");
+        let uuid_short: String = Uuid::new_v4().to_string().chars().take(8).collect();
+        Some(format!(
+            "{}_{}_{}.swarm",
+            recipient_short, timestamp, uuid_short
+        ))
+    }
+
+    /// Returns `true` if `filename` is a new-format bundle addressed to the
+    /// identity whose short prefix is `identity_short`.
+    pub(crate) fn is_addressed_to(filename: &str, identity_short: &str) -> bool {
+        if !filename.ends_with(".swarm") {
+            return false;
+        }
+        // Inbox filter: only process files addressed to this device.
+        let inbox_prefix = format!("{}_", identity_short);
+        if !filename.starts_with(&inbox_prefix) {
+            return false;
+        }
+
+        // Format guard: new-format files have a 14-digit timestamp as the second segment.
+        // Old-format files (sender_device_ts_uuid) have an 8-char device short there — skip them.
+        let rest = &filename[inbox_prefix.len()..];
+        let next_segment = rest.split('_').next().unwrap_or("");
+        if next_segment.len() != 14 || !next_segment.chars().all(|c| c.is_ascii_digit()) {
+            log::debug!(
+                target: "krillnotes::sync::folder",
+                "skipping old-format or misaddressed file: {}",
+                filename
+            );
+            return false;
+        }
+        true
+    }
+
+    /// Read every bundle in `dir` addressed to `identity_short`, skipping
+    /// files that cannot be read yet. `dir` must exist.
+    pub(crate) fn read_inbox(
+        dir: &Path,
+        identity_short: &str,
+    ) -> Result<Vec<BundleRef>, KrillnotesError> {
+        let mut bundles = Vec::new();
+
+        let entries = std::fs::read_dir(dir).map_err(|e| {
+            KrillnotesError::Swarm(format!("Cannot read folder {}: {}", dir.display(), e))
+        })?;
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let filename = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if !Self::is_addressed_to(filename, identity_short) {
+                continue;
+            }
+
+            // Try to read the file; skip if it fails (partially written)
+            match std::fs::read(&path) {
+                Ok(data) => {
+                    log::debug!(target: "krillnotes::sync::folder", "read bundle {} ({} bytes)", path.display(), data.len());
+                    bundles.push(BundleRef {
+                        id: path.to_string_lossy().to_string(),
+                        data,
+                    });
+                }
+                Err(e) => {
+                    log::debug!(target: "krillnotes::sync::folder", "skipping partially written file {}: {e}", path.display());
+                    continue;
+                }
+            }
+        }
+        Ok(bundles)
+    }
+
     pub fn new(identity_id: String, device_id: String) -> Self {
         Self {
             identity_short: Self::identity_short(&identity_id),
@@ -71,53 +152,7 @@ impl FolderChannel {
             )));
         }
 
-        let mut bundles = Vec::new();
-
-        let entries = std::fs::read_dir(dir).map_err(|e| {
-            KrillnotesError::Swarm(format!("Cannot read folder {}: {}", dir.display(), e))
-        })?;
-
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.extension().is_none_or(|ext| ext != "swarm") {
-                continue;
-            }
-
-            // Inbox filter: only process files addressed to this device.
-            let filename = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
-            let inbox_prefix = format!("{}_", self.identity_short);
-            if !filename.starts_with(&inbox_prefix) {
-                continue;
-            }
-
-            // Format guard: new-format files have a 14-digit timestamp as the second segment.
-            // Old-format files (sender_device_ts_uuid) have an 8-char device short there — skip them.
-            let rest = &filename[inbox_prefix.len()..];
-            let next_segment = rest.split('_').next().unwrap_or("");
-            if next_segment.len() != 14 || !next_segment.chars().all(|c| c.is_ascii_digit()) {
-                log::debug!(
-                    target: "krillnotes::sync::folder",
-                    "skipping old-format or misaddressed file: {}",
-                    filename
-                );
-                continue;
-            }
-
-            // Try to read the file; skip if it fails (partially written)
-            match std::fs::read(&path) {
-                Ok(data) => {
-                    log::debug!(target: "krillnotes::sync::folder", "read bundle {} ({} bytes)", path.display(), data.len());
-                    bundles.push(BundleRef {
-                        id: path.to_string_lossy().to_string(),
-                        data,
-                    });
-                }
-                Err(e) => {
-                    log::debug!(target: "krillnotes::sync::folder", "skipping partially written file {}: {e}", path.display());
-                    continue;
-                }
-            }
-        }
+        let bundles = Self::read_inbox(dir, &self.identity_short)?;
 
         log::info!(target: "krillnotes::sync::folder", "found {} bundles in {}", bundles.len(), dir.display());
         Ok(bundles)
@@ -141,15 +176,9 @@ impl SyncChannel for FolderChannel {
             )));
         }
 
-        let recipient_short = Self::identity_short(&peer.peer_identity_id);
-        if recipient_short.is_empty() {
-            return Err(crate::core::error::KrillnotesError::Swarm(
-                "folder channel peer has no identity key".to_string(),
-            ));
-        }
-        let timestamp = Utc::now().format("%Y%m%d%H%M%S");
-        let uuid_short: String = Uuid::new_v4().to_string().chars().take(8).collect();
-        let filename = format!("{}_{}_{}.swarm", recipient_short, timestamp, uuid_short);
+        let filename = Self::bundle_filename(&peer.peer_identity_id).ok_or_else(|| {
+            KrillnotesError::Swarm("folder channel peer has no identity key".to_string())
+        })?;
 
         let path = dir.join(filename);
         std::fs::write(&path, bundle_bytes).map_err(|e| {
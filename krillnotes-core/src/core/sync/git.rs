@@ -0,0 +1,419 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Git sync channel: bundles travel as commits in a shared git repository.
+//!
+//! Each configured remote gets a local clone under the channel's clones
+//! directory. Outbound bundles are committed to
+//! `<workspace_id>/<recipient_short>/<filename>`, using the same filename
+//! addressing as [`FolderChannel`], and pushed. Receiving pulls (fetch +
+//! rebase) and reads the local identity's directory. Acknowledged bundles
+//! are removed in local commits that the sync engine pushes with
+//! [`GitChannel::flush`] once it has acknowledged a poll cycle's bundles, so
+//! one poll cycle costs one push per remote rather than one per bundle.
+//!
+//! The `git` executable is used for all repository operations, so any remote
+//! it can reach works — SSH (via the user's agent), HTTPS (via the user's
+//! credential helper) or a local bare repository. Interactive prompts are
+//! disabled; a remote that needs one fails instead of hanging the poll.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+
+use crate::core::error::KrillnotesError;
+use crate::core::sync::channel::{BundleRef, ChannelType, PeerSyncInfo, SendResult, SyncChannel};
+use crate::core::sync::folder::FolderChannel;
+
+/// Branch used when a peer's `channel_params` do not name one.
+pub const DEFAULT_BRANCH: &str = "main";
+
+/// Attempts at pushing before giving up when the remote keeps moving.
+const PUSH_ATTEMPTS: usize = 3;
+
+/// A remote repository and branch, as configured in a peer's
+/// `channel_params` (`{"remote": "<url or path>", "branch": "main"}`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GitRemote {
+    pub remote: String,
+    pub branch: String,
+}
+
+impl GitRemote {
+    pub fn from_params(params: &serde_json::Value) -> Result<Self, KrillnotesError> {
+        let remote = params
+            .get("remote")
+            .and_then(|v| v.as_str())
+            .filter(|r| !r.trim().is_empty())
+            .ok_or_else(|| {
+                KrillnotesError::Git("Git channel peer missing 'remote' in channel_params".into())
+            })?;
+        let branch = params
+            .get("branch")
+            .and_then(|v| v.as_str())
+            .filter(|b| !b.is_empty())
+            .unwrap_or(DEFAULT_BRANCH);
+        // Both end up as git arguments — refuse anything git could read as an option.
+        if remote.starts_with('-') || branch.starts_with('-') || branch.contains("..") {
+            return Err(KrillnotesError::Git(format!(
+                "invalid git remote or branch: {remote} {branch}"
+            )));
+        }
+        Ok(Self {
+            remote: remote.to_string(),
+            branch: branch.to_string(),
+        })
+    }
+
+    /// Directory name of this remote's clone: a hash of the remote and
+    /// branch, so different remotes never share a clone.
+    fn clone_name(&self) -> String {
+        let hash = blake3::hash(format!("{}\n{}", self.remote, self.branch).as_bytes());
+        hash.to_hex()[..16].to_string()
+    }
+}
+
+pub struct GitChannel {
+    /// Short prefix of local identity key, used for the inbox directory and filenames.
+    identity_short: String,
+    /// Parent directory of the per-remote clones.
+    clones_dir: PathBuf,
+    /// All unique remotes configured on peers using this channel.
+    /// Updated by the SyncEngine before each poll cycle.
+    remotes: Mutex<Vec<GitRemote>>,
+    /// Serialises git invocations; a clone cannot run two commands at once.
+    git_lock: Mutex<()>,
+}
+
+impl GitChannel {
+    /// `identity_id` is the local identity's base64 public key; clones are
+    /// kept under `clones_dir` and reused across polls.
+    pub fn new(identity_id: &str, clones_dir: PathBuf) -> Self {
+        Self {
+            identity_short: FolderChannel::identity_short(identity_id),
+            clones_dir,
+            remotes: Mutex::new(Vec::new()),
+            git_lock: Mutex::new(()),
+        }
+    }
+
+    /// Update the set of remotes to pull from. Called by SyncEngine before
+    /// each poll cycle with the remotes of all git-channel peers.
+    pub fn set_remotes(&self, remotes: Vec<GitRemote>) {
+        log::debug!(target: "krillnotes::sync::git", "set_remotes: {} remotes", remotes.len());
+        *self.remotes.lock().unwrap() = remotes;
+    }
+
+    /// Push pending commits (e.g. removals of acknowledged bundles) to every
+    /// known remote. Called by the sync engine at the end of each inbound
+    /// phase.
+    pub fn flush(&self) -> Result<(), KrillnotesError> {
+        let remotes = self.remotes.lock().unwrap().clone();
+        let _guard = self.git_lock.lock().unwrap();
+        for remote in &remotes {
+            let dir = self.clones_dir.join(remote.clone_name());
+            if dir.join(".git").exists() {
+                push_pending(&dir, &remote.branch)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Path of the clone for `remote`, cloning it first if needed.
+    fn ensure_clone(&self, remote: &GitRemote) -> Result<PathBuf, KrillnotesError> {
+        let dir = self.clones_dir.join(remote.clone_name());
+        if dir.join(".git").exists() {
+            return Ok(dir);
+        }
+        std::fs::create_dir_all(&self.clones_dir)?;
+        log::info!(target: "krillnotes::sync::git", "cloning {} into {}", remote.remote, dir.display());
+        run_git(
+            &self.clones_dir,
+            &[
+                "clone",
+                "--quiet",
+                "--",
+                &remote.remote,
+                &dir.to_string_lossy(),
+            ],
+        )?;
+        for (key, value) in [
+            ("user.name", "Krillnotes"),
+            ("user.email", "sync@krillnotes.invalid"),
+            ("commit.gpgsign", "false"),
+        ] {
+            run_git(&dir, &["config", key, value])?;
+        }
+        let tracking = format!("refs/remotes/origin/{}", remote.branch);
+        if git_succeeds(&dir, &["rev-parse", "--verify", "--quiet", &tracking]) {
+            run_git(
+                &dir,
+                &[
+                    "checkout",
+                    "--quiet",
+                    "-B",
+                    &remote.branch,
+                    &format!("origin/{}", remote.branch),
+                ],
+            )?;
+        } else {
+            // Empty remote (or new branch): the first push creates it.
+            run_git(
+                &dir,
+                &[
+                    "symbolic-ref",
+                    "HEAD",
+                    &format!("refs/heads/{}", remote.branch),
+                ],
+            )?;
+        }
+        Ok(dir)
+    }
+
+    fn receive_from(
+        &self,
+        remote: &GitRemote,
+        workspace_id: &str,
+    ) -> Result<Vec<BundleRef>, KrillnotesError> {
+        let _guard = self.git_lock.lock().unwrap();
+        let dir = self.ensure_clone(remote)?;
+        pull(&dir, &remote.branch)?;
+        // Removals from an earlier cycle whose flush failed.
+        push_pending(&dir, &remote.branch)?;
+
+        let inbox = dir.join(workspace_id).join(&self.identity_short);
+        if !inbox.is_dir() {
+            return Ok(Vec::new());
+        }
+        FolderChannel::read_inbox(&inbox, &self.identity_short)
+    }
+}
+
+impl SyncChannel for GitChannel {
+    fn send_bundle(
+        &self,
+        peer: &PeerSyncInfo,
+        bundle_bytes: &[u8],
+    ) -> Result<SendResult, KrillnotesError> {
+        let remote = GitRemote::from_params(&peer.channel_params)?;
+        let filename = FolderChannel::bundle_filename(&peer.peer_identity_id).ok_or_else(|| {
+            KrillnotesError::Git("git channel peer has no identity key".to_string())
+        })?;
+        let recipient_short = FolderChannel::identity_short(&peer.peer_identity_id);
+        // The workspace id travels inside the bundle; read it back so the
+        // directory matches what the receiver reads.
+        let workspace_id = crate::core::swarm::header::read_header(bundle_bytes)?.workspace_id;
+        let rel = format!("{workspace_id}/{recipient_short}/{filename}");
+
+        let _guard = self.git_lock.lock().unwrap();
+        let dir = self.ensure_clone(&remote)?;
+        pull(&dir, &remote.branch)?;
+
+        let path = dir.join(&rel);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, bundle_bytes).map_err(|e| {
+            log::error!(target: "krillnotes::sync::git", "failed to write bundle to {}: {e}", path.display());
+            KrillnotesError::Git(format!("Failed to write bundle to {}: {e}", path.display()))
+        })?;
+        run_git(&dir, &["add", "--", &rel])?;
+        run_git(&dir, &["commit", "--quiet", "-m", &format!("Add {rel}")])?;
+        push_pending(&dir, &remote.branch)?;
+
+        log::info!(target: "krillnotes::sync::git", "pushed bundle {rel} to {} ({} bytes)", remote.remote, bundle_bytes.len());
+        Ok(SendResult::Delivered)
+    }
+
+    fn receive_bundles(&self, workspace_id: &str) -> Result<Vec<BundleRef>, KrillnotesError> {
+        let remotes = self.remotes.lock().unwrap().clone();
+        log::debug!(target: "krillnotes::sync::git", "receiving bundles from {} remotes", remotes.len());
+        let mut bundles = Vec::new();
+        let mut failures = Vec::new();
+        for remote in &remotes {
+            match self.receive_from(remote, workspace_id) {
+                Ok(found) => bundles.extend(found),
+                Err(e) => {
+                    log::warn!(target: "krillnotes::sync::git", "skipping git remote {}: {e}", remote.remote);
+                    failures.push(e);
+                }
+            }
+        }
+        // Surface the failure when no remote could be pulled at all.
+        if !failures.is_empty() && failures.len() == remotes.len() {
+            return Err(failures.remove(0));
+        }
+        log::info!(target: "krillnotes::sync::git", "found {} bundles for workspace {workspace_id}", bundles.len());
+        Ok(bundles)
+    }
+
+    fn acknowledge(&self, bundle_ref: &BundleRef) -> Result<(), KrillnotesError> {
+        let path = Path::new(&bundle_ref.id);
+        let rel = path.strip_prefix(&self.clones_dir).map_err(|_| {
+            KrillnotesError::Git(format!("{} is not in a git clone", path.display()))
+        })?;
+        let mut components = rel.components();
+        let clone = components
+            .next()
+            .ok_or_else(|| KrillnotesError::Git(format!("invalid bundle id {}", bundle_ref.id)))?;
+        let dir = self.clones_dir.join(clone);
+        let file = components.as_path().to_string_lossy().into_owned();
+
+        let _guard = self.git_lock.lock().unwrap();
+        if !path.exists() {
+            return Ok(());
+        }
+        run_git(&dir, &["rm", "--quiet", "--", &file])?;
+        run_git(
+            &dir,
+            &[
+                "commit",
+                "--quiet",
+                "-m",
+                &format!("Remove acknowledged {file}"),
+            ],
+        )?;
+        log::debug!(target: "krillnotes::sync::git", "acknowledged and removed {file}");
+        Ok(())
+    }
+
+    fn channel_type(&self) -> ChannelType {
+        ChannelType::Git
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+// ── git plumbing ──────────────────────────────────────────────────────────────
+
+fn git_command(dir: &Path, args: &[&str]) -> Command {
+    let mut cmd = Command::new("git");
+    cmd.arg("-C")
+        .arg(dir)
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("GIT_SSH_COMMAND", "ssh -o BatchMode=yes");
+    cmd
+}
+
+/// Runs git in `dir` and returns its stdout, or its stderr as the error.
+fn run_git(dir: &Path, args: &[&str]) -> Result<String, KrillnotesError> {
+    let output = git_command(dir, args)
+        .output()
+        .map_err(|e| KrillnotesError::Git(format!("cannot run git (is it installed?): {e}")))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        log::debug!(target: "krillnotes::sync::git", "git {} failed: {}", args.join(" "), stderr.trim());
+        Err(KrillnotesError::Git(format!(
+            "git {}: {}",
+            args.first().copied().unwrap_or_default(),
+            stderr.trim()
+        )))
+    }
+}
+
+fn git_succeeds(dir: &Path, args: &[&str]) -> bool {
+    git_command(dir, args)
+        .output()
+        .is_ok_and(|o| o.status.success())
+}
+
+/// Fetches `branch` and rebases local commits onto it.
+fn pull(dir: &Path, branch: &str) -> Result<(), KrillnotesError> {
+    run_git(dir, &["fetch", "--quiet", "origin"])?;
+    let tracking = format!("refs/remotes/origin/{branch}");
+    if !git_succeeds(dir, &["rev-parse", "--verify", "--quiet", &tracking]) {
+        return Ok(());
+    }
+    if !git_succeeds(dir, &["rev-parse", "--verify", "--quiet", "HEAD"]) {
+        run_git(dir, &["reset", "--quiet", "--hard", &tracking])?;
+        return Ok(());
+    }
+    if let Err(e) = run_git(dir, &["rebase", "--quiet", &tracking]) {
+        let _ = run_git(dir, &["rebase", "--abort"]);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Pushes local commits not yet on the remote, pulling and retrying when
+/// another peer pushed first.
+fn push_pending(dir: &Path, branch: &str) -> Result<(), KrillnotesError> {
+    let tracking = format!("refs/remotes/origin/{branch}");
+    let refspec = format!("HEAD:refs/heads/{branch}");
+    for attempt in 1..=PUSH_ATTEMPTS {
+        if !git_succeeds(dir, &["rev-parse", "--verify", "--quiet", "HEAD"]) {
+            return Ok(());
+        }
+        if git_succeeds(dir, &["rev-parse", "--verify", "--quiet", &tracking]) {
+            let ahead = run_git(dir, &["rev-list", "--count", &format!("{tracking}..HEAD")])?;
+            if ahead.trim() == "0" {
+                return Ok(());
+            }
+        }
+        match run_git(dir, &["push", "--quiet", "origin", &refspec]) {
+            Ok(_) => {
+                // Record what the remote now has without another fetch.
+                run_git(dir, &["update-ref", &tracking, "HEAD"])?;
+                return Ok(());
+            }
+            Err(e) if attempt < PUSH_ATTEMPTS => {
+                log::debug!(target: "krillnotes::sync::git", "push rejected (attempt {attempt}): {e}");
+                pull(dir, branch)?;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_git_remote_from_params() {
+        let remote = GitRemote::from_params(
+            &serde_json::json!({ "remote": "git@example.com:team/sync.git" }),
+        )
+        .unwrap();
+        assert_eq!(remote.branch, DEFAULT_BRANCH);
+
+        let other = GitRemote::from_params(
+            &serde_json::json!({ "remote": "git@example.com:team/sync.git", "branch": "krill" }),
+        )
+        .unwrap();
+        assert_eq!(other.branch, "krill");
+        assert_ne!(remote.clone_name(), other.clone_name());
+
+        assert!(GitRemote::from_params(&serde_json::json!({})).is_err());
+        assert!(
+            GitRemote::from_params(&serde_json::json!({ "remote": "--upload-pack=x" })).is_err()
+        );
+        assert!(GitRemote::from_params(
+            &serde_json::json!({ "remote": "/srv/sync.git", "branch": "-f" })
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_git_channel_acknowledge_outside_clones_fails() {
+        let clones = tempfile::tempdir().unwrap();
+        let channel = GitChannel::new("my-identity", clones.path().to_path_buf());
+        let bundle_ref = BundleRef {
+            id: "/tmp/elsewhere/x.swarm".to_string(),
+            data: vec![],
+        };
+        assert!(matches!(
+            channel.acknowledge(&bundle_ref),
+            Err(KrillnotesError::Git(_))
+        ));
+    }
+}
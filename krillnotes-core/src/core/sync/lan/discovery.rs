@@ -0,0 +1,123 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! UDP peer discovery for the LAN channel.
+//!
+//! Each running service periodically broadcasts a small JSON announcement
+//! carrying a hash of its identity key and its TCP port. Listeners record
+//! the sender's address against that hash. The identity key itself is never
+//! broadcast: only peers that already know the key can recognise the hash,
+//! and the TCP handshake still proves possession of the key before any
+//! bundle changes hands.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// Service tag carried in every announcement.
+const SERVICE: &str = "krillnotes-lan/1";
+
+/// Largest datagram read from the discovery socket.
+pub const MAX_DATAGRAM_BYTES: usize = 512;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Announcement {
+    service: String,
+    /// [`announce_id`] of the announcing identity.
+    peer: String,
+    /// TCP port the announcing service accepts transfers on.
+    port: u16,
+}
+
+/// The identifier an identity announces itself under: the first 16 bytes of
+/// a BLAKE3 hash of its base64 public key, hex-encoded.
+pub fn announce_id(identity_id: &str) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(b"krillnotes-lan-v1 announce\0");
+    hasher.update(identity_id.as_bytes());
+    hex::encode(&hasher.finalize().as_bytes()[..16])
+}
+
+pub fn encode_announcement(announce_id: &str, port: u16) -> Vec<u8> {
+    serde_json::to_vec(&Announcement {
+        service: SERVICE.to_string(),
+        peer: announce_id.to_string(),
+        port,
+    })
+    .unwrap_or_default()
+}
+
+/// Parses a datagram received from `source`, returning the announced id and
+/// the TCP address to reach it at (the datagram's source IP, announced port).
+pub fn decode_announcement(datagram: &[u8], source: SocketAddr) -> Option<(String, SocketAddr)> {
+    let announcement: Announcement = serde_json::from_slice(datagram).ok()?;
+    if announcement.service != SERVICE || announcement.port == 0 {
+        return None;
+    }
+    Some((
+        announcement.peer,
+        SocketAddr::new(source.ip(), announcement.port),
+    ))
+}
+
+/// Peers seen recently on the local network, keyed by [`announce_id`].
+#[derive(Debug, Default)]
+pub struct PeerTable {
+    peers: Mutex<HashMap<String, (SocketAddr, Instant)>>,
+}
+
+impl PeerTable {
+    pub fn record(&self, announce_id: String, addr: SocketAddr) {
+        if let Ok(mut peers) = self.peers.lock() {
+            peers.insert(announce_id, (addr, Instant::now()));
+        }
+    }
+
+    /// The last address announced for `announce_id`, if seen within `max_age`.
+    pub fn lookup(&self, announce_id: &str, max_age: Duration) -> Option<SocketAddr> {
+        let mut peers = self.peers.lock().ok()?;
+        peers.retain(|_, (_, seen)| seen.elapsed() <= max_age);
+        peers.get(announce_id).map(|(addr, _)| *addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_announcement_roundtrip_uses_source_ip() {
+        let id = announce_id("some-identity-key");
+        assert_eq!(id.len(), 32);
+        assert_ne!(id, announce_id("other-identity-key"));
+
+        let datagram = encode_announcement(&id, 4100);
+        let source: SocketAddr = "192.168.1.20:47821".parse().unwrap();
+        let (peer, addr) = decode_announcement(&datagram, source).unwrap();
+        assert_eq!(peer, id);
+        assert_eq!(addr, "192.168.1.20:4100".parse().unwrap());
+
+        assert!(
+            decode_announcement(b"{\"service\":\"other\",\"peer\":\"x\",\"port\":1}", source)
+                .is_none()
+        );
+        assert!(decode_announcement(b"garbage", source).is_none());
+    }
+
+    #[test]
+    fn test_peer_table_expires_entries() {
+        let table = PeerTable::default();
+        let addr: SocketAddr = "10.0.0.5:4100".parse().unwrap();
+        table.record("peer".to_string(), addr);
+        assert_eq!(table.lookup("peer", Duration::from_secs(60)), Some(addr));
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(table.lookup("peer", Duration::from_millis(10)), None);
+        assert_eq!(table.lookup("peer", Duration::from_secs(60)), None);
+    }
+}
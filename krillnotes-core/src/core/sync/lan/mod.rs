@@ -0,0 +1,556 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Direct peer-to-peer sync between devices on the same local network.
+//!
+//! A [`LanService`] runs for as long as an identity is unlocked: it accepts
+//! transfers on a TCP port, announces itself over UDP broadcast (see
+//! [`discovery`]) and spools received bundles to disk. A [`LanChannel`]
+//! wraps the service so the [`SyncEngine`](super::SyncEngine) can send to
+//! peers and drain the spool like any other channel.
+//!
+//! Both ends authenticate with their identity Ed25519 keys (see
+//! [`protocol`]). The sender only talks to the identity recorded for the
+//! peer; the receiver only accepts bundles from identities its authorizer
+//! approves, normally those in the identity's `ContactManager`.
+//!
+//! Peer `channel_params` may pin an address (`{"address": "host:port"}`);
+//! otherwise the most recent discovery announcement is used.
+
+pub mod discovery;
+pub mod protocol;
+
+use std::net::{
+    IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket,
+};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use ed25519_dalek::SigningKey;
+
+use super::channel::{BundleRef, ChannelType, PeerSyncInfo, SendResult, SyncChannel};
+use super::folder::FolderChannel;
+use crate::core::error::KrillnotesError;
+use crate::core::swarm::header::read_header;
+
+use discovery::PeerTable;
+use protocol::{ClientHello, ClientProof, ServerHello, Status, MAX_BUNDLE_BYTES, PROTOCOL};
+
+/// Default UDP port for discovery announcements.
+pub const DISCOVERY_PORT: u16 = 47821;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+/// How often the discovery thread wakes to check for shutdown.
+const DISCOVERY_POLL: Duration = Duration::from_millis(200);
+
+/// Decides whether an authenticated identity (base64 public key) may
+/// deliver bundles to this service.
+pub type PeerAuthorizer = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// Network settings for a [`LanService`].
+#[derive(Debug, Clone)]
+pub struct LanConfig {
+    /// TCP address to accept transfers on. Port 0 picks a free port.
+    pub listen_addr: SocketAddr,
+    /// UDP address to receive announcements on, or `None` to only announce.
+    pub discovery_addr: Option<SocketAddr>,
+    /// Where announcements are sent; normally the broadcast address.
+    pub announce_to: Vec<SocketAddr>,
+    /// Time between announcements. Peers not heard from for three intervals
+    /// are forgotten.
+    pub announce_interval: Duration,
+}
+
+impl Default for LanConfig {
+    fn default() -> Self {
+        Self {
+            listen_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+            discovery_addr: Some(SocketAddr::new(
+                IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                DISCOVERY_PORT,
+            )),
+            announce_to: vec![SocketAddr::new(
+                IpAddr::V4(Ipv4Addr::BROADCAST),
+                DISCOVERY_PORT,
+            )],
+            announce_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+fn lan_err(msg: impl std::fmt::Display) -> KrillnotesError {
+    KrillnotesError::Lan(msg.to_string())
+}
+
+/// Workspace ids become spool directory names, so only accept ones that are
+/// a single plain path segment.
+fn is_safe_segment(s: &str) -> bool {
+    !s.is_empty()
+        && s.len() <= 64
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+struct Inner {
+    signing_key: SigningKey,
+    /// Base64 public key of the local identity.
+    identity_id: String,
+    announce_id: String,
+    spool_dir: PathBuf,
+    authorizer: PeerAuthorizer,
+    local_addr: SocketAddr,
+    peers: PeerTable,
+    announce_to: Mutex<Vec<SocketAddr>>,
+    announce_interval: Duration,
+    shutdown: AtomicBool,
+}
+
+/// The long-lived listener, announcer and spool for one unlocked identity.
+///
+/// Dropping the service stops its threads.
+pub struct LanService {
+    inner: Arc<Inner>,
+    discovery_addr: Option<SocketAddr>,
+    threads: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl LanService {
+    /// Binds the listener and discovery sockets and starts serving.
+    ///
+    /// Received bundles are written under `spool_dir/<workspace_id>/` until
+    /// the sync engine acknowledges them. A discovery port that is already
+    /// taken (e.g. by a second unlocked identity) is not fatal: the service
+    /// then announces itself but does not learn about others.
+    pub fn start(
+        config: LanConfig,
+        signing_key: SigningKey,
+        spool_dir: PathBuf,
+        authorizer: PeerAuthorizer,
+    ) -> Result<Self, KrillnotesError> {
+        std::fs::create_dir_all(&spool_dir)?;
+        let listener = TcpListener::bind(config.listen_addr)
+            .map_err(|e| lan_err(format!("cannot listen on {}: {e}", config.listen_addr)))?;
+        let local_addr = listener.local_addr()?;
+
+        let discovery_socket = match config.discovery_addr {
+            Some(addr) => match UdpSocket::bind(addr) {
+                Ok(socket) => Some(socket),
+                Err(e) => {
+                    log::warn!(target: "krillnotes::sync::lan", "discovery disabled, cannot bind {addr}: {e}");
+                    None
+                }
+            },
+            None => None,
+        };
+        let discovery_addr = discovery_socket.as_ref().and_then(|s| s.local_addr().ok());
+        let announce_socket = match discovery_socket {
+            Some(socket) => socket,
+            None => {
+                let unspecified = if local_addr.is_ipv6() {
+                    IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+                } else {
+                    IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+                };
+                UdpSocket::bind(SocketAddr::new(unspecified, 0))?
+            }
+        };
+        announce_socket.set_broadcast(true)?;
+        announce_socket.set_read_timeout(Some(DISCOVERY_POLL))?;
+
+        let identity_id = BASE64.encode(signing_key.verifying_key().as_bytes());
+        let inner = Arc::new(Inner {
+            announce_id: discovery::announce_id(&identity_id),
+            identity_id,
+            signing_key,
+            spool_dir,
+            authorizer,
+            local_addr,
+            peers: PeerTable::default(),
+            announce_to: Mutex::new(config.announce_to),
+            announce_interval: config.announce_interval,
+            shutdown: AtomicBool::new(false),
+        });
+
+        let accept_inner = Arc::clone(&inner);
+        let accept = std::thread::spawn(move || accept_loop(&accept_inner, listener));
+        let discovery_inner = Arc::clone(&inner);
+        let listening = discovery_addr.is_some();
+        let discovery = std::thread::spawn(move || {
+            discovery_loop(&discovery_inner, announce_socket, listening)
+        });
+
+        log::info!(target: "krillnotes::sync::lan", "LAN sync listening on {local_addr}");
+        Ok(Self {
+            inner,
+            discovery_addr,
+            threads: Mutex::new(vec![accept, discovery]),
+        })
+    }
+
+    /// Base64 public key of the identity this service runs for.
+    pub fn identity_id(&self) -> &str {
+        &self.inner.identity_id
+    }
+
+    /// The TCP address transfers are accepted on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.inner.local_addr
+    }
+
+    /// The UDP address announcements are received on, if discovery is active.
+    pub fn discovery_addr(&self) -> Option<SocketAddr> {
+        self.discovery_addr
+    }
+
+    /// Adds a unicast announcement target, for networks where broadcast
+    /// does not reach the peer.
+    pub fn add_announce_target(&self, target: SocketAddr) {
+        if let Ok(mut targets) = self.inner.announce_to.lock() {
+            if !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+    }
+
+    /// The address `identity_id` was last announced from, if still fresh.
+    pub fn peer_address(&self, identity_id: &str) -> Option<SocketAddr> {
+        self.inner.peers.lookup(
+            &discovery::announce_id(identity_id),
+            self.inner.announce_interval * 3,
+        )
+    }
+
+    /// Delivers `bundle` to the service at `addr`, which must authenticate
+    /// as `peer_identity_id`.
+    pub fn send(
+        &self,
+        addr: SocketAddr,
+        peer_identity_id: &str,
+        bundle: &[u8],
+    ) -> Result<(), KrillnotesError> {
+        let stream = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)
+            .map_err(|e| lan_err(format!("cannot connect to {addr}: {e}")))?;
+        send_on(&self.inner, stream, peer_identity_id, bundle)
+    }
+
+    /// Stops the listener and discovery threads. Idempotent.
+    pub fn shutdown(&self) {
+        if self.inner.shutdown.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        // Wake the blocking accept() so the loop sees the flag.
+        let mut wake = self.inner.local_addr;
+        if wake.ip().is_unspecified() {
+            wake.set_ip(match wake.ip() {
+                IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+                IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            });
+        }
+        let _ = TcpStream::connect_timeout(&wake, CONNECT_TIMEOUT);
+        let threads = match self.threads.lock() {
+            Ok(mut threads) => std::mem::take(&mut *threads),
+            Err(_) => return,
+        };
+        for thread in threads {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for LanService {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+// ── Threads ──────────────────────────────────────────────────────────────────
+
+fn accept_loop(inner: &Arc<Inner>, listener: TcpListener) {
+    for stream in listener.incoming() {
+        if inner.shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+        let Ok(stream) = stream else { continue };
+        let inner = Arc::clone(inner);
+        std::thread::spawn(move || {
+            let peer = stream.peer_addr().ok();
+            if let Err(e) = serve(&inner, stream) {
+                log::warn!(target: "krillnotes::sync::lan", "transfer from {peer:?} failed: {e}");
+            }
+        });
+    }
+}
+
+fn discovery_loop(inner: &Inner, socket: UdpSocket, listening: bool) {
+    let announcement = discovery::encode_announcement(&inner.announce_id, inner.local_addr.port());
+    let mut next_announce = Instant::now();
+    let mut buf = [0u8; discovery::MAX_DATAGRAM_BYTES];
+    while !inner.shutdown.load(Ordering::SeqCst) {
+        if Instant::now() >= next_announce {
+            let targets = inner
+                .announce_to
+                .lock()
+                .map(|t| t.clone())
+                .unwrap_or_default();
+            for target in targets {
+                if let Err(e) = socket.send_to(&announcement, target) {
+                    log::debug!(target: "krillnotes::sync::lan", "announce to {target} failed: {e}");
+                }
+            }
+            next_announce = Instant::now() + inner.announce_interval;
+        }
+        if !listening {
+            std::thread::sleep(DISCOVERY_POLL);
+            continue;
+        }
+        let Ok((len, source)) = socket.recv_from(&mut buf) else {
+            continue;
+        };
+        if let Some((peer, addr)) = discovery::decode_announcement(&buf[..len], source) {
+            if peer != inner.announce_id {
+                inner.peers.record(peer, addr);
+            }
+        }
+    }
+}
+
+// ── Transfers ────────────────────────────────────────────────────────────────
+
+/// Server side of one connection: authenticate, authorize, spool.
+fn serve(inner: &Inner, mut stream: TcpStream) -> Result<(), KrillnotesError> {
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+
+    let hello: ClientHello = protocol::read_message(&mut stream)?;
+    if hello.protocol != PROTOCOL {
+        protocol::write_message(&mut stream, &Status::error("unsupported protocol"))?;
+        return Err(lan_err(format!("unsupported protocol {}", hello.protocol)));
+    }
+    let client_key = protocol::decode_key(&hello.identity)?;
+    let client_nonce = protocol::decode_nonce(&hello.nonce)?;
+    let server_key = inner.signing_key.verifying_key();
+    let server_nonce = protocol::new_nonce();
+
+    let server_transcript = protocol::transcript(
+        "server",
+        &client_nonce,
+        &server_nonce,
+        &client_key,
+        &server_key,
+    );
+    protocol::write_message(
+        &mut stream,
+        &ServerHello {
+            identity: inner.identity_id.clone(),
+            nonce: BASE64.encode(server_nonce),
+            signature: protocol::sign(&inner.signing_key, &server_transcript),
+        },
+    )?;
+
+    let proof: ClientProof = protocol::read_message(&mut stream)?;
+    let client_transcript = protocol::transcript(
+        "client",
+        &client_nonce,
+        &server_nonce,
+        &client_key,
+        &server_key,
+    );
+    if !protocol::verify(&client_key, &client_transcript, &proof.signature) {
+        protocol::write_message(&mut stream, &Status::error("authentication failed"))?;
+        return Err(lan_err("client failed authentication"));
+    }
+    let client_id = BASE64.encode(client_key.as_bytes());
+    if !(inner.authorizer)(&client_id) {
+        protocol::write_message(&mut stream, &Status::error("unknown identity"))?;
+        return Err(lan_err(format!("rejected unknown identity {client_id}")));
+    }
+    protocol::write_message(&mut stream, &Status::ok())?;
+
+    let bundle = protocol::read_frame(&mut stream, MAX_BUNDLE_BYTES)?;
+    let status = match spool(inner, &bundle) {
+        Ok(path) => {
+            log::debug!(target: "krillnotes::sync::lan", "spooled {} bytes from {client_id} to {}", bundle.len(), path.display());
+            Status::ok()
+        }
+        Err(e) => Status::error(e.to_string()),
+    };
+    protocol::write_message(&mut stream, &status)
+}
+
+/// Writes a received bundle into the spool for its workspace.
+fn spool(inner: &Inner, bundle: &[u8]) -> Result<PathBuf, KrillnotesError> {
+    let header = read_header(bundle)?;
+    if !is_safe_segment(&header.workspace_id) {
+        return Err(lan_err("invalid workspace id in bundle header"));
+    }
+    let dir = inner.spool_dir.join(&header.workspace_id);
+    std::fs::create_dir_all(&dir)?;
+    let filename = FolderChannel::bundle_filename(&inner.identity_id)
+        .ok_or_else(|| lan_err("local identity has no key"))?;
+    let partial = dir.join(format!(".{filename}.part"));
+    std::fs::write(&partial, bundle)?;
+    let path = dir.join(filename);
+    std::fs::rename(&partial, &path)?;
+    Ok(path)
+}
+
+/// Client side of one connection: authenticate both ways, then send.
+fn send_on(
+    inner: &Inner,
+    mut stream: TcpStream,
+    peer_identity_id: &str,
+    bundle: &[u8],
+) -> Result<(), KrillnotesError> {
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    let expected_key = protocol::decode_key(peer_identity_id)?;
+    let client_key = inner.signing_key.verifying_key();
+    let client_nonce = protocol::new_nonce();
+
+    protocol::write_message(
+        &mut stream,
+        &ClientHello {
+            protocol: PROTOCOL.to_string(),
+            identity: inner.identity_id.clone(),
+            nonce: BASE64.encode(client_nonce),
+        },
+    )?;
+    let hello: ServerHello = protocol::read_message(&mut stream)?;
+    let server_key = protocol::decode_key(&hello.identity)?;
+    if server_key != expected_key {
+        return Err(lan_err("peer answered with a different identity"));
+    }
+    let server_nonce = protocol::decode_nonce(&hello.nonce)?;
+    let server_transcript = protocol::transcript(
+        "server",
+        &client_nonce,
+        &server_nonce,
+        &client_key,
+        &server_key,
+    );
+    if !protocol::verify(&server_key, &server_transcript, &hello.signature) {
+        return Err(lan_err("peer failed authentication"));
+    }
+
+    let client_transcript = protocol::transcript(
+        "client",
+        &client_nonce,
+        &server_nonce,
+        &client_key,
+        &server_key,
+    );
+    protocol::write_message(
+        &mut stream,
+        &ClientProof {
+            signature: protocol::sign(&inner.signing_key, &client_transcript),
+        },
+    )?;
+    expect_ok(protocol::read_message(&mut stream)?)?;
+
+    protocol::write_frame(&mut stream, bundle)?;
+    expect_ok(protocol::read_message(&mut stream)?)
+}
+
+fn expect_ok(status: Status) -> Result<(), KrillnotesError> {
+    if status.ok {
+        Ok(())
+    } else {
+        Err(lan_err(format!(
+            "peer refused transfer: {}",
+            status.error.unwrap_or_default()
+        )))
+    }
+}
+
+// ── LanChannel ───────────────────────────────────────────────────────────────
+
+/// [`SyncChannel`] adapter over a running [`LanService`].
+pub struct LanChannel {
+    service: Arc<LanService>,
+}
+
+impl LanChannel {
+    pub fn new(service: Arc<LanService>) -> Self {
+        Self { service }
+    }
+
+    /// The pinned `address` from `channel_params`, else the discovered one.
+    fn resolve(&self, peer: &PeerSyncInfo) -> Option<SocketAddr> {
+        match peer.channel_params.get("address").and_then(|v| v.as_str()) {
+            Some(address) => address.to_socket_addrs().ok()?.next(),
+            None => self.service.peer_address(&peer.peer_identity_id),
+        }
+    }
+
+    fn spool_dir(&self, workspace_id: &str) -> Option<PathBuf> {
+        is_safe_segment(workspace_id).then(|| self.service.inner.spool_dir.join(workspace_id))
+    }
+}
+
+impl SyncChannel for LanChannel {
+    fn send_bundle(
+        &self,
+        peer: &PeerSyncInfo,
+        bundle_bytes: &[u8],
+    ) -> Result<SendResult, KrillnotesError> {
+        let Some(addr) = self.resolve(peer) else {
+            return Ok(SendResult::NotDelivered {
+                reason: "peer not found on the local network".to_string(),
+            });
+        };
+        let stream = match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+            Ok(stream) => stream,
+            // Peers come and go; an unreachable one is retried next poll.
+            Err(e) => {
+                return Ok(SendResult::NotDelivered {
+                    reason: format!("peer unreachable at {addr}: {e}"),
+                })
+            }
+        };
+        send_on(
+            &self.service.inner,
+            stream,
+            &peer.peer_identity_id,
+            bundle_bytes,
+        )?;
+        Ok(SendResult::Delivered)
+    }
+
+    fn receive_bundles(&self, workspace_id: &str) -> Result<Vec<BundleRef>, KrillnotesError> {
+        let Some(dir) = self.spool_dir(workspace_id).filter(|d| d.is_dir()) else {
+            return Ok(Vec::new());
+        };
+        FolderChannel::read_inbox(
+            &dir,
+            &FolderChannel::identity_short(&self.service.inner.identity_id),
+        )
+    }
+
+    fn acknowledge(&self, bundle: &BundleRef) -> Result<(), KrillnotesError> {
+        let path = Path::new(&bundle.id);
+        if !path.starts_with(&self.service.inner.spool_dir) {
+            return Err(lan_err("bundle is not in the LAN spool"));
+        }
+        match std::fs::remove_file(path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn channel_type(&self) -> ChannelType {
+        ChannelType::Lan
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
@@ -0,0 +1,230 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Wire protocol for direct LAN bundle transfer.
+//!
+//! Every message is a frame: a 4-byte big-endian length followed by the
+//! payload. A transfer is one TCP connection:
+//!
+//! 1. client → [`ClientHello`] (identity key + random nonce)
+//! 2. server → [`ServerHello`] (identity key + nonce + signature over the transcript)
+//! 3. client → [`ClientProof`] (signature over the transcript)
+//! 4. server → [`Status`] (whether the client is an accepted contact)
+//! 5. client → raw `.swarm` bundle bytes
+//! 6. server → [`Status`] (whether the bundle was stored)
+//!
+//! The two signatures cover both nonces and both keys with distinct role
+//! labels, so each side proves possession of its identity key and neither
+//! signature can be replayed or reflected. The channel itself is not
+//! encrypted: bundles are already end-to-end encrypted and signed.
+
+use std::io::{Read, Write};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::core::error::KrillnotesError;
+
+/// Protocol identifier carried in [`ClientHello`].
+pub const PROTOCOL: &str = "krillnotes-lan/1";
+
+/// Largest handshake message accepted.
+const MAX_MESSAGE_BYTES: u32 = 16 * 1024;
+
+/// Largest bundle accepted.
+pub const MAX_BUNDLE_BYTES: u32 = 256 * 1024 * 1024;
+
+const TRANSCRIPT_LABEL: &[u8] = b"krillnotes-lan-v1\0";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClientHello {
+    pub protocol: String,
+    /// Client identity public key, base64.
+    pub identity: String,
+    /// 32 random bytes, base64.
+    pub nonce: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ServerHello {
+    pub identity: String,
+    pub nonce: String,
+    /// Server signature over the `"server"` transcript, base64.
+    pub signature: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClientProof {
+    /// Client signature over the `"client"` transcript, base64.
+    pub signature: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Status {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Status {
+    pub fn ok() -> Self {
+        Self {
+            ok: true,
+            error: None,
+        }
+    }
+
+    pub fn error(msg: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            error: Some(msg.into()),
+        }
+    }
+}
+
+fn lan_err(msg: impl std::fmt::Display) -> KrillnotesError {
+    KrillnotesError::Lan(msg.to_string())
+}
+
+fn io_err(e: std::io::Error) -> KrillnotesError {
+    lan_err(format!("connection failed: {e}"))
+}
+
+// ── Framing ──────────────────────────────────────────────────────────────────
+
+pub fn write_frame(stream: &mut impl Write, payload: &[u8]) -> Result<(), KrillnotesError> {
+    let len = u32::try_from(payload.len()).map_err(|_| lan_err("frame too large"))?;
+    stream.write_all(&len.to_be_bytes()).map_err(io_err)?;
+    stream.write_all(payload).map_err(io_err)?;
+    stream.flush().map_err(io_err)
+}
+
+pub fn read_frame(stream: &mut impl Read, max: u32) -> Result<Vec<u8>, KrillnotesError> {
+    let mut len = [0u8; 4];
+    stream.read_exact(&mut len).map_err(io_err)?;
+    let len = u32::from_be_bytes(len);
+    if len > max {
+        return Err(lan_err(format!(
+            "frame of {len} bytes exceeds limit of {max}"
+        )));
+    }
+    let mut buf = vec![0u8; len as usize];
+    stream.read_exact(&mut buf).map_err(io_err)?;
+    Ok(buf)
+}
+
+pub fn write_message<T: Serialize>(
+    stream: &mut impl Write,
+    message: &T,
+) -> Result<(), KrillnotesError> {
+    write_frame(stream, &serde_json::to_vec(message)?)
+}
+
+pub fn read_message<T: DeserializeOwned>(stream: &mut impl Read) -> Result<T, KrillnotesError> {
+    let frame = read_frame(stream, MAX_MESSAGE_BYTES)?;
+    serde_json::from_slice(&frame).map_err(|e| lan_err(format!("malformed message: {e}")))
+}
+
+// ── Handshake crypto ─────────────────────────────────────────────────────────
+
+pub fn new_nonce() -> [u8; 32] {
+    use rand::RngCore;
+    let mut nonce = [0u8; 32];
+    rand::rng().fill_bytes(&mut nonce);
+    nonce
+}
+
+/// The bytes each side signs: role label, both nonces, both identity keys.
+pub fn transcript(
+    role: &str,
+    client_nonce: &[u8],
+    server_nonce: &[u8],
+    client_key: &VerifyingKey,
+    server_key: &VerifyingKey,
+) -> Vec<u8> {
+    let mut t = Vec::with_capacity(TRANSCRIPT_LABEL.len() + role.len() + 1 + 128);
+    t.extend_from_slice(TRANSCRIPT_LABEL);
+    t.extend_from_slice(role.as_bytes());
+    t.push(0);
+    t.extend_from_slice(client_nonce);
+    t.extend_from_slice(server_nonce);
+    t.extend_from_slice(client_key.as_bytes());
+    t.extend_from_slice(server_key.as_bytes());
+    t
+}
+
+pub fn sign(key: &SigningKey, message: &[u8]) -> String {
+    BASE64.encode(key.sign(message).to_bytes())
+}
+
+pub fn verify(key: &VerifyingKey, message: &[u8], signature_b64: &str) -> bool {
+    let Ok(bytes) = BASE64.decode(signature_b64) else {
+        return false;
+    };
+    let Ok(signature) = Signature::from_slice(&bytes) else {
+        return false;
+    };
+    key.verify(message, &signature).is_ok()
+}
+
+pub fn decode_key(b64: &str) -> Result<VerifyingKey, KrillnotesError> {
+    let bytes: [u8; 32] = BASE64
+        .decode(b64)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| lan_err("invalid identity key"))?;
+    VerifyingKey::from_bytes(&bytes).map_err(|_| lan_err("invalid identity key"))
+}
+
+pub fn decode_nonce(b64: &str) -> Result<Vec<u8>, KrillnotesError> {
+    BASE64
+        .decode(b64)
+        .ok()
+        .filter(|n| n.len() == 32)
+        .ok_or_else(|| lan_err("invalid nonce"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_core::OsRng;
+
+    #[test]
+    fn test_frame_roundtrip_and_limit() {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, b"hello").unwrap();
+        assert_eq!(read_frame(&mut buf.as_slice(), 16).unwrap(), b"hello");
+        assert!(read_frame(&mut buf.as_slice(), 4).is_err());
+    }
+
+    #[test]
+    fn test_transcript_signatures_are_role_bound() {
+        let client = SigningKey::generate(&mut OsRng);
+        let server = SigningKey::generate(&mut OsRng);
+        let (cn, sn) = (new_nonce(), new_nonce());
+        let server_t = transcript(
+            "server",
+            &cn,
+            &sn,
+            &client.verifying_key(),
+            &server.verifying_key(),
+        );
+        let client_t = transcript(
+            "client",
+            &cn,
+            &sn,
+            &client.verifying_key(),
+            &server.verifying_key(),
+        );
+        let sig = sign(&server, &server_t);
+        assert!(verify(&server.verifying_key(), &server_t, &sig));
+        // A server signature cannot stand in for the client's proof.
+        assert!(!verify(&server.verifying_key(), &client_t, &sig));
+        assert!(!verify(&client.verifying_key(), &server_t, &sig));
+        assert!(!verify(&server.verifying_key(), &server_t, "not base64!"));
+    }
+}
@@ -4,19 +4,29 @@
 //
 // Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
 
+mod account_store;
 pub mod channel;
 pub mod folder;
+pub mod git;
+pub mod lan;
 pub mod manual;
 
 pub mod receive_poll;
 pub mod relay;
+pub mod s3;
+pub mod scheduler;
+pub mod webdav;
 
 pub use channel::{BundleRef, ChannelType, PeerSyncInfo, SendResult, SyncChannel};
 pub use folder::FolderChannel;
+pub use git::GitChannel;
+pub use lan::{LanChannel, LanConfig, LanService};
+pub use scheduler::{SchedulerConfig, SyncDaemon, SyncScheduler};
 
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use ed25519_dalek::SigningKey;
 
 use crate::core::contact::{ContactManager, TrustLevel};
@@ -25,6 +35,10 @@ use crate::core::operation::Operation;
 use crate::core::swarm::delta::{parse_delta_bundle, ParsedDelta};
 use crate::core::swarm::header::{SwarmHeader, SwarmMode};
 use crate::core::swarm::sync::DeltaBundle;
+use crate::core::swarm::verification::{
+    create_verification_bundle, parse_verification_bundle, VerificationBundleParams,
+};
+use crate::core::verification::SasCode;
 use crate::core::workspace::Workspace;
 
 // ── SyncEvent ──────────────────────────────────────────────────────────────
@@ -72,6 +86,14 @@ pub enum SyncEvent {
         peer_device_id: String,
         reason: String,
     },
+    /// A contact verification (SAS) message from a peer was processed.
+    /// `code` is set once both sides can compare it.
+    VerificationUpdated {
+        workspace_id: String,
+        peer_device_id: String,
+        session_id: String,
+        code: Option<SasCode>,
+    },
 }
 
 // ── SyncContext ─────────────────────────────────────────────────────────────
@@ -122,6 +144,19 @@ impl SyncEngine {
         &self,
         workspace: &mut Workspace,
         ctx: &mut SyncContext<'_>,
+    ) -> Result<Vec<SyncEvent>, KrillnotesError> {
+        self.poll_peers(workspace, ctx, &mut |_| true)
+    }
+
+    /// Like [`poll`](Self::poll), but only for the active peers `include`
+    /// accepts. Channels are only received from when at least one of their
+    /// peers is included. Used by the [`scheduler`] to honour per-channel
+    /// intervals and per-peer backoff.
+    pub fn poll_peers(
+        &self,
+        workspace: &mut Workspace,
+        ctx: &mut SyncContext<'_>,
+        include: &mut dyn FnMut(&PeerSyncInfo) -> bool,
     ) -> Result<Vec<SyncEvent>, KrillnotesError> {
         let mut events = Vec::new();
         let workspace_id = workspace.workspace_id().to_string();
@@ -158,7 +193,8 @@ impl SyncEngine {
             }
         }
 
-        let active_peers = workspace.get_active_sync_peers()?;
+        let mut active_peers = workspace.get_active_sync_peers()?;
+        active_peers.retain(|p| include(p));
 
         // Track peers that sent us bundles so we know which 0-op outbound
         // bundles actually need to carry a fresh ACK.
@@ -216,6 +252,20 @@ impl SyncEngine {
                 }
             }
 
+            // For GitChannel: likewise update the remotes to pull from
+            if *ct == ChannelType::Git {
+                if let Some(git) = channel.as_any().downcast_ref::<GitChannel>() {
+                    let remotes: Vec<git::GitRemote> = active_peers
+                        .iter()
+                        .filter(|p| p.channel_type == ChannelType::Git)
+                        .filter_map(|p| git::GitRemote::from_params(&p.channel_params).ok())
+                        .collect::<HashSet<_>>()
+                        .into_iter()
+                        .collect();
+                    git.set_remotes(remotes);
+                }
+            }
+
             log::debug!(target: "krillnotes::sync", "receiving bundles from channel {ct}");
             let bundles = match channel.receive_bundles(&workspace_id) {
                 Ok(b) => {
@@ -321,6 +371,47 @@ impl SyncEngine {
                             }
                         }
                     }
+                    SwarmMode::Verification => {
+                        let our_key = BASE64.encode(ctx.signing_key.verifying_key().as_bytes());
+                        if header.target_peer.as_deref() != Some(our_key.as_str()) {
+                            log::debug!(target: "krillnotes::sync", "skipping verification bundle for another identity");
+                            continue;
+                        }
+                        match parse_verification_bundle(&bundle_ref.data) {
+                            Ok(parsed) => {
+                                for msg in &parsed.messages {
+                                    match ctx
+                                        .contact_manager
+                                        .receive_sas_message(ctx.signing_key, msg)
+                                    {
+                                        Ok(code) => events.push(SyncEvent::VerificationUpdated {
+                                            workspace_id: workspace_id.clone(),
+                                            peer_device_id: parsed.source_device_id.clone(),
+                                            session_id: msg.session_id.clone(),
+                                            code,
+                                        }),
+                                        Err(e) => {
+                                            log::warn!(target: "krillnotes::sync", "verification message from peer {} rejected: {e}", parsed.source_device_id);
+                                            events.push(SyncEvent::IngestError {
+                                                workspace_id: workspace_id.clone(),
+                                                peer_device_id: parsed.source_device_id.clone(),
+                                                error: format!("verification: {e}"),
+                                            });
+                                        }
+                                    }
+                                }
+                            }
+                            Err(e) => {
+                                log::error!(target: "krillnotes::sync", "parse_verification_bundle failed for peer {}: {e}", header.source_device_id);
+                                events.push(SyncEvent::IngestError {
+                                    workspace_id: workspace_id.clone(),
+                                    peer_device_id: header.source_device_id.clone(),
+                                    error: format!("parse_verification_bundle: {e}"),
+                                });
+                            }
+                        }
+                        let _ = channel.acknowledge(&bundle_ref);
+                    }
                     other => {
                         log::debug!(target: "krillnotes::sync", "skipping bundle mode {:?} — handled by invite poller", other);
                         // Do NOT acknowledge Accept/Invite bundles here.
@@ -338,6 +429,7 @@ impl SyncEngine {
             sender_device_id: String,
             sender_public_key: String,
             verified_by: Option<String>,
+            scope_boundary: bool,
             bundle_ack: Option<String>,
             attachment_blobs: Arc<Vec<(String, Vec<u8>)>>, // shared ref to parent delta's blobs
         }
@@ -357,6 +449,7 @@ impl SyncEngine {
                         sender_device_id: sender.clone(),
                         sender_public_key: sender_pk.clone(),
                         verified_by: delta_op.verified_by.clone(),
+                        scope_boundary: delta_op.scope_boundary,
                         bundle_ack: ack.clone(),
                         attachment_blobs: Arc::clone(&blobs),
                     })
@@ -372,6 +465,32 @@ impl SyncEngine {
         let mut sender_skipped: HashMap<String, usize> = HashMap::new();
 
         for entry in &op_entries {
+            // Scope boundary ops are sender-signed state transfers, not log entries.
+            if entry.scope_boundary {
+                match workspace.apply_scope_boundary_operation(
+                    &entry.op,
+                    &entry.sender_public_key,
+                    &entry.attachment_blobs,
+                ) {
+                    Ok(true) => {
+                        *sender_applied
+                            .entry(entry.sender_device_id.clone())
+                            .or_insert(0) += 1;
+                    }
+                    Ok(false) => {
+                        *sender_skipped
+                            .entry(entry.sender_device_id.clone())
+                            .or_insert(0) += 1;
+                    }
+                    Err(e) => {
+                        log::error!(target: "krillnotes::sync",
+                            "apply_scope_boundary_operation failed for op {} from {}: {e}",
+                            entry.op.operation_id(), entry.sender_device_id);
+                    }
+                }
+                continue;
+            }
+
             // TOFU: auto-register unknown operation authors.
             let author_key = entry.op.author_key();
             if !author_key.is_empty()
@@ -429,7 +548,7 @@ impl SyncEngine {
         // entry for each sender holds their HLC-max op — the correct value for
         // last_received_op regardless of which bundle that op came from.
         let mut sender_last_op: HashMap<String, (String, Option<String>)> = HashMap::new();
-        for entry in &op_entries {
+        for entry in op_entries.iter().filter(|e| !e.scope_boundary) {
             sender_last_op.insert(
                 entry.sender_device_id.clone(),
                 (
@@ -448,6 +567,13 @@ impl SyncEngine {
                 let _ = channel.acknowledge(&pd.bundle_ref);
             }
 
+            if let Err(e) =
+                workspace.ingest_blob_traffic(sender, &pd.parsed.blob_wants, &pd.parsed.blob_chunks)
+            {
+                log::error!(target: "krillnotes::sync",
+                    "attachment transfer from {} failed: {e}", sender);
+            }
+
             if !upserted.contains(sender) {
                 upserted.insert(sender.clone());
                 let last_received = sender_last_op.get(sender).map(|(op_id, _)| op_id.as_str());
@@ -475,6 +601,20 @@ impl SyncEngine {
             });
         }
 
+        // Push the git removals of the bundles acknowledged above, one push
+        // per remote for the whole cycle.
+        if inbound_channel_types.contains(&ChannelType::Git) {
+            if let Some(git) = self
+                .channels
+                .get(&ChannelType::Git)
+                .and_then(|ch| ch.as_any().downcast_ref::<GitChannel>())
+            {
+                if let Err(e) = git.flush() {
+                    log::warn!(target: "krillnotes::sync", "git flush failed, removals are pushed on the next poll: {e}");
+                }
+            }
+        }
+
         // ACK-behind watermark check: for each sender, use the ack from their
         // HLC-max bundle (stored alongside the last op in sender_last_op).
         // For 0-op bundles (not in sender_last_op), use the bundle's own ack.
@@ -519,10 +659,12 @@ impl SyncEngine {
                 run_ack_check(sender, ack, workspace, &mut events, &workspace_id)?;
             }
         }
-        // Also check senders who only sent 0-op ack bundles (not in sender_last_op).
+        // Also check senders who only sent 0-op ack bundles (not in sender_last_op);
+        // scope boundary ops do not count as logged ops.
         for pd in &pending_deltas {
             let sender = &pd.parsed.sender_device_id;
-            if pd.parsed.delta_operations.is_empty() && ack_checked.insert(sender.clone()) {
+            let logged_ops = pd.parsed.delta_operations.iter().any(|d| !d.scope_boundary);
+            if !logged_ops && ack_checked.insert(sender.clone()) {
                 run_ack_check(
                     sender,
                     &pd.parsed.ack_operation_id,
@@ -536,9 +678,11 @@ impl SyncEngine {
         // ── 2. Outbound: generate + send deltas ────────────────────────────
         // Re-fetch peers: inbound upsert_peer_from_delta may have consolidated
         // placeholder "identity:…" rows into real device-UUID rows.
-        let active_peers = workspace.get_active_sync_peers()?;
+        let mut active_peers = workspace.get_active_sync_peers()?;
+        active_peers.retain(|p| include(p));
         log::debug!(target: "krillnotes::sync", "outbound: {} active peers", active_peers.len());
 
+        let mut wants_delivered: Vec<String> = Vec::new();
         for peer in &active_peers {
             // Mark peer as syncing
             let _ = workspace.update_peer_sync_status(&peer.peer_device_id, "syncing", None, None);
@@ -593,8 +737,12 @@ impl SyncEngine {
             };
 
             // Skip 0-op bundles unless we received from this peer in the
-            // inbound phase (meaning we have a fresh ACK to deliver).
-            if delta.op_count == 0 && !peers_with_inbound.contains(&peer.peer_device_id) {
+            // inbound phase (meaning we have a fresh ACK to deliver) or they
+            // move attachment content.
+            if delta.op_count == 0
+                && !delta.has_blob_traffic()
+                && !peers_with_inbound.contains(&peer.peer_device_id)
+            {
                 let _ = workspace.update_peer_sync_status(&peer.peer_device_id, "idle", None, None);
                 continue;
             }
@@ -614,6 +762,10 @@ impl SyncEngine {
                             None,
                         );
                     }
+                    let _ = workspace
+                        .record_peer_sent_scope(&peer.peer_device_id, delta.scope.as_ref());
+                    let _ = workspace.record_blob_progress(&peer.peer_device_id, &delta.blobs_sent);
+                    wants_delivered.extend(delta.blob_wants.iter().map(|w| w.hash_sha256.clone()));
                     let _ =
                         workspace.update_peer_sync_status(&peer.peer_device_id, "idle", None, None);
                     events.push(SyncEvent::DeltaSent {
@@ -671,6 +823,39 @@ impl SyncEngine {
                 }
             }
         }
+        if !wants_delivered.is_empty() {
+            let _ = workspace.mark_blob_wants_sent(&wants_delivered);
+        }
+
+        // ── 3. Outbound: contact verification messages ─────────────────────
+        for peer in &active_peers {
+            let messages = ctx
+                .contact_manager
+                .take_sas_messages_for(&peer.peer_identity_id);
+            if messages.is_empty() {
+                continue;
+            }
+            let Some(channel) = self.channels.get(&peer.channel_type) else {
+                ctx.contact_manager.requeue_sas_messages(messages);
+                continue;
+            };
+            let sent = create_verification_bundle(VerificationBundleParams {
+                protocol: workspace.protocol_id().to_string(),
+                workspace_id: workspace_id.clone(),
+                workspace_name: ctx.workspace_name.to_string(),
+                source_device_id: workspace.device_id().to_string(),
+                source_display_name: ctx.sender_display_name.to_string(),
+                recipient_key: peer.peer_identity_id.clone(),
+                messages: &messages,
+                sender_key: ctx.signing_key,
+            })
+            .and_then(|bundle| channel.send_bundle(peer, &bundle));
+            if !matches!(sent, Ok(SendResult::Delivered)) {
+                log::warn!(target: "krillnotes::sync",
+                    "verification messages not delivered to peer {}; will retry", peer.peer_device_id);
+                ctx.contact_manager.requeue_sas_messages(messages);
+            }
+        }
 
         log::info!(target: "krillnotes::sync", "poll complete for workspace {workspace_id}: {} events", events.len());
         Ok(events)
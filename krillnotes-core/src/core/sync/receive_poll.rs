@@ -14,10 +14,10 @@ use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use uuid::Uuid;
 
-use crate::core::error::KrillnotesError;
 use crate::core::received_response::ReceivedResponse;
 
-type Result<T> = std::result::Result<T, KrillnotesError>;
+#[cfg(feature = "relay")]
+type Result<T> = std::result::Result<T, crate::core::error::KrillnotesError>;
 
 // ── Result types ─────────────────────────────────────────────────────────────
 
@@ -760,6 +760,24 @@ impl RelayClient {
         })?;
         Self::handle_response(resp)
     }
+
+    /// Revoke one of this account's device keys (hex). The relay drops its
+    /// pending bundles, ends the account's other sessions and refuses the
+    /// key from then on.
+    pub fn remove_device(&self, device_public_key: &str) -> Result<(), KrillnotesError> {
+        log::debug!(target: "krillnotes::relay", "DELETE {}/account/devices/{device_public_key}", self.base_url);
+        let auth = self.auth_header()?;
+        let resp = self
+            .http
+            .delete(self.url(&format!("/account/devices/{device_public_key}")))
+            .header("Authorization", auth)
+            .send()
+            .map_err(|e| {
+                log::error!(target: "krillnotes::relay", "remove_device request failed: {e}");
+                KrillnotesError::RelayUnavailable(e.to_string())
+            })?;
+        Self::handle_empty(resp)
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,355 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! S3 HTTP client — the object operations the sync channel needs, signed
+//! with SigV4.
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use reqwest::blocking::{RequestBuilder, Response};
+use reqwest::{Method, StatusCode, Url};
+
+use super::s3_account::S3Account;
+use super::sigv4;
+use crate::core::error::KrillnotesError;
+
+/// Thin blocking S3 client for one bucket (and key prefix).
+///
+/// Keys passed to the methods are relative to the account prefix.
+pub struct S3Client {
+    http: reqwest::blocking::Client,
+    bucket_url: Url,
+    prefix: String,
+    region: String,
+    access_key_id: String,
+    secret_access_key: String,
+}
+
+impl S3Client {
+    /// Create a client for the bucket described by `account`.
+    pub fn new(account: &S3Account) -> Result<Self, KrillnotesError> {
+        let endpoint = Url::parse(account.endpoint.trim_end_matches('/')).map_err(|e| {
+            KrillnotesError::S3(format!("invalid endpoint {}: {e}", account.endpoint))
+        })?;
+        if account.bucket.is_empty() {
+            return Err(KrillnotesError::S3("bucket name is empty".to_string()));
+        }
+        let bucket_url = if account.path_style {
+            let base = endpoint.as_str().trim_end_matches('/');
+            format!("{base}/{}/", sigv4::uri_encode(&account.bucket, true))
+        } else {
+            format!(
+                "{}://{}.{}/",
+                endpoint.scheme(),
+                account.bucket,
+                sigv4::host_header(&endpoint)
+            )
+        };
+        let bucket_url = Url::parse(&bucket_url)
+            .map_err(|e| KrillnotesError::S3(format!("invalid bucket URL {bucket_url}: {e}")))?;
+
+        let prefix = account.prefix.trim_matches('/');
+        let prefix = if prefix.is_empty() {
+            String::new()
+        } else {
+            format!("{prefix}/")
+        };
+        Ok(Self {
+            http: reqwest::blocking::Client::builder()
+                .user_agent(concat!("KrillNotes/", env!("CARGO_PKG_VERSION")))
+                .build()
+                .expect("failed to build HTTP client"),
+            bucket_url,
+            prefix,
+            region: account.region.clone(),
+            access_key_id: account.access_key_id.clone(),
+            secret_access_key: account.secret_access_key.clone(),
+        })
+    }
+
+    /// The bucket URL, always ending in `/`.
+    pub fn bucket_url(&self) -> &str {
+        self.bucket_url.as_str()
+    }
+
+    // ── Private helpers ──────────────────────────────────────────────────────
+
+    fn object_url(&self, key: &str) -> Result<Url, KrillnotesError> {
+        let path = sigv4::uri_encode(&format!("{}{key}", self.prefix), false);
+        self.bucket_url
+            .join(&path)
+            .map_err(|e| KrillnotesError::S3(format!("invalid key {key}: {e}")))
+    }
+
+    /// Builds a SigV4-signed request carrying `body`.
+    fn request(&self, method: &str, url: Url, body: Vec<u8>) -> RequestBuilder {
+        let amz_date = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let content_sha256 = sigv4::payload_hash(&body);
+        let authorization = sigv4::authorization(
+            method,
+            &url,
+            &[
+                ("host", &sigv4::host_header(&url)),
+                ("x-amz-content-sha256", &content_sha256),
+                ("x-amz-date", &amz_date),
+            ],
+            &content_sha256,
+            &self.access_key_id,
+            &self.secret_access_key,
+            &self.region,
+            &amz_date,
+        );
+        let method = Method::from_bytes(method.as_bytes()).expect("valid S3 method");
+        self.http
+            .request(method, url)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", content_sha256)
+            .header("Authorization", authorization)
+            .body(body)
+    }
+
+    fn send(&self, request: RequestBuilder, what: &str) -> Result<Response, KrillnotesError> {
+        request.send().map_err(|e| {
+            log::warn!(target: "krillnotes::sync::s3", "{what} failed: {e}");
+            KrillnotesError::S3(format!("{what}: {e}"))
+        })
+    }
+
+    /// Turns an error response into a `KrillnotesError`, using the S3
+    /// `<Error><Code>` when the body carries one.
+    fn status_error(resp: Response, what: &str) -> KrillnotesError {
+        let status = resp.status().as_u16();
+        let code = resp
+            .text()
+            .ok()
+            .and_then(|body| parse_error_code(&body))
+            .map(|code| format!(" {code}"))
+            .unwrap_or_default();
+        match status {
+            401 | 403 => KrillnotesError::S3(format!(
+                "{what}: access denied (HTTP {status}{code}) — check the access key and secret"
+            )),
+            _ => KrillnotesError::S3(format!("{what}: HTTP {status}{code}")),
+        }
+    }
+
+    fn list_page(
+        &self,
+        prefix: &str,
+        continuation: Option<&str>,
+        max_keys: Option<u32>,
+    ) -> Result<ListPage, KrillnotesError> {
+        let mut query = format!(
+            "list-type=2&prefix={}",
+            sigv4::uri_encode(&format!("{}{prefix}", self.prefix), true)
+        );
+        if let Some(token) = continuation {
+            query.push_str(&format!(
+                "&continuation-token={}",
+                sigv4::uri_encode(token, true)
+            ));
+        }
+        if let Some(max) = max_keys {
+            query.push_str(&format!("&max-keys={max}"));
+        }
+        let mut url = self.bucket_url.clone();
+        url.set_query(Some(&query));
+
+        let what = format!("LIST {}{prefix}", self.prefix);
+        let resp = self.send(self.request("GET", url, Vec::new()), &what)?;
+        if !resp.status().is_success() {
+            return Err(Self::status_error(resp, &what));
+        }
+        let body = resp
+            .text()
+            .map_err(|e| KrillnotesError::S3(format!("{what}: {e}")))?;
+        parse_list_objects(&body).map_err(|e| KrillnotesError::S3(format!("{what}: {e}")))
+    }
+
+    // ── Object operations ────────────────────────────────────────────────────
+
+    /// `PutObject` — upload `bytes` under `key`.
+    pub fn put_object(&self, key: &str, bytes: &[u8]) -> Result<(), KrillnotesError> {
+        let url = self.object_url(key)?;
+        let what = format!("PUT {key}");
+        let resp = self.send(self.request("PUT", url, bytes.to_vec()), &what)?;
+        if resp.status().is_success() {
+            Ok(())
+        } else {
+            Err(Self::status_error(resp, &what))
+        }
+    }
+
+    /// `GetObject` — download the object at `key`.
+    pub fn get_object(&self, key: &str) -> Result<Vec<u8>, KrillnotesError> {
+        let url = self.object_url(key)?;
+        let what = format!("GET {key}");
+        let resp = self.send(self.request("GET", url, Vec::new()), &what)?;
+        if !resp.status().is_success() {
+            return Err(Self::status_error(resp, &what));
+        }
+        resp.bytes()
+            .map(|b| b.to_vec())
+            .map_err(|e| KrillnotesError::S3(format!("{what}: {e}")))
+    }
+
+    /// `DeleteObject` — remove the object at `key`. Succeeds if it is already gone.
+    pub fn delete_object(&self, key: &str) -> Result<(), KrillnotesError> {
+        let url = self.object_url(key)?;
+        let what = format!("DELETE {key}");
+        let resp = self.send(self.request("DELETE", url, Vec::new()), &what)?;
+        if resp.status().is_success() || resp.status() == StatusCode::NOT_FOUND {
+            Ok(())
+        } else {
+            Err(Self::status_error(resp, &what))
+        }
+    }
+
+    /// `ListObjectsV2` — all keys under `prefix`, following continuation tokens.
+    pub fn list_objects(&self, prefix: &str) -> Result<Vec<String>, KrillnotesError> {
+        let mut keys = Vec::new();
+        let mut continuation: Option<String> = None;
+        loop {
+            let page = self.list_page(prefix, continuation.as_deref(), None)?;
+            keys.extend(
+                page.keys
+                    .into_iter()
+                    .filter_map(|k| k.strip_prefix(&self.prefix).map(str::to_string)),
+            );
+            match page.next_continuation_token {
+                Some(token) if page.is_truncated => continuation = Some(token),
+                _ => break,
+            }
+        }
+        Ok(keys)
+    }
+
+    /// Checks that the bucket exists and the credentials may list it.
+    pub fn check_access(&self) -> Result<(), KrillnotesError> {
+        self.list_page("", None, Some(1)).map(|_| ())
+    }
+}
+
+/// One page of a `ListObjectsV2` response.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct ListPage {
+    pub keys: Vec<String>,
+    pub is_truncated: bool,
+    pub next_continuation_token: Option<String>,
+}
+
+/// Parses a `ListBucketResult` body. Elements are matched by local name so
+/// the namespace declaration does not matter.
+pub(crate) fn parse_list_objects(xml: &str) -> Result<ListPage, String> {
+    let mut reader = Reader::from_str(xml);
+    let mut page = ListPage::default();
+    let mut path: Vec<String> = Vec::new();
+    loop {
+        match reader.read_event().map_err(|e| e.to_string())? {
+            Event::Start(e) => {
+                path.push(String::from_utf8_lossy(e.local_name().as_ref()).into_owned());
+            }
+            Event::End(_) => {
+                path.pop();
+            }
+            Event::Text(t) => {
+                let text = t.unescape().map_err(|e| e.to_string())?;
+                let parent = path.len().checked_sub(2).map(|i| path[i].as_str());
+                match (parent, path.last().map(String::as_str)) {
+                    (Some("Contents"), Some("Key")) => page.keys.push(text.into_owned()),
+                    (_, Some("IsTruncated")) => page.is_truncated = text.trim() == "true",
+                    (_, Some("NextContinuationToken")) => {
+                        page.next_continuation_token = Some(text.into_owned())
+                    }
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(page)
+}
+
+/// Extracts `<Error><Code>` from an S3 error body, if present.
+fn parse_error_code(xml: &str) -> Option<String> {
+    let mut reader = Reader::from_str(xml);
+    let mut in_code = false;
+    loop {
+        match reader.read_event().ok()? {
+            Event::Start(e) => in_code = e.local_name().as_ref() == b"Code",
+            Event::End(_) => in_code = false,
+            Event::Text(t) if in_code => return t.unescape().ok().map(|c| c.into_owned()),
+            Event::Eof => return None,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(endpoint: &str, path_style: bool, prefix: &str) -> S3Account {
+        S3Account {
+            s3_account_id: uuid::Uuid::nil(),
+            endpoint: endpoint.to_string(),
+            region: "us-east-1".to_string(),
+            bucket: "team-sync".to_string(),
+            prefix: prefix.to_string(),
+            path_style,
+            access_key_id: "AKID".to_string(),
+            secret_access_key: "secret".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_list_objects() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <Name>team-sync</Name>
+  <Prefix>ws/inbox/</Prefix>
+  <KeyCount>2</KeyCount>
+  <IsTruncated>true</IsTruncated>
+  <NextContinuationToken>1ueGcxLPRx1Tr/XYExHnhbYLgveDs2J/wm36Hy4vbOwM=</NextContinuationToken>
+  <Contents><Key>ws/inbox/a.swarm</Key><Size>10</Size></Contents>
+  <Contents><Key>ws/inbox/b&amp;c.swarm</Key><Size>12</Size></Contents>
+</ListBucketResult>"#;
+        let page = parse_list_objects(xml).unwrap();
+        assert_eq!(page.keys, vec!["ws/inbox/a.swarm", "ws/inbox/b&c.swarm"]);
+        assert!(page.is_truncated);
+        assert_eq!(
+            page.next_continuation_token.as_deref(),
+            Some("1ueGcxLPRx1Tr/XYExHnhbYLgveDs2J/wm36Hy4vbOwM=")
+        );
+        assert_eq!(
+            parse_error_code("<Error><Code>SignatureDoesNotMatch</Code></Error>").as_deref(),
+            Some("SignatureDoesNotMatch")
+        );
+    }
+
+    #[test]
+    fn test_bucket_and_object_urls() {
+        let path = S3Client::new(&account("http://127.0.0.1:9000/", true, "/krill/")).unwrap();
+        assert_eq!(path.bucket_url(), "http://127.0.0.1:9000/team-sync/");
+        assert_eq!(
+            path.object_url("ws/inbox/x.swarm").unwrap().as_str(),
+            "http://127.0.0.1:9000/team-sync/krill/ws/inbox/x.swarm"
+        );
+
+        let virt =
+            S3Client::new(&account("https://s3.eu-west-1.amazonaws.com", false, "")).unwrap();
+        assert_eq!(
+            virt.bucket_url(),
+            "https://team-sync.s3.eu-west-1.amazonaws.com/"
+        );
+        assert_eq!(
+            virt.object_url("a b.swarm").unwrap().as_str(),
+            "https://team-sync.s3.eu-west-1.amazonaws.com/a%20b.swarm"
+        );
+        assert!(S3Client::new(&account("not a url", true, "")).is_err());
+    }
+}
@@ -0,0 +1,187 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! S3 sync channel (AWS S3, MinIO, any SigV4-compatible object store).
+//!
+//! Peers sharing a bucket exchange bundles under per-recipient key prefixes
+//! `[<prefix>/]<workspace_id>/<recipient>/<ts>_<id>.swarm`, where `<recipient>`
+//! is the recipient's inbox name (see [`inbox_name`]). `PutObject` is atomic,
+//! so a receiver never sees a half-written bundle.
+
+pub mod s3_account;
+
+#[cfg(feature = "s3")]
+pub mod client;
+#[cfg(feature = "s3")]
+pub mod sigv4;
+
+pub use s3_account::{S3Account, S3AccountManager};
+
+#[cfg(feature = "s3")]
+pub use client::S3Client;
+
+pub use crate::core::sync::webdav::inbox_name;
+
+#[cfg(feature = "s3")]
+use crate::core::error::KrillnotesError;
+#[cfg(feature = "s3")]
+use crate::core::sync::channel::{BundleRef, ChannelType, PeerSyncInfo, SendResult, SyncChannel};
+#[cfg(feature = "s3")]
+use uuid::Uuid;
+
+#[cfg(feature = "s3")]
+pub struct S3Channel {
+    /// This identity's inbox name.
+    inbox: String,
+    /// One client per configured account; peers pick theirs via
+    /// `channel_params.s3_account_id`.
+    accounts: Vec<(Uuid, S3Client)>,
+}
+
+#[cfg(feature = "s3")]
+impl S3Channel {
+    /// `identity_id` is the local identity's base64 public key.
+    pub fn new(identity_id: &str) -> Self {
+        Self {
+            inbox: inbox_name(identity_id),
+            accounts: Vec::new(),
+        }
+    }
+
+    /// Add an account whose bucket this channel sends to and polls.
+    pub fn add_account(&mut self, account: &S3Account) -> Result<(), KrillnotesError> {
+        let client = S3Client::new(account)?;
+        self.accounts.push((account.s3_account_id, client));
+        Ok(())
+    }
+
+    fn client(&self, id: Uuid) -> Result<&S3Client, KrillnotesError> {
+        self.accounts
+            .iter()
+            .find(|(account_id, _)| *account_id == id)
+            .map(|(_, client)| client)
+            .ok_or_else(|| KrillnotesError::S3(format!("no S3 account {id} configured")))
+    }
+
+    fn client_for(&self, peer: &PeerSyncInfo) -> Result<&S3Client, KrillnotesError> {
+        let id = peer
+            .channel_params
+            .get("s3_account_id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| {
+                KrillnotesError::S3(
+                    "S3 channel peer missing 's3_account_id' in channel_params".to_string(),
+                )
+            })?;
+        let id = Uuid::parse_str(id)
+            .map_err(|e| KrillnotesError::S3(format!("invalid s3_account_id: {e}")))?;
+        self.client(id)
+    }
+
+    fn receive_from(
+        &self,
+        account_id: Uuid,
+        client: &S3Client,
+        workspace_id: &str,
+    ) -> Result<Vec<BundleRef>, KrillnotesError> {
+        let keys = client.list_objects(&format!("{workspace_id}/{}/", self.inbox))?;
+        let mut bundles = Vec::new();
+        for key in keys {
+            if !key.ends_with(".swarm") {
+                continue;
+            }
+            match client.get_object(&key) {
+                Ok(data) => {
+                    log::debug!(target: "krillnotes::sync::s3", "downloaded {key} ({} bytes)", data.len());
+                    // The account id lets acknowledge() find the bucket again.
+                    bundles.push(BundleRef {
+                        id: format!("{account_id}:{key}"),
+                        data,
+                    });
+                }
+                Err(e) => {
+                    // Removed by another poller — nothing to apply.
+                    log::debug!(target: "krillnotes::sync::s3", "skipping {key}: {e}");
+                }
+            }
+        }
+        Ok(bundles)
+    }
+}
+
+#[cfg(feature = "s3")]
+impl SyncChannel for S3Channel {
+    fn send_bundle(
+        &self,
+        peer: &PeerSyncInfo,
+        bundle_bytes: &[u8],
+    ) -> Result<SendResult, KrillnotesError> {
+        let client = self.client_for(peer)?;
+        let recipient = inbox_name(&peer.peer_identity_id);
+        if recipient.is_empty() {
+            return Err(KrillnotesError::S3(
+                "S3 channel peer has no identity key".to_string(),
+            ));
+        }
+        // The workspace id travels inside the bundle; read it back so the
+        // key prefix matches what the receiver lists.
+        let workspace_id = crate::core::swarm::header::read_header(bundle_bytes)?.workspace_id;
+
+        let timestamp = chrono::Utc::now().format("%Y%m%d%H%M%S");
+        let uuid_short: String = Uuid::new_v4()
+            .simple()
+            .to_string()
+            .chars()
+            .take(8)
+            .collect();
+        let key = format!("{workspace_id}/{recipient}/{timestamp}_{uuid_short}.swarm");
+        client.put_object(&key, bundle_bytes)?;
+
+        log::info!(target: "krillnotes::sync::s3", "uploaded bundle to {}{key} ({} bytes)", client.bucket_url(), bundle_bytes.len());
+        Ok(SendResult::Delivered)
+    }
+
+    fn receive_bundles(&self, workspace_id: &str) -> Result<Vec<BundleRef>, KrillnotesError> {
+        let mut bundles = Vec::new();
+        let mut failures = Vec::new();
+        for (id, client) in &self.accounts {
+            match self.receive_from(*id, client, workspace_id) {
+                Ok(found) => bundles.extend(found),
+                Err(e) => {
+                    log::warn!(target: "krillnotes::sync::s3", "skipping S3 account {id}: {e}");
+                    failures.push(e);
+                }
+            }
+        }
+        // Surface the failure when no account could be polled at all.
+        if !failures.is_empty() && failures.len() == self.accounts.len() {
+            return Err(failures.remove(0));
+        }
+        log::info!(target: "krillnotes::sync::s3", "found {} bundles for workspace {workspace_id}", bundles.len());
+        Ok(bundles)
+    }
+
+    fn acknowledge(&self, bundle_ref: &BundleRef) -> Result<(), KrillnotesError> {
+        let (account_id, key) = bundle_ref
+            .id
+            .split_once(':')
+            .and_then(|(id, key)| Some((Uuid::parse_str(id).ok()?, key)))
+            .ok_or_else(|| {
+                KrillnotesError::S3(format!("invalid S3 bundle id {}", bundle_ref.id))
+            })?;
+        self.client(account_id)?.delete_object(key)?;
+        log::debug!(target: "krillnotes::sync::s3", "acknowledged and deleted {key}");
+        Ok(())
+    }
+
+    fn channel_type(&self) -> ChannelType {
+        ChannelType::S3
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
@@ -0,0 +1,205 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Per-identity S3 account management.
+//!
+//! Each account is stored as an AES-256-GCM encrypted JSON file in the
+//! per-identity S3 directory (`identities/<uuid>/s3/<account_id>.json`)
+//! by the shared `AccountStore`.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use crate::core::sync::account_store::{AccountStore, StoredAccount};
+use crate::{KrillnotesError, Result};
+
+/// Credentials and location of one S3-compatible bucket (AWS, MinIO, …).
+///
+/// Stored at `<s3_dir>/<s3_account_id>.json` (encrypted).
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct S3Account {
+    pub s3_account_id: Uuid,
+    /// Service endpoint, e.g. `https://s3.eu-west-1.amazonaws.com` or a MinIO URL.
+    pub endpoint: String,
+    /// SigV4 signing region (`us-east-1` for most MinIO deployments).
+    pub region: String,
+    pub bucket: String,
+    /// Optional key prefix, so several teams can share one bucket.
+    #[serde(default)]
+    pub prefix: String,
+    /// Address the bucket as `<endpoint>/<bucket>/` instead of
+    /// `<bucket>.<endpoint host>/` (required by most MinIO setups).
+    #[serde(default)]
+    pub path_style: bool,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+}
+
+impl std::fmt::Debug for S3Account {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("S3Account")
+            .field("s3_account_id", &self.s3_account_id)
+            .field("endpoint", &self.endpoint)
+            .field("region", &self.region)
+            .field("bucket", &self.bucket)
+            .field("prefix", &self.prefix)
+            .field("path_style", &self.path_style)
+            .field("access_key_id", &self.access_key_id)
+            .field("secret_access_key", &"[REDACTED]")
+            .finish()
+    }
+}
+
+impl Drop for S3Account {
+    fn drop(&mut self) {
+        use zeroize::Zeroize;
+        self.secret_access_key.zeroize();
+    }
+}
+
+impl StoredAccount for S3Account {
+    fn account_id(&self) -> Uuid {
+        self.s3_account_id
+    }
+}
+
+/// Manages the S3 accounts directory for a single identity.
+///
+/// Mirrors [`WebDavAccountManager`](crate::core::sync::webdav::WebDavAccountManager):
+/// all accounts are decrypted into an in-memory cache on construction, and
+/// writes update the cache after a successful disk write.
+pub struct S3AccountManager {
+    store: AccountStore<S3Account>,
+}
+
+impl S3AccountManager {
+    /// Per-identity constructor — accounts are AES-256-GCM encrypted with `key`.
+    ///
+    /// Creates `s3_dir` if needed and fails if an existing file cannot be
+    /// decrypted (e.g. wrong key).
+    pub fn for_identity(s3_dir: PathBuf, key: [u8; 32]) -> Result<Self> {
+        Ok(Self {
+            store: AccountStore::open(s3_dir, key, KrillnotesError::S3)?,
+        })
+    }
+
+    /// Returns the on-disk path for the given S3 account UUID.
+    pub fn path_for(&self, id: Uuid) -> PathBuf {
+        self.store.path_for(id)
+    }
+
+    /// Persist a new S3 account, assigning it a fresh id.
+    ///
+    /// Returns an error if an account for the same endpoint, bucket and
+    /// prefix already exists.
+    pub fn create_s3_account(&self, mut account: S3Account) -> Result<S3Account> {
+        if let Some(existing) =
+            self.find_by_bucket(&account.endpoint, &account.bucket, &account.prefix)?
+        {
+            return Err(KrillnotesError::S3(format!(
+                "An S3 account for bucket {} at {} already exists (id: {})",
+                existing.bucket, existing.endpoint, existing.s3_account_id
+            )));
+        }
+        account.s3_account_id = Uuid::new_v4();
+        self.save_s3_account(&account)?;
+        Ok(account)
+    }
+
+    /// Save (create or overwrite) an S3 account.
+    pub fn save_s3_account(&self, account: &S3Account) -> Result<()> {
+        self.store.save(account)
+    }
+
+    /// Load an S3 account by UUID from the in-memory cache.
+    pub fn get_s3_account(&self, id: Uuid) -> Result<Option<S3Account>> {
+        Ok(self.store.get(id))
+    }
+
+    /// Return all S3 accounts sorted by endpoint, bucket and prefix.
+    pub fn list_s3_accounts(&self) -> Result<Vec<S3Account>> {
+        let mut list = self.store.list();
+        list.sort_by(|a, b| {
+            (&a.endpoint, &a.bucket, &a.prefix).cmp(&(&b.endpoint, &b.bucket, &b.prefix))
+        });
+        Ok(list)
+    }
+
+    /// Find an account by exact endpoint, bucket and prefix.
+    pub fn find_by_bucket(
+        &self,
+        endpoint: &str,
+        bucket: &str,
+        prefix: &str,
+    ) -> Result<Option<S3Account>> {
+        Ok(self
+            .store
+            .find(|a| a.endpoint == endpoint && a.bucket == bucket && a.prefix == prefix))
+    }
+
+    /// Delete an S3 account from disk and the in-memory cache.
+    pub fn delete_s3_account(&self, id: Uuid) -> Result<()> {
+        self.store.delete(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(bucket: &str, secret: &str) -> S3Account {
+        S3Account {
+            s3_account_id: Uuid::nil(),
+            endpoint: "https://s3.example.com".to_string(),
+            region: "us-east-1".to_string(),
+            bucket: bucket.to_string(),
+            prefix: String::new(),
+            path_style: true,
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: secret.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_s3_account_roundtrip_is_encrypted() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = [9u8; 32];
+        let mgr = S3AccountManager::for_identity(dir.path().to_path_buf(), key).unwrap();
+        let created = mgr
+            .create_s3_account(account("team-sync", "s3cret-key"))
+            .unwrap();
+        assert_ne!(created.s3_account_id, Uuid::nil());
+
+        let raw = std::fs::read_to_string(mgr.path_for(created.s3_account_id)).unwrap();
+        assert!(!raw.contains("s3cret-key"));
+        assert!(!raw.contains("AKIDEXAMPLE"));
+
+        // A fresh manager decrypts what the first one wrote.
+        let reopened = S3AccountManager::for_identity(dir.path().to_path_buf(), key).unwrap();
+        let loaded = reopened
+            .get_s3_account(created.s3_account_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(loaded.secret_access_key, "s3cret-key");
+        assert!(loaded.path_style);
+
+        // Duplicates are refused; deletion removes the file.
+        assert!(mgr.create_s3_account(account("team-sync", "x")).is_err());
+        mgr.delete_s3_account(created.s3_account_id).unwrap();
+        assert!(!mgr.path_for(created.s3_account_id).exists());
+        assert!(mgr.list_s3_accounts().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_s3_account_wrong_key_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let mgr = S3AccountManager::for_identity(dir.path().to_path_buf(), [1u8; 32]).unwrap();
+        mgr.create_s3_account(account("b", "pw")).unwrap();
+        assert!(S3AccountManager::for_identity(dir.path().to_path_buf(), [2u8; 32]).is_err());
+    }
+}
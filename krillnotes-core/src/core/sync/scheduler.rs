@@ -0,0 +1,562 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Background sync scheduling.
+//!
+//! [`SyncScheduler`] decides which peers take part in each poll cycle:
+//!
+//! - each channel type is polled on its own interval (a LAN peer can be
+//!   polled far more often than an S3 bucket);
+//! - a peer whose send fails with a `SyncError` is backed off exponentially
+//!   and retried when the backoff expires, not on every cycle;
+//! - a locally committed operation schedules a push to every peer shortly
+//!   afterwards, batching bursts of edits into one cycle.
+//!
+//! [`SyncDaemon`] runs a scheduler on its own thread against a
+//! [`SyncTarget`] — anything that can run one filtered
+//! [`SyncEngine::poll_peers`](super::SyncEngine::poll_peers) cycle — and
+//! streams the resulting events through a [`SyncEventCallback`]. The target
+//! owns the workspace access, so the same daemon serves the desktop app
+//! (workspace behind a mutex, channels rebuilt per cycle) and a headless
+//! runner (workspace owned outright).
+
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use super::channel::{ChannelType, PeerSyncInfo};
+use super::{SyncEvent, SyncEventCallback};
+use crate::core::error::KrillnotesError;
+use crate::core::operation_log::CommitListener;
+
+/// Timing policy for a [`SyncScheduler`].
+#[derive(Debug, Clone)]
+pub struct SchedulerConfig {
+    /// Poll interval for channel types without an entry in `channel_intervals`.
+    pub default_interval: Duration,
+    /// Per-channel poll intervals.
+    pub channel_intervals: HashMap<ChannelType, Duration>,
+    /// Delay between a local commit and the push it triggers. Commits made
+    /// during the delay ride along in the same cycle.
+    pub push_delay: Duration,
+    /// Backoff after a peer's first consecutive failure; doubled per failure.
+    pub backoff_initial: Duration,
+    /// Upper bound on a peer's backoff.
+    pub backoff_max: Duration,
+    /// Run a final cycle to every peer when the daemon shuts down.
+    pub sync_on_close: bool,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            default_interval: Duration::from_secs(60),
+            channel_intervals: HashMap::from([
+                (ChannelType::Lan, Duration::from_secs(15)),
+                (ChannelType::Folder, Duration::from_secs(30)),
+            ]),
+            push_delay: Duration::from_secs(2),
+            backoff_initial: Duration::from_secs(30),
+            backoff_max: Duration::from_secs(30 * 60),
+            sync_on_close: false,
+        }
+    }
+}
+
+impl SchedulerConfig {
+    /// The poll interval for `channel_type`.
+    pub fn interval_for(&self, channel_type: ChannelType) -> Duration {
+        self.channel_intervals
+            .get(&channel_type)
+            .copied()
+            .unwrap_or(self.default_interval)
+    }
+
+    /// The backoff after `failures` consecutive failures (at least one).
+    pub fn backoff_for(&self, failures: u32) -> Duration {
+        let factor = 1u32 << failures.saturating_sub(1).min(20);
+        self.backoff_initial
+            .saturating_mul(factor)
+            .min(self.backoff_max)
+    }
+}
+
+/// Runs one sync cycle restricted to the peers `include` accepts.
+///
+/// Implemented for any matching closure, which typically locks or owns a
+/// workspace and calls [`SyncEngine::poll_peers`](super::SyncEngine::poll_peers).
+pub trait SyncTarget: Send {
+    fn poll(
+        &mut self,
+        include: &mut dyn FnMut(&PeerSyncInfo) -> bool,
+    ) -> Result<Vec<SyncEvent>, KrillnotesError>;
+}
+
+impl<F> SyncTarget for F
+where
+    F: FnMut(&mut dyn FnMut(&PeerSyncInfo) -> bool) -> Result<Vec<SyncEvent>, KrillnotesError>
+        + Send,
+{
+    fn poll(
+        &mut self,
+        include: &mut dyn FnMut(&PeerSyncInfo) -> bool,
+    ) -> Result<Vec<SyncEvent>, KrillnotesError> {
+        self(include)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Backoff {
+    failures: u32,
+    retry_at: Instant,
+}
+
+/// Decides when to poll and which peers to include. Time is passed in, so
+/// the policy can be driven by [`SyncDaemon`] or by a caller's own loop.
+#[derive(Debug)]
+pub struct SyncScheduler {
+    config: SchedulerConfig,
+    next_due: HashMap<ChannelType, Instant>,
+    backoff: HashMap<String, Backoff>,
+    push_at: Option<Instant>,
+    last_cycle: Option<Instant>,
+}
+
+impl SyncScheduler {
+    pub fn new(config: SchedulerConfig) -> Self {
+        Self {
+            config,
+            next_due: HashMap::new(),
+            backoff: HashMap::new(),
+            push_at: None,
+            last_cycle: None,
+        }
+    }
+
+    pub fn config(&self) -> &SchedulerConfig {
+        &self.config
+    }
+
+    /// Schedules a push to every peer `push_delay` after the first
+    /// unpushed local commit.
+    pub fn notify_local_commit(&mut self, now: Instant) {
+        if self.push_at.is_none() {
+            self.push_at = Some(now + self.config.push_delay);
+        }
+    }
+
+    /// Whether a commit-triggered push is waiting to run.
+    pub fn push_pending(&self) -> bool {
+        self.push_at.is_some()
+    }
+
+    /// When a backed-off peer will next be retried.
+    pub fn retry_at(&self, peer_device_id: &str) -> Option<Instant> {
+        self.backoff.get(peer_device_id).map(|b| b.retry_at)
+    }
+
+    /// When the next cycle should run. The first cycle runs immediately;
+    /// after that, at least every `default_interval` so that peers on newly
+    /// configured channels are picked up.
+    pub fn next_wakeup(&self, now: Instant) -> Instant {
+        let Some(last) = self.last_cycle else {
+            return now;
+        };
+        self.next_due
+            .values()
+            .chain(self.push_at.iter())
+            .chain(self.backoff.values().map(|b| &b.retry_at))
+            .copied()
+            .fold(last + self.config.default_interval, Instant::min)
+    }
+
+    /// Whether `peer` takes part in a cycle at `now`. A backed-off peer is
+    /// only included once its backoff expires; any other peer when its
+    /// channel is due or a push is pending.
+    pub fn should_include(&self, peer: &PeerSyncInfo, now: Instant) -> bool {
+        if let Some(backoff) = self.backoff.get(&peer.peer_device_id) {
+            return now >= backoff.retry_at;
+        }
+        self.push_at.is_some_and(|at| now >= at)
+            || self
+                .next_due
+                .get(&peer.channel_type)
+                .is_none_or(|due| now >= *due)
+    }
+
+    /// Updates due times and backoff after a cycle run at `now` with the
+    /// `included` peers.
+    pub fn record_cycle(
+        &mut self,
+        now: Instant,
+        included: &[(String, ChannelType)],
+        events: &[SyncEvent],
+    ) {
+        let failed: HashSet<&str> = events
+            .iter()
+            .filter_map(|e| match e {
+                SyncEvent::SyncError { peer_device_id, .. } if !peer_device_id.is_empty() => {
+                    Some(peer_device_id.as_str())
+                }
+                _ => None,
+            })
+            .collect();
+
+        for (peer, channel_type) in included {
+            self.next_due
+                .insert(*channel_type, now + self.config.interval_for(*channel_type));
+            if failed.contains(peer.as_str()) {
+                self.record_failure(peer, now);
+            } else if self.backoff.remove(peer).is_some() {
+                log::info!(target: "krillnotes::sync::scheduler", "peer {peer} recovered");
+            }
+        }
+        // A failure reported under a different id than the one filtered on
+        // (the engine consolidates placeholder peer rows) still backs off.
+        for peer in failed {
+            if !included.iter().any(|(p, _)| p == peer) {
+                self.record_failure(peer, now);
+            }
+        }
+
+        // Reschedule channels that were due but had no eligible peer, and
+        // forget backoff for peers that have gone away, so nothing stays
+        // permanently due.
+        for (channel_type, due) in self.next_due.iter_mut() {
+            if *due <= now {
+                *due = now + self.config.interval_for(*channel_type);
+            }
+        }
+        self.backoff.retain(|_, b| b.retry_at > now);
+
+        if self.push_at.is_some_and(|at| at <= now) {
+            self.push_at = None;
+        }
+        self.last_cycle = Some(now);
+    }
+
+    fn record_failure(&mut self, peer: &str, now: Instant) {
+        let failures = self.backoff.get(peer).map_or(0, |b| b.failures) + 1;
+        let delay = self.config.backoff_for(failures);
+        log::warn!(target: "krillnotes::sync::scheduler",
+            "peer {peer} failed {failures} time(s), retrying in {}s", delay.as_secs());
+        self.backoff.insert(
+            peer.to_string(),
+            Backoff {
+                failures,
+                retry_at: now + delay,
+            },
+        );
+    }
+
+    /// Runs one cycle against `target` and records its outcome. With
+    /// `force`, every peer is included regardless of intervals and backoff
+    /// (for "Sync Now" and the final cycle on close).
+    ///
+    /// A failure of the whole cycle is reported as a `SyncError` without a
+    /// peer and backs off every included peer.
+    pub fn run_cycle(
+        &mut self,
+        target: &mut dyn SyncTarget,
+        now: Instant,
+        force: bool,
+    ) -> Vec<SyncEvent> {
+        let mut included: Vec<(String, ChannelType)> = Vec::new();
+        let result = target.poll(&mut |peer| {
+            let include = force || self.should_include(peer, now);
+            if include && !included.iter().any(|(p, _)| *p == peer.peer_device_id) {
+                included.push((peer.peer_device_id.clone(), peer.channel_type));
+            }
+            include
+        });
+        let events = match result {
+            Ok(events) => events,
+            Err(e) => {
+                log::error!(target: "krillnotes::sync::scheduler", "sync cycle failed: {e}");
+                let mut events = vec![SyncEvent::SyncError {
+                    workspace_id: String::new(),
+                    peer_device_id: String::new(),
+                    error: e.to_string(),
+                }];
+                events.extend(included.iter().map(|(peer, _)| SyncEvent::SyncError {
+                    workspace_id: String::new(),
+                    peer_device_id: peer.clone(),
+                    error: e.to_string(),
+                }));
+                events
+            }
+        };
+        self.record_cycle(now, &included, &events);
+        events
+    }
+}
+
+// ── SyncDaemon ──────────────────────────────────────────────────────────────
+
+enum Command {
+    Commit,
+    SyncNow,
+    Shutdown,
+}
+
+/// A [`SyncScheduler`] running on a background thread.
+///
+/// Dropping the daemon shuts it down, running a final cycle first when
+/// `sync_on_close` is set.
+pub struct SyncDaemon {
+    tx: Sender<Command>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SyncDaemon {
+    /// Starts the daemon. The first cycle runs immediately.
+    pub fn spawn(
+        config: SchedulerConfig,
+        target: impl SyncTarget + 'static,
+        on_event: SyncEventCallback,
+    ) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let thread = std::thread::spawn(move || {
+            let mut target = target;
+            let mut scheduler = SyncScheduler::new(config);
+            let emit = |events: Vec<SyncEvent>| events.into_iter().for_each(&on_event);
+            loop {
+                let now = Instant::now();
+                let wait = scheduler.next_wakeup(now).saturating_duration_since(now);
+                // Run due work before queued commands so a stream of
+                // commits cannot starve it.
+                if wait.is_zero() {
+                    emit(scheduler.run_cycle(&mut target, now, false));
+                    continue;
+                }
+                match rx.recv_timeout(wait) {
+                    Ok(Command::Commit) => scheduler.notify_local_commit(Instant::now()),
+                    Ok(Command::SyncNow) => {
+                        emit(scheduler.run_cycle(&mut target, Instant::now(), true))
+                    }
+                    Err(RecvTimeoutError::Timeout) => {
+                        emit(scheduler.run_cycle(&mut target, Instant::now(), false))
+                    }
+                    Ok(Command::Shutdown) | Err(RecvTimeoutError::Disconnected) => {
+                        if scheduler.config().sync_on_close {
+                            emit(scheduler.run_cycle(&mut target, Instant::now(), true));
+                        }
+                        break;
+                    }
+                }
+            }
+            log::debug!(target: "krillnotes::sync::scheduler", "sync daemon stopped");
+        });
+        Self {
+            tx,
+            thread: Some(thread),
+        }
+    }
+
+    /// A listener for [`Workspace::set_commit_listener`](crate::Workspace::set_commit_listener)
+    /// that schedules a push after each local commit.
+    pub fn commit_notifier(&self) -> CommitListener {
+        let tx = self.tx.clone();
+        Arc::new(move || {
+            let _ = tx.send(Command::Commit);
+        })
+    }
+
+    /// Schedules a push, as if an operation had just been committed.
+    pub fn notify_local_commit(&self) {
+        let _ = self.tx.send(Command::Commit);
+    }
+
+    /// Runs a cycle to every peer now, ignoring intervals and backoff.
+    pub fn sync_now(&self) {
+        let _ = self.tx.send(Command::SyncNow);
+    }
+
+    /// Stops the daemon and waits for its thread, including the final
+    /// cycle when `sync_on_close` is set.
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            let _ = self.tx.send(Command::Shutdown);
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for SyncDaemon {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer(id: &str, channel_type: ChannelType) -> PeerSyncInfo {
+        PeerSyncInfo {
+            peer_device_id: id.to_string(),
+            peer_identity_id: String::new(),
+            channel_type,
+            channel_params: serde_json::Value::Null,
+            last_sent_op: None,
+            last_received_op: None,
+        }
+    }
+
+    fn sync_error(peer: &str) -> SyncEvent {
+        SyncEvent::SyncError {
+            workspace_id: "ws".to_string(),
+            peer_device_id: peer.to_string(),
+            error: "boom".to_string(),
+        }
+    }
+
+    fn config() -> SchedulerConfig {
+        SchedulerConfig {
+            default_interval: Duration::from_secs(60),
+            channel_intervals: HashMap::from([(ChannelType::Lan, Duration::from_secs(10))]),
+            push_delay: Duration::from_secs(2),
+            backoff_initial: Duration::from_secs(30),
+            backoff_max: Duration::from_secs(100),
+            sync_on_close: false,
+        }
+    }
+
+    #[test]
+    fn test_channels_are_polled_on_their_own_interval() {
+        let mut s = SyncScheduler::new(config());
+        let t0 = Instant::now();
+        let lan = peer("lan-peer", ChannelType::Lan);
+        let relay = peer("relay-peer", ChannelType::Relay);
+        assert_eq!(s.next_wakeup(t0), t0);
+        assert!(s.should_include(&lan, t0) && s.should_include(&relay, t0));
+
+        s.record_cycle(
+            t0,
+            &[
+                ("lan-peer".into(), ChannelType::Lan),
+                ("relay-peer".into(), ChannelType::Relay),
+            ],
+            &[],
+        );
+        assert_eq!(s.next_wakeup(t0), t0 + Duration::from_secs(10));
+        let t1 = t0 + Duration::from_secs(10);
+        assert!(s.should_include(&lan, t1));
+        assert!(!s.should_include(&relay, t1));
+        assert!(s.should_include(&relay, t0 + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_failing_peer_backs_off_exponentially_and_recovers() {
+        let mut s = SyncScheduler::new(config());
+        let relay = peer("p", ChannelType::Relay);
+        let included = [("p".to_string(), ChannelType::Relay)];
+        let t0 = Instant::now();
+
+        s.record_cycle(t0, &included, &[sync_error("p")]);
+        assert_eq!(s.retry_at("p"), Some(t0 + Duration::from_secs(30)));
+        assert!(!s.should_include(&relay, t0 + Duration::from_secs(29)));
+        let t1 = t0 + Duration::from_secs(30);
+        assert!(s.should_include(&relay, t1));
+
+        s.record_cycle(t1, &included, &[sync_error("p")]);
+        assert_eq!(s.retry_at("p"), Some(t1 + Duration::from_secs(60)));
+        let t2 = t1 + Duration::from_secs(60);
+        s.record_cycle(t2, &included, &[sync_error("p")]);
+        // 120s is capped at backoff_max.
+        assert_eq!(s.retry_at("p"), Some(t2 + Duration::from_secs(100)));
+
+        let t3 = t2 + Duration::from_secs(100);
+        s.record_cycle(t3, &included, &[]);
+        assert_eq!(s.retry_at("p"), None);
+    }
+
+    #[test]
+    fn test_local_commit_pushes_to_all_but_backed_off_peers() {
+        let mut s = SyncScheduler::new(config());
+        let t0 = Instant::now();
+        s.record_cycle(
+            t0,
+            &[
+                ("a".into(), ChannelType::Relay),
+                ("b".into(), ChannelType::Relay),
+            ],
+            &[sync_error("b")],
+        );
+
+        let t1 = t0 + Duration::from_secs(5);
+        s.notify_local_commit(t1);
+        // A second commit does not push the deadline back.
+        s.notify_local_commit(t1 + Duration::from_secs(1));
+        let push = t1 + Duration::from_secs(2);
+        assert_eq!(s.next_wakeup(t1), push);
+        assert!(!s.should_include(&peer("a", ChannelType::Relay), t1));
+        assert!(s.should_include(&peer("a", ChannelType::Relay), push));
+        assert!(!s.should_include(&peer("b", ChannelType::Relay), push));
+
+        s.record_cycle(push, &[("a".into(), ChannelType::Relay)], &[]);
+        assert!(!s.push_pending());
+    }
+
+    #[test]
+    fn test_due_channel_without_eligible_peers_is_rescheduled() {
+        let mut s = SyncScheduler::new(config());
+        let t0 = Instant::now();
+        s.record_cycle(t0, &[("p".into(), ChannelType::Lan)], &[sync_error("p")]);
+        // The only LAN peer is backed off: the due LAN slot must not make
+        // the scheduler spin.
+        let t1 = t0 + Duration::from_secs(10);
+        s.record_cycle(t1, &[], &[]);
+        assert!(s.next_wakeup(t1) > t1);
+    }
+
+    #[test]
+    fn test_daemon_streams_events_and_syncs_on_close() {
+        use std::sync::Mutex;
+
+        let cycles = Arc::new(Mutex::new(0usize));
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let target = {
+            let cycles = Arc::clone(&cycles);
+            move |include: &mut dyn FnMut(&PeerSyncInfo) -> bool| {
+                *cycles.lock().unwrap() += 1;
+                let mut events = Vec::new();
+                if include(&peer("p", ChannelType::Relay)) {
+                    events.push(SyncEvent::DeltaSent {
+                        workspace_id: "ws".into(),
+                        peer_device_id: "p".into(),
+                        op_count: 1,
+                    });
+                }
+                Ok(events)
+            }
+        };
+        let on_event: SyncEventCallback = {
+            let seen = Arc::clone(&seen);
+            Box::new(move |e| seen.lock().unwrap().push(e))
+        };
+        let daemon = SyncDaemon::spawn(
+            SchedulerConfig {
+                sync_on_close: true,
+                ..config()
+            },
+            target,
+            on_event,
+        );
+        daemon.sync_now();
+        daemon.shutdown();
+
+        // Initial cycle, "Sync Now", and the final cycle on close.
+        assert_eq!(*cycles.lock().unwrap(), 3);
+        assert_eq!(seen.lock().unwrap().len(), 3);
+    }
+}
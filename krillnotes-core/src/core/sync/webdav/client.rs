@@ -0,0 +1,316 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! WebDAV HTTP client — the handful of RFC 4918 verbs the sync channel needs.
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use reqwest::blocking::{RequestBuilder, Response};
+use reqwest::{Method, StatusCode, Url};
+
+use crate::core::error::KrillnotesError;
+
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:resourcetype/></d:prop></d:propfind>"#;
+
+/// A member of a collection as reported by `PROPFIND`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DavEntry {
+    /// Absolute URL of the resource.
+    pub url: String,
+    pub is_collection: bool,
+}
+
+/// Thin blocking WebDAV client rooted at a collection URL.
+///
+/// Paths passed to the methods are relative to that collection; URLs
+/// returned by [`list`](Self::list) are absolute and can be fed back to
+/// [`get`](Self::get) and [`delete`](Self::delete).
+pub struct WebDavClient {
+    http: reqwest::blocking::Client,
+    base_url: Url,
+    username: String,
+    password: String,
+}
+
+impl WebDavClient {
+    /// Create a client for the collection at `base_url` (a trailing `/` is added).
+    pub fn new(base_url: &str, username: &str, password: &str) -> Result<Self, KrillnotesError> {
+        let mut base = base_url.trim_end_matches('/').to_string();
+        base.push('/');
+        let base_url = Url::parse(&base)
+            .map_err(|e| KrillnotesError::WebDav(format!("invalid URL {base_url}: {e}")))?;
+        Ok(Self {
+            http: reqwest::blocking::Client::builder()
+                .user_agent(concat!("KrillNotes/", env!("CARGO_PKG_VERSION")))
+                .build()
+                .expect("failed to build HTTP client"),
+            base_url,
+            username: username.to_string(),
+            password: password.to_string(),
+        })
+    }
+
+    /// The collection URL, always ending in `/`.
+    pub fn base_url(&self) -> &str {
+        self.base_url.as_str()
+    }
+
+    /// Returns `true` if `url` lies inside this client's collection.
+    pub fn contains(&self, url: &str) -> bool {
+        url.starts_with(self.base_url.as_str())
+    }
+
+    // ── Private helpers ──────────────────────────────────────────────────────
+
+    /// Resolves `path` against the collection, refusing anything outside it
+    /// so credentials are never sent to a URL a server listing pointed us at.
+    fn url(&self, path: &str) -> Result<Url, KrillnotesError> {
+        let url = self
+            .base_url
+            .join(path)
+            .map_err(|e| KrillnotesError::WebDav(format!("invalid path {path}: {e}")))?;
+        if !self.contains(url.as_str()) {
+            return Err(KrillnotesError::WebDav(format!(
+                "{url} is outside {}",
+                self.base_url
+            )));
+        }
+        Ok(url)
+    }
+
+    fn request(&self, method: &str, url: Url) -> RequestBuilder {
+        let method = Method::from_bytes(method.as_bytes()).expect("valid WebDAV method");
+        self.http
+            .request(method, url)
+            .basic_auth(&self.username, Some(&self.password))
+    }
+
+    fn send(&self, request: RequestBuilder, what: &str) -> Result<Response, KrillnotesError> {
+        request.send().map_err(|e| {
+            log::warn!(target: "krillnotes::sync::webdav", "{what} failed: {e}");
+            KrillnotesError::WebDav(format!("{what}: {e}"))
+        })
+    }
+
+    fn status_error(status: StatusCode, what: &str) -> KrillnotesError {
+        match status.as_u16() {
+            401 | 403 => KrillnotesError::WebDav(format!(
+                "{what}: access denied (HTTP {}) — check the WebDAV username and password",
+                status.as_u16()
+            )),
+            code => KrillnotesError::WebDav(format!("{what}: HTTP {code}")),
+        }
+    }
+
+    // ── WebDAV verbs ─────────────────────────────────────────────────────────
+
+    /// `MKCOL` — create a collection. Succeeds if it already exists.
+    pub fn ensure_collection(&self, path: &str) -> Result<(), KrillnotesError> {
+        let url = self.url(path)?;
+        let what = format!("MKCOL {url}");
+        let resp = self.send(self.request("MKCOL", url), &what)?;
+        match resp.status().as_u16() {
+            // 405: the collection already exists.
+            200..=299 | 405 => Ok(()),
+            _ => Err(Self::status_error(resp.status(), &what)),
+        }
+    }
+
+    /// `PUT` — upload `bytes` to `path`.
+    pub fn put(&self, path: &str, bytes: &[u8]) -> Result<(), KrillnotesError> {
+        let url = self.url(path)?;
+        let what = format!("PUT {url}");
+        let resp = self.send(
+            self.request("PUT", url)
+                .header("Content-Type", "application/octet-stream")
+                .body(bytes.to_vec()),
+            &what,
+        )?;
+        if resp.status().is_success() {
+            Ok(())
+        } else {
+            Err(Self::status_error(resp.status(), &what))
+        }
+    }
+
+    /// `MOVE` — rename `from` to `to` within the collection, overwriting.
+    pub fn move_to(&self, from: &str, to: &str) -> Result<(), KrillnotesError> {
+        let from = self.url(from)?;
+        let to = self.url(to)?;
+        let what = format!("MOVE {from}");
+        let resp = self.send(
+            self.request("MOVE", from)
+                .header("Destination", to.as_str())
+                .header("Overwrite", "T"),
+            &what,
+        )?;
+        if resp.status().is_success() {
+            Ok(())
+        } else {
+            Err(Self::status_error(resp.status(), &what))
+        }
+    }
+
+    /// `PROPFIND` with `Depth: 1` — list the members of a collection.
+    ///
+    /// A missing collection lists as empty.
+    pub fn list(&self, path: &str) -> Result<Vec<DavEntry>, KrillnotesError> {
+        let url = self.url(path)?;
+        let what = format!("PROPFIND {url}");
+        let resp = self.send(
+            self.request("PROPFIND", url.clone())
+                .header("Depth", "1")
+                .header("Content-Type", "application/xml; charset=utf-8")
+                .body(PROPFIND_BODY),
+            &what,
+        )?;
+        let status = resp.status();
+        if status == StatusCode::NOT_FOUND {
+            return Ok(Vec::new());
+        }
+        if !status.is_success() {
+            return Err(Self::status_error(status, &what));
+        }
+        let body = resp
+            .text()
+            .map_err(|e| KrillnotesError::WebDav(format!("{what}: {e}")))?;
+        let entries = parse_multistatus(&body)
+            .map_err(|e| KrillnotesError::WebDav(format!("{what}: {e}")))?;
+        Ok(entries
+            .into_iter()
+            .filter_map(|(href, is_collection)| {
+                let member = url.join(&href).ok()?;
+                // Depth 1 includes the collection itself.
+                (self.contains(member.as_str())
+                    && member.path().trim_end_matches('/') != url.path().trim_end_matches('/'))
+                .then(|| DavEntry {
+                    url: member.to_string(),
+                    is_collection,
+                })
+            })
+            .collect())
+    }
+
+    /// `GET` an absolute resource URL.
+    pub fn get(&self, url: &str) -> Result<Vec<u8>, KrillnotesError> {
+        let url = self.url(url)?;
+        let what = format!("GET {url}");
+        let resp = self.send(self.request("GET", url), &what)?;
+        if !resp.status().is_success() {
+            return Err(Self::status_error(resp.status(), &what));
+        }
+        resp.bytes()
+            .map(|b| b.to_vec())
+            .map_err(|e| KrillnotesError::WebDav(format!("{what}: {e}")))
+    }
+
+    /// `DELETE` an absolute resource URL. Succeeds if it is already gone.
+    pub fn delete(&self, url: &str) -> Result<(), KrillnotesError> {
+        let url = self.url(url)?;
+        let what = format!("DELETE {url}");
+        let resp = self.send(self.request("DELETE", url), &what)?;
+        if resp.status().is_success() || resp.status() == StatusCode::NOT_FOUND {
+            Ok(())
+        } else {
+            Err(Self::status_error(resp.status(), &what))
+        }
+    }
+}
+
+/// Extracts `(href, is_collection)` pairs from a `207 Multi-Status` body.
+///
+/// Namespace prefixes vary between servers, so elements are matched by
+/// local name only.
+pub(crate) fn parse_multistatus(xml: &str) -> Result<Vec<(String, bool)>, String> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().expand_empty_elements = true;
+
+    let mut entries = Vec::new();
+    let mut path: Vec<String> = Vec::new();
+    let mut href = String::new();
+    let mut is_collection = false;
+    loop {
+        match reader.read_event().map_err(|e| e.to_string())? {
+            Event::Start(e) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                match name.as_str() {
+                    "response" => {
+                        href.clear();
+                        is_collection = false;
+                    }
+                    "collection" if path.last().is_some_and(|p| p == "resourcetype") => {
+                        is_collection = true;
+                    }
+                    _ => {}
+                }
+                path.push(name);
+            }
+            Event::End(_) => {
+                let closed = path.pop();
+                if closed.as_deref() == Some("response") && !href.is_empty() {
+                    entries.push((href.trim().to_string(), is_collection));
+                }
+            }
+            Event::Text(t) if path.last().is_some_and(|p| p == "href") => {
+                let text = t.unescape().map_err(|e| e.to_string())?;
+                href.push_str(&text);
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_multistatus_handles_prefixes_and_collections() {
+        let xml = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:">
+  <d:response>
+    <d:href>/dav/ws/inbox/</d:href>
+    <d:propstat><d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop></d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/dav/ws/inbox/a%20b.swarm</d:href>
+    <d:propstat><d:prop><d:resourcetype/></d:prop></d:propstat>
+  </d:response>
+  <D:response xmlns:D="DAV:">
+    <D:href>https://dav.example.com/dav/ws/inbox/c&amp;d.swarm</D:href>
+    <D:propstat><D:prop><D:resourcetype></D:resourcetype></D:prop></D:propstat>
+  </D:response>
+</d:multistatus>"#;
+        let entries = parse_multistatus(xml).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                ("/dav/ws/inbox/".to_string(), true),
+                ("/dav/ws/inbox/a%20b.swarm".to_string(), false),
+                (
+                    "https://dav.example.com/dav/ws/inbox/c&d.swarm".to_string(),
+                    false
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_client_normalises_base_url() {
+        let client = WebDavClient::new("https://dav.example.com/shared", "u", "p").unwrap();
+        assert_eq!(client.base_url(), "https://dav.example.com/shared/");
+        assert!(client.contains("https://dav.example.com/shared/ws/x.swarm"));
+        assert!(!client.contains("https://dav.example.com/other/x.swarm"));
+        assert!(WebDavClient::new("not a url", "u", "p").is_err());
+        // Resolved paths may not escape the collection.
+        assert!(client.url("ws/x.swarm").is_ok());
+        assert!(client.url("../other/x.swarm").is_err());
+        assert!(client.url("https://evil.example.com/x").is_err());
+    }
+}
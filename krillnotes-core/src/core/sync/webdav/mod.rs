@@ -0,0 +1,210 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! WebDAV sync channel (Nextcloud, ownCloud, any RFC 4918 server).
+//!
+//! Peers sharing a WebDAV collection exchange bundles through per-recipient
+//! inboxes laid out as `<collection>/<workspace_id>/<recipient>/<ts>_<id>.swarm`,
+//! where `<recipient>` is the recipient's identity key in URL-safe base64.
+//! Bundles are uploaded under a `.part` name and moved into place, so a
+//! receiver never reads a half-written file.
+
+pub mod webdav_account;
+
+#[cfg(feature = "webdav")]
+pub mod client;
+
+pub use webdav_account::{WebDavAccount, WebDavAccountManager};
+
+#[cfg(feature = "webdav")]
+pub use client::WebDavClient;
+
+#[cfg(feature = "webdav")]
+use crate::core::error::KrillnotesError;
+#[cfg(feature = "webdav")]
+use crate::core::sync::channel::{BundleRef, ChannelType, PeerSyncInfo, SendResult, SyncChannel};
+#[cfg(feature = "webdav")]
+use uuid::Uuid;
+
+/// Inbox directory name for an identity: its base64 key made URL- and
+/// path-safe (`+`→`-`, `/`→`_`, padding dropped).
+pub fn inbox_name(identity_id: &str) -> String {
+    identity_id
+        .trim_end_matches('=')
+        .chars()
+        .map(|c| match c {
+            '+' => '-',
+            '/' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(feature = "webdav")]
+pub struct WebDavChannel {
+    /// This identity's inbox directory name.
+    inbox: String,
+    /// One client per configured account; peers pick theirs via
+    /// `channel_params.webdav_account_id`.
+    accounts: Vec<(Uuid, WebDavClient)>,
+}
+
+#[cfg(feature = "webdav")]
+impl WebDavChannel {
+    /// `identity_id` is the local identity's base64 public key.
+    pub fn new(identity_id: &str) -> Self {
+        Self {
+            inbox: inbox_name(identity_id),
+            accounts: Vec::new(),
+        }
+    }
+
+    /// Add an account whose collection this channel sends to and polls.
+    pub fn add_account(&mut self, account: &WebDavAccount) -> Result<(), KrillnotesError> {
+        let client = WebDavClient::new(&account.url, &account.username, &account.password)?;
+        self.accounts.push((account.webdav_account_id, client));
+        Ok(())
+    }
+
+    fn client_for(&self, peer: &PeerSyncInfo) -> Result<&WebDavClient, KrillnotesError> {
+        let id = peer
+            .channel_params
+            .get("webdav_account_id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| {
+                KrillnotesError::WebDav(
+                    "WebDAV channel peer missing 'webdav_account_id' in channel_params".to_string(),
+                )
+            })?;
+        let id = Uuid::parse_str(id)
+            .map_err(|e| KrillnotesError::WebDav(format!("invalid webdav_account_id: {e}")))?;
+        self.accounts
+            .iter()
+            .find(|(account_id, _)| *account_id == id)
+            .map(|(_, client)| client)
+            .ok_or_else(|| KrillnotesError::WebDav(format!("no WebDAV account {id} configured")))
+    }
+
+    fn receive_from(
+        &self,
+        client: &WebDavClient,
+        workspace_id: &str,
+    ) -> Result<Vec<BundleRef>, KrillnotesError> {
+        let entries = client.list(&format!("{workspace_id}/{}/", self.inbox))?;
+        let mut bundles = Vec::new();
+        for entry in entries {
+            if entry.is_collection || !entry.url.ends_with(".swarm") {
+                continue;
+            }
+            match client.get(&entry.url) {
+                Ok(data) => {
+                    log::debug!(target: "krillnotes::sync::webdav", "downloaded {} ({} bytes)", entry.url, data.len());
+                    bundles.push(BundleRef {
+                        id: entry.url,
+                        data,
+                    });
+                }
+                Err(e) => {
+                    // Removed or still being written by another poller — retry next cycle.
+                    log::debug!(target: "krillnotes::sync::webdav", "skipping {}: {e}", entry.url);
+                }
+            }
+        }
+        Ok(bundles)
+    }
+}
+
+#[cfg(feature = "webdav")]
+impl SyncChannel for WebDavChannel {
+    fn send_bundle(
+        &self,
+        peer: &PeerSyncInfo,
+        bundle_bytes: &[u8],
+    ) -> Result<SendResult, KrillnotesError> {
+        let client = self.client_for(peer)?;
+        let recipient = inbox_name(&peer.peer_identity_id);
+        if recipient.is_empty() {
+            return Err(KrillnotesError::WebDav(
+                "WebDAV channel peer has no identity key".to_string(),
+            ));
+        }
+        // The workspace id travels inside the bundle; read it back so the
+        // inbox path matches what the receiver polls.
+        let workspace_id = crate::core::swarm::header::read_header(bundle_bytes)?.workspace_id;
+
+        client.ensure_collection(&format!("{workspace_id}/"))?;
+        let inbox = format!("{workspace_id}/{recipient}/");
+        client.ensure_collection(&inbox)?;
+
+        let timestamp = chrono::Utc::now().format("%Y%m%d%H%M%S");
+        let uuid_short: String = Uuid::new_v4()
+            .simple()
+            .to_string()
+            .chars()
+            .take(8)
+            .collect();
+        let name = format!("{inbox}{timestamp}_{uuid_short}.swarm");
+        let partial = format!("{name}.part");
+        client.put(&partial, bundle_bytes)?;
+        client.move_to(&partial, &name)?;
+
+        log::info!(target: "krillnotes::sync::webdav", "uploaded bundle to {}{name} ({} bytes)", client.base_url(), bundle_bytes.len());
+        Ok(SendResult::Delivered)
+    }
+
+    fn receive_bundles(&self, workspace_id: &str) -> Result<Vec<BundleRef>, KrillnotesError> {
+        let mut bundles = Vec::new();
+        let mut failures = Vec::new();
+        for (id, client) in &self.accounts {
+            match self.receive_from(client, workspace_id) {
+                Ok(found) => bundles.extend(found),
+                Err(e) => {
+                    log::warn!(target: "krillnotes::sync::webdav", "skipping WebDAV account {id}: {e}");
+                    failures.push(e);
+                }
+            }
+        }
+        // Surface the failure when no account could be polled at all.
+        if !failures.is_empty() && failures.len() == self.accounts.len() {
+            return Err(failures.remove(0));
+        }
+        log::info!(target: "krillnotes::sync::webdav", "found {} bundles for workspace {workspace_id}", bundles.len());
+        Ok(bundles)
+    }
+
+    fn acknowledge(&self, bundle_ref: &BundleRef) -> Result<(), KrillnotesError> {
+        let client = self
+            .accounts
+            .iter()
+            .map(|(_, client)| client)
+            .find(|client| client.contains(&bundle_ref.id))
+            .ok_or_else(|| {
+                KrillnotesError::WebDav(format!("no WebDAV account holds {}", bundle_ref.id))
+            })?;
+        client.delete(&bundle_ref.id)?;
+        log::debug!(target: "krillnotes::sync::webdav", "acknowledged and deleted {}", bundle_ref.id);
+        Ok(())
+    }
+
+    fn channel_type(&self) -> ChannelType {
+        ChannelType::WebDav
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inbox_name_is_path_safe() {
+        assert_eq!(inbox_name("ab+/cd=="), "ab-_cd");
+        assert_eq!(inbox_name("plain"), "plain");
+    }
+}
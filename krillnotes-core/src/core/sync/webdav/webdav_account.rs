@@ -0,0 +1,178 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Per-identity WebDAV account management.
+//!
+//! Each account is stored as an AES-256-GCM encrypted JSON file in the
+//! per-identity WebDAV directory (`identities/<uuid>/webdav/<account_id>.json`)
+//! by the shared `AccountStore`.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use crate::core::sync::account_store::{AccountStore, StoredAccount};
+use crate::{KrillnotesError, Result};
+
+/// WebDAV credentials for one shared collection (e.g. a Nextcloud folder).
+///
+/// Stored at `<webdav_dir>/<webdav_account_id>.json` (encrypted).
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebDavAccount {
+    pub webdav_account_id: Uuid,
+    /// Collection URL that holds the per-workspace inboxes.
+    pub url: String,
+    pub username: String,
+    pub password: String,
+}
+
+impl std::fmt::Debug for WebDavAccount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebDavAccount")
+            .field("webdav_account_id", &self.webdav_account_id)
+            .field("url", &self.url)
+            .field("username", &self.username)
+            .field("password", &"[REDACTED]")
+            .finish()
+    }
+}
+
+impl Drop for WebDavAccount {
+    fn drop(&mut self) {
+        use zeroize::Zeroize;
+        self.password.zeroize();
+    }
+}
+
+impl StoredAccount for WebDavAccount {
+    fn account_id(&self) -> Uuid {
+        self.webdav_account_id
+    }
+}
+
+/// Manages the WebDAV accounts directory for a single identity.
+///
+/// Mirrors [`RelayAccountManager`](crate::core::sync::relay::RelayAccountManager):
+/// all accounts are decrypted into an in-memory cache on construction, and
+/// writes update the cache after a successful disk write.
+pub struct WebDavAccountManager {
+    store: AccountStore<WebDavAccount>,
+}
+
+impl WebDavAccountManager {
+    /// Per-identity constructor — accounts are AES-256-GCM encrypted with `key`.
+    ///
+    /// Creates `webdav_dir` if needed and fails if an existing file cannot be
+    /// decrypted (e.g. wrong key).
+    pub fn for_identity(webdav_dir: PathBuf, key: [u8; 32]) -> Result<Self> {
+        Ok(Self {
+            store: AccountStore::open(webdav_dir, key, KrillnotesError::WebDav)?,
+        })
+    }
+
+    /// Returns the on-disk path for the given WebDAV account UUID.
+    pub fn path_for(&self, id: Uuid) -> PathBuf {
+        self.store.path_for(id)
+    }
+
+    /// Create a new WebDAV account and persist it.
+    ///
+    /// Returns an error if an account for the same URL and user already exists.
+    pub fn create_webdav_account(
+        &self,
+        url: &str,
+        username: &str,
+        password: &str,
+    ) -> Result<WebDavAccount> {
+        if let Some(existing) = self.find_by_url(url, username)? {
+            return Err(KrillnotesError::WebDav(format!(
+                "A WebDAV account for {} as {} already exists (id: {})",
+                existing.url, existing.username, existing.webdav_account_id
+            )));
+        }
+        let account = WebDavAccount {
+            webdav_account_id: Uuid::new_v4(),
+            url: url.to_string(),
+            username: username.to_string(),
+            password: password.to_string(),
+        };
+        self.save_webdav_account(&account)?;
+        Ok(account)
+    }
+
+    /// Save (create or overwrite) a WebDAV account.
+    pub fn save_webdav_account(&self, account: &WebDavAccount) -> Result<()> {
+        self.store.save(account)
+    }
+
+    /// Load a WebDAV account by UUID from the in-memory cache.
+    pub fn get_webdav_account(&self, id: Uuid) -> Result<Option<WebDavAccount>> {
+        Ok(self.store.get(id))
+    }
+
+    /// Return all WebDAV accounts sorted by URL.
+    pub fn list_webdav_accounts(&self) -> Result<Vec<WebDavAccount>> {
+        let mut list = self.store.list();
+        list.sort_by(|a, b| a.url.cmp(&b.url).then_with(|| a.username.cmp(&b.username)));
+        Ok(list)
+    }
+
+    /// Find an account by exact URL and username.
+    pub fn find_by_url(&self, url: &str, username: &str) -> Result<Option<WebDavAccount>> {
+        Ok(self.store.find(|a| a.url == url && a.username == username))
+    }
+
+    /// Delete a WebDAV account from disk and the in-memory cache.
+    pub fn delete_webdav_account(&self, id: Uuid) -> Result<()> {
+        self.store.delete(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_webdav_account_roundtrip_is_encrypted() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = [7u8; 32];
+        let mgr = WebDavAccountManager::for_identity(dir.path().to_path_buf(), key).unwrap();
+        let account = mgr
+            .create_webdav_account("https://dav.example.com/shared/", "alice", "s3cret-pass")
+            .unwrap();
+
+        let raw = std::fs::read_to_string(mgr.path_for(account.webdav_account_id)).unwrap();
+        assert!(!raw.contains("s3cret-pass"));
+        assert!(!raw.contains("alice"));
+
+        // A fresh manager decrypts what the first one wrote.
+        let reopened = WebDavAccountManager::for_identity(dir.path().to_path_buf(), key).unwrap();
+        let loaded = reopened
+            .get_webdav_account(account.webdav_account_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(loaded.password, "s3cret-pass");
+        assert_eq!(reopened.list_webdav_accounts().unwrap().len(), 1);
+
+        // Duplicates are refused; deletion removes the file.
+        assert!(mgr
+            .create_webdav_account("https://dav.example.com/shared/", "alice", "x")
+            .is_err());
+        mgr.delete_webdav_account(account.webdav_account_id)
+            .unwrap();
+        assert!(!mgr.path_for(account.webdav_account_id).exists());
+    }
+
+    #[test]
+    fn test_webdav_account_wrong_key_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let mgr = WebDavAccountManager::for_identity(dir.path().to_path_buf(), [1u8; 32]).unwrap();
+        mgr.create_webdav_account("https://dav.example.com/", "bob", "pw")
+            .unwrap();
+        assert!(WebDavAccountManager::for_identity(dir.path().to_path_buf(), [2u8; 32]).is_err());
+    }
+}
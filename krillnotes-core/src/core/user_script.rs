@@ -64,6 +64,20 @@ pub fn parse_front_matter(source: &str) -> FrontMatter {
     fm
 }
 
+/// Returns the schema names a script registers via `schema("Name", ...)` calls.
+///
+/// This is a textual scan, not an evaluation: it is used to decide which scripts an
+/// export needs without running them.
+pub fn declared_schema_names(source: &str) -> Vec<String> {
+    use std::sync::OnceLock;
+    static SCHEMA_RE: OnceLock<regex::Regex> = OnceLock::new();
+    let re = SCHEMA_RE
+        .get_or_init(|| regex::Regex::new(r#"\bschema\s*\(\s*"([^"]+)""#).expect("valid regex"));
+    re.captures_iter(source)
+        .map(|cap| cap[1].to_string())
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -125,4 +139,11 @@ schema("Test", #{ fields: [] });
         let fm = parse_front_matter(source);
         assert_eq!(fm.name, "Spacey");
     }
+
+    #[test]
+    fn test_declared_schema_names() {
+        let source = "// @name: Travel\nschema(\"Trip\", #{ fields: [] });\nschema( \"Stay\", #{});\nlet x = my_schema(\"Nope\");";
+        assert_eq!(declared_schema_names(source), vec!["Trip", "Stay"]);
+        assert!(declared_schema_names("fn helper() { 1 }").is_empty());
+    }
 }
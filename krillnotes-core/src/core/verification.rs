@@ -0,0 +1,613 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Contact verification: short authentication strings and QR payloads.
+//!
+//! **SAS** (`CodeVerified`) is a three-message commit-reveal exchange that can
+//! travel over any channel, since every message is signed by its sender:
+//!
+//! 1. The initiator sends a *commit*: a hash over a random nonce.
+//! 2. The responder replies with its own nonce in the clear.
+//! 3. The initiator reveals its nonce; the responder checks it against the
+//!    commitment.
+//!
+//! Both sides then derive the same [`SasCode`] from the two keys and nonces.
+//! Because the initiator is bound to its nonce before seeing the responder's,
+//! an attacker relaying substituted keys cannot steer both codes to match —
+//! comparing the codes over a phone or video call detects them.
+//!
+//! **QR** (`VerifiedInPerson`): a [`QrVerificationPayload`] carries a public
+//! key signed by itself, shown on one screen and scanned by the other.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use base64::{
+    engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD},
+    Engine as _,
+};
+use chrono::{DateTime, Utc};
+use ed25519_dalek::{Signer, SigningKey, Verifier};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{KrillnotesError, Result};
+
+/// BLAKE3 key-derivation context for SAS codes.
+const SAS_CONTEXT: &str = "krillnotes 2026-01-01 contact verification sas v1";
+
+/// Scheme prefix of a QR verification payload.
+pub const QR_URI_PREFIX: &str = "krillnotes-verify:";
+
+/// How old a scanned QR payload may be, in seconds. A payload is meant to be
+/// generated on the spot, not passed on as a screenshot.
+pub const QR_PAYLOAD_MAX_AGE_SECS: i64 = 600;
+
+/// 64 emoji, each standing for six bits of the code.
+const SAS_EMOJI: [(&str, &str); 64] = [
+    ("🐶", "Dog"),
+    ("🐱", "Cat"),
+    ("🦁", "Lion"),
+    ("🐎", "Horse"),
+    ("🦄", "Unicorn"),
+    ("🐷", "Pig"),
+    ("🐘", "Elephant"),
+    ("🐰", "Rabbit"),
+    ("🐼", "Panda"),
+    ("🐓", "Rooster"),
+    ("🐧", "Penguin"),
+    ("🐢", "Turtle"),
+    ("🐟", "Fish"),
+    ("🐙", "Octopus"),
+    ("🦋", "Butterfly"),
+    ("🌷", "Flower"),
+    ("🌳", "Tree"),
+    ("🌵", "Cactus"),
+    ("🍄", "Mushroom"),
+    ("🌏", "Globe"),
+    ("🌙", "Moon"),
+    ("☁️", "Cloud"),
+    ("🔥", "Fire"),
+    ("🍌", "Banana"),
+    ("🍎", "Apple"),
+    ("🍓", "Strawberry"),
+    ("🌽", "Corn"),
+    ("🍕", "Pizza"),
+    ("🎂", "Cake"),
+    ("❤️", "Heart"),
+    ("😀", "Smiley"),
+    ("🤖", "Robot"),
+    ("🎩", "Hat"),
+    ("👓", "Glasses"),
+    ("🔧", "Spanner"),
+    ("🎅", "Santa"),
+    ("👍", "Thumbs Up"),
+    ("☂️", "Umbrella"),
+    ("⌛", "Hourglass"),
+    ("⏰", "Clock"),
+    ("🎁", "Gift"),
+    ("💡", "Light Bulb"),
+    ("📕", "Book"),
+    ("✏️", "Pencil"),
+    ("📎", "Paperclip"),
+    ("✂️", "Scissors"),
+    ("🔒", "Lock"),
+    ("🔑", "Key"),
+    ("🔨", "Hammer"),
+    ("☎️", "Telephone"),
+    ("🏁", "Flag"),
+    ("🚂", "Train"),
+    ("🚲", "Bicycle"),
+    ("✈️", "Aeroplane"),
+    ("🚀", "Rocket"),
+    ("🏆", "Trophy"),
+    ("⚽", "Ball"),
+    ("🎸", "Guitar"),
+    ("🎺", "Trumpet"),
+    ("🔔", "Bell"),
+    ("⚓", "Anchor"),
+    ("🎧", "Headphones"),
+    ("📁", "Folder"),
+    ("📌", "Pin"),
+];
+
+/// How a contact's identity was verified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationMethod {
+    /// Matching short authentication strings (`CodeVerified`).
+    Sas,
+    /// A scanned QR verification payload (`VerifiedInPerson`).
+    QrCode,
+}
+
+/// Record of a completed verification, kept on the contact.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContactVerification {
+    pub method: VerificationMethod,
+    pub verified_at: DateTime<Utc>,
+    /// The SAS session the codes were compared in.
+    #[serde(default)]
+    pub session_id: Option<String>,
+}
+
+/// One step of the SAS exchange.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum SasStep {
+    /// Initiator → responder: hex BLAKE3 commitment to the initiator's nonce.
+    Commit { commitment: String },
+    /// Responder → initiator: the responder's nonce (base64).
+    Nonce { nonce: String },
+    /// Initiator → responder: the committed nonce (base64).
+    Reveal { nonce: String },
+}
+
+/// A signed SAS message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SasMessage {
+    pub session_id: String,
+    /// Base64 Ed25519 public key of the sender.
+    pub sender_key: String,
+    /// Base64 Ed25519 public key of the recipient.
+    pub recipient_key: String,
+    #[serde(flatten)]
+    pub step: SasStep,
+    /// Base64 Ed25519 signature by `sender_key` over the message with
+    /// `signature = ""`.
+    pub signature: String,
+}
+
+impl SasMessage {
+    fn new(session_id: &str, sender: &SigningKey, recipient_key: &str, step: SasStep) -> Self {
+        let mut msg = Self {
+            session_id: session_id.to_string(),
+            sender_key: BASE64.encode(sender.verifying_key().as_bytes()),
+            recipient_key: recipient_key.to_string(),
+            step,
+            signature: String::new(),
+        };
+        let payload = serde_json::to_string(&msg).expect("SasMessage must be serializable");
+        msg.signature = BASE64.encode(sender.sign(payload.as_bytes()).to_bytes());
+        msg
+    }
+
+    /// Verifies the signature against `sender_key`.
+    pub fn verify(&self) -> bool {
+        let mut unsigned = self.clone();
+        unsigned.signature = String::new();
+        let payload = serde_json::to_string(&unsigned).expect("SasMessage must be serializable");
+        verify_signature(&self.sender_key, payload.as_bytes(), &self.signature)
+    }
+}
+
+/// One emoji of a [`SasCode`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SasEmoji {
+    pub symbol: &'static str,
+    pub name: &'static str,
+}
+
+/// The short authentication string both parties compare: six digits, or
+/// equivalently five emoji.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SasCode {
+    /// Six digits as two groups of three, e.g. `"042 917"`.
+    pub digits: String,
+    pub emoji: Vec<SasEmoji>,
+}
+
+impl SasCode {
+    fn derive(transcript: &[&[u8]]) -> Self {
+        let mut material = Vec::new();
+        for part in transcript {
+            material.extend_from_slice(&(part.len() as u32).to_be_bytes());
+            material.extend_from_slice(part);
+        }
+        let out = blake3::derive_key(SAS_CONTEXT, &material);
+        let number = u32::from_be_bytes(out[0..4].try_into().unwrap()) % 1_000_000;
+        let bits = u64::from_be_bytes(out[4..12].try_into().unwrap());
+        let emoji = (0..5)
+            .map(|i| {
+                let (symbol, name) = SAS_EMOJI[((bits >> (6 * i)) & 63) as usize];
+                SasEmoji { symbol, name }
+            })
+            .collect();
+        Self {
+            digits: format!("{:03} {:03}", number / 1000, number % 1000),
+            emoji,
+        }
+    }
+}
+
+/// Which side of a SAS exchange this device is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SasRole {
+    Initiator,
+    Responder,
+}
+
+/// This side of one SAS exchange.
+#[derive(Clone)]
+pub struct SasSession {
+    session_id: String,
+    role: SasRole,
+    our_key: String,
+    their_key: String,
+    our_nonce: [u8; 32],
+    /// Responder only: the initiator's commitment.
+    their_commitment: Option<String>,
+    their_nonce: Option<Vec<u8>>,
+}
+
+impl SasSession {
+    /// Starts an exchange with `their_key`, returning the commit message to
+    /// send.
+    pub fn initiate(our_key: &SigningKey, their_key: &str) -> (Self, SasMessage) {
+        let session = Self {
+            session_id: Uuid::new_v4().to_string(),
+            role: SasRole::Initiator,
+            our_key: BASE64.encode(our_key.verifying_key().as_bytes()),
+            their_key: their_key.to_string(),
+            our_nonce: random_nonce(),
+            their_commitment: None,
+            their_nonce: None,
+        };
+        let commitment = commitment(&session.session_id, &session.our_key, &session.our_nonce);
+        let msg = SasMessage::new(
+            &session.session_id,
+            our_key,
+            their_key,
+            SasStep::Commit { commitment },
+        );
+        (session, msg)
+    }
+
+    /// Answers a commit message, returning the nonce message to send back.
+    pub fn accept(our_key: &SigningKey, commit: &SasMessage) -> Result<(Self, SasMessage)> {
+        let our_b64 = BASE64.encode(our_key.verifying_key().as_bytes());
+        check_message(commit, &our_b64, None)?;
+        let SasStep::Commit { commitment } = &commit.step else {
+            return Err(invalid("expected a commit message"));
+        };
+        let session = Self {
+            session_id: commit.session_id.clone(),
+            role: SasRole::Responder,
+            our_key: our_b64,
+            their_key: commit.sender_key.clone(),
+            our_nonce: random_nonce(),
+            their_commitment: Some(commitment.clone()),
+            their_nonce: None,
+        };
+        let msg = SasMessage::new(
+            &session.session_id,
+            our_key,
+            &session.their_key,
+            SasStep::Nonce {
+                nonce: BASE64.encode(session.our_nonce),
+            },
+        );
+        Ok((session, msg))
+    }
+
+    /// Processes the next message of the exchange, returning the reply to
+    /// send, if any. Re-delivered messages are ignored.
+    pub fn receive(
+        &mut self,
+        our_key: &SigningKey,
+        msg: &SasMessage,
+    ) -> Result<Option<SasMessage>> {
+        check_message(msg, &self.our_key, Some(self))?;
+        match (&msg.step, self.role) {
+            (SasStep::Nonce { nonce }, SasRole::Initiator) => {
+                let nonce = decode_nonce(nonce)?;
+                if self.their_nonce.as_ref().is_some_and(|n| *n != nonce) {
+                    return Err(invalid("the responder changed its nonce"));
+                }
+                self.their_nonce = Some(nonce);
+                Ok(Some(SasMessage::new(
+                    &self.session_id,
+                    our_key,
+                    &self.their_key,
+                    SasStep::Reveal {
+                        nonce: BASE64.encode(self.our_nonce),
+                    },
+                )))
+            }
+            (SasStep::Reveal { nonce }, SasRole::Responder) => {
+                let nonce = decode_nonce(nonce)?;
+                let expected = self.their_commitment.as_deref().unwrap_or_default();
+                if commitment(&self.session_id, &self.their_key, &nonce) != expected {
+                    return Err(invalid("the revealed nonce does not match the commitment"));
+                }
+                self.their_nonce = Some(nonce);
+                Ok(None)
+            }
+            (SasStep::Commit { commitment }, SasRole::Responder)
+                if self.their_commitment.as_ref() == Some(commitment) =>
+            {
+                Ok(None)
+            }
+            _ => Err(invalid("unexpected message for this side of the exchange")),
+        }
+    }
+
+    /// The code to compare, once both nonces are known.
+    pub fn code(&self) -> Option<SasCode> {
+        let their_nonce = self.their_nonce.as_deref()?;
+        let (initiator, responder, initiator_nonce, responder_nonce) = match self.role {
+            SasRole::Initiator => (
+                &self.our_key,
+                &self.their_key,
+                &self.our_nonce[..],
+                their_nonce,
+            ),
+            SasRole::Responder => (
+                &self.their_key,
+                &self.our_key,
+                their_nonce,
+                &self.our_nonce[..],
+            ),
+        };
+        Some(SasCode::derive(&[
+            self.session_id.as_bytes(),
+            initiator.as_bytes(),
+            responder.as_bytes(),
+            initiator_nonce,
+            responder_nonce,
+        ]))
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    pub fn role(&self) -> SasRole {
+        self.role
+    }
+
+    /// Base64 public key of the other party.
+    pub fn their_key(&self) -> &str {
+        &self.their_key
+    }
+}
+
+/// Summary of a SAS exchange in progress.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SasSessionInfo {
+    pub session_id: String,
+    pub role: SasRole,
+    /// Base64 public key of the other party.
+    pub contact_key: String,
+    /// Set once both nonces are known.
+    pub code: Option<SasCode>,
+}
+
+/// SAS exchanges in progress for one identity, and the messages waiting to
+/// be sent. Held in memory only: an interrupted exchange is started again.
+#[derive(Default)]
+pub struct SasSessions {
+    sessions: RwLock<HashMap<String, SasSession>>,
+    outbox: RwLock<Vec<SasMessage>>,
+}
+
+impl SasSessions {
+    /// Starts an exchange with `their_key` and queues its commit message.
+    pub fn start(&self, our_key: &SigningKey, their_key: &str) -> SasMessage {
+        let (session, msg) = SasSession::initiate(our_key, their_key);
+        self.sessions
+            .write()
+            .unwrap()
+            .insert(session.session_id.clone(), session);
+        self.outbox.write().unwrap().push(msg.clone());
+        msg
+    }
+
+    /// Processes an incoming message, queuing any reply. Returns the code
+    /// once it can be compared.
+    pub fn receive(&self, our_key: &SigningKey, msg: &SasMessage) -> Result<Option<SasCode>> {
+        let mut sessions = self.sessions.write().unwrap();
+        let reply = match sessions.get_mut(&msg.session_id) {
+            Some(session) => session.receive(our_key, msg)?,
+            None => {
+                let (session, reply) = SasSession::accept(our_key, msg)?;
+                sessions.insert(session.session_id.clone(), session);
+                Some(reply)
+            }
+        };
+        if let Some(reply) = reply {
+            self.outbox.write().unwrap().push(reply);
+        }
+        Ok(sessions.get(&msg.session_id).and_then(SasSession::code))
+    }
+
+    /// Removes and returns the queued messages for `recipient_key`.
+    pub fn take_outgoing(&self, recipient_key: &str) -> Vec<SasMessage> {
+        let mut outbox = self.outbox.write().unwrap();
+        let (taken, kept) = outbox
+            .drain(..)
+            .partition(|m| m.recipient_key == recipient_key);
+        *outbox = kept;
+        taken
+    }
+
+    /// Puts messages that could not be delivered back in the queue.
+    pub fn requeue(&self, messages: Vec<SasMessage>) {
+        self.outbox.write().unwrap().extend(messages);
+    }
+
+    pub fn code(&self, session_id: &str) -> Option<SasCode> {
+        self.sessions
+            .read()
+            .unwrap()
+            .get(session_id)
+            .and_then(SasSession::code)
+    }
+
+    pub fn list(&self) -> Vec<SasSessionInfo> {
+        let mut list: Vec<_> = self
+            .sessions
+            .read()
+            .unwrap()
+            .values()
+            .map(|s| SasSessionInfo {
+                session_id: s.session_id.clone(),
+                role: s.role,
+                contact_key: s.their_key.clone(),
+                code: s.code(),
+            })
+            .collect();
+        list.sort_by(|a, b| a.session_id.cmp(&b.session_id));
+        list
+    }
+
+    /// Ends a completed exchange, returning it. Fails if the code cannot
+    /// be compared yet.
+    pub fn finish(&self, session_id: &str) -> Result<SasSession> {
+        let mut sessions = self.sessions.write().unwrap();
+        match sessions.get(session_id) {
+            Some(session) if session.code().is_some() => Ok(sessions.remove(session_id).unwrap()),
+            Some(_) => Err(invalid("the exchange is not complete")),
+            None => Err(invalid("no such verification in progress")),
+        }
+    }
+
+    /// Abandons an exchange and drops its queued messages.
+    pub fn cancel(&self, session_id: &str) {
+        self.sessions.write().unwrap().remove(session_id);
+        self.outbox
+            .write()
+            .unwrap()
+            .retain(|m| m.session_id != session_id);
+    }
+}
+
+/// A public key signed by itself, for display as a QR code and scanning by
+/// someone standing next to its owner.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QrVerificationPayload {
+    pub version: u32,
+    /// Base64 Ed25519 public key being shown.
+    pub public_key: String,
+    pub declared_name: String,
+    /// Unix seconds.
+    pub created_at: i64,
+    /// Base64 Ed25519 signature by `public_key` over the payload with
+    /// `signature = ""`.
+    pub signature: String,
+}
+
+impl QrVerificationPayload {
+    /// Creates a payload for `key`, signed by it.
+    pub fn new(key: &SigningKey, declared_name: &str) -> Self {
+        let mut payload = Self {
+            version: 1,
+            public_key: BASE64.encode(key.verifying_key().as_bytes()),
+            declared_name: declared_name.to_string(),
+            created_at: Utc::now().timestamp(),
+            signature: String::new(),
+        };
+        let json = serde_json::to_string(&payload).expect("QR payload must be serializable");
+        payload.signature = BASE64.encode(key.sign(json.as_bytes()).to_bytes());
+        payload
+    }
+
+    /// Verifies the signature against `public_key`.
+    pub fn verify(&self) -> bool {
+        let mut unsigned = self.clone();
+        unsigned.signature = String::new();
+        let json = serde_json::to_string(&unsigned).expect("QR payload must be serializable");
+        verify_signature(&self.public_key, json.as_bytes(), &self.signature)
+    }
+
+    /// The string to encode in the QR code.
+    pub fn to_uri(&self) -> String {
+        let json = serde_json::to_vec(self).expect("QR payload must be serializable");
+        format!("{QR_URI_PREFIX}{}", URL_SAFE_NO_PAD.encode(json))
+    }
+
+    /// Parses a scanned QR code. Does not check the signature.
+    pub fn from_uri(uri: &str) -> Result<Self> {
+        let encoded = uri
+            .trim()
+            .strip_prefix(QR_URI_PREFIX)
+            .ok_or_else(|| invalid("not a Krillnotes verification code"))?;
+        let json = URL_SAFE_NO_PAD
+            .decode(encoded)
+            .map_err(|_| invalid("malformed verification code"))?;
+        Ok(serde_json::from_slice(&json)?)
+    }
+}
+
+fn check_message(msg: &SasMessage, our_key: &str, session: Option<&SasSession>) -> Result<()> {
+    if msg.recipient_key != our_key {
+        return Err(invalid("message is addressed to another identity"));
+    }
+    if session.is_some_and(|s| s.their_key != msg.sender_key || s.session_id != msg.session_id) {
+        return Err(invalid("message is from another exchange"));
+    }
+    if !msg.verify() {
+        return Err(invalid("message signature does not verify"));
+    }
+    Ok(())
+}
+
+fn commitment(session_id: &str, initiator_key: &str, nonce: &[u8]) -> String {
+    let mut hasher = blake3::Hasher::new();
+    for part in [session_id.as_bytes(), initiator_key.as_bytes(), nonce] {
+        hasher.update(&(part.len() as u32).to_be_bytes());
+        hasher.update(part);
+    }
+    hasher.finalize().to_hex().to_string()
+}
+
+fn random_nonce() -> [u8; 32] {
+    let mut nonce = [0u8; 32];
+    rand::rng().fill_bytes(&mut nonce);
+    nonce
+}
+
+fn decode_nonce(nonce: &str) -> Result<Vec<u8>> {
+    BASE64
+        .decode(nonce)
+        .ok()
+        .filter(|n| n.len() == 32)
+        .ok_or_else(|| invalid("malformed nonce"))
+}
+
+fn verify_signature(key: &str, payload: &[u8], signature: &str) -> bool {
+    let Some(vk) = BASE64
+        .decode(key)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .and_then(|arr| ed25519_dalek::VerifyingKey::from_bytes(&arr).ok())
+    else {
+        return false;
+    };
+    let Some(sig) = BASE64
+        .decode(signature)
+        .ok()
+        .and_then(|bytes| <[u8; 64]>::try_from(bytes).ok())
+        .map(|arr| ed25519_dalek::Signature::from_bytes(&arr))
+    else {
+        return false;
+    };
+    vk.verify(payload, &sig).is_ok()
+}
+
+fn invalid(msg: &str) -> KrillnotesError {
+    KrillnotesError::InvalidVerification(msg.to_string())
+}
+
+#[cfg(test)]
+#[path = "verification_tests.rs"]
+mod tests;
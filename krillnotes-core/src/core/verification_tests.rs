@@ -0,0 +1,104 @@
+use super::*;
+
+fn key(byte: u8) -> SigningKey {
+    SigningKey::from_bytes(&[byte; 32])
+}
+
+fn pubkey(key: &SigningKey) -> String {
+    BASE64.encode(key.verifying_key().as_bytes())
+}
+
+#[test]
+fn test_sas_exchange_yields_matching_codes() {
+    let (alice, bob) = (key(1), key(2));
+    let (alice_sessions, bob_sessions) = (SasSessions::default(), SasSessions::default());
+
+    let commit = alice_sessions.start(&alice, &pubkey(&bob));
+    assert_eq!(
+        alice_sessions.take_outgoing(&pubkey(&bob)),
+        vec![commit.clone()]
+    );
+    assert!(bob_sessions.receive(&bob, &commit).unwrap().is_none());
+
+    let nonce = bob_sessions.take_outgoing(&pubkey(&alice)).remove(0);
+    let alice_code = alice_sessions.receive(&alice, &nonce).unwrap().unwrap();
+    let reveal = alice_sessions.take_outgoing(&pubkey(&bob)).remove(0);
+    let bob_code = bob_sessions.receive(&bob, &reveal).unwrap().unwrap();
+
+    assert_eq!(alice_code, bob_code);
+    assert_eq!(alice_code.digits.len(), 7);
+    assert_eq!(alice_code.emoji.len(), 5);
+    assert_eq!(
+        bob_sessions.finish(&commit.session_id).unwrap().role(),
+        SasRole::Responder
+    );
+    assert!(bob_sessions.list().is_empty());
+}
+
+#[test]
+fn test_sas_detects_substituted_key_and_bad_reveal() {
+    let (alice, bob, mallory) = (key(1), key(2), key(3));
+
+    // Mallory sits between Alice and Bob, completing an exchange with each
+    // under her own key: the codes Alice and Bob read out differ.
+    let (mut alice_side, commit_to_mallory) = SasSession::initiate(&alice, &pubkey(&mallory));
+    let (mut mallory_responder, nonce_to_alice) =
+        SasSession::accept(&mallory, &commit_to_mallory).unwrap();
+    let (mut mallory_initiator, commit_to_bob) = SasSession::initiate(&mallory, &pubkey(&bob));
+    let (mut bob_side, nonce_to_mallory) = SasSession::accept(&bob, &commit_to_bob).unwrap();
+
+    let reveal_to_mallory = alice_side
+        .receive(&alice, &nonce_to_alice)
+        .unwrap()
+        .unwrap();
+    mallory_responder
+        .receive(&mallory, &reveal_to_mallory)
+        .unwrap();
+    let reveal_to_bob = mallory_initiator
+        .receive(&mallory, &nonce_to_mallory)
+        .unwrap()
+        .unwrap();
+    assert!(bob_side.code().is_none());
+    bob_side.receive(&bob, &reveal_to_bob).unwrap();
+    assert_ne!(alice_side.code().unwrap(), bob_side.code().unwrap());
+
+    // Replaying Alice's messages to Bob fails: they are not addressed to him.
+    assert!(SasSession::accept(&bob, &commit_to_mallory).is_err());
+
+    // A reveal that does not match the commitment is refused.
+    let forged = SasMessage::new(
+        &commit_to_bob.session_id,
+        &mallory,
+        &pubkey(&bob),
+        SasStep::Reveal {
+            nonce: BASE64.encode([7u8; 32]),
+        },
+    );
+    assert!(matches!(
+        bob_side.receive(&bob, &forged),
+        Err(KrillnotesError::InvalidVerification(_))
+    ));
+
+    // So is a message whose signature was tampered with.
+    let mut tampered = commit_to_bob.clone();
+    tampered.step = SasStep::Commit {
+        commitment: "00".into(),
+    };
+    assert!(SasSession::accept(&bob, &tampered).is_err());
+}
+
+#[test]
+fn test_qr_payload_roundtrip() {
+    let alice = key(1);
+    let payload = QrVerificationPayload::new(&alice, "Alice");
+    let uri = payload.to_uri();
+    assert!(uri.starts_with(QR_URI_PREFIX));
+    let scanned = QrVerificationPayload::from_uri(&uri).unwrap();
+    assert_eq!(scanned, payload);
+    assert!(scanned.verify());
+
+    let mut renamed = scanned.clone();
+    renamed.declared_name = "Mallory".into();
+    assert!(!renamed.verify());
+    assert!(QrVerificationPayload::from_uri("https://example.com").is_err());
+}
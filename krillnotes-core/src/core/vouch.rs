@@ -0,0 +1,119 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Signed vouch statements: one identity attesting to another's key.
+//!
+//! A [`VouchStatement`] is signed by the voucher and names the subject's
+//! public key. Statements travel in vouch `.swarm` bundles (see
+//! [`crate::core::swarm::vouch`]). A later statement from the same voucher
+//! about the same subject supersedes an earlier one, so a vouch is revoked by
+//! issuing a statement with `revoked = true`.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{Deserialize, Serialize};
+
+/// A statement by `voucher_key` that `subject_key` belongs to `subject_name`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VouchStatement {
+    /// Base64 Ed25519 public key of the vouching identity.
+    pub voucher_key: String,
+    /// Base64 Ed25519 public key being vouched for.
+    pub subject_key: String,
+    /// The subject's declared name, as the voucher knows it.
+    pub subject_name: String,
+    /// Unix milliseconds; orders statements from the same voucher.
+    pub issued_at_ms: u64,
+    /// True if this statement withdraws the voucher's earlier vouch.
+    pub revoked: bool,
+    /// Base64 Ed25519 signature by `voucher_key` over the statement with
+    /// `signature = ""`.
+    pub signature: String,
+}
+
+impl VouchStatement {
+    /// Creates a statement about `subject_key` signed by `voucher` — a
+    /// vouch, or its revocation if `revoked` is set.
+    pub fn new(
+        voucher: &ed25519_dalek::SigningKey,
+        subject_key: &str,
+        subject_name: &str,
+        revoked: bool,
+        issued_at_ms: u64,
+    ) -> Self {
+        use ed25519_dalek::Signer;
+
+        let mut statement = Self {
+            voucher_key: BASE64.encode(voucher.verifying_key().as_bytes()),
+            subject_key: subject_key.to_string(),
+            subject_name: subject_name.to_string(),
+            issued_at_ms,
+            revoked,
+            signature: String::new(),
+        };
+        let payload =
+            serde_json::to_string(&statement).expect("VouchStatement must be serializable");
+        statement.signature = BASE64.encode(voucher.sign(payload.as_bytes()).to_bytes());
+        statement
+    }
+
+    /// Verifies the signature against `voucher_key`.
+    pub fn verify(&self) -> bool {
+        use ed25519_dalek::Verifier;
+
+        let Some(vk) = BASE64
+            .decode(&self.voucher_key)
+            .ok()
+            .and_then(|b| <[u8; 32]>::try_from(b).ok())
+            .and_then(|arr| ed25519_dalek::VerifyingKey::from_bytes(&arr).ok())
+        else {
+            return false;
+        };
+        let Some(sig) = BASE64
+            .decode(&self.signature)
+            .ok()
+            .and_then(|b| <[u8; 64]>::try_from(b).ok())
+            .map(|arr| ed25519_dalek::Signature::from_bytes(&arr))
+        else {
+            return false;
+        };
+        let mut unsigned = self.clone();
+        unsigned.signature = String::new();
+        let payload =
+            serde_json::to_string(&unsigned).expect("VouchStatement must be serializable");
+        vk.verify(payload.as_bytes(), &sig).is_ok()
+    }
+
+    /// True if this statement replaces `other`: same voucher and subject,
+    /// issued later.
+    pub fn supersedes(&self, other: &VouchStatement) -> bool {
+        self.voucher_key == other.voucher_key
+            && self.subject_key == other.subject_key
+            && self.issued_at_ms > other.issued_at_ms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vouch_signature_and_revocation() {
+        let voucher = ed25519_dalek::SigningKey::from_bytes(&[5u8; 32]);
+        let vouch = VouchStatement::new(&voucher, "c3ViamVjdA==", "Carol", false, 1_000);
+        assert!(vouch.verify());
+        assert!(!vouch.revoked);
+
+        let mut retargeted = vouch.clone();
+        retargeted.subject_key = "b3RoZXI=".into();
+        assert!(!retargeted.verify());
+
+        let revocation = VouchStatement::new(&voucher, "c3ViamVjdA==", "Carol", true, 2_000);
+        assert!(revocation.verify() && revocation.revoked);
+        assert!(revocation.supersedes(&vouch));
+        assert!(!vouch.supersedes(&revocation));
+    }
+}
@@ -7,6 +7,10 @@
 //! File attachment operations: encrypt, store, retrieve, and delete.
 
 use super::*;
+use crate::core::attachment::{
+    decrypt_attachment_stream, encrypt_attachment_stream, StreamedAttachment,
+};
+use std::io::{Read, Write};
 
 impl Workspace {
     // -------------------------------------------------------------------------
@@ -26,7 +30,7 @@ impl Workspace {
         data: &[u8],
         signing_key: Option<&ed25519_dalek::SigningKey>,
     ) -> Result<AttachmentMeta> {
-        // Enforce workspace size limit
+        // Reject oversized buffers before encrypting anything.
         if let Some(limit) = self.attachment_max_size_bytes()? {
             if data.len() as u64 > limit {
                 return Err(KrillnotesError::AttachmentTooLarge {
@@ -35,34 +39,147 @@ impl Workspace {
                 });
             }
         }
+        self.attach_file_from_reader(note_id, filename, mime_type, data, signing_key)
+    }
 
-        // SHA-256 hash for integrity
-        let hash = {
-            let mut h = Sha256::new();
-            h.update(data);
-            format!("{:x}", h.finalize())
-        };
-
+    /// Streaming variant of [`Self::attach_file`]: the file is read from `reader`
+    /// and encrypted chunk by chunk, so arbitrarily large files are attached in
+    /// bounded memory.
+    ///
+    /// The workspace size limit is enforced while reading; an oversized source is
+    /// rejected with [`KrillnotesError::AttachmentTooLarge`] once the limit is passed.
+    pub fn attach_file_from_reader<R: Read>(
+        &mut self,
+        note_id: &str,
+        filename: &str,
+        mime_type: Option<&str>,
+        mut reader: R,
+        signing_key: Option<&ed25519_dalek::SigningKey>,
+    ) -> Result<AttachmentMeta> {
         let id = uuid::Uuid::new_v4().to_string();
-        let now = UnixSecs::now();
+        let limit = self.attachment_max_size_bytes()?;
+        self.attach_file_inner(
+            id,
+            note_id,
+            filename,
+            mime_type,
+            &mut reader,
+            limit,
+            signing_key,
+        )
+    }
 
-        let (encrypted_bytes, file_salt) = encrypt_attachment(data, self.attachment_key.as_ref())?;
+    /// Like [`Self::attach_file`] but with a caller-chosen attachment ID, always signed
+    /// with the workspace's own key. Used when grafting archives, where `File` fields
+    /// must be rewritten to the new ID before the attachment is stored.
+    pub(crate) fn attach_file_signed_with_id(
+        &mut self,
+        id: &str,
+        note_id: &str,
+        filename: &str,
+        mime_type: Option<&str>,
+        reader: &mut dyn Read,
+    ) -> Result<AttachmentMeta> {
+        let key = self.signing_key.clone();
+        let limit = self.attachment_max_size_bytes()?;
+        self.attach_file_inner(
+            id.to_string(),
+            note_id,
+            filename,
+            mime_type,
+            reader,
+            limit,
+            Some(&key),
+        )
+    }
 
-        // Write to disk
-        let enc_path = self
+    /// Encrypts an attachment's content into the workspace without recording
+    /// it, so merge-import can read and check archive content before its first
+    /// write. The size limit applies. The content is later recorded with
+    /// [`Self::attach_staged_file`] or removed with [`Self::discard_staged_file`].
+    pub(crate) fn stage_attachment_file(
+        &self,
+        id: &str,
+        reader: &mut dyn Read,
+    ) -> Result<StreamedAttachment> {
+        let limit = self.attachment_max_size_bytes()?;
+        self.write_attachment_file(id, reader, limit)
+    }
+
+    /// Records content staged by [`Self::stage_attachment_file`] as an
+    /// attachment of `note_id`, signed with the workspace's own key.
+    pub(crate) fn attach_staged_file(
+        &mut self,
+        id: &str,
+        note_id: &str,
+        filename: &str,
+        mime_type: Option<&str>,
+        streamed: StreamedAttachment,
+    ) -> Result<AttachmentMeta> {
+        let key = self.signing_key.clone();
+        self.record_attachment(
+            id.to_string(),
+            note_id,
+            filename,
+            mime_type,
+            streamed,
+            Some(&key),
+        )
+    }
+
+    /// Removes content staged by [`Self::stage_attachment_file`] that was
+    /// never attached.
+    pub(crate) fn discard_staged_file(&self, id: &str) {
+        let path = self
             .workspace_root
             .join("attachments")
             .join(format!("{id}.enc"));
-        std::fs::write(&enc_path, &encrypted_bytes)?;
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn attach_file_inner(
+        &mut self,
+        id: String,
+        note_id: &str,
+        filename: &str,
+        mime_type: Option<&str>,
+        reader: &mut dyn Read,
+        size_limit: Option<u64>,
+        signing_key: Option<&ed25519_dalek::SigningKey>,
+    ) -> Result<AttachmentMeta> {
+        // Encrypt to disk; the size limit is checked as the stream is consumed.
+        let streamed = self.write_attachment_file(&id, reader, size_limit)?;
+        self.record_attachment(id, note_id, filename, mime_type, streamed, signing_key)
+    }
+
+    /// Inserts the metadata row (and signed `AddAttachment` operation) for
+    /// content already written to `attachments/<id>.enc`.
+    fn record_attachment(
+        &mut self,
+        id: String,
+        note_id: &str,
+        filename: &str,
+        mime_type: Option<&str>,
+        streamed: StreamedAttachment,
+        signing_key: Option<&ed25519_dalek::SigningKey>,
+    ) -> Result<AttachmentMeta> {
+        let now = UnixSecs::now();
+
+        // Content already stored for another attachment is kept once.
+        let salt = match self.link_existing_content(&id, &streamed.hash_sha256)? {
+            Some(salt) => salt,
+            None => streamed.salt.to_vec(),
+        };
 
         let meta = AttachmentMeta {
             id,
             note_id: note_id.to_string(),
             filename: filename.to_string(),
             mime_type: mime_type.map(|s| s.to_string()),
-            size_bytes: data.len() as i64,
-            hash_sha256: hash,
-            salt: hex::encode(file_salt),
+            size_bytes: streamed.size_bytes as i64,
+            hash_sha256: streamed.hash_sha256,
+            salt: hex::encode(&salt),
             created_at: now,
         };
 
@@ -80,6 +197,7 @@ impl Workspace {
                 size_bytes: meta.size_bytes,
                 hash_sha256: meta.hash_sha256.clone(),
                 added_by: String::new(),
+                prev_hash: None,
                 signature: String::new(),
             };
             op.sign(key);
@@ -96,7 +214,7 @@ impl Workspace {
                  VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
                 rusqlite::params![
                     meta.id, meta.note_id, meta.filename, meta.mime_type.as_deref(),
-                    meta.size_bytes, meta.hash_sha256, file_salt.as_slice(), meta.created_at
+                    meta.size_bytes, meta.hash_sha256, salt, meta.created_at
                 ],
             )?;
             if let Some((_, ref op)) = signed_op {
@@ -118,6 +236,56 @@ impl Workspace {
         Ok(meta)
     }
 
+    /// Encrypts `reader` into `attachments/<id>.enc` via a `.part` file that is
+    /// renamed into place only once the whole stream has been written, so a failed
+    /// or oversized write never leaves a truncated attachment behind.
+    pub(super) fn write_attachment_file(
+        &self,
+        id: &str,
+        reader: &mut dyn Read,
+        size_limit: Option<u64>,
+    ) -> Result<StreamedAttachment> {
+        let dir = self.workspace_root.join("attachments");
+        let enc_path = dir.join(format!("{id}.enc"));
+        let part_path = dir.join(format!("{id}.enc.part"));
+
+        let result = (|| {
+            let mut out = std::io::BufWriter::new(std::fs::File::create(&part_path)?);
+            // Read one byte past the limit so an oversized source is detectable.
+            let streamed = match size_limit {
+                Some(limit) => {
+                    let mut limited = reader.take(limit.saturating_add(1));
+                    let streamed = encrypt_attachment_stream(
+                        &mut limited,
+                        &mut out,
+                        self.attachment_key.as_ref(),
+                    )?;
+                    if streamed.size_bytes > limit {
+                        return Err(KrillnotesError::AttachmentTooLarge {
+                            size: streamed.size_bytes,
+                            limit,
+                        });
+                    }
+                    streamed
+                }
+                None => encrypt_attachment_stream(reader, &mut out, self.attachment_key.as_ref())?,
+            };
+            out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+            Ok(streamed)
+        })();
+
+        match result {
+            Ok(streamed) => {
+                std::fs::rename(&part_path, &enc_path)?;
+                Ok(streamed)
+            }
+            Err(e) => {
+                let _ = std::fs::remove_file(&part_path);
+                Err(e)
+            }
+        }
+    }
+
     /// Import-only: attach a file with a pre-specified ID (preserves IDs from export).
     /// Does NOT enforce size limits (the size was already validated at export time).
     pub fn attach_file_with_id(
@@ -128,22 +296,28 @@ impl Workspace {
         mime_type: Option<&str>,
         data: &[u8],
     ) -> Result<()> {
-        let hash = {
-            let mut h = Sha256::new();
-            h.update(data);
-            format!("{:x}", h.finalize())
-        };
+        self.attach_file_with_id_from_reader(id, note_id, filename, mime_type, data)
+    }
+
+    /// Streaming variant of [`Self::attach_file_with_id`], used by archive import
+    /// so large attachments are never held in memory.
+    pub fn attach_file_with_id_from_reader<R: Read>(
+        &mut self,
+        id: &str,
+        note_id: &str,
+        filename: &str,
+        mime_type: Option<&str>,
+        mut reader: R,
+    ) -> Result<()> {
         let now = UnixSecs::now();
-        let (encrypted_bytes, file_salt) = encrypt_attachment(data, self.attachment_key.as_ref())?;
-        let enc_path = self
-            .workspace_root
-            .join("attachments")
-            .join(format!("{id}.enc"));
-        std::fs::write(&enc_path, &encrypted_bytes)?;
+        let streamed = self.write_attachment_file(id, &mut reader, None)?;
         let _ = self.storage.connection().execute(
             "INSERT OR IGNORE INTO attachments (id, note_id, filename, mime_type, size_bytes, hash_sha256, salt, created_at)
              VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
-            rusqlite::params![id, note_id, filename, mime_type, data.len() as i64, hash, file_salt.as_slice(), now],
+            rusqlite::params![
+                id, note_id, filename, mime_type,
+                streamed.size_bytes as i64, streamed.hash_sha256, streamed.salt.as_slice(), now
+            ],
         );
         Ok(())
     }
@@ -197,14 +371,29 @@ impl Workspace {
     }
 
     /// Decrypts and returns the plaintext bytes for an attachment.
+    ///
+    /// Loads the whole file into memory; prefer [`Self::copy_attachment_to`] for
+    /// attachments that may be large.
     pub fn get_attachment_bytes(&self, attachment_id: &str) -> Result<Vec<u8>> {
-        let (salt_bytes, _): (Vec<u8>, i64) = self
+        let mut bytes = Vec::new();
+        self.copy_attachment_to(attachment_id, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Decrypts an attachment straight into `writer` in bounded memory and returns
+    /// the number of plaintext bytes written.
+    pub fn copy_attachment_to<W: Write + ?Sized>(
+        &self,
+        attachment_id: &str,
+        writer: &mut W,
+    ) -> Result<u64> {
+        let salt_bytes: Vec<u8> = self
             .storage
             .connection()
             .query_row(
-                "SELECT salt, size_bytes FROM attachments WHERE id = ?",
+                "SELECT salt FROM attachments WHERE id = ?",
                 [attachment_id],
-                |row| Ok((row.get(0)?, row.get(1)?)),
+                |row| row.get(0),
             )
             .map_err(|_| KrillnotesError::NoteNotFound(attachment_id.to_string()))?;
 
@@ -212,8 +401,8 @@ impl Workspace {
             .workspace_root
             .join("attachments")
             .join(format!("{attachment_id}.enc"));
-        let encrypted_bytes = std::fs::read(&enc_path)?;
-        decrypt_attachment(&encrypted_bytes, self.attachment_key.as_ref(), &salt_bytes)
+        let mut file = std::io::BufReader::new(std::fs::File::open(&enc_path)?);
+        decrypt_attachment_stream(&mut file, writer, self.attachment_key.as_ref(), &salt_bytes)
     }
 
     /// Returns decrypted attachment bytes together with the stored MIME type.
@@ -221,23 +410,16 @@ impl Workspace {
         &self,
         attachment_id: &str,
     ) -> Result<(Vec<u8>, Option<String>)> {
-        let (salt_bytes, _, mime_type): (Vec<u8>, i64, Option<String>) = self
+        let mime_type: Option<String> = self
             .storage
             .connection()
             .query_row(
-                "SELECT salt, size_bytes, mime_type FROM attachments WHERE id = ?",
+                "SELECT mime_type FROM attachments WHERE id = ?",
                 [attachment_id],
-                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+                |row| row.get(0),
             )
             .map_err(|_| KrillnotesError::NoteNotFound(attachment_id.to_string()))?;
-
-        let enc_path = self
-            .workspace_root
-            .join("attachments")
-            .join(format!("{attachment_id}.enc"));
-        let encrypted_bytes = std::fs::read(&enc_path)?;
-        let bytes =
-            decrypt_attachment(&encrypted_bytes, self.attachment_key.as_ref(), &salt_bytes)?;
+        let bytes = self.get_attachment_bytes(attachment_id)?;
         Ok((bytes, mime_type))
     }
 
@@ -360,6 +542,7 @@ impl Workspace {
                     attachment_id: attachment_id.to_string(),
                     note_id: m.note_id.clone(),
                     removed_by: String::new(),
+                    prev_hash: None,
                     signature: String::new(),
                 };
                 op.sign(key);
@@ -440,7 +623,8 @@ impl Workspace {
         Ok(())
     }
 
-    /// Purges any `.enc.trash` files left over from a previous session.
+    /// Purges any `.enc.trash` files left over from a previous session, along with
+    /// `.enc.part` files from attachment writes that were interrupted.
     ///
     /// Should be called once on workspace open. Since undo stacks are in-session
     /// only, all `.enc.trash` files from prior sessions are safe to remove.
@@ -449,7 +633,10 @@ impl Workspace {
         if let Ok(entries) = std::fs::read_dir(&trash_dir) {
             for entry in entries.flatten() {
                 let path = entry.path();
-                if path.extension().and_then(|e| e.to_str()) == Some("trash") {
+                if matches!(
+                    path.extension().and_then(|e| e.to_str()),
+                    Some("trash") | Some("part")
+                ) {
                     let _ = std::fs::remove_file(&path);
                 }
             }
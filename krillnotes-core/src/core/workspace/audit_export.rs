@@ -0,0 +1,175 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Signed audit export of the operation log.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::core::audit_export::{
+    AuditExport, AuditExportOptions, AuditManifest, AuditRecord, AUDIT_FORMAT_VERSION,
+};
+use crate::core::contact::ContactManager;
+use crate::core::error::Result;
+use crate::core::operation::Operation;
+use crate::core::workspace::Workspace;
+
+impl Workspace {
+    /// Exports the operation log — optionally only the operations on one
+    /// subtree within an HLC date range — as signed JSON Lines with a
+    /// detached manifest signed by the current identity.
+    ///
+    /// Author and voucher names are resolved from `contact_manager`; the
+    /// current identity appears as `exporter_name`.
+    pub fn export_audit_log(
+        &self,
+        options: &AuditExportOptions,
+        contact_manager: &ContactManager,
+        exporter_name: &str,
+    ) -> Result<AuditExport> {
+        let conn = self.storage.connection();
+        let mut stmt = conn.prepare(
+            "SELECT operation_data, received_from_peer, verified_by FROM operations \
+             ORDER BY timestamp_wall_ms, timestamp_counter, timestamp_node_id, operation_id",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let ops = rows
+            .into_iter()
+            .map(|(data, from, verified_by)| {
+                Ok((serde_json::from_str::<Operation>(&data)?, from, verified_by))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let subtree = match &options.subtree_root {
+            Some(root) => Some(self.subtree_note_ids(root, ops.iter().map(|(op, ..)| op))?),
+            None => None,
+        };
+
+        let mut names: HashMap<String, Option<String>> = HashMap::new();
+        let mut name_of = |key: &str| -> Result<Option<String>> {
+            if key.is_empty() {
+                return Ok(None);
+            }
+            if key == self.current_identity_pubkey {
+                return Ok(Some(exporter_name.to_string()));
+            }
+            if let Some(name) = names.get(key) {
+                return Ok(name.clone());
+            }
+            let name = contact_manager
+                .find_by_public_key(key)?
+                .map(|c| c.display_name().to_string());
+            names.insert(key.to_string(), name.clone());
+            Ok(name)
+        };
+
+        let mut records = Vec::new();
+        let mut record_count = 0u64;
+        for (op, received_from_peer, verified_by) in ops {
+            let wall_ms = op.timestamp().wall_ms;
+            if options.from_ms.is_some_and(|from| wall_ms < from)
+                || options.to_ms.is_some_and(|to| wall_ms > to)
+            {
+                continue;
+            }
+            if let Some(subtree) = &subtree {
+                let note_ids = Self::operation_note_ids(&op);
+                if note_ids.is_empty() || !note_ids.iter().any(|id| subtree.contains(*id)) {
+                    continue;
+                }
+            }
+            let verified_by = verified_by.unwrap_or_else(|| self.current_identity_pubkey.clone());
+            let record = AuditRecord {
+                operation_id: op.operation_id().to_string(),
+                operation_type: Self::operation_type_str(&op).to_string(),
+                timestamp: op.timestamp(),
+                device_id: op.device_id().to_string(),
+                author_key: op.author_key().to_string(),
+                author_name: name_of(op.author_key())?,
+                verified_by_name: name_of(&verified_by)?,
+                verified_by,
+                received_from_peer,
+                prev_hash: op.prev_hash().map(str::to_string),
+                signature: op.get_signature().to_string(),
+                operation: op,
+            };
+            serde_json::to_writer(&mut records, &record)?;
+            records.push(b'\n');
+            record_count += 1;
+        }
+
+        let mut manifest = AuditManifest {
+            format_version: AUDIT_FORMAT_VERSION,
+            workspace_id: self.workspace_id.clone(),
+            exported_at: chrono::Utc::now().timestamp(),
+            exported_by: String::new(),
+            exported_by_name: exporter_name.to_string(),
+            subtree_root: options.subtree_root.clone(),
+            from_ms: options.from_ms,
+            to_ms: options.to_ms,
+            record_count,
+            signature: String::new(),
+        };
+        manifest.sign(&records, &self.signing_key);
+        Ok(AuditExport { records, manifest })
+    }
+
+    /// `root` and every note that was ever below it — current notes, and
+    /// notes created or moved under it in `ops` but deleted or moved since.
+    fn subtree_note_ids<'a>(
+        &self,
+        root: &str,
+        ops: impl Iterator<Item = &'a Operation>,
+    ) -> Result<HashSet<String>> {
+        let mut children: HashMap<String, HashSet<String>> = HashMap::new();
+        let conn = self.storage.connection();
+        let mut stmt =
+            conn.prepare("SELECT id, parent_id FROM notes WHERE parent_id IS NOT NULL")?;
+        for row in stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })? {
+            let (id, parent) = row?;
+            children.entry(parent).or_default().insert(id);
+        }
+        for op in ops {
+            let (note_id, Some(parent)) = (match op {
+                Operation::CreateNote {
+                    note_id, parent_id, ..
+                } => (note_id, parent_id),
+                Operation::MoveNote {
+                    note_id,
+                    new_parent_id,
+                    ..
+                } => (note_id, new_parent_id),
+                _ => continue,
+            }) else {
+                continue;
+            };
+            children
+                .entry(parent.clone())
+                .or_default()
+                .insert(note_id.clone());
+        }
+
+        let mut subtree = HashSet::from([root.to_string()]);
+        let mut pending = vec![root.to_string()];
+        while let Some(id) = pending.pop() {
+            for child in children.get(&id).into_iter().flatten() {
+                if subtree.insert(child.clone()) {
+                    pending.push(child.clone());
+                }
+            }
+        }
+        Ok(subtree)
+    }
+}
@@ -0,0 +1,556 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Attachment content transfer by hash, outside the operation stream.
+//!
+//! A synced `AddAttachment` carries only metadata. The recipient first looks
+//! for local content with the same `hash_sha256` and links to it; otherwise it
+//! records a pending download and asks its peers for the hash in the next
+//! bundles it sends ([`BlobWant`]). A peer holding the content answers with
+//! [`BlobChunk`]s, a few per bundle, until the transfer is complete. Received
+//! chunks are kept encrypted under `attachments/incoming/<hash>/`, so a transfer
+//! interrupted by a crash or a lost bundle resumes from the last chunk.
+
+use super::*;
+use crate::core::attachment::{decrypt_attachment, decrypt_attachment_range, encrypt_attachment};
+use crate::core::swarm::delta::{BlobChunk, BlobWant};
+use crate::core::workspace::permissions::ReadScope;
+use std::io::{Cursor, Read};
+
+/// Plaintext bytes per [`BlobChunk`].
+pub const BLOB_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Most attachment bytes sent to one peer in a single bundle.
+pub const MAX_BLOB_BYTES_PER_BUNDLE: usize = 8 * 1024 * 1024;
+
+/// Seconds without progress before a want is sent again.
+const BLOB_WANT_RETRY_SECS: i64 = 300;
+
+/// An attachment whose metadata arrived by sync but whose content has not.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingAttachment {
+    pub id: String,
+    pub note_id: String,
+    pub filename: String,
+    pub size_bytes: i64,
+    pub hash_sha256: String,
+    /// Bytes of the content received so far.
+    pub received_bytes: i64,
+}
+
+/// True for a lowercase hex SHA-256, the only form used as a directory name.
+fn is_sha256_hex(hash: &str) -> bool {
+    hash.len() == 64
+        && hash
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+/// Reads the plaintext of a download's chunk files, in offset order, one chunk
+/// in memory at a time.
+struct IncomingChunks<'a> {
+    files: std::vec::IntoIter<std::path::PathBuf>,
+    current: Cursor<Vec<u8>>,
+    key: Option<&'a [u8; 32]>,
+}
+
+impl Read for IncomingChunks<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            let n = self.current.read(buf)?;
+            if n > 0 || buf.is_empty() {
+                return Ok(n);
+            }
+            let Some(path) = self.files.next() else {
+                return Ok(0);
+            };
+            let stored = std::fs::read(&path)?;
+            if stored.len() < 32 {
+                return Err(std::io::Error::other("truncated chunk file"));
+            }
+            let (salt, data) = stored.split_at(32);
+            let plaintext =
+                decrypt_attachment(data, self.key, salt).map_err(std::io::Error::other)?;
+            self.current = Cursor::new(plaintext);
+        }
+    }
+}
+
+impl Workspace {
+    fn incoming_dir(&self, hash: &str) -> std::path::PathBuf {
+        self.workspace_root
+            .join("attachments")
+            .join("incoming")
+            .join(hash)
+    }
+
+    /// Points attachment `id` at the stored content of another attachment with
+    /// the same hash, so identical files are kept once. Returns that file's
+    /// salt, or `None` if no local copy exists.
+    ///
+    /// Falls back to a plain copy where the filesystem has no hard links.
+    pub(super) fn link_existing_content(&self, id: &str, hash: &str) -> Result<Option<Vec<u8>>> {
+        let candidates: Vec<(String, Vec<u8>)> = {
+            let mut stmt = self
+                .storage
+                .connection()
+                .prepare("SELECT id, salt FROM attachments WHERE hash_sha256 = ?1 AND id != ?2")?;
+            let rows = stmt
+                .query_map(rusqlite::params![hash, id], |row| {
+                    Ok((row.get(0)?, row.get(1)?))
+                })?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            rows
+        };
+        let dir = self.workspace_root.join("attachments");
+        for (other, salt) in candidates {
+            let source = dir.join(format!("{other}.enc"));
+            if !source.exists() {
+                continue;
+            }
+            let target = dir.join(format!("{id}.enc"));
+            if target.exists() {
+                std::fs::remove_file(&target)?;
+            }
+            if std::fs::hard_link(&source, &target).is_err() {
+                std::fs::copy(&source, &target)?;
+            }
+            return Ok(Some(salt));
+        }
+        Ok(None)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn insert_attachment_row(
+        &self,
+        id: &str,
+        note_id: &str,
+        filename: &str,
+        mime_type: Option<&str>,
+        size_bytes: i64,
+        hash: &str,
+        salt: &[u8],
+    ) -> Result<()> {
+        self.storage.connection().execute(
+            "INSERT OR IGNORE INTO attachments (id, note_id, filename, mime_type, size_bytes, hash_sha256, salt, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            rusqlite::params![id, note_id, filename, mime_type, size_bytes, hash, salt, UnixSecs::now()],
+        )?;
+        Ok(())
+    }
+
+    /// Makes a synced attachment available: from local content with the same
+    /// hash if there is any, otherwise by queueing a download from peers.
+    pub(super) fn receive_attachment_metadata(
+        &mut self,
+        id: &str,
+        note_id: &str,
+        filename: &str,
+        mime_type: Option<&str>,
+        size_bytes: i64,
+        hash: &str,
+    ) -> Result<()> {
+        let exists: bool = self.storage.connection().query_row(
+            "SELECT EXISTS(SELECT 1 FROM attachments WHERE id = ?1)",
+            [id],
+            |row| row.get(0),
+        )?;
+        if exists {
+            return Ok(());
+        }
+        if let Some(salt) = self.link_existing_content(id, hash)? {
+            log::debug!(target: "krillnotes::sync",
+                "attachment {id}: content already present, linked by hash");
+            return self
+                .insert_attachment_row(id, note_id, filename, mime_type, size_bytes, hash, &salt);
+        }
+        if size_bytes == 0 {
+            let streamed = self.write_attachment_file(id, &mut std::io::empty(), None)?;
+            return self.insert_attachment_row(
+                id,
+                note_id,
+                filename,
+                mime_type,
+                0,
+                &streamed.hash_sha256,
+                &streamed.salt,
+            );
+        }
+        if !is_sha256_hex(hash) || size_bytes < 0 {
+            log::warn!(target: "krillnotes::sync",
+                "attachment {id} has an invalid hash or size, not downloading");
+            return Ok(());
+        }
+
+        let conn = self.storage.connection();
+        conn.execute(
+            "INSERT OR IGNORE INTO pending_attachments (id, note_id, filename, mime_type, size_bytes, hash_sha256, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+            rusqlite::params![id, note_id, filename, mime_type, size_bytes, hash, UnixSecs::now()],
+        )?;
+        conn.execute(
+            "INSERT OR IGNORE INTO blob_downloads (hash_sha256, size_bytes) VALUES (?1, ?2)",
+            rusqlite::params![hash, size_bytes],
+        )?;
+        log::info!(target: "krillnotes::sync",
+            "attachment {id}: queued download of {size_bytes} bytes");
+        Ok(())
+    }
+
+    /// Lists attachments still waiting for their content.
+    pub fn pending_attachments(&self) -> Result<Vec<PendingAttachment>> {
+        let mut stmt = self.storage.connection().prepare(
+            "SELECT p.id, p.note_id, p.filename, p.size_bytes, p.hash_sha256, COALESCE(d.received_bytes, 0)
+             FROM pending_attachments p LEFT JOIN blob_downloads d ON d.hash_sha256 = p.hash_sha256
+             ORDER BY p.created_at ASC",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(PendingAttachment {
+                    id: row.get(0)?,
+                    note_id: row.get(1)?,
+                    filename: row.get(2)?,
+                    size_bytes: row.get(3)?,
+                    hash_sha256: row.get(4)?,
+                    received_bytes: row.get(5)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Downloads to ask peers for now: those never requested, and those
+    /// without progress for a while. Downloads no live note needs any more
+    /// are dropped along with their chunks.
+    pub fn due_blob_wants(&self) -> Result<Vec<BlobWant>> {
+        let conn = self.storage.connection();
+        let orphans: Vec<String> = {
+            let mut stmt = conn.prepare(
+                "SELECT hash_sha256 FROM blob_downloads d WHERE NOT EXISTS (
+                     SELECT 1 FROM pending_attachments p JOIN notes n ON n.id = p.note_id
+                     WHERE p.hash_sha256 = d.hash_sha256)",
+            )?;
+            let rows = stmt
+                .query_map([], |row| row.get(0))?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            rows
+        };
+        for hash in &orphans {
+            conn.execute("DELETE FROM blob_downloads WHERE hash_sha256 = ?1", [hash])?;
+            conn.execute(
+                "DELETE FROM pending_attachments WHERE hash_sha256 = ?1",
+                [hash],
+            )?;
+            let _ = std::fs::remove_dir_all(self.incoming_dir(hash));
+        }
+
+        let cutoff = chrono::Utc::now().timestamp() - BLOB_WANT_RETRY_SECS;
+        let mut stmt = conn.prepare(
+            "SELECT hash_sha256, received_bytes FROM blob_downloads
+             WHERE requested_at IS NULL OR requested_at <= ?1 ORDER BY hash_sha256",
+        )?;
+        let wants = stmt
+            .query_map([cutoff], |row| {
+                Ok(BlobWant {
+                    hash_sha256: row.get(0)?,
+                    offset: row.get::<_, i64>(1)? as u64,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(wants)
+    }
+
+    /// Records that wants for `hashes` reached a peer, deferring the next
+    /// request until the retry interval passes without progress.
+    pub fn mark_blob_wants_sent(&self, hashes: &[String]) -> Result<()> {
+        let now = chrono::Utc::now().timestamp();
+        for hash in hashes {
+            self.storage.connection().execute(
+                "UPDATE blob_downloads SET requested_at = ?1 WHERE hash_sha256 = ?2",
+                rusqlite::params![now, hash],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Handles the blob section of a bundle from `sender_device_id`: stores
+    /// its requests and writes the chunks it answered ours with.
+    pub(crate) fn ingest_blob_traffic(
+        &mut self,
+        sender_device_id: &str,
+        wants: &[BlobWant],
+        chunks: &[BlobChunk],
+    ) -> Result<()> {
+        for want in wants {
+            // Only requests we can answer are kept; the read scope is checked
+            // again when chunks are generated.
+            let have: bool = self.storage.connection().query_row(
+                "SELECT EXISTS(SELECT 1 FROM attachments WHERE hash_sha256 = ?1)",
+                [&want.hash_sha256],
+                |row| row.get(0),
+            )?;
+            if have {
+                self.storage.connection().execute(
+                    "INSERT OR REPLACE INTO blob_requests (peer_device_id, hash_sha256, sent_bytes)
+                     VALUES (?1, ?2, ?3)",
+                    rusqlite::params![sender_device_id, want.hash_sha256, want.offset as i64],
+                )?;
+            }
+        }
+        for chunk in chunks {
+            self.receive_blob_chunk(chunk)?;
+        }
+        Ok(())
+    }
+
+    fn receive_blob_chunk(&mut self, chunk: &BlobChunk) -> Result<()> {
+        let progress: Option<(i64, i64)> = self
+            .storage
+            .connection()
+            .query_row(
+                "SELECT size_bytes, received_bytes FROM blob_downloads WHERE hash_sha256 = ?1",
+                [&chunk.hash_sha256],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let Some((size, received)) = progress else {
+            return Ok(()); // not (or no longer) wanted
+        };
+        let (size, received) = (size as u64, received as u64);
+        let end = chunk.offset + chunk.data.len() as u64;
+        if chunk.total_size != size || end > size {
+            log::warn!(target: "krillnotes::sync",
+                "ignoring chunk of {} with inconsistent size", chunk.hash_sha256);
+            return Ok(());
+        }
+        // Only a chunk continuing what we have is useful; a gap is closed by the
+        // next want, which restarts the sender at `received`.
+        if chunk.offset > received || end <= received {
+            return Ok(());
+        }
+        let fresh = &chunk.data[(received - chunk.offset) as usize..];
+
+        let dir = self.incoming_dir(&chunk.hash_sha256);
+        std::fs::create_dir_all(&dir)?;
+        let (ciphertext, salt) = encrypt_attachment(fresh, self.attachment_key.as_ref())?;
+        let mut stored = salt.to_vec();
+        stored.extend_from_slice(&ciphertext);
+        let part = dir.join(format!("{received:020}.part"));
+        std::fs::write(&part, &stored)?;
+        std::fs::rename(&part, dir.join(format!("{received:020}.chunk")))?;
+
+        self.storage.connection().execute(
+            "UPDATE blob_downloads SET received_bytes = ?1, requested_at = ?2 WHERE hash_sha256 = ?3",
+            rusqlite::params![end as i64, chrono::Utc::now().timestamp(), chunk.hash_sha256],
+        )?;
+        if end == size {
+            self.finish_blob_download(&chunk.hash_sha256)?;
+        }
+        Ok(())
+    }
+
+    /// Assembles a completed download, verifies its hash and stores it for
+    /// every pending attachment with that content.
+    fn finish_blob_download(&mut self, hash: &str) -> Result<()> {
+        type Waiting = (String, String, String, Option<String>, i64);
+        let waiting: Vec<Waiting> = {
+            let mut stmt = self.storage.connection().prepare(
+                "SELECT p.id, p.note_id, p.filename, p.mime_type, p.size_bytes
+                 FROM pending_attachments p JOIN notes n ON n.id = p.note_id
+                 WHERE p.hash_sha256 = ?1 ORDER BY p.created_at ASC",
+            )?;
+            let rows = stmt
+                .query_map([hash], |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                    ))
+                })?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            rows
+        };
+
+        let dir = self.incoming_dir(hash);
+        if let Some((first_id, ..)) = waiting.first() {
+            let mut files: Vec<std::path::PathBuf> = std::fs::read_dir(&dir)?
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.extension().is_some_and(|ext| ext == "chunk"))
+                .collect();
+            files.sort();
+            let mut reader = IncomingChunks {
+                files: files.into_iter(),
+                current: Cursor::new(Vec::new()),
+                key: self.attachment_key.as_ref(),
+            };
+            let streamed = self.write_attachment_file(first_id, &mut reader, None)?;
+            if streamed.hash_sha256 != hash {
+                log::warn!(target: "krillnotes::sync",
+                    "downloaded content does not match hash {hash}, starting over");
+                let _ = std::fs::remove_file(
+                    self.workspace_root
+                        .join("attachments")
+                        .join(format!("{first_id}.enc")),
+                );
+                let _ = std::fs::remove_dir_all(&dir);
+                self.storage.connection().execute(
+                    "UPDATE blob_downloads SET received_bytes = 0, requested_at = NULL
+                     WHERE hash_sha256 = ?1",
+                    [hash],
+                )?;
+                return Ok(());
+            }
+            for (i, (id, note_id, filename, mime_type, size_bytes)) in waiting.iter().enumerate() {
+                let salt = if i == 0 {
+                    streamed.salt.to_vec()
+                } else {
+                    match self.link_existing_content(id, hash)? {
+                        Some(salt) => salt,
+                        None => continue,
+                    }
+                };
+                self.insert_attachment_row(
+                    id,
+                    note_id,
+                    filename,
+                    mime_type.as_deref(),
+                    *size_bytes,
+                    hash,
+                    &salt,
+                )?;
+            }
+            log::info!(target: "krillnotes::sync",
+                "downloaded content {hash} for {} attachment(s)", waiting.len());
+        }
+
+        let conn = self.storage.connection();
+        conn.execute(
+            "DELETE FROM pending_attachments WHERE hash_sha256 = ?1",
+            [hash],
+        )?;
+        conn.execute("DELETE FROM blob_downloads WHERE hash_sha256 = ?1", [hash])?;
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    /// Chunks answering `peer_device_id`'s requests, at most
+    /// [`MAX_BLOB_BYTES_PER_BUNDLE`] in total. Content of notes outside the
+    /// peer's read scope is never sent.
+    pub(crate) fn blob_chunks_for_peer(
+        &self,
+        peer_device_id: &str,
+        scope: Option<&ReadScope>,
+    ) -> Result<Vec<BlobChunk>> {
+        let conn = self.storage.connection();
+        let requests: Vec<(String, i64)> = {
+            let mut stmt = conn.prepare(
+                "SELECT hash_sha256, sent_bytes FROM blob_requests
+                 WHERE peer_device_id = ?1 ORDER BY rowid",
+            )?;
+            let rows = stmt
+                .query_map([peer_device_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            rows
+        };
+
+        let mut chunks = Vec::new();
+        let mut budget = MAX_BLOB_BYTES_PER_BUNDLE;
+        for (hash, sent) in requests {
+            if budget == 0 {
+                break;
+            }
+            let sources: Vec<(String, String, Vec<u8>, i64)> = {
+                let mut stmt = conn.prepare(
+                    "SELECT id, note_id, salt, size_bytes FROM attachments WHERE hash_sha256 = ?1",
+                )?;
+                let rows = stmt
+                    .query_map([&hash], |row| {
+                        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+                    })?
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+                rows
+            };
+            let source = sources.into_iter().find(|(id, note_id, ..)| {
+                scope.is_none_or(|s| s.full.contains(note_id))
+                    && self
+                        .workspace_root
+                        .join("attachments")
+                        .join(format!("{id}.enc"))
+                        .exists()
+            });
+            let Some((id, _, salt, size)) = source else {
+                conn.execute(
+                    "DELETE FROM blob_requests WHERE peer_device_id = ?1 AND hash_sha256 = ?2",
+                    rusqlite::params![peer_device_id, hash],
+                )?;
+                continue;
+            };
+
+            let size = size as u64;
+            let mut offset = sent as u64;
+            let path = self
+                .workspace_root
+                .join("attachments")
+                .join(format!("{id}.enc"));
+            let mut file = std::io::BufReader::new(std::fs::File::open(&path)?);
+            while offset < size && budget > 0 {
+                let len = BLOB_CHUNK_SIZE.min(budget).min((size - offset) as usize);
+                let data = decrypt_attachment_range(
+                    &mut file,
+                    self.attachment_key.as_ref(),
+                    &salt,
+                    offset,
+                    len,
+                )?;
+                if data.is_empty() {
+                    break;
+                }
+                budget -= data.len();
+                let next = offset + data.len() as u64;
+                chunks.push(BlobChunk {
+                    hash_sha256: hash.clone(),
+                    offset,
+                    total_size: size,
+                    data,
+                });
+                offset = next;
+            }
+        }
+        Ok(chunks)
+    }
+
+    /// Advances `peer_device_id`'s requests past chunks it has been sent;
+    /// each entry gives the bytes of a hash delivered so far. Finished
+    /// requests are removed.
+    pub fn record_blob_progress(&self, peer_device_id: &str, sent: &[BlobWant]) -> Result<()> {
+        let conn = self.storage.connection();
+        for progress in sent {
+            let size: Option<i64> = conn
+                .query_row(
+                    "SELECT size_bytes FROM attachments WHERE hash_sha256 = ?1 LIMIT 1",
+                    [&progress.hash_sha256],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            if size.is_none_or(|size| progress.offset >= size as u64) {
+                conn.execute(
+                    "DELETE FROM blob_requests WHERE peer_device_id = ?1 AND hash_sha256 = ?2",
+                    rusqlite::params![peer_device_id, progress.hash_sha256],
+                )?;
+            } else {
+                conn.execute(
+                    "UPDATE blob_requests SET sent_bytes = ?1
+                     WHERE peer_device_id = ?2 AND hash_sha256 = ?3",
+                    rusqlite::params![progress.offset as i64, peer_device_id, progress.hash_sha256],
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
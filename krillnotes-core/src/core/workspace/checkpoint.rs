@@ -0,0 +1,479 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Operation log compaction into signed checkpoints.
+//!
+//! [`Workspace::compact_operation_log`] removes operations older than the
+//! retention period and keeps a [`Checkpoint`] of the resulting state in the
+//! `checkpoints` table (only the latest one). Membership and permission
+//! operations are never compacted: the permission gate and peer bookkeeping
+//! replay them. [`Workspace::operations_since_with_checkpoint`] serves a peer
+//! whose watermark is no longer in the log with the checkpoint and the
+//! operations after its frontier; [`Workspace::apply_checkpoint`] merges a
+//! received checkpoint's state.
+//!
+//! A checkpoint replaces notes, deletions and scripts wholesale, so only the
+//! Root Owner compacts and peers accept checkpoints from the Root Owner only
+//! (see [`Workspace::accepts_checkpoint_from`]). Other peers keep their full
+//! log.
+
+use super::*;
+use crate::core::checkpoint::{hash_state, ChainTail, Checkpoint, CheckpointState};
+
+/// Default `checkpoint_retention_days`.
+pub const DEFAULT_CHECKPOINT_RETENTION_DAYS: u32 = 90;
+
+/// Minimum seconds between two runs of [`Workspace::compact_if_due`].
+const COMPACTION_INTERVAL_SECS: i64 = 24 * 60 * 60;
+
+/// Operation types that stay in the log through compaction.
+const RETAINED_OPERATION_TYPES: &[&str] = &[
+    "SetPermission",
+    "RevokePermission",
+    "JoinWorkspace",
+    "RemovePeer",
+    "TransferRootOwnership",
+    "RegisterDevice",
+    "RotateIdentityKey",
+    "RevokeDevice",
+    "SetRootRecovery",
+];
+
+/// What to send a peer for a given watermark.
+#[derive(Debug)]
+pub struct OperationsSince {
+    /// Checkpoint and serialized [`CheckpointState`], when the watermark
+    /// predates the compacted range.
+    pub checkpoint: Option<(Checkpoint, Vec<u8>)>,
+    /// Operations to send, with their `verified_by` column.
+    pub operations: Vec<(Operation, String)>,
+}
+
+/// Changes made by [`Workspace::apply_checkpoint`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointMerge {
+    pub notes_added: usize,
+    pub notes_updated: usize,
+    pub notes_deleted: usize,
+}
+
+impl Workspace {
+    /// Days of operation history kept by compaction; `0` disables it.
+    pub fn checkpoint_retention_days(&self) -> Result<u32> {
+        let value: Option<String> = self
+            .storage
+            .connection()
+            .query_row(
+                "SELECT value FROM workspace_meta WHERE key = 'checkpoint_retention_days'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(value
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_CHECKPOINT_RETENTION_DAYS))
+    }
+
+    /// Sets the retention period used by [`Self::compact_if_due`].
+    pub fn set_checkpoint_retention_days(&mut self, days: u32) -> Result<()> {
+        self.storage.connection().execute(
+            "INSERT OR REPLACE INTO workspace_meta (key, value) VALUES ('checkpoint_retention_days', ?)",
+            [days.to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// Compacts the log with the configured retention if compaction is
+    /// enabled, this identity is the Root Owner and compaction has not run
+    /// in the last day. Meant to be called periodically, e.g. from the
+    /// background sync cycle.
+    pub fn compact_if_due(&mut self) -> Result<Option<Checkpoint>> {
+        let days = self.checkpoint_retention_days()?;
+        if days == 0 || !self.is_owner() {
+            return Ok(None);
+        }
+        let last_run: Option<String> = self
+            .storage
+            .connection()
+            .query_row(
+                "SELECT value FROM workspace_meta WHERE key = 'last_compaction_at'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let now = UnixSecs::now().as_i64();
+        if let Some(last) = last_run.and_then(|v| v.parse::<i64>().ok()) {
+            if now - last < COMPACTION_INTERVAL_SECS {
+                return Ok(None);
+            }
+        }
+        let checkpoint = self.compact_operation_log(days)?;
+        self.storage.connection().execute(
+            "INSERT OR REPLACE INTO workspace_meta (key, value) VALUES ('last_compaction_at', ?)",
+            [now.to_string()],
+        )?;
+        Ok(checkpoint)
+    }
+
+    /// Removes operations older than `retention_days` (other than membership
+    /// and permission operations) and records a signed checkpoint of the
+    /// current state in their place.
+    ///
+    /// Returns `None` if no operation was old enough to compact.
+    pub fn compact_operation_log(&mut self, retention_days: u32) -> Result<Option<Checkpoint>> {
+        let now_ms = UnixSecs::now().as_i64().max(0) as u64 * 1000;
+        let cutoff_ms = now_ms.saturating_sub(u64::from(retention_days) * 86_400_000);
+
+        let placeholders = vec!["?"; RETAINED_OPERATION_TYPES.len()].join(", ");
+        let mut params: Vec<rusqlite::types::Value> = vec![(cutoff_ms as i64).into()];
+        params.extend(
+            RETAINED_OPERATION_TYPES
+                .iter()
+                .map(|t| rusqlite::types::Value::from(t.to_string())),
+        );
+        let compactable = format!(
+            "FROM operations WHERE timestamp_wall_ms < ? AND operation_type NOT IN ({placeholders})"
+        );
+
+        let conn = self.storage.connection();
+        let removed: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) {compactable}"),
+            rusqlite::params_from_iter(params.iter()),
+            |row| row.get(0),
+        )?;
+        if removed == 0 {
+            return Ok(None);
+        }
+
+        // The state covers the whole log, so the frontier is its newest op.
+        let (frontier_operation_id, wall_ms, counter, node_id): (String, i64, i64, i64) = conn
+            .query_row(
+                "SELECT operation_id, timestamp_wall_ms, timestamp_counter, timestamp_node_id \
+                 FROM operations \
+                 ORDER BY timestamp_wall_ms DESC, timestamp_counter DESC, timestamp_node_id DESC \
+                 LIMIT 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )?;
+
+        let previous = self.latest_checkpoint_with_state()?;
+        let mut deleted_note_ids: Vec<String> = match &previous {
+            Some((_, state)) => serde_json::from_slice::<CheckpointState>(state)?.deleted_note_ids,
+            None => Vec::new(),
+        };
+        // Every deletion up to the frontier, not just the compacted ones: a
+        // peer served this checkpoint never receives the retained operations
+        // before the frontier, and `apply_checkpoint` keeps local notes that
+        // are merely absent from the snapshot.
+        let deletions: Vec<String> = conn
+            .prepare("SELECT operation_data FROM operations WHERE operation_type = 'DeleteNote'")?
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        for data in &deletions {
+            if let Ok(Operation::DeleteNote { note_id, .. }) = serde_json::from_str(data) {
+                if !deleted_note_ids.contains(&note_id) {
+                    deleted_note_ids.push(note_id);
+                }
+            }
+        }
+
+        // Every chain link is at or before the frontier; the tails are the
+        // links nothing continues yet, including tails of checkpoints this
+        // workspace applied itself.
+        let chain_tails: Vec<ChainTail> = conn
+            .prepare(
+                "WITH links AS ( \
+                     SELECT author_key, device_id, hash, prev_hash FROM op_chain \
+                     UNION SELECT author_key, device_id, hash, NULL FROM checkpoint_chain_tails \
+                 ) \
+                 SELECT DISTINCT l.author_key, l.device_id, l.hash FROM links l \
+                 WHERE NOT EXISTS ( \
+                     SELECT 1 FROM links n \
+                     WHERE n.author_key = l.author_key AND n.device_id = l.device_id \
+                       AND n.prev_hash = l.hash) \
+                 ORDER BY l.author_key, l.device_id, l.hash",
+            )?
+            .query_map([], |row| {
+                Ok(ChainTail {
+                    author_key: row.get(0)?,
+                    device_id: row.get(1)?,
+                    hash: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let state = serde_json::to_vec(&CheckpointState {
+            snapshot: self.snapshot(None)?,
+            deleted_note_ids,
+            chain_tails,
+        })?;
+        let mut checkpoint = Checkpoint {
+            checkpoint_id: Uuid::new_v4().to_string(),
+            workspace_id: self.workspace_id.clone(),
+            frontier: HlcTimestamp {
+                wall_ms: wall_ms as u64,
+                counter: counter as u32,
+                node_id: node_id as u32,
+            },
+            frontier_operation_id,
+            compacted_before_ms: cutoff_ms,
+            compacted_count: previous.map(|(cp, _)| cp.compacted_count).unwrap_or(0)
+                + removed as u64,
+            state_hash: hash_state(&state),
+            created_at: UnixSecs::now().as_i64(),
+            author_key: String::new(),
+            signature: String::new(),
+        };
+        checkpoint.sign(&self.signing_key);
+
+        let tx = self.storage.connection_mut().transaction()?;
+        tx.execute(
+            &format!("DELETE {compactable}"),
+            rusqlite::params_from_iter(params.iter()),
+        )?;
+        tx.execute("DELETE FROM checkpoints", [])?;
+        tx.execute(
+            "INSERT INTO checkpoints (checkpoint_id, checkpoint_json, state, created_at) \
+             VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![
+                checkpoint.checkpoint_id,
+                serde_json::to_string(&checkpoint)?,
+                state,
+                checkpoint.created_at,
+            ],
+        )?;
+        tx.commit()?;
+
+        log::info!(target: "krillnotes::sync",
+            "compacted {} operations older than {} into checkpoint {}",
+            removed, cutoff_ms, checkpoint.checkpoint_id);
+        Ok(Some(checkpoint))
+    }
+
+    /// The latest checkpoint, if the log has been compacted.
+    pub fn latest_checkpoint(&self) -> Result<Option<Checkpoint>> {
+        Ok(self.latest_checkpoint_with_state()?.map(|(cp, _)| cp))
+    }
+
+    /// The latest checkpoint with its serialized [`CheckpointState`].
+    pub fn latest_checkpoint_with_state(&self) -> Result<Option<(Checkpoint, Vec<u8>)>> {
+        let row: Option<(String, Vec<u8>)> = self
+            .storage
+            .connection()
+            .query_row(
+                "SELECT checkpoint_json, state FROM checkpoints ORDER BY created_at DESC LIMIT 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        row.map(|(json, state)| Ok((serde_json::from_str(&json)?, state)))
+            .transpose()
+    }
+
+    /// True if `operation_id` is the frontier of the latest checkpoint: a
+    /// valid watermark even once that operation has been compacted away.
+    pub fn is_checkpoint_frontier(&self, operation_id: &str) -> Result<bool> {
+        Ok(self
+            .latest_checkpoint()?
+            .is_some_and(|cp| cp.frontier_operation_id == operation_id))
+    }
+
+    /// Like [`Self::operations_since_with_verified_by`], but a watermark that
+    /// predates the latest checkpoint (or is unknown, or absent) is answered
+    /// with the checkpoint and the operations after its frontier.
+    pub fn operations_since_with_checkpoint(
+        &self,
+        since_op_id: Option<&str>,
+        exclude_device_id: &str,
+    ) -> Result<OperationsSince> {
+        let Some((checkpoint, state)) = self.latest_checkpoint_with_state()? else {
+            return Ok(OperationsSince {
+                checkpoint: None,
+                operations: self
+                    .operations_since_with_verified_by(since_op_id, exclude_device_id)?,
+            });
+        };
+        if since_op_id == Some(checkpoint.frontier_operation_id.as_str()) {
+            return Ok(OperationsSince {
+                checkpoint: None,
+                operations: self.operations_after_with_verified_by(
+                    Some(checkpoint.frontier),
+                    exclude_device_id,
+                )?,
+            });
+        }
+        let watermark = match since_op_id {
+            Some(op_id) => self.operation_timestamp(op_id)?,
+            None => None,
+        };
+        match watermark {
+            Some(ts) if ts.wall_ms >= checkpoint.compacted_before_ms => Ok(OperationsSince {
+                checkpoint: None,
+                operations: self.operations_after_with_verified_by(Some(ts), exclude_device_id)?,
+            }),
+            _ => Ok(OperationsSince {
+                operations: self.operations_after_with_verified_by(
+                    Some(checkpoint.frontier),
+                    exclude_device_id,
+                )?,
+                checkpoint: Some((checkpoint, state)),
+            }),
+        }
+    }
+
+    /// True if a checkpoint sent from `device_id` may be applied here: it is
+    /// signed by the current Root Owner, whose key had not been rotated out
+    /// and who had not revoked `device_id` when the checkpoint was created.
+    ///
+    /// Does not check the signature or the state hash.
+    pub fn accepts_checkpoint_from(
+        &self,
+        checkpoint: &Checkpoint,
+        device_id: &str,
+    ) -> Result<bool> {
+        if checkpoint.author_key != self.owner_pubkey {
+            return Ok(false);
+        }
+        let created_at_ms = checkpoint.created_at.max(0) as u64 * 1000;
+        if let Some((_, rotated_at_ms)) = self.successor_of(&checkpoint.author_key)? {
+            if created_at_ms > rotated_at_ms {
+                return Ok(false);
+            }
+        }
+        Ok(self
+            .device_revoked_at(&checkpoint.author_key, device_id)?
+            .is_none_or(|revoked_at| created_at_ms <= revoked_at.wall_ms))
+    }
+
+    /// Merges the state of a verified checkpoint: adds missing notes, tags
+    /// and scripts, updates notes and scripts the checkpoint has newer
+    /// versions of, removes notes deleted at or before its frontier, queues
+    /// attachment downloads and replays permission operations.
+    ///
+    /// Local notes absent from the checkpoint are kept; the caller is
+    /// responsible for verifying the checkpoint against `state` and checking
+    /// [`Self::accepts_checkpoint_from`] first.
+    pub fn apply_checkpoint(
+        &mut self,
+        checkpoint: &Checkpoint,
+        state: &[u8],
+    ) -> Result<CheckpointMerge> {
+        let state: CheckpointState = serde_json::from_slice(state)?;
+        let snapshot = state.snapshot;
+        let mut merge = CheckpointMerge::default();
+
+        self.storage
+            .connection_mut()
+            .execute_batch("PRAGMA defer_foreign_keys = ON;")?;
+        let tx = self.storage.connection_mut().transaction()?;
+        for note in &snapshot.notes {
+            if state.deleted_note_ids.contains(&note.id) {
+                continue;
+            }
+            let local_modified: Option<i64> = tx
+                .query_row(
+                    "SELECT modified_at FROM notes WHERE id = ?1",
+                    [&note.id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            match local_modified {
+                Some(modified) if modified >= note.modified_at.as_i64() => continue,
+                Some(_) => merge.notes_updated += 1,
+                None => merge.notes_added += 1,
+            }
+            tx.execute(
+                "INSERT OR REPLACE INTO notes (id, title, schema, parent_id, position, created_at, modified_at, created_by, modified_by, fields_json, is_expanded, schema_version, is_checked)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                rusqlite::params![
+                    note.id,
+                    note.title,
+                    note.schema,
+                    note.parent_id,
+                    note.position,
+                    note.created_at,
+                    note.modified_at,
+                    note.created_by,
+                    note.modified_by,
+                    serde_json::to_string(&note.fields)?,
+                    note.is_expanded,
+                    note.schema_version,
+                    note.is_checked,
+                ],
+            )?;
+            tx.execute("DELETE FROM note_tags WHERE note_id = ?1", [&note.id])?;
+            for tag in &note.tags {
+                tx.execute(
+                    "INSERT OR IGNORE INTO note_tags (note_id, tag) VALUES (?, ?)",
+                    rusqlite::params![note.id, tag],
+                )?;
+            }
+        }
+        for note_id in &state.deleted_note_ids {
+            merge.notes_deleted += tx.execute("DELETE FROM notes WHERE id = ?1", [note_id])?;
+        }
+        for tail in &state.chain_tails {
+            tx.execute(
+                "INSERT OR IGNORE INTO checkpoint_chain_tails (author_key, device_id, hash) \
+                 VALUES (?1, ?2, ?3)",
+                rusqlite::params![tail.author_key, tail.device_id, tail.hash],
+            )?;
+        }
+        let mut scripts_changed = 0;
+        for script in &snapshot.user_scripts {
+            scripts_changed += tx.execute(
+                "INSERT INTO user_scripts (id, name, description, source_code, load_order, enabled, created_at, modified_at, category)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                 ON CONFLICT(id) DO UPDATE SET
+                     name = excluded.name, description = excluded.description,
+                     source_code = excluded.source_code, load_order = excluded.load_order,
+                     enabled = excluded.enabled, modified_at = excluded.modified_at,
+                     category = excluded.category
+                 WHERE excluded.modified_at > user_scripts.modified_at",
+                rusqlite::params![
+                    script.id,
+                    script.name,
+                    script.description,
+                    script.source_code,
+                    script.load_order,
+                    script.enabled,
+                    script.created_at,
+                    script.modified_at,
+                    script.category,
+                ],
+            )?;
+        }
+        tx.commit()?;
+
+        if scripts_changed > 0 {
+            self.reload_scripts()?;
+        }
+        for meta in &snapshot.attachments {
+            let note_exists: bool = self.storage.connection().query_row(
+                "SELECT EXISTS(SELECT 1 FROM notes WHERE id = ?1)",
+                [&meta.note_id],
+                |row| row.get(0),
+            )?;
+            if note_exists {
+                self.receive_attachment_metadata(
+                    &meta.id,
+                    &meta.note_id,
+                    &meta.filename,
+                    meta.mime_type.as_deref(),
+                    meta.size_bytes,
+                    &meta.hash_sha256,
+                )?;
+            }
+        }
+        self.replay_snapshot_permission_ops(&snapshot.permission_ops)?;
+
+        log::info!(target: "krillnotes::sync",
+            "applied checkpoint {}: {} notes added, {} updated, {} deleted",
+            checkpoint.checkpoint_id, merge.notes_added, merge.notes_updated, merge.notes_deleted);
+        Ok(merge)
+    }
+}
@@ -0,0 +1,228 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Device revocation — `RevokeDevice` operations, the `revoked_devices`
+//! table and the "My Devices" listing.
+
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+
+use super::sync::OpRejection;
+use crate::core::error::{KrillnotesError, Result};
+use crate::core::hlc::HlcTimestamp;
+use crate::core::operation::Operation;
+use crate::core::succession::SuccessionRecord;
+use crate::core::workspace::Workspace;
+
+/// Keys of one principal: `?1` and every key it succeeded, directly or not.
+const KEY_CHAIN_CTE: &str = "WITH RECURSIVE chain(k) AS ( \
+         SELECT ?1 \
+         UNION SELECT s.old_key FROM key_successions s JOIN chain ON s.new_key = chain.k \
+     )";
+
+/// One of the current identity's devices, as seen in the operation log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceInfo {
+    /// Composite device ID carried by the device's operations.
+    pub device_id: String,
+    /// Human-readable name from the device's `RegisterDevice` operation.
+    pub device_name: String,
+    /// When the device registered in this workspace (Unix ms).
+    pub registered_at_ms: i64,
+    /// Wall time of the newest logged operation from the device (Unix ms).
+    pub last_seen_ms: i64,
+    /// When the device was revoked (Unix ms), if it was.
+    pub revoked_at_ms: Option<i64>,
+    /// Whether this is the device the workspace is open on.
+    pub is_current: bool,
+}
+
+impl Workspace {
+    /// Revokes one of this identity's other devices with a signed
+    /// `RevokeDevice` operation. Peers reject operations from the device
+    /// stamped after the revocation.
+    ///
+    /// This does not lock out a compromised device: all devices of an
+    /// identity sign with the same key, and the device ID and HLC timestamp
+    /// an operation is checked against are chosen by its signer. A device
+    /// that may be in someone else's hands must also have the identity key
+    /// rotated away from it, see [`Self::revoke_device_and_rotate_key`].
+    ///
+    /// Returns `Ok(false)` if the device is already revoked.
+    pub fn revoke_device(&mut self, device_id: &str) -> Result<bool> {
+        if device_id == self.device_id {
+            return Err(KrillnotesError::ValidationFailed(
+                "cannot revoke the device this workspace is open on".into(),
+            ));
+        }
+        let device = self
+            .list_devices()?
+            .into_iter()
+            .find(|d| d.device_id == device_id)
+            .ok_or_else(|| {
+                KrillnotesError::ValidationFailed(format!(
+                    "device {device_id} is not registered by this identity"
+                ))
+            })?;
+        if device.revoked_at_ms.is_some() {
+            return Ok(false);
+        }
+
+        let ts = self.hlc.now();
+        let mut op = Operation::RevokeDevice {
+            operation_id: uuid::Uuid::new_v4().to_string(),
+            timestamp: ts,
+            device_id: self.device_id.clone(),
+            revoked_device_id: device_id.to_string(),
+            revoked_by: String::new(),
+            prev_hash: None,
+            signature: String::new(),
+        };
+        Self::sign_op_with(&self.signing_key, self.storage.connection(), &mut op)?;
+        {
+            let tx = self.storage.connection_mut().transaction()?;
+            Self::save_hlc(&ts, &tx)?;
+            Self::log_op(&self.operation_log, &tx, &op)?;
+            tx.commit()?;
+        }
+        self.apply_op_to_working_tables(&op, &[])?;
+
+        log::info!(target: "krillnotes::sync", "revoked device {device_id}");
+        Ok(true)
+    }
+
+    /// Revokes `device_id` and then rotates this workspace to `new_key`, so
+    /// that the revoked device, which still holds the old key, can no longer
+    /// sign operations peers accept as this identity's.
+    ///
+    /// `record` is as for [`Self::rotate_identity_key`]. The revocation is
+    /// signed with the old key, before the rotation. Returns `Ok(false)` if
+    /// the device was already revoked; the key is rotated either way.
+    pub fn revoke_device_and_rotate_key(
+        &mut self,
+        device_id: &str,
+        new_key: ed25519_dalek::SigningKey,
+        record: &SuccessionRecord,
+    ) -> Result<bool> {
+        if !record.verify() || record.old_key != self.current_identity_pubkey {
+            return Err(KrillnotesError::InvalidSuccession(
+                "record does not retire this workspace's identity key".into(),
+            ));
+        }
+        let revoked = self.revoke_device(device_id)?;
+        self.rotate_identity_key(new_key, record)?;
+        Ok(revoked)
+    }
+
+    /// Lists the devices registered by the current identity (including
+    /// under keys it rotated out of), oldest registration first.
+    pub fn list_devices(&self) -> Result<Vec<DeviceInfo>> {
+        let conn = self.storage.connection();
+        let mut stmt = conn.prepare(&format!(
+            "{KEY_CHAIN_CTE} \
+             SELECT o.device_id, json_extract(o.operation_data, '$.device_name'), \
+                    MIN(o.timestamp_wall_ms), \
+                    (SELECT MAX(a.timestamp_wall_ms) FROM operations a WHERE a.device_id = o.device_id), \
+                    (SELECT MIN(r.revoked_at_wall_ms) FROM revoked_devices r \
+                     WHERE r.device_id = o.device_id AND r.revoked_by IN (SELECT k FROM chain)) \
+             FROM operations o \
+             WHERE o.operation_type = 'RegisterDevice' \
+               AND json_extract(o.operation_data, '$.identity_public_key') IN (SELECT k FROM chain) \
+             GROUP BY o.device_id \
+             ORDER BY MIN(o.timestamp_wall_ms) ASC"
+        ))?;
+        let devices = stmt
+            .query_map([&self.current_identity_pubkey], |row| {
+                let device_id: String = row.get(0)?;
+                Ok(DeviceInfo {
+                    is_current: device_id == self.device_id,
+                    device_id,
+                    device_name: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    registered_at_ms: row.get(2)?,
+                    last_seen_ms: row.get(3)?,
+                    revoked_at_ms: row.get(4)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(devices)
+    }
+
+    /// Rejects operations from a device its author revoked, when stamped
+    /// after the revocation. Applies to logged and scope boundary operations.
+    ///
+    /// Both the device ID and the timestamp are declared by the signer, so
+    /// this only stops a revoked device that follows the protocol.
+    pub(super) fn check_device_revocation(&self, op: &Operation) -> Result<Option<OpRejection>> {
+        let author_key = op.author_key();
+        if author_key.is_empty() {
+            return Ok(None);
+        }
+        Ok(self
+            .device_revoked_at(author_key, op.device_id())?
+            .filter(|revoked_at| op.timestamp() > *revoked_at)
+            .map(|_| OpRejection::RevokedDevice))
+    }
+
+    /// When `author_key` (or a key in its succession chain) first revoked
+    /// `device_id`, if it did.
+    pub(super) fn device_revoked_at(
+        &self,
+        author_key: &str,
+        device_id: &str,
+    ) -> Result<Option<HlcTimestamp>> {
+        Ok(self
+            .storage
+            .connection()
+            .query_row(
+                &format!(
+                    "{KEY_CHAIN_CTE} \
+                     SELECT revoked_at_wall_ms, revoked_at_counter, revoked_at_node_id \
+                     FROM revoked_devices \
+                     WHERE device_id = ?2 AND revoked_by IN (SELECT k FROM chain) \
+                     ORDER BY revoked_at_wall_ms, revoked_at_counter, revoked_at_node_id \
+                     LIMIT 1"
+                ),
+                rusqlite::params![author_key, device_id],
+                |row| {
+                    Ok(HlcTimestamp {
+                        wall_ms: row.get::<_, i64>(0)? as u64,
+                        counter: row.get::<_, i64>(1)? as u32,
+                        node_id: row.get::<_, i64>(2)? as u32,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    /// Records the revocation carried by a `RevokeDevice` operation.
+    pub(super) fn record_device_revocation(conn: &Connection, op: &Operation) -> Result<()> {
+        let Operation::RevokeDevice {
+            operation_id,
+            timestamp,
+            revoked_device_id,
+            revoked_by,
+            ..
+        } = op
+        else {
+            return Ok(());
+        };
+        conn.execute(
+            "INSERT OR IGNORE INTO revoked_devices \
+             (device_id, revoked_by, revoked_at_wall_ms, revoked_at_counter, revoked_at_node_id, operation_id) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                revoked_device_id,
+                revoked_by,
+                timestamp.wall_ms as i64,
+                timestamp.counter as i64,
+                timestamp.node_id as i64,
+                operation_id,
+            ],
+        )?;
+        Ok(())
+    }
+}
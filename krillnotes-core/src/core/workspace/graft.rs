@@ -0,0 +1,545 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Grafting an externally supplied set of notes (e.g. from an export archive)
+//! into this workspace as normal signed operations.
+
+use super::*;
+
+/// How note IDs from an external source are mapped when grafting into this workspace.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GraftIdStrategy {
+    /// Every incoming note receives a fresh UUID. Parent links and `NoteLink`
+    /// fields that point inside the incoming set are rewritten accordingly.
+    #[default]
+    Remap,
+    /// Incoming IDs are kept. Notes that already exist are updated in place
+    /// (title, fields, tags); notes that do not exist are created with their
+    /// incoming `created_at` and `modified_at`.
+    Preserve,
+}
+
+/// Summary of a [`Workspace::graft_notes`] call.
+#[derive(Debug, Clone, Default)]
+pub struct GraftOutcome {
+    /// Incoming note ID → ID of the note in this workspace.
+    pub id_map: HashMap<String, String>,
+    /// IDs (in this workspace) of the incoming top-level notes, in insertion order.
+    pub root_ids: Vec<String>,
+    /// Number of notes inserted.
+    pub created: usize,
+    /// Number of existing notes updated in place (`Preserve` only).
+    pub updated: usize,
+}
+
+/// A single row-level change planned by `graft_notes`, applied inside one transaction.
+enum GraftStep {
+    Insert(Note),
+    Update(Note),
+}
+
+/// The validated changes of a graft, not yet written. Each step comes with
+/// its operations, which are timestamped only when the plan is committed.
+struct GraftPlan {
+    outcome: GraftOutcome,
+    steps: Vec<(GraftStep, Vec<Operation>)>,
+}
+
+/// Placeholder timestamp of planned operations.
+const UNSTAMPED: HlcTimestamp = HlcTimestamp {
+    wall_ms: 0,
+    counter: 0,
+    node_id: 0,
+};
+
+impl Workspace {
+    /// Grafts `notes` under `parent_id`, emitting a signed operation for every change
+    /// so that peers receive the result through the normal sync path.
+    ///
+    /// Notes whose `parent_id` is `None` or points outside `notes` become children of
+    /// `parent_id`, appended after its existing children. `attachment_id_map` rewrites
+    /// `File` fields and `data-kn-attach-id` image sentinels in text fields; callers
+    /// that do not import attachments may pass an empty map.
+    ///
+    /// With [`GraftIdStrategy::Preserve`], notes that already exist keep their current
+    /// position and only have their content updated, and new notes keep their
+    /// timestamps (e.g. the dates an importer read from its source), which their
+    /// `CreateNote` operations carry. Otherwise notes are stamped with the
+    /// timestamps of their operations, as peers applying them do.
+    ///
+    /// Grafts are not pushed onto the undo stack.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`KrillnotesError::NoteNotFound`] if `parent_id` does not exist,
+    /// [`KrillnotesError::SchemaNotFound`] if an incoming note uses an unknown schema,
+    /// [`KrillnotesError::InvalidMove`] if the parent cannot hold the top-level
+    /// notes or the incoming parent links form a cycle,
+    /// [`KrillnotesError::ValidationFailed`] if a preserved note exists with a
+    /// different schema, or a permission error if the gate rejects a change.
+    pub fn graft_notes(
+        &mut self,
+        notes: &[Note],
+        parent_id: &str,
+        strategy: GraftIdStrategy,
+        attachment_id_map: &HashMap<String, String>,
+    ) -> Result<GraftOutcome> {
+        let GraftPlan { outcome, mut steps } =
+            self.plan_graft(notes, parent_id, strategy, attachment_id_map)?;
+        if steps.is_empty() {
+            return Ok(outcome);
+        }
+
+        // Stamp the operations, and give each row the dates peers derive
+        // from them when applying the same operations.
+        let mut last_ts = None;
+        for (step, ops) in &mut steps {
+            let (GraftStep::Insert(note) | GraftStep::Update(note)) = step;
+            for op in ops.iter_mut() {
+                let ts = self.advance_hlc();
+                op.set_timestamp(ts);
+                last_ts = Some(ts);
+                match op {
+                    Operation::CreateNote {
+                        created_at,
+                        modified_at,
+                        ..
+                    } => {
+                        note.created_at = created_at.unwrap_or(ts.to_unix_secs());
+                        note.modified_at = modified_at.unwrap_or(ts.to_unix_secs());
+                    }
+                    Operation::UpdateNote { .. }
+                    | Operation::UpdateField { .. }
+                    | Operation::SetChecked { .. } => note.modified_at = ts.to_unix_secs(),
+                    _ => {}
+                }
+            }
+        }
+
+        let signing_key = self.signing_key.clone();
+        let tx = self.storage.connection_mut().transaction()?;
+        for (step, _) in &steps {
+            match step {
+                GraftStep::Insert(note) => {
+                    tx.execute(
+                        "INSERT INTO notes (id, title, schema, parent_id, position, created_at, modified_at, created_by, modified_by, fields_json, is_expanded, schema_version, is_checked)
+                         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                        rusqlite::params![
+                            note.id,
+                            note.title,
+                            note.schema,
+                            note.parent_id,
+                            note.position,
+                            note.created_at,
+                            note.modified_at,
+                            note.created_by,
+                            note.modified_by,
+                            serde_json::to_string(&note.fields)?,
+                            note.is_expanded,
+                            note.schema_version,
+                            note.is_checked,
+                        ],
+                    )?;
+                }
+                GraftStep::Update(note) => {
+                    tx.execute(
+                        "UPDATE notes SET title = ?, fields_json = ?, modified_at = ?, modified_by = ? WHERE id = ?",
+                        rusqlite::params![
+                            note.title,
+                            serde_json::to_string(&note.fields)?,
+                            note.modified_at,
+                            note.modified_by,
+                            note.id,
+                        ],
+                    )?;
+                    tx.execute("DELETE FROM note_tags WHERE note_id = ?", [&note.id])?;
+                }
+            }
+            let note = match step {
+                GraftStep::Insert(n) | GraftStep::Update(n) => n,
+            };
+            for tag in &note.tags {
+                tx.execute(
+                    "INSERT OR IGNORE INTO note_tags (note_id, tag) VALUES (?, ?)",
+                    rusqlite::params![note.id, tag],
+                )?;
+            }
+            sync_note_links(&tx, &note.id, &note.fields)?;
+        }
+        for mut op in steps.into_iter().flat_map(|(_, ops)| ops) {
+            Self::sign_op_with(&signing_key, &tx, &mut op)?;
+            Self::log_op(&self.operation_log, &tx, &op)?;
+        }
+        if let Some(ts) = last_ts {
+            Self::save_hlc(&ts, &tx)?;
+        }
+        Self::purge_ops_if_needed(&self.operation_log, &tx)?;
+        tx.commit()?;
+
+        Ok(outcome)
+    }
+
+    /// Checks that [`Self::graft_notes`] would accept `notes` once `scripts`
+    /// are applied, without writing anything.
+    ///
+    /// Each `(source, category)` in `scripts` is compiled into the script
+    /// registry as creating or updating it would; the registry is reloaded
+    /// from the database afterwards. The clock is not advanced.
+    pub(crate) fn check_graft(
+        &mut self,
+        scripts: &[(&str, &str)],
+        notes: &[Note],
+        parent_id: &str,
+        strategy: GraftIdStrategy,
+    ) -> Result<()> {
+        let result = self
+            .load_pending_scripts(scripts)
+            .and_then(|()| self.plan_graft(notes, parent_id, strategy, &HashMap::new()));
+        self.reload_scripts()?;
+        result.map(|_| ())
+    }
+
+    fn load_pending_scripts(&mut self, scripts: &[(&str, &str)]) -> Result<()> {
+        for (source, category) in scripts {
+            let fm = user_script::parse_front_matter(source);
+            if fm.name.is_empty() {
+                return Err(KrillnotesError::ValidationFailed(
+                    "Script must include a '// @name:' front matter line".to_string(),
+                ));
+            }
+            self.script_registry
+                .set_loading_category(Some(category.to_string()));
+            self.script_registry.load_script(source, &fm.name)?;
+        }
+        self.script_registry.resolve_bindings();
+        Ok(())
+    }
+
+    /// Validates and authorises a graft and plans its row changes and
+    /// operations. Nothing is written and the operations are not yet
+    /// timestamped.
+    fn plan_graft(
+        &self,
+        notes: &[Note],
+        parent_id: &str,
+        strategy: GraftIdStrategy,
+        attachment_id_map: &HashMap<String, String>,
+    ) -> Result<GraftPlan> {
+        let parent = self.get_note(parent_id)?;
+
+        let incoming_ids: std::collections::HashSet<&str> =
+            notes.iter().map(|n| n.id.as_str()).collect();
+        let is_top = |n: &Note| {
+            n.parent_id
+                .as_deref()
+                .is_none_or(|p| !incoming_ids.contains(p))
+        };
+
+        // Order parents before children: BFS from the top-level notes, siblings by position.
+        let mut children: HashMap<&str, Vec<&Note>> = HashMap::new();
+        let mut tops: Vec<&Note> = Vec::new();
+        for note in notes {
+            if is_top(note) {
+                tops.push(note);
+            } else if let Some(pid) = note.parent_id.as_deref() {
+                children.entry(pid).or_default().push(note);
+            }
+        }
+        let by_position = |a: &&Note, b: &&Note| a.position.total_cmp(&b.position);
+        tops.sort_by(by_position);
+        for list in children.values_mut() {
+            list.sort_by(by_position);
+        }
+        let mut ordered: Vec<&Note> = Vec::with_capacity(notes.len());
+        let mut queue: std::collections::VecDeque<&Note> = tops.iter().copied().collect();
+        while let Some(note) = queue.pop_front() {
+            ordered.push(note);
+            if let Some(list) = children.get(note.id.as_str()) {
+                queue.extend(list.iter().copied());
+            }
+        }
+        // Notes not reached from a top-level note have a parent cycle above them.
+        if ordered.len() < notes.len() {
+            let reached: std::collections::HashSet<&str> =
+                ordered.iter().map(|n| n.id.as_str()).collect();
+            let unreached: Vec<&str> = notes
+                .iter()
+                .map(|n| n.id.as_str())
+                .filter(|id| !reached.contains(id))
+                .collect();
+            return Err(KrillnotesError::InvalidMove(format!(
+                "Incoming notes form a parent cycle: {}",
+                unreached.join(", ")
+            )));
+        }
+
+        let mut outcome = GraftOutcome::default();
+        for note in &ordered {
+            let new_id = match strategy {
+                GraftIdStrategy::Remap => Uuid::new_v4().to_string(),
+                GraftIdStrategy::Preserve => note.id.clone(),
+            };
+            outcome.id_map.insert(note.id.clone(), new_id);
+        }
+
+        // Validate schemas and placement of the top-level notes. Nested notes
+        // are placed under incoming notes and checked while planning below.
+        for note in &ordered {
+            self.script_registry.get_schema(&note.schema)?;
+        }
+        for top in &tops {
+            self.check_graft_placement(&parent.schema, &top.schema)?;
+        }
+
+        let mut next_top_position: f64 = self.storage.connection().query_row(
+            "SELECT COALESCE(MAX(position), -1) FROM notes WHERE parent_id = ?",
+            [parent_id],
+            |row| row.get(0),
+        )?;
+        next_top_position += 1.0;
+
+        // Schema of each planned note, and where creating its children is
+        // authorised: the note itself if it already exists, otherwise its
+        // closest existing ancestor, since new notes are not stored yet.
+        let mut planned_schema: HashMap<String, String> = HashMap::new();
+        let mut child_scope: HashMap<String, String> = HashMap::new();
+
+        // Plan the row changes and their operations before opening the transaction.
+        let mut steps: Vec<(GraftStep, Vec<Operation>)> = Vec::with_capacity(ordered.len());
+        for note in &ordered {
+            let new_id = outcome.id_map[&note.id].clone();
+            let mut fields = note.fields.clone();
+            for value in fields.values_mut() {
+                match value {
+                    FieldValue::NoteLink(Some(target)) => {
+                        if let Some(mapped) = outcome.id_map.get(target.as_str()) {
+                            *target = mapped.clone();
+                        } else if self.get_note(target).is_err() {
+                            *value = FieldValue::NoteLink(None);
+                        }
+                    }
+                    FieldValue::File(Some(att_id)) => {
+                        if let Some(mapped) = attachment_id_map.get(att_id.as_str()) {
+                            *att_id = mapped.clone();
+                        }
+                    }
+                    FieldValue::Text(text) => {
+                        for (old, new) in attachment_id_map {
+                            if old != new && text.contains(old.as_str()) {
+                                *text = text.replace(
+                                    &format!(r#"data-kn-attach-id="{old}""#),
+                                    &format!(r#"data-kn-attach-id="{new}""#),
+                                );
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            let mut tags: Vec<String> = note
+                .tags
+                .iter()
+                .map(|t| t.trim().to_lowercase())
+                .filter(|t| !t.is_empty())
+                .collect();
+            tags.sort();
+            tags.dedup();
+
+            let existing = match strategy {
+                GraftIdStrategy::Preserve => self.get_note(&new_id).ok(),
+                GraftIdStrategy::Remap => None,
+            };
+
+            if let Some(existing) = existing {
+                if existing.schema != note.schema {
+                    return Err(KrillnotesError::ValidationFailed(format!(
+                        "Note {new_id} exists with schema '{}' but the incoming note has schema '{}'",
+                        existing.schema, note.schema
+                    )));
+                }
+                planned_schema.insert(new_id.clone(), existing.schema.clone());
+                child_scope.insert(new_id.clone(), new_id.clone());
+                let mut update_ops = Vec::new();
+                if existing.title != note.title {
+                    update_ops.push(Operation::UpdateNote {
+                        operation_id: Uuid::new_v4().to_string(),
+                        timestamp: UNSTAMPED,
+                        device_id: self.device_id.clone(),
+                        note_id: new_id.clone(),
+                        title: note.title.clone(),
+                        modified_by: self.current_identity_pubkey.clone(),
+                        prev_hash: None,
+                        signature: String::new(),
+                    });
+                }
+                for (field, value) in &fields {
+                    if existing.fields.get(field) != Some(value) {
+                        update_ops.push(Operation::UpdateField {
+                            operation_id: Uuid::new_v4().to_string(),
+                            timestamp: UNSTAMPED,
+                            device_id: self.device_id.clone(),
+                            note_id: new_id.clone(),
+                            field: field.clone(),
+                            value: value.clone(),
+                            modified_by: self.current_identity_pubkey.clone(),
+                            prev_hash: None,
+                            signature: String::new(),
+                        });
+                    }
+                }
+                if existing.tags != tags {
+                    update_ops.push(Operation::SetTags {
+                        operation_id: Uuid::new_v4().to_string(),
+                        timestamp: UNSTAMPED,
+                        device_id: self.device_id.clone(),
+                        note_id: new_id.clone(),
+                        tags: tags.clone(),
+                        modified_by: self.current_identity_pubkey.clone(),
+                        prev_hash: None,
+                        signature: String::new(),
+                    });
+                }
+                if update_ops.is_empty() {
+                    continue;
+                }
+                for op in &update_ops {
+                    self.authorize(op)?;
+                }
+                let mut merged_fields = existing.fields.clone();
+                merged_fields.extend(fields);
+                steps.push((
+                    GraftStep::Update(Note {
+                        title: note.title.clone(),
+                        fields: merged_fields,
+                        tags,
+                        modified_by: self.current_identity_pubkey.clone(),
+                        ..existing
+                    }),
+                    update_ops,
+                ));
+                outcome.updated += 1;
+                continue;
+            }
+
+            let is_top = is_top(note);
+            let (new_parent, position, scope) = if is_top {
+                let pos = next_top_position;
+                next_top_position += 1.0;
+                (Some(parent_id.to_string()), pos, parent_id.to_string())
+            } else {
+                let pid = note.parent_id.as_deref().unwrap_or_default();
+                let new_pid = outcome.id_map[pid].clone();
+                self.check_graft_placement(&planned_schema[&new_pid], &note.schema)?;
+                let scope = child_scope[&new_pid].clone();
+                (Some(new_pid), note.position, scope)
+            };
+            planned_schema.insert(new_id.clone(), note.schema.clone());
+            child_scope.insert(new_id.clone(), scope.clone());
+            let preserve_dates = strategy == GraftIdStrategy::Preserve;
+            let create_op = Operation::CreateNote {
+                operation_id: Uuid::new_v4().to_string(),
+                timestamp: UNSTAMPED,
+                device_id: self.device_id.clone(),
+                note_id: new_id.clone(),
+                parent_id: new_parent.clone(),
+                position,
+                schema: note.schema.clone(),
+                title: note.title.clone(),
+                fields: fields.clone(),
+                created_by: self.current_identity_pubkey.clone(),
+                created_at: preserve_dates.then_some(note.created_at),
+                modified_at: preserve_dates.then_some(note.modified_at),
+                original_author: None,
+                prev_hash: None,
+                signature: String::new(),
+            };
+            // A nested note's new parent does not exist yet, so its creation is
+            // authorised as if directly under the closest existing ancestor.
+            if is_top {
+                self.authorize(&create_op)?;
+                outcome.root_ids.push(new_id.clone());
+            } else {
+                let mut auth_op = create_op.clone();
+                if let Operation::CreateNote { parent_id, .. } = &mut auth_op {
+                    *parent_id = Some(scope);
+                }
+                self.authorize(&auth_op)?;
+            }
+            let mut note_ops = vec![create_op];
+            if !tags.is_empty() {
+                note_ops.push(Operation::SetTags {
+                    operation_id: Uuid::new_v4().to_string(),
+                    timestamp: UNSTAMPED,
+                    device_id: self.device_id.clone(),
+                    note_id: new_id.clone(),
+                    tags: tags.clone(),
+                    modified_by: String::new(),
+                    prev_hash: None,
+                    signature: String::new(),
+                });
+            }
+            if note.is_checked {
+                note_ops.push(Operation::SetChecked {
+                    operation_id: Uuid::new_v4().to_string(),
+                    timestamp: UNSTAMPED,
+                    device_id: self.device_id.clone(),
+                    note_id: new_id.clone(),
+                    checked: true,
+                    modified_by: String::new(),
+                    prev_hash: None,
+                    signature: String::new(),
+                });
+            }
+            steps.push((
+                GraftStep::Insert(Note {
+                    id: new_id,
+                    parent_id: new_parent,
+                    position,
+                    created_by: self.current_identity_pubkey.clone(),
+                    modified_by: self.current_identity_pubkey.clone(),
+                    fields,
+                    tags,
+                    ..(*note).clone()
+                }),
+                note_ops,
+            ));
+            outcome.created += 1;
+        }
+
+        Ok(GraftPlan { outcome, steps })
+    }
+
+    /// Checks that a note of schema `child` may be placed under one of schema
+    /// `parent`.
+    fn check_graft_placement(&self, parent: &str, child: &str) -> Result<()> {
+        let parent_schema = self.script_registry.get_schema(parent)?;
+        let schema = self.script_registry.get_schema(child)?;
+        if parent_schema.is_leaf {
+            return Err(KrillnotesError::InvalidMove(format!(
+                "Cannot add children to a leaf note (schema: '{parent}')"
+            )));
+        }
+        if !schema.allowed_parent_schemas.is_empty()
+            && !schema.allowed_parent_schemas.iter().any(|s| s == parent)
+        {
+            return Err(KrillnotesError::InvalidMove(format!(
+                "Note type '{child}' cannot be placed under '{parent}'"
+            )));
+        }
+        if !parent_schema.allowed_children_schemas.is_empty()
+            && !parent_schema
+                .allowed_children_schemas
+                .iter()
+                .any(|s| s == child)
+        {
+            return Err(KrillnotesError::InvalidMove(format!(
+                "Note type '{child}' is not allowed as a child of '{parent}'"
+            )));
+        }
+        Ok(())
+    }
+}
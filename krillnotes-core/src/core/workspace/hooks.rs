@@ -482,9 +482,13 @@ impl Workspace {
                         title: effective_title.to_string(),
                         fields: effective_fields,
                         created_by: String::new(),
+                        created_at: None,
+                        modified_at: None,
+                        original_author: None,
+                        prev_hash: None,
                         signature: String::new(),
                     };
-                    Self::sign_op_with(&signing_key, &mut op);
+                    Self::sign_op_with(&signing_key, &tx_db, &mut op)?;
                     Self::log_op(&self.operation_log, &tx_db, &op)?;
                 } else {
                     // ── UPDATE existing note ─────────────────────────────────────
@@ -513,9 +517,10 @@ impl Workspace {
                         note_id: pending.note_id.clone(),
                         title: effective_title.to_string(),
                         modified_by: String::new(),
+                        prev_hash: None,
                         signature: String::new(),
                     };
-                    Self::sign_op_with(&signing_key, &mut title_op);
+                    Self::sign_op_with(&signing_key, &tx_db, &mut title_op)?;
                     Self::log_op(&self.operation_log, &tx_db, &title_op)?;
 
                     for ((field_key, field_value), field_ts) in
@@ -530,9 +535,10 @@ impl Workspace {
                             field: field_key.clone(),
                             value: field_value.clone(),
                             modified_by: String::new(),
+                            prev_hash: None,
                             signature: String::new(),
                         };
-                        Self::sign_op_with(&signing_key, &mut field_op);
+                        Self::sign_op_with(&signing_key, &tx_db, &mut field_op)?;
                         Self::log_op(&self.operation_log, &tx_db, &field_op)?;
                     }
 
@@ -551,9 +557,10 @@ impl Workspace {
                             note_id: pending.note_id.clone(),
                             checked,
                             modified_by: String::new(),
+                            prev_hash: None,
                             signature: String::new(),
                         };
-                        Self::sign_op_with(&signing_key, &mut checked_op);
+                        Self::sign_op_with(&signing_key, &tx_db, &mut checked_op)?;
                         Self::log_op(&self.operation_log, &tx_db, &checked_op)?;
                     }
                 }
@@ -0,0 +1,213 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Operation log integrity — hash chain continuity of incoming operations
+//! and the signed log audit.
+
+use std::collections::HashMap;
+
+use base64::Engine as _;
+use rusqlite::{Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+
+use crate::core::error::Result;
+use crate::core::integrity::{ChainBreak, ChainBreakKind, IntegrityReport};
+use crate::core::operation::Operation;
+use crate::core::workspace::Workspace;
+
+impl Workspace {
+    /// Audits the operation log: verifies every operation's signature,
+    /// checks that logged operations still match the hash recorded when they
+    /// arrived, and looks for gaps and forks in every author's hash chain,
+    /// including chain links of operations purged since. The report is
+    /// signed by the current identity.
+    pub fn audit_log_integrity(&self) -> Result<IntegrityReport> {
+        let conn = self.storage.connection();
+        let recorded: HashMap<String, String> = conn
+            .prepare("SELECT operation_id, hash FROM op_chain")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<_, _>>()?;
+
+        let mut stmt = conn.prepare(
+            "SELECT operation_id, operation_data FROM operations \
+             ORDER BY timestamp_wall_ms, timestamp_counter, timestamp_node_id, operation_id",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let mut digest = Sha256::new();
+        let mut invalid_signatures = Vec::new();
+        let mut altered_operations = Vec::new();
+        for (operation_id, data) in &rows {
+            let Ok(op) = serde_json::from_str::<Operation>(data) else {
+                altered_operations.push(operation_id.clone());
+                continue;
+            };
+            let hash = op.chain_hash();
+            digest.update(hash.as_bytes());
+            digest.update(b"\n");
+            if recorded.get(operation_id).is_some_and(|h| *h != hash) {
+                altered_operations.push(operation_id.clone());
+            }
+            let author_key = op.author_key();
+            if !author_key.is_empty() && !signature_verifies(&op, author_key) {
+                invalid_signatures.push(operation_id.clone());
+            }
+        }
+
+        let mut chain_breaks = chain_breaks(conn)?;
+        chain_breaks.sort_by(|a, b| a.operation_id.cmp(&b.operation_id));
+        let chain_count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM (SELECT DISTINCT author_key, device_id FROM op_chain)",
+            [],
+            |row| row.get(0),
+        )?;
+
+        let mut report = IntegrityReport {
+            workspace_id: self.workspace_id.clone(),
+            generated_at: chrono::Utc::now().timestamp(),
+            operation_count: rows.len() as u64,
+            chain_count: chain_count as u64,
+            log_digest: hex::encode(digest.finalize()),
+            invalid_signatures,
+            altered_operations,
+            chain_breaks,
+            author_key: String::new(),
+            signature: String::new(),
+        };
+        report.sign(&self.signing_key);
+        Ok(report)
+    }
+
+    /// Whether every operation of every author reaches this workspace, so
+    /// that an unknown predecessor means a gap. Peers limited to a read
+    /// scope do not receive operations outside it.
+    pub(super) fn receives_full_chains(&self) -> Result<bool> {
+        Ok(self
+            .read_scope_for(&self.current_identity_pubkey)?
+            .is_none())
+    }
+}
+
+/// Checks how an incoming operation continues its author's chain on this
+/// peer. Call before recording the operation's own link.
+///
+/// An operation from a chain not seen before starts it here; only later
+/// operations can leave a gap. A predecessor folded into an applied
+/// checkpoint counts as known.
+pub(super) fn find_chain_break(conn: &Connection, op: &Operation) -> Result<Option<ChainBreak>> {
+    let Some(prev_hash) = op.prev_hash() else {
+        return Ok(None);
+    };
+    let chain_break = |kind| ChainBreak {
+        kind,
+        operation_id: op.operation_id().to_string(),
+        author_key: op.author_key().to_string(),
+        device_id: op.device_id().to_string(),
+        prev_hash: prev_hash.to_string(),
+    };
+    let sibling: Option<String> = conn
+        .query_row(
+            "SELECT operation_id FROM op_chain \
+             WHERE author_key = ?1 AND device_id = ?2 AND prev_hash = ?3 AND operation_id != ?4 \
+             LIMIT 1",
+            rusqlite::params![
+                op.author_key(),
+                op.device_id(),
+                prev_hash,
+                op.operation_id()
+            ],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if sibling.is_some() {
+        return Ok(Some(chain_break(ChainBreakKind::Fork)));
+    }
+
+    // An operation arriving ahead of its successor fills a hole rather
+    // than opening one.
+    let (seen, has_prev, has_next): (i64, i64, i64) = conn.query_row(
+        "SELECT COUNT(*), \
+                COALESCE(SUM(hash = ?3), 0) + ( \
+                    SELECT COUNT(*) FROM checkpoint_chain_tails \
+                    WHERE author_key = ?1 AND device_id = ?2 AND hash = ?3), \
+                COALESCE(SUM(prev_hash = ?5), 0) \
+         FROM op_chain WHERE author_key = ?1 AND device_id = ?2 AND operation_id != ?4",
+        rusqlite::params![
+            op.author_key(),
+            op.device_id(),
+            prev_hash,
+            op.operation_id(),
+            op.chain_hash()
+        ],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    Ok((seen > 0 && has_prev == 0 && has_next == 0).then(|| chain_break(ChainBreakKind::Gap)))
+}
+
+/// Every gap and fork among the recorded chain links. A chain starts at its
+/// earliest recorded link without a known predecessor; any later such link
+/// is a gap. Tails of applied checkpoints are known predecessors.
+fn chain_breaks(conn: &Connection) -> Result<Vec<ChainBreak>> {
+    let mut stmt = conn.prepare(
+        "WITH links AS ( \
+             SELECT c.rowid AS pos, c.operation_id, c.author_key, c.device_id, c.prev_hash, \
+                    c.prev_hash IS NULL OR NOT EXISTS ( \
+                        SELECT 1 FROM op_chain p \
+                        WHERE p.author_key = c.author_key AND p.device_id = c.device_id \
+                          AND p.hash = c.prev_hash \
+                        UNION ALL \
+                        SELECT 1 FROM checkpoint_chain_tails t \
+                        WHERE t.author_key = c.author_key AND t.device_id = c.device_id \
+                          AND t.hash = c.prev_hash) AS unlinked \
+             FROM op_chain c \
+         ) \
+         SELECT l.operation_id, l.author_key, l.device_id, l.prev_hash, \
+                l.unlinked AND EXISTS ( \
+                    SELECT 1 FROM links s \
+                    WHERE s.author_key = l.author_key AND s.device_id = l.device_id \
+                      AND s.unlinked AND s.pos < l.pos), \
+                EXISTS ( \
+                    SELECT 1 FROM op_chain f \
+                    WHERE f.author_key = l.author_key AND f.device_id = l.device_id \
+                      AND f.prev_hash = l.prev_hash AND f.operation_id != l.operation_id) \
+         FROM links l WHERE l.prev_hash IS NOT NULL",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        let (gap, forked): (bool, bool) = (row.get(4)?, row.get(5)?);
+        let kind = if forked {
+            ChainBreakKind::Fork
+        } else if gap {
+            ChainBreakKind::Gap
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(ChainBreak {
+            kind,
+            operation_id: row.get(0)?,
+            author_key: row.get(1)?,
+            device_id: row.get(2)?,
+            prev_hash: row.get(3)?,
+        }))
+    })?;
+    let mut breaks = Vec::new();
+    for row in rows {
+        breaks.extend(row?);
+    }
+    Ok(breaks)
+}
+
+fn signature_verifies(op: &Operation, author_key: &str) -> bool {
+    base64::engine::general_purpose::STANDARD
+        .decode(author_key)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .and_then(|arr| ed25519_dalek::VerifyingKey::from_bytes(&arr).ok())
+        .is_some_and(|vk| op.verify(&vk))
+}
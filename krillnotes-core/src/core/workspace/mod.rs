@@ -6,10 +6,11 @@
 
 //! High-level workspace operations over a Krillnotes SQLite database.
 
-use crate::core::attachment::{decrypt_attachment, encrypt_attachment, AttachmentMeta};
+use crate::core::attachment::AttachmentMeta;
 use crate::core::contact::{generate_fingerprint, TrustLevel};
 use crate::core::export::WorkspaceMetadata;
 use crate::core::hlc::{HlcClock, HlcTimestamp};
+use crate::core::operation_log::CommitListener;
 use crate::core::peer_registry::{PeerInfo, PeerRegistry};
 use crate::core::user_script;
 #[allow(unused_imports)]
@@ -21,7 +22,6 @@ use crate::{
 use rhai::Dynamic;
 use rusqlite::{Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
 use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 use uuid::Uuid;
@@ -802,6 +802,13 @@ impl Workspace {
         &mut self.script_registry
     }
 
+    /// Registers a callback notified whenever a local operation is logged,
+    /// such as [`SyncDaemon::commit_notifier`](crate::core::sync::scheduler::SyncDaemon::commit_notifier)
+    /// for an immediate outbound push. Replaces any previous listener.
+    pub fn set_commit_listener(&mut self, listener: Option<CommitListener>) {
+        self.operation_log.set_listener(listener);
+    }
+
     /// Returns the underlying SQLite connection.
     pub fn connection(&self) -> &Connection {
         self.storage.connection()
@@ -828,9 +835,11 @@ impl Workspace {
 
     /// Check permission before applying an operation.
     fn authorize(&self, operation: &Operation) -> Result<()> {
+        let predecessors = self.predecessors_of(&self.current_identity_pubkey)?;
         self.permission_gate.authorize(
             self.storage.connection(),
             &self.current_identity_pubkey,
+            &predecessors,
             operation,
         )?;
         Ok(())
@@ -868,12 +877,26 @@ impl Workspace {
         Ok(())
     }
 
-    /// Signs `op` in place if a signing key is present. No-op otherwise.
+    /// Chains `op` to the signer's previous operation from this device (see
+    /// [`crate::core::operation_log::chain_head`]) and signs it in place.
     ///
-    /// Takes the signing key as an explicit parameter to avoid a borrow conflict
-    /// with the transaction (which is borrowed from `self.storage`).
-    fn sign_op_with(signing_key: &ed25519_dalek::SigningKey, op: &mut Operation) {
+    /// Takes the signing key and connection as explicit parameters to avoid a
+    /// borrow conflict with the transaction (which is borrowed from `self.storage`).
+    fn sign_op_with(
+        signing_key: &ed25519_dalek::SigningKey,
+        conn: &rusqlite::Connection,
+        op: &mut Operation,
+    ) -> Result<()> {
+        use base64::Engine as _;
+        let author_key = base64::engine::general_purpose::STANDARD
+            .encode(signing_key.verifying_key().as_bytes());
+        op.set_prev_hash(crate::core::operation_log::chain_head(
+            conn,
+            &author_key,
+            op.device_id(),
+        )?);
         op.sign(signing_key);
+        Ok(())
     }
 
     /// Phase D: batch-migrate notes whose `schema_version` is behind the current schema version.
@@ -1021,6 +1044,7 @@ impl Workspace {
                 operation_id: uuid::Uuid::new_v4().to_string(),
                 timestamp: ts,
                 device_id: self.device_id.clone(),
+                prev_hash: None,
                 signature: String::new(),
                 updated_by: String::new(),
                 schema_name: schema_name.clone(),
@@ -1028,7 +1052,7 @@ impl Workspace {
                 to_version: schema_version,
                 notes_migrated: notes_count,
             };
-            Self::sign_op_with(&self.signing_key, &mut op);
+            Self::sign_op_with(&self.signing_key, &tx, &mut op)?;
             Self::log_op(&self.operation_log, &tx, &op)?;
 
             tx.commit()?;
@@ -1089,9 +1113,10 @@ impl Workspace {
             device_uuid,
             device_name,
             identity_public_key: String::new(),
+            prev_hash: None,
             signature: String::new(),
         };
-        Self::sign_op_with(&self.signing_key, &mut op);
+        Self::sign_op_with(&self.signing_key, &tx, &mut op)?;
         Self::log_op(&self.operation_log, &tx, &op)?;
         tx.commit()?;
 
@@ -1115,12 +1140,27 @@ impl Workspace {
 // ── Domain sub-modules (split from this file for readability) ──────
 
 mod attachments;
+mod audit_export;
+mod blob_transfer;
+mod checkpoint;
+mod devices;
+mod graft;
 mod hooks;
+mod integrity;
 mod notes;
+mod root_recovery;
+mod scope;
 mod scripts;
+mod succession;
 mod sync;
 mod sync_events;
 mod undo;
+pub use blob_transfer::{PendingAttachment, BLOB_CHUNK_SIZE, MAX_BLOB_BYTES_PER_BUNDLE};
+pub use checkpoint::{CheckpointMerge, OperationsSince, DEFAULT_CHECKPOINT_RETENTION_DAYS};
+pub use devices::DeviceInfo;
+pub use graft::{GraftIdStrategy, GraftOutcome};
+pub use scope::ScopedOperations;
+pub use sync::OpRejection;
 pub use sync_events::SyncEventRecord;
 pub mod permissions;
 
@@ -142,6 +142,10 @@ impl Workspace {
             title: note.title.clone(),
             fields: note.fields.clone(),
             created_by: self.current_identity_pubkey.clone(),
+            created_at: None,
+            modified_at: None,
+            original_author: None,
+            prev_hash: None,
             signature: String::new(),
         };
         self.authorize(&auth_op)?;
@@ -230,9 +234,13 @@ impl Workspace {
             title: note.title.clone(),
             fields: note.fields.clone(),
             created_by: String::new(),
+            created_at: None,
+            modified_at: None,
+            original_author: None,
+            prev_hash: None,
             signature: String::new(),
         };
-        Self::sign_op_with(&signing_key, &mut op);
+        Self::sign_op_with(&signing_key, &tx, &mut op)?;
         Self::log_op(&self.operation_log, &tx, &op)?;
         Self::purge_ops_if_needed(&self.operation_log, &tx)?;
 
@@ -366,6 +374,10 @@ impl Workspace {
             title: root_source.title.clone(),
             fields: root_source.fields.clone(),
             created_by: self.current_identity_pubkey.clone(),
+            created_at: None,
+            modified_at: None,
+            original_author: None,
+            prev_hash: None,
             signature: String::new(),
         };
         self.authorize(&auth_op)?;
@@ -440,9 +452,13 @@ impl Workspace {
                 title: note.title.clone(),
                 fields: note.fields.clone(),
                 created_by: String::new(),
+                created_at: None,
+                modified_at: None,
+                original_author: None,
+                prev_hash: None,
                 signature: String::new(),
             };
-            Self::sign_op_with(&signing_key, &mut op);
+            Self::sign_op_with(&signing_key, &tx, &mut op)?;
             Self::log_op(&self.operation_log, &tx, &op)?;
         }
 
@@ -513,6 +529,10 @@ impl Workspace {
             title: new_note.title.clone(),
             fields: new_note.fields.clone(),
             created_by: self.current_identity_pubkey.clone(),
+            created_at: None,
+            modified_at: None,
+            original_author: None,
+            prev_hash: None,
             signature: String::new(),
         };
         self.authorize(&auth_op)?;
@@ -554,9 +574,13 @@ impl Workspace {
             title: new_note.title.clone(),
             fields: new_note.fields.clone(),
             created_by: String::new(),
+            created_at: None,
+            modified_at: None,
+            original_author: None,
+            prev_hash: None,
             signature: String::new(),
         };
-        Self::sign_op_with(&signing_key, &mut op);
+        Self::sign_op_with(&signing_key, &tx, &mut op)?;
         Self::log_op(&self.operation_log, &tx, &op)?;
         Self::purge_ops_if_needed(&self.operation_log, &tx)?;
 
@@ -597,6 +621,7 @@ impl Workspace {
             note_id: note_id.to_string(),
             title: new_title.clone(),
             modified_by: self.current_identity_pubkey.clone(),
+            prev_hash: None,
             signature: String::new(),
         };
         self.authorize(&auth_op)?;
@@ -625,9 +650,10 @@ impl Workspace {
             note_id: note_id.to_string(),
             title: new_title,
             modified_by: String::new(),
+            prev_hash: None,
             signature: String::new(),
         };
-        Self::sign_op_with(&signing_key, &mut op);
+        Self::sign_op_with(&signing_key, &tx, &mut op)?;
         Self::log_op(&self.operation_log, &tx, &op)?;
         Self::purge_ops_if_needed(&self.operation_log, &tx)?;
 
@@ -660,6 +686,7 @@ impl Workspace {
             note_id: note_id.to_string(),
             tags: normalised.clone(),
             modified_by: self.current_identity_pubkey.clone(),
+            prev_hash: None,
             signature: String::new(),
         };
         self.authorize(&auth_op)?;
@@ -685,9 +712,10 @@ impl Workspace {
             note_id: note_id.to_string(),
             tags: normalised,
             modified_by: String::new(),
+            prev_hash: None,
             signature: String::new(),
         };
-        Self::sign_op_with(&signing_key, &mut op);
+        Self::sign_op_with(&signing_key, &tx, &mut op)?;
         Self::log_op(&self.operation_log, &tx, &op)?;
         Self::purge_ops_if_needed(&self.operation_log, &tx)?;
 
@@ -950,6 +978,7 @@ impl Workspace {
             note_id: note_id.to_string(),
             checked,
             modified_by: self.current_identity_pubkey.clone(),
+            prev_hash: None,
             signature: String::new(),
         };
         self.authorize(&auth_op)?;
@@ -976,9 +1005,10 @@ impl Workspace {
             note_id: note_id.to_string(),
             checked,
             modified_by: String::new(),
+            prev_hash: None,
             signature: String::new(),
         };
-        Self::sign_op_with(&signing_key, &mut op);
+        Self::sign_op_with(&signing_key, &tx, &mut op)?;
         Self::log_op(&self.operation_log, &tx, &op)?;
         Self::purge_ops_if_needed(&self.operation_log, &tx)?;
         tx.commit()?;
@@ -1200,6 +1230,7 @@ impl Workspace {
             new_parent_id: new_parent_id.map(|s| s.to_string()),
             new_position,
             moved_by: self.current_identity_pubkey.clone(),
+            prev_hash: None,
             signature: String::new(),
         };
         self.authorize(&auth_op)?;
@@ -1270,9 +1301,10 @@ impl Workspace {
             new_parent_id: new_parent_id.map(|s| s.to_string()),
             new_position,
             moved_by: String::new(),
+            prev_hash: None,
             signature: String::new(),
         };
-        Self::sign_op_with(&signing_key, &mut op);
+        Self::sign_op_with(&signing_key, &tx, &mut op)?;
         Self::log_op(&self.operation_log, &tx, &op)?;
         Self::purge_ops_if_needed(&self.operation_log, &tx)?;
 
@@ -1356,6 +1388,7 @@ impl Workspace {
             device_id: self.device_id.clone(),
             note_id: note_id.to_string(),
             deleted_by: self.current_identity_pubkey.clone(),
+            prev_hash: None,
             signature: String::new(),
         };
         self.authorize(&auth_op)?;
@@ -1410,9 +1443,10 @@ impl Workspace {
             device_id: self.device_id.clone(),
             note_id: note_id.to_string(),
             deleted_by: String::new(),
+            prev_hash: None,
             signature: String::new(),
         };
-        Self::sign_op_with(&signing_key, &mut op);
+        Self::sign_op_with(&signing_key, &tx, &mut op)?;
         Self::log_op(&self.operation_log, &tx, &op)?;
         Self::purge_ops_if_needed(&self.operation_log, &tx)?;
         tx.commit()?;
@@ -1509,6 +1543,7 @@ impl Workspace {
             device_id: self.device_id.clone(),
             note_id: note_id.to_string(),
             deleted_by: self.current_identity_pubkey.clone(),
+            prev_hash: None,
             signature: String::new(),
         };
         self.authorize(&auth_op)?;
@@ -1587,9 +1622,10 @@ impl Workspace {
             device_id: self.device_id.clone(),
             note_id: note_id.to_string(),
             deleted_by: String::new(),
+            prev_hash: None,
             signature: String::new(),
         };
-        Self::sign_op_with(&signing_key, &mut op);
+        Self::sign_op_with(&signing_key, &tx, &mut op)?;
         Self::log_op(&self.operation_log, &tx, &op)?;
         Self::purge_ops_if_needed(&self.operation_log, &tx)?;
 
@@ -1810,6 +1846,7 @@ impl Workspace {
             note_id: note_id.to_string(),
             title: title.clone(),
             modified_by: self.current_identity_pubkey.clone(),
+            prev_hash: None,
             signature: String::new(),
         };
         self.authorize(&auth_op)?;
@@ -1956,9 +1993,10 @@ impl Workspace {
             note_id: note_id.to_string(),
             title: title.clone(),
             modified_by: String::new(),
+            prev_hash: None,
             signature: String::new(),
         };
-        Self::sign_op_with(&signing_key, &mut title_op);
+        Self::sign_op_with(&signing_key, &tx, &mut title_op)?;
         Self::log_op(&self.operation_log, &tx, &title_op)?;
 
         // Log one UpdateField operation per field value that was written.
@@ -1974,9 +2012,10 @@ impl Workspace {
                 field: field_key.clone(),
                 value: field_value.clone(),
                 modified_by: String::new(),
+                prev_hash: None,
                 signature: String::new(),
             };
-            Self::sign_op_with(&signing_key, &mut field_op);
+            Self::sign_op_with(&signing_key, &tx, &mut field_op)?;
             Self::log_op(&self.operation_log, &tx, &field_op)?;
         }
 
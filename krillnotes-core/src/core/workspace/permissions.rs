@@ -16,8 +16,8 @@ use crate::core::operation::Operation;
 use crate::core::workspace::Workspace;
 use crate::Result;
 use rusqlite::OptionalExtension;
-use serde::Serialize;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
 use uuid::Uuid;
 
 // ── Return types ────────────────────────────────────────────────────
@@ -63,6 +63,25 @@ pub struct CascadeImpactRow {
     pub reason: String,
 }
 
+/// The notes an identity may read, as used to filter what is replicated to it.
+///
+/// `full` notes are readable in their entirety. `ancestors` are the "ghost"
+/// ancestors of granted subtrees: the recipient sees only their title and
+/// place in the tree, never their fields, tags or attachments.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadScope {
+    pub full: BTreeSet<String>,
+    pub ancestors: BTreeSet<String>,
+}
+
+impl ReadScope {
+    /// True if the note is in the scope at all, fully or as a ghost ancestor.
+    pub fn contains(&self, note_id: &str) -> bool {
+        self.full.contains(note_id) || self.ancestors.contains(note_id)
+    }
+}
+
 // ── Workspace methods ───────────────────────────────────────────────
 
 impl Workspace {
@@ -227,8 +246,13 @@ impl Workspace {
     /// Uses top-down grant propagation to avoid per-note ancestor walks.
     /// The workspace owner is short-circuited to `"root_owner"` for every note.
     pub fn get_all_effective_roles(&self) -> Result<HashMap<String, String>> {
+        self.effective_roles_of(self.identity_pubkey())
+    }
+
+    /// [`get_all_effective_roles`](Self::get_all_effective_roles) for an
+    /// arbitrary identity rather than the workspace's own.
+    fn effective_roles_of(&self, user_id: &str) -> Result<HashMap<String, String>> {
         let conn = self.connection();
-        let user_id = self.identity_pubkey();
         let owner_pubkey = self.owner_pubkey();
 
         // 1. Root owner: every note gets "root_owner"
@@ -321,6 +345,7 @@ impl Workspace {
             user_id: user_id.to_string(),
             role: role.to_string(),
             granted_by: self.current_identity_pubkey.clone(),
+            prev_hash: None,
             signature: String::new(),
         };
         self.authorize(&auth_op)?;
@@ -338,13 +363,14 @@ impl Workspace {
             user_id: user_id.to_string(),
             role: role.to_string(),
             granted_by: self.current_identity_pubkey.clone(),
+            prev_hash: None,
             signature: String::new(),
         };
         Self::apply_permission_op_via(&*self.permission_gate, &tx, &op)?;
 
         // Sign and log.
         Self::save_hlc(&ts, &tx)?;
-        Self::sign_op_with(&signing_key, &mut op);
+        Self::sign_op_with(&signing_key, &tx, &mut op)?;
         Self::log_op(&self.operation_log, &tx, &op)?;
         Self::purge_ops_if_needed(&self.operation_log, &tx)?;
 
@@ -369,6 +395,7 @@ impl Workspace {
             note_id: Some(note_id.to_string()),
             user_id: user_id.to_string(),
             revoked_by: self.current_identity_pubkey.clone(),
+            prev_hash: None,
             signature: String::new(),
         };
         self.authorize(&auth_op)?;
@@ -385,13 +412,14 @@ impl Workspace {
             note_id: Some(note_id.to_string()),
             user_id: user_id.to_string(),
             revoked_by: self.current_identity_pubkey.clone(),
+            prev_hash: None,
             signature: String::new(),
         };
         Self::apply_permission_op_via(&*self.permission_gate, &tx, &op)?;
 
         // Sign and log.
         Self::save_hlc(&ts, &tx)?;
-        Self::sign_op_with(&signing_key, &mut op);
+        Self::sign_op_with(&signing_key, &tx, &mut op)?;
         Self::log_op(&self.operation_log, &tx, &op)?;
         Self::purge_ops_if_needed(&self.operation_log, &tx)?;
 
@@ -489,8 +517,21 @@ impl Workspace {
     /// if no read filtering is needed (root owner, or no `note_permissions`
     /// table).
     pub fn visible_note_ids(&self) -> Result<Option<std::collections::HashSet<String>>> {
+        Ok(self
+            .read_scope_for(self.identity_pubkey())?
+            .map(|scope| scope.full.into_iter().chain(scope.ancestors).collect()))
+    }
+
+    /// Returns the read scope of `identity` (base64 public key), or `None`
+    /// if no read filtering applies to it (root owner, or no
+    /// `note_permissions` table).
+    ///
+    /// Used by delta and snapshot generation to decide what a recipient may
+    /// receive; [`visible_note_ids`](Self::visible_note_ids) is the same scope
+    /// for the workspace's own identity.
+    pub fn read_scope_for(&self, identity: &str) -> Result<Option<ReadScope>> {
         // Root owner sees everything.
-        if self.identity_pubkey() == self.owner_pubkey() {
+        if identity == self.owner_pubkey() {
             return Ok(None);
         }
 
@@ -510,15 +551,13 @@ impl Workspace {
         }
 
         let conn = self.connection();
-        let actor = self.identity_pubkey();
-
-        let roles = self.get_all_effective_roles()?;
-        let mut visible: std::collections::HashSet<String> = roles.into_keys().collect();
+        let full: BTreeSet<String> = self.effective_roles_of(identity)?.into_keys().collect();
+        let mut ancestors = BTreeSet::new();
 
-        // Include ghost ancestors — walk up parent chain for each granted subtree root
+        // Ghost ancestors — walk up parent chain for each granted subtree root
         let grant_anchors: Vec<String> = conn
             .prepare("SELECT DISTINCT note_id FROM note_permissions WHERE user_id = ?1 AND note_id IS NOT NULL")?
-            .query_map(rusqlite::params![actor], |row| row.get::<_, String>(0))?
+            .query_map(rusqlite::params![identity], |row| row.get::<_, String>(0))?
             .collect::<std::result::Result<Vec<_>, _>>()?;
 
         for anchor_id in &grant_anchors {
@@ -534,10 +573,9 @@ impl Workspace {
                     .flatten();
                 match parent {
                     Some(pid) => {
-                        if visible.contains(&pid) {
+                        if full.contains(&pid) || !ancestors.insert(pid.clone()) {
                             break;
                         }
-                        visible.insert(pid.clone());
                         current_id = pid;
                     }
                     None => break,
@@ -545,7 +583,7 @@ impl Workspace {
             }
         }
 
-        Ok(Some(visible))
+        Ok(Some(ReadScope { full, ancestors }))
     }
 
     /// Check that the current user can read `note_id`.
@@ -0,0 +1,239 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Social recovery of Root Ownership — `SetRootRecovery` operations and
+//! `TransferRootOwnership` operations authorised by recovery shares.
+
+use rusqlite::{Connection, OptionalExtension};
+
+use super::sync::OpRejection;
+use crate::core::error::{KrillnotesError, Result};
+use crate::core::operation::Operation;
+use crate::core::root_recovery::{
+    combine_recovery_shares, sign_recovery_transfer, split_recovery_key, verify_recovery_transfer,
+    RecoveryShare,
+};
+use crate::core::workspace::Workspace;
+
+impl Workspace {
+    /// Sets up social recovery of Root Ownership: generates a recovery key,
+    /// announces it to peers with a `SetRootRecovery` operation and returns
+    /// `share_count` shares for trusted contacts, any `threshold` of which
+    /// can later authorise a transfer to a new owner key.
+    ///
+    /// Replaces any earlier setup; its shares stop working.
+    pub fn set_up_root_recovery(
+        &mut self,
+        threshold: u8,
+        share_count: u8,
+    ) -> Result<Vec<RecoveryShare>> {
+        if !self.is_owner() {
+            return Err(KrillnotesError::NotOwner);
+        }
+        let (recovery_key, shares) = split_recovery_key(
+            &self.signing_key,
+            &self.workspace_id,
+            threshold,
+            share_count,
+        )?;
+
+        let ts = self.hlc.now();
+        let mut op = Operation::SetRootRecovery {
+            operation_id: uuid::Uuid::new_v4().to_string(),
+            timestamp: ts,
+            device_id: self.device_id.clone(),
+            recovery_key,
+            threshold,
+            set_by: String::new(),
+            prev_hash: None,
+            signature: String::new(),
+        };
+        Self::sign_op_with(&self.signing_key, self.storage.connection(), &mut op)?;
+        {
+            let tx = self.storage.connection_mut().transaction()?;
+            Self::save_hlc(&ts, &tx)?;
+            Self::log_op(&self.operation_log, &tx, &op)?;
+            tx.commit()?;
+        }
+        self.apply_op_to_working_tables(&op, &[])?;
+        Ok(shares)
+    }
+
+    /// Returns the current recovery key (base64) and share threshold, if
+    /// the Root Owner has set up social recovery.
+    pub fn root_recovery(&self) -> Result<Option<(String, u8)>> {
+        root_recovery(self.storage.connection())
+    }
+
+    /// Takes over Root Ownership with the current identity key, authorised
+    /// by `threshold` recovery shares released by the owner's contacts.
+    pub fn recover_root_ownership(&mut self, shares: &[RecoveryShare]) -> Result<()> {
+        let invalid = |msg: &str| KrillnotesError::InvalidRecoveryShares(msg.to_string());
+        let (recovery_key, _) = self
+            .root_recovery()?
+            .ok_or_else(|| invalid("the Root Owner has not set up recovery"))?;
+        if shares
+            .iter()
+            .any(|s| s.workspace_id != self.workspace_id || s.recovery_key != recovery_key)
+        {
+            return Err(invalid(
+                "shares belong to another workspace or recovery setup",
+            ));
+        }
+        let recovery = combine_recovery_shares(shares)?;
+        let recovery_signature = sign_recovery_transfer(
+            &recovery,
+            &self.workspace_id,
+            &self.owner_pubkey,
+            &self.current_identity_pubkey,
+        );
+
+        let ts = self.hlc.now();
+        let mut op = Operation::TransferRootOwnership {
+            operation_id: uuid::Uuid::new_v4().to_string(),
+            timestamp: ts,
+            device_id: self.device_id.clone(),
+            new_owner: self.current_identity_pubkey.clone(),
+            transferred_by: String::new(),
+            recovery_signature: Some(recovery_signature),
+            prev_hash: None,
+            signature: String::new(),
+        };
+        Self::sign_op_with(&self.signing_key, self.storage.connection(), &mut op)?;
+        {
+            let tx = self.storage.connection_mut().transaction()?;
+            Self::save_hlc(&ts, &tx)?;
+            Self::log_op(&self.operation_log, &tx, &op)?;
+            tx.commit()?;
+        }
+        self.apply_op_to_working_tables(&op, &[])?;
+
+        log::info!(target: "krillnotes::sync",
+            "recovered Root Ownership of workspace {}", self.workspace_id);
+        Ok(())
+    }
+
+    /// Returns the new owner named by `op` if it is a `TransferRootOwnership`
+    /// from the current owner authorised by the current recovery key.
+    pub(crate) fn recovered_owner(&self, op: &Operation) -> Result<Option<String>> {
+        recovered_owner(
+            self.storage.connection(),
+            &self.workspace_id,
+            &self.owner_pubkey,
+            op,
+        )
+    }
+
+    /// Rejects recovery-authorised transfers whose recovery signature does
+    /// not verify against the workspace's recovery key.
+    pub(super) fn check_root_recovery(&self, op: &Operation) -> Result<Option<OpRejection>> {
+        let Operation::TransferRootOwnership {
+            recovery_signature: Some(_),
+            ..
+        } = op
+        else {
+            return Ok(None);
+        };
+        Ok(self
+            .recovered_owner(op)?
+            .is_none()
+            .then_some(OpRejection::BadRecovery))
+    }
+
+    /// Records the recovery key of a `SetRootRecovery` by the Root Owner.
+    ///
+    /// `set_by` is only trusted once the operation's signature verifies
+    /// against it: relayed and replayed operations reach here without the
+    /// sender having signed them.
+    pub(super) fn record_root_recovery(
+        conn: &Connection,
+        owner_pubkey: &str,
+        op: &Operation,
+    ) -> Result<()> {
+        let Operation::SetRootRecovery {
+            recovery_key,
+            threshold,
+            set_by,
+            ..
+        } = op
+        else {
+            return Ok(());
+        };
+        if set_by != owner_pubkey {
+            log::warn!(target: "krillnotes::sync",
+                "ignoring SetRootRecovery {} — not set by the Root Owner", op.operation_id());
+            return Ok(());
+        }
+        if !op.verify_author() {
+            log::warn!(target: "krillnotes::sync",
+                "ignoring SetRootRecovery {} — signature does not verify", op.operation_id());
+            return Ok(());
+        }
+        conn.execute(
+            "INSERT OR REPLACE INTO workspace_meta (key, value) VALUES \
+             ('root_recovery_key', ?1), ('root_recovery_threshold', ?2)",
+            rusqlite::params![recovery_key, threshold.to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// Forgets the recovery key once it has been used: its shares must not
+    /// authorise a second transfer.
+    pub(super) fn clear_root_recovery(conn: &Connection) -> Result<()> {
+        conn.execute(
+            "DELETE FROM workspace_meta WHERE key IN ('root_recovery_key', 'root_recovery_threshold')",
+            [],
+        )?;
+        Ok(())
+    }
+}
+
+pub(super) fn recovered_owner(
+    conn: &Connection,
+    workspace_id: &str,
+    owner_pubkey: &str,
+    op: &Operation,
+) -> Result<Option<String>> {
+    let Operation::TransferRootOwnership {
+        new_owner,
+        transferred_by,
+        recovery_signature: Some(recovery_signature),
+        ..
+    } = op
+    else {
+        return Ok(None);
+    };
+    let Some((recovery_key, _)) = root_recovery(conn)? else {
+        return Ok(None);
+    };
+    let authorised = transferred_by == new_owner
+        && verify_recovery_transfer(
+            &recovery_key,
+            workspace_id,
+            owner_pubkey,
+            new_owner,
+            recovery_signature,
+        );
+    Ok(authorised.then(|| new_owner.clone()))
+}
+
+fn root_recovery(conn: &Connection) -> Result<Option<(String, u8)>> {
+    let meta = |key: &str| -> Result<Option<String>> {
+        Ok(conn
+            .query_row(
+                "SELECT value FROM workspace_meta WHERE key = ?1",
+                [key],
+                |row| row.get(0),
+            )
+            .optional()?)
+    };
+    let (Some(key), Some(threshold)) =
+        (meta("root_recovery_key")?, meta("root_recovery_threshold")?)
+    else {
+        return Ok(None);
+    };
+    Ok(threshold.parse().ok().map(|threshold| (key, threshold)))
+}
@@ -0,0 +1,528 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Scope-filtered replication: projecting outgoing operations onto a
+//! recipient's [`ReadScope`].
+//!
+//! A peer invited to one subtree must never receive content from outside it,
+//! not even encrypted to them. Delta generation therefore drops operations on
+//! notes the recipient cannot read, and emits synthetic *boundary* operations
+//! for notes that crossed the edge of the recipient's scope since the last
+//! delivered bundle:
+//!
+//!   - notes entering the scope are created with their full current state;
+//!   - ghost ancestors are created with title and tree position only;
+//!   - notes leaving the scope (or downgraded to ghost ancestors) are deleted.
+//!
+//! Boundary operations are signed by this identity but never written to the
+//! local log; the recipient applies them to its working tables only, so they
+//! are never relayed onwards. Signing makes this identity the `created_by` of
+//! a rebuilt note, so the note's original author travels in the signed
+//! operation's `original_author` and replaces it on the recipient.
+//!
+//! Being unlogged, boundary operations are exempt from the per-device hash
+//! chain (`prev_hash`) and absent from the audit log export on both sides.
+//! They restate state the sender already holds through logged operations,
+//! which remain the auditable record; the recipient's log only covers what
+//! happens to the notes after they arrive.
+
+use super::*;
+use crate::core::undo::RetractInverse;
+use crate::core::workspace::permissions::ReadScope;
+use std::collections::{BTreeSet, HashSet};
+
+/// Outgoing operations after projection onto a recipient's read scope.
+#[derive(Debug, Default)]
+pub struct ScopedOperations {
+    /// Logged operations the recipient may receive, in their original order,
+    /// with their `verified_by` metadata.
+    pub operations: Vec<(Operation, String)>,
+    /// Synthetic operations moving notes across the scope boundary. Applied
+    /// after `operations`.
+    pub boundary_operations: Vec<Operation>,
+}
+
+/// How an operation relates to the note tree, for scope filtering.
+enum OpTarget<'a> {
+    /// Workspace-level operation (scripts, permissions, membership).
+    Workspace,
+    /// Operation on a single note's content or structure.
+    Note { note_id: &'a str, structural: bool },
+    /// Retraction touching the listed notes.
+    Retract(Vec<&'a str>),
+}
+
+fn op_target(op: &Operation) -> OpTarget<'_> {
+    match op {
+        Operation::UpdateNote { note_id, .. }
+        | Operation::DeleteNote { note_id, .. }
+        | Operation::MoveNote { note_id, .. } => OpTarget::Note {
+            note_id,
+            structural: true,
+        },
+        Operation::CreateNote { note_id, .. }
+        | Operation::UpdateField { note_id, .. }
+        | Operation::SetTags { note_id, .. }
+        | Operation::SetChecked { note_id, .. }
+        | Operation::AddAttachment { note_id, .. }
+        | Operation::RemoveAttachment { note_id, .. } => OpTarget::Note {
+            note_id,
+            structural: false,
+        },
+        Operation::RetractOperation { inverse, .. } => {
+            let mut ids = Vec::new();
+            inverse_note_ids(inverse, &mut ids);
+            OpTarget::Retract(ids)
+        }
+        Operation::CreateUserScript { .. }
+        | Operation::UpdateUserScript { .. }
+        | Operation::DeleteUserScript { .. }
+        | Operation::UpdateSchema { .. }
+        | Operation::SetPermission { .. }
+        | Operation::RevokePermission { .. }
+        | Operation::JoinWorkspace { .. }
+        | Operation::RemovePeer { .. }
+        | Operation::TransferRootOwnership { .. }
+        | Operation::RegisterDevice { .. }
+        | Operation::RotateIdentityKey { .. }
+        | Operation::RevokeDevice { .. }
+        | Operation::SetRootRecovery { .. } => OpTarget::Workspace,
+    }
+}
+
+fn inverse_note_ids<'a>(inverse: &'a RetractInverse, out: &mut Vec<&'a str>) {
+    match inverse {
+        RetractInverse::DeleteNote { note_id }
+        | RetractInverse::NoteRestore { note_id, .. }
+        | RetractInverse::PositionRestore { note_id, .. } => out.push(note_id),
+        RetractInverse::SubtreeRestore { notes, attachments } => {
+            out.extend(notes.iter().map(|n| n.id.as_str()));
+            out.extend(attachments.iter().map(|a| a.note_id.as_str()));
+        }
+        RetractInverse::AttachmentRestore { meta } => out.push(&meta.note_id),
+        RetractInverse::Batch(items) => {
+            for item in items {
+                inverse_note_ids(item, out);
+            }
+        }
+        RetractInverse::DeleteScript { .. }
+        | RetractInverse::ScriptRestore { .. }
+        | RetractInverse::AttachmentSoftDelete { .. } => {}
+    }
+}
+
+impl Workspace {
+    /// Projects `operations` (as returned by
+    /// [`operations_since_with_verified_by`](Self::operations_since_with_verified_by))
+    /// onto a recipient's read scope.
+    ///
+    /// `scope` is the recipient's current scope (`None` = unfiltered).
+    /// `previous` is the scope last delivered to it, or `None` if unknown —
+    /// in which case the recipient is assumed to hold every note, and every
+    /// note outside `scope` is deleted on its side.
+    ///
+    /// Operations on fully readable notes pass through unchanged. On ghost
+    /// ancestors only title, move and delete operations pass. Retractions that
+    /// touch any note outside the full scope are dropped and the notes they
+    /// touch are re-sent as boundary operations instead.
+    ///
+    /// With `resend`, every note in `scope` is re-sent with its current
+    /// state, for a recipient whose watermark predates the compacted log and
+    /// who therefore missed operations `operations` no longer contains.
+    pub fn scope_operations(
+        &mut self,
+        operations: Vec<(Operation, String)>,
+        scope: Option<&ReadScope>,
+        previous: Option<&ReadScope>,
+        resend: bool,
+    ) -> Result<ScopedOperations> {
+        let notes: HashMap<String, Note> = self
+            .list_all_notes()?
+            .into_iter()
+            .map(|n| (n.id.clone(), n))
+            .collect();
+        let current = match scope {
+            Some(scope) => scope.clone(),
+            None => ReadScope {
+                full: notes.keys().cloned().collect(),
+                ancestors: BTreeSet::new(),
+            },
+        };
+        let may_hold = |id: &str| previous.is_none_or(|p| p.contains(id));
+        let held_full = |id: &str| previous.is_none_or(|p| p.full.contains(id));
+
+        // 1. Filter the logged operations.
+        let mut kept = Vec::with_capacity(operations.len());
+        let mut created_in_batch: HashSet<String> = HashSet::new();
+        let mut deleted_in_batch: HashSet<String> = HashSet::new();
+        let mut refresh: BTreeSet<String> = if resend {
+            current
+                .full
+                .iter()
+                .chain(&current.ancestors)
+                .cloned()
+                .collect()
+        } else {
+            BTreeSet::new()
+        };
+        for (op, verified_by) in operations {
+            let keep = match op_target(&op) {
+                OpTarget::Workspace => true,
+                OpTarget::Note {
+                    note_id,
+                    structural,
+                } => {
+                    current.full.contains(note_id)
+                        || (structural && current.ancestors.contains(note_id))
+                        || (matches!(op, Operation::DeleteNote { .. }) && may_hold(note_id))
+                }
+                OpTarget::Retract(ids) => {
+                    if ids.iter().all(|id| current.full.contains(*id)) {
+                        true
+                    } else {
+                        refresh.extend(
+                            ids.into_iter()
+                                .filter(|id| current.contains(id))
+                                .map(str::to_string),
+                        );
+                        false
+                    }
+                }
+            };
+            if !keep {
+                log::debug!(target: "krillnotes::sync",
+                    "withholding out-of-scope op {}", op.operation_id());
+                continue;
+            }
+            match &op {
+                Operation::CreateNote { note_id, .. } => {
+                    created_in_batch.insert(note_id.clone());
+                }
+                Operation::DeleteNote { note_id, .. } => {
+                    deleted_in_batch.insert(note_id.clone());
+                }
+                _ => {}
+            }
+            kept.push((op, verified_by));
+        }
+
+        // 2. Work out which notes crossed the boundary.
+        let leaving: Vec<String> = match previous {
+            Some(p) => p
+                .full
+                .iter()
+                .chain(&p.ancestors)
+                .filter(|id| !current.contains(id))
+                .cloned()
+                .collect(),
+            None => notes
+                .keys()
+                .filter(|id| !current.contains(id))
+                .cloned()
+                .collect(),
+        };
+        // Notes created in this batch and gone again (e.g. by a withheld
+        // retraction) must not linger on the recipient either.
+        let leaving: BTreeSet<String> = leaving
+            .into_iter()
+            .chain(
+                created_in_batch
+                    .iter()
+                    .filter(|id| !current.contains(id))
+                    .cloned(),
+            )
+            .filter(|id| !deleted_in_batch.contains(id))
+            .collect();
+        let downgraded: Vec<&String> = current
+            .ancestors
+            .iter()
+            .filter(|id| held_full(id))
+            .collect();
+        let ghosts: Vec<&String> = current
+            .ancestors
+            .iter()
+            .filter(|id| !may_hold(id) || held_full(id) || refresh.contains(*id))
+            .collect();
+        let fulls: Vec<&String> = current
+            .full
+            .iter()
+            .filter(|id| {
+                (!held_full(id) && !created_in_batch.contains(*id)) || refresh.contains(*id)
+            })
+            .collect();
+
+        let depth = |id: &str| {
+            let mut depth = 0usize;
+            let mut parent = notes.get(id).and_then(|n| n.parent_id.as_deref());
+            while let Some(pid) = parent {
+                depth += 1;
+                parent = notes.get(pid).and_then(|n| n.parent_id.as_deref());
+            }
+            depth
+        };
+
+        // 3. Emit boundary operations: downgrades are deleted and rebuilt as
+        //    ghosts, entering notes are created parent-first, leaving notes
+        //    are deleted child-first.
+        let mut boundary = Vec::new();
+        for id in &downgraded {
+            self.push_removal_ops(id, &mut boundary)?;
+        }
+        let mut ghosts = ghosts;
+        ghosts.sort_by_key(|id| depth(id));
+        for id in ghosts {
+            if let Some(note) = notes.get(id.as_str()) {
+                let existing = may_hold(id) && !held_full(id);
+                self.push_state_ops(&Self::ghost_note(note), true, existing, &mut boundary)?;
+            }
+        }
+        let mut fulls = fulls;
+        fulls.sort_by_key(|id| depth(id));
+        for id in fulls {
+            if let Some(note) = notes.get(id.as_str()) {
+                self.push_state_ops(note, false, may_hold(id), &mut boundary)?;
+            }
+        }
+        let mut leaving: Vec<String> = leaving.into_iter().collect();
+        leaving.sort_by_key(|id| std::cmp::Reverse(depth(id)));
+        for id in &leaving {
+            self.push_removal_ops(id, &mut boundary)?;
+        }
+
+        if !boundary.is_empty() {
+            log::debug!(target: "krillnotes::sync",
+                "scope boundary: {} downgraded, {} leaving, {} boundary ops",
+                downgraded.len(), leaving.len(), boundary.len());
+        }
+        Ok(ScopedOperations {
+            operations: kept,
+            boundary_operations: boundary,
+        })
+    }
+
+    /// Boundary operations rebuilding `note` on the recipient. A `ghost` note
+    /// carries no tags, checked state or attachments. `existing` adds updates
+    /// for a note the recipient may already hold, since `CreateNote` does not
+    /// overwrite.
+    fn push_state_ops(
+        &mut self,
+        note: &Note,
+        ghost: bool,
+        existing: bool,
+        out: &mut Vec<Operation>,
+    ) -> Result<()> {
+        let mut ops = vec![Operation::CreateNote {
+            operation_id: String::new(),
+            timestamp: self.advance_hlc(),
+            device_id: self.device_id.clone(),
+            note_id: note.id.clone(),
+            parent_id: note.parent_id.clone(),
+            position: note.position,
+            schema: note.schema.clone(),
+            title: note.title.clone(),
+            fields: note.fields.clone(),
+            created_by: String::new(),
+            created_at: None,
+            modified_at: None,
+            original_author: Some(note.created_by.clone()),
+            prev_hash: None,
+            signature: String::new(),
+        }];
+        if existing {
+            ops.push(Operation::UpdateNote {
+                operation_id: String::new(),
+                timestamp: self.advance_hlc(),
+                device_id: self.device_id.clone(),
+                note_id: note.id.clone(),
+                title: note.title.clone(),
+                modified_by: note.modified_by.clone(),
+                prev_hash: None,
+                signature: String::new(),
+            });
+            ops.push(Operation::MoveNote {
+                operation_id: String::new(),
+                timestamp: self.advance_hlc(),
+                device_id: self.device_id.clone(),
+                note_id: note.id.clone(),
+                new_parent_id: note.parent_id.clone(),
+                new_position: note.position,
+                moved_by: note.modified_by.clone(),
+                prev_hash: None,
+                signature: String::new(),
+            });
+            for (field, value) in &note.fields {
+                ops.push(Operation::UpdateField {
+                    operation_id: String::new(),
+                    timestamp: self.advance_hlc(),
+                    device_id: self.device_id.clone(),
+                    note_id: note.id.clone(),
+                    field: field.clone(),
+                    value: value.clone(),
+                    modified_by: note.modified_by.clone(),
+                    prev_hash: None,
+                    signature: String::new(),
+                });
+            }
+        }
+        if !ghost || existing {
+            ops.push(Operation::SetTags {
+                operation_id: String::new(),
+                timestamp: self.advance_hlc(),
+                device_id: self.device_id.clone(),
+                note_id: note.id.clone(),
+                tags: note.tags.clone(),
+                modified_by: note.modified_by.clone(),
+                prev_hash: None,
+                signature: String::new(),
+            });
+            ops.push(Operation::SetChecked {
+                operation_id: String::new(),
+                timestamp: self.advance_hlc(),
+                device_id: self.device_id.clone(),
+                note_id: note.id.clone(),
+                checked: note.is_checked,
+                modified_by: note.modified_by.clone(),
+                prev_hash: None,
+                signature: String::new(),
+            });
+        }
+        if !ghost {
+            for meta in self.get_attachments(&note.id)? {
+                ops.push(Operation::AddAttachment {
+                    operation_id: String::new(),
+                    timestamp: self.advance_hlc(),
+                    device_id: self.device_id.clone(),
+                    attachment_id: meta.id,
+                    note_id: meta.note_id,
+                    filename: meta.filename,
+                    mime_type: meta.mime_type,
+                    size_bytes: meta.size_bytes,
+                    hash_sha256: meta.hash_sha256,
+                    added_by: note.created_by.clone(),
+                    prev_hash: None,
+                    signature: String::new(),
+                });
+            }
+        }
+        for op in ops {
+            out.push(self.sign_boundary_op(op));
+        }
+        Ok(())
+    }
+
+    /// Boundary operations removing a note (and the attachments we know it
+    /// has) from the recipient.
+    fn push_removal_ops(&mut self, note_id: &str, out: &mut Vec<Operation>) -> Result<()> {
+        for meta in self.get_attachments(note_id)? {
+            let op = Operation::RemoveAttachment {
+                operation_id: String::new(),
+                timestamp: self.advance_hlc(),
+                device_id: self.device_id.clone(),
+                attachment_id: meta.id,
+                note_id: note_id.to_string(),
+                removed_by: String::new(),
+                prev_hash: None,
+                signature: String::new(),
+            };
+            out.push(self.sign_boundary_op(op));
+        }
+        let op = Operation::DeleteNote {
+            operation_id: String::new(),
+            timestamp: self.advance_hlc(),
+            device_id: self.device_id.clone(),
+            note_id: note_id.to_string(),
+            deleted_by: String::new(),
+            prev_hash: None,
+            signature: String::new(),
+        };
+        out.push(self.sign_boundary_op(op));
+        Ok(())
+    }
+
+    fn sign_boundary_op(&self, mut op: Operation) -> Operation {
+        if let Operation::CreateNote { operation_id, .. }
+        | Operation::UpdateNote { operation_id, .. }
+        | Operation::UpdateField { operation_id, .. }
+        | Operation::DeleteNote { operation_id, .. }
+        | Operation::MoveNote { operation_id, .. }
+        | Operation::SetTags { operation_id, .. }
+        | Operation::SetChecked { operation_id, .. }
+        | Operation::AddAttachment { operation_id, .. }
+        | Operation::RemoveAttachment { operation_id, .. } = &mut op
+        {
+            *operation_id = Uuid::new_v4().to_string();
+        }
+        // Not chained: boundary operations are synthesised per peer and never
+        // logged (see the module docs).
+        op.sign(&self.signing_key);
+        op
+    }
+
+    /// Checks whether [`apply_scope_boundary_operation`](Self::apply_scope_boundary_operation)
+    /// would accept `op`. Returns `None` if it would.
+    ///
+    /// Besides the signature, the sender's key must not have been rotated out
+    /// and its device not revoked, as for logged operations.
+    pub fn check_scope_boundary_operation(
+        &self,
+        op: &Operation,
+        sender_identity: &str,
+    ) -> Result<Option<OpRejection>> {
+        if !matches!(op_target(op), OpTarget::Note { .. }) {
+            return Ok(Some(OpRejection::NotNoteOperation));
+        }
+        if op.author_key() != sender_identity || !op.verify_author() {
+            return Ok(Some(OpRejection::BadSignature));
+        }
+        self.check_operation_authority(op)
+    }
+
+    /// IDs of the notes an operation touches; empty for workspace-level
+    /// operations.
+    pub(crate) fn operation_note_ids(op: &Operation) -> Vec<&str> {
+        match op_target(op) {
+            OpTarget::Workspace => Vec::new(),
+            OpTarget::Note { note_id, .. } => vec![note_id],
+            OpTarget::Retract(ids) => ids,
+        }
+    }
+
+    /// Applies a boundary operation received in a delta (see the module docs)
+    /// to the working tables, without logging it.
+    ///
+    /// Only note-level operations signed by the bundle's sender are accepted
+    /// (see [`check_scope_boundary_operation`](Self::check_scope_boundary_operation)).
+    /// A `CreateNote` records its signed `original_author` as the note's
+    /// `created_by` rather than the sender. Returns `Ok(false)` if the
+    /// operation is rejected.
+    pub fn apply_scope_boundary_operation(
+        &mut self,
+        op: &Operation,
+        sender_identity: &str,
+        attachment_blobs: &[(String, Vec<u8>)],
+    ) -> Result<bool> {
+        if let Some(rejection) = self.check_scope_boundary_operation(op, sender_identity)? {
+            log::warn!(target: "krillnotes::sync",
+                "rejecting boundary op {} — {}", op.operation_id(), rejection.describe());
+            return Ok(false);
+        }
+        self.hlc.observe(op.timestamp());
+        match op {
+            Operation::CreateNote {
+                original_author: Some(author),
+                ..
+            } if !author.is_empty() => {
+                let mut op = op.clone();
+                if let Operation::CreateNote { created_by, .. } = &mut op {
+                    *created_by = author.clone();
+                }
+                self.apply_op_to_working_tables(&op, attachment_blobs)?;
+            }
+            _ => self.apply_op_to_working_tables(op, attachment_blobs)?,
+        }
+        Ok(true)
+    }
+}
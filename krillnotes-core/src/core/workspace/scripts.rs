@@ -104,6 +104,7 @@ impl Workspace {
             load_order: 0,
             enabled: true,
             created_by: self.current_identity_pubkey.clone(),
+            prev_hash: None,
             signature: String::new(),
         };
         self.authorize(&auth_op)?;
@@ -158,9 +159,10 @@ impl Workspace {
             load_order,
             enabled: true,
             created_by: String::new(),
+            prev_hash: None,
             signature: String::new(),
         };
-        Self::sign_op_with(&signing_key, &mut op);
+        Self::sign_op_with(&signing_key, &tx, &mut op)?;
         Self::log_op(&self.operation_log, &tx, &op)?;
         Self::purge_ops_if_needed(&self.operation_log, &tx)?;
 
@@ -209,6 +211,7 @@ impl Workspace {
             load_order: 0,
             enabled: true,
             modified_by: self.current_identity_pubkey.clone(),
+            prev_hash: None,
             signature: String::new(),
         };
         self.authorize(&auth_op)?;
@@ -275,9 +278,10 @@ impl Workspace {
             load_order,
             enabled,
             modified_by: String::new(),
+            prev_hash: None,
             signature: String::new(),
         };
-        Self::sign_op_with(&signing_key, &mut op);
+        Self::sign_op_with(&signing_key, &tx, &mut op)?;
         Self::log_op(&self.operation_log, &tx, &op)?;
         Self::purge_ops_if_needed(&self.operation_log, &tx)?;
 
@@ -321,6 +325,7 @@ impl Workspace {
             device_id: self.device_id.clone(),
             script_id: script_id.to_string(),
             deleted_by: self.current_identity_pubkey.clone(),
+            prev_hash: None,
             signature: String::new(),
         };
         self.authorize(&auth_op)?;
@@ -342,9 +347,10 @@ impl Workspace {
             device_id: self.device_id.clone(),
             script_id: script_id.to_string(),
             deleted_by: String::new(),
+            prev_hash: None,
             signature: String::new(),
         };
-        Self::sign_op_with(&signing_key, &mut op);
+        Self::sign_op_with(&signing_key, &tx, &mut op)?;
         Self::log_op(&self.operation_log, &tx, &op)?;
         Self::purge_ops_if_needed(&self.operation_log, &tx)?;
 
@@ -395,6 +401,7 @@ impl Workspace {
             load_order: 0,
             enabled,
             modified_by: self.current_identity_pubkey.clone(),
+            prev_hash: None,
             signature: String::new(),
         };
         self.authorize(&auth_op)?;
@@ -429,9 +436,10 @@ impl Workspace {
             load_order,
             enabled,
             modified_by: String::new(),
+            prev_hash: None,
             signature: String::new(),
         };
-        Self::sign_op_with(&signing_key, &mut op);
+        Self::sign_op_with(&signing_key, &tx, &mut op)?;
         Self::log_op(&self.operation_log, &tx, &op)?;
         Self::purge_ops_if_needed(&self.operation_log, &tx)?;
 
@@ -466,6 +474,7 @@ impl Workspace {
             load_order: new_load_order,
             enabled: true,
             modified_by: self.current_identity_pubkey.clone(),
+            prev_hash: None,
             signature: String::new(),
         };
         self.authorize(&auth_op)?;
@@ -507,9 +516,10 @@ impl Workspace {
             load_order: new_load_order,
             enabled,
             modified_by: String::new(),
+            prev_hash: None,
             signature: String::new(),
         };
-        Self::sign_op_with(&signing_key, &mut op);
+        Self::sign_op_with(&signing_key, &tx, &mut op)?;
         Self::log_op(&self.operation_log, &tx, &op)?;
         Self::purge_ops_if_needed(&self.operation_log, &tx)?;
 
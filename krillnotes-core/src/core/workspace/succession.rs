@@ -0,0 +1,182 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Identity key rotation — `RotateIdentityKey` operations, the
+//! `key_successions` table and the rejection of retired keys.
+
+use base64::Engine;
+use rusqlite::{Connection, OptionalExtension};
+
+use super::sync::OpRejection;
+use crate::core::error::{KrillnotesError, Result};
+use crate::core::operation::Operation;
+use crate::core::succession::SuccessionRecord;
+use crate::core::workspace::Workspace;
+
+impl Workspace {
+    /// Switches this workspace to `new_key` and announces the rotation to
+    /// peers with a `RotateIdentityKey` operation signed by it.
+    ///
+    /// `record` must be a valid succession statement naming `new_key` as the
+    /// successor of the workspace's identity key. A workspace already opened
+    /// with `new_key` (the rotation happened while it was closed) takes the
+    /// same record. Returns `Ok(false)` if the succession of
+    /// `record.old_key` is already known here, e.g. synced from another
+    /// device, in which case only the signing key is switched.
+    pub fn rotate_identity_key(
+        &mut self,
+        new_key: ed25519_dalek::SigningKey,
+        record: &SuccessionRecord,
+    ) -> Result<bool> {
+        let new_pubkey =
+            base64::engine::general_purpose::STANDARD.encode(new_key.verifying_key().as_bytes());
+        if !record.verify() {
+            return Err(KrillnotesError::InvalidSuccession(
+                "signature does not verify".into(),
+            ));
+        }
+        if record.new_key != new_pubkey {
+            return Err(KrillnotesError::InvalidSuccession(
+                "record names a different successor key".into(),
+            ));
+        }
+        if record.old_key != self.current_identity_pubkey
+            && record.new_key != self.current_identity_pubkey
+        {
+            return Err(KrillnotesError::InvalidSuccession(
+                "record does not retire this workspace's identity key".into(),
+            ));
+        }
+
+        if self.successor_of(&record.old_key)?.is_some() {
+            self.signing_key = new_key;
+            self.current_identity_pubkey = new_pubkey;
+            return Ok(false);
+        }
+
+        let ts = self.hlc.now();
+        let mut op = Operation::RotateIdentityKey {
+            operation_id: uuid::Uuid::new_v4().to_string(),
+            timestamp: ts,
+            device_id: self.device_id.clone(),
+            old_key: record.old_key.clone(),
+            new_key: String::new(),
+            rotated_at_ms: record.rotated_at_ms,
+            succession_signature: record.signature.clone(),
+            prev_hash: None,
+            signature: String::new(),
+        };
+        Self::sign_op_with(&new_key, self.storage.connection(), &mut op)?;
+        {
+            let tx = self.storage.connection_mut().transaction()?;
+            Self::save_hlc(&ts, &tx)?;
+            Self::log_op(&self.operation_log, &tx, &op)?;
+            tx.commit()?;
+        }
+        // Switch keys only once the rotation is on record, so a failed commit
+        // leaves the workspace signing with the key its peers still accept.
+        self.signing_key = new_key;
+        self.current_identity_pubkey = new_pubkey;
+        self.apply_op_to_working_tables(&op, &[])?;
+
+        log::info!(target: "krillnotes::sync",
+            "rotated identity key {}… → {}…", &record.old_key[..8.min(record.old_key.len())],
+            &record.new_key[..8.min(record.new_key.len())]);
+        Ok(true)
+    }
+
+    /// Returns the key that succeeded `key` and the rotation time (Unix ms),
+    /// if `key` has been rotated out.
+    pub fn successor_of(&self, key: &str) -> Result<Option<(String, u64)>> {
+        successor_of(self.storage.connection(), key)
+    }
+
+    /// Returns the keys that were rotated, directly or through intermediate
+    /// keys, to `key`.
+    pub fn predecessors_of(&self, key: &str) -> Result<Vec<String>> {
+        let mut stmt = self.storage.connection().prepare(
+            "WITH RECURSIVE chain(key) AS ( \
+                 SELECT ?1 \
+                 UNION SELECT s.old_key FROM key_successions s JOIN chain ON s.new_key = chain.key \
+             ) \
+             SELECT key FROM chain WHERE key != ?1",
+        )?;
+        let keys = stmt
+            .query_map([key], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(keys)
+    }
+
+    /// Rejects operations signed by a key after it was rotated out, and
+    /// rotations whose succession statement is forged or contradicts one
+    /// already recorded (the first rotation of a key wins).
+    ///
+    /// "After" compares the operation's HLC `wall_ms` with the rotation time,
+    /// and both are chosen by their signers: whoever still holds a retired
+    /// key can backdate operations to before its rotation and have them
+    /// accepted. Nothing unforgeable orders an operation against a rotation
+    /// it never saw, so this is not a hard cut-off for a leaked key: its
+    /// backdated operations look like ones genuinely written before the
+    /// rotation.
+    pub(super) fn check_key_succession(&self, op: &Operation) -> Result<Option<OpRejection>> {
+        let conn = self.storage.connection();
+        let author_key = op.author_key();
+        if !author_key.is_empty() {
+            if let Some((_, rotated_at_ms)) = successor_of(conn, author_key)? {
+                if op.timestamp().wall_ms > rotated_at_ms {
+                    return Ok(Some(OpRejection::RotatedKey));
+                }
+            }
+        }
+        if let Some(record) = SuccessionRecord::from_operation(op) {
+            if !record.verify() {
+                return Ok(Some(OpRejection::BadSuccession));
+            }
+            if let Some((successor, _)) = successor_of(conn, &record.old_key)? {
+                if successor != record.new_key {
+                    return Ok(Some(OpRejection::RotatedKey));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Records the succession carried by a `RotateIdentityKey` operation and
+    /// moves sync peer rows of the old key to the new one.
+    pub(super) fn record_key_succession(conn: &Connection, op: &Operation) -> Result<()> {
+        let Operation::RotateIdentityKey {
+            operation_id,
+            old_key,
+            new_key,
+            rotated_at_ms,
+            ..
+        } = op
+        else {
+            return Ok(());
+        };
+        conn.execute(
+            "INSERT OR IGNORE INTO key_successions (old_key, new_key, rotated_at_ms, operation_id) \
+             VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![old_key, new_key, *rotated_at_ms as i64, operation_id],
+        )?;
+        conn.execute(
+            "UPDATE sync_peers SET peer_identity_id = ?2 WHERE peer_identity_id = ?1",
+            rusqlite::params![old_key, new_key],
+        )?;
+        Ok(())
+    }
+}
+
+fn successor_of(conn: &Connection, key: &str) -> Result<Option<(String, u64)>> {
+    let row = conn
+        .query_row(
+            "SELECT new_key, rotated_at_ms FROM key_successions WHERE old_key = ?1",
+            [key],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64)),
+        )
+        .optional()?;
+    Ok(row)
+}
@@ -7,9 +7,57 @@
 //! Snapshot sync, delta operations, and peer registry management.
 
 use super::*;
+use crate::core::integrity::ChainBreakKind;
+use crate::core::operation_log::record_chain_link;
 use crate::core::peer_registry::SyncPeer;
 use crate::core::sync::channel::{ChannelType, PeerSyncInfo};
+use crate::core::workspace::permissions::ReadScope;
 use base64::Engine as _;
+use serde::Serialize;
+
+/// Why an operation received from a peer is not applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OpRejection {
+    /// A retract that must stay on the device that made it.
+    LocalOnly,
+    /// No author key, and the sender does not vouch for it.
+    Unattributed,
+    /// Authored by the sender, but the signature does not verify.
+    BadSignature,
+    /// Relayed, but vouched for by someone other than the sender.
+    VoucherMismatch,
+    /// Relayed without the sender vouching for it.
+    Unvouched,
+    /// A scope boundary operation that is not a note operation.
+    NotNoteOperation,
+    /// A key rotation whose succession statement does not verify.
+    BadSuccession,
+    /// Signed by a key that was rotated out before the operation's timestamp.
+    RotatedKey,
+    /// From a device its author revoked before the operation's timestamp.
+    RevokedDevice,
+    /// A Root Ownership transfer whose recovery signature does not verify.
+    BadRecovery,
+}
+
+impl OpRejection {
+    /// Human-readable reason, for logs.
+    pub fn describe(self) -> &'static str {
+        match self {
+            Self::LocalOnly => "local-only retract",
+            Self::Unattributed => "no author key and not vouched by sender",
+            Self::BadSignature => "sender-authored but signature invalid",
+            Self::VoucherMismatch => "verified_by doesn't match sender",
+            Self::Unvouched => "relayed without vouching",
+            Self::NotNoteOperation => "not a note operation",
+            Self::BadSuccession => "key rotation with an invalid succession statement",
+            Self::RotatedKey => "signed by a rotated-out identity key",
+            Self::RevokedDevice => "from a revoked device",
+            Self::BadRecovery => "ownership transfer with an invalid recovery signature",
+        }
+    }
+}
 
 impl Workspace {
     // ── Snapshot (peer sync) ───────────────────────────────────────
@@ -17,22 +65,63 @@ impl Workspace {
     /// Serialise all notes, user scripts, attachment metadata, and permission
     /// operations to JSON bytes for a snapshot bundle.
     pub fn to_snapshot_json(&self) -> Result<Vec<u8>> {
+        self.snapshot_json(None)
+    }
+
+    /// Like [`to_snapshot_json`](Self::to_snapshot_json), but limited to what
+    /// `recipient` (base64 public key) may read.
+    ///
+    /// Notes outside the recipient's [`ReadScope`] are omitted, ghost ancestors
+    /// are reduced to title and tree position, and only attachments of fully
+    /// readable notes are listed. Scripts and permission operations are always
+    /// included. Returns the scope used (`None` = unfiltered) so the caller can
+    /// record it with [`record_peer_sent_scope_by_identity`](Self::record_peer_sent_scope_by_identity).
+    pub fn to_snapshot_json_for(&self, recipient: &str) -> Result<(Vec<u8>, Option<ReadScope>)> {
+        let scope = self.read_scope_for(recipient)?;
+        Ok((self.snapshot_json(scope.as_ref())?, scope))
+    }
+
+    fn snapshot_json(&self, scope: Option<&ReadScope>) -> Result<Vec<u8>> {
         log::info!(target: "krillnotes::sync", "generating snapshot JSON");
-        let notes = self.list_all_notes()?;
+        Ok(serde_json::to_vec(&self.snapshot(scope)?)?)
+    }
+
+    /// The workspace state sent in snapshots, limited to `scope` if given.
+    pub(super) fn snapshot(&self, scope: Option<&ReadScope>) -> Result<WorkspaceSnapshot> {
+        let mut notes = self.list_all_notes()?;
         let user_scripts = self.list_user_scripts()?;
-        let attachments = self.list_all_attachments()?;
+        let mut attachments = self.list_all_attachments()?;
         let permission_ops = self.collect_permission_ops()?;
+        if let Some(scope) = scope {
+            notes.retain(|note| scope.contains(&note.id));
+            for note in &mut notes {
+                if !scope.full.contains(&note.id) {
+                    *note = Self::ghost_note(note);
+                }
+            }
+            attachments.retain(|meta| scope.full.contains(&meta.note_id));
+        }
         log::debug!(target: "krillnotes::sync",
             "snapshot: {} notes, {} scripts, {} attachments, {} permission ops",
             notes.len(), user_scripts.len(), attachments.len(), permission_ops.len());
-        let snapshot = WorkspaceSnapshot {
+        Ok(WorkspaceSnapshot {
             version: 1,
             notes,
             user_scripts,
             attachments,
             permission_ops,
-        };
-        Ok(serde_json::to_vec(&snapshot)?)
+        })
+    }
+
+    /// The structure-only view of a note sent as a ghost ancestor: title and
+    /// tree position survive, fields, tags and the checked state do not.
+    pub(crate) fn ghost_note(note: &Note) -> Note {
+        Note {
+            fields: BTreeMap::new(),
+            tags: Vec::new(),
+            is_checked: false,
+            ..note.clone()
+        }
     }
 
     /// Query all SetPermission / RevokePermission operations from the log,
@@ -41,7 +130,7 @@ impl Workspace {
         let conn = self.storage.connection();
         let mut stmt = conn.prepare(
             "SELECT operation_data FROM operations \
-             WHERE operation_type IN ('SetPermission', 'RevokePermission') \
+             WHERE operation_type IN ('SetPermission', 'RevokePermission', 'RotateIdentityKey', 'RevokeDevice', 'SetRootRecovery') \
              ORDER BY timestamp_wall_ms ASC, timestamp_counter ASC, timestamp_node_id ASC",
         )?;
         let ops = stmt.query_map([], |row| {
@@ -206,63 +295,71 @@ impl Workspace {
         &self,
         since_op_id: Option<&str>,
         exclude_device_id: &str,
+    ) -> Result<Vec<(Operation, String)>> {
+        // A watermark op missing from the log falls back to sending
+        // everything, as in `operations_since`.
+        let after = match since_op_id {
+            Some(op_id) => self.operation_timestamp(op_id)?,
+            None => None,
+        };
+        self.operations_after_with_verified_by(after, exclude_device_id)
+    }
+
+    /// HLC timestamp of a logged operation, or `None` if it is not in the log.
+    pub(super) fn operation_timestamp(&self, operation_id: &str) -> Result<Option<HlcTimestamp>> {
+        let row: Option<(i64, i64, i64)> = self
+            .storage
+            .connection()
+            .query_row(
+                "SELECT timestamp_wall_ms, timestamp_counter, timestamp_node_id \
+                 FROM operations WHERE operation_id = ?1",
+                [operation_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+        Ok(row.map(|(wall_ms, counter, node_id)| HlcTimestamp {
+            wall_ms: wall_ms as u64,
+            counter: counter as u32,
+            node_id: node_id as u32,
+        }))
+    }
+
+    /// Operations strictly after `after` (all if `None`) with their
+    /// `verified_by` column, minus the peer's own and echoed ones and
+    /// local-only retracts.
+    pub(super) fn operations_after_with_verified_by(
+        &self,
+        after: Option<HlcTimestamp>,
+        exclude_device_id: &str,
     ) -> Result<Vec<(Operation, String)>> {
         let conn = self.storage.connection();
 
-        let rows: Vec<(String, String)> = if let Some(op_id) = since_op_id {
-            let hlc_row: Option<(i64, i64, i64)> = conn
-                .query_row(
-                    "SELECT timestamp_wall_ms, timestamp_counter, timestamp_node_id \
-                     FROM operations WHERE operation_id = ?1",
-                    [op_id],
-                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
-                )
-                .optional()
+        let rows: Vec<(String, String)> = if let Some(after) = after {
+            let mut stmt = conn.prepare(
+                "SELECT operation_data, COALESCE(verified_by, '') FROM operations \
+                 WHERE ((timestamp_wall_ms > ?1) \
+                    OR  (timestamp_wall_ms = ?1 AND timestamp_counter > ?2) \
+                    OR  (timestamp_wall_ms = ?1 AND timestamp_counter = ?2 \
+                         AND timestamp_node_id > ?3)) \
+                 AND device_id != ?4 \
+                 AND (received_from_peer IS NULL OR received_from_peer != ?5) \
+                 ORDER BY timestamp_wall_ms ASC, timestamp_counter ASC, \
+                          timestamp_node_id ASC",
+            )?;
+            let rows = stmt
+                .query_map(
+                    rusqlite::params![
+                        after.wall_ms as i64,
+                        after.counter as i64,
+                        after.node_id as i64,
+                        exclude_device_id,
+                        exclude_device_id
+                    ],
+                    |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+                )?
+                .collect::<rusqlite::Result<Vec<_>>>()
                 .map_err(KrillnotesError::Database)?;
-
-            if let Some((wall_ms, counter, node_id)) = hlc_row {
-                let mut stmt = conn.prepare(
-                    "SELECT operation_data, COALESCE(verified_by, '') FROM operations \
-                     WHERE ((timestamp_wall_ms > ?1) \
-                        OR  (timestamp_wall_ms = ?1 AND timestamp_counter > ?2) \
-                        OR  (timestamp_wall_ms = ?1 AND timestamp_counter = ?2 \
-                             AND timestamp_node_id > ?3)) \
-                     AND device_id != ?4 \
-                     AND (received_from_peer IS NULL OR received_from_peer != ?5) \
-                     ORDER BY timestamp_wall_ms ASC, timestamp_counter ASC, \
-                              timestamp_node_id ASC",
-                )?;
-                let rows = stmt
-                    .query_map(
-                        rusqlite::params![
-                            wall_ms,
-                            counter,
-                            node_id,
-                            exclude_device_id,
-                            exclude_device_id
-                        ],
-                        |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
-                    )?
-                    .collect::<rusqlite::Result<Vec<_>>>()
-                    .map_err(KrillnotesError::Database)?;
-                rows
-            } else {
-                let mut stmt = conn.prepare(
-                    "SELECT operation_data, COALESCE(verified_by, '') FROM operations \
-                     WHERE device_id != ?1 \
-                     AND (received_from_peer IS NULL OR received_from_peer != ?2) \
-                     ORDER BY timestamp_wall_ms ASC, timestamp_counter ASC, \
-                              timestamp_node_id ASC",
-                )?;
-                let rows = stmt
-                    .query_map(
-                        rusqlite::params![exclude_device_id, exclude_device_id],
-                        |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
-                    )?
-                    .collect::<rusqlite::Result<Vec<_>>>()
-                    .map_err(KrillnotesError::Database)?;
-                rows
-            }
+            rows
         } else {
             let mut stmt = conn.prepare(
                 "SELECT operation_data, COALESCE(verified_by, '') FROM operations \
@@ -300,22 +397,20 @@ impl Workspace {
         Ok(ops)
     }
 
-    /// Apply a single operation received from a remote peer.
+    /// Checks whether an operation received from `sender_identity` would be
+    /// accepted by [`apply_incoming_operation`](Self::apply_incoming_operation),
+    /// without touching the workspace.
     ///
-    /// Returns `Ok(true)` if the operation was inserted and applied to the working tables,
-    /// or `Ok(false)` if it was skipped (duplicate or local-only retract).
-    ///
-    /// Idempotent: calling this twice with the same operation is safe — the second call
-    /// returns `Ok(false)` without modifying any data.
-    pub fn apply_incoming_operation(
-        &mut self,
-        op: Operation,
-        received_from_peer: &str,
-        attachment_blobs: &[(String, Vec<u8>)],
+    /// Returns `Ok(None)` if it passes the signature, vouch, key
+    /// succession, device revocation and root recovery checks.
+    /// Duplicates are not detected here.
+    pub fn check_incoming_operation(
+        &self,
+        op: &Operation,
         verified_by: Option<&str>,
         sender_identity: &str,
-    ) -> Result<bool> {
-        // 1. Skip local-only retracts — they must never cross device boundaries.
+    ) -> Result<Option<OpRejection>> {
+        // Local-only retracts must never cross device boundaries.
         if matches!(
             op,
             Operation::RetractOperation {
@@ -323,32 +418,13 @@ impl Workspace {
                 ..
             }
         ) {
-            log::debug!(target: "krillnotes::sync", "skipping local-only retract operation {}", op.operation_id());
-            return Ok(false);
+            return Ok(Some(OpRejection::LocalOnly));
         }
 
-        // No RBAC gate on inbound operations — all ops replicate unconditionally.
-        // Access control is enforced at the visibility layer (visible_note_ids,
-        // list_notes) not at the replication layer.
-
-        log::debug!(target: "krillnotes::sync", "applying incoming operation {} ({})", op.operation_id(), Self::operation_type_str(&op));
-
-        // 2. Advance the local HLC by observing the incoming timestamp.
-        self.hlc.observe(op.timestamp());
-
-        // 3. Per-op verification — reject ops that fail signature or vouch checks.
         let author_key = op.author_key();
-        let resolved_verified_by: String = if author_key.is_empty() {
+        let rejection = if author_key.is_empty() {
             // Ops with no author key (RetractOperation) — accept only if vouched
-            match verified_by {
-                Some(vb) if vb == sender_identity => sender_identity.to_string(),
-                _ => {
-                    log::warn!(target: "krillnotes::sync",
-                        "rejecting op {} — no author key and not vouched by sender",
-                        op.operation_id());
-                    return Ok(false);
-                }
-            }
+            (verified_by != Some(sender_identity)).then_some(OpRejection::Unattributed)
         } else if author_key == sender_identity {
             // Sender-authored: verify the original Ed25519 signature
             let vk_bytes = base64::engine::general_purpose::STANDARD
@@ -359,30 +435,74 @@ impl Workspace {
                 .map_err(|_| KrillnotesError::Swarm("sender identity wrong length".into()))?;
             let vk = ed25519_dalek::VerifyingKey::from_bytes(&vk_arr)
                 .map_err(|e| KrillnotesError::Swarm(format!("invalid sender key: {e}")))?;
-
-            if !op.verify(&vk) {
-                log::warn!(target: "krillnotes::sync",
-                    "rejecting op {} — sender-authored but signature invalid",
-                    op.operation_id());
-                return Ok(false);
+            (!op.verify(&vk)).then_some(OpRejection::BadSignature)
+        } else {
+            // Relayed op — the sender must vouch for it
+            match verified_by {
+                Some(vb) if vb == sender_identity => None,
+                Some(_) => Some(OpRejection::VoucherMismatch),
+                None => Some(OpRejection::Unvouched),
             }
-            sender_identity.to_string()
-        } else if let Some(vb) = verified_by {
-            // Relayed op with vouch — sender must be the voucher
-            if vb != sender_identity {
+        };
+        if rejection.is_some() {
+            return Ok(rejection);
+        }
+        self.check_operation_authority(op)
+    }
+
+    /// Checks that apply to every operation from another device, whatever
+    /// path it arrived by, once its signature or voucher is accepted:
+    /// retired identity keys, revoked devices and recovery-authorised
+    /// ownership transfers.
+    pub(super) fn check_operation_authority(&self, op: &Operation) -> Result<Option<OpRejection>> {
+        if let Some(rejection) = self.check_key_succession(op)? {
+            return Ok(Some(rejection));
+        }
+        if let Some(rejection) = self.check_device_revocation(op)? {
+            return Ok(Some(rejection));
+        }
+        self.check_root_recovery(op)
+    }
+
+    /// Apply a single operation received from a remote peer.
+    ///
+    /// Returns `Ok(true)` if the operation was inserted and applied to the working tables,
+    /// or `Ok(false)` if it was skipped (duplicate or local-only retract).
+    ///
+    /// Idempotent: calling this twice with the same operation is safe — the second call
+    /// returns `Ok(false)` without modifying any data.
+    pub fn apply_incoming_operation(
+        &mut self,
+        op: Operation,
+        received_from_peer: &str,
+        attachment_blobs: &[(String, Vec<u8>)],
+        verified_by: Option<&str>,
+        sender_identity: &str,
+    ) -> Result<bool> {
+        // 1. Skip local-only retracts and reject ops that fail signature or
+        //    vouch checks.
+        if let Some(rejection) = self.check_incoming_operation(&op, verified_by, sender_identity)? {
+            if rejection == OpRejection::LocalOnly {
+                log::debug!(target: "krillnotes::sync", "skipping local-only retract operation {}", op.operation_id());
+            } else {
                 log::warn!(target: "krillnotes::sync",
-                    "rejecting op {} — verified_by doesn't match sender",
-                    op.operation_id());
-                return Ok(false);
+                    "rejecting op {} — {}", op.operation_id(), rejection.describe());
             }
-            sender_identity.to_string()
-        } else {
-            // Relayed op with no vouch — reject
-            log::warn!(target: "krillnotes::sync",
-                "rejecting op {} — relayed without vouching",
-                op.operation_id());
             return Ok(false);
-        };
+        }
+
+        // No RBAC gate on inbound operations — all ops replicate unconditionally.
+        // Access control is enforced at the visibility layer (visible_note_ids,
+        // list_notes) not at the replication layer.
+
+        log::debug!(target: "krillnotes::sync", "applying incoming operation {} ({})", op.operation_id(), Self::operation_type_str(&op));
+
+        // 2. Advance the local HLC by observing the incoming timestamp.
+        self.hlc.observe(op.timestamp());
+
+        // 3. Every accepted op is attributed to the sender, either as its
+        //    author or as the peer vouching for it.
+        let resolved_verified_by = sender_identity;
 
         // 4. Insert into the operations log with synced = 1.
         //    INSERT OR IGNORE gives 0 changed rows if the operation_id already exists.
@@ -390,7 +510,7 @@ impl Workspace {
         let ts = op.timestamp();
         let op_type = Self::operation_type_str(&op);
 
-        let rows = {
+        let (rows, chain_break) = {
             let tx = self.storage.connection_mut().transaction()?;
             let rows = tx.execute(
                 "INSERT OR IGNORE INTO operations \
@@ -406,11 +526,17 @@ impl Workspace {
                     op_type,
                     op_json,
                     received_from_peer,
-                    &resolved_verified_by,
+                    resolved_verified_by,
                 ],
             )?;
+            let chain_break = if rows > 0 {
+                super::integrity::find_chain_break(&tx, &op)?
+            } else {
+                None
+            };
+            record_chain_link(&tx, &op)?;
             tx.commit()?;
-            rows
+            (rows, chain_break)
         };
 
         // 5. Duplicate — already applied.
@@ -419,7 +545,31 @@ impl Workspace {
             return Ok(false);
         }
 
-        // 6. Apply the state change to working tables.
+        // 6. Report breaks in the author's hash chain. Operations still apply:
+        //    they may just have arrived ahead of their predecessor.
+        if let Some(chain_break) = chain_break {
+            if chain_break.kind == ChainBreakKind::Fork || self.receives_full_chains()? {
+                let detail = chain_break.describe();
+                log::warn!(target: "krillnotes::sync", "hash chain break: {detail}");
+                self.log_sync_event(sender_identity, chain_break.event_type(), Some(&detail))?;
+            }
+        }
+
+        // 7. Apply the state change to working tables.
+        self.apply_op_to_working_tables(&op, attachment_blobs)?;
+
+        log::debug!(target: "krillnotes::sync", "operation {} applied successfully", op.operation_id());
+        Ok(true)
+    }
+
+    /// Applies an operation's state change to the working tables (notes, tags,
+    /// scripts, permissions, attachments) without touching the operation log.
+    pub(super) fn apply_op_to_working_tables(
+        &mut self,
+        op: &Operation,
+        attachment_blobs: &[(String, Vec<u8>)],
+    ) -> Result<()> {
+        let ts = op.timestamp();
         let mut scripts_changed = false;
         // (attachment_id, note_id, filename, mime_type, blob)
         #[allow(clippy::type_complexity)]
@@ -431,8 +581,13 @@ impl Workspace {
             Vec<u8>,
         )> = None;
         let mut pending_attachment_delete: Option<String> = None;
+        // AddAttachment without inline content: fetched by hash after commit.
+        let mut download_content = false;
+        // RotateIdentityKey of the Root Owner or recovery-authorised
+        // TransferRootOwnership: new owner key, set after commit.
+        let mut rotated_owner: Option<String> = None;
         let tx = self.storage.connection_mut().transaction()?;
-        match &op {
+        match op {
             Operation::CreateNote {
                 note_id,
                 title,
@@ -440,11 +595,15 @@ impl Workspace {
                 parent_id,
                 position,
                 created_by,
+                created_at,
+                modified_at,
                 fields,
                 ..
             } => {
                 let fields_json = serde_json::to_string(fields)?;
                 let ts_secs = ts.to_unix_secs();
+                let created_at = created_at.unwrap_or(ts_secs);
+                let modified_at = modified_at.unwrap_or(ts_secs);
                 tx.execute(
                     "INSERT OR IGNORE INTO notes \
                      (id, title, schema, parent_id, position, created_at, modified_at, \
@@ -452,7 +611,7 @@ impl Workspace {
                      VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 1, 1, 0)",
                     rusqlite::params![
                         note_id, title, schema, parent_id, position,
-                        ts_secs, ts_secs, created_by, created_by, fields_json,
+                        created_at, modified_at, created_by, created_by, fields_json,
                     ],
                 )?;
             }
@@ -605,7 +764,41 @@ impl Workspace {
 
             // Permission-modifying operations: apply through the gate.
             Operation::SetPermission { .. } | Operation::RevokePermission { .. } => {
-                Self::apply_permission_op_via(&*self.permission_gate, &tx, &op)?;
+                Self::apply_permission_op_via(&*self.permission_gate, &tx, op)?;
+            }
+
+            // Record the succession, then move the old key's grants and
+            // peer rows over to the new key.
+            Operation::RotateIdentityKey {
+                old_key, new_key, ..
+            } => {
+                Self::record_key_succession(&tx, op)?;
+                Self::apply_permission_op_via(&*self.permission_gate, &tx, op)?;
+                if *old_key == self.owner_pubkey {
+                    rotated_owner = Some(new_key.clone());
+                }
+            }
+
+            Operation::RevokeDevice { .. } => {
+                Self::record_device_revocation(&tx, op)?;
+            }
+
+            Operation::SetRootRecovery { .. } => {
+                Self::record_root_recovery(&tx, &self.owner_pubkey, op)?;
+            }
+
+            // Owner-signed transfers stay log-only; a transfer authorised by
+            // recovery shares consumes the recovery key.
+            Operation::TransferRootOwnership { .. } => {
+                if let Some(new_owner) = super::root_recovery::recovered_owner(
+                    &tx,
+                    &self.workspace_id,
+                    &self.owner_pubkey,
+                    op,
+                )? {
+                    Self::clear_root_recovery(&tx)?;
+                    rotated_owner = Some(new_owner);
+                }
             }
 
             // Log-only variants — no working table change in this phase.
@@ -613,7 +806,6 @@ impl Workspace {
             | Operation::UpdateSchema { .. }
             | Operation::RetractOperation { .. }
             | Operation::RemovePeer { .. }
-            | Operation::TransferRootOwnership { .. }
             | Operation::RegisterDevice { .. } => {}
 
             Operation::AddAttachment {
@@ -640,9 +832,7 @@ impl Workspace {
                             blob.clone(),
                         ));
                     } else {
-                        log::warn!(target: "krillnotes::sync",
-                            "AddAttachment {} has no matching blob in delta, recording op only",
-                            attachment_id);
+                        download_content = true;
                     }
                 } else {
                     log::warn!(target: "krillnotes::sync",
@@ -653,11 +843,19 @@ impl Workspace {
 
             Operation::RemoveAttachment { attachment_id, .. } => {
                 tx.execute("DELETE FROM attachments WHERE id = ?1", [attachment_id])?;
+                tx.execute(
+                    "DELETE FROM pending_attachments WHERE id = ?1",
+                    [attachment_id],
+                )?;
                 pending_attachment_delete = Some(attachment_id.clone());
             }
         }
         tx.commit()?;
 
+        if let Some(new_owner) = rotated_owner {
+            self.set_owner_pubkey(&new_owner)?;
+        }
+
         // Deferred attachment file write (after state-mutation tx is committed).
         //
         // TODO: split-transaction window — `attach_file_with_id` both encrypts the file
@@ -676,6 +874,31 @@ impl Workspace {
             }
         }
 
+        if let Operation::AddAttachment {
+            attachment_id,
+            note_id,
+            filename,
+            mime_type,
+            size_bytes,
+            hash_sha256,
+            ..
+        } = op
+        {
+            if download_content {
+                if let Err(e) = self.receive_attachment_metadata(
+                    attachment_id,
+                    note_id,
+                    filename,
+                    mime_type.as_deref(),
+                    *size_bytes,
+                    hash_sha256,
+                ) {
+                    log::error!(target: "krillnotes::sync",
+                        "Failed to queue attachment content {}: {e}", attachment_id);
+                }
+            }
+        }
+
         // NOTE: DB row deleted in transaction above; file deletion below is best-effort.
         // If process crashes between the two, an orphan .enc file may remain on disk.
         // This is acceptable: the operation is in the log and will be replayed; orphans
@@ -707,12 +930,11 @@ impl Workspace {
             self.reload_scripts()?;
         }
 
-        log::debug!(target: "krillnotes::sync", "operation {} applied successfully", op.operation_id());
-        Ok(true)
+        Ok(())
     }
 
     /// Returns the `operation_type` string for a given `Operation` variant.
-    fn operation_type_str(op: &Operation) -> &'static str {
+    pub(crate) fn operation_type_str(op: &Operation) -> &'static str {
         match op {
             Operation::CreateNote { .. } => "CreateNote",
             Operation::UpdateNote { .. } => "UpdateNote",
@@ -734,6 +956,9 @@ impl Workspace {
             Operation::RemoveAttachment { .. } => "RemoveAttachment",
             Operation::RegisterDevice { .. } => "RegisterDevice",
             Operation::SetChecked { .. } => "SetChecked",
+            Operation::RotateIdentityKey { .. } => "RotateIdentityKey",
+            Operation::RevokeDevice { .. } => "RevokeDevice",
+            Operation::SetRootRecovery { .. } => "SetRootRecovery",
         }
     }
 
@@ -815,38 +1040,78 @@ impl Workspace {
 
         // Replay permission operations through the gate so the recipient
         // can see the notes they've been granted access to.
-        if !snapshot.permission_ops.is_empty() {
-            log::info!(target: "krillnotes::sync",
-                "replaying {} permission ops from snapshot", snapshot.permission_ops.len());
+        self.replay_snapshot_permission_ops(&snapshot.permission_ops)?;
+
+        log::info!(target: "krillnotes::sync", "snapshot import complete: {} notes", note_count);
+        Ok(note_count)
+    }
+
+    /// Logs permission operations received in a snapshot (as synced, from
+    /// `'snapshot'`) and applies them through the permission gate.
+    ///
+    /// The snapshot's sender merely relays these operations, so each one must
+    /// carry a valid signature by its own author and pass the same key
+    /// succession, device revocation and recovery checks as an incoming
+    /// operation. Operations that fail are skipped.
+    pub(super) fn replay_snapshot_permission_ops(
+        &mut self,
+        permission_ops: &[Operation],
+    ) -> Result<()> {
+        if permission_ops.is_empty() {
+            return Ok(());
+        }
+        log::info!(target: "krillnotes::sync",
+            "replaying {} permission ops from snapshot", permission_ops.len());
+        for op in permission_ops {
+            let rejection = if op.verify_author() {
+                self.check_operation_authority(op)?
+            } else {
+                Some(OpRejection::BadSignature)
+            };
+            if let Some(rejection) = rejection {
+                log::warn!(target: "krillnotes::sync",
+                    "skipping snapshot op {} — {}", op.operation_id(), rejection.describe());
+                continue;
+            }
+
+            // Each op commits on its own so that later ops are checked
+            // against the successions and revocations recorded before them.
             let tx = self.storage.connection_mut().transaction()?;
-            for op in &snapshot.permission_ops {
-                // Log the operation so it can be forwarded via future delta syncs.
-                let op_json = serde_json::to_string(op)?;
-                let ts = op.timestamp();
-                let op_type = Self::operation_type_str(op);
-                tx.execute(
-                    "INSERT OR IGNORE INTO operations \
-                     (operation_id, timestamp_wall_ms, timestamp_counter, timestamp_node_id, \
-                      device_id, operation_type, operation_data, synced, received_from_peer) \
-                     VALUES (?, ?, ?, ?, ?, ?, ?, 1, 'snapshot')",
-                    rusqlite::params![
-                        op.operation_id(),
-                        ts.wall_ms as i64,
-                        ts.counter as i64,
-                        ts.node_id as i64,
-                        op.device_id(),
-                        op_type,
-                        op_json,
-                    ],
-                )?;
-                // Apply through the permission gate.
-                Self::apply_permission_op_via(&*self.permission_gate, &tx, op)?;
+            // Log the operation so it can be forwarded via future delta syncs.
+            let op_json = serde_json::to_string(op)?;
+            let ts = op.timestamp();
+            let op_type = Self::operation_type_str(op);
+            tx.execute(
+                "INSERT OR IGNORE INTO operations \
+                 (operation_id, timestamp_wall_ms, timestamp_counter, timestamp_node_id, \
+                  device_id, operation_type, operation_data, synced, received_from_peer) \
+                 VALUES (?, ?, ?, ?, ?, ?, ?, 1, 'snapshot')",
+                rusqlite::params![
+                    op.operation_id(),
+                    ts.wall_ms as i64,
+                    ts.counter as i64,
+                    ts.node_id as i64,
+                    op.device_id(),
+                    op_type,
+                    op_json,
+                ],
+            )?;
+            match op {
+                Operation::RevokeDevice { .. } => Self::record_device_revocation(&tx, op)?,
+                Operation::SetRootRecovery { .. } => {
+                    Self::record_root_recovery(&tx, &self.owner_pubkey, op)?
+                }
+                _ => {
+                    if matches!(op, Operation::RotateIdentityKey { .. }) {
+                        Self::record_key_succession(&tx, op)?;
+                    }
+                    // Apply through the permission gate.
+                    Self::apply_permission_op_via(&*self.permission_gate, &tx, op)?;
+                }
             }
             tx.commit()?;
         }
-
-        log::info!(target: "krillnotes::sync", "snapshot import complete: {} notes", note_count);
-        Ok(note_count)
+        Ok(())
     }
 
     /// Returns a resolved view of all sync peers for this workspace, joining
@@ -943,6 +1208,38 @@ impl Workspace {
         registry.upsert_last_sent(&placeholder_device_id, identity_pk, op_id)
     }
 
+    /// The read scope last delivered to a peer, or `None` if it has only
+    /// received unfiltered bundles (or none that recorded a scope).
+    pub fn get_peer_sent_scope(&self, peer_device_id: &str) -> Result<Option<ReadScope>> {
+        PeerRegistry::new(self.storage.connection())
+            .get_sent_scope(peer_device_id)?
+            .map(|json| serde_json::from_str(&json).map_err(KrillnotesError::Json))
+            .transpose()
+    }
+
+    /// Record the read scope delivered to a peer. Call only once the bundle
+    /// built for that scope is known to have been delivered.
+    pub fn record_peer_sent_scope(
+        &self,
+        peer_device_id: &str,
+        scope: Option<&ReadScope>,
+    ) -> Result<()> {
+        let json = scope.map(serde_json::to_string).transpose()?;
+        PeerRegistry::new(self.storage.connection()).set_sent_scope(peer_device_id, json.as_deref())
+    }
+
+    /// Record the read scope delivered to every device of `identity_pk`,
+    /// e.g. after sending a snapshot.
+    pub fn record_peer_sent_scope_by_identity(
+        &self,
+        identity_pk: &str,
+        scope: Option<&ReadScope>,
+    ) -> Result<()> {
+        let json = scope.map(serde_json::to_string).transpose()?;
+        PeerRegistry::new(self.storage.connection())
+            .set_sent_scope_by_identity(identity_pk, json.as_deref())
+    }
+
     /// Retrieve a sync peer by device ID.
     pub fn get_sync_peer(
         &self,
@@ -1132,6 +1429,10 @@ impl Workspace {
                 channel_type: match p.channel_type.as_str() {
                     "relay" => ChannelType::Relay,
                     "folder" => ChannelType::Folder,
+                    "webdav" => ChannelType::WebDav,
+                    "s3" => ChannelType::S3,
+                    "git" => ChannelType::Git,
+                    "lan" => ChannelType::Lan,
                     _ => ChannelType::Manual,
                 },
                 channel_params: serde_json::from_str(&p.channel_params)
@@ -2765,6 +2765,47 @@ fn test_get_attachment_bytes_decrypts_correctly() {
     assert_eq!(recovered, data as &[u8]);
 }
 
+#[test]
+fn test_attach_file_from_reader_streams_multi_frame_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("notes.db");
+    let mut ws = Workspace::create(
+        &db_path,
+        "testpass",
+        "test-identity",
+        ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]),
+        test_gate(),
+        None,
+    )
+    .unwrap();
+    let root_id = ws.list_all_notes().unwrap()[0].id.clone();
+
+    let data: Vec<u8> = (0..200_000u32).map(|i| (i % 253) as u8).collect();
+    let meta = ws
+        .attach_file_from_reader(&root_id, "video.bin", None, &data[..], None)
+        .unwrap();
+    assert_eq!(meta.size_bytes, data.len() as i64);
+
+    let mut out = Vec::new();
+    let written = ws.copy_attachment_to(&meta.id, &mut out).unwrap();
+    assert_eq!(written, data.len() as u64);
+    assert_eq!(out, data);
+
+    // The size limit is enforced while streaming and leaves no partial file behind.
+    ws.set_attachment_max_size_bytes(Some(1000)).unwrap();
+    let result = ws.attach_file_from_reader(&root_id, "big.bin", None, &data[..], None);
+    assert!(matches!(
+        result,
+        Err(KrillnotesError::AttachmentTooLarge { limit: 1000, .. })
+    ));
+    let leftovers: Vec<_> = std::fs::read_dir(dir.path().join("attachments"))
+        .unwrap()
+        .flatten()
+        .filter(|e| e.path().extension().and_then(|x| x.to_str()) == Some("part"))
+        .collect();
+    assert!(leftovers.is_empty());
+}
+
 #[test]
 fn test_get_attachments_returns_metadata_list() {
     let dir = tempfile::tempdir().unwrap();
@@ -4585,7 +4626,7 @@ fn test_snapshot_includes_permission_ops() {
     let root = src.list_all_notes().unwrap()[0].clone();
 
     // Manually insert a SetPermission operation into the source log.
-    let perm_op = Operation::SetPermission {
+    let mut perm_op = Operation::SetPermission {
         operation_id: "perm-op-1".to_string(),
         timestamp: HlcTimestamp {
             wall_ms: 1000,
@@ -4596,9 +4637,11 @@ fn test_snapshot_includes_permission_ops() {
         note_id: Some(root.id.clone()),
         user_id: "charlie-pubkey".to_string(),
         role: "reader".to_string(),
-        granted_by: "bob-pubkey".to_string(),
-        signature: "sig-placeholder".to_string(),
+        granted_by: String::new(),
+        prev_hash: None,
+        signature: String::new(),
     };
+    perm_op.sign(&ed25519_dalek::SigningKey::from_bytes(&[4u8; 32]));
     let op_json = serde_json::to_string(&perm_op).unwrap();
     src.storage
         .connection_mut()
@@ -4672,6 +4715,219 @@ fn test_snapshot_includes_permission_ops() {
     assert_eq!(received_from, "snapshot");
 }
 
+/// Permission operations relayed in a snapshot are checked like incoming
+/// operations: a forged key rotation or a tampered grant is not replayed.
+#[test]
+fn test_snapshot_replay_skips_forged_permission_ops() {
+    use crate::core::hlc::HlcTimestamp;
+    use crate::core::succession::SuccessionRecord;
+    use base64::Engine as _;
+
+    let temp = NamedTempFile::new().unwrap();
+    let mut ws = Workspace::create(
+        temp.path(),
+        "",
+        "test-identity",
+        ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]),
+        test_gate(),
+        None,
+    )
+    .unwrap();
+    let b64 = |key: &ed25519_dalek::SigningKey| {
+        base64::engine::general_purpose::STANDARD.encode(key.verifying_key().as_bytes())
+    };
+    let ts = |wall_ms| HlcTimestamp {
+        wall_ms,
+        counter: 0,
+        node_id: 9,
+    };
+    let victim = ed25519_dalek::SigningKey::from_bytes(&[5u8; 32]);
+    let attacker = ed25519_dalek::SigningKey::from_bytes(&[6u8; 32]);
+    let root = ws.list_all_notes().unwrap()[0].id.clone();
+
+    // The attacker claims the victim rotated to the attacker's key.
+    let mut rotation = Operation::RotateIdentityKey {
+        operation_id: "op-forged-rotation".to_string(),
+        timestamp: ts(1_000),
+        device_id: "attacker-device".to_string(),
+        old_key: b64(&victim),
+        new_key: String::new(),
+        rotated_at_ms: 1_000,
+        succession_signature: SuccessionRecord::new(&attacker, &attacker.verifying_key(), 1_000)
+            .signature,
+        prev_hash: None,
+        signature: String::new(),
+    };
+    rotation.sign(&attacker);
+
+    // A grant whose content was changed after signing.
+    let mut tampered = Operation::SetPermission {
+        operation_id: "op-tampered-grant".to_string(),
+        timestamp: ts(1_100),
+        device_id: "attacker-device".to_string(),
+        note_id: Some(root.clone()),
+        user_id: b64(&victim),
+        role: "reader".to_string(),
+        granted_by: String::new(),
+        prev_hash: None,
+        signature: String::new(),
+    };
+    tampered.sign(&attacker);
+    if let Operation::SetPermission { role, .. } = &mut tampered {
+        *role = "owner".to_string();
+    }
+
+    let mut genuine = Operation::SetPermission {
+        operation_id: "op-genuine-grant".to_string(),
+        timestamp: ts(1_200),
+        device_id: "attacker-device".to_string(),
+        note_id: Some(root),
+        user_id: b64(&victim),
+        role: "reader".to_string(),
+        granted_by: String::new(),
+        prev_hash: None,
+        signature: String::new(),
+    };
+    genuine.sign(&attacker);
+
+    ws.replay_snapshot_permission_ops(&[rotation, tampered, genuine])
+        .unwrap();
+
+    assert_eq!(ws.successor_of(&b64(&victim)).unwrap(), None);
+    let logged: Vec<String> = ws
+        .connection()
+        .prepare("SELECT operation_id FROM operations WHERE received_from_peer = 'snapshot'")
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<std::result::Result<_, _>>()
+        .unwrap();
+    assert_eq!(logged, vec!["op-genuine-grant".to_string()]);
+}
+
+/// Scope boundary operations get the same key succession check as logged
+/// operations: once a peer's key is rotated out, boundary operations it
+/// signs later are rejected.
+#[test]
+fn test_scope_boundary_op_from_rotated_key_rejected() {
+    use crate::core::hlc::HlcTimestamp;
+    use crate::core::succession::SuccessionRecord;
+    use base64::Engine as _;
+
+    let temp = NamedTempFile::new().unwrap();
+    let mut ws = Workspace::create(
+        temp.path(),
+        "",
+        "test-identity",
+        ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]),
+        test_gate(),
+        None,
+    )
+    .unwrap();
+    let b64 = |key: &ed25519_dalek::SigningKey| {
+        base64::engine::general_purpose::STANDARD.encode(key.verifying_key().as_bytes())
+    };
+    let old_key = ed25519_dalek::SigningKey::from_bytes(&[5u8; 32]);
+    let new_key = ed25519_dalek::SigningKey::from_bytes(&[6u8; 32]);
+
+    let mut rotation = Operation::RotateIdentityKey {
+        operation_id: "op-rotation".to_string(),
+        timestamp: HlcTimestamp {
+            wall_ms: 10_000,
+            counter: 0,
+            node_id: 9,
+        },
+        device_id: "peer:laptop".to_string(),
+        old_key: b64(&old_key),
+        new_key: String::new(),
+        rotated_at_ms: 10_000,
+        succession_signature: SuccessionRecord::new(&old_key, &new_key.verifying_key(), 10_000)
+            .signature,
+        prev_hash: None,
+        signature: String::new(),
+    };
+    rotation.sign(&new_key);
+    assert!(ws
+        .apply_incoming_operation(rotation, "peer", &[], None, &b64(&new_key))
+        .unwrap());
+
+    let mut before = make_create_note_op("op-before", "note-before", "peer:laptop", 5_000);
+    before.sign(&old_key);
+    assert!(ws
+        .apply_scope_boundary_operation(&before, &b64(&old_key), &[])
+        .unwrap());
+
+    let mut after = make_create_note_op("op-after", "note-after", "peer:laptop", 20_000);
+    after.sign(&old_key);
+    assert_eq!(
+        ws.check_scope_boundary_operation(&after, &b64(&old_key))
+            .unwrap(),
+        Some(OpRejection::RotatedKey)
+    );
+    assert!(!ws
+        .apply_scope_boundary_operation(&after, &b64(&old_key), &[])
+        .unwrap());
+    assert!(ws.get_note("note-after").is_err());
+}
+
+/// A boundary `CreateNote` records the original author it carries, and that
+/// author is covered by the sender's signature.
+#[test]
+fn test_scope_boundary_create_records_signed_original_author() {
+    use base64::Engine as _;
+    let b64 = |k: &ed25519_dalek::SigningKey| {
+        base64::engine::general_purpose::STANDARD.encode(k.verifying_key().as_bytes())
+    };
+    let temp = NamedTempFile::new().unwrap();
+    let mut ws = Workspace::create(
+        temp.path(),
+        "",
+        "test-identity",
+        ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]),
+        test_gate(),
+        None,
+    )
+    .unwrap();
+    let sender = ed25519_dalek::SigningKey::from_bytes(&[5u8; 32]);
+    let author = b64(&ed25519_dalek::SigningKey::from_bytes(&[6u8; 32]));
+
+    let with_author = |note_id: &str, op_id: &str| {
+        let mut op = make_create_note_op(op_id, note_id, "peer:laptop", 5_000);
+        if let Operation::CreateNote {
+            original_author, ..
+        } = &mut op
+        {
+            *original_author = Some(author.clone());
+        }
+        op.sign(&sender);
+        op
+    };
+
+    let op = with_author("note-rebuilt", "op-rebuilt");
+    assert!(ws
+        .apply_scope_boundary_operation(&op, &b64(&sender), &[])
+        .unwrap());
+    assert_eq!(ws.get_note("note-rebuilt").unwrap().created_by, author);
+
+    // Substituting the author after signing breaks the signature.
+    let mut forged = with_author("note-forged", "op-forged");
+    if let Operation::CreateNote {
+        original_author, ..
+    } = &mut forged
+    {
+        *original_author = Some(b64(&sender));
+    }
+    assert_eq!(
+        ws.check_scope_boundary_operation(&forged, &b64(&sender))
+            .unwrap(),
+        Some(OpRejection::BadSignature)
+    );
+    assert!(!ws
+        .apply_scope_boundary_operation(&forged, &b64(&sender), &[])
+        .unwrap());
+    assert!(ws.get_note("note-forged").is_err());
+}
+
 #[test]
 fn test_is_leaf_defaults_to_false() {
     let temp = NamedTempFile::new().unwrap();
@@ -4776,6 +5032,194 @@ fn test_is_leaf_blocks_move_note() {
     );
 }
 
+#[test]
+fn test_graft_rejects_nested_note_under_leaf() {
+    let temp = NamedTempFile::new().unwrap();
+    let mut ws = Workspace::create(
+        temp.path(),
+        "",
+        "test-identity",
+        ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]),
+        test_gate(),
+        None,
+    )
+    .unwrap();
+    ws.create_user_script(
+            "// @name: IsLeafGraftSchemas\nschema(\"LeafType\", #{ version: 1, is_leaf: true, fields: [] });\nschema(\"ChildType\", #{ version: 1, fields: [] });"
+        ).unwrap();
+
+    let top_id = ws.create_note_root("ChildType").unwrap();
+    let leaf_id = ws
+        .create_note(&top_id, AddPosition::AsChild, "LeafType")
+        .unwrap();
+    let top = ws.get_note(&top_id).unwrap();
+    let leaf = ws.get_note(&leaf_id).unwrap();
+
+    // A grandchild under the incoming leaf note: only the nested placement is invalid.
+    let grandchild = Note {
+        id: "incoming-grandchild".to_string(),
+        parent_id: Some(leaf_id.clone()),
+        ..top.clone()
+    };
+    let before = ws.list_all_notes().unwrap().len();
+    let result = ws.graft_notes(
+        &[top.clone(), leaf, grandchild],
+        &top_id,
+        GraftIdStrategy::Remap,
+        &HashMap::new(),
+    );
+    assert!(
+        matches!(result, Err(KrillnotesError::InvalidMove(_))),
+        "expected InvalidMove, got {result:?}"
+    );
+    assert_eq!(ws.list_all_notes().unwrap().len(), before);
+}
+
+#[test]
+fn test_graft_rejects_parent_cycle() {
+    let temp = NamedTempFile::new().unwrap();
+    let mut ws = Workspace::create(
+        temp.path(),
+        "",
+        "test-identity",
+        ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]),
+        test_gate(),
+        None,
+    )
+    .unwrap();
+    let root = ws.list_all_notes().unwrap()[0].clone();
+
+    // "a" is a proper top-level note; "b" and "c" are each other's parent.
+    let a = Note {
+        id: "incoming-a".to_string(),
+        parent_id: None,
+        ..root.clone()
+    };
+    let b = Note {
+        id: "incoming-b".to_string(),
+        parent_id: Some("incoming-c".to_string()),
+        ..root.clone()
+    };
+    let c = Note {
+        id: "incoming-c".to_string(),
+        parent_id: Some("incoming-b".to_string()),
+        ..root.clone()
+    };
+    let before = ws.list_all_notes().unwrap().len();
+    let result = ws.graft_notes(
+        &[a, b, c],
+        &root.id,
+        GraftIdStrategy::Remap,
+        &HashMap::new(),
+    );
+    assert!(
+        matches!(&result, Err(KrillnotesError::InvalidMove(msg)) if msg.contains("incoming-b")),
+        "expected InvalidMove naming the cycle, got {result:?}"
+    );
+    assert_eq!(ws.list_all_notes().unwrap().len(), before);
+}
+
+#[test]
+fn test_graft_preserve_rejects_schema_mismatch() {
+    let temp = NamedTempFile::new().unwrap();
+    let mut ws = Workspace::create(
+        temp.path(),
+        "",
+        "test-identity",
+        ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]),
+        test_gate(),
+        None,
+    )
+    .unwrap();
+    ws.create_user_script(
+        "// @name: GraftSchemaMismatch\nschema(\"OtherType\", #{ version: 1, fields: [] });",
+    )
+    .unwrap();
+    let root_id = ws.list_all_notes().unwrap()[0].id.clone();
+    let existing_id = ws
+        .create_note(&root_id, AddPosition::AsChild, "TextNote")
+        .unwrap();
+
+    let incoming = Note {
+        schema: "OtherType".to_string(),
+        title: "Changed".to_string(),
+        fields: BTreeMap::new(),
+        ..ws.get_note(&existing_id).unwrap()
+    };
+    let result = ws.graft_notes(
+        &[incoming],
+        &root_id,
+        GraftIdStrategy::Preserve,
+        &HashMap::new(),
+    );
+    assert!(
+        matches!(&result, Err(KrillnotesError::ValidationFailed(msg)) if msg.contains("OtherType")),
+        "expected a schema mismatch, got {result:?}"
+    );
+    let note = ws.get_note(&existing_id).unwrap();
+    assert_eq!(note.schema, "TextNote");
+    assert_ne!(note.title, "Changed");
+}
+
+/// Validating a graft does not consume timestamps; committing it stamps the
+/// notes with the timestamps of their operations.
+#[test]
+fn test_check_graft_leaves_clock_and_commit_stamps_ops() {
+    use crate::core::hlc::HlcTimestamp;
+
+    let temp = NamedTempFile::new().unwrap();
+    let mut ws = Workspace::create(
+        temp.path(),
+        "",
+        "test-identity",
+        ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]),
+        test_gate(),
+        None,
+    )
+    .unwrap();
+    let root = ws.list_all_notes().unwrap()[0].clone();
+    let incoming = Note {
+        id: "incoming".to_string(),
+        parent_id: None,
+        created_at: UnixSecs::from_secs(1_000),
+        modified_at: UnixSecs::from_secs(1_000),
+        ..root.clone()
+    };
+
+    // Park the clock in the future so it only moves when a timestamp is taken.
+    let future = UnixSecs::now().as_i64() as u64 * 1000 + 3_600_000;
+    ws.hlc.observe(HlcTimestamp {
+        wall_ms: future,
+        counter: 0,
+        node_id: 9,
+    });
+    let before = ws.hlc.now();
+    ws.check_graft(
+        &[],
+        std::slice::from_ref(&incoming),
+        &root.id,
+        GraftIdStrategy::Remap,
+    )
+    .unwrap();
+    let after = ws.hlc.now();
+    assert_eq!(
+        (after.wall_ms, after.counter),
+        (before.wall_ms, before.counter + 1)
+    );
+
+    let outcome = ws
+        .graft_notes(
+            &[incoming],
+            &root.id,
+            GraftIdStrategy::Remap,
+            &HashMap::new(),
+        )
+        .unwrap();
+    let note = ws.get_note(&outcome.root_ids[0]).unwrap();
+    assert_eq!(note.created_at.as_i64(), (future / 1000) as i64);
+    assert_eq!(note.modified_at, note.created_at);
+}
+
 #[test]
 fn test_is_leaf_blocks_deep_copy() {
     // deep_copy_note (paste) should also be blocked when the target parent is a leaf
@@ -5040,6 +5484,10 @@ fn make_create_note_op(op_id: &str, note_id: &str, device_id: &str, wall_ms: u64
         title: "Remote Note".to_string(),
         fields: BTreeMap::new(),
         created_by: String::new(),
+        created_at: None,
+        modified_at: None,
+        original_author: None,
+        prev_hash: None,
         signature: String::new(),
     };
     op.sign(&test_signing_key());
@@ -5089,6 +5537,41 @@ fn test_apply_incoming_create_note() {
     assert_eq!(synced, 1, "incoming operation must have synced=1");
 }
 
+/// A CreateNote carrying the dates an importer kept stamps the note with
+/// them instead of the operation's timestamp.
+#[test]
+fn test_apply_incoming_create_note_keeps_carried_dates() {
+    let temp = NamedTempFile::new().unwrap();
+    let mut ws = Workspace::create(
+        temp.path(),
+        "",
+        "local-device",
+        ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]),
+        test_gate(),
+        None,
+    )
+    .unwrap();
+
+    let mut op = make_create_note_op("op-dated", "note-dated", "remote-device", 1_000_000);
+    if let Operation::CreateNote {
+        created_at,
+        modified_at,
+        ..
+    } = &mut op
+    {
+        *created_at = Some(UnixSecs::from_secs(1_680_689_700));
+        *modified_at = Some(UnixSecs::from_secs(1_704_164_645));
+    }
+    op.sign(&test_signing_key());
+    assert!(ws
+        .apply_incoming_operation(op, "test-peer", &[], None, &test_sender_identity())
+        .unwrap());
+
+    let note = ws.get_note("note-dated").unwrap();
+    assert_eq!(note.created_at.as_i64(), 1_680_689_700);
+    assert_eq!(note.modified_at.as_i64(), 1_704_164_645);
+}
+
 #[test]
 fn test_apply_incoming_duplicate_is_idempotent() {
     let temp = NamedTempFile::new().unwrap();
@@ -5459,6 +5942,7 @@ fn test_apply_incoming_script_op_from_owner_applied() {
         load_order: 99,
         enabled: true,
         created_by: owner_pubkey,
+        prev_hash: None,
         signature: String::new(),
     };
     op.sign(&key);
@@ -5501,6 +5985,7 @@ fn test_apply_incoming_script_op_from_non_owner_skipped() {
         load_order: 99,
         enabled: true,
         created_by: pubkey_b.clone(),
+        prev_hash: None,
         signature: String::new(),
     };
     op.sign(&key_b);
@@ -5573,6 +6058,10 @@ fn test_verify_sender_authored_op_tampered_rejected() {
         title: "Original Title".to_string(),
         fields: BTreeMap::new(),
         created_by: String::new(),
+        created_at: None,
+        modified_at: None,
+        original_author: None,
+        prev_hash: None,
         signature: String::new(),
     };
     op.sign(&sender_key);
@@ -6213,6 +6702,10 @@ fn test_apply_incoming_create_note_stores_seconds_timestamp() {
         title: "Synced Note".to_string(),
         fields: BTreeMap::new(),
         created_by: String::new(),
+        created_at: None,
+        modified_at: None,
+        original_author: None,
+        prev_hash: None,
         signature: String::new(),
     };
     op.sign(&remote_key);
@@ -6313,3 +6806,498 @@ fn test_self_authored_ops_have_verified_by() {
         "Self-authored operations should have verified_by = identity pubkey"
     );
 }
+
+#[test]
+fn test_revoke_device_rejects_later_ops_and_lists_devices() {
+    use crate::core::hlc::HlcTimestamp;
+    use base64::Engine as _;
+
+    let temp = NamedTempFile::new().unwrap();
+    let key = ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]);
+    let pubkey = base64::engine::general_purpose::STANDARD.encode(key.verifying_key().as_bytes());
+    let mut ws = Workspace::create(
+        temp.path(),
+        "",
+        "test-identity",
+        key.clone(),
+        test_gate(),
+        None,
+    )
+    .unwrap();
+
+    // A second device of the same identity registers and syncs in.
+    let laptop = "test-identity:laptop";
+    let mut register = Operation::RegisterDevice {
+        operation_id: "op-register-laptop".to_string(),
+        timestamp: HlcTimestamp {
+            wall_ms: 1_000,
+            counter: 0,
+            node_id: 7,
+        },
+        device_id: laptop.to_string(),
+        device_uuid: "laptop".to_string(),
+        device_name: "Laptop".to_string(),
+        identity_public_key: String::new(),
+        prev_hash: None,
+        signature: String::new(),
+    };
+    register.sign(&key);
+    assert!(ws
+        .apply_incoming_operation(register, "test-peer", &[], None, &pubkey)
+        .unwrap());
+
+    let devices = ws.list_devices().unwrap();
+    assert_eq!(devices.len(), 2);
+    let listed = devices.iter().find(|d| d.device_id == laptop).unwrap();
+    assert_eq!(listed.device_name, "Laptop");
+    assert!(!listed.is_current && listed.revoked_at_ms.is_none());
+    assert!(devices.iter().any(|d| d.is_current));
+
+    let current = ws.device_id().to_string();
+    assert!(ws.revoke_device(&current).is_err());
+    assert!(ws.revoke_device("someone-else:phone").is_err());
+    assert!(ws.revoke_device(laptop).unwrap());
+    assert!(!ws.revoke_device(laptop).unwrap(), "already revoked");
+    let revoked_at = ws
+        .list_devices()
+        .unwrap()
+        .into_iter()
+        .find(|d| d.device_id == laptop)
+        .unwrap()
+        .revoked_at_ms
+        .expect("laptop must be listed as revoked");
+
+    // Ops the laptop made before the revocation still sync; later ones do not.
+    let mut before = make_create_note_op("op-before", "note-before", laptop, 2_000);
+    before.sign(&key);
+    assert!(ws
+        .apply_incoming_operation(before, "test-peer", &[], None, &pubkey)
+        .unwrap());
+    let mut after =
+        make_create_note_op("op-after", "note-after", laptop, revoked_at as u64 + 60_000);
+    after.sign(&key);
+    assert_eq!(
+        ws.check_incoming_operation(&after, None, &pubkey).unwrap(),
+        Some(OpRejection::RevokedDevice)
+    );
+    assert!(!ws
+        .apply_incoming_operation(after, "test-peer", &[], None, &pubkey)
+        .unwrap());
+}
+
+/// Scope boundary operations from a revoked device are rejected like logged
+/// ones when stamped after the revocation.
+#[test]
+fn test_revoke_device_rejects_later_scope_boundary_ops() {
+    use crate::core::hlc::HlcTimestamp;
+    use base64::Engine as _;
+
+    let temp = NamedTempFile::new().unwrap();
+    let key = ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]);
+    let pubkey = base64::engine::general_purpose::STANDARD.encode(key.verifying_key().as_bytes());
+    let mut ws = Workspace::create(
+        temp.path(),
+        "",
+        "test-identity",
+        key.clone(),
+        test_gate(),
+        None,
+    )
+    .unwrap();
+
+    let laptop = "test-identity:laptop";
+    let mut register = Operation::RegisterDevice {
+        operation_id: "op-register-laptop".to_string(),
+        timestamp: HlcTimestamp {
+            wall_ms: 1_000,
+            counter: 0,
+            node_id: 7,
+        },
+        device_id: laptop.to_string(),
+        device_uuid: "laptop".to_string(),
+        device_name: "Laptop".to_string(),
+        identity_public_key: String::new(),
+        prev_hash: None,
+        signature: String::new(),
+    };
+    register.sign(&key);
+    assert!(ws
+        .apply_incoming_operation(register, "test-peer", &[], None, &pubkey)
+        .unwrap());
+    assert!(ws.revoke_device(laptop).unwrap());
+    let revoked_at = ws
+        .list_devices()
+        .unwrap()
+        .into_iter()
+        .find(|d| d.device_id == laptop)
+        .unwrap()
+        .revoked_at_ms
+        .unwrap();
+
+    let mut before = make_create_note_op("op-before", "note-before", laptop, 2_000);
+    before.sign(&key);
+    assert!(ws
+        .apply_scope_boundary_operation(&before, &pubkey, &[])
+        .unwrap());
+
+    let mut after =
+        make_create_note_op("op-after", "note-after", laptop, revoked_at as u64 + 60_000);
+    after.sign(&key);
+    assert_eq!(
+        ws.check_scope_boundary_operation(&after, &pubkey).unwrap(),
+        Some(OpRejection::RevokedDevice)
+    );
+    assert!(!ws
+        .apply_scope_boundary_operation(&after, &pubkey, &[])
+        .unwrap());
+    assert!(ws.get_note("note-after").is_err());
+}
+
+/// Revocation alone is keyed on the signer-declared device ID and timestamp,
+/// so a compromised device holding the identity key gets past it; rotating
+/// the key away from it in the same flow does lock it out.
+#[test]
+fn test_revoked_device_needs_key_rotation_to_be_locked_out() {
+    use crate::core::hlc::HlcTimestamp;
+    use crate::core::succession::SuccessionRecord;
+    use base64::Engine as _;
+
+    let temp = NamedTempFile::new().unwrap();
+    let key = ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]);
+    let pubkey = base64::engine::general_purpose::STANDARD.encode(key.verifying_key().as_bytes());
+    let mut ws = Workspace::create(
+        temp.path(),
+        "",
+        "test-identity",
+        key.clone(),
+        test_gate(),
+        None,
+    )
+    .unwrap();
+    for (i, device) in ["laptop", "phone"].into_iter().enumerate() {
+        let mut register = Operation::RegisterDevice {
+            operation_id: format!("op-register-{device}"),
+            timestamp: HlcTimestamp {
+                wall_ms: 1_000 + i as u64,
+                counter: 0,
+                node_id: 7,
+            },
+            device_id: format!("test-identity:{device}"),
+            device_uuid: device.to_string(),
+            device_name: device.to_string(),
+            identity_public_key: String::new(),
+            prev_hash: None,
+            signature: String::new(),
+        };
+        register.sign(&key);
+        assert!(ws
+            .apply_incoming_operation(register, "test-peer", &[], None, &pubkey)
+            .unwrap());
+    }
+
+    let laptop = "test-identity:laptop";
+    assert!(ws.revoke_device(laptop).unwrap());
+    let later = UnixSecs::now().as_i64() as u64 * 1000 + 60_000;
+    let current = ws.device_id().to_string();
+    let mut spoofed = make_create_note_op("op-spoofed", "note-spoofed", &current, later);
+    spoofed.sign(&key);
+    assert_eq!(
+        ws.check_incoming_operation(&spoofed, None, &pubkey)
+            .unwrap(),
+        None,
+        "another device ID gets past the revocation"
+    );
+    let mut backdated = make_create_note_op("op-backdated", "note-backdated", laptop, 2_000);
+    backdated.sign(&key);
+    assert_eq!(
+        ws.check_incoming_operation(&backdated, None, &pubkey)
+            .unwrap(),
+        None,
+        "a backdated timestamp gets past the revocation"
+    );
+
+    let new_key = ed25519_dalek::SigningKey::from_bytes(&[2u8; 32]);
+    let record = SuccessionRecord::new(&key, &new_key.verifying_key(), later - 60_000);
+    assert!(ws
+        .revoke_device_and_rotate_key("test-identity:phone", new_key, &record)
+        .unwrap());
+    assert_eq!(
+        ws.check_incoming_operation(&spoofed, None, &pubkey)
+            .unwrap(),
+        Some(OpRejection::RotatedKey),
+        "the retired key no longer signs for the identity"
+    );
+    assert!(ws
+        .revoke_device_and_rotate_key(
+            laptop,
+            ed25519_dalek::SigningKey::from_bytes(&[3u8; 32]),
+            &record
+        )
+        .is_err());
+}
+
+/// A rotation that fails to commit leaves the old key in use; committed
+/// rotations chain, and the retired keys are passed to the gate as the
+/// current key's predecessors.
+#[test]
+fn test_rotate_identity_key_switches_after_commit() {
+    use crate::core::succession::SuccessionRecord;
+    use base64::Engine as _;
+    let b64 = |k: &ed25519_dalek::SigningKey| {
+        base64::engine::general_purpose::STANDARD.encode(k.verifying_key().as_bytes())
+    };
+
+    let temp = NamedTempFile::new().unwrap();
+    let first = ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]);
+    let mut ws = Workspace::create(
+        temp.path(),
+        "",
+        "test-identity",
+        first.clone(),
+        test_gate(),
+        None,
+    )
+    .unwrap();
+    let second = ed25519_dalek::SigningKey::from_bytes(&[2u8; 32]);
+    let third = ed25519_dalek::SigningKey::from_bytes(&[3u8; 32]);
+    let record = SuccessionRecord::new(&first, &second.verifying_key(), 1_000);
+
+    ws.connection()
+        .execute_batch(
+            "CREATE TRIGGER fail_log BEFORE INSERT ON operations \
+             BEGIN SELECT RAISE(ABORT, 'disk full'); END;",
+        )
+        .unwrap();
+    assert!(ws.rotate_identity_key(second.clone(), &record).is_err());
+    assert_eq!(ws.identity_pubkey(), b64(&first));
+    assert_eq!(ws.successor_of(&b64(&first)).unwrap(), None);
+
+    ws.connection()
+        .execute_batch("DROP TRIGGER fail_log;")
+        .unwrap();
+    assert!(ws.rotate_identity_key(second.clone(), &record).unwrap());
+    let record = SuccessionRecord::new(&second, &third.verifying_key(), 2_000);
+    assert!(ws.rotate_identity_key(third.clone(), &record).unwrap());
+    assert_eq!(ws.identity_pubkey(), b64(&third));
+
+    let mut predecessors = ws.predecessors_of(&b64(&third)).unwrap();
+    predecessors.sort();
+    let mut expected = vec![b64(&first), b64(&second)];
+    expected.sort();
+    assert_eq!(predecessors, expected);
+    assert!(ws.predecessors_of(&b64(&first)).unwrap().is_empty());
+}
+
+#[test]
+fn test_operations_are_hash_chained_and_breaks_reported() {
+    use base64::Engine as _;
+
+    let temp = NamedTempFile::new().unwrap();
+    let mut ws = Workspace::create(
+        temp.path(),
+        "",
+        "test-identity",
+        ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]),
+        test_gate(),
+        None,
+    )
+    .unwrap();
+
+    // Local operations link to their predecessor.
+    let root_id = ws.list_all_notes().unwrap()[0].id.clone();
+    ws.create_note(&root_id, AddPosition::AsChild, "TextNote")
+        .unwrap();
+    ws.create_note(&root_id, AddPosition::AsChild, "TextNote")
+        .unwrap();
+    let local = ws.operations_since(None, "other-device").unwrap();
+    let last_two = &local[local.len() - 2..];
+    assert_eq!(
+        last_two[1].prev_hash(),
+        Some(last_two[0].chain_hash().as_str())
+    );
+    assert!(ws.audit_log_integrity().unwrap().is_intact());
+
+    // A peer's chain: op-1 ← op-2 ← op-3, plus op-2b forking from op-1.
+    let peer = ed25519_dalek::SigningKey::from_bytes(&[2u8; 32]);
+    let peer_pubkey =
+        base64::engine::general_purpose::STANDARD.encode(peer.verifying_key().as_bytes());
+    let mut prev = None;
+    let mut chain = Vec::new();
+    for (i, op_id) in ["op-1", "op-2", "op-3"].into_iter().enumerate() {
+        let mut op = make_create_note_op(op_id, op_id, "peer-device", 1_000 + i as u64);
+        op.set_prev_hash(prev);
+        op.sign(&peer);
+        prev = Some(op.chain_hash());
+        chain.push(op);
+    }
+    let mut fork = make_create_note_op("op-2b", "op-2b", "peer-device", 1_500);
+    fork.set_prev_hash(chain[1].prev_hash().map(str::to_string));
+    fork.sign(&peer);
+
+    let mut apply = |op: &Operation| {
+        ws.apply_incoming_operation(op.clone(), "peer-device", &[], None, &peer_pubkey)
+            .unwrap()
+    };
+    assert!(apply(&chain[0]));
+    assert!(apply(&chain[2]), "gaps are reported, not rejected");
+    assert!(apply(&chain[1]), "op-2 fills the gap");
+    assert!(apply(&fork));
+
+    let events: Vec<_> = ws
+        .list_sync_events(10, 0)
+        .unwrap()
+        .into_iter()
+        .map(|e| (e.event_type, e.peer_pubkey))
+        .collect();
+    assert_eq!(
+        events,
+        vec![
+            ("chain_fork".to_string(), peer_pubkey.clone()),
+            ("chain_gap".to_string(), peer_pubkey.clone()),
+        ]
+    );
+
+    let report = ws.audit_log_integrity().unwrap();
+    assert!(report.verify());
+    assert!(!report.is_intact());
+    assert!(report.invalid_signatures.is_empty() && report.altered_operations.is_empty());
+    let breaks: Vec<_> = report
+        .chain_breaks
+        .iter()
+        .map(|b| (b.kind, b.operation_id.as_str()))
+        .collect();
+    assert_eq!(
+        breaks,
+        vec![
+            (crate::ChainBreakKind::Fork, "op-2"),
+            (crate::ChainBreakKind::Fork, "op-2b"),
+        ]
+    );
+
+    // Tampering with a stored operation shows up in the next audit.
+    ws.connection()
+        .execute(
+            "UPDATE operations SET operation_data = replace(operation_data, 'Remote Note', 'Forged') \
+             WHERE operation_id = 'op-3'",
+            [],
+        )
+        .unwrap();
+    let report = ws.audit_log_integrity().unwrap();
+    assert_eq!(report.altered_operations, vec!["op-3".to_string()]);
+    assert_eq!(report.invalid_signatures, vec!["op-3".to_string()]);
+}
+
+#[test]
+fn test_audit_export_filters_names_and_verifies_offline() {
+    use crate::core::audit_export::{verify_audit_export, AuditExportOptions, AuditRecord};
+
+    let temp = NamedTempFile::new().unwrap();
+    let mut ws = Workspace::create(
+        temp.path(),
+        "",
+        "test-identity",
+        ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]),
+        test_gate(),
+        None,
+    )
+    .unwrap();
+    let root_id = ws.list_all_notes().unwrap()[0].id.clone();
+    let folder = ws
+        .create_note(&root_id, AddPosition::AsChild, "TextNote")
+        .unwrap();
+    let inner = ws
+        .create_note(&folder, AddPosition::AsChild, "TextNote")
+        .unwrap();
+    ws.create_note(&root_id, AddPosition::AsChild, "TextNote")
+        .unwrap();
+    let remote = make_create_note_op("op-remote", "note-remote", "peer-device", 1_000);
+    ws.apply_incoming_operation(remote, "peer-device", &[], None, &test_sender_identity())
+        .unwrap();
+
+    let cm_dir = tempfile::tempdir().unwrap();
+    let cm = ContactManager::for_identity(cm_dir.path().to_path_buf(), [0u8; 32]).unwrap();
+    cm.create_contact("Bob", &test_sender_identity(), TrustLevel::Tofu)
+        .unwrap();
+
+    let records = |export: &crate::core::audit_export::AuditExport| -> Vec<AuditRecord> {
+        export
+            .records
+            .split(|b| *b == b'\n')
+            .filter(|l| !l.is_empty())
+            .map(|l| serde_json::from_slice(l).unwrap())
+            .collect()
+    };
+
+    let full = ws
+        .export_audit_log(&AuditExportOptions::default(), &cm, "Alice")
+        .unwrap();
+    let all = records(&full);
+    assert_eq!(
+        all.len(),
+        ws.operations_since(None, "other-device").unwrap().len()
+    );
+    assert_eq!(full.manifest.record_count, all.len() as u64);
+    let remote = all.iter().find(|r| r.operation_id == "op-remote").unwrap();
+    assert_eq!(remote.author_name.as_deref(), Some("Bob"));
+    assert_eq!(remote.verified_by_name.as_deref(), Some("Bob"));
+    assert_eq!(remote.received_from_peer.as_deref(), Some("peer-device"));
+    assert_eq!(all.last().unwrap().author_name.as_deref(), Some("Alice"));
+    let verification = verify_audit_export(&full.records, &full.manifest).unwrap();
+    assert!(verification.is_valid());
+    assert_eq!(verification.record_count, all.len() as u64);
+
+    // Subtree: the folder and the note inside it, nothing else.
+    let subtree = ws
+        .export_audit_log(
+            &AuditExportOptions {
+                subtree_root: Some(folder.clone()),
+                ..Default::default()
+            },
+            &cm,
+            "Alice",
+        )
+        .unwrap();
+    let mut notes: Vec<_> = records(&subtree)
+        .iter()
+        .flat_map(|r| {
+            Workspace::operation_note_ids(&r.operation)
+                .into_iter()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    notes.dedup();
+    assert_eq!(notes, vec![folder.clone(), inner]);
+
+    // Date range: only the remote operation is that old.
+    let range = ws
+        .export_audit_log(
+            &AuditExportOptions {
+                to_ms: Some(1_500),
+                ..Default::default()
+            },
+            &cm,
+            "Alice",
+        )
+        .unwrap();
+    let ids: Vec<_> = records(&range)
+        .into_iter()
+        .map(|r| r.operation_id)
+        .collect();
+    assert_eq!(ids, vec!["op-remote".to_string()]);
+
+    // Editing a record breaks the manifest; re-signing it under another
+    // identity still leaves the operation's own signature failing.
+    let tampered = String::from_utf8(range.records.clone())
+        .unwrap()
+        .replace("Remote Note", "Forged Note")
+        .into_bytes();
+    assert!(verify_audit_export(&tampered, &range.manifest).is_err());
+    let mut resigned = range.manifest.clone();
+    resigned.sign(
+        &tampered,
+        &ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]),
+    );
+    let verification = verify_audit_export(&tampered, &resigned).unwrap();
+    assert_eq!(verification.invalid_records, vec!["op-remote".to_string()]);
+}
@@ -18,39 +18,70 @@ pub mod core;
 #[doc(inline)]
 pub use core::{
     accepted_invite::{AcceptedInvite, AcceptedInviteManager, AcceptedInviteStatus},
+    archive_format::{
+        upgrade_archive, validate_archive, ArchiveDocuments, ArchiveValidationError,
+        ARCHIVE_FORMAT_VERSION, NOTES_JSON_SCHEMA, SCRIPTS_JSON_SCHEMA, WORKSPACE_JSON_SCHEMA,
+    },
     attachment::AttachmentMeta,
+    audit_export::{
+        verify_audit_export, AuditExport, AuditExportOptions, AuditManifest, AuditRecord,
+        AuditVerification,
+    },
+    checkpoint::{Checkpoint, CheckpointState},
     delete::{DeleteResult, DeleteStrategy},
     device::get_device_id,
     error::{KrillnotesError, Result},
     export::{
-        export_workspace, import_workspace, peek_import, ExportError, ExportNotes, ImportResult,
-        ScriptManifest, ScriptManifestEntry, WorkspaceMetadata, APP_VERSION,
+        export_workspace, export_workspace_with_options, import_workspace, merge_import_workspace,
+        peek_import, ExportError, ExportNotes, ExportOptions, ImportResult, MergeImportOptions,
+        MergeImportResult, ReferencedNotes, ScriptConflict, ScriptConflictPolicy, ScriptManifest,
+        ScriptManifestEntry, SubtreeExportInfo, WorkspaceMetadata, APP_VERSION,
     },
     hlc::{HlcClock, HlcTimestamp},
     identity::{
-        ExportedRelayFile, IdentityFile, IdentityManager, IdentityRef, SwarmIdFile,
-        UnlockedIdentity, WorkspaceBinding,
+        ExportedRelayFile, IdentityFile, IdentityManager, IdentityRef, RecoveredIdentity,
+        SwarmIdFile, UnlockedIdentity, WorkspaceBinding,
+    },
+    importers::{
+        import_enex, import_foreign, import_foreign_workspace, import_notion_export, ForeignFormat,
+        ForeignImportReport, ImportIssue,
     },
+    integrity::{ChainBreak, ChainBreakKind, IntegrityReport},
     invite::{InviteFile, InviteManager, InviteRecord, InviteResponseFile},
+    kdf::{benchmark_kdf_cost, KdfCost},
     note::{FieldValue, Note},
     operation::Operation,
-    operation_log::{OperationLog, OperationSummary, PurgeStrategy},
+    operation_log::{CommitListener, OperationLog, OperationSummary, PurgeStrategy},
+    passphrase::{
+        estimate_passphrase_strength, PassphraseEstimate, PassphraseHint, PassphraseStrength,
+        MIN_PASSPHRASE_STRENGTH,
+    },
     peer_registry::PeerInfo,
     permission::{AllowAllGate, PermissionError, PermissionGate},
     received_response::{ReceivedResponse, ReceivedResponseManager, ReceivedResponseStatus},
+    root_recovery::RecoveryShare,
     save_transaction::{SaveResult, SaveTransaction, SoftError},
     scripting::{
         FieldDefinition, FieldGroup, QueryContext, Schema, ScriptError, ScriptRegistry,
         ScriptWarning, StarterScript, ViewRegistration,
     },
     storage::Storage,
+    succession::SuccessionRecord,
     swarm::sync::ApplyResult,
     timestamp::UnixSecs,
     undo::{RetractInverse, UndoResult},
     user_script::UserScript,
+    verification::{
+        ContactVerification, QrVerificationPayload, SasCode, SasMessage, SasSessionInfo,
+        VerificationMethod,
+    },
+    vouch::VouchStatement,
     workspace::{
-        permissions::{CascadeImpactRow, EffectiveRoleInfo, InheritedGrant, PermissionGrantRow},
-        AddPosition, NoteSearchResult, SyncEventRecord, Workspace,
+        permissions::{
+            CascadeImpactRow, EffectiveRoleInfo, InheritedGrant, PermissionGrantRow, ReadScope,
+        },
+        AddPosition, CheckpointMerge, DeviceInfo, GraftIdStrategy, GraftOutcome, NoteSearchResult,
+        SyncEventRecord, Workspace,
     },
 };
 
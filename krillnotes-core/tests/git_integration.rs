@@ -0,0 +1,402 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Integration tests for the git sync channel, run against a local bare
+//! repository as the remote. Requires the `git` executable.
+//!
+//! ```sh
+//! cargo test -p krillnotes-core --test git_integration
+//! ```
+
+use std::path::Path;
+use std::process::Command;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use ed25519_dalek::SigningKey;
+use rand_core::OsRng;
+use tempfile::{NamedTempFile, TempDir};
+
+use krillnotes_core::{
+    core::{
+        contact::{ContactManager, TrustLevel},
+        permission::AllowAllGate,
+        sync::{
+            channel::{ChannelType, PeerSyncInfo, SyncChannel},
+            git::GitRemote,
+            GitChannel, SyncContext, SyncEngine, SyncEvent,
+        },
+    },
+    KrillnotesError, Workspace,
+};
+
+// ── Helpers ──────────────────────────────────────────────────────────────────
+
+/// An empty bare repository standing in for the shared remote.
+fn bare_remote() -> TempDir {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let status = Command::new("git")
+        .args(["init", "--bare", "--quiet"])
+        .arg(dir.path())
+        .status()
+        .expect("git init --bare");
+    assert!(status.success());
+    dir
+}
+
+/// Files on the remote's `main` branch.
+fn remote_files(remote: &Path) -> Vec<String> {
+    let output = Command::new("git")
+        .arg("--git-dir")
+        .arg(remote)
+        .args(["ls-tree", "-r", "--name-only", "main"])
+        .output()
+        .expect("git ls-tree");
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+fn make_key() -> SigningKey {
+    SigningKey::generate(&mut OsRng)
+}
+
+fn b64_pubkey(key: &SigningKey) -> String {
+    BASE64.encode(key.verifying_key().as_bytes())
+}
+
+/// The per-recipient directory name: first 8 key characters, path-safe.
+fn short(pubkey: &str) -> String {
+    pubkey
+        .chars()
+        .take(8)
+        .map(|c| match c {
+            '/' => '-',
+            '+' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+fn make_contact_manager(enc_key: [u8; 32]) -> (TempDir, ContactManager) {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let cm = ContactManager::for_identity(dir.path().to_path_buf(), enc_key)
+        .expect("ContactManager::for_identity");
+    (dir, cm)
+}
+
+/// A `.swarm`-shaped zip with just enough header for the channel to route it.
+fn fake_bundle(workspace_id: &str, tag: &str) -> Vec<u8> {
+    let header = serde_json::json!({
+        "protocol": "krillnotes/1",
+        "formatVersion": 1,
+        "mode": "delta",
+        "workspaceId": workspace_id,
+        "workspaceName": "GitWorkspace",
+        "sourceDeviceId": "dev-sender",
+        "sourceIdentity": "sender",
+        "sourceDisplayName": "Sender",
+        "createdAt": "2026-01-01T00:00:00Z",
+        "sinceOperationId": "",
+        "hasAttachments": false,
+    });
+    zip_bundle(&header, tag)
+}
+
+/// A verification bundle for `target` whose payload is not a valid message.
+fn fake_verification_bundle(workspace_id: &str, target: &str) -> Vec<u8> {
+    let header = serde_json::json!({
+        "protocol": "krillnotes/1",
+        "formatVersion": 1,
+        "mode": "verification",
+        "workspaceId": workspace_id,
+        "workspaceName": "GitWorkspace",
+        "sourceDeviceId": "dev-sender",
+        "sourceIdentity": "sender",
+        "sourceDisplayName": "Sender",
+        "createdAt": "2026-01-01T00:00:00Z",
+        "targetPeer": target,
+        "hasAttachments": false,
+    });
+    zip_bundle(&header, "garbage")
+}
+
+fn zip_bundle(header: &serde_json::Value, tag: &str) -> Vec<u8> {
+    use std::io::Write;
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default();
+    zip.start_file("header.json", options).unwrap();
+    zip.write_all(header.to_string().as_bytes()).unwrap();
+    zip.start_file("payload", options).unwrap();
+    zip.write_all(tag.as_bytes()).unwrap();
+    zip.finish().unwrap().into_inner()
+}
+
+fn git_params(remote: &Path) -> serde_json::Value {
+    serde_json::json!({ "remote": remote.to_string_lossy() })
+}
+
+fn poll(
+    engine: &SyncEngine,
+    ws: &mut Workspace,
+    key: &SigningKey,
+    cm: &mut ContactManager,
+    name: &str,
+) -> Vec<SyncEvent> {
+    engine
+        .poll(
+            ws,
+            &mut SyncContext {
+                signing_key: key,
+                contact_manager: cm,
+                workspace_name: "GitWorkspace",
+                sender_display_name: name,
+            },
+        )
+        .unwrap()
+}
+
+// ── Tests ────────────────────────────────────────────────────────────────────
+
+/// Alice and Bob each poll through a `SyncEngine` with a `GitChannel` on
+/// their own clone of a shared bare repository. Alice's delta is pushed into
+/// Bob's directory, Bob applies it, and the same poll pushes its removal.
+#[test]
+fn git_sync_engine_roundtrip() {
+    let remote = bare_remote();
+    let alice_clones = tempfile::tempdir().unwrap();
+    let bob_clones = tempfile::tempdir().unwrap();
+    let alice_key = make_key();
+    let bob_key = make_key();
+    let alice_pub = b64_pubkey(&alice_key);
+    let bob_pub = b64_pubkey(&bob_key);
+    let params = git_params(remote.path()).to_string();
+
+    // Alice's workspace with Bob as a git peer.
+    let alice_tmp = NamedTempFile::new().unwrap();
+    let mut alice_ws = Workspace::create(
+        alice_tmp.path(),
+        "",
+        "alice-id",
+        SigningKey::from_bytes(&alice_key.to_bytes()),
+        Box::new(AllowAllGate::new("test")),
+        None,
+    )
+    .unwrap();
+    let (_alice_cm_dir, mut alice_cm) = make_contact_manager([0x44u8; 32]);
+    alice_cm
+        .find_or_create_by_public_key("Bob", &bob_pub, TrustLevel::Tofu)
+        .unwrap();
+    alice_ws
+        .upsert_sync_peer("dev-bob", &bob_pub, None, None)
+        .unwrap();
+    alice_ws
+        .update_peer_channel("dev-bob", "git", &params)
+        .unwrap();
+    let note_id = alice_ws.create_note_root("TextNote").unwrap();
+
+    // Bob's empty replica of the same workspace, with Alice as a git peer.
+    let bob_tmp = NamedTempFile::new().unwrap();
+    let mut bob_ws = Workspace::create_with_id(
+        bob_tmp.path(),
+        "",
+        "bob-id",
+        SigningKey::from_bytes(&bob_key.to_bytes()),
+        alice_ws.workspace_id(),
+        Box::new(AllowAllGate::new("test")),
+        None,
+    )
+    .unwrap();
+    bob_ws.set_owner_pubkey(&alice_pub).unwrap();
+    let (_bob_cm_dir, mut bob_cm) = make_contact_manager([0x55u8; 32]);
+    bob_ws
+        .upsert_sync_peer("dev-alice", &alice_pub, None, None)
+        .unwrap();
+    bob_ws
+        .update_peer_channel("dev-alice", "git", &params)
+        .unwrap();
+
+    // ── Alice polls: the delta is committed and pushed ──────────────────────
+    let mut alice_engine = SyncEngine::new();
+    alice_engine.register_channel(Box::new(GitChannel::new(
+        &alice_pub,
+        alice_clones.path().to_path_buf(),
+    )));
+    let events = poll(
+        &alice_engine,
+        &mut alice_ws,
+        &alice_key,
+        &mut alice_cm,
+        "Alice",
+    );
+    assert!(
+        events
+            .iter()
+            .any(|e| matches!(e, SyncEvent::DeltaSent { .. })),
+        "expected DeltaSent, got {events:?}"
+    );
+    let bob_dir = format!("{}/{}/", alice_ws.workspace_id(), short(&bob_pub));
+    let files = remote_files(remote.path());
+    assert!(
+        files
+            .iter()
+            .any(|f| f.starts_with(&bob_dir) && f.ends_with(".swarm")),
+        "{files:?}"
+    );
+
+    // ── Bob polls: the delta is pulled and applied ──────────────────────────
+    let mut bob_engine = SyncEngine::new();
+    bob_engine.register_channel(Box::new(GitChannel::new(
+        &bob_pub,
+        bob_clones.path().to_path_buf(),
+    )));
+    let events = poll(&bob_engine, &mut bob_ws, &bob_key, &mut bob_cm, "Bob");
+    assert!(
+        events
+            .iter()
+            .any(|e| matches!(e, SyncEvent::BundleApplied { .. })),
+        "expected BundleApplied, got {events:?}"
+    );
+    assert!(bob_ws.get_note(&note_id).is_ok());
+
+    // ── The removal of the acknowledged bundle is pushed in the same poll ───
+    let files = remote_files(remote.path());
+    assert!(
+        !files.iter().any(|f| f.starts_with(&bob_dir)),
+        "Bob's directory should be empty after acknowledge, got {files:?}"
+    );
+}
+
+/// Bundles a poll acknowledges are removed from the remote by that same
+/// poll, even when it sends nothing back.
+#[test]
+fn git_poll_pushes_acknowledged_removals() {
+    let remote = bare_remote();
+    let sender_clones = tempfile::tempdir().unwrap();
+    let bob_clones = tempfile::tempdir().unwrap();
+    let bob_key = make_key();
+    let bob_pub = b64_pubkey(&bob_key);
+    let params = git_params(remote.path());
+
+    let bob_tmp = NamedTempFile::new().unwrap();
+    let mut bob_ws = Workspace::create(
+        bob_tmp.path(),
+        "",
+        "bob-id",
+        SigningKey::from_bytes(&bob_key.to_bytes()),
+        Box::new(AllowAllGate::new("test")),
+        None,
+    )
+    .unwrap();
+    let (_bob_cm_dir, mut bob_cm) = make_contact_manager([0x55u8; 32]);
+    bob_ws
+        .upsert_sync_peer("dev-alice", &b64_pubkey(&make_key()), None, None)
+        .unwrap();
+    bob_ws
+        .update_peer_channel("dev-alice", "git", &params.to_string())
+        .unwrap();
+
+    let sender = GitChannel::new(&b64_pubkey(&make_key()), sender_clones.path().to_path_buf());
+    let peer = PeerSyncInfo {
+        peer_device_id: "dev-bob".to_string(),
+        peer_identity_id: bob_pub.clone(),
+        channel_type: ChannelType::Git,
+        channel_params: params.clone(),
+        last_sent_op: None,
+        last_received_op: None,
+    };
+    sender
+        .send_bundle(
+            &peer,
+            &fake_verification_bundle(bob_ws.workspace_id(), &bob_pub),
+        )
+        .unwrap();
+
+    let mut bob_engine = SyncEngine::new();
+    bob_engine.register_channel(Box::new(GitChannel::new(
+        &bob_pub,
+        bob_clones.path().to_path_buf(),
+    )));
+    let events = poll(&bob_engine, &mut bob_ws, &bob_key, &mut bob_cm, "Bob");
+    assert!(
+        !events
+            .iter()
+            .any(|e| matches!(e, SyncEvent::DeltaSent { .. })),
+        "nothing should be sent back, got {events:?}"
+    );
+    let bob_dir = format!("{}/{}/", bob_ws.workspace_id(), short(&bob_pub));
+    let files = remote_files(remote.path());
+    assert!(
+        !files.iter().any(|f| f.starts_with(&bob_dir)),
+        "the acknowledged bundle should be removed, got {files:?}"
+    );
+}
+
+/// A removal committed locally is rebased onto bundles pushed in the
+/// meantime, and only bundles addressed to the polling identity are read.
+#[test]
+fn git_channel_rebases_pending_removals() {
+    let remote = bare_remote();
+    let sender_clones = tempfile::tempdir().unwrap();
+    let me_clones = tempfile::tempdir().unwrap();
+    let me = b64_pubkey(&make_key());
+    let someone_else = b64_pubkey(&make_key());
+    let params = git_params(remote.path());
+    let workspace_id = "3f0c9a2e-0000-4000-8000-000000000001";
+
+    let sender = GitChannel::new(&b64_pubkey(&make_key()), sender_clones.path().to_path_buf());
+    let peer = |identity: &str| PeerSyncInfo {
+        peer_device_id: "dev".to_string(),
+        peer_identity_id: identity.to_string(),
+        channel_type: ChannelType::Git,
+        channel_params: params.clone(),
+        last_sent_op: None,
+        last_received_op: None,
+    };
+    let bundle = |tag: &str| fake_bundle(workspace_id, tag);
+
+    sender.send_bundle(&peer(&me), &bundle("first")).unwrap();
+    sender
+        .send_bundle(&peer(&someone_else), &bundle("other"))
+        .unwrap();
+
+    let channel = GitChannel::new(&me, me_clones.path().to_path_buf());
+    channel.set_remotes(vec![GitRemote::from_params(&params).unwrap()]);
+    let received = channel.receive_bundles(workspace_id).unwrap();
+    assert_eq!(received.len(), 1);
+    channel.acknowledge(&received[0]).unwrap();
+
+    // Another bundle lands before the removal is pushed.
+    sender.send_bundle(&peer(&me), &bundle("second")).unwrap();
+
+    let received = channel.receive_bundles(workspace_id).unwrap();
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].data, bundle("second"));
+    let mine: Vec<String> = remote_files(remote.path())
+        .into_iter()
+        .filter(|f| f.contains(&format!("/{}/", short(&me))))
+        .collect();
+    assert_eq!(
+        mine.len(),
+        1,
+        "first bundle's removal should be pushed: {mine:?}"
+    );
+}
+
+/// An unreachable remote surfaces as a `Git` error.
+#[test]
+fn git_channel_reports_missing_remote() {
+    let clones = tempfile::tempdir().unwrap();
+    let channel = GitChannel::new(&b64_pubkey(&make_key()), clones.path().to_path_buf());
+    channel.set_remotes(vec![GitRemote::from_params(
+        &serde_json::json!({ "remote": clones.path().join("missing.git").to_string_lossy() }),
+    )
+    .unwrap()]);
+    assert!(matches!(
+        channel.receive_bundles("ws-1"),
+        Err(KrillnotesError::Git(_))
+    ));
+}
@@ -0,0 +1,318 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Integration tests for the LAN sync channel: two services on loopback,
+//! each discovering the other by unicast announcement.
+//!
+//! ```sh
+//! cargo test -p krillnotes-core --test lan_integration
+//! ```
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use ed25519_dalek::SigningKey;
+use rand_core::OsRng;
+use tempfile::{NamedTempFile, TempDir};
+
+use krillnotes_core::{
+    core::{
+        contact::{ContactManager, TrustLevel},
+        permission::AllowAllGate,
+        sync::{
+            channel::{ChannelType, PeerSyncInfo, SendResult, SyncChannel},
+            lan::PeerAuthorizer,
+            LanChannel, LanConfig, LanService, SyncContext, SyncEngine, SyncEvent,
+        },
+    },
+    KrillnotesError, Workspace,
+};
+
+// ── Helpers ──────────────────────────────────────────────────────────────────
+
+fn make_key() -> SigningKey {
+    SigningKey::generate(&mut OsRng)
+}
+
+fn b64_pubkey(key: &SigningKey) -> String {
+    BASE64.encode(key.verifying_key().as_bytes())
+}
+
+fn loopback_config() -> LanConfig {
+    let loopback: SocketAddr = "127.0.0.1:0".parse().unwrap();
+    LanConfig {
+        listen_addr: loopback,
+        discovery_addr: Some(loopback),
+        announce_to: Vec::new(),
+        announce_interval: Duration::from_millis(100),
+    }
+}
+
+fn start(key: &SigningKey, spool: &TempDir, authorizer: PeerAuthorizer) -> Arc<LanService> {
+    Arc::new(
+        LanService::start(
+            loopback_config(),
+            SigningKey::from_bytes(&key.to_bytes()),
+            spool.path().to_path_buf(),
+            authorizer,
+        )
+        .expect("LanService::start"),
+    )
+}
+
+/// Authorizes exactly the identities in `cm`, as the desktop app does.
+fn contacts_authorizer(cm: Arc<ContactManager>) -> PeerAuthorizer {
+    Arc::new(move |key: &str| matches!(cm.find_by_public_key(key), Ok(Some(_))))
+}
+
+fn make_contact_manager(enc_key: [u8; 32]) -> (TempDir, ContactManager) {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let cm = ContactManager::for_identity(dir.path().to_path_buf(), enc_key)
+        .expect("ContactManager::for_identity");
+    (dir, cm)
+}
+
+/// Points each service's announcements at the other and waits until both
+/// have discovered each other.
+fn introduce(a: &LanService, b: &LanService) {
+    a.add_announce_target(b.discovery_addr().unwrap());
+    b.add_announce_target(a.discovery_addr().unwrap());
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while a.peer_address(b.identity_id()).is_none() || b.peer_address(a.identity_id()).is_none() {
+        assert!(
+            Instant::now() < deadline,
+            "services did not discover each other"
+        );
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// A `.swarm`-shaped zip with just enough header for the channel to route it.
+fn fake_bundle(workspace_id: &str, tag: &str) -> Vec<u8> {
+    use std::io::Write;
+    let header = serde_json::json!({
+        "protocol": "krillnotes/1",
+        "formatVersion": 1,
+        "mode": "delta",
+        "workspaceId": workspace_id,
+        "workspaceName": "LanWorkspace",
+        "sourceDeviceId": "dev-sender",
+        "sourceIdentity": "sender",
+        "sourceDisplayName": "Sender",
+        "createdAt": "2026-01-01T00:00:00Z",
+        "sinceOperationId": "",
+        "hasAttachments": false,
+    });
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default();
+    zip.start_file("header.json", options).unwrap();
+    zip.write_all(header.to_string().as_bytes()).unwrap();
+    zip.start_file("payload", options).unwrap();
+    zip.write_all(tag.as_bytes()).unwrap();
+    zip.finish().unwrap().into_inner()
+}
+
+fn peer(identity: &str, params: serde_json::Value) -> PeerSyncInfo {
+    PeerSyncInfo {
+        peer_device_id: "dev".to_string(),
+        peer_identity_id: identity.to_string(),
+        channel_type: ChannelType::Lan,
+        channel_params: params,
+        last_sent_op: None,
+        last_received_op: None,
+    }
+}
+
+fn poll(
+    engine: &SyncEngine,
+    ws: &mut Workspace,
+    key: &SigningKey,
+    cm: &mut ContactManager,
+    name: &str,
+) -> Vec<SyncEvent> {
+    engine
+        .poll(
+            ws,
+            &mut SyncContext {
+                signing_key: key,
+                contact_manager: cm,
+                workspace_name: "LanWorkspace",
+                sender_display_name: name,
+            },
+        )
+        .unwrap()
+}
+
+// ── Tests ────────────────────────────────────────────────────────────────────
+
+/// Alice and Bob discover each other on loopback; Alice's poll delivers her
+/// delta straight to Bob's service and Bob's poll applies it.
+#[test]
+fn lan_sync_engine_roundtrip() {
+    let alice_key = make_key();
+    let bob_key = make_key();
+    let alice_pub = b64_pubkey(&alice_key);
+    let bob_pub = b64_pubkey(&bob_key);
+
+    // Each side knows the other as a contact.
+    let (_alice_cm_dir, mut alice_cm) = make_contact_manager([0x44u8; 32]);
+    alice_cm
+        .find_or_create_by_public_key("Bob", &bob_pub, TrustLevel::Tofu)
+        .unwrap();
+    let (_bob_cm_dir, mut bob_cm) = make_contact_manager([0x55u8; 32]);
+    bob_cm
+        .find_or_create_by_public_key("Alice", &alice_pub, TrustLevel::Tofu)
+        .unwrap();
+    let (_bob_auth_dir, bob_auth_cm) = make_contact_manager([0x55u8; 32]);
+    bob_auth_cm
+        .find_or_create_by_public_key("Alice", &alice_pub, TrustLevel::Tofu)
+        .unwrap();
+
+    let alice_spool = tempfile::tempdir().unwrap();
+    let bob_spool = tempfile::tempdir().unwrap();
+    let alice_lan = start(&alice_key, &alice_spool, Arc::new(|_: &str| true));
+    let bob_lan = start(
+        &bob_key,
+        &bob_spool,
+        contacts_authorizer(Arc::new(bob_auth_cm)),
+    );
+    introduce(&alice_lan, &bob_lan);
+
+    let alice_tmp = NamedTempFile::new().unwrap();
+    let mut alice_ws = Workspace::create(
+        alice_tmp.path(),
+        "",
+        "alice-id",
+        SigningKey::from_bytes(&alice_key.to_bytes()),
+        Box::new(AllowAllGate::new("test")),
+        None,
+    )
+    .unwrap();
+    alice_ws
+        .upsert_sync_peer("dev-bob", &bob_pub, None, None)
+        .unwrap();
+    alice_ws
+        .update_peer_channel("dev-bob", "lan", "{}")
+        .unwrap();
+    let note_id = alice_ws.create_note_root("TextNote").unwrap();
+
+    let bob_tmp = NamedTempFile::new().unwrap();
+    let mut bob_ws = Workspace::create_with_id(
+        bob_tmp.path(),
+        "",
+        "bob-id",
+        SigningKey::from_bytes(&bob_key.to_bytes()),
+        alice_ws.workspace_id(),
+        Box::new(AllowAllGate::new("test")),
+        None,
+    )
+    .unwrap();
+    bob_ws.set_owner_pubkey(&alice_pub).unwrap();
+    bob_ws
+        .upsert_sync_peer("dev-alice", &alice_pub, None, None)
+        .unwrap();
+    bob_ws
+        .update_peer_channel("dev-alice", "lan", "{}")
+        .unwrap();
+
+    // ── Alice polls: the delta goes straight to Bob's spool ─────────────────
+    let mut alice_engine = SyncEngine::new();
+    alice_engine.register_channel(Box::new(LanChannel::new(Arc::clone(&alice_lan))));
+    let events = poll(
+        &alice_engine,
+        &mut alice_ws,
+        &alice_key,
+        &mut alice_cm,
+        "Alice",
+    );
+    assert!(
+        events
+            .iter()
+            .any(|e| matches!(e, SyncEvent::DeltaSent { .. })),
+        "expected DeltaSent, got {events:?}"
+    );
+    let bob_inbox = bob_spool.path().join(alice_ws.workspace_id());
+    assert_eq!(std::fs::read_dir(&bob_inbox).unwrap().count(), 1);
+
+    // ── Bob polls: the spooled delta is applied and removed ─────────────────
+    let mut bob_engine = SyncEngine::new();
+    bob_engine.register_channel(Box::new(LanChannel::new(Arc::clone(&bob_lan))));
+    let events = poll(&bob_engine, &mut bob_ws, &bob_key, &mut bob_cm, "Bob");
+    assert!(
+        events
+            .iter()
+            .any(|e| matches!(e, SyncEvent::BundleApplied { .. })),
+        "expected BundleApplied, got {events:?}"
+    );
+    assert!(bob_ws.get_note(&note_id).is_ok());
+    assert_eq!(std::fs::read_dir(&bob_inbox).unwrap().count(), 0);
+}
+
+/// A sender that is not in the receiver's contacts is refused after the
+/// handshake, and nothing is spooled.
+#[test]
+fn lan_rejects_unknown_sender() {
+    let receiver_key = make_key();
+    let stranger_key = make_key();
+    let (_cm_dir, cm) = make_contact_manager([0x66u8; 32]);
+    let receiver_spool = tempfile::tempdir().unwrap();
+    let stranger_spool = tempfile::tempdir().unwrap();
+    let receiver = start(
+        &receiver_key,
+        &receiver_spool,
+        contacts_authorizer(Arc::new(cm)),
+    );
+    let stranger = start(&stranger_key, &stranger_spool, Arc::new(|_: &str| true));
+
+    let channel = LanChannel::new(stranger);
+    let params = serde_json::json!({ "address": receiver.local_addr().to_string() });
+    let result = channel.send_bundle(
+        &peer(&b64_pubkey(&receiver_key), params),
+        &fake_bundle("ws-1", "hello"),
+    );
+    assert!(
+        matches!(&result, Err(KrillnotesError::Lan(msg)) if msg.contains("unknown identity")),
+        "{result:?}"
+    );
+    assert!(!receiver_spool.path().join("ws-1").exists());
+}
+
+/// The sender refuses to hand a bundle to a service answering with a
+/// different identity than the peer's.
+#[test]
+fn lan_rejects_impostor_receiver() {
+    let sender_key = make_key();
+    let impostor_key = make_key();
+    let sender_spool = tempfile::tempdir().unwrap();
+    let impostor_spool = tempfile::tempdir().unwrap();
+    let sender = start(&sender_key, &sender_spool, Arc::new(|_: &str| true));
+    let impostor = start(&impostor_key, &impostor_spool, Arc::new(|_: &str| true));
+
+    let channel = LanChannel::new(sender);
+    let expected_peer = b64_pubkey(&make_key());
+    let params = serde_json::json!({ "address": impostor.local_addr().to_string() });
+    let result = channel.send_bundle(&peer(&expected_peer, params), &fake_bundle("ws-1", "x"));
+    assert!(matches!(result, Err(KrillnotesError::Lan(_))), "{result:?}");
+    assert!(!impostor_spool.path().join("ws-1").exists());
+}
+
+/// With no pinned address and no announcement heard, the send is reported
+/// as not delivered so the watermark stays put.
+#[test]
+fn lan_undiscovered_peer_is_not_delivered() {
+    let key = make_key();
+    let spool = tempfile::tempdir().unwrap();
+    let channel = LanChannel::new(start(&key, &spool, Arc::new(|_: &str| true)));
+    let result = channel
+        .send_bundle(
+            &peer(&b64_pubkey(&make_key()), serde_json::json!({})),
+            &fake_bundle("ws-1", "x"),
+        )
+        .unwrap();
+    assert!(matches!(result, SendResult::NotDelivered { .. }));
+}
@@ -6,12 +6,14 @@
 
 //! Integration tests for the Krillnotes sync engine.
 //!
-//! # Relay tests (ignored by default)
+//! # Relay tests (`relay` feature)
 //!
-//! Tests marked `#[ignore]` require a running relay server. Run them with:
+//! The `relay_*` tests start an in-process `krillnotes-relay` server on an
+//! ephemeral port. Set `RELAY_URL` to run them against an external relay
+//! instead:
 //!
 //! ```sh
-//! RELAY_URL=http://localhost:8080 cargo test -p krillnotes-core --features relay -- --ignored relay_
+//! RELAY_URL=http://localhost:8080 cargo test -p krillnotes-core --features relay relay_
 //! ```
 //!
 //! # Folder channel test (always runs)
@@ -75,7 +77,21 @@ fn make_contact_manager(enc_key: [u8; 32]) -> (tempfile::TempDir, ContactManager
     (dir, cm)
 }
 
-// ── Relay tests (ignored) ─────────────────────────────────────────────────────
+/// Returns the relay to test against: `RELAY_URL` if set, otherwise a fresh
+/// in-process server that stops when the returned handle is dropped.
+#[cfg(feature = "relay")]
+fn test_relay() -> (Option<krillnotes_relay::RelayHandle>, String) {
+    if let Ok(url) = std::env::var("RELAY_URL") {
+        return (None, url);
+    }
+    let handle = krillnotes_relay::RelayServer::bind(krillnotes_relay::RelayConfig::ephemeral())
+        .expect("start in-process relay")
+        .spawn();
+    let url = handle.url().to_string();
+    (Some(handle), url)
+}
+
+// ── Relay tests ───────────────────────────────────────────────────────────────
 
 /// End-to-end relay registration flow:
 /// 1. Generate an Ed25519 keypair (acts as the device key).
@@ -84,19 +100,13 @@ fn make_contact_manager(enc_key: [u8; 32]) -> (tempfile::TempDir, ContactManager
 /// 4. Verify the session token works by fetching account info.
 /// 5. Clean up: log out.
 ///
-/// Run with:
-/// ```sh
-/// RELAY_URL=http://localhost:8080 cargo test -p krillnotes-core --features relay -- --ignored relay_registration_flow
-/// ```
 #[test]
-#[ignore]
 #[cfg(feature = "relay")]
 fn relay_registration_flow() {
     use krillnotes_core::core::sync::relay::{auth::decrypt_pop_challenge, client::RelayClient};
     use uuid::Uuid;
 
-    let relay_url =
-        std::env::var("RELAY_URL").unwrap_or_else(|_| "http://localhost:8080".to_string());
+    let (_relay, relay_url) = test_relay();
 
     // 1. Generate identity keypair.
     let device_key = make_key();
@@ -151,19 +161,13 @@ fn relay_registration_flow() {
 /// 5. Bob polls the relay, downloads the bundle, and applies it.
 /// 6. Verify Bob's workspace received Alice's operations.
 ///
-/// Run with:
-/// ```sh
-/// RELAY_URL=http://localhost:8080 cargo test -p krillnotes-core --features relay -- --ignored relay_delta_roundtrip
-/// ```
 #[test]
-#[ignore]
 #[cfg(feature = "relay")]
 fn relay_delta_roundtrip() {
     use krillnotes_core::core::sync::relay::{auth::decrypt_pop_challenge, client::RelayClient};
     use uuid::Uuid;
 
-    let relay_url =
-        std::env::var("RELAY_URL").unwrap_or_else(|_| "http://localhost:8080".to_string());
+    let (_relay, relay_url) = test_relay();
 
     // ── 1. Create identities ────────────────────────────────────────────────
     let alice_key = make_key();
@@ -199,7 +203,7 @@ fn relay_delta_roundtrip() {
     let bob_client = register_and_verify(&bob_key, &bob_uuid, &bob_email);
 
     // ── 3. Alice creates workspace, registers Bob as peer ──────────────────
-    let (alice_tmp, mut alice_ws) = make_workspace(&alice_key, &alice_uuid);
+    let (_alice_tmp, mut alice_ws) = make_workspace(&alice_key, &alice_uuid);
     let (_alice_cm_dir, alice_cm) = make_contact_manager([0xAAu8; 32]);
 
     // Set snapshot watermark BEFORE adding the note, so the note op is in the delta.
@@ -256,19 +260,21 @@ fn relay_delta_roundtrip() {
         .expect("relay returned at least one bundle_id");
 
     // ── 5. Bob downloads and applies the delta ─────────────────────────────
-    // Drop alice_ws before Bob opens the same DB file to avoid concurrent access.
-    drop(alice_ws);
-
-    // Bob opens the same DB so workspace_id matches the bundle.
-    let mut bob_ws = Workspace::open(
-        alice_tmp.path(),
+    // Bob holds a separate, empty replica of the same workspace, owned by Alice.
+    let bob_tmp = NamedTempFile::new().expect("bob_tmp");
+    let mut bob_ws = Workspace::create_with_id(
+        bob_tmp.path(),
         "",
         &bob_uuid,
         SigningKey::from_bytes(&bob_key.to_bytes()),
+        alice_ws.workspace_id(),
         test_gate(),
         None,
     )
-    .expect("Workspace::open");
+    .expect("Workspace::create_with_id for Bob");
+    bob_ws
+        .set_owner_pubkey(&b64_pubkey(&alice_key))
+        .expect("set_owner_pubkey to Alice");
     let (_bob_cm_dir, mut bob_cm) = make_contact_manager([0xBBu8; 32]);
 
     let bundle_bytes = bob_client
@@ -539,3 +545,94 @@ fn sync_engine_poll_outbound_folder() {
         "at least one .swarm file should have been written to the shared folder"
     );
 }
+
+/// Two identities complete a SAS contact verification through their sync
+/// engines over a shared folder: commit, nonce and reveal each ride one poll.
+#[test]
+fn sync_engine_carries_sas_verification() {
+    use krillnotes_core::core::sync::SyncEvent;
+
+    let alice_key = make_key();
+    let bob_key = make_key();
+    let (alice_b64, bob_b64) = (b64_pubkey(&alice_key), b64_pubkey(&bob_key));
+    let (_alice_tmp, mut alice_ws) = make_workspace(&alice_key, "alice-id");
+    let (_bob_tmp, mut bob_ws) = make_workspace(&bob_key, "bob-id");
+    let (_alice_cm_dir, mut alice_cm) = make_contact_manager([0x41u8; 32]);
+    let (_bob_cm_dir, mut bob_cm) = make_contact_manager([0x42u8; 32]);
+
+    let shared_dir = tempfile::tempdir().expect("shared_dir");
+    let params_json = serde_json::json!({ "path": shared_dir.path() }).to_string();
+    for (ws, peer_device, peer_key) in [
+        (&mut alice_ws, "dev-bob", &bob_b64),
+        (&mut bob_ws, "dev-alice", &alice_b64),
+    ] {
+        ws.upsert_sync_peer(peer_device, peer_key, None, None)
+            .expect("upsert_sync_peer");
+        ws.update_peer_channel(peer_device, "folder", &params_json)
+            .expect("update_peer_channel");
+    }
+    let bob_contact = alice_cm
+        .find_or_create_by_public_key("Bob", &bob_b64, TrustLevel::Tofu)
+        .expect("Alice registers Bob");
+    bob_cm
+        .find_or_create_by_public_key("Alice", &alice_b64, TrustLevel::Tofu)
+        .expect("Bob registers Alice");
+
+    let mut alice_engine = SyncEngine::new();
+    alice_engine.register_channel(Box::new(FolderChannel::new(
+        alice_b64.clone(),
+        "alice-device-uuid".to_string(),
+    )));
+    let mut bob_engine = SyncEngine::new();
+    bob_engine.register_channel(Box::new(FolderChannel::new(
+        bob_b64.clone(),
+        "bob-device-uuid".to_string(),
+    )));
+
+    let commit = alice_cm
+        .start_sas_verification(&alice_key, bob_contact.contact_id)
+        .expect("start verification");
+    let codes = |events: Vec<SyncEvent>| -> Vec<Option<String>> {
+        events
+            .into_iter()
+            .filter_map(|e| match e {
+                SyncEvent::VerificationUpdated {
+                    session_id, code, ..
+                } if session_id == commit.session_id => Some(code.map(|c| c.digits)),
+                _ => None,
+            })
+            .collect()
+    };
+    let mut poll = |alice_turn: bool| {
+        let (engine, ws, key, cm, name) = if alice_turn {
+            (
+                &alice_engine,
+                &mut alice_ws,
+                &alice_key,
+                &mut alice_cm,
+                "Alice",
+            )
+        } else {
+            (&bob_engine, &mut bob_ws, &bob_key, &mut bob_cm, "Bob")
+        };
+        let mut ctx = SyncContext {
+            signing_key: key,
+            contact_manager: cm,
+            workspace_name: "Verification",
+            sender_display_name: name,
+        };
+        codes(engine.poll(ws, &mut ctx).expect("poll"))
+    };
+
+    assert!(poll(true).is_empty(), "Alice sends the commit");
+    assert_eq!(poll(false), vec![None], "Bob answers with his nonce");
+    let alice_code = poll(true);
+    let bob_code = poll(false);
+    assert!(alice_code[0].is_some());
+    assert_eq!(alice_code, bob_code);
+
+    let verified = bob_cm
+        .confirm_sas_verification(&commit.session_id)
+        .expect("Bob confirms");
+    assert_eq!(verified.trust_level, TrustLevel::CodeVerified);
+}
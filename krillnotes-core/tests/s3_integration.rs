@@ -0,0 +1,416 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Integration tests for the S3 sync channel (`s3` feature).
+//!
+//! The tests run against `FakeS3`, a minimal in-memory, MinIO-like object
+//! store on an ephemeral port. It serves one path-style bucket, implements
+//! PutObject, GetObject, DeleteObject and paginated ListObjectsV2, and
+//! rejects any request whose SigV4 signature does not verify.
+//!
+//! ```sh
+//! cargo test -p krillnotes-core --features s3 --test s3_integration
+//! ```
+
+#![cfg(feature = "s3")]
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use ed25519_dalek::SigningKey;
+use rand_core::OsRng;
+use reqwest::Url;
+use tempfile::NamedTempFile;
+use uuid::Uuid;
+
+use krillnotes_core::{
+    core::{
+        contact::{ContactManager, TrustLevel},
+        permission::AllowAllGate,
+        sync::{
+            channel::SyncChannel,
+            s3::{inbox_name, sigv4, S3Account, S3Channel},
+            SyncContext, SyncEngine, SyncEvent,
+        },
+    },
+    KrillnotesError, Workspace,
+};
+
+const BUCKET: &str = "team-sync";
+const ACCESS_KEY: &str = "minioadmin";
+const SECRET_KEY: &str = "minio-secret";
+/// Small page size so listing exercises continuation tokens.
+const PAGE_SIZE: usize = 2;
+
+// ── FakeS3: in-memory S3 stand-in ────────────────────────────────────────────
+
+struct FakeS3 {
+    endpoint: String,
+    http: Arc<tiny_http::Server>,
+    objects: Arc<Mutex<BTreeMap<String, Vec<u8>>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl FakeS3 {
+    fn start() -> Self {
+        let http = Arc::new(tiny_http::Server::http("127.0.0.1:0").expect("bind fake s3"));
+        let endpoint = format!("http://{}", http.server_addr().to_ip().unwrap());
+        let objects = Arc::new(Mutex::new(BTreeMap::new()));
+        let thread = {
+            let http = Arc::clone(&http);
+            let objects = Arc::clone(&objects);
+            std::thread::spawn(move || {
+                while let Ok(request) = http.recv() {
+                    serve(&objects, request);
+                }
+            })
+        };
+        Self {
+            endpoint,
+            http,
+            objects,
+            thread: Some(thread),
+        }
+    }
+
+    fn account(&self, secret: &str, prefix: &str) -> S3Account {
+        S3Account {
+            s3_account_id: Uuid::new_v4(),
+            endpoint: self.endpoint.clone(),
+            region: "us-east-1".to_string(),
+            bucket: BUCKET.to_string(),
+            prefix: prefix.to_string(),
+            path_style: true,
+            access_key_id: ACCESS_KEY.to_string(),
+            secret_access_key: secret.to_string(),
+        }
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.objects.lock().unwrap().keys().cloned().collect()
+    }
+}
+
+impl Drop for FakeS3 {
+    fn drop(&mut self) {
+        self.http.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn header(request: &tiny_http::Request, name: &str) -> Option<String> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.as_str().as_str().eq_ignore_ascii_case(name))
+        .map(|h| h.value.as_str().to_string())
+}
+
+fn error(status: u16, code: &str) -> (u16, Vec<u8>) {
+    (
+        status,
+        format!("<?xml version=\"1.0\"?><Error><Code>{code}</Code></Error>").into_bytes(),
+    )
+}
+
+/// Recomputes the SigV4 signature from the request as received.
+fn signature_ok(request: &tiny_http::Request, url: &Url, body: &[u8]) -> bool {
+    let Some(auth) = header(request, "Authorization") else {
+        return false;
+    };
+    let Some(signed) = auth
+        .split(',')
+        .find_map(|part| part.trim().strip_prefix("SignedHeaders="))
+    else {
+        return false;
+    };
+    let headers: Vec<(String, String)> = signed
+        .split(';')
+        .map(|name| (name.to_string(), header(request, name).unwrap_or_default()))
+        .collect();
+    let headers: Vec<(&str, &str)> = headers
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    let content_sha256 = header(request, "x-amz-content-sha256").unwrap_or_default();
+    let amz_date = header(request, "x-amz-date").unwrap_or_default();
+    content_sha256 == sigv4::payload_hash(body)
+        && auth
+            == sigv4::authorization(
+                request.method().as_str(),
+                url,
+                &headers,
+                &content_sha256,
+                ACCESS_KEY,
+                SECRET_KEY,
+                "us-east-1",
+                &amz_date,
+            )
+}
+
+fn list_objects(objects: &BTreeMap<String, Vec<u8>>, url: &Url) -> (u16, Vec<u8>) {
+    let query: BTreeMap<String, String> = url.query_pairs().into_owned().collect();
+    let prefix = query.get("prefix").cloned().unwrap_or_default();
+    let max_keys = query
+        .get("max-keys")
+        .and_then(|m| m.parse().ok())
+        .unwrap_or(PAGE_SIZE)
+        .min(PAGE_SIZE);
+    let after = query.get("continuation-token").cloned().unwrap_or_default();
+    let matching: Vec<&String> = objects
+        .keys()
+        .filter(|k| k.starts_with(&prefix) && k.as_str() > after.as_str())
+        .collect();
+    let page = &matching[..matching.len().min(max_keys)];
+    let truncated = matching.len() > page.len();
+
+    let mut xml = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8"?><ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">"#,
+    );
+    xml.push_str(&format!("<IsTruncated>{truncated}</IsTruncated>"));
+    if truncated {
+        let last = page.last().unwrap();
+        xml.push_str(&format!(
+            "<NextContinuationToken>{last}</NextContinuationToken>"
+        ));
+    }
+    for key in page {
+        xml.push_str(&format!("<Contents><Key>{key}</Key></Contents>"));
+    }
+    xml.push_str("</ListBucketResult>");
+    (200, xml.into_bytes())
+}
+
+fn serve(objects: &Mutex<BTreeMap<String, Vec<u8>>>, mut request: tiny_http::Request) {
+    let host = header(&request, "Host").unwrap_or_default();
+    let url = Url::parse(&format!("http://{host}{}", request.url())).expect("request URL");
+    let method = request.method().as_str().to_string();
+    let mut body = Vec::new();
+    let _ = request.as_reader().read_to_end(&mut body);
+
+    let bucket_root = format!("/{BUCKET}/");
+    let (status, reply) = if !signature_ok(&request, &url, &body) {
+        error(403, "SignatureDoesNotMatch")
+    } else if !url.path().starts_with(&bucket_root) {
+        error(404, "NoSuchBucket")
+    } else {
+        let key = url.path()[bucket_root.len()..].to_string();
+        let mut objects = objects.lock().unwrap();
+        match method.as_str() {
+            "GET" if key.is_empty() => list_objects(&objects, &url),
+            "PUT" => {
+                objects.insert(key, body);
+                (200, Vec::new())
+            }
+            "GET" => match objects.get(&key) {
+                Some(data) => (200, data.clone()),
+                None => error(404, "NoSuchKey"),
+            },
+            "DELETE" => {
+                objects.remove(&key);
+                (204, Vec::new())
+            }
+            _ => error(405, "MethodNotAllowed"),
+        }
+    };
+    let _ = request.respond(tiny_http::Response::from_data(reply).with_status_code(status));
+}
+
+// ── Helpers ──────────────────────────────────────────────────────────────────
+
+fn make_key() -> SigningKey {
+    SigningKey::generate(&mut OsRng)
+}
+
+fn b64_pubkey(key: &SigningKey) -> String {
+    BASE64.encode(key.verifying_key().as_bytes())
+}
+
+fn make_contact_manager(enc_key: [u8; 32]) -> (tempfile::TempDir, ContactManager) {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let cm = ContactManager::for_identity(dir.path().to_path_buf(), enc_key)
+        .expect("ContactManager::for_identity");
+    (dir, cm)
+}
+
+fn s3_channel(key: &SigningKey, account: &S3Account) -> S3Channel {
+    let mut channel = S3Channel::new(&b64_pubkey(key));
+    channel.add_account(account).expect("add_account");
+    channel
+}
+
+// ── Tests ────────────────────────────────────────────────────────────────────
+
+/// Alice and Bob each poll through a `SyncEngine` with an `S3Channel`:
+/// Alice's delta lands under Bob's key prefix, Bob applies it and the object
+/// is deleted once acknowledged.
+#[test]
+fn s3_sync_engine_roundtrip() {
+    let s3 = FakeS3::start();
+    let alice_key = make_key();
+    let bob_key = make_key();
+    let alice_pub = b64_pubkey(&alice_key);
+    let bob_pub = b64_pubkey(&bob_key);
+    let alice_account = s3.account(SECRET_KEY, "krill");
+    let bob_account = s3.account(SECRET_KEY, "krill");
+
+    // Alice's workspace with Bob as an S3 peer.
+    let alice_tmp = NamedTempFile::new().unwrap();
+    let mut alice_ws = Workspace::create(
+        alice_tmp.path(),
+        "",
+        "alice-id",
+        SigningKey::from_bytes(&alice_key.to_bytes()),
+        Box::new(AllowAllGate::new("test")),
+        None,
+    )
+    .unwrap();
+    let (_alice_cm_dir, mut alice_cm) = make_contact_manager([0x44u8; 32]);
+    alice_cm
+        .find_or_create_by_public_key("Bob", &bob_pub, TrustLevel::Tofu)
+        .unwrap();
+    alice_ws
+        .upsert_sync_peer("dev-bob", &bob_pub, None, None)
+        .unwrap();
+    let alice_params = serde_json::json!({
+        "s3_account_id": alice_account.s3_account_id.to_string()
+    });
+    alice_ws
+        .update_peer_channel("dev-bob", "s3", &alice_params.to_string())
+        .unwrap();
+    let note_id = alice_ws.create_note_root("TextNote").unwrap();
+
+    // Bob's empty replica of the same workspace, with Alice as an S3 peer.
+    let bob_tmp = NamedTempFile::new().unwrap();
+    let mut bob_ws = Workspace::create_with_id(
+        bob_tmp.path(),
+        "",
+        "bob-id",
+        SigningKey::from_bytes(&bob_key.to_bytes()),
+        alice_ws.workspace_id(),
+        Box::new(AllowAllGate::new("test")),
+        None,
+    )
+    .unwrap();
+    bob_ws.set_owner_pubkey(&alice_pub).unwrap();
+    let (_bob_cm_dir, mut bob_cm) = make_contact_manager([0x55u8; 32]);
+    bob_ws
+        .upsert_sync_peer("dev-alice", &alice_pub, None, None)
+        .unwrap();
+    let bob_params = serde_json::json!({
+        "s3_account_id": bob_account.s3_account_id.to_string()
+    });
+    bob_ws
+        .update_peer_channel("dev-alice", "s3", &bob_params.to_string())
+        .unwrap();
+
+    // ── Alice polls: the delta is uploaded under Bob's prefix ───────────────
+    let mut alice_engine = SyncEngine::new();
+    alice_engine.register_channel(Box::new(s3_channel(&alice_key, &alice_account)));
+    let events = alice_engine
+        .poll(
+            &mut alice_ws,
+            &mut SyncContext {
+                signing_key: &alice_key,
+                contact_manager: &mut alice_cm,
+                workspace_name: "S3Workspace",
+                sender_display_name: "Alice",
+            },
+        )
+        .unwrap();
+    assert!(
+        events
+            .iter()
+            .any(|e| matches!(e, SyncEvent::DeltaSent { .. })),
+        "expected DeltaSent, got {events:?}"
+    );
+    let inbox = format!(
+        "krill/{}/{}/",
+        alice_ws.workspace_id(),
+        inbox_name(&bob_pub)
+    );
+    let keys = s3.keys();
+    assert_eq!(keys.len(), 1, "{keys:?}");
+    assert!(keys[0].starts_with(&inbox) && keys[0].ends_with(".swarm"));
+
+    // ── Bob polls: the delta is downloaded, applied and acknowledged ────────
+    let mut bob_engine = SyncEngine::new();
+    bob_engine.register_channel(Box::new(s3_channel(&bob_key, &bob_account)));
+    let events = bob_engine
+        .poll(
+            &mut bob_ws,
+            &mut SyncContext {
+                signing_key: &bob_key,
+                contact_manager: &mut bob_cm,
+                workspace_name: "S3Workspace",
+                sender_display_name: "Bob",
+            },
+        )
+        .unwrap();
+    assert!(
+        events
+            .iter()
+            .any(|e| matches!(e, SyncEvent::BundleApplied { .. })),
+        "expected BundleApplied, got {events:?}"
+    );
+    assert!(bob_ws.get_note(&note_id).is_ok());
+    assert!(
+        !s3.keys().iter().any(|k| k.starts_with(&inbox)),
+        "Bob's prefix should be empty after acknowledge"
+    );
+}
+
+/// Only bundles addressed to the polling identity are returned, across
+/// several list pages.
+#[test]
+fn s3_channel_reads_only_own_inbox() {
+    let s3 = FakeS3::start();
+    let account = s3.account(SECRET_KEY, "");
+    let me = make_key();
+    let channel = s3_channel(&me, &account);
+    {
+        let mut objects = s3.objects.lock().unwrap();
+        let mine = format!("ws-1/{}/", inbox_name(&b64_pubkey(&me)));
+        let theirs = format!("ws-1/{}/", inbox_name(&b64_pubkey(&make_key())));
+        for i in 0..5 {
+            objects.insert(format!("{mine}2026010100000{i}_a.swarm"), vec![i]);
+        }
+        objects.insert(format!("{mine}notes.txt"), b"not a bundle".to_vec());
+        objects.insert(
+            format!("{theirs}20260101000000_c.swarm"),
+            b"theirs".to_vec(),
+        );
+    }
+
+    let bundles = channel.receive_bundles("ws-1").unwrap();
+    let data: Vec<Vec<u8>> = bundles.iter().map(|b| b.data.clone()).collect();
+    assert_eq!(data, vec![vec![0], vec![1], vec![2], vec![3], vec![4]]);
+    assert!(channel.receive_bundles("ws-2").unwrap().is_empty());
+
+    channel.acknowledge(&bundles[0]).unwrap();
+    assert_eq!(channel.receive_bundles("ws-1").unwrap().len(), 4);
+}
+
+/// A wrong secret fails SigV4 verification and surfaces as an `S3` error
+/// instead of an empty inbox.
+#[test]
+fn s3_channel_reports_bad_signature() {
+    let s3 = FakeS3::start();
+    let channel = s3_channel(&make_key(), &s3.account("wrong-secret", ""));
+    match channel.receive_bundles("ws-1") {
+        Err(KrillnotesError::S3(msg)) => {
+            assert!(
+                msg.contains("403") && msg.contains("SignatureDoesNotMatch"),
+                "{msg}"
+            )
+        }
+        other => panic!("expected S3 auth error, got {other:?}"),
+    }
+}
@@ -0,0 +1,202 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Integration tests for the background sync daemon driving a real
+//! workspace over the folder channel.
+//!
+//! ```sh
+//! cargo test -p krillnotes-core --test scheduler_integration
+//! ```
+
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use ed25519_dalek::SigningKey;
+use rand_core::OsRng;
+use tempfile::NamedTempFile;
+
+use krillnotes_core::{
+    core::{
+        contact::{ContactManager, TrustLevel},
+        permission::AllowAllGate,
+        sync::{
+            channel::PeerSyncInfo, FolderChannel, SchedulerConfig, SyncContext, SyncDaemon,
+            SyncEngine, SyncEvent,
+        },
+    },
+    Workspace,
+};
+
+fn b64_pubkey(key: &SigningKey) -> String {
+    BASE64.encode(key.verifying_key().as_bytes())
+}
+
+/// Quiet schedule: nothing is polled on a timer during the test, so every
+/// cycle after the first is caused by a commit.
+fn commit_only_config() -> SchedulerConfig {
+    SchedulerConfig {
+        default_interval: Duration::from_secs(3600),
+        channel_intervals: Default::default(),
+        push_delay: Duration::from_millis(100),
+        ..SchedulerConfig::default()
+    }
+}
+
+/// A local edit reaches the peer's folder without anyone calling `poll`.
+#[test]
+fn daemon_pushes_after_local_commit() {
+    let alice_key = SigningKey::generate(&mut OsRng);
+    let bob_pub = b64_pubkey(&SigningKey::generate(&mut OsRng));
+    let folder = tempfile::tempdir().unwrap();
+    let cm_dir = tempfile::tempdir().unwrap();
+
+    let ws_file = NamedTempFile::new().unwrap();
+    let ws = Workspace::create(
+        ws_file.path(),
+        "",
+        "alice-id",
+        SigningKey::from_bytes(&alice_key.to_bytes()),
+        Box::new(AllowAllGate::new("test")),
+        None,
+    )
+    .unwrap();
+    ws.upsert_sync_peer("dev-bob", &bob_pub, None, None)
+        .unwrap();
+    let params = serde_json::json!({ "path": folder.path().to_string_lossy() }).to_string();
+    ws.update_peer_channel("dev-bob", "folder", &params)
+        .unwrap();
+    let ws = Arc::new(Mutex::new(ws));
+
+    let mut cm = ContactManager::for_identity(cm_dir.path().to_path_buf(), [0x11u8; 32]).unwrap();
+    cm.find_or_create_by_public_key("Bob", &bob_pub, TrustLevel::Tofu)
+        .unwrap();
+
+    let mut engine = SyncEngine::new();
+    engine.register_channel(Box::new(FolderChannel::new(
+        b64_pubkey(&alice_key),
+        "dev-alice".to_string(),
+    )));
+
+    // A headless runner: the target owns everything but the workspace.
+    let target = {
+        let ws = Arc::clone(&ws);
+        move |include: &mut dyn FnMut(&PeerSyncInfo) -> bool| {
+            let mut ws = ws.lock().unwrap();
+            engine.poll_peers(
+                &mut ws,
+                &mut SyncContext {
+                    signing_key: &alice_key,
+                    contact_manager: &mut cm,
+                    workspace_name: "Scheduled",
+                    sender_display_name: "Alice",
+                },
+                include,
+            )
+        }
+    };
+    let (tx, rx) = mpsc::channel();
+    let tx = Mutex::new(tx);
+    let daemon = SyncDaemon::spawn(
+        commit_only_config(),
+        target,
+        Box::new(move |event| {
+            let _ = tx.lock().unwrap().send(event);
+        }),
+    );
+
+    // The first cycle runs at once and flushes whatever creation logged.
+    let first = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(matches!(first, SyncEvent::DeltaSent { .. }), "{first:?}");
+    while rx.recv_timeout(Duration::from_millis(300)).is_ok() {}
+    let bundles_before = std::fs::read_dir(folder.path()).unwrap().count();
+
+    {
+        let mut ws = ws.lock().unwrap();
+        ws.set_commit_listener(Some(daemon.commit_notifier()));
+        ws.create_note_root("TextNote").unwrap();
+    }
+
+    let event = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(
+        matches!(event, SyncEvent::DeltaSent { op_count, .. } if op_count >= 1),
+        "{event:?}"
+    );
+    assert_eq!(
+        std::fs::read_dir(folder.path()).unwrap().count(),
+        bundles_before + 1
+    );
+    daemon.shutdown();
+}
+
+/// A peer whose channel is not registered fails every cycle and is backed
+/// off instead of being retried on every timed cycle.
+#[test]
+fn daemon_backs_off_failing_peer() {
+    let key = SigningKey::generate(&mut OsRng);
+    let cm_dir = tempfile::tempdir().unwrap();
+    let ws_file = NamedTempFile::new().unwrap();
+    let mut ws = Workspace::create(
+        ws_file.path(),
+        "",
+        "alice-id",
+        SigningKey::from_bytes(&key.to_bytes()),
+        Box::new(AllowAllGate::new("test")),
+        None,
+    )
+    .unwrap();
+    ws.upsert_sync_peer(
+        "dev-bob",
+        &b64_pubkey(&SigningKey::generate(&mut OsRng)),
+        None,
+        None,
+    )
+    .unwrap();
+    ws.update_peer_channel("dev-bob", "s3", "{}").unwrap();
+    let mut cm = ContactManager::for_identity(cm_dir.path().to_path_buf(), [0x22u8; 32]).unwrap();
+
+    // No S3 channel registered: every send to dev-bob is a SyncError.
+    let engine = SyncEngine::new();
+    let attempts = Arc::new(Mutex::new(0usize));
+    let target = {
+        let attempts = Arc::clone(&attempts);
+        move |include: &mut dyn FnMut(&PeerSyncInfo) -> bool| {
+            let mut counting = |peer: &PeerSyncInfo| {
+                let included = include(peer);
+                if included && peer.peer_device_id == "dev-bob" {
+                    *attempts.lock().unwrap() += 1;
+                }
+                included
+            };
+            engine.poll_peers(
+                &mut ws,
+                &mut SyncContext {
+                    signing_key: &key,
+                    contact_manager: &mut cm,
+                    workspace_name: "Scheduled",
+                    sender_display_name: "Alice",
+                },
+                &mut counting,
+            )
+        }
+    };
+    let daemon = SyncDaemon::spawn(
+        SchedulerConfig {
+            default_interval: Duration::from_millis(20),
+            channel_intervals: Default::default(),
+            backoff_initial: Duration::from_secs(3600),
+            ..SchedulerConfig::default()
+        },
+        target,
+        Box::new(|_| {}),
+    );
+    std::thread::sleep(Duration::from_millis(300));
+    daemon.shutdown();
+
+    // Included in the inbound and outbound pass of the first cycle only.
+    assert_eq!(*attempts.lock().unwrap(), 2);
+}
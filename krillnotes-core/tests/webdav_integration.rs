@@ -0,0 +1,379 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Integration tests for the WebDAV sync channel (`webdav` feature).
+//!
+//! The tests run against `FakeDav`, a minimal in-memory WebDAV server on an
+//! ephemeral port that implements the verbs the channel uses (MKCOL, PUT,
+//! MOVE, PROPFIND, GET, DELETE) and checks HTTP basic auth.
+//!
+//! ```sh
+//! cargo test -p krillnotes-core --features webdav --test webdav_integration
+//! ```
+
+#![cfg(feature = "webdav")]
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use ed25519_dalek::SigningKey;
+use rand_core::OsRng;
+use tempfile::NamedTempFile;
+use uuid::Uuid;
+
+use krillnotes_core::{
+    core::{
+        contact::{ContactManager, TrustLevel},
+        permission::AllowAllGate,
+        sync::{
+            channel::SyncChannel,
+            webdav::{inbox_name, WebDavAccount, WebDavChannel},
+            SyncContext, SyncEngine, SyncEvent,
+        },
+    },
+    KrillnotesError, Workspace,
+};
+
+// ── FakeDav: in-memory WebDAV stand-in ───────────────────────────────────────
+
+#[derive(Default)]
+struct DavState {
+    collections: BTreeSet<String>,
+    files: BTreeMap<String, Vec<u8>>,
+}
+
+struct FakeDav {
+    url: String,
+    http: Arc<tiny_http::Server>,
+    state: Arc<Mutex<DavState>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl FakeDav {
+    /// Serves `/dav/` for user `alice`/`bob` with password `pw`.
+    fn start() -> Self {
+        let http = Arc::new(tiny_http::Server::http("127.0.0.1:0").expect("bind fake dav"));
+        let url = format!("http://{}/dav/", http.server_addr().to_ip().unwrap());
+        let state = Arc::new(Mutex::new(DavState::default()));
+        state.lock().unwrap().collections.insert("/dav/".into());
+        let thread = {
+            let http = Arc::clone(&http);
+            let state = Arc::clone(&state);
+            std::thread::spawn(move || {
+                while let Ok(request) = http.recv() {
+                    serve(&state, request);
+                }
+            })
+        };
+        Self {
+            url,
+            http,
+            state,
+            thread: Some(thread),
+        }
+    }
+
+    fn account(&self, username: &str, password: &str) -> WebDavAccount {
+        WebDavAccount {
+            webdav_account_id: Uuid::new_v4(),
+            url: self.url.clone(),
+            username: username.to_string(),
+            password: password.to_string(),
+        }
+    }
+
+    fn files(&self) -> Vec<String> {
+        self.state.lock().unwrap().files.keys().cloned().collect()
+    }
+}
+
+impl Drop for FakeDav {
+    fn drop(&mut self) {
+        self.http.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn parent_of(path: &str) -> String {
+    let trimmed = path.trim_end_matches('/');
+    format!("{}/", &trimmed[..trimmed.rfind('/').unwrap_or(0)])
+}
+
+fn header(request: &tiny_http::Request, name: &'static str) -> Option<String> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv(name))
+        .map(|h| h.value.as_str().to_string())
+}
+
+fn serve(state: &Mutex<DavState>, mut request: tiny_http::Request) {
+    let authorized = header(&request, "Authorization").is_some_and(|v| {
+        v == format!("Basic {}", BASE64.encode("alice:pw"))
+            || v == format!("Basic {}", BASE64.encode("bob:pw"))
+    });
+    let destination = header(&request, "Destination");
+    let method = request.method().as_str().to_string();
+    let path = request.url().to_string();
+    let mut body = Vec::new();
+    let _ = request.as_reader().read_to_end(&mut body);
+
+    let (status, reply) = if !authorized {
+        (401, Vec::new())
+    } else {
+        let mut dav = state.lock().unwrap();
+        match method.as_str() {
+            "MKCOL" if dav.collections.contains(&path) => (405, Vec::new()),
+            "MKCOL" if !dav.collections.contains(&parent_of(&path)) => (409, Vec::new()),
+            "MKCOL" => {
+                dav.collections.insert(path);
+                (201, Vec::new())
+            }
+            "PUT" if !dav.collections.contains(&parent_of(&path)) => (409, Vec::new()),
+            "PUT" => {
+                dav.files.insert(path, body);
+                (201, Vec::new())
+            }
+            "MOVE" => {
+                let to = destination
+                    .as_deref()
+                    .and_then(|d| d.find("/dav/").map(|i| d[i..].to_string()));
+                match (dav.files.remove(&path), to) {
+                    (Some(data), Some(to)) => {
+                        dav.files.insert(to, data);
+                        (201, Vec::new())
+                    }
+                    _ => (404, Vec::new()),
+                }
+            }
+            "GET" => match dav.files.get(&path) {
+                Some(data) => (200, data.clone()),
+                None => (404, Vec::new()),
+            },
+            "DELETE" => match dav.files.remove(&path) {
+                Some(_) => (204, Vec::new()),
+                None => (404, Vec::new()),
+            },
+            "PROPFIND" if !dav.collections.contains(&path) => (404, Vec::new()),
+            "PROPFIND" => {
+                let mut xml =
+                    String::from(r#"<?xml version="1.0"?><d:multistatus xmlns:d="DAV:">"#);
+                let mut entry = |href: &str, collection: bool| {
+                    let kind = if collection { "<d:collection/>" } else { "" };
+                    xml.push_str(&format!(
+                        "<d:response><d:href>{href}</d:href><d:propstat><d:prop>\
+                         <d:resourcetype>{kind}</d:resourcetype></d:prop></d:propstat></d:response>"
+                    ));
+                };
+                entry(&path, true);
+                for c in dav
+                    .collections
+                    .iter()
+                    .filter(|c| parent_of(c) == path && **c != path)
+                {
+                    entry(c, true);
+                }
+                for f in dav.files.keys().filter(|f| parent_of(f) == path) {
+                    entry(f, false);
+                }
+                xml.push_str("</d:multistatus>");
+                (207, xml.into_bytes())
+            }
+            _ => (405, Vec::new()),
+        }
+    };
+    let _ = request.respond(tiny_http::Response::from_data(reply).with_status_code(status));
+}
+
+// ── Helpers ──────────────────────────────────────────────────────────────────
+
+fn make_key() -> SigningKey {
+    SigningKey::generate(&mut OsRng)
+}
+
+fn b64_pubkey(key: &SigningKey) -> String {
+    BASE64.encode(key.verifying_key().as_bytes())
+}
+
+fn make_contact_manager(enc_key: [u8; 32]) -> (tempfile::TempDir, ContactManager) {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let cm = ContactManager::for_identity(dir.path().to_path_buf(), enc_key)
+        .expect("ContactManager::for_identity");
+    (dir, cm)
+}
+
+fn webdav_channel(key: &SigningKey, account: &WebDavAccount) -> WebDavChannel {
+    let mut channel = WebDavChannel::new(&b64_pubkey(key));
+    channel.add_account(account).expect("add_account");
+    channel
+}
+
+// ── Tests ────────────────────────────────────────────────────────────────────
+
+/// Alice and Bob each poll through a `SyncEngine` with a `WebDavChannel`:
+/// Alice's delta lands in Bob's inbox, Bob applies it and the bundle is
+/// deleted from the server once acknowledged.
+#[test]
+fn webdav_sync_engine_roundtrip() {
+    let dav = FakeDav::start();
+    let alice_key = make_key();
+    let bob_key = make_key();
+    let alice_pub = b64_pubkey(&alice_key);
+    let bob_pub = b64_pubkey(&bob_key);
+    let alice_account = dav.account("alice", "pw");
+    let bob_account = dav.account("bob", "pw");
+
+    // Alice's workspace with Bob as a WebDAV peer.
+    let alice_tmp = NamedTempFile::new().unwrap();
+    let mut alice_ws = Workspace::create(
+        alice_tmp.path(),
+        "",
+        "alice-id",
+        SigningKey::from_bytes(&alice_key.to_bytes()),
+        Box::new(AllowAllGate::new("test")),
+        None,
+    )
+    .unwrap();
+    let (_alice_cm_dir, mut alice_cm) = make_contact_manager([0x44u8; 32]);
+    alice_cm
+        .find_or_create_by_public_key("Bob", &bob_pub, TrustLevel::Tofu)
+        .unwrap();
+    alice_ws
+        .upsert_sync_peer("dev-bob", &bob_pub, None, None)
+        .unwrap();
+    let alice_params = serde_json::json!({
+        "webdav_account_id": alice_account.webdav_account_id.to_string()
+    });
+    alice_ws
+        .update_peer_channel("dev-bob", "webdav", &alice_params.to_string())
+        .unwrap();
+    let note_id = alice_ws.create_note_root("TextNote").unwrap();
+
+    // Bob's empty replica of the same workspace, with Alice as a WebDAV peer.
+    let bob_tmp = NamedTempFile::new().unwrap();
+    let mut bob_ws = Workspace::create_with_id(
+        bob_tmp.path(),
+        "",
+        "bob-id",
+        SigningKey::from_bytes(&bob_key.to_bytes()),
+        alice_ws.workspace_id(),
+        Box::new(AllowAllGate::new("test")),
+        None,
+    )
+    .unwrap();
+    bob_ws.set_owner_pubkey(&alice_pub).unwrap();
+    let (_bob_cm_dir, mut bob_cm) = make_contact_manager([0x55u8; 32]);
+    bob_ws
+        .upsert_sync_peer("dev-alice", &alice_pub, None, None)
+        .unwrap();
+    let bob_params = serde_json::json!({
+        "webdav_account_id": bob_account.webdav_account_id.to_string()
+    });
+    bob_ws
+        .update_peer_channel("dev-alice", "webdav", &bob_params.to_string())
+        .unwrap();
+
+    // ── Alice polls: the delta is uploaded to Bob's inbox ───────────────────
+    let mut alice_engine = SyncEngine::new();
+    alice_engine.register_channel(Box::new(webdav_channel(&alice_key, &alice_account)));
+    let events = alice_engine
+        .poll(
+            &mut alice_ws,
+            &mut SyncContext {
+                signing_key: &alice_key,
+                contact_manager: &mut alice_cm,
+                workspace_name: "WebDavWorkspace",
+                sender_display_name: "Alice",
+            },
+        )
+        .unwrap();
+    assert!(
+        events
+            .iter()
+            .any(|e| matches!(e, SyncEvent::DeltaSent { .. })),
+        "expected DeltaSent, got {events:?}"
+    );
+    let inbox = format!("/dav/{}/{}/", alice_ws.workspace_id(), inbox_name(&bob_pub));
+    let files = dav.files();
+    assert_eq!(files.len(), 1, "{files:?}");
+    assert!(files[0].starts_with(&inbox) && files[0].ends_with(".swarm"));
+
+    // ── Bob polls: the delta is downloaded, applied and acknowledged ────────
+    let mut bob_engine = SyncEngine::new();
+    bob_engine.register_channel(Box::new(webdav_channel(&bob_key, &bob_account)));
+    let events = bob_engine
+        .poll(
+            &mut bob_ws,
+            &mut SyncContext {
+                signing_key: &bob_key,
+                contact_manager: &mut bob_cm,
+                workspace_name: "WebDavWorkspace",
+                sender_display_name: "Bob",
+            },
+        )
+        .unwrap();
+    assert!(
+        events
+            .iter()
+            .any(|e| matches!(e, SyncEvent::BundleApplied { .. })),
+        "expected BundleApplied, got {events:?}"
+    );
+    assert!(bob_ws.get_note(&note_id).is_ok());
+    assert!(
+        !dav.files().iter().any(|f| f.starts_with(&inbox)),
+        "Bob's inbox should be empty after acknowledge"
+    );
+}
+
+/// Only bundles addressed to the polling identity are returned, and
+/// `.part` uploads are never picked up.
+#[test]
+fn webdav_channel_reads_only_own_inbox() {
+    let dav = FakeDav::start();
+    let account = dav.account("alice", "pw");
+    let me = make_key();
+    let channel = webdav_channel(&me, &account);
+    {
+        let mut state = dav.state.lock().unwrap();
+        let mine = format!("/dav/ws-1/{}/", inbox_name(&b64_pubkey(&me)));
+        let theirs = format!("/dav/ws-1/{}/", inbox_name(&b64_pubkey(&make_key())));
+        for dir in ["/dav/ws-1/", mine.as_str(), theirs.as_str()] {
+            state.collections.insert(dir.to_string());
+        }
+        state
+            .files
+            .insert(format!("{mine}20260101000000_a.swarm"), b"mine".to_vec());
+        state.files.insert(
+            format!("{mine}20260101000000_b.swarm.part"),
+            b"partial".to_vec(),
+        );
+        state.files.insert(
+            format!("{theirs}20260101000000_c.swarm"),
+            b"theirs".to_vec(),
+        );
+    }
+
+    let bundles = channel.receive_bundles("ws-1").unwrap();
+    assert_eq!(bundles.len(), 1);
+    assert_eq!(bundles[0].data, b"mine");
+    // Another workspace's inbox is not created or read.
+    assert!(channel.receive_bundles("ws-2").unwrap().is_empty());
+}
+
+/// Wrong credentials surface as a `WebDav` error instead of an empty inbox.
+#[test]
+fn webdav_channel_reports_auth_failure() {
+    let dav = FakeDav::start();
+    let channel = webdav_channel(&make_key(), &dav.account("alice", "wrong"));
+    match channel.receive_bundles("ws-1") {
+        Err(KrillnotesError::WebDav(msg)) => assert!(msg.contains("401"), "{msg}"),
+        other => panic!("expected WebDav auth error, got {other:?}"),
+    }
+}
@@ -0,0 +1,238 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Automatic locking of unlocked identities.
+//!
+//! A background thread started at launch locks every unlocked identity once
+//! the user has been idle for [`AppSettings::auto_lock_minutes`], or — with
+//! [`AppSettings::lock_on_sleep`] — when the wall clock jumps between two
+//! ticks, meaning the computer slept.
+//!
+//! Unlike `lock_identity`, an automatic lock keeps the identity's workspace
+//! windows open. Their workspaces are closed and the windows are parked in
+//! [`AppState::suspended_windows`] behind an unlock prompt, so edits still in
+//! the editor survive; `unlock_identity` reopens them.
+
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use uuid::Uuid;
+
+use crate::settings::AppSettings;
+use crate::AppState;
+
+/// How often the watcher checks for idleness.
+const TICK: Duration = Duration::from_secs(15);
+
+/// Wall-clock time between two ticks beyond [`TICK`] taken to mean the
+/// computer slept.
+const SLEEP_GAP: Duration = Duration::from_secs(60);
+
+/// Why identities were locked automatically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LockReason {
+    Idle,
+    Sleep,
+}
+
+/// Payload of the `identity-locked` event sent to each suspended window.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityLockedEvent {
+    pub identity_uuid: String,
+    pub identity_name: String,
+    pub reason: LockReason,
+}
+
+/// Decides whether to lock, given how long the user has been idle and how
+/// much wall-clock time passed since the previous tick.
+pub(crate) fn lock_reason(
+    settings: &AppSettings,
+    idle: Duration,
+    since_last_tick: Duration,
+) -> Option<LockReason> {
+    if settings.lock_on_sleep && since_last_tick > TICK + SLEEP_GAP {
+        return Some(LockReason::Sleep);
+    }
+    let limit = Duration::from_secs(u64::from(settings.auto_lock_minutes) * 60);
+    (settings.auto_lock_minutes > 0 && idle >= limit).then_some(LockReason::Idle)
+}
+
+/// Starts the auto-lock watcher thread.
+pub fn spawn_watcher(app: AppHandle) {
+    let spawned = std::thread::Builder::new()
+        .name("auto-lock".to_string())
+        .spawn(move || {
+            let mut last_tick = SystemTime::now();
+            loop {
+                std::thread::sleep(TICK);
+                let now = SystemTime::now();
+                let since_last_tick = now.duration_since(last_tick).unwrap_or_default();
+                last_tick = now;
+
+                let state = app.state::<AppState>();
+                let idle = state
+                    .last_activity
+                    .lock()
+                    .expect("Mutex poisoned")
+                    .elapsed();
+                let settings = crate::settings::load_settings();
+                if let Some(reason) = lock_reason(&settings, idle, since_last_tick) {
+                    lock_all(&app, &state, reason);
+                }
+            }
+        });
+    if let Err(e) = spawned {
+        log::error!("Failed to start auto-lock watcher: {e}");
+    }
+}
+
+/// Locks every unlocked identity, suspending its workspace windows.
+fn lock_all(app: &AppHandle, state: &AppState, reason: LockReason) {
+    let identities: Vec<(Uuid, String)> = state
+        .unlocked_identities
+        .lock()
+        .expect("Mutex poisoned")
+        .iter()
+        .map(|(uuid, id)| (*uuid, id.display_name.clone()))
+        .collect();
+    for (uuid, name) in identities {
+        log::info!("Auto-locking identity {uuid} ({reason:?})");
+        suspend_identity(app, state, uuid, &name, reason);
+    }
+}
+
+/// Closes the workspaces of `uuid`'s windows without closing the windows,
+/// then wipes the identity from memory.
+fn suspend_identity(
+    app: &AppHandle,
+    state: &AppState,
+    uuid: Uuid,
+    identity_name: &str,
+    reason: LockReason,
+) {
+    let labels: Vec<String> = state
+        .workspace_identities
+        .lock()
+        .expect("Mutex poisoned")
+        .iter()
+        .filter(|(_, id)| **id == uuid)
+        .map(|(label, _)| label.clone())
+        .collect();
+    for label in &labels {
+        crate::commands::sync::stop_sync_daemon(state, label);
+        let workspace = state
+            .workspaces
+            .lock()
+            .expect("Mutex poisoned")
+            .remove(label);
+        if let Some(ws) = workspace {
+            let _ = ws.write_info_json();
+        }
+        state
+            .suspended_windows
+            .lock()
+            .expect("Mutex poisoned")
+            .insert(label.clone(), uuid);
+        let _ = app.emit_to(
+            label.as_str(),
+            "identity-locked",
+            IdentityLockedEvent {
+                identity_uuid: uuid.to_string(),
+                identity_name: identity_name.to_string(),
+                reason,
+            },
+        );
+    }
+    crate::commands::identity::wipe_identity(state, uuid);
+}
+
+/// Reopens the workspaces of windows suspended when `uuid` was locked.
+/// Called once the identity is unlocked again.
+pub(crate) fn resume_windows(app: &AppHandle, state: &AppState, uuid: Uuid) {
+    let labels: Vec<String> = state
+        .suspended_windows
+        .lock()
+        .expect("Mutex poisoned")
+        .iter()
+        .filter(|(_, id)| **id == uuid)
+        .map(|(label, _)| label.clone())
+        .collect();
+    for label in labels {
+        let folder = state
+            .workspace_paths
+            .lock()
+            .expect("Mutex poisoned")
+            .get(&label)
+            .cloned();
+        let Some(folder) = folder else {
+            state
+                .suspended_windows
+                .lock()
+                .expect("Mutex poisoned")
+                .remove(&label);
+            continue;
+        };
+        match crate::commands::workspace::open_bound_workspace(state, &folder, uuid) {
+            Ok(workspace) => {
+                state
+                    .workspaces
+                    .lock()
+                    .expect("Mutex poisoned")
+                    .insert(label.clone(), workspace);
+                state
+                    .suspended_windows
+                    .lock()
+                    .expect("Mutex poisoned")
+                    .remove(&label);
+                let _ = app.emit_to(label.as_str(), "identity-unlocked", ());
+            }
+            Err(e) => log::error!("Failed to reopen {} after unlock: {e}", folder.display()),
+        }
+    }
+}
+
+/// Records user activity, postponing the idle lock.
+pub(crate) fn touch(state: &AppState) {
+    *state.last_activity.lock().expect("Mutex poisoned") = Instant::now();
+}
+
+/// Records user activity. Called by the frontend on input, throttled.
+#[tauri::command]
+pub fn record_activity(state: tauri::State<'_, AppState>) {
+    touch(&state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock_reason_respects_settings() {
+        let minute = Duration::from_secs(60);
+        let mut settings = AppSettings::default();
+        assert_eq!(lock_reason(&settings, 600 * minute, 600 * minute), None);
+
+        settings.auto_lock_minutes = 10;
+        assert_eq!(lock_reason(&settings, 9 * minute, TICK), None);
+        assert_eq!(
+            lock_reason(&settings, 10 * minute, TICK),
+            Some(LockReason::Idle)
+        );
+
+        settings.lock_on_sleep = true;
+        assert_eq!(
+            lock_reason(&settings, Duration::ZERO, TICK + minute / 2),
+            None
+        );
+        assert_eq!(
+            lock_reason(&settings, Duration::ZERO, 30 * minute),
+            Some(LockReason::Sleep)
+        );
+    }
+}
@@ -215,15 +215,8 @@ pub async fn open_attachment(
     attachment_id: String,
     filename: String,
 ) -> std::result::Result<(), String> {
+    use std::io::Write;
     use tauri_plugin_opener::OpenerExt;
-    let bytes = {
-        let label = window.label();
-        let workspaces = state.workspaces.lock().expect("Mutex poisoned");
-        let workspace = workspaces.get(label).ok_or("No workspace open")?;
-        workspace
-            .get_attachment_bytes(&attachment_id)
-            .map_err(|e| e.to_string())?
-    };
 
     let tmp_dir = std::env::temp_dir().join("krillnotes-attachments");
     std::fs::create_dir_all(&tmp_dir).map_err(|e| e.to_string())?;
@@ -231,7 +224,24 @@ pub async fn open_attachment(
         .file_name()
         .ok_or("Invalid attachment filename")?;
     let tmp_path = tmp_dir.join(safe_name);
-    std::fs::write(&tmp_path, &bytes).map_err(|e| e.to_string())?;
+
+    // Decrypt straight to the temp file so large attachments never sit in memory.
+    {
+        let label = window.label();
+        let workspaces = state.workspaces.lock().expect("Mutex poisoned");
+        let workspace = workspaces.get(label).ok_or("No workspace open")?;
+        let mut out =
+            std::io::BufWriter::new(std::fs::File::create(&tmp_path).map_err(|e| e.to_string())?);
+        let copied = workspace
+            .copy_attachment_to(&attachment_id, &mut out)
+            .map_err(|e| e.to_string())
+            .and_then(|_| out.flush().map_err(|e| e.to_string()));
+        if let Err(e) = copied {
+            drop(out);
+            let _ = std::fs::remove_file(&tmp_path);
+            return Err(e);
+        }
+    }
 
     window
         .app_handle()
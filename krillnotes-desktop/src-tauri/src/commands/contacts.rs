@@ -19,6 +19,8 @@ pub struct ContactInfo {
     pub public_key: String,
     pub fingerprint: String,
     pub trust_level: String,
+    /// Contact ID of the voucher, when trusted through a vouch.
+    pub vouched_by: Option<String>,
     pub first_seen: String,
     pub notes: Option<String>,
 }
@@ -32,6 +34,7 @@ impl ContactInfo {
             public_key: c.public_key,
             fingerprint: c.fingerprint,
             trust_level: trust_level_to_str(&c.trust_level).to_string(),
+            vouched_by: c.vouched_by.map(|id| id.to_string()),
             first_seen: c.first_seen.to_rfc3339(),
             notes: c.notes,
         }
@@ -164,6 +167,179 @@ pub fn get_fingerprint(public_key: String) -> std::result::Result<String, String
     krillnotes_core::core::contact::generate_fingerprint(&public_key).map_err(|e| e.to_string())
 }
 
+// ── Verification commands ─────────────────────────────────────────
+
+/// Starts a SAS verification with a contact and returns its session ID.
+/// The opening message is sent on the next sync with the contact, or can be
+/// written to a file with `export_contact_verification`.
+#[tauri::command]
+pub fn start_contact_verification(
+    state: State<'_, AppState>,
+    identity_uuid: String,
+    contact_id: String,
+) -> std::result::Result<String, String> {
+    let uuid = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+    let cid = Uuid::parse_str(&contact_id).map_err(|e| e.to_string())?;
+    let signing_key = identity_signing_key(&state, &uuid)?;
+    let cms = state.contact_managers.lock().expect("Mutex poisoned");
+    let cm = cms.get(&uuid).ok_or("Identity not unlocked")?;
+    let msg = cm.start_sas_verification(&signing_key, cid).map_err(|e| {
+        log::error!("start_contact_verification failed: {e}");
+        e.to_string()
+    })?;
+    Ok(msg.session_id)
+}
+
+/// Lists SAS verifications in progress, with their codes once derived.
+#[tauri::command]
+pub fn list_contact_verifications(
+    state: State<'_, AppState>,
+    identity_uuid: String,
+) -> std::result::Result<Vec<krillnotes_core::SasSessionInfo>, String> {
+    let uuid = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+    let cms = state.contact_managers.lock().expect("Mutex poisoned");
+    let cm = cms.get(&uuid).ok_or("Identity not unlocked")?;
+    Ok(cm.pending_sas_verifications())
+}
+
+/// Confirms that both sides show the same code; the contact becomes
+/// `CodeVerified`.
+#[tauri::command]
+pub fn confirm_contact_verification(
+    state: State<'_, AppState>,
+    identity_uuid: String,
+    session_id: String,
+) -> std::result::Result<ContactInfo, String> {
+    let uuid = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+    let cms = state.contact_managers.lock().expect("Mutex poisoned");
+    let cm = cms.get(&uuid).ok_or("Identity not unlocked")?;
+    let contact = cm.confirm_sas_verification(&session_id).map_err(|e| {
+        log::error!("confirm_contact_verification failed: {e}");
+        e.to_string()
+    })?;
+    Ok(ContactInfo::from_contact(contact))
+}
+
+/// Abandons a SAS verification, e.g. because the codes did not match.
+#[tauri::command]
+pub fn cancel_contact_verification(
+    state: State<'_, AppState>,
+    identity_uuid: String,
+    session_id: String,
+) -> std::result::Result<(), String> {
+    let uuid = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+    let cms = state.contact_managers.lock().expect("Mutex poisoned");
+    let cm = cms.get(&uuid).ok_or("Identity not unlocked")?;
+    cm.cancel_sas_verification(&session_id);
+    Ok(())
+}
+
+/// Returns the `krillnotes-verify:` URI to show as a QR code for in-person
+/// verification.
+#[tauri::command]
+pub fn get_verification_qr(
+    state: State<'_, AppState>,
+    identity_uuid: String,
+) -> std::result::Result<String, String> {
+    let uuid = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+    let ids = state.unlocked_identities.lock().expect("Mutex poisoned");
+    let id = ids.get(&uuid).ok_or("Identity not unlocked")?;
+    let signing_key = krillnotes_core::Ed25519SigningKey::from_bytes(&id.signing_key.to_bytes());
+    Ok(krillnotes_core::QrVerificationPayload::new(&signing_key, &id.display_name).to_uri())
+}
+
+/// Verifies a scanned QR URI and marks its identity `VerifiedInPerson`,
+/// creating the contact if needed.
+#[tauri::command]
+pub fn verify_contact_qr(
+    state: State<'_, AppState>,
+    identity_uuid: String,
+    uri: String,
+) -> std::result::Result<ContactInfo, String> {
+    let uuid = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+    let payload =
+        krillnotes_core::QrVerificationPayload::from_uri(&uri).map_err(|e| e.to_string())?;
+    let cms = state.contact_managers.lock().expect("Mutex poisoned");
+    let cm = cms.get(&uuid).ok_or("Identity not unlocked")?;
+    let contact = cm.verify_in_person(&payload).map_err(|e| {
+        log::error!("verify_contact_qr failed: {e}");
+        e.to_string()
+    })?;
+    Ok(ContactInfo::from_contact(contact))
+}
+
+// ── Vouch commands ────────────────────────────────────────────────
+
+/// Vouches for a verified contact. Share the vouch with
+/// `export_vouches`.
+#[tauri::command]
+pub fn vouch_for_contact(
+    state: State<'_, AppState>,
+    identity_uuid: String,
+    contact_id: String,
+) -> std::result::Result<(), String> {
+    let uuid = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+    let cid = Uuid::parse_str(&contact_id).map_err(|e| e.to_string())?;
+    let signing_key = identity_signing_key(&state, &uuid)?;
+    let cms = state.contact_managers.lock().expect("Mutex poisoned");
+    let cm = cms.get(&uuid).ok_or("Identity not unlocked")?;
+    cm.issue_vouch(&signing_key, cid).map_err(|e| {
+        log::error!("vouch_for_contact failed: {e}");
+        e.to_string()
+    })?;
+    Ok(())
+}
+
+/// Withdraws this identity's vouch for a contact. Share the revocation with
+/// `export_vouches`.
+#[tauri::command]
+pub fn revoke_contact_vouch(
+    state: State<'_, AppState>,
+    identity_uuid: String,
+    contact_id: String,
+) -> std::result::Result<(), String> {
+    let uuid = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+    let cid = Uuid::parse_str(&contact_id).map_err(|e| e.to_string())?;
+    let signing_key = identity_signing_key(&state, &uuid)?;
+    let cms = state.contact_managers.lock().expect("Mutex poisoned");
+    let cm = cms.get(&uuid).ok_or("Identity not unlocked")?;
+    cm.revoke_vouch(&signing_key, cid).map_err(|e| {
+        log::error!("revoke_contact_vouch failed: {e}");
+        e.to_string()
+    })?;
+    Ok(())
+}
+
+/// Returns the chain of contacts through which a contact is trusted, from a
+/// directly verified contact to the contact itself.
+#[tauri::command]
+pub fn get_trust_path(
+    state: State<'_, AppState>,
+    identity_uuid: String,
+    contact_id: String,
+) -> std::result::Result<Vec<ContactInfo>, String> {
+    let uuid = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+    let cid = Uuid::parse_str(&contact_id).map_err(|e| e.to_string())?;
+    let cms = state.contact_managers.lock().expect("Mutex poisoned");
+    let cm = cms.get(&uuid).ok_or("Identity not unlocked")?;
+    let path = cm.trust_path(cid).map_err(|e| {
+        log::error!("get_trust_path failed: {e}");
+        e.to_string()
+    })?;
+    Ok(path.into_iter().map(ContactInfo::from_contact).collect())
+}
+
+pub(crate) fn identity_signing_key(
+    state: &State<'_, AppState>,
+    identity_uuid: &Uuid,
+) -> std::result::Result<krillnotes_core::Ed25519SigningKey, String> {
+    let ids = state.unlocked_identities.lock().expect("Mutex poisoned");
+    let id = ids.get(identity_uuid).ok_or("Identity not unlocked")?;
+    Ok(krillnotes_core::Ed25519SigningKey::from_bytes(
+        &id.signing_key.to_bytes(),
+    ))
+}
+
 // ── Peer commands ─────────────────────────────────────────────────
 
 /// Returns all sync peers registered for the calling window's workspace,
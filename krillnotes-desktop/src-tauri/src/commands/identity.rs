@@ -7,6 +7,7 @@
 use crate::AppState;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::sync::Arc;
 use tauri::State;
 use uuid::Uuid;
 
@@ -25,6 +26,20 @@ pub struct IdentityKeyInfo {
     pub fingerprint: String,
 }
 
+/// Time a single identity unlock should take on this machine; the target
+/// handed to [`krillnotes_core::benchmark_kdf_cost`].
+const KDF_BENCHMARK_TARGET: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Rejects passphrases below [`krillnotes_core::MIN_PASSPHRASE_STRENGTH`]
+/// with `PASSPHRASE_TOO_WEAK`.
+fn check_passphrase_strength(passphrase: &str) -> std::result::Result<(), String> {
+    if krillnotes_core::estimate_passphrase_strength(passphrase).acceptable {
+        Ok(())
+    } else {
+        Err("PASSPHRASE_TOO_WEAK".to_string())
+    }
+}
+
 // ── Identity commands ─────────────────────────────────────────────
 
 /// Lists all registered identities.
@@ -73,6 +88,9 @@ pub fn create_identity(
     display_name: String,
     passphrase: String,
 ) -> std::result::Result<crate::IdentityRef, String> {
+    use zeroize::Zeroize;
+
+    check_passphrase_strength(&passphrase)?;
     let mut mgr = state.identity_manager.lock().expect("Mutex poisoned");
     let file = mgr
         .create_identity(&display_name, &passphrase)
@@ -89,7 +107,7 @@ pub fn create_identity(
     let identity_dir = mgr.identity_dir(&uuid);
     drop(mgr); // Release the lock before acquiring unlocked_identities
                // Derive contacts key before consuming `unlocked` via insert
-    let contacts_key = unlocked.contacts_key();
+    let mut contacts_key = unlocked.contacts_key();
     state
         .unlocked_identities
         .lock()
@@ -109,6 +127,7 @@ pub fn create_identity(
             log::warn!("Failed to initialize contact manager for {uuid}: {e}");
         }
     }
+    contacts_key.zeroize();
     let invites_dir = identity_dir.join("invites");
     match krillnotes_core::core::invite::InviteManager::new(invites_dir) {
         Ok(im) => {
@@ -128,7 +147,7 @@ pub fn create_identity(
         let ids = state.unlocked_identities.lock().expect("Mutex poisoned");
         ids.get(&uuid).map(|u| u.relay_key())
     };
-    if let Some(relay_key) = relay_key {
+    if let Some(mut relay_key) = relay_key {
         let relays_dir = identity_dir.join("relays");
         match krillnotes_core::core::sync::relay::RelayAccountManager::for_identity(
             relays_dir, relay_key,
@@ -144,8 +163,57 @@ pub fn create_identity(
                 log::warn!("Failed to initialize relay account manager for {uuid}: {e}");
             }
         }
+        relay_key.zeroize();
+    }
+
+    // Initialize per-identity WebDavAccountManager (encrypted WebDAV accounts)
+    let webdav_key = {
+        let ids = state.unlocked_identities.lock().expect("Mutex poisoned");
+        ids.get(&uuid).map(|u| u.webdav_key())
+    };
+    if let Some(mut webdav_key) = webdav_key {
+        let webdav_dir = identity_dir.join("webdav");
+        match krillnotes_core::core::sync::webdav::WebDavAccountManager::for_identity(
+            webdav_dir, webdav_key,
+        ) {
+            Ok(webdav_mgr) => {
+                state
+                    .webdav_account_managers
+                    .lock()
+                    .expect("Mutex poisoned")
+                    .insert(uuid, webdav_mgr);
+            }
+            Err(e) => {
+                log::warn!("Failed to initialize WebDAV account manager for {uuid}: {e}");
+            }
+        }
+        webdav_key.zeroize();
     }
 
+    // Initialize per-identity S3AccountManager (encrypted S3 credentials)
+    let s3_key = {
+        let ids = state.unlocked_identities.lock().expect("Mutex poisoned");
+        ids.get(&uuid).map(|u| u.s3_key())
+    };
+    if let Some(mut s3_key) = s3_key {
+        let s3_dir = identity_dir.join("s3");
+        match krillnotes_core::core::sync::s3::S3AccountManager::for_identity(s3_dir, s3_key) {
+            Ok(s3_mgr) => {
+                state
+                    .s3_account_managers
+                    .lock()
+                    .expect("Mutex poisoned")
+                    .insert(uuid, s3_mgr);
+            }
+            Err(e) => {
+                log::warn!("Failed to initialize S3 account manager for {uuid}: {e}");
+            }
+        }
+        s3_key.zeroize();
+    }
+
+    start_lan_service(&state, uuid, &identity_dir);
+
     let accepted_dir = identity_dir.join("accepted_invites");
     match krillnotes_core::core::accepted_invite::AcceptedInviteManager::new(accepted_dir) {
         Ok(mgr) => {
@@ -184,12 +252,56 @@ pub fn create_identity(
 }
 
 /// Unlocks an identity and stores the unlocked state in memory.
+/// Starts the LAN sync listener for a freshly unlocked identity. Only
+/// identities in its contact list may deliver bundles. Non-fatal on failure.
+fn start_lan_service(state: &AppState, uuid: Uuid, identity_dir: &std::path::Path) {
+    let signing_key = {
+        let ids = state.unlocked_identities.lock().expect("Mutex poisoned");
+        match ids.get(&uuid) {
+            Some(id) => crate::Ed25519SigningKey::from_bytes(&id.signing_key.to_bytes()),
+            None => return,
+        }
+    };
+    let contact_managers = Arc::clone(&state.contact_managers);
+    let authorizer: krillnotes_core::core::sync::lan::PeerAuthorizer =
+        Arc::new(move |public_key: &str| {
+            contact_managers
+                .lock()
+                .ok()
+                .and_then(|cms| {
+                    cms.get(&uuid)
+                        .map(|cm| matches!(cm.find_by_public_key(public_key), Ok(Some(_))))
+                })
+                .unwrap_or(false)
+        });
+    match krillnotes_core::core::sync::LanService::start(
+        krillnotes_core::core::sync::LanConfig::default(),
+        signing_key,
+        identity_dir.join("lan_inbox"),
+        authorizer,
+    ) {
+        Ok(service) => {
+            state
+                .lan_services
+                .lock()
+                .expect("Mutex poisoned")
+                .insert(uuid, Arc::new(service));
+        }
+        Err(e) => {
+            log::warn!("Failed to start LAN sync for {uuid}: {e}");
+        }
+    }
+}
+
 #[tauri::command]
 pub fn unlock_identity(
+    app: tauri::AppHandle,
     state: State<'_, AppState>,
     identity_uuid: String,
     passphrase: String,
 ) -> std::result::Result<(), String> {
+    use zeroize::Zeroize;
+
     let uuid = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
     let mut mgr = state.identity_manager.lock().expect("Mutex poisoned");
     let unlocked = mgr
@@ -204,7 +316,7 @@ pub fn unlock_identity(
     let identity_dir = mgr.identity_dir(&uuid);
     drop(mgr);
     // Derive contacts key before consuming `unlocked` via insert
-    let contacts_key = unlocked.contacts_key();
+    let mut contacts_key = unlocked.contacts_key();
     state
         .unlocked_identities
         .lock()
@@ -225,6 +337,7 @@ pub fn unlock_identity(
             log::warn!("Failed to initialize contact manager for {uuid}: {e}");
         }
     }
+    contacts_key.zeroize();
     let invites_dir = identity_dir.join("invites");
     match krillnotes_core::core::invite::InviteManager::new(invites_dir) {
         Ok(im) => {
@@ -244,7 +357,7 @@ pub fn unlock_identity(
         let ids = state.unlocked_identities.lock().expect("Mutex poisoned");
         ids.get(&uuid).map(|u| u.relay_key())
     };
-    if let Some(relay_key) = relay_key {
+    if let Some(mut relay_key) = relay_key {
         let relays_dir = identity_dir.join("relays");
         match krillnotes_core::core::sync::relay::RelayAccountManager::for_identity(
             relays_dir, relay_key,
@@ -260,6 +373,53 @@ pub fn unlock_identity(
                 log::warn!("Failed to initialize relay account manager for {uuid}: {e}");
             }
         }
+        relay_key.zeroize();
+    }
+
+    // Initialize per-identity WebDavAccountManager (encrypted WebDAV accounts)
+    let webdav_key = {
+        let ids = state.unlocked_identities.lock().expect("Mutex poisoned");
+        ids.get(&uuid).map(|u| u.webdav_key())
+    };
+    if let Some(mut webdav_key) = webdav_key {
+        let webdav_dir = identity_dir.join("webdav");
+        match krillnotes_core::core::sync::webdav::WebDavAccountManager::for_identity(
+            webdav_dir, webdav_key,
+        ) {
+            Ok(webdav_mgr) => {
+                state
+                    .webdav_account_managers
+                    .lock()
+                    .expect("Mutex poisoned")
+                    .insert(uuid, webdav_mgr);
+            }
+            Err(e) => {
+                log::warn!("Failed to initialize WebDAV account manager for {uuid}: {e}");
+            }
+        }
+        webdav_key.zeroize();
+    }
+
+    // Initialize per-identity S3AccountManager (encrypted S3 credentials)
+    let s3_key = {
+        let ids = state.unlocked_identities.lock().expect("Mutex poisoned");
+        ids.get(&uuid).map(|u| u.s3_key())
+    };
+    if let Some(mut s3_key) = s3_key {
+        let s3_dir = identity_dir.join("s3");
+        match krillnotes_core::core::sync::s3::S3AccountManager::for_identity(s3_dir, s3_key) {
+            Ok(s3_mgr) => {
+                state
+                    .s3_account_managers
+                    .lock()
+                    .expect("Mutex poisoned")
+                    .insert(uuid, s3_mgr);
+            }
+            Err(e) => {
+                log::warn!("Failed to initialize S3 account manager for {uuid}: {e}");
+            }
+        }
+        s3_key.zeroize();
     }
 
     // ── Auto-refresh stale relay device keys ────────────────────────────
@@ -385,6 +545,10 @@ pub fn unlock_identity(
         }
     }
 
+    start_lan_service(&state, uuid, &identity_dir);
+    crate::autolock::touch(&state);
+    crate::autolock::resume_windows(&app, &state, uuid);
+
     let accepted_dir = identity_dir.join("accepted_invites");
     match krillnotes_core::core::accepted_invite::AcceptedInviteManager::new(accepted_dir) {
         Ok(mgr) => {
@@ -507,6 +671,7 @@ pub fn lock_identity(
             .lock()
             .expect("Mutex poisoned")
             .insert(label.clone());
+        crate::commands::sync::stop_sync_daemon(&state, label);
         if let Some(win) = app.get_webview_window(label) {
             let _ = win.destroy();
         }
@@ -527,7 +692,13 @@ pub fn lock_identity(
             .remove(label);
     }
 
-    // Wipe identity from memory.
+    wipe_identity(&state, uuid);
+    Ok(())
+}
+
+/// Wipes an unlocked identity from memory: its per-identity managers, which
+/// zeroize their derived keys on drop, then the identity and its signing key.
+pub(crate) fn wipe_identity(state: &AppState, uuid: Uuid) {
     // Remove per-identity managers first so there is no window where
     // the identity is "locked" but its managers are still live.
     state
@@ -545,6 +716,22 @@ pub fn lock_identity(
         .lock()
         .expect("Mutex poisoned")
         .remove(&uuid);
+    state
+        .webdav_account_managers
+        .lock()
+        .expect("Mutex poisoned")
+        .remove(&uuid);
+    state
+        .s3_account_managers
+        .lock()
+        .expect("Mutex poisoned")
+        .remove(&uuid);
+    // Dropping the service stops its listener and discovery threads.
+    state
+        .lan_services
+        .lock()
+        .expect("Mutex poisoned")
+        .remove(&uuid);
     state
         .accepted_invite_managers
         .lock()
@@ -560,7 +747,6 @@ pub fn lock_identity(
         .lock()
         .expect("Mutex poisoned")
         .remove(&uuid);
-    Ok(())
 }
 
 /// Deletes an identity. The identity must be locked first.
@@ -633,6 +819,7 @@ pub fn change_identity_passphrase(
     if !is_unlocked {
         return Err(format!("IDENTITY_LOCKED:{}", identity_uuid));
     }
+    check_passphrase_strength(&new_passphrase)?;
 
     let mut mgr = state.identity_manager.lock().expect("Mutex poisoned");
     mgr.change_passphrase(&uuid, &old_passphrase, &new_passphrase)
@@ -645,6 +832,157 @@ pub fn change_identity_passphrase(
         })
 }
 
+/// Estimates passphrase strength for live feedback while the user types a
+/// new passphrase. `create_identity` and `change_identity_passphrase` reject
+/// passphrases the estimate marks as not acceptable.
+#[tauri::command]
+pub fn get_passphrase_strength(passphrase: String) -> krillnotes_core::PassphraseEstimate {
+    krillnotes_core::estimate_passphrase_strength(&passphrase)
+}
+
+/// Measures the Argon2id cost this machine can afford, saves it in the
+/// settings and makes it the identity manager's policy. Identity files below
+/// the new policy are rehashed the next time they are unlocked.
+#[tauri::command]
+pub async fn calibrate_identity_kdf(
+    state: State<'_, AppState>,
+) -> std::result::Result<krillnotes_core::KdfCost, String> {
+    let policy =
+        tokio::task::spawn_blocking(|| krillnotes_core::benchmark_kdf_cost(KDF_BENCHMARK_TARGET))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| {
+                log::error!("calibrate_identity_kdf failed: {e}");
+                e.to_string()
+            })?;
+
+    let mut settings = crate::settings::load_settings();
+    settings.kdf_policy = Some(policy);
+    crate::settings::save_settings(&settings)?;
+
+    let mut mgr = state.identity_manager.lock().expect("Mutex poisoned");
+    mgr.set_kdf_policy(policy);
+    Ok(mgr.kdf_policy())
+}
+
+/// Replaces an unlocked identity's key with a new one, signed over by the old
+/// key. Open workspaces of the identity announce the rotation to their peers
+/// right away; the others do when next opened. The identity's contacts and
+/// sync accounts are re-opened under the new key.
+#[tauri::command]
+pub fn rotate_identity_key(
+    state: State<'_, AppState>,
+    identity_uuid: String,
+    passphrase: String,
+) -> std::result::Result<(), String> {
+    let uuid = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+
+    // Must be unlocked
+    let is_unlocked = state
+        .unlocked_identities
+        .lock()
+        .expect("Mutex poisoned")
+        .contains_key(&uuid);
+    if !is_unlocked {
+        return Err(format!("IDENTITY_LOCKED:{}", identity_uuid));
+    }
+
+    let mut mgr = state.identity_manager.lock().expect("Mutex poisoned");
+    let (unlocked, record) = mgr
+        .rotate_identity_key(&uuid, &passphrase)
+        .map_err(|e| match e {
+            crate::KrillnotesError::IdentityWrongPassphrase => "WRONG_PASSPHRASE".to_string(),
+            other => {
+                log::error!("rotate_identity_key(identity={identity_uuid}) failed: {other}");
+                other.user_message()
+            }
+        })?;
+    let identity_dir = mgr.identity_dir(&uuid);
+    drop(mgr);
+
+    let labels: Vec<String> = state
+        .workspace_identities
+        .lock()
+        .expect("Mutex poisoned")
+        .iter()
+        .filter(|(_, id)| **id == uuid)
+        .map(|(label, _)| label.clone())
+        .collect();
+    {
+        let mut workspaces = state.workspaces.lock().expect("Mutex poisoned");
+        for label in &labels {
+            if let Some(ws) = workspaces.get_mut(label) {
+                let key = krillnotes_core::Ed25519SigningKey::from_bytes(
+                    &unlocked.signing_key.to_bytes(),
+                );
+                if let Err(e) = ws.rotate_identity_key(key, &record) {
+                    log::error!("Failed to rotate key in workspace {label}: {e}");
+                }
+            }
+        }
+    }
+
+    match krillnotes_core::core::contact::ContactManager::for_identity(
+        identity_dir.join("contacts"),
+        unlocked.contacts_key(),
+    ) {
+        Ok(cm) => {
+            state
+                .contact_managers
+                .lock()
+                .expect("Mutex poisoned")
+                .insert(uuid, cm);
+        }
+        Err(e) => log::warn!("Failed to reopen contact manager for {uuid}: {e}"),
+    }
+    match krillnotes_core::core::sync::relay::RelayAccountManager::for_identity(
+        identity_dir.join("relays"),
+        unlocked.relay_key(),
+    ) {
+        Ok(relay_mgr) => {
+            state
+                .relay_account_managers
+                .lock()
+                .expect("Mutex poisoned")
+                .insert(uuid, relay_mgr);
+        }
+        Err(e) => log::warn!("Failed to reopen relay account manager for {uuid}: {e}"),
+    }
+    match krillnotes_core::core::sync::webdav::WebDavAccountManager::for_identity(
+        identity_dir.join("webdav"),
+        unlocked.webdav_key(),
+    ) {
+        Ok(webdav_mgr) => {
+            state
+                .webdav_account_managers
+                .lock()
+                .expect("Mutex poisoned")
+                .insert(uuid, webdav_mgr);
+        }
+        Err(e) => log::warn!("Failed to reopen WebDAV account manager for {uuid}: {e}"),
+    }
+    match krillnotes_core::core::sync::s3::S3AccountManager::for_identity(
+        identity_dir.join("s3"),
+        unlocked.s3_key(),
+    ) {
+        Ok(s3_mgr) => {
+            state
+                .s3_account_managers
+                .lock()
+                .expect("Mutex poisoned")
+                .insert(uuid, s3_mgr);
+        }
+        Err(e) => log::warn!("Failed to reopen S3 account manager for {uuid}: {e}"),
+    }
+
+    state
+        .unlocked_identities
+        .lock()
+        .expect("Mutex poisoned")
+        .insert(uuid, unlocked);
+    Ok(())
+}
+
 /// Returns the UUIDs of all currently unlocked identities.
 #[tauri::command]
 pub fn get_unlocked_identities(state: State<'_, AppState>) -> Vec<String> {
@@ -721,6 +1059,53 @@ pub fn export_swarmid_cmd(
     Ok(())
 }
 
+/// Return the 24-word recovery phrase of an identity, for the user to write down.
+/// Identity must already be unlocked (ownership proven via passphrase at unlock time).
+#[tauri::command]
+pub fn get_recovery_phrase(
+    state: State<'_, AppState>,
+    identity_uuid: String,
+) -> std::result::Result<String, String> {
+    let uuid = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+    let ids = state.unlocked_identities.lock().expect("Mutex poisoned");
+    let unlocked = ids
+        .get(&uuid)
+        .ok_or_else(|| format!("IDENTITY_LOCKED:{}", identity_uuid))?;
+    Ok(unlocked.recovery_phrase())
+}
+
+/// Rebuild an identity from its recovery phrase under a new passphrase.
+/// The identity is added in locked state, like an imported `.swarmid`; its
+/// workspaces still on disk are bound to it again.
+/// Returns `"IDENTITY_EXISTS:<name>"` if the identity is already registered.
+#[tauri::command]
+pub fn recover_identity_from_mnemonic(
+    state: State<'_, AppState>,
+    phrase: String,
+    display_name: String,
+    passphrase: String,
+) -> std::result::Result<crate::IdentityRef, String> {
+    let mut mgr = state.identity_manager.lock().expect("Mutex poisoned");
+    let uuid = mgr
+        .recover_identity_from_mnemonic(&phrase, &display_name, &passphrase)
+        .map_err(|e| match e {
+            crate::KrillnotesError::IdentityAlreadyExists(name) => {
+                format!("IDENTITY_EXISTS:{name}")
+            }
+            other => {
+                log::error!("recover_identity_from_mnemonic failed: {other}");
+                other.user_message()
+            }
+        })?
+        .identity
+        .identity_uuid;
+    mgr.list_identities()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|i| i.uuid == uuid)
+        .ok_or_else(|| "Identity recovered but not found in registry".to_string())
+}
+
 /// Return the Base64-encoded Ed25519 public key and 4-word fingerprint for the given identity.
 /// No passphrase required — the public key is stored unencrypted on disk.
 #[tauri::command]
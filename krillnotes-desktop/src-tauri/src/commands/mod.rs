@@ -13,10 +13,12 @@ pub mod notes;
 pub mod permissions;
 pub mod receive_poll;
 pub mod relay_accounts;
+pub mod s3_accounts;
 pub mod scripting;
 pub mod scripts;
 pub mod swarm;
 pub mod sync;
+pub mod webdav_accounts;
 pub mod workspace;
 
 pub use accepted_invites::*;
@@ -28,8 +30,10 @@ pub use notes::*;
 pub use permissions::*;
 pub use receive_poll::*;
 pub use relay_accounts::*;
+pub use s3_accounts::*;
 pub use scripting::*;
 pub use scripts::*;
 pub use swarm::*;
 pub use sync::*;
+pub use webdav_accounts::*;
 pub use workspace::*;
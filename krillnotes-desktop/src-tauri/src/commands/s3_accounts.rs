@@ -0,0 +1,136 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Tauri commands for S3 account CRUD.
+//!
+//! A peer is switched to S3 with `update_peer_channel(peer, "s3",
+//! {"s3_account_id": "<uuid>"})`.
+
+use crate::AppState;
+use serde::Serialize;
+use tauri::State;
+use uuid::Uuid;
+
+use krillnotes_core::core::sync::s3::{S3Account, S3Client};
+
+/// S3 account info returned to the frontend. Never exposes the secret key.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct S3AccountInfo {
+    pub s3_account_id: String,
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    pub prefix: String,
+    pub path_style: bool,
+    pub access_key_id: String,
+}
+
+impl S3AccountInfo {
+    fn from_account(a: &S3Account) -> Self {
+        Self {
+            s3_account_id: a.s3_account_id.to_string(),
+            endpoint: a.endpoint.clone(),
+            region: a.region.clone(),
+            bucket: a.bucket.clone(),
+            prefix: a.prefix.clone(),
+            path_style: a.path_style,
+            access_key_id: a.access_key_id.clone(),
+        }
+    }
+}
+
+// ── list_s3_accounts ───────────────────────────────────────────────────────
+
+/// List all S3 accounts for the given identity.
+#[tauri::command]
+pub fn list_s3_accounts(
+    state: State<'_, AppState>,
+    identity_uuid: String,
+) -> Result<Vec<S3AccountInfo>, String> {
+    let uuid = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+    let managers = state.s3_account_managers.lock().expect("Mutex poisoned");
+    let mgr = managers.get(&uuid).ok_or("Identity not unlocked")?;
+    let accounts = mgr.list_s3_accounts().map_err(|e| {
+        log::error!("list_s3_accounts failed: {e}");
+        e.to_string()
+    })?;
+    Ok(accounts.iter().map(S3AccountInfo::from_account).collect())
+}
+
+// ── add_s3_account ─────────────────────────────────────────────────────────
+
+/// Check that the bucket can be listed with the given credentials, then
+/// store them encrypted for the identity.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn add_s3_account(
+    state: State<'_, AppState>,
+    identity_uuid: String,
+    endpoint: String,
+    region: String,
+    bucket: String,
+    prefix: String,
+    path_style: bool,
+    access_key_id: String,
+    secret_access_key: String,
+) -> Result<S3AccountInfo, String> {
+    log::debug!("add_s3_account(identity={identity_uuid}, endpoint={endpoint}, bucket={bucket})");
+    let uuid = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+    let account = S3Account {
+        s3_account_id: Uuid::nil(),
+        endpoint,
+        region,
+        bucket,
+        prefix,
+        path_style,
+        access_key_id,
+        secret_access_key,
+    };
+
+    // S3Client uses reqwest::blocking — must run in spawn_blocking.
+    let check = account.clone();
+    tokio::task::spawn_blocking(move || S3Client::new(&check)?.check_access())
+        .await
+        .map_err(|e| {
+            log::error!("add_s3_account spawn_blocking join failed: {e}");
+            e.to_string()
+        })?
+        .map_err(|e| {
+            log::warn!(
+                "add_s3_account: access check failed for bucket {}: {e}",
+                account.bucket
+            );
+            e.user_message()
+        })?;
+
+    let managers = state.s3_account_managers.lock().expect("Mutex poisoned");
+    let mgr = managers.get(&uuid).ok_or("Identity not unlocked")?;
+    let account = mgr.create_s3_account(account).map_err(|e| {
+        log::error!("add_s3_account: create_s3_account failed: {e}");
+        e.to_string()
+    })?;
+    Ok(S3AccountInfo::from_account(&account))
+}
+
+// ── delete_s3_account ──────────────────────────────────────────────────────
+
+/// Delete a stored S3 account.
+#[tauri::command]
+pub fn delete_s3_account(
+    state: State<'_, AppState>,
+    identity_uuid: String,
+    s3_account_id: String,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+    let account_id = Uuid::parse_str(&s3_account_id).map_err(|e| e.to_string())?;
+    let managers = state.s3_account_managers.lock().expect("Mutex poisoned");
+    let mgr = managers.get(&uuid).ok_or("Identity not unlocked")?;
+    mgr.delete_s3_account(account_id).map_err(|e| {
+        log::error!("delete_s3_account failed: {e}");
+        e.to_string()
+    })
+}
@@ -7,6 +7,7 @@
 use crate::AppState;
 use krillnotes_core::Ed25519SigningKey;
 use krillnotes_core::Ed25519VerifyingKey;
+use krillnotes_core::{ReadScope, Workspace};
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Emitter, Manager, State};
 use uuid::Uuid;
@@ -80,6 +81,26 @@ pub enum SwarmFileInfo {
         #[serde(rename = "targetIdentityName")]
         target_identity_name: Option<String>,
     },
+    RecoveryShare {
+        #[serde(rename = "workspaceName")]
+        workspace_name: String,
+        #[serde(rename = "senderDisplayName")]
+        sender_display_name: String,
+        #[serde(rename = "senderFingerprint")]
+        sender_fingerprint: String,
+    },
+    Verification {
+        #[serde(rename = "senderDisplayName")]
+        sender_display_name: String,
+        #[serde(rename = "senderFingerprint")]
+        sender_fingerprint: String,
+    },
+    Vouch {
+        #[serde(rename = "senderDisplayName")]
+        sender_display_name: String,
+        #[serde(rename = "senderFingerprint")]
+        sender_fingerprint: String,
+    },
 }
 
 /// Peek at a .swarm file and return its type + display metadata.
@@ -241,7 +262,41 @@ pub fn open_swarm_file_cmd(
                 target_identity_name,
             })
         }
+        SwarmMode::RecoveryShare => Ok(SwarmFileInfo::RecoveryShare {
+            workspace_name: header.workspace_name,
+            sender_display_name: header.source_display_name,
+            sender_fingerprint: fingerprint,
+        }),
+        SwarmMode::Verification => Ok(SwarmFileInfo::Verification {
+            sender_display_name: header.source_display_name,
+            sender_fingerprint: fingerprint,
+        }),
+        SwarmMode::Vouch => Ok(SwarmFileInfo::Vouch {
+            sender_display_name: header.source_display_name,
+            sender_fingerprint: fingerprint,
+        }),
+    }
+}
+
+/// Snapshot JSON for a bundle shared by `peer_public_keys`, filtered to what
+/// they may read. One bundle can only serve recipients with the same scope.
+fn snapshot_json_for_recipients(
+    ws: &Workspace,
+    peer_public_keys: &[String],
+) -> std::result::Result<(Vec<u8>, Option<ReadScope>), String> {
+    let Some(first) = peer_public_keys.first() else {
+        return Ok((ws.to_snapshot_json().map_err(|e| e.to_string())?, None));
+    };
+    let (json, scope) = ws.to_snapshot_json_for(first).map_err(|e| e.to_string())?;
+    for pk in &peer_public_keys[1..] {
+        if ws.read_scope_for(pk).map_err(|e| e.to_string())? != scope {
+            return Err(
+                "These peers have access to different notes; send each of them a separate snapshot"
+                    .to_string(),
+            );
+        }
     }
+    Ok((json, scope))
 }
 
 /// Serialisable result returned after a snapshot bundle is written to disk.
@@ -297,6 +352,7 @@ pub async fn create_snapshot_for_peers(
         workspace_id,
         workspace_name,
         workspace_json,
+        scope,
         attachment_blobs,
         as_of_op_id,
         owner_pubkey,
@@ -316,7 +372,7 @@ pub async fn create_snapshot_for_peers(
         let owner_pubkey = ws.owner_pubkey().to_string();
         let protocol = ws.protocol_id().to_string();
 
-        let workspace_json = ws.to_snapshot_json().map_err(|e| e.to_string())?;
+        let (workspace_json, scope) = snapshot_json_for_recipients(ws, &peer_public_keys)?;
 
         // Get attachment metadata from the snapshot JSON to load blobs.
         let snapshot: krillnotes_core::core::workspace::WorkspaceSnapshot =
@@ -338,6 +394,7 @@ pub async fn create_snapshot_for_peers(
             workspace_id,
             workspace_name,
             workspace_json,
+            scope,
             attachment_blobs,
             as_of_op_id,
             owner_pubkey,
@@ -377,6 +434,7 @@ pub async fn create_snapshot_for_peers(
         if let Some(ws) = workspaces.get(window.label()) {
             for pk in &peer_public_keys {
                 let _ = ws.update_peer_last_sent_by_identity(pk, &as_of_op_id);
+                let _ = ws.record_peer_sent_scope_by_identity(pk, scope.as_ref());
             }
         }
     }
@@ -685,6 +743,46 @@ pub async fn apply_swarm_delta(
     .to_string())
 }
 
+/// Dry run of `apply_swarm_delta`: lists what applying the `.swarm` delta at
+/// `path` would change, without writing anything.
+#[tauri::command]
+pub async fn preview_swarm_delta(
+    state: State<'_, AppState>,
+    path: String,
+    identity_uuid: String,
+) -> std::result::Result<krillnotes_core::core::swarm::sync::BundlePreview, String> {
+    use krillnotes_core::core::swarm::sync::preview_bundle;
+
+    let identity_uuid_parsed = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+
+    let bundle_bytes = std::fs::read(&path).map_err(|e| e.to_string())?;
+
+    let recipient_key = {
+        let ids = state.unlocked_identities.lock().expect("Mutex poisoned");
+        let id = ids
+            .get(&identity_uuid_parsed)
+            .ok_or("Identity not unlocked")?;
+        Ed25519SigningKey::from_bytes(&id.signing_key.to_bytes())
+    };
+
+    let target_label = {
+        let identity_map = state.workspace_identities.lock().expect("Mutex poisoned");
+        identity_map
+            .iter()
+            .find(|(_, id)| **id == identity_uuid_parsed)
+            .map(|(lbl, _)| lbl.clone())
+            .ok_or("No open workspace for this identity")?
+    };
+
+    let cm_guard = state.contact_managers.lock().expect("Mutex poisoned");
+    let workspaces = state.workspaces.lock().expect("Mutex poisoned");
+    let ws = workspaces.get(&target_label).ok_or("Workspace not open")?;
+    let cm = cm_guard
+        .get(&identity_uuid_parsed)
+        .ok_or("Contact manager not available")?;
+    preview_bundle(&bundle_bytes, ws, &recipient_key, cm).map_err(|e| e.to_string())
+}
+
 /// Send a snapshot bundle to peers via the relay instead of saving to a file.
 ///
 /// Reuses the same bundle creation logic as `create_snapshot_for_peers` but
@@ -735,6 +833,7 @@ pub async fn send_snapshot_via_relay(
         workspace_id,
         workspace_name,
         workspace_json,
+        scope,
         attachment_blobs,
         as_of_op_id,
         owner_pubkey,
@@ -754,7 +853,7 @@ pub async fn send_snapshot_via_relay(
         let owner_pubkey = ws.owner_pubkey().to_string();
         let protocol = ws.protocol_id().to_string();
 
-        let workspace_json = ws.to_snapshot_json().map_err(|e| e.to_string())?;
+        let (workspace_json, scope) = snapshot_json_for_recipients(ws, &peer_public_keys)?;
 
         let snapshot: krillnotes_core::core::workspace::WorkspaceSnapshot =
             serde_json::from_slice(&workspace_json).map_err(|e| e.to_string())?;
@@ -775,6 +874,7 @@ pub async fn send_snapshot_via_relay(
             workspace_id,
             workspace_name,
             workspace_json,
+            scope,
             attachment_blobs,
             as_of_op_id,
             owner_pubkey,
@@ -888,6 +988,7 @@ pub async fn send_snapshot_via_relay(
         if let Some(ws) = workspaces.get(window.label()) {
             for pk in &peer_public_keys {
                 let _ = ws.update_peer_last_sent_by_identity(pk, &as_of_op_id);
+                let _ = ws.record_peer_sent_scope_by_identity(pk, scope.as_ref());
             }
         }
     }
@@ -904,6 +1005,54 @@ pub struct GenerateDeltasResult {
     files_written: Vec<String>,    // absolute paths of written .swarm files
 }
 
+/// Dry run of `generate_deltas_for_peers` for one peer: what the next delta
+/// would contain. The peer's watermark is left untouched.
+#[tauri::command]
+pub async fn preview_delta_for_peer(
+    window: tauri::Window,
+    state: State<'_, AppState>,
+    peer_device_id: String,
+) -> std::result::Result<krillnotes_core::core::swarm::sync::DeltaPreview, String> {
+    use krillnotes_core::core::swarm::sync::preview_delta;
+
+    let (signing_key, sender_display_name, workspace_name, identity_uuid) = {
+        let ids = state.unlocked_identities.lock().expect("Mutex poisoned");
+        let workspaces = state.workspaces.lock().expect("Mutex poisoned");
+        let ws = workspaces.get(window.label()).ok_or("Workspace not open")?;
+        let identity_uuid = Uuid::parse_str(ws.identity_uuid()).map_err(|e| e.to_string())?;
+        let id = ids.get(&identity_uuid).ok_or("Identity not unlocked")?;
+        let key = Ed25519SigningKey::from_bytes(&id.signing_key.to_bytes());
+
+        let paths = state.workspace_paths.lock().expect("Mutex poisoned");
+        let ws_name = paths
+            .get(window.label())
+            .and_then(|p| p.file_stem())
+            .and_then(|s| s.to_str())
+            .unwrap_or("Untitled")
+            .to_string();
+
+        (key, id.display_name.clone(), ws_name, identity_uuid)
+    };
+
+    let cm_guard = state.contact_managers.lock().expect("Mutex poisoned");
+    let mut workspaces = state.workspaces.lock().expect("Mutex poisoned");
+    let ws = workspaces
+        .get_mut(window.label())
+        .ok_or("Workspace not open")?;
+    let cm = cm_guard
+        .get(&identity_uuid)
+        .ok_or("Contact manager not available")?;
+    preview_delta(
+        ws,
+        &peer_device_id,
+        &workspace_name,
+        &signing_key,
+        &sender_display_name,
+        cm,
+    )
+    .map_err(|e| e.to_string())
+}
+
 /// Batch-generates one delta .swarm per selected peer into `dir_path`.
 ///
 /// Continues on per-peer errors so a single failure doesn't block the others.
@@ -1087,6 +1236,7 @@ pub async fn send_self_snapshot_via_relay(
         workspace_id,
         workspace_name,
         workspace_json,
+        scope,
         attachment_blobs,
         as_of_op_id,
         owner_pubkey,
@@ -1106,7 +1256,9 @@ pub async fn send_self_snapshot_via_relay(
         let owner_pubkey = ws.owner_pubkey().to_string();
         let protocol = ws.protocol_id().to_string();
 
-        let workspace_json = ws.to_snapshot_json().map_err(|e| e.to_string())?;
+        let (workspace_json, scope) = ws
+            .to_snapshot_json_for(&own_pubkey_b64)
+            .map_err(|e| e.to_string())?;
 
         let snapshot: krillnotes_core::core::workspace::WorkspaceSnapshot =
             serde_json::from_slice(&workspace_json).map_err(|e| e.to_string())?;
@@ -1127,6 +1279,7 @@ pub async fn send_self_snapshot_via_relay(
             workspace_id,
             workspace_name,
             workspace_json,
+            scope,
             attachment_blobs,
             as_of_op_id,
             owner_pubkey,
@@ -1224,6 +1377,7 @@ pub async fn send_self_snapshot_via_relay(
                 Some(&as_of_op_id),
                 None,
             );
+            let _ = ws.record_peer_sent_scope(&target_device_id_for_peer, scope.as_ref());
             // Set the relay channel on the peer.
             let rams = state.relay_account_managers.lock().expect("Mutex poisoned");
             if let Some(ram) = rams.get(&identity_uuid_parsed) {
@@ -1337,3 +1491,439 @@ pub async fn list_devices_on_relay(
 
     Ok(devices)
 }
+
+/// Removes another device's key from a relay account, e.g. after revoking the
+/// device with `revoke_device`. The relay refuses the key from then on and
+/// ends the account's other sessions.
+#[tauri::command]
+pub async fn remove_device_from_relay(
+    state: State<'_, AppState>,
+    identity_uuid: String,
+    relay_account_id: String,
+    device_key: String,
+) -> std::result::Result<(), String> {
+    use krillnotes_core::core::sync::relay::client::RelayClient;
+
+    log::debug!("remove_device_from_relay(identity={identity_uuid}, device_key={device_key})");
+    let identity_uuid_parsed = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+    let relay_account_uuid = Uuid::parse_str(&relay_account_id).map_err(|e| e.to_string())?;
+
+    let relay_account = {
+        let rams = state.relay_account_managers.lock().expect("Mutex poisoned");
+        let ram = rams
+            .get(&identity_uuid_parsed)
+            .ok_or("No relay account manager for this identity")?;
+        let accounts = ram.list_relay_accounts().map_err(|e| e.to_string())?;
+        accounts
+            .into_iter()
+            .find(|a| a.relay_account_id == relay_account_uuid)
+            .ok_or("Relay account not found")?
+    };
+    if device_key == relay_account.device_public_key {
+        return Err("Cannot remove this device's own relay key".to_string());
+    }
+
+    tokio::task::spawn_blocking(move || -> std::result::Result<(), String> {
+        // Auto-login if session expired.
+        let mut token = relay_account.session_token;
+        if relay_account.session_expires_at < chrono::Utc::now()
+            && !relay_account.password.is_empty()
+        {
+            let client = RelayClient::new(&relay_account.relay_url);
+            match client.login(
+                &relay_account.email,
+                &relay_account.password,
+                &relay_account.device_public_key,
+            ) {
+                Ok(session) => token = session.session_token,
+                Err(e) => log::warn!("remove_device_from_relay: auto-login failed: {e}"),
+            }
+        }
+        RelayClient::new(&relay_account.relay_url)
+            .with_session_token(&token)
+            .remove_device(&device_key)
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// ── Social recovery of Root Ownership ──────────────────────────────
+
+fn decode_verifying_key(pk_b64: &str) -> std::result::Result<Ed25519VerifyingKey, String> {
+    use base64::Engine;
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(pk_b64)
+        .map_err(|e| e.to_string())?;
+    let arr: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| "key wrong length".to_string())?;
+    Ed25519VerifyingKey::from_bytes(&arr).map_err(|e| e.to_string())
+}
+
+/// Sets up social recovery of the open workspace's Root Ownership and writes
+/// one recovery share bundle per contact into `dir_path`. Any `threshold` of
+/// the contacts can later release their shares to restore ownership.
+///
+/// Returns the paths of the bundles written.
+#[tauri::command]
+pub async fn set_up_root_recovery(
+    window: tauri::Window,
+    state: State<'_, AppState>,
+    identity_uuid: String,
+    contact_ids: Vec<String>,
+    threshold: u8,
+    dir_path: String,
+) -> std::result::Result<Vec<String>, String> {
+    use krillnotes_core::core::swarm::recovery::{
+        create_recovery_share_bundle, RecoveryShareParams,
+    };
+
+    let identity_uuid = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+    let dir = std::path::Path::new(&dir_path);
+    if !dir.exists() {
+        return Err(format!("Directory does not exist: {dir_path}"));
+    }
+    let share_count = u8::try_from(contact_ids.len()).map_err(|_| "Too many contacts")?;
+
+    let (signing_key, source_display_name) = {
+        let ids = state.unlocked_identities.lock().expect("Mutex poisoned");
+        let id = ids.get(&identity_uuid).ok_or("Identity not unlocked")?;
+        (
+            Ed25519SigningKey::from_bytes(&id.signing_key.to_bytes()),
+            id.display_name.clone(),
+        )
+    };
+    let source_device_id = krillnotes_core::get_device_id().map_err(|e| e.to_string())?;
+
+    // Resolve every contact before touching the workspace.
+    let contacts = {
+        let contact_managers = state.contact_managers.lock().expect("Mutex poisoned");
+        let cm = contact_managers
+            .get(&identity_uuid)
+            .ok_or("Contact manager not found — identity must be unlocked")?;
+        contact_ids
+            .iter()
+            .map(|cid| {
+                let cid = Uuid::parse_str(cid).map_err(|e| e.to_string())?;
+                cm.get_contact(cid)
+                    .map_err(|e| e.to_string())?
+                    .ok_or_else(|| "Contact not found".to_string())
+            })
+            .collect::<std::result::Result<Vec<_>, String>>()?
+    };
+
+    let (shares, workspace_name, protocol) = {
+        let mut workspaces = state.workspaces.lock().expect("Mutex poisoned");
+        let paths = state.workspace_paths.lock().expect("Mutex poisoned");
+        let ws = workspaces
+            .get_mut(window.label())
+            .ok_or("Workspace not open")?;
+        let workspace_name = paths
+            .get(window.label())
+            .and_then(|p| p.file_stem())
+            .and_then(|s| s.to_str())
+            .unwrap_or("Untitled")
+            .to_string();
+        let shares = ws
+            .set_up_root_recovery(threshold, share_count)
+            .map_err(|e| {
+                log::error!("set_up_root_recovery failed: {e}");
+                e.to_string()
+            })?;
+        (shares, workspace_name, ws.protocol_id().to_string())
+    };
+
+    let mut written = Vec::new();
+    for (contact, share) in contacts.iter().zip(&shares) {
+        let recipient_key = decode_verifying_key(&contact.public_key)?;
+        let bundle = create_recovery_share_bundle(RecoveryShareParams {
+            protocol: protocol.clone(),
+            workspace_name: workspace_name.clone(),
+            source_device_id: source_device_id.clone(),
+            source_display_name: source_display_name.clone(),
+            share,
+            sender_key: &signing_key,
+            recipient_key: &recipient_key,
+        })
+        .map_err(|e| e.to_string())?;
+
+        let safe_name: String = contact
+            .display_name()
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let file_path = dir.join(format!("recovery-share-{safe_name}.swarm"));
+        std::fs::write(&file_path, &bundle).map_err(|e| e.to_string())?;
+        written.push(file_path.to_string_lossy().to_string());
+    }
+    Ok(written)
+}
+
+/// Releases a recovery share held by this identity to the identity taking
+/// over the workspace: decrypts the bundle at `path` and re-encrypts the
+/// share for `recipient_public_key` at `save_path`.
+#[tauri::command]
+pub async fn release_recovery_share(
+    state: State<'_, AppState>,
+    identity_uuid: String,
+    path: String,
+    recipient_public_key: String,
+    save_path: String,
+) -> std::result::Result<(), String> {
+    use krillnotes_core::core::swarm::recovery::{
+        create_recovery_share_bundle, parse_recovery_share_bundle, RecoveryShareParams,
+    };
+
+    let identity_uuid = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+    let (signing_key, source_display_name) = {
+        let ids = state.unlocked_identities.lock().expect("Mutex poisoned");
+        let id = ids.get(&identity_uuid).ok_or("Identity not unlocked")?;
+        (
+            Ed25519SigningKey::from_bytes(&id.signing_key.to_bytes()),
+            id.display_name.clone(),
+        )
+    };
+    let source_device_id = krillnotes_core::get_device_id().map_err(|e| e.to_string())?;
+    let recipient_key = decode_verifying_key(&recipient_public_key)?;
+
+    let data = std::fs::read(&path).map_err(|e| format!("Cannot read file: {e}"))?;
+    let held = parse_recovery_share_bundle(&data, &signing_key).map_err(|e| e.to_string())?;
+    let protocol = krillnotes_core::core::swarm::header::read_header(&data)
+        .map_err(|e| e.to_string())?
+        .protocol;
+    let bundle = create_recovery_share_bundle(RecoveryShareParams {
+        protocol,
+        workspace_name: held.workspace_name,
+        source_device_id,
+        source_display_name,
+        share: &held.share,
+        sender_key: &signing_key,
+        recipient_key: &recipient_key,
+    })
+    .map_err(|e| e.to_string())?;
+    std::fs::write(&save_path, &bundle).map_err(|e| e.to_string())
+}
+
+/// Takes over Root Ownership of the open workspace with the shares released
+/// to this identity in the recovery share bundles at `paths`.
+#[tauri::command]
+pub async fn recover_root_ownership(
+    window: tauri::Window,
+    state: State<'_, AppState>,
+    identity_uuid: String,
+    paths: Vec<String>,
+) -> std::result::Result<(), String> {
+    use krillnotes_core::core::swarm::recovery::parse_recovery_share_bundle;
+
+    let identity_uuid = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+    let signing_key = {
+        let ids = state.unlocked_identities.lock().expect("Mutex poisoned");
+        let id = ids.get(&identity_uuid).ok_or("Identity not unlocked")?;
+        Ed25519SigningKey::from_bytes(&id.signing_key.to_bytes())
+    };
+    let shares = paths
+        .iter()
+        .map(|path| {
+            let data = std::fs::read(path).map_err(|e| format!("Cannot read file: {e}"))?;
+            parse_recovery_share_bundle(&data, &signing_key)
+                .map(|parsed| parsed.share)
+                .map_err(|e| e.to_string())
+        })
+        .collect::<std::result::Result<Vec<_>, String>>()?;
+
+    let mut workspaces = state.workspaces.lock().expect("Mutex poisoned");
+    let ws = workspaces
+        .get_mut(window.label())
+        .ok_or("Workspace not open")?;
+    ws.recover_root_ownership(&shares).map_err(|e| {
+        log::error!("recover_root_ownership failed: {e}");
+        e.to_string()
+    })
+}
+
+/// Writes the queued SAS verification messages for a contact to a .swarm
+/// file, for exchanging outside the open workspace's sync channels.
+#[tauri::command]
+pub async fn export_contact_verification(
+    window: tauri::Window,
+    state: State<'_, AppState>,
+    identity_uuid: String,
+    contact_id: String,
+    save_path: String,
+) -> std::result::Result<(), String> {
+    use krillnotes_core::core::swarm::verification::{
+        create_verification_bundle, VerificationBundleParams,
+    };
+
+    let identity_uuid = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+    let contact_id = Uuid::parse_str(&contact_id).map_err(|e| e.to_string())?;
+    let signing_key = super::contacts::identity_signing_key(&state, &identity_uuid)?;
+    let source_display_name = {
+        let ids = state.unlocked_identities.lock().expect("Mutex poisoned");
+        let id = ids.get(&identity_uuid).ok_or("Identity not unlocked")?;
+        id.display_name.clone()
+    };
+    let source_device_id = krillnotes_core::get_device_id().map_err(|e| e.to_string())?;
+    let (protocol, workspace_id, workspace_name) = {
+        let workspaces = state.workspaces.lock().expect("Mutex poisoned");
+        let paths = state.workspace_paths.lock().expect("Mutex poisoned");
+        let ws = workspaces.get(window.label()).ok_or("Workspace not open")?;
+        let workspace_name = paths
+            .get(window.label())
+            .and_then(|p| p.file_stem())
+            .and_then(|s| s.to_str())
+            .unwrap_or("Untitled")
+            .to_string();
+        (
+            ws.protocol_id().to_string(),
+            ws.workspace_id().to_string(),
+            workspace_name,
+        )
+    };
+
+    let cms = state.contact_managers.lock().expect("Mutex poisoned");
+    let cm = cms.get(&identity_uuid).ok_or("Identity not unlocked")?;
+    let recipient_key = cm
+        .get_contact(contact_id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Contact not found")?
+        .public_key;
+    let messages = cm.take_sas_messages_for(&recipient_key);
+    if messages.is_empty() {
+        return Err("No verification messages are waiting for this contact".to_string());
+    }
+    let written = create_verification_bundle(VerificationBundleParams {
+        protocol,
+        workspace_id,
+        workspace_name,
+        source_device_id,
+        source_display_name,
+        recipient_key,
+        messages: &messages,
+        sender_key: &signing_key,
+    })
+    .map_err(|e| e.to_string())
+    .and_then(|bundle| std::fs::write(&save_path, &bundle).map_err(|e| e.to_string()));
+    if let Err(e) = written {
+        log::error!("export_contact_verification failed: {e}");
+        cm.requeue_sas_messages(messages);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Reads SAS verification messages from a .swarm file and returns the
+/// verifications in progress. Replies are queued for the next export or sync.
+#[tauri::command]
+pub async fn import_contact_verification(
+    state: State<'_, AppState>,
+    identity_uuid: String,
+    path: String,
+) -> std::result::Result<Vec<krillnotes_core::SasSessionInfo>, String> {
+    use base64::Engine;
+    use krillnotes_core::core::swarm::verification::parse_verification_bundle;
+
+    let identity_uuid = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+    let signing_key = super::contacts::identity_signing_key(&state, &identity_uuid)?;
+    let data = std::fs::read(&path).map_err(|e| format!("Cannot read file: {e}"))?;
+    let parsed = parse_verification_bundle(&data).map_err(|e| e.to_string())?;
+    if parsed.recipient_key
+        != base64::engine::general_purpose::STANDARD.encode(signing_key.verifying_key().as_bytes())
+    {
+        return Err("This verification file is addressed to another identity".to_string());
+    }
+
+    let cms = state.contact_managers.lock().expect("Mutex poisoned");
+    let cm = cms.get(&identity_uuid).ok_or("Identity not unlocked")?;
+    for msg in &parsed.messages {
+        cm.receive_sas_message(&signing_key, msg).map_err(|e| {
+            log::error!("import_contact_verification failed: {e}");
+            e.to_string()
+        })?;
+    }
+    Ok(cm.pending_sas_verifications())
+}
+
+/// Writes every vouch and revocation this identity has issued to a .swarm
+/// file for its contacts to import.
+#[tauri::command]
+pub async fn export_vouches(
+    state: State<'_, AppState>,
+    identity_uuid: String,
+    save_path: String,
+) -> std::result::Result<usize, String> {
+    use base64::Engine;
+    use krillnotes_core::core::swarm::vouch::{create_vouch_bundle, VouchBundleParams};
+
+    let identity_uuid = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+    let signing_key = super::contacts::identity_signing_key(&state, &identity_uuid)?;
+    let source_display_name = {
+        let ids = state.unlocked_identities.lock().expect("Mutex poisoned");
+        let id = ids.get(&identity_uuid).ok_or("Identity not unlocked")?;
+        id.display_name.clone()
+    };
+    let source_device_id = krillnotes_core::get_device_id().map_err(|e| e.to_string())?;
+    let own_key =
+        base64::engine::general_purpose::STANDARD.encode(signing_key.verifying_key().as_bytes());
+    let statements = {
+        let cms = state.contact_managers.lock().expect("Mutex poisoned");
+        let cm = cms.get(&identity_uuid).ok_or("Identity not unlocked")?;
+        cm.issued_vouches(&own_key)
+    };
+    if statements.is_empty() {
+        return Err("You have not vouched for any contacts".to_string());
+    }
+    let bundle = create_vouch_bundle(VouchBundleParams {
+        protocol: "krillnotes/1".to_string(),
+        source_device_id,
+        source_display_name,
+        statements: &statements,
+        sender_key: &signing_key,
+    })
+    .map_err(|e| e.to_string())?;
+    std::fs::write(&save_path, &bundle).map_err(|e| e.to_string())?;
+    Ok(statements.len())
+}
+
+/// Imports the vouches in a .swarm file from one of this identity's
+/// contacts. Returns the contacts they are about.
+#[tauri::command]
+pub async fn import_vouches(
+    state: State<'_, AppState>,
+    identity_uuid: String,
+    path: String,
+) -> std::result::Result<Vec<crate::ContactInfo>, String> {
+    use base64::Engine;
+    use krillnotes_core::core::swarm::vouch::parse_vouch_bundle;
+
+    let identity_uuid = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+    let signing_key = super::contacts::identity_signing_key(&state, &identity_uuid)?;
+    let own_key =
+        base64::engine::general_purpose::STANDARD.encode(signing_key.verifying_key().as_bytes());
+    let data = std::fs::read(&path).map_err(|e| format!("Cannot read file: {e}"))?;
+    let parsed = parse_vouch_bundle(&data).map_err(|e| e.to_string())?;
+
+    let cms = state.contact_managers.lock().expect("Mutex poisoned");
+    let cm = cms.get(&identity_uuid).ok_or("Identity not unlocked")?;
+    let mut subjects = Vec::new();
+    // Vouches about this identity itself say nothing about its contacts.
+    for statement in parsed
+        .statements
+        .iter()
+        .filter(|v| v.subject_key != own_key)
+    {
+        let subject = cm.receive_vouch(statement).map_err(|e| {
+            log::error!("import_vouches failed: {e}");
+            e.to_string()
+        })?;
+        subjects.extend(subject.map(crate::ContactInfo::from_contact));
+    }
+    Ok(subjects)
+}
@@ -9,12 +9,16 @@
 use crate::AppState;
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use krillnotes_core::core::sync::relay::{RelayAccount, RelayChannel, RelayClient};
+use krillnotes_core::core::sync::s3::{S3Account, S3Channel};
+use krillnotes_core::core::sync::webdav::{WebDavAccount, WebDavChannel};
 use krillnotes_core::core::{
     device::get_device_id,
-    sync::{FolderChannel, SyncContext, SyncEngine, SyncEvent},
+    sync::{
+        FolderChannel, GitChannel, LanChannel, PeerSyncInfo, SchedulerConfig, SyncContext,
+        SyncDaemon, SyncEngine, SyncEvent,
+    },
 };
-use std::sync::Arc;
-use tauri::{Emitter, State, Window};
+use tauri::{AppHandle, Emitter, Manager, State, Window};
 use uuid::Uuid;
 
 // ── update_peer_channel ────────────────────────────────────────────────────
@@ -50,17 +54,52 @@ pub async fn update_peer_channel(
 
 /// Run one sync poll cycle for the current workspace window.
 ///
-/// Builds a fresh `SyncEngine` with the `FolderChannel` registered, runs one
-/// `poll()` cycle, and returns the resulting `SyncEvent` list as JSON.
+/// Runs [`run_sync_cycle`] for every peer and returns the resulting
+/// `SyncEvent` list as JSON.
 #[tauri::command]
-pub async fn poll_sync(
-    window: Window,
-    state: State<'_, AppState>,
-) -> Result<Vec<SyncEvent>, String> {
+pub async fn poll_sync(window: Window) -> Result<Vec<SyncEvent>, String> {
     log::debug!("poll_sync(window={})", window.label());
+    let app = window.app_handle().clone();
     let workspace_label = window.label().to_string();
 
-    // -- Collect context data under brief locks (all guards released before spawn) --
+    // RelayChannel holds a reqwest::blocking::Client which owns an internal Tokio
+    // runtime. Creating, using, and dropping it must happen on a spawn_blocking
+    // thread (no outer tokio context) — block_in_place is insufficient because the
+    // outer runtime context is still present on the thread, causing a panic on drop.
+    let events =
+        tokio::task::spawn_blocking(move || run_sync_cycle(&app, &workspace_label, &mut |_| true))
+            .await
+            .map_err(|e| {
+                log::error!("poll_sync spawn_blocking join failed: {e}");
+                e.to_string()
+            })??;
+
+    // If any bundles were applied, notify WorkspaceView to reload the note tree.
+    let bundles_applied = events
+        .iter()
+        .any(|e| matches!(e, SyncEvent::BundleApplied { .. }));
+    if bundles_applied {
+        let _ = window.emit("workspace-updated", ());
+    }
+
+    Ok(events)
+}
+
+/// Runs one sync cycle for the workspace in window `workspace_label`,
+/// limited to the peers `include` accepts.
+///
+/// Builds a fresh `SyncEngine` with the folder, git, LAN, WebDAV, S3 and relay
+/// channels registered. Blocks on network I/O, so it must run on a blocking
+/// thread: `poll_sync`'s `spawn_blocking` or the background sync daemon.
+fn run_sync_cycle(
+    app: &AppHandle,
+    workspace_label: &str,
+    include: &mut dyn FnMut(&PeerSyncInfo) -> bool,
+) -> Result<Vec<SyncEvent>, String> {
+    let state = app.state::<AppState>();
+    let workspace_label = workspace_label.to_string();
+
+    // -- Collect context data under brief locks --
     let identity_uuid = {
         let m = state
             .workspace_identities
@@ -92,7 +131,7 @@ pub async fn poll_sync(
         format!("{}:identity:{}", short, identity_uuid)
     };
 
-    // Load all relay accounts from RelayAccountManager (clone before spawn_blocking)
+    // Load all relay accounts from RelayAccountManager
     let relay_accounts: Vec<RelayAccount> = {
         let ram = state
             .relay_account_managers
@@ -105,6 +144,37 @@ pub async fn poll_sync(
         }
     };
 
+    // WebDAV accounts are all registered on one WebDavChannel; each peer
+    // picks its account via channel_params.webdav_account_id.
+    let webdav_accounts: Vec<WebDavAccount> = {
+        let wam = state
+            .webdav_account_managers
+            .lock()
+            .map_err(|e| e.to_string())?;
+        wam.get(&identity_uuid)
+            .map(|mgr| mgr.list_webdav_accounts().unwrap_or_default())
+            .unwrap_or_default()
+    };
+
+    // Likewise for S3 buckets, chosen via channel_params.s3_account_id.
+    let s3_accounts: Vec<S3Account> = {
+        let sam = state
+            .s3_account_managers
+            .lock()
+            .map_err(|e| e.to_string())?;
+        sam.get(&identity_uuid)
+            .map(|mgr| mgr.list_s3_accounts().unwrap_or_default())
+            .unwrap_or_default()
+    };
+
+    // The LAN listener outlives the poll; the channel only borrows it.
+    let lan_service = state
+        .lan_services
+        .lock()
+        .map_err(|e| e.to_string())?
+        .get(&identity_uuid)
+        .cloned();
+
     let workspace_id_str = {
         let workspaces = state.workspaces.lock().map_err(|e| e.to_string())?;
         workspaces
@@ -167,84 +237,171 @@ pub async fn poll_sync(
         }
     }
 
-    // Clone Arcs so the spawn_blocking closure can own them (guards are NOT held).
-    // RelayChannel holds a reqwest::blocking::Client which owns an internal Tokio
-    // runtime. Creating, using, and dropping it must happen on a spawn_blocking
-    // thread (no outer tokio context) — block_in_place is insufficient because the
-    // outer runtime context is still present on the thread, causing a panic on drop.
-    let workspaces_arc = Arc::clone(&state.workspaces);
-    let contact_managers_arc = Arc::clone(&state.contact_managers);
-
-    let events = tokio::task::spawn_blocking(move || -> Result<Vec<SyncEvent>, String> {
-        let mut engine = SyncEngine::new();
-        engine.register_channel(Box::new(FolderChannel::new(
-            identity_pubkey,
+    // Git clones persist between polls so each cycle only fetches new commits.
+    let git_clones_dir = crate::settings::home_dir()
+        .join("git-sync")
+        .join(identity_uuid.to_string());
+
+    let mut engine = SyncEngine::new();
+    if !webdav_accounts.is_empty() {
+        let mut webdav = WebDavChannel::new(&identity_pubkey);
+        for acct in &webdav_accounts {
+            if let Err(e) = webdav.add_account(acct) {
+                log::warn!("run_sync_cycle: skipping WebDAV account {}: {e}", acct.url);
+            }
+        }
+        engine.register_channel(Box::new(webdav));
+    }
+    if !s3_accounts.is_empty() {
+        let mut s3 = S3Channel::new(&identity_pubkey);
+        for acct in &s3_accounts {
+            if let Err(e) = s3.add_account(acct) {
+                log::warn!("run_sync_cycle: skipping S3 bucket {}: {e}", acct.bucket);
+            }
+        }
+        engine.register_channel(Box::new(s3));
+    }
+    engine.register_channel(Box::new(GitChannel::new(&identity_pubkey, git_clones_dir)));
+    if let Some(service) = lan_service {
+        engine.register_channel(Box::new(LanChannel::new(service)));
+    }
+    engine.register_channel(Box::new(FolderChannel::new(
+        identity_pubkey,
+        device_id.clone(),
+    )));
+
+    // NOTE: SyncEngine supports one channel per ChannelType (HashMap keyed by type).
+    // Multiple relay accounts would overwrite each other. For now, register only the
+    // first relay account. Multi-relay support requires SyncEngine architecture changes.
+    if let Some(acct) = relay_accounts.first() {
+        let mut token = acct.session_token.clone();
+        // Auto-login if session expired and password stored
+        if acct.session_expires_at < chrono::Utc::now() && !acct.password.is_empty() {
+            let client = RelayClient::new(&acct.relay_url);
+            match client.login(&acct.email, &acct.password, &acct.device_public_key) {
+                Ok(session) => token = session.session_token,
+                Err(e) => log::warn!(
+                    "run_sync_cycle: inline auto-login failed for {}: {e}",
+                    acct.relay_url
+                ),
+            }
+        }
+        let relay_client = RelayClient::new(&acct.relay_url).with_session_token(&token);
+        engine.register_channel(Box::new(RelayChannel::new(
+            relay_client,
+            workspace_id_str.clone(),
+            acct.device_public_key.clone(),
             device_id.clone(),
         )));
+    }
 
-        // NOTE: SyncEngine supports one channel per ChannelType (HashMap keyed by type).
-        // Multiple relay accounts would overwrite each other. For now, register only the
-        // first relay account. Multi-relay support requires SyncEngine architecture changes.
-        if let Some(acct) = relay_accounts.first() {
-            let mut token = acct.session_token.clone();
-            // Auto-login if session expired and password stored
-            if acct.session_expires_at < chrono::Utc::now() && !acct.password.is_empty() {
-                let client = RelayClient::new(&acct.relay_url);
-                match client.login(&acct.email, &acct.password, &acct.device_public_key) {
-                    Ok(session) => token = session.session_token,
-                    Err(e) => log::warn!(
-                        "poll_sync: inline auto-login failed for {}: {e}",
-                        acct.relay_url
-                    ),
-                }
-            }
-            let relay_client = RelayClient::new(&acct.relay_url).with_session_token(&token);
-            engine.register_channel(Box::new(RelayChannel::new(
-                relay_client,
-                workspace_id_str.clone(),
-                acct.device_public_key.clone(),
-                device_id.clone(),
-            )));
-        }
+    let mut contact_managers = state.contact_managers.lock().map_err(|e| e.to_string())?;
+    let contact_manager = contact_managers
+        .get_mut(&identity_uuid)
+        .ok_or("Contact manager not found — is the identity unlocked?")?;
+
+    let mut workspaces = state.workspaces.lock().map_err(|e| e.to_string())?;
+    let workspace = workspaces
+        .get_mut(&workspace_label)
+        .ok_or_else(|| format!("Workspace not found: {workspace_label}"))?;
+
+    // Fold old operation history into a checkpoint at most once a day, so
+    // peers that fall far behind are served checkpoint + tail.
+    if let Err(e) = workspace.compact_if_due() {
+        log::warn!("operation log compaction (window={workspace_label}) failed: {e}");
+    }
+
+    let mut ctx = SyncContext {
+        signing_key: &signing_key,
+        contact_manager,
+        workspace_name: &workspace_name,
+        sender_display_name: &sender_display_name,
+    };
 
-        let mut contact_managers = contact_managers_arc.lock().map_err(|e| e.to_string())?;
-        let contact_manager = contact_managers
-            .get_mut(&identity_uuid)
-            .ok_or("Contact manager not found — is the identity unlocked?")?;
-
-        let mut workspaces = workspaces_arc.lock().map_err(|e| e.to_string())?;
-        let workspace = workspaces
-            .get_mut(&workspace_label)
-            .ok_or_else(|| format!("Workspace not found: {workspace_label}"))?;
-
-        let mut ctx = SyncContext {
-            signing_key: &signing_key,
-            contact_manager,
-            workspace_name: &workspace_name,
-            sender_display_name: &sender_display_name,
-        };
-
-        engine.poll(workspace, &mut ctx).map_err(|e| {
-            log::error!("poll_sync(window={workspace_label}) failed: {e}");
+    engine
+        .poll_peers(workspace, &mut ctx, include)
+        .map_err(|e| {
+            log::error!("sync cycle (window={workspace_label}) failed: {e}");
             e.to_string()
         })
-        // engine (and RelayClient) dropped here — safe on a spawn_blocking thread
-    })
-    .await
-    .map_err(|e| {
-        log::error!("poll_sync spawn_blocking join failed: {e}");
-        e.to_string()
-    })??;
+    // engine (and RelayClient) dropped here — safe on a blocking thread
+}
 
-    // If any bundles were applied, notify WorkspaceView to reload the note tree.
-    let bundles_applied = events
-        .iter()
-        .any(|e| matches!(e, SyncEvent::BundleApplied { .. }));
-    if bundles_applied {
-        let _ = window.emit("workspace-updated", ());
+// ── background sync ────────────────────────────────────────────────────────
+
+/// Start the background sync daemon for the current workspace window.
+///
+/// The daemon polls each channel on its own interval, backs off peers whose
+/// sends fail, and pushes shortly after each local edit — independently of
+/// the webview, whose timers are throttled while the window is minimised.
+/// Events are emitted as `sync-event`; applied bundles also emit
+/// `workspace-updated`. Does nothing if the daemon is already running.
+#[tauri::command]
+pub fn start_background_sync(window: Window, state: State<'_, AppState>) -> Result<(), String> {
+    let label = window.label().to_string();
+    let mut daemons = state.sync_daemons.lock().map_err(|e| e.to_string())?;
+    if daemons.contains_key(&label) {
+        return Ok(());
     }
+    log::info!("start_background_sync(window={label})");
 
-    Ok(events)
+    let target = {
+        let app = window.app_handle().clone();
+        let label = label.clone();
+        move |include: &mut dyn FnMut(&PeerSyncInfo) -> bool| {
+            run_sync_cycle(&app, &label, include).map_err(crate::KrillnotesError::Swarm)
+        }
+    };
+    let on_event = {
+        let window = window.clone();
+        Box::new(move |event: SyncEvent| {
+            if matches!(event, SyncEvent::BundleApplied { .. }) {
+                let _ = window.emit("workspace-updated", ());
+            }
+            let _ = window.emit("sync-event", &event);
+        })
+    };
+    // sync_on_close stays off: the close dialog (useSyncOnClose) applies that
+    // setting before the window goes away and can report a failed sync.
+    let daemon = SyncDaemon::spawn(SchedulerConfig::default(), target, on_event);
+
+    let mut workspaces = state.workspaces.lock().map_err(|e| e.to_string())?;
+    if let Some(ws) = workspaces.get_mut(&label) {
+        ws.set_commit_listener(Some(daemon.commit_notifier()));
+    }
+    daemons.insert(label, daemon);
+    Ok(())
+}
+
+/// Stop the background sync daemon for the current workspace window.
+#[tauri::command]
+pub fn stop_background_sync(window: Window, state: State<'_, AppState>) -> Result<(), String> {
+    stop_sync_daemon(&state, window.label());
+    Ok(())
+}
+
+/// Removes the sync daemon for window `label`, if any, and waits for a cycle
+/// in progress to finish. Call it before removing the window's workspace or
+/// its identity, which the cycle reads from `state`; no `state` lock may be
+/// held.
+pub(crate) fn stop_sync_daemon(state: &AppState, label: &str) {
+    let daemon = state
+        .sync_daemons
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(label);
+    if let Some(daemon) = daemon {
+        log::info!("stopping background sync (window={label})");
+        if let Some(ws) = state
+            .workspaces
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get_mut(label)
+        {
+            ws.set_commit_listener(None);
+        }
+        daemon.shutdown();
+    }
 }
 
 // ── share_invite_link ──────────────────────────────────────────────────────
@@ -999,3 +1156,129 @@ pub fn list_sync_events(
         .list_sync_events(limit, offset)
         .map_err(|e| e.to_string())
 }
+
+/// Audits the workspace's operation log (signatures, stored hashes and
+/// per-author hash chains) and returns the signed report.
+#[tauri::command]
+pub fn audit_log_integrity(
+    window: Window,
+    state: State<'_, AppState>,
+) -> Result<krillnotes_core::IntegrityReport, String> {
+    let label = window.label().to_string();
+    let workspaces = state.workspaces.lock().map_err(|e| e.to_string())?;
+    let workspace = workspaces
+        .get(&label)
+        .ok_or_else(|| format!("No workspace for window {label}"))?;
+    workspace.audit_log_integrity().map_err(|e| {
+        log::error!("audit_log_integrity failed: {e}");
+        e.to_string()
+    })
+}
+
+/// Exports the operation log (optionally one subtree and date range) as
+/// signed JSON Lines to `path`, with the detached manifest written next to it
+/// as `<stem>.manifest.json`. Returns the number of exported operations.
+#[tauri::command]
+pub fn export_audit_log(
+    window: Window,
+    state: State<'_, AppState>,
+    options: krillnotes_core::AuditExportOptions,
+    path: String,
+) -> Result<u64, String> {
+    let (identity_uuid, exporter_name) = {
+        let workspaces = state.workspaces.lock().map_err(|e| e.to_string())?;
+        let ws = workspaces
+            .get(window.label())
+            .ok_or("No workspace open for this window")?;
+        let identity_uuid = Uuid::parse_str(ws.identity_uuid()).map_err(|e| e.to_string())?;
+        let ids = state
+            .unlocked_identities
+            .lock()
+            .map_err(|e| e.to_string())?;
+        let id = ids.get(&identity_uuid).ok_or("Identity not unlocked")?;
+        (identity_uuid, id.display_name.clone())
+    };
+    let export = {
+        let contact_managers = state.contact_managers.lock().map_err(|e| e.to_string())?;
+        let cm = contact_managers
+            .get(&identity_uuid)
+            .ok_or("Contact manager not found — identity must be unlocked")?;
+        let workspaces = state.workspaces.lock().map_err(|e| e.to_string())?;
+        let ws = workspaces
+            .get(window.label())
+            .ok_or("No workspace open for this window")?;
+        ws.export_audit_log(&options, cm, &exporter_name)
+            .map_err(|e| {
+                log::error!("export_audit_log failed: {e}");
+                e.to_string()
+            })?
+    };
+
+    let records_path = std::path::PathBuf::from(&path);
+    let manifest_json = serde_json::to_vec_pretty(&export.manifest).map_err(|e| e.to_string())?;
+    std::fs::write(&records_path, &export.records).map_err(|e| e.to_string())?;
+    std::fs::write(records_path.with_extension("manifest.json"), manifest_json)
+        .map_err(|e| e.to_string())?;
+    Ok(export.manifest.record_count)
+}
+
+/// Verifies an audit export offline: the manifest signature over the
+/// records file, then every operation's own signature.
+#[tauri::command]
+pub fn verify_audit_export_files(
+    records_path: String,
+    manifest_path: String,
+) -> Result<krillnotes_core::AuditVerification, String> {
+    let records = std::fs::read(&records_path).map_err(|e| e.to_string())?;
+    let manifest_json = std::fs::read(&manifest_path).map_err(|e| e.to_string())?;
+    let manifest: krillnotes_core::AuditManifest =
+        serde_json::from_slice(&manifest_json).map_err(|e| e.to_string())?;
+    krillnotes_core::verify_audit_export(&records, &manifest).map_err(|e| e.to_string())
+}
+
+/// Lists the current identity's devices in this workspace, with last-seen
+/// and revocation times from the operation log.
+#[tauri::command]
+pub fn list_my_devices(
+    window: Window,
+    state: State<'_, AppState>,
+) -> Result<Vec<krillnotes_core::DeviceInfo>, String> {
+    let workspaces = state.workspaces.lock().map_err(|e| e.to_string())?;
+    let ws = workspaces
+        .get(window.label())
+        .ok_or("No workspace open for this window")?;
+    ws.list_devices().map_err(|e| e.to_string())
+}
+
+/// Revokes one of the current identity's other devices in this workspace.
+/// Its relay key is removed separately with `remove_device_from_relay`.
+///
+/// Revocation alone does not lock out a lost or stolen device, which still
+/// holds the identity key. With `rotate_passphrase` the identity key is
+/// rotated right after the revocation, as by `rotate_identity_key`.
+#[tauri::command]
+pub fn revoke_device(
+    window: Window,
+    state: State<'_, AppState>,
+    device_id: String,
+    rotate_passphrase: Option<String>,
+) -> Result<bool, String> {
+    log::debug!("revoke_device(device_id={device_id})");
+    let revoked = {
+        let mut workspaces = state.workspaces.lock().map_err(|e| e.to_string())?;
+        let ws = workspaces
+            .get_mut(window.label())
+            .ok_or("No workspace open for this window")?;
+        ws.revoke_device(&device_id).map_err(|e| e.to_string())?
+    };
+    if let Some(passphrase) = rotate_passphrase {
+        let identity_uuid = *state
+            .workspace_identities
+            .lock()
+            .map_err(|e| e.to_string())?
+            .get(window.label())
+            .ok_or("No identity bound to this workspace")?;
+        super::identity::rotate_identity_key(state.clone(), identity_uuid.to_string(), passphrase)?;
+    }
+    Ok(revoked)
+}
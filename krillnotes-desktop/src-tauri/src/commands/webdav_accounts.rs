@@ -0,0 +1,128 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Tauri commands for WebDAV account CRUD.
+//!
+//! A peer is switched to WebDAV with `update_peer_channel(peer, "webdav",
+//! {"webdav_account_id": "<uuid>"})`.
+
+use crate::AppState;
+use serde::Serialize;
+use tauri::State;
+use uuid::Uuid;
+
+use krillnotes_core::core::sync::webdav::{WebDavAccount, WebDavClient};
+
+/// WebDAV account info returned to the frontend. Never exposes the password.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebDavAccountInfo {
+    pub webdav_account_id: String,
+    pub url: String,
+    pub username: String,
+}
+
+impl WebDavAccountInfo {
+    fn from_account(a: &WebDavAccount) -> Self {
+        Self {
+            webdav_account_id: a.webdav_account_id.to_string(),
+            url: a.url.clone(),
+            username: a.username.clone(),
+        }
+    }
+}
+
+// ── list_webdav_accounts ───────────────────────────────────────────────────
+
+/// List all WebDAV accounts for the given identity.
+#[tauri::command]
+pub fn list_webdav_accounts(
+    state: State<'_, AppState>,
+    identity_uuid: String,
+) -> Result<Vec<WebDavAccountInfo>, String> {
+    let uuid = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+    let managers = state
+        .webdav_account_managers
+        .lock()
+        .expect("Mutex poisoned");
+    let mgr = managers.get(&uuid).ok_or("Identity not unlocked")?;
+    let accounts = mgr.list_webdav_accounts().map_err(|e| {
+        log::error!("list_webdav_accounts failed: {e}");
+        e.to_string()
+    })?;
+    Ok(accounts
+        .iter()
+        .map(WebDavAccountInfo::from_account)
+        .collect())
+}
+
+// ── add_webdav_account ─────────────────────────────────────────────────────
+
+/// Check that the collection at `url` is reachable with the given credentials,
+/// then store them encrypted for the identity.
+#[tauri::command]
+pub async fn add_webdav_account(
+    state: State<'_, AppState>,
+    identity_uuid: String,
+    url: String,
+    username: String,
+    password: String,
+) -> Result<WebDavAccountInfo, String> {
+    log::debug!("add_webdav_account(identity={identity_uuid}, url={url})");
+    let uuid = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+
+    // WebDavClient uses reqwest::blocking — must run in spawn_blocking.
+    let (check_url, check_user, check_pass) = (url.clone(), username.clone(), password.clone());
+    tokio::task::spawn_blocking(move || {
+        WebDavClient::new(&check_url, &check_user, &check_pass)?
+            .list("")
+            .map(|_| ())
+    })
+    .await
+    .map_err(|e| {
+        log::error!("add_webdav_account spawn_blocking join failed: {e}");
+        e.to_string()
+    })?
+    .map_err(|e| {
+        log::warn!("add_webdav_account: connection check failed for {url}: {e}");
+        e.user_message()
+    })?;
+
+    let managers = state
+        .webdav_account_managers
+        .lock()
+        .expect("Mutex poisoned");
+    let mgr = managers.get(&uuid).ok_or("Identity not unlocked")?;
+    let account = mgr
+        .create_webdav_account(&url, &username, &password)
+        .map_err(|e| {
+            log::error!("add_webdav_account: create_webdav_account failed: {e}");
+            e.to_string()
+        })?;
+    Ok(WebDavAccountInfo::from_account(&account))
+}
+
+// ── delete_webdav_account ──────────────────────────────────────────────────
+
+/// Delete a stored WebDAV account.
+#[tauri::command]
+pub fn delete_webdav_account(
+    state: State<'_, AppState>,
+    identity_uuid: String,
+    webdav_account_id: String,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+    let account_id = Uuid::parse_str(&webdav_account_id).map_err(|e| e.to_string())?;
+    let managers = state
+        .webdav_account_managers
+        .lock()
+        .expect("Mutex poisoned");
+    let mgr = managers.get(&uuid).ok_or("Identity not unlocked")?;
+    mgr.delete_webdav_account(account_id).map_err(|e| {
+        log::error!("delete_webdav_account failed: {e}");
+        e.to_string()
+    })
+}
@@ -580,7 +580,6 @@ pub async fn open_workspace(
         }
         None => {
             let label = generate_unique_label(&state, &folder);
-            let db_path = folder.join("notes.db");
 
             // Read workspace_id from info.json
             let (ws_uuid_opt, _, _, _, _) = read_info_json_full(&folder);
@@ -595,7 +594,7 @@ pub async fn open_workspace(
             // the above; never hold identity_manager or unlocked_identities simultaneously
             // with contact_managers.
 
-            // Step 1: Get identity_uuid from identity_manager (drop lock after)
+            // Get identity_uuid from identity_manager (drop lock after)
             let identity_uuid = {
                 let mgr = state.identity_manager.lock().expect("Mutex poisoned");
                 let binding = mgr
@@ -606,52 +605,7 @@ pub async fn open_workspace(
                 // mgr drops here
             };
 
-            // Step 2: Get signing key from unlocked_identities (drop lock after)
-            let seed = {
-                let identities = state.unlocked_identities.lock().expect("Mutex poisoned");
-                let unlocked = identities
-                    .get(&identity_uuid)
-                    .ok_or_else(|| format!("IDENTITY_LOCKED:{}", identity_uuid))?;
-                unlocked.signing_key.to_bytes()
-                // identities drops here
-            };
-
-            // Step 3: Decrypt DB password (no other locks held)
-            let db_password = {
-                let mgr = state.identity_manager.lock().expect("Mutex poisoned");
-                mgr.decrypt_db_password(&folder, &seed)
-                    .map_err(|e| format!("Failed to decrypt DB password: {e}"))?
-            };
-
-            let signing_key = Ed25519SigningKey::from_bytes(&seed);
-            let owner_pubkey = {
-                use base64::Engine;
-                base64::engine::general_purpose::STANDARD
-                    .encode(signing_key.verifying_key().as_bytes())
-            };
-            let gate = create_permission_gate(owner_pubkey);
-            let identity_dir = state
-                .identity_manager
-                .lock()
-                .expect("Mutex poisoned")
-                .identity_dir(&identity_uuid);
-            let mut workspace = Workspace::open(
-                &db_path,
-                &db_password,
-                &identity_uuid.to_string(),
-                signing_key,
-                gate,
-                Some(&identity_dir),
-            )
-            .map_err(|e| match e {
-                KrillnotesError::WrongPassword => "WRONG_PASSWORD".to_string(),
-                KrillnotesError::UnencryptedWorkspace => "UNENCRYPTED_WORKSPACE".to_string(),
-                other => format!("Failed to open: {other}"),
-            })?;
-
-            // Apply global undo limit from settings
-            let global_undo_limit = crate::settings::load_settings().undo_history_limit;
-            let _ = workspace.set_undo_limit(global_undo_limit);
+            let mut workspace = open_bound_workspace(&state, &folder, identity_uuid)?;
 
             let migration_results = std::mem::take(&mut workspace.pending_migration_results);
             let new_window = create_workspace_window(&app, &label, &window)?;
@@ -689,6 +643,83 @@ pub async fn open_workspace(
     }
 }
 
+/// Opens the workspace at `folder` with the signing key of `identity_uuid`,
+/// which it is bound to and which must be unlocked.
+///
+/// Applies the global undo limit and announces a key rotation made while
+/// the workspace was closed. The caller registers the workspace.
+pub(crate) fn open_bound_workspace(
+    state: &AppState,
+    folder: &Path,
+    identity_uuid: Uuid,
+) -> std::result::Result<Workspace, String> {
+    use zeroize::Zeroize;
+
+    let db_path = folder.join("notes.db");
+    // Get signing key from unlocked_identities (drop lock after)
+    let mut seed = {
+        let identities = state.unlocked_identities.lock().expect("Mutex poisoned");
+        let unlocked = identities
+            .get(&identity_uuid)
+            .ok_or_else(|| format!("IDENTITY_LOCKED:{}", identity_uuid))?;
+        unlocked.signing_key.to_bytes()
+        // identities drops here
+    };
+
+    // Decrypt DB password (no other locks held)
+    let db_password = {
+        let mgr = state.identity_manager.lock().expect("Mutex poisoned");
+        mgr.decrypt_db_password(folder, &seed)
+            .map_err(|e| format!("Failed to decrypt DB password: {e}"))?
+    };
+
+    let signing_key = Ed25519SigningKey::from_bytes(&seed);
+    let owner_pubkey = {
+        use base64::Engine;
+        base64::engine::general_purpose::STANDARD.encode(signing_key.verifying_key().as_bytes())
+    };
+    let gate = create_permission_gate(owner_pubkey);
+    let identity_dir = state
+        .identity_manager
+        .lock()
+        .expect("Mutex poisoned")
+        .identity_dir(&identity_uuid);
+    let mut workspace = Workspace::open(
+        &db_path,
+        &db_password,
+        &identity_uuid.to_string(),
+        signing_key,
+        gate,
+        Some(&identity_dir),
+    )
+    .map_err(|e| match e {
+        KrillnotesError::WrongPassword => "WRONG_PASSWORD".to_string(),
+        KrillnotesError::UnencryptedWorkspace => "UNENCRYPTED_WORKSPACE".to_string(),
+        other => format!("Failed to open: {other}"),
+    })?;
+
+    // Apply global undo limit from settings
+    let global_undo_limit = crate::settings::load_settings().undo_history_limit;
+    let _ = workspace.set_undo_limit(global_undo_limit);
+
+    // Announce a key rotation made while this workspace was closed.
+    let latest_succession = state
+        .identity_manager
+        .lock()
+        .expect("Mutex poisoned")
+        .key_successions(&identity_uuid)
+        .ok()
+        .and_then(|records| records.last().cloned());
+    if let Some(record) = latest_succession {
+        if let Err(e) = workspace.rotate_identity_key(Ed25519SigningKey::from_bytes(&seed), &record)
+        {
+            log::warn!("Failed to apply key rotation to {}: {e}", folder.display());
+        }
+    }
+    seed.zeroize();
+    Ok(workspace)
+}
+
 /// Returns the [`WorkspaceInfo`] for the calling window's workspace.
 #[tauri::command]
 pub fn get_workspace_info(
@@ -730,12 +761,16 @@ pub fn set_workspace_metadata(
 // ── Export / Import commands ──────────────────────────────────────
 
 /// Exports the calling window's workspace as a zip archive at `path`.
+///
+/// When `options.subtree_root` is set, only that note and its descendants are
+/// exported (see [`krillnotes_core::ExportOptions`]).
 #[tauri::command]
 pub fn export_workspace_cmd(
     window: tauri::Window,
     state: State<'_, AppState>,
     path: String,
     password: Option<String>,
+    options: Option<krillnotes_core::ExportOptions>,
 ) -> std::result::Result<(), String> {
     let label = window.label();
     let workspaces = state.workspaces.lock().expect("Mutex poisoned");
@@ -746,7 +781,13 @@ pub fn export_workspace_cmd(
     }
 
     let file = std::fs::File::create(&path).map_err(|e| e.to_string())?;
-    krillnotes_core::export_workspace(workspace, file, password.as_deref()).map_err(|e| {
+    krillnotes_core::export_workspace_with_options(
+        workspace,
+        file,
+        password.as_deref(),
+        &options.unwrap_or_default(),
+    )
+    .map_err(|e| {
         log::error!("export_workspace failed: {e}");
         e.to_string()
     })
@@ -880,6 +921,166 @@ pub async fn execute_import(
     get_workspace_info_internal(&state, &label)
 }
 
+/// Merges an export archive into the calling window's workspace, grafting its
+/// notes under `parent_id`. All changes are logged as signed operations.
+#[tauri::command]
+pub fn merge_import_cmd(
+    window: tauri::Window,
+    state: State<'_, AppState>,
+    zip_path: String,
+    parent_id: String,
+    password: Option<String>,
+    options: Option<krillnotes_core::MergeImportOptions>,
+) -> std::result::Result<krillnotes_core::MergeImportResult, String> {
+    let label = window.label();
+    let mut workspaces = state.workspaces.lock().expect("Mutex poisoned");
+    let workspace = workspaces.get_mut(label).ok_or("No workspace open")?;
+
+    let file = std::fs::File::open(&zip_path).map_err(|e| e.to_string())?;
+    let reader = std::io::BufReader::new(file);
+    krillnotes_core::merge_import_workspace(
+        reader,
+        workspace,
+        &parent_id,
+        password.as_deref(),
+        options.unwrap_or_default(),
+    )
+    .map_err(|e| match e {
+        krillnotes_core::ExportError::EncryptedArchive => "ENCRYPTED_ARCHIVE".to_string(),
+        krillnotes_core::ExportError::InvalidPassword => "INVALID_PASSWORD".to_string(),
+        other => {
+            log::error!("merge_import_workspace failed: {other}");
+            other.to_string()
+        }
+    })
+}
+
+/// Imports an Evernote `.enex` file or a Notion "Markdown & CSV" export into the
+/// calling window's workspace under `parent_id`. See
+/// [`import_foreign_workspace_cmd`] to import into a new workspace.
+#[tauri::command]
+pub fn import_foreign_cmd(
+    window: tauri::Window,
+    state: State<'_, AppState>,
+    path: String,
+    format: krillnotes_core::ForeignFormat,
+    parent_id: String,
+) -> std::result::Result<krillnotes_core::ForeignImportReport, String> {
+    let label = window.label();
+    let mut workspaces = state.workspaces.lock().expect("Mutex poisoned");
+    let workspace = workspaces.get_mut(label).ok_or("No workspace open")?;
+
+    let file = std::fs::File::open(&path).map_err(|e| e.to_string())?;
+    let reader = std::io::BufReader::new(file);
+    krillnotes_core::import_foreign(format, reader, workspace, &parent_id).map_err(|e| {
+        log::error!("import_foreign failed: {e}");
+        e.to_string()
+    })
+}
+
+/// Imports an Evernote `.enex` file or a Notion "Markdown & CSV" export into a
+/// new workspace folder `name` of identity `identity_uuid`, and opens it in a
+/// new window. The imported notes are placed under the new workspace's root
+/// note; the folder is removed again if the import fails.
+#[tauri::command]
+pub async fn import_foreign_workspace_cmd(
+    window: tauri::Window,
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    format: krillnotes_core::ForeignFormat,
+    name: String,
+    identity_uuid: String,
+) -> std::result::Result<WorkspaceInfo, String> {
+    let uuid = Uuid::parse_str(&identity_uuid).map_err(|e| e.to_string())?;
+    let folder = {
+        let mgr = state.identity_manager.lock().expect("Mutex poisoned");
+        mgr.identity_base_dir(&uuid)
+            .ok_or_else(|| format!("Identity folder not found for {identity_uuid}"))?
+            .join(&name)
+    };
+    if folder.exists() {
+        return Err("Workspace already exists. Use Open Workspace instead.".to_string());
+    }
+
+    // Generate a random DB password for the new workspace.
+    let workspace_password: String = {
+        use base64::Engine;
+        use rand::RngCore;
+        let mut bytes = [0u8; 32];
+        rand::rng().fill_bytes(&mut bytes);
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    };
+    let seed = {
+        let identities = state.unlocked_identities.lock().expect("Mutex poisoned");
+        let unlocked = identities
+            .get(&uuid)
+            .ok_or_else(|| "Identity is not unlocked".to_string())?;
+        unlocked.signing_key.to_bytes()
+    };
+    let signing_key = Ed25519SigningKey::from_bytes(&seed);
+    let owner_pubkey = {
+        use base64::Engine;
+        base64::engine::general_purpose::STANDARD.encode(signing_key.verifying_key().as_bytes())
+    };
+    let identity_dir = state
+        .identity_manager
+        .lock()
+        .expect("Mutex poisoned")
+        .identity_dir(&uuid);
+
+    std::fs::create_dir_all(&folder)
+        .map_err(|e| format!("Failed to create workspace directory: {e}"))?;
+    let file = std::fs::File::open(&path).map_err(|e| e.to_string())?;
+    let imported = krillnotes_core::import_foreign_workspace(
+        format,
+        std::io::BufReader::new(file),
+        &folder.join("notes.db"),
+        &workspace_password,
+        &uuid.to_string(),
+        signing_key,
+        create_permission_gate(owner_pubkey),
+        Some(&identity_dir),
+    );
+    let (workspace, report) = match imported {
+        Ok(imported) => imported,
+        Err(e) => {
+            log::error!("import_foreign_workspace failed: {e}");
+            let _ = std::fs::remove_dir_all(&folder);
+            return Err(e.to_string());
+        }
+    };
+    log::info!(
+        "imported {} notes and {} attachments into new workspace {name} ({} issues)",
+        report.notes_created,
+        report.attachments_imported,
+        report.issues.len()
+    );
+
+    // Bind the new workspace to the identity so it can be opened later.
+    let workspace_uuid = workspace.workspace_id().to_string();
+    state
+        .identity_manager
+        .lock()
+        .expect("Mutex poisoned")
+        .bind_workspace(&uuid, &workspace_uuid, &folder, &workspace_password, &seed)
+        .map_err(|e| format!("Failed to bind workspace to identity: {e}"))?;
+
+    let label = generate_unique_label(&state, &folder);
+    let new_window = create_workspace_window(&app, &label, &window)?;
+    store_workspace(&state, label.clone(), workspace, folder, uuid);
+
+    new_window
+        .set_title(&format!("Krillnotes - {label}"))
+        .map_err(|e| e.to_string())?;
+
+    if window.label() == "main" {
+        window.close().map_err(|e| e.to_string())?;
+    }
+
+    get_workspace_info_internal(&state, &label)
+}
+
 /// Returns the application version string from the core crate.
 #[tauri::command]
 pub fn get_app_version() -> String {
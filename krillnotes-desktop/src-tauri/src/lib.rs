@@ -10,6 +10,7 @@
 //! Each command is scoped to the calling window's workspace via
 //! [`AppState`] and the window label.
 
+pub mod autolock;
 pub mod locales;
 pub mod menu;
 pub mod settings;
@@ -59,6 +60,18 @@ pub struct AppState {
     /// Per-identity relay account managers — keyed by identity UUID, created on unlock.
     pub relay_account_managers:
         Arc<Mutex<HashMap<Uuid, krillnotes_core::core::sync::relay::RelayAccountManager>>>,
+    /// Per-identity WebDAV account managers — keyed by identity UUID, created on unlock.
+    pub webdav_account_managers:
+        Arc<Mutex<HashMap<Uuid, krillnotes_core::core::sync::webdav::WebDavAccountManager>>>,
+    /// Per-identity S3 account managers — keyed by identity UUID, created on unlock.
+    pub s3_account_managers:
+        Arc<Mutex<HashMap<Uuid, krillnotes_core::core::sync::s3::S3AccountManager>>>,
+    /// Per-identity LAN sync listeners — keyed by identity UUID, started on
+    /// unlock and stopped on lock.
+    pub lan_services: Arc<Mutex<HashMap<Uuid, Arc<krillnotes_core::core::sync::LanService>>>>,
+    /// Per-window background sync daemons — keyed by window label, started by
+    /// the workspace view and stopped when the window closes or its identity locks.
+    pub sync_daemons: Arc<Mutex<HashMap<String, krillnotes_core::core::sync::SyncDaemon>>>,
     /// Per-identity accepted invite managers — keyed by identity UUID, created on unlock.
     pub accepted_invite_managers:
         Arc<Mutex<HashMap<Uuid, krillnotes_core::core::accepted_invite::AcceptedInviteManager>>>,
@@ -107,6 +120,12 @@ pub struct AppState {
     /// When a label is in this set, the next `CloseRequested` event for
     /// that window is allowed through without interception.
     pub closing_windows: Arc<Mutex<HashSet<String>>>,
+    /// When the user last interacted with any window; drives the idle lock.
+    pub last_activity: Arc<Mutex<std::time::Instant>>,
+    /// Workspace windows whose identity was locked automatically, keyed by
+    /// window label. Their workspaces are closed until the identity is
+    /// unlocked again (see [`autolock`]).
+    pub suspended_windows: Arc<Mutex<HashMap<String, Uuid>>>,
 }
 
 /// Maps raw menu event IDs to the user-facing message strings emitted to the frontend.
@@ -211,12 +230,21 @@ pub fn run() {
             workspace_paths: Arc::new(Mutex::new(HashMap::new())),
             workspace_identities: Arc::new(Mutex::new(HashMap::new())),
             focused_window: Arc::new(Mutex::new(None)),
-            identity_manager: Arc::new(Mutex::new(
-                IdentityManager::new(settings::home_dir()).expect("Failed to init IdentityManager"),
-            )),
+            identity_manager: Arc::new(Mutex::new({
+                let mut mgr = IdentityManager::new(settings::home_dir())
+                    .expect("Failed to init IdentityManager");
+                if let Some(policy) = settings::load_settings().kdf_policy {
+                    mgr.set_kdf_policy(policy);
+                }
+                mgr
+            })),
             contact_managers: Arc::new(Mutex::new(HashMap::new())),
             invite_managers: Arc::new(Mutex::new(HashMap::new())),
             relay_account_managers: Arc::new(Mutex::new(HashMap::new())),
+            webdav_account_managers: Arc::new(Mutex::new(HashMap::new())),
+            s3_account_managers: Arc::new(Mutex::new(HashMap::new())),
+            lan_services: Arc::new(Mutex::new(HashMap::new())),
+            sync_daemons: Arc::new(Mutex::new(HashMap::new())),
             accepted_invite_managers: Arc::new(Mutex::new(HashMap::new())),
             received_response_managers: Arc::new(Mutex::new(HashMap::new())),
             sync_engines: Arc::new(Mutex::new(HashMap::new())),
@@ -227,6 +255,8 @@ pub fn run() {
             pending_krillnotes_open: Arc::new(Mutex::new(None)),
             pending_swarm_open: Arc::new(Mutex::new(None)),
             closing_windows: Arc::new(Mutex::new(HashSet::new())),
+            last_activity: Arc::new(Mutex::new(std::time::Instant::now())),
+            suspended_windows: Arc::new(Mutex::new(HashMap::new())),
         })
         .on_window_event(|window, event| {
             let label = window.label().to_string();
@@ -245,6 +275,7 @@ pub fn run() {
                 // Remove workspace state when a window is destroyed so the same
                 // file can be reopened after its window has been closed.
                 tauri::WindowEvent::Destroyed => {
+                    commands::sync::stop_sync_daemon(&state, &label);
                     // Persist cached metadata before dropping the workspace.
                     // Use unwrap_or_else to recover from a poisoned mutex (e.g. after a panic
                     // in a command that held the lock) rather than double-panicking on destroy.
@@ -271,6 +302,11 @@ pub fn run() {
                         .lock()
                         .unwrap_or_else(|e| e.into_inner())
                         .remove(&label);
+                    state
+                        .suspended_windows
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .remove(&label);
 
                     // On macOS the menu bar is global. If this was the last
                     // workspace window, disable workspace-specific items so
@@ -355,6 +391,8 @@ pub fn run() {
                     Some(menu_result.export_item);
             }
 
+            autolock::spawn_watcher(app.handle().clone());
+
             // Ensure home directory exists
             let home = settings::home_dir();
             if !home.exists() {
@@ -435,6 +473,9 @@ pub fn run() {
             export_workspace_cmd,
             peek_import_cmd,
             execute_import,
+            merge_import_cmd,
+            import_foreign_cmd,
+            import_foreign_workspace_cmd,
             get_app_version,
             consume_pending_file_open,
             consume_pending_swarm_file,
@@ -460,10 +501,15 @@ pub fn run() {
             delete_identity,
             rename_identity,
             change_identity_passphrase,
+            get_passphrase_strength,
+            calibrate_identity_kdf,
             get_unlocked_identities,
             is_identity_unlocked,
             get_workspaces_for_identity,
             export_swarmid_cmd,
+            get_recovery_phrase,
+            recover_identity_from_mnemonic,
+            rotate_identity_key,
             get_identity_public_key,
             import_swarmid_cmd,
             import_swarmid_overwrite_cmd,
@@ -503,6 +549,16 @@ pub fn run() {
             update_contact,
             delete_contact,
             get_fingerprint,
+            autolock::record_activity,
+            start_contact_verification,
+            list_contact_verifications,
+            confirm_contact_verification,
+            cancel_contact_verification,
+            get_verification_qr,
+            verify_contact_qr,
+            vouch_for_contact,
+            revoke_contact_vouch,
+            get_trust_path,
             list_workspace_peers,
             get_workspace_peers,
             remove_workspace_peer,
@@ -511,15 +567,33 @@ pub fn run() {
             send_snapshot_via_relay,
             send_self_snapshot_via_relay,
             list_devices_on_relay,
+            remove_device_from_relay,
+            set_up_root_recovery,
+            release_recovery_share,
+            recover_root_ownership,
+            export_contact_verification,
+            import_contact_verification,
+            export_vouches,
+            import_vouches,
             apply_swarm_snapshot,
             apply_swarm_delta,
             generate_deltas_for_peers,
+            preview_delta_for_peer,
+            preview_swarm_delta,
             update_peer_channel,
             poll_sync,
+            start_background_sync,
+            stop_background_sync,
             list_relay_accounts,
             register_relay_account,
             login_relay_account,
             delete_relay_account,
+            list_webdav_accounts,
+            add_webdav_account,
+            delete_webdav_account,
+            list_s3_accounts,
+            add_s3_account,
+            delete_s3_account,
             set_peer_relay,
             share_invite_link,
             create_relay_invite,
@@ -530,6 +604,11 @@ pub fn run() {
             sync::reset_peer_watermark,
             sync::has_pending_sync_ops,
             sync::list_sync_events,
+            sync::audit_log_integrity,
+            sync::export_audit_log,
+            sync::verify_audit_export_files,
+            sync::list_my_devices,
+            sync::revoke_device,
             list_accepted_invites,
             save_accepted_invite,
             update_accepted_invite_status,
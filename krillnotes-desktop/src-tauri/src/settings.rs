@@ -43,6 +43,18 @@ pub struct AppSettings {
     /// "ask" = prompt the user, "always" = sync automatically, "never" = close without syncing.
     #[serde(default = "default_sync_on_close")]
     pub sync_on_close: String,
+    /// Lock unlocked identities after this many minutes without user
+    /// activity. 0 disables the idle lock.
+    #[serde(default)]
+    pub auto_lock_minutes: u32,
+    /// Lock unlocked identities when the computer wakes from sleep.
+    #[serde(default)]
+    pub lock_on_sleep: bool,
+    /// Argon2id cost picked for this machine by `calibrate_identity_kdf`.
+    /// Identity files below it are rehashed on unlock. `None` uses the
+    /// library minimum.
+    #[serde(default)]
+    pub kdf_policy: Option<krillnotes_core::KdfCost>,
 }
 
 impl Default for AppSettings {
@@ -55,6 +67,9 @@ impl Default for AppSettings {
             sharing_indicator_mode: default_sharing_indicator_mode(),
             undo_history_limit: default_undo_history_limit(),
             sync_on_close: default_sync_on_close(),
+            auto_lock_minutes: 0,
+            lock_on_sleep: false,
+            kdf_policy: None,
         }
     }
 }
@@ -205,5 +220,8 @@ mod tests {
         assert_eq!(s.dark_theme, "dark");
         assert_eq!(s.language, "en");
         assert_eq!(s.sharing_indicator_mode, "auto");
+        assert_eq!(s.auto_lock_minutes, 0);
+        assert!(!s.lock_on_sleep);
+        assert!(s.kdf_policy.is_none());
     }
 }
@@ -8,6 +8,7 @@ import { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { useTranslation } from 'react-i18next';
 import type { IdentityRef } from '../types';
+import PassphraseStrengthMeter from './PassphraseStrengthMeter';
 
 interface CreateIdentityDialogProps {
   isOpen: boolean;
@@ -61,7 +62,8 @@ function CreateIdentityDialog({ isOpen, isFirstLaunch = false, onCreated, onCanc
       });
       onCreated(identity);
     } catch (err) {
-      setError(String(err));
+      const msg = String(err);
+      setError(msg === 'PASSPHRASE_TOO_WEAK' ? t('identity.passphraseTooWeak') : msg);
     } finally {
       setLoading(false);
     }
@@ -101,6 +103,7 @@ function CreateIdentityDialog({ isOpen, isFirstLaunch = false, onCreated, onCanc
               className="w-full bg-secondary border border-secondary rounded px-3 py-2"
               disabled={loading}
             />
+            <PassphraseStrengthMeter passphrase={passphrase} />
           </div>
 
           <div className="mb-4">
@@ -15,6 +15,7 @@ import ContactBookDialog from './ContactBookDialog';
 import RelayBookDialog from './RelayBookDialog';
 import AcceptedInvitesSection from './AcceptedInvitesSection';
 import { AcceptInviteWorkflow } from './AcceptInviteWorkflow';
+import PassphraseStrengthMeter from './PassphraseStrengthMeter';
 
 interface IdentityManagerDialogProps {
   isOpen: boolean;
@@ -217,6 +218,8 @@ function IdentityManagerDialog({ isOpen, onClose }: IdentityManagerDialogProps)
       const msg = String(err);
       if (msg === 'WRONG_PASSPHRASE' || msg.includes('WrongPassphrase') || msg.includes('wrong passphrase')) {
         setPassphraseError(t('identity.wrongPassphrase'));
+      } else if (msg === 'PASSPHRASE_TOO_WEAK') {
+        setPassphraseError(t('identity.passphraseTooWeak'));
       } else {
         setPassphraseError(msg);
       }
@@ -460,6 +463,7 @@ function IdentityManagerDialog({ isOpen, onClose }: IdentityManagerDialogProps)
                       className="w-full bg-background border border-border rounded px-2 py-1 text-sm"
                       disabled={savingPassphrase}
                     />
+                    <PassphraseStrengthMeter passphrase={newPassphrase} />
                   </div>
                   <div>
                     <label className="block text-xs font-medium mb-1 text-muted-foreground">
@@ -0,0 +1,68 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+import { useState, useEffect } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { useTranslation } from 'react-i18next';
+import type { PassphraseEstimate, PassphraseStrength } from '../types';
+
+const STRENGTH_LEVELS: PassphraseStrength[] = ['veryWeak', 'weak', 'fair', 'strong', 'veryStrong'];
+
+const STRENGTH_COLORS: Record<PassphraseStrength, string> = {
+  veryWeak: 'bg-red-500',
+  weak: 'bg-orange-500',
+  fair: 'bg-yellow-500',
+  strong: 'bg-green-500',
+  veryStrong: 'bg-green-600',
+};
+
+interface PassphraseStrengthMeterProps {
+  passphrase: string;
+}
+
+/** Live strength feedback for a new identity passphrase. */
+function PassphraseStrengthMeter({ passphrase }: PassphraseStrengthMeterProps) {
+  const { t } = useTranslation();
+  const [estimate, setEstimate] = useState<PassphraseEstimate | null>(null);
+
+  useEffect(() => {
+    if (!passphrase) {
+      setEstimate(null);
+      return;
+    }
+    let cancelled = false;
+    invoke<PassphraseEstimate>('get_passphrase_strength', { passphrase })
+      .then(e => { if (!cancelled) setEstimate(e); })
+      .catch(e => console.warn('get_passphrase_strength failed:', e));
+    return () => { cancelled = true; };
+  }, [passphrase]);
+
+  if (!estimate) return null;
+
+  const level = STRENGTH_LEVELS.indexOf(estimate.strength);
+  return (
+    <div className="mt-2">
+      <div className="flex gap-1">
+        {STRENGTH_LEVELS.map((s, i) => (
+          <div
+            key={s}
+            className={`h-1 flex-1 rounded ${i <= level ? STRENGTH_COLORS[estimate.strength] : 'bg-secondary'}`}
+          />
+        ))}
+      </div>
+      <p className="text-xs text-muted-foreground mt-1">
+        {t(`identity.strength.${estimate.strength}`)}
+      </p>
+      {estimate.hints.map(h => (
+        <p key={h} className="text-xs text-muted-foreground">
+          {t(`identity.strengthHint.${h}`)}
+        </p>
+      ))}
+    </div>
+  );
+}
+
+export default PassphraseStrengthMeter;
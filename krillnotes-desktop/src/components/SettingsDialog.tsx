@@ -7,12 +7,15 @@
 import { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { open } from '@tauri-apps/plugin-dialog';
-import type { AppSettings } from '../types';
+import type { AppSettings, KdfCost } from '../types';
 import { useTheme } from '../contexts/ThemeContext';
 import ManageThemesDialog from './ManageThemesDialog';
 import i18n from '../i18n';
 import { useTranslation } from 'react-i18next';
 
+/** Idle auto-lock delays offered in the settings, in minutes. */
+const AUTO_LOCK_CHOICES = [5, 10, 15, 30, 60];
+
 interface SettingsDialogProps {
   isOpen: boolean;
   onClose: () => void;
@@ -32,6 +35,10 @@ function SettingsDialog({ isOpen, onClose, onSaved }: SettingsDialogProps) {
   const [undoLimit, setUndoLimit] = useState<number | undefined>(undefined);
   const [sharingIndicatorMode, setSharingIndicatorMode] = useState<'off' | 'auto' | 'on'>('auto');
   const [syncOnClose, setSyncOnClose] = useState('ask');
+  const [autoLockMinutes, setAutoLockMinutes] = useState(0);
+  const [lockOnSleep, setLockOnSleep] = useState(false);
+  const [kdfPolicy, setKdfPolicy] = useState<KdfCost | null>(null);
+  const [calibrating, setCalibrating] = useState(false);
 
   useEffect(() => {
     if (isOpen) {
@@ -43,6 +50,9 @@ function SettingsDialog({ isOpen, onClose, onSaved }: SettingsDialogProps) {
           setSharingIndicatorMode((s.sharingIndicatorMode ?? 'auto') as 'off' | 'auto' | 'on');
           setUndoLimit(s.undoHistoryLimit ?? 50);
           setSyncOnClose(s.syncOnClose ?? 'ask');
+          setAutoLockMinutes(s.autoLockMinutes ?? 0);
+          setLockOnSleep(s.lockOnSleep ?? false);
+          setKdfPolicy(s.kdfPolicy ?? null);
           setError('');
         })
         .catch(err => setError(t('settings.failedLoad', { error: String(err) })));
@@ -73,6 +83,18 @@ function SettingsDialog({ isOpen, onClose, onSaved }: SettingsDialogProps) {
 
   if (!isOpen) return null;
 
+  const handleCalibrate = async () => {
+    setCalibrating(true);
+    setError('');
+    try {
+      setKdfPolicy(await invoke<KdfCost>('calibrate_identity_kdf'));
+    } catch (err) {
+      setError(String(err));
+    } finally {
+      setCalibrating(false);
+    }
+  };
+
   const handleSave = async () => {
     setSaving(true);
     setError('');
@@ -83,6 +105,8 @@ function SettingsDialog({ isOpen, onClose, onSaved }: SettingsDialogProps) {
           sharingIndicatorMode,
           undoHistoryLimit: undoLimit ?? 50,
           syncOnClose,
+          autoLockMinutes,
+          lockOnSleep,
         },
       });
       if (homeDir) {
@@ -191,6 +215,53 @@ function SettingsDialog({ isOpen, onClose, onSaved }: SettingsDialogProps) {
               </select>
             </div>
 
+            <div>
+              <label className="block text-sm font-medium mb-1">
+                {t('settings.autoLock')}
+              </label>
+              <select
+                className="w-full px-3 py-2 border border-secondary rounded bg-background text-foreground"
+                value={autoLockMinutes}
+                onChange={e => setAutoLockMinutes(parseInt(e.target.value, 10))}
+              >
+                <option value={0}>{t('settings.autoLockNever')}</option>
+                {AUTO_LOCK_CHOICES.map(m => (
+                  <option key={m} value={m}>{t('settings.autoLockMinutes', { count: m })}</option>
+                ))}
+              </select>
+              <label className="flex items-center gap-2 text-sm cursor-pointer mt-2">
+                <input
+                  type="checkbox"
+                  checked={lockOnSleep}
+                  onChange={e => setLockOnSleep(e.target.checked)}
+                  className="rounded"
+                />
+                {t('settings.lockOnSleep')}
+              </label>
+              <p className="text-xs text-muted-foreground mt-1">
+                {t('settings.autoLockHint')}
+              </p>
+            </div>
+
+            <div>
+              <label className="block text-sm font-medium mb-1">
+                {t('settings.identityKdf')}
+              </label>
+              <button
+                type="button"
+                onClick={handleCalibrate}
+                disabled={calibrating}
+                className="px-3 py-1 text-sm border border-secondary rounded hover:bg-secondary disabled:opacity-50"
+              >
+                {calibrating ? t('settings.calibrating') : t('settings.calibrateKdf')}
+              </button>
+              <p className="text-xs text-muted-foreground mt-1">
+                {kdfPolicy
+                  ? t('settings.kdfPolicy', { memory: Math.round(kdfPolicy.mCost / 1024), passes: kdfPolicy.tCost })
+                  : t('settings.calibrateKdfHint')}
+              </p>
+            </div>
+
           </>
         )}
 
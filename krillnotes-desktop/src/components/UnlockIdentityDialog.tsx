@@ -12,11 +12,13 @@ interface UnlockIdentityDialogProps {
   isOpen: boolean;
   identityUuid: string;
   identityName: string;
+  /** Optional note shown under the title, e.g. why the identity was locked. */
+  message?: string;
   onUnlocked: () => void;
   onCancel: () => void;
 }
 
-function UnlockIdentityDialog({ isOpen, identityUuid, identityName, onUnlocked, onCancel }: UnlockIdentityDialogProps) {
+function UnlockIdentityDialog({ isOpen, identityUuid, identityName, message, onUnlocked, onCancel }: UnlockIdentityDialogProps) {
   const { t } = useTranslation();
   const [passphrase, setPassphrase] = useState('');
   const [error, setError] = useState('');
@@ -60,6 +62,7 @@ function UnlockIdentityDialog({ isOpen, identityUuid, identityName, onUnlocked,
     <div className="fixed inset-0 bg-black/50 flex items-center justify-center z-50">
       <div className="bg-background border border-secondary p-6 rounded-lg w-96">
         <h2 className="text-xl font-bold mb-4">{t('identity.enterPassphrase', { name: identityName })}</h2>
+        {message && <p className="text-sm text-muted-foreground mb-4">{message}</p>}
 
         <form onSubmit={handleSubmit}>
           <div className="mb-4">
@@ -11,6 +11,8 @@ import { useUndoRedo } from '../hooks/useUndoRedo';
 import { useTagCloud } from '../hooks/useTagCloud';
 import { useTreeState } from '../hooks/useTreeState';
 import { useRelayPolling } from '../hooks/useRelayPolling';
+import { useBackgroundSync } from '../hooks/useBackgroundSync';
+import { useAutoLock } from '../hooks/useAutoLock';
 import { Undo2, Redo2 } from 'lucide-react';
 import { invoke } from '@tauri-apps/api/core';
 import { getCurrentWebviewWindow } from '@tauri-apps/api/webviewWindow';
@@ -26,6 +28,7 @@ import ScriptManagerDialog from './ScriptManagerDialog';
 import OperationsLogDialog from './OperationsLogDialog';
 import WorkspacePropertiesDialog from './WorkspacePropertiesDialog';
 import InviteWorkflow from './InviteWorkflow';
+import UnlockIdentityDialog from './UnlockIdentityDialog';
 import { ShareDialog } from './ShareDialog';
 import { CascadePreviewDialog } from './CascadePreviewDialog';
 import type { Note, TreeNode, WorkspaceInfo, DeleteResult, SchemaInfo, DropIndicator, SchemaMigratedEvent, ReceivedResponseInfo, CascadeImpactRow, SyncEvent, PeerInfo } from '../types';
@@ -113,6 +116,7 @@ function WorkspaceView({ workspaceInfo, onOpenWorkspacePeers, sharingIndicatorMo
 
   // Workspace-level relay polling
   const [hasRelayPeers, setHasRelayPeers] = useState(false);
+  const [hasSyncPeers, setHasSyncPeers] = useState(false);
 
   // Drag and drop state
   const [draggedNoteId, setDraggedNoteId] = useState<string | null>(null);
@@ -184,13 +188,16 @@ function WorkspaceView({ workspaceInfo, onOpenWorkspacePeers, sharingIndicatorMo
       invoke<boolean>("has_relay_credentials").catch(() => false),
     ]).then(([peers, hasCreds]) => {
       setHasPeers(peers.length > 0);
+      setHasSyncPeers(peers.some(p => p.channelType !== "manual"));
       setHasRelayPeers(
         peers.some(p => p.channelType !== "manual") || hasCreds
       );
     });
   }, []);
 
-  useRelayPolling(hasRelayPeers);
+  const autoLock = useAutoLock();
+  useRelayPolling(hasRelayPeers && !autoLock);
+  useBackgroundSync(hasSyncPeers && !autoLock);
 
   // Set up menu listener
   useEffect(() => {
@@ -901,6 +908,16 @@ function WorkspaceView({ workspaceInfo, onOpenWorkspacePeers, sharingIndicatorMo
         onClose={() => setShowWorkspaceProperties(false)}
       />
 
+      {/* Auto-lock prompt: the workspace is closed until the identity is unlocked again */}
+      <UnlockIdentityDialog
+        isOpen={autoLock !== null}
+        identityUuid={autoLock?.identityUuid ?? ''}
+        identityName={autoLock?.identityName ?? ''}
+        message={autoLock ? t(autoLock.reason === 'sleep' ? 'identity.lockedSleep' : 'identity.lockedIdle') : undefined}
+        onUnlocked={() => {}}
+        onCancel={() => getCurrentWebviewWindow().destroy()}
+      />
+
       {/* Invite to Subtree Dialog */}
       {inviteScope && workspaceInfo.identityUuid && (
         <InviteWorkflow
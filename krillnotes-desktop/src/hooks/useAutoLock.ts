@@ -0,0 +1,53 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { getCurrentWebviewWindow } from "@tauri-apps/api/webviewWindow";
+
+/** Minimum time between two `record_activity` calls. */
+const ACTIVITY_THROTTLE_MS = 30_000;
+
+export interface AutoLockState {
+  identityUuid: string;
+  identityName: string;
+  reason: "idle" | "sleep";
+}
+
+/**
+ * Reports user input to the backend auto-lock watcher and tracks whether
+ * this window's identity has been locked automatically. Returns the lock
+ * details while locked, or `null`. The window and its unsaved edits stay in
+ * place; the workspace is reopened once the identity is unlocked again.
+ */
+export function useAutoLock(): AutoLockState | null {
+  const [locked, setLocked] = useState<AutoLockState | null>(null);
+
+  useEffect(() => {
+    let last = 0;
+    const onActivity = () => {
+      const now = Date.now();
+      if (now - last < ACTIVITY_THROTTLE_MS) return;
+      last = now;
+      invoke("record_activity").catch(e =>
+        console.warn("record_activity failed:", e)
+      );
+    };
+    const events = ["keydown", "mousedown", "mousemove", "wheel"] as const;
+    events.forEach(e => window.addEventListener(e, onActivity, { passive: true }));
+    return () => events.forEach(e => window.removeEventListener(e, onActivity));
+  }, []);
+
+  useEffect(() => {
+    const win = getCurrentWebviewWindow();
+    const unlistenLocked = win.listen<AutoLockState>("identity-locked", event => {
+      setLocked(event.payload);
+    });
+    const unlistenUnlocked = win.listen("identity-unlocked", () => {
+      setLocked(null);
+    });
+    return () => {
+      unlistenLocked.then(f => f());
+      unlistenUnlocked.then(f => f());
+    };
+  }, []);
+
+  return locked;
+}
@@ -0,0 +1,23 @@
+import { useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * Runs the backend sync daemon for this window while the workspace has
+ * peers on an automatic channel. The daemon schedules its own polls and
+ * pushes after local edits, so it keeps working while the window is hidden.
+ */
+export function useBackgroundSync(hasSyncPeers: boolean) {
+  useEffect(() => {
+    if (!hasSyncPeers) return;
+
+    invoke("start_background_sync").catch(e =>
+      console.warn("start_background_sync failed:", e)
+    );
+
+    return () => {
+      invoke("stop_background_sync").catch(e =>
+        console.warn("stop_background_sync failed:", e)
+      );
+    };
+  }, [hasSyncPeers]);
+}
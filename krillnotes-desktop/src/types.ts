@@ -172,6 +172,16 @@ export interface AppSettings {
   sharingIndicatorMode?: string;
   undoHistoryLimit?: number;
   syncOnClose?: string;
+  autoLockMinutes?: number;
+  lockOnSleep?: boolean;
+  kdfPolicy?: KdfCost | null;
+}
+
+/** Argon2id cost: memory in KiB, iterations and lanes. */
+export interface KdfCost {
+  mCost: number;
+  tCost: number;
+  pCost: number;
 }
 
 export interface WorkspaceEntry {
@@ -224,6 +234,22 @@ export interface IdentityRef {
   lastUsed: string;  // ISO 8601
 }
 
+export type PassphraseStrength = 'veryWeak' | 'weak' | 'fair' | 'strong' | 'veryStrong';
+
+export type PassphraseHint =
+  | 'tooShort'
+  | 'addCharacterClasses'
+  | 'avoidRepeats'
+  | 'avoidSequences'
+  | 'common';
+
+export interface PassphraseEstimate {
+  entropyBits: number;
+  strength: PassphraseStrength;
+  acceptable: boolean;
+  hints: PassphraseHint[];
+}
+
 export interface WorkspaceBindingInfo {
   workspaceUuid: string;
   folderPath: string;
@@ -238,6 +264,7 @@ export interface ContactInfo {
   publicKey: string;
   fingerprint: string;
   trustLevel: TrustLevel;
+  vouchedBy: string | null; // contactId of the voucher
   firstSeen: string; // ISO 8601
   notes: string | null;
 }
@@ -252,7 +279,7 @@ export interface PeerInfo {
   lastSync?: string;      // ISO 8601, undefined if never synced
   isOwner?: boolean;
   isSelfPeer?: boolean;
-  channelType: string;          // "relay" | "folder" | "manual"
+  channelType: string;          // "relay" | "folder" | "git" | "lan" | "webdav" | "s3" | "manual"
   channelParams: string;        // JSON-encoded channel config, e.g. {"path":"/shared/folder"}
   syncStatus: string;           // "idle" | "syncing" | "error" | "auth_expired"
   syncStatusDetail: string | null;
@@ -281,6 +308,22 @@ export interface RelayAccountInfo {
   sessionValid: boolean;
 }
 
+export interface WebDavAccountInfo {
+  webdavAccountId: string;
+  url: string;
+  username: string;
+}
+
+export interface S3AccountInfo {
+  s3AccountId: string;
+  endpoint: string;
+  region: string;
+  bucket: string;
+  prefix: string;
+  pathStyle: boolean;
+  accessKeyId: string;
+}
+
 export interface InviteInfo {
   inviteId: string;
   workspaceId: string;
@@ -51,7 +51,10 @@ impl RbacGate {
             | Operation::UpdateSchema { .. }
             | Operation::RetractOperation { .. }
             | Operation::JoinWorkspace { .. }
-            | Operation::RegisterDevice { .. } => Ok(None),
+            | Operation::RegisterDevice { .. }
+            | Operation::RotateIdentityKey { .. }
+            | Operation::RevokeDevice { .. }
+            | Operation::SetRootRecovery { .. } => Ok(None),
         }
     }
 
@@ -60,6 +63,7 @@ impl RbacGate {
         &self,
         conn: &Connection,
         actor: &str,
+        actor_predecessors: &[String],
         role: Role,
         operation: &Operation,
     ) -> Result<(), PermissionError> {
@@ -73,7 +77,7 @@ impl RbacGate {
                 require_at_least(role, Role::Writer)?;
             }
             Operation::DeleteNote { note_id, .. } if role < Role::Owner => {
-                self.require_authorship(conn, actor, note_id, role)?;
+                self.require_authorship(conn, actor, actor_predecessors, note_id, role)?;
             }
             Operation::MoveNote {
                 note_id,
@@ -81,7 +85,7 @@ impl RbacGate {
                 ..
             } => {
                 if role < Role::Owner {
-                    self.require_authorship(conn, actor, note_id, role)?;
+                    self.require_authorship(conn, actor, actor_predecessors, note_id, role)?;
                 }
                 // Check destination scope — actor must have Writer+ at the target
                 if let Some(dest_id) = new_parent_id {
@@ -155,11 +159,13 @@ impl RbacGate {
         Ok(())
     }
 
-    /// For Writer delete/move: verify the actor authored the target note.
+    /// For Writer delete/move: verify the actor, under its current or an
+    /// earlier key, authored the target note.
     fn require_authorship(
         &self,
         conn: &Connection,
         actor: &str,
+        actor_predecessors: &[String],
         note_id: &str,
         role: Role,
     ) -> Result<(), PermissionError> {
@@ -171,7 +177,7 @@ impl RbacGate {
                 |row| row.get(0),
             )
             .map_err(|_| PermissionError::Denied("note not found".into()))?;
-        if created_by != actor {
+        if created_by != actor && !actor_predecessors.contains(&created_by) {
             return Err(PermissionError::Denied(
                 "writers can only delete/move notes they authored".into(),
             ));
@@ -201,6 +207,7 @@ impl PermissionGate for RbacGate {
         &self,
         conn: &Connection,
         actor: &str,
+        actor_predecessors: &[String],
         operation: &Operation,
     ) -> Result<(), PermissionError> {
         // Root Owner bypasses all checks
@@ -222,7 +229,7 @@ impl PermissionGate for RbacGate {
         let role = crate::resolver::resolve_role(conn, actor, &note_id)?
             .ok_or_else(|| PermissionError::Denied("no access to this subtree".into()))?;
 
-        self.check_role_for_operation(conn, actor, role, operation)
+        self.check_role_for_operation(conn, actor, actor_predecessors, role, operation)
     }
 
     fn apply_permission_op(
@@ -292,6 +299,21 @@ impl PermissionGate for RbacGate {
                 }
                 Ok(())
             }
+            Operation::RotateIdentityKey {
+                old_key, new_key, ..
+            } => {
+                // Same principal under a new key: grants held and made by
+                // the old key move over.
+                conn.execute(
+                    "UPDATE OR REPLACE note_permissions SET user_id = ?2 WHERE user_id = ?1",
+                    rusqlite::params![old_key, new_key],
+                )?;
+                conn.execute(
+                    "UPDATE note_permissions SET granted_by = ?2 WHERE granted_by = ?1",
+                    rusqlite::params![old_key, new_key],
+                )?;
+                Ok(())
+            }
             _ => Err(PermissionError::NotAPermissionOp),
         }
     }
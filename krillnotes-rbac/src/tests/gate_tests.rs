@@ -58,6 +58,10 @@ fn make_create_note(parent_id: &str) -> Operation {
         position: 0.0,
         fields: std::collections::BTreeMap::new(),
         created_by: String::new(),
+        created_at: None,
+        modified_at: None,
+        original_author: None,
+        prev_hash: None,
         signature: String::new(),
     }
 }
@@ -78,6 +82,10 @@ fn make_create_note_root() -> Operation {
         position: 0.0,
         fields: std::collections::BTreeMap::new(),
         created_by: String::new(),
+        created_at: None,
+        modified_at: None,
+        original_author: None,
+        prev_hash: None,
         signature: String::new(),
     }
 }
@@ -95,6 +103,7 @@ fn make_update_field(note_id: &str) -> Operation {
         field: "content".into(),
         value: krillnotes_core::FieldValue::Text("test".into()),
         modified_by: String::new(),
+        prev_hash: None,
         signature: String::new(),
     }
 }
@@ -110,6 +119,7 @@ fn make_delete_note(note_id: &str) -> Operation {
         device_id: "test_device".into(),
         note_id: note_id.into(),
         deleted_by: String::new(),
+        prev_hash: None,
         signature: String::new(),
     }
 }
@@ -131,6 +141,7 @@ fn make_set_permission_by(note_id: &str, user_id: &str, role: &str, granted_by:
         user_id: user_id.into(),
         role: role.into(),
         granted_by: granted_by.into(),
+        prev_hash: None,
         signature: String::new(),
     }
 }
@@ -159,6 +170,7 @@ fn make_revoke_permission(note_id: &str, user_id: &str) -> Operation {
         note_id: Some(note_id.into()),
         user_id: user_id.into(),
         revoked_by: ROOT_OWNER.into(),
+        prev_hash: None,
         signature: String::new(),
     }
 }
@@ -176,6 +188,7 @@ fn make_move_note_op(note_id: &str, new_parent_id: &str) -> Operation {
         new_parent_id: Some(new_parent_id.into()),
         new_position: 0.0,
         moved_by: String::new(),
+        prev_hash: None,
         signature: String::new(),
     }
 }
@@ -186,14 +199,14 @@ fn make_move_note_op(note_id: &str, new_parent_id: &str) -> Operation {
 fn test_root_owner_allowed_everything() {
     let (conn, gate) = setup_gate_db();
     let op = make_create_note("root_a");
-    assert!(gate.authorize(&conn, ROOT_OWNER, &op).is_ok());
+    assert!(gate.authorize(&conn, ROOT_OWNER, &[], &op).is_ok());
 }
 
 #[test]
 fn test_no_grant_denied() {
     let (conn, gate) = setup_gate_db();
     let op = make_create_note("root_a");
-    assert!(gate.authorize(&conn, BOB, &op).is_err());
+    assert!(gate.authorize(&conn, BOB, &[], &op).is_err());
 }
 
 #[test]
@@ -201,13 +214,13 @@ fn test_owner_can_create_update_delete() {
     let (conn, gate) = setup_gate_db();
     grant(&conn, "root_a", BOB, "owner");
     assert!(gate
-        .authorize(&conn, BOB, &make_create_note("root_a"))
+        .authorize(&conn, BOB, &[], &make_create_note("root_a"))
         .is_ok());
     assert!(gate
-        .authorize(&conn, BOB, &make_update_field("child_1"))
+        .authorize(&conn, BOB, &[], &make_update_field("child_1"))
         .is_ok());
     assert!(gate
-        .authorize(&conn, BOB, &make_delete_note("child_2"))
+        .authorize(&conn, BOB, &[], &make_delete_note("child_2"))
         .is_ok());
 }
 
@@ -216,10 +229,10 @@ fn test_writer_can_create_and_edit() {
     let (conn, gate) = setup_gate_db();
     grant(&conn, "root_a", BOB, "writer");
     assert!(gate
-        .authorize(&conn, BOB, &make_create_note("root_a"))
+        .authorize(&conn, BOB, &[], &make_create_note("root_a"))
         .is_ok());
     assert!(gate
-        .authorize(&conn, BOB, &make_update_field("child_1"))
+        .authorize(&conn, BOB, &[], &make_update_field("child_1"))
         .is_ok());
 }
 
@@ -229,7 +242,7 @@ fn test_writer_can_delete_own_note() {
     grant(&conn, "root_a", BOB, "writer");
     // child_1 was created_by BOB
     assert!(gate
-        .authorize(&conn, BOB, &make_delete_note("child_1"))
+        .authorize(&conn, BOB, &[], &make_delete_note("child_1"))
         .is_ok());
 }
 
@@ -239,7 +252,7 @@ fn test_writer_cannot_delete_others_note() {
     grant(&conn, "root_a", BOB, "writer");
     // child_2 was created_by CAROL
     assert!(gate
-        .authorize(&conn, BOB, &make_delete_note("child_2"))
+        .authorize(&conn, BOB, &[], &make_delete_note("child_2"))
         .is_err());
 }
 
@@ -248,7 +261,7 @@ fn test_reader_cannot_create() {
     let (conn, gate) = setup_gate_db();
     grant(&conn, "root_a", BOB, "reader");
     assert!(gate
-        .authorize(&conn, BOB, &make_create_note("root_a"))
+        .authorize(&conn, BOB, &[], &make_create_note("root_a"))
         .is_err());
 }
 
@@ -257,7 +270,7 @@ fn test_reader_cannot_update() {
     let (conn, gate) = setup_gate_db();
     grant(&conn, "root_a", BOB, "reader");
     assert!(gate
-        .authorize(&conn, BOB, &make_update_field("child_1"))
+        .authorize(&conn, BOB, &[], &make_update_field("child_1"))
         .is_err());
 }
 
@@ -266,7 +279,12 @@ fn test_writer_cannot_set_permission() {
     let (conn, gate) = setup_gate_db();
     grant(&conn, "root_a", BOB, "writer");
     assert!(gate
-        .authorize(&conn, BOB, &make_set_permission("root_a", CAROL, "reader"))
+        .authorize(
+            &conn,
+            BOB,
+            &[],
+            &make_set_permission("root_a", CAROL, "reader")
+        )
         .is_err());
 }
 
@@ -275,13 +293,28 @@ fn test_owner_can_set_permission_up_to_owner() {
     let (conn, gate) = setup_gate_db();
     grant(&conn, "root_a", BOB, "owner");
     assert!(gate
-        .authorize(&conn, BOB, &make_set_permission("root_a", CAROL, "owner"))
+        .authorize(
+            &conn,
+            BOB,
+            &[],
+            &make_set_permission("root_a", CAROL, "owner")
+        )
         .is_ok());
     assert!(gate
-        .authorize(&conn, BOB, &make_set_permission("root_a", CAROL, "writer"))
+        .authorize(
+            &conn,
+            BOB,
+            &[],
+            &make_set_permission("root_a", CAROL, "writer")
+        )
         .is_ok());
     assert!(gate
-        .authorize(&conn, BOB, &make_set_permission("root_a", CAROL, "reader"))
+        .authorize(
+            &conn,
+            BOB,
+            &[],
+            &make_set_permission("root_a", CAROL, "reader")
+        )
         .is_ok());
 }
 
@@ -290,14 +323,14 @@ fn test_owner_cannot_create_root_note() {
     let (conn, gate) = setup_gate_db();
     grant(&conn, "root_a", BOB, "owner");
     let op = make_create_note_root();
-    assert!(gate.authorize(&conn, BOB, &op).is_err());
+    assert!(gate.authorize(&conn, BOB, &[], &op).is_err());
 }
 
 #[test]
 fn test_root_owner_can_create_root_note() {
     let (conn, gate) = setup_gate_db();
     let op = make_create_note_root();
-    assert!(gate.authorize(&conn, ROOT_OWNER, &op).is_ok());
+    assert!(gate.authorize(&conn, ROOT_OWNER, &[], &op).is_ok());
 }
 
 // --- apply_permission_op tests ---
@@ -401,7 +434,7 @@ fn test_move_note_denied_to_inaccessible_destination() {
     // Bob authored child_1 (created_by = BOB from setup_gate_db)
 
     let op = make_move_note_op("child_1", "subtree_b");
-    let result = gate.authorize(&conn, BOB, &op);
+    let result = gate.authorize(&conn, BOB, &[], &op);
     assert!(result.is_err(), "should be denied — no access to subtree_b");
 }
 
@@ -413,7 +446,7 @@ fn test_move_note_allowed_within_same_subtree() {
 
     // Move child_1 under child_2 (both in root_a)
     let op = make_move_note_op("child_1", "child_2");
-    let result = gate.authorize(&conn, BOB, &op);
+    let result = gate.authorize(&conn, BOB, &[], &op);
     assert!(result.is_ok(), "should be allowed — both in root_a subtree");
 }
 
@@ -432,9 +465,69 @@ fn test_move_note_denied_reader_at_destination() {
     grant(&conn, "subtree_b", BOB, "reader");
 
     let op = make_move_note_op("child_1", "subtree_b");
-    let result = gate.authorize(&conn, BOB, &op);
+    let result = gate.authorize(&conn, BOB, &[], &op);
     assert!(
         result.is_err(),
         "should be denied — reader at destination can't write"
     );
 }
+
+#[test]
+fn test_rotate_identity_key_moves_grants_and_authorship() {
+    const BOB_NEW: &str = "bob_new_pubkey_base64";
+    let (conn, gate) = setup_gate_db();
+    grant(&conn, "root_a", BOB, "owner");
+    grant_by(&conn, "child_2", CAROL, "writer", BOB);
+
+    let op = Operation::RotateIdentityKey {
+        operation_id: uuid::Uuid::new_v4().to_string(),
+        timestamp: krillnotes_core::HlcTimestamp {
+            wall_ms: 1,
+            counter: 0,
+            node_id: 0,
+        },
+        device_id: "test_device".into(),
+        old_key: BOB.into(),
+        new_key: BOB_NEW.into(),
+        rotated_at_ms: 1,
+        succession_signature: String::new(),
+        prev_hash: None,
+        signature: String::new(),
+    };
+    gate.apply_permission_op(&conn, &op).unwrap();
+
+    assert_eq!(
+        crate::resolver::resolve_role(&conn, BOB_NEW, "root_a").unwrap(),
+        Some(Role::Owner)
+    );
+    assert_eq!(
+        crate::resolver::resolve_role(&conn, BOB, "root_a").unwrap(),
+        None
+    );
+    let granted_by: String = conn
+        .query_row(
+            "SELECT granted_by FROM note_permissions WHERE note_id = 'child_2'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(granted_by, BOB_NEW);
+
+    // child_1 was created under Bob's old key, which the new key counts as its
+    // own only when core passes it as a predecessor.
+    conn.execute(
+        "UPDATE note_permissions SET role = 'writer' WHERE user_id = ?1",
+        [BOB_NEW],
+    )
+    .unwrap();
+    let predecessors = [BOB.to_string()];
+    assert!(gate
+        .authorize(&conn, BOB_NEW, &predecessors, &make_delete_note("child_1"))
+        .is_ok());
+    assert!(gate
+        .authorize(&conn, BOB_NEW, &[], &make_delete_note("child_1"))
+        .is_err());
+    assert!(gate
+        .authorize(&conn, BOB_NEW, &predecessors, &make_delete_note("child_2"))
+        .is_err());
+}
@@ -62,6 +62,10 @@ fn make_create_note(parent_id: &str) -> Operation {
         position: 0.0,
         fields: std::collections::BTreeMap::new(),
         created_by: String::new(),
+        created_at: None,
+        modified_at: None,
+        original_author: None,
+        prev_hash: None,
         signature: String::new(),
     }
 }
@@ -79,6 +83,7 @@ fn make_update_field(note_id: &str) -> Operation {
         field: "content".into(),
         value: krillnotes_core::FieldValue::Text("test".into()),
         modified_by: String::new(),
+        prev_hash: None,
         signature: String::new(),
     }
 }
@@ -94,6 +99,7 @@ fn make_delete_note(note_id: &str) -> Operation {
         device_id: "test_device".into(),
         note_id: note_id.into(),
         deleted_by: String::new(),
+        prev_hash: None,
         signature: String::new(),
     }
 }
@@ -115,6 +121,7 @@ fn make_set_permission_by(note_id: &str, user_id: &str, role: &str, granted_by:
         user_id: user_id.into(),
         role: role.into(),
         granted_by: granted_by.into(),
+        prev_hash: None,
         signature: String::new(),
     }
 }
@@ -131,6 +138,7 @@ fn make_revoke_permission(note_id: &str, user_id: &str) -> Operation {
         note_id: Some(note_id.into()),
         user_id: user_id.into(),
         revoked_by: ROOT_OWNER.into(),
+        prev_hash: None,
         signature: String::new(),
     }
 }
@@ -147,25 +155,25 @@ fn test_full_lifecycle_invite_and_authorize() {
 
     // Root Owner grants Bob owner on root_a
     let grant_op = make_set_permission("root_a", BOB, "owner");
-    gate.authorize(&conn, ROOT_OWNER, &grant_op).unwrap();
+    gate.authorize(&conn, ROOT_OWNER, &[], &grant_op).unwrap();
     gate.apply_permission_op(&conn, &grant_op).unwrap();
 
     // Bob (now owner) grants Carol writer on root_a
     let grant_op2 = make_set_permission_by("root_a", CAROL, "writer", BOB);
-    gate.authorize(&conn, BOB, &grant_op2).unwrap();
+    gate.authorize(&conn, BOB, &[], &grant_op2).unwrap();
     gate.apply_permission_op(&conn, &grant_op2).unwrap();
 
     // Carol can create and edit
     assert!(gate
-        .authorize(&conn, CAROL, &make_create_note("root_a"))
+        .authorize(&conn, CAROL, &[], &make_create_note("root_a"))
         .is_ok());
     assert!(gate
-        .authorize(&conn, CAROL, &make_update_field("child_1"))
+        .authorize(&conn, CAROL, &[], &make_update_field("child_1"))
         .is_ok());
 
     // Carol cannot delete Bob's note (child_1 created_by BOB, Carol is writer)
     assert!(gate
-        .authorize(&conn, CAROL, &make_delete_note("child_1"))
+        .authorize(&conn, CAROL, &[], &make_delete_note("child_1"))
         .is_err());
 
     // Carol cannot set permissions (writers lack that ability)
@@ -173,6 +181,7 @@ fn test_full_lifecycle_invite_and_authorize() {
         .authorize(
             &conn,
             CAROL,
+            &[],
             &make_set_permission("root_a", "dave", "reader")
         )
         .is_err());
@@ -188,26 +197,26 @@ fn test_cascade_revocation_end_to_end() {
 
     // Chain: root_owner → bob (owner) → carol (writer)
     let g1 = make_set_permission("root_a", BOB, "owner");
-    gate.authorize(&conn, ROOT_OWNER, &g1).unwrap();
+    gate.authorize(&conn, ROOT_OWNER, &[], &g1).unwrap();
     gate.apply_permission_op(&conn, &g1).unwrap();
 
     let g2 = make_set_permission_by("root_a", CAROL, "writer", BOB);
-    gate.authorize(&conn, BOB, &g2).unwrap();
+    gate.authorize(&conn, BOB, &[], &g2).unwrap();
     gate.apply_permission_op(&conn, &g2).unwrap();
 
     // Carol can create
     assert!(gate
-        .authorize(&conn, CAROL, &make_create_note("root_a"))
+        .authorize(&conn, CAROL, &[], &make_create_note("root_a"))
         .is_ok());
 
     // Revoke Bob
     let revoke = make_revoke_permission("root_a", BOB);
-    gate.authorize(&conn, ROOT_OWNER, &revoke).unwrap();
+    gate.authorize(&conn, ROOT_OWNER, &[], &revoke).unwrap();
     gate.apply_permission_op(&conn, &revoke).unwrap();
 
     // Carol's grant is PRESERVED (opt-in cascade — UI decides)
     assert!(gate
-        .authorize(&conn, CAROL, &make_create_note("root_a"))
+        .authorize(&conn, CAROL, &[], &make_create_note("root_a"))
         .is_ok());
 }
 
@@ -220,17 +229,17 @@ fn test_multi_subtree_isolation() {
 
     // Bob is owner on root_a, no access to root_b
     let g1 = make_set_permission("root_a", BOB, "owner");
-    gate.authorize(&conn, ROOT_OWNER, &g1).unwrap();
+    gate.authorize(&conn, ROOT_OWNER, &[], &g1).unwrap();
     gate.apply_permission_op(&conn, &g1).unwrap();
 
     // Bob can operate on root_a subtree
     assert!(gate
-        .authorize(&conn, BOB, &make_create_note("root_a"))
+        .authorize(&conn, BOB, &[], &make_create_note("root_a"))
         .is_ok());
 
     // Bob cannot operate on root_b subtree
     assert!(gate
-        .authorize(&conn, BOB, &make_create_note("root_b"))
+        .authorize(&conn, BOB, &[], &make_create_note("root_b"))
         .is_err());
 }
 
@@ -257,6 +266,7 @@ fn test_root_owner_can_do_everything() {
         "test-identity",
         SigningKey::from_bytes(&[1u8; 32]),
         gate,
+        None,
     )
     .unwrap();
 
@@ -311,6 +321,7 @@ fn test_delete_recursive_cleans_up_permissions() {
         "test-identity",
         SigningKey::from_bytes(&[1u8; 32]),
         gate,
+        None,
     )
     .unwrap();
 
@@ -382,6 +393,7 @@ fn test_delete_promote_cleans_up_permissions() {
         "test-identity",
         SigningKey::from_bytes(&[1u8; 32]),
         gate,
+        None,
     )
     .unwrap();
 
@@ -453,6 +465,7 @@ fn test_non_owner_without_grants_is_denied() {
         "owner-identity",
         SigningKey::from_bytes(&[1u8; 32]),
         gate,
+        None,
     )
     .unwrap();
 
@@ -477,6 +490,7 @@ fn test_non_owner_without_grants_is_denied() {
         "non-owner-identity",
         non_owner_key,
         non_owner_gate,
+        None,
     )
     .unwrap();
 
@@ -2,3 +2,4 @@ mod gate_tests;
 mod integration_tests;
 mod query_tests;
 mod resolver_tests;
+mod scope_sync_tests;
@@ -0,0 +1,368 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Scope-filtered sync: deltas and snapshots sent to a peer must only carry
+//! content the peer can read under RBAC, and notes crossing the scope
+//! boundary must appear / disappear on the peer's side.
+
+use std::collections::BTreeMap;
+
+use base64::Engine;
+use ed25519_dalek::SigningKey;
+use krillnotes_core::core::contact::{ContactManager, TrustLevel};
+use krillnotes_core::core::operation::Operation;
+use krillnotes_core::core::permission::{AllowAllGate, PermissionGate};
+use krillnotes_core::core::swarm::delta::{
+    create_delta_bundle, parse_delta_bundle, BlobWant, DeltaParams,
+};
+use krillnotes_core::core::swarm::sync::{apply_delta, generate_delta};
+use krillnotes_core::core::workspace::{AddPosition, Workspace};
+use krillnotes_core::FieldValue;
+
+use crate::gate::RbacGate;
+
+const SECRET_VALUE: &str = "TOP-SECRET-FIELD-VALUE";
+const ROOT_VALUE: &str = "PRIVATE-ROOT-VALUE";
+const SECRET_BLOB: &[u8] = b"SECRET-ATTACHMENT-BLOB";
+const SHARED_BLOB: &[u8] = b"shared attachment blob";
+
+fn b64(key: &SigningKey) -> String {
+    base64::engine::general_purpose::STANDARD.encode(key.verifying_key().as_bytes())
+}
+
+fn body(value: &str) -> BTreeMap<String, FieldValue> {
+    let mut fields = BTreeMap::new();
+    fields.insert("body".to_string(), FieldValue::Text(value.to_string()));
+    fields
+}
+
+/// Alice's workspace plus the ids of the notes in this tree:
+///
+/// ```text
+/// root        (body = ROOT_VALUE)
+/// ├── shared  (Bob: reader, attachment SHARED_BLOB)
+/// │   └── inner
+/// └── secret  (body = SECRET_VALUE, attachment SECRET_BLOB)
+/// ```
+struct Fixture {
+    _dir: tempfile::TempDir,
+    ws: Workspace,
+    alice_key: SigningKey,
+    bob_key: SigningKey,
+    cm: ContactManager,
+    root: String,
+    shared: String,
+    inner: String,
+    secret: String,
+}
+
+fn setup() -> Fixture {
+    let alice_key = SigningKey::from_bytes(&[1u8; 32]);
+    let bob_key = SigningKey::from_bytes(&[2u8; 32]);
+    let gate: Box<dyn PermissionGate> = Box::new(RbacGate::new(b64(&alice_key)));
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut ws = Workspace::create(
+        dir.path().join("alice.db"),
+        "",
+        "alice-identity",
+        SigningKey::from_bytes(&alice_key.to_bytes()),
+        gate,
+        None,
+    )
+    .unwrap();
+
+    let root = ws.list_all_notes().unwrap()[0].id.clone();
+    ws.update_note(&root, "Root".into(), body(ROOT_VALUE))
+        .unwrap();
+    let shared = ws
+        .create_note(&root, AddPosition::AsChild, "TextNote")
+        .unwrap();
+    ws.update_note(&shared, "Shared".into(), body("shared body"))
+        .unwrap();
+    ws.attach_file(&shared, "shared.txt", None, SHARED_BLOB, Some(&alice_key))
+        .unwrap();
+    let inner = ws
+        .create_note(&shared, AddPosition::AsChild, "TextNote")
+        .unwrap();
+    ws.update_note(&inner, "Inner".into(), body("inner body"))
+        .unwrap();
+    let secret = ws
+        .create_note(&root, AddPosition::AsChild, "TextNote")
+        .unwrap();
+    ws.update_note(&secret, "Secret".into(), body(SECRET_VALUE))
+        .unwrap();
+    ws.attach_file(&secret, "secret.bin", None, SECRET_BLOB, Some(&alice_key))
+        .unwrap();
+    ws.set_permission(&shared, &b64(&bob_key), "reader")
+        .unwrap();
+
+    let cm_dir = dir.path().join("contacts");
+    let cm = ContactManager::for_identity(cm_dir, [30u8; 32]).unwrap();
+    cm.find_or_create_by_public_key("Bob", &b64(&bob_key), TrustLevel::Tofu)
+        .unwrap();
+
+    Fixture {
+        _dir: dir,
+        ws,
+        alice_key,
+        bob_key,
+        cm,
+        root,
+        shared,
+        inner,
+        secret,
+    }
+}
+
+/// Builds Bob's replica from the snapshot Alice would send him.
+fn bob_from_snapshot(fx: &Fixture, dir: &tempfile::TempDir) -> Workspace {
+    let (json, scope) = fx.ws.to_snapshot_json_for(&b64(&fx.bob_key)).unwrap();
+    assert!(scope.is_some(), "Bob is not the root owner");
+    let text = String::from_utf8_lossy(&json);
+    assert!(!text.contains(SECRET_VALUE), "snapshot leaks secret field");
+    assert!(
+        !text.contains(ROOT_VALUE),
+        "snapshot leaks ghost ancestor field"
+    );
+
+    let mut bob = Workspace::create_empty_with_id(
+        dir.path().join("bob.db"),
+        "",
+        "bob-identity",
+        SigningKey::from_bytes(&fx.bob_key.to_bytes()),
+        fx.ws.workspace_id(),
+        Box::new(AllowAllGate::new("krillnotes/1")),
+        None,
+    )
+    .unwrap();
+    bob.set_owner_pubkey(&b64(&fx.alice_key)).unwrap();
+    bob.import_snapshot_json(&json).unwrap();
+    bob
+}
+
+/// Delivers a bundle from Bob's device asking Alice for attachment content,
+/// the way Bob's sync engine would (or a misbehaving peer could).
+fn request_blobs(fx: &mut Fixture, hashes: &[&str]) {
+    let alice_vk = fx.alice_key.verifying_key();
+    let bytes = create_delta_bundle(DeltaParams {
+        protocol: fx.ws.protocol_id().to_string(),
+        workspace_id: fx.ws.workspace_id().to_string(),
+        workspace_name: "Test".into(),
+        source_device_id: "dev-bob".into(),
+        source_display_name: "Bob".into(),
+        since_operation_id: String::new(),
+        delta_operations: vec![],
+        sender_key: &fx.bob_key,
+        recipient_keys: vec![&alice_vk],
+        recipient_peer_ids: vec![fx.ws.device_id().to_string()],
+        recipient_identity_id: b64(&fx.alice_key),
+        owner_pubkey: b64(&fx.alice_key),
+        ack_operation_id: None,
+        attachment_blobs: vec![],
+        blob_wants: hashes
+            .iter()
+            .map(|h| BlobWant {
+                hash_sha256: h.to_string(),
+                offset: 0,
+            })
+            .collect(),
+        blob_chunks: vec![],
+        checkpoint: None,
+    })
+    .unwrap();
+    apply_delta(&bytes, &mut fx.ws, &fx.alice_key, &mut fx.cm).unwrap();
+}
+
+fn attachment_hash(ws: &Workspace, note_id: &str) -> String {
+    ws.get_attachments(note_id).unwrap()[0].hash_sha256.clone()
+}
+
+#[test]
+fn test_delta_excludes_out_of_scope_content() {
+    let mut fx = setup();
+    fx.ws
+        .upsert_sync_peer("dev-bob", &b64(&fx.bob_key), None, None)
+        .unwrap();
+
+    let bundle = generate_delta(
+        &mut fx.ws,
+        "dev-bob",
+        "Test",
+        &fx.alice_key,
+        "Alice",
+        &fx.cm,
+    )
+    .unwrap();
+    let parsed = parse_delta_bundle(&bundle.bundle_bytes, &fx.bob_key).unwrap();
+
+    let ops_json = serde_json::to_string(&parsed.delta_operations).unwrap();
+    assert!(!ops_json.contains(SECRET_VALUE), "delta leaks secret field");
+    assert!(
+        !ops_json.contains(ROOT_VALUE),
+        "delta leaks ghost ancestor field"
+    );
+
+    assert!(
+        !ops_json.contains(&fx.secret),
+        "secret note must not be sent"
+    );
+    assert!(ops_json.contains(&fx.shared));
+    assert!(ops_json.contains(&fx.inner));
+
+    assert!(parsed.attachment_blobs.is_empty() && parsed.blob_chunks.is_empty());
+    assert!(bundle.scope.is_some());
+
+    // Content is served on request, and only for notes Bob can read.
+    let secret_hash = attachment_hash(&fx.ws, &fx.secret);
+    let shared_hash = attachment_hash(&fx.ws, &fx.shared);
+    request_blobs(&mut fx, &[&secret_hash, &shared_hash]);
+    let bundle = generate_delta(
+        &mut fx.ws,
+        "dev-bob",
+        "Test",
+        &fx.alice_key,
+        "Alice",
+        &fx.cm,
+    )
+    .unwrap();
+    let parsed = parse_delta_bundle(&bundle.bundle_bytes, &fx.bob_key).unwrap();
+    let chunks: Vec<_> = parsed
+        .blob_chunks
+        .iter()
+        .map(|c| (c.hash_sha256.as_str(), c.data.as_slice()))
+        .collect();
+    assert_eq!(
+        chunks,
+        vec![(shared_hash.as_str(), SHARED_BLOB)],
+        "delta leaks secret attachment"
+    );
+}
+
+#[test]
+fn test_notes_crossing_scope_boundary() {
+    let mut fx = setup();
+    let bob_pk = b64(&fx.bob_key);
+    let bob_dir = tempfile::tempdir().unwrap();
+    let mut bob = bob_from_snapshot(&fx, &bob_dir);
+    let mut bob_cm =
+        ContactManager::for_identity(bob_dir.path().join("contacts"), [31u8; 32]).unwrap();
+
+    assert!(bob.get_note(&fx.shared).is_ok());
+    assert!(bob.get_note(&fx.inner).is_ok());
+    assert!(bob.get_note(&fx.secret).is_err());
+    let ghost_root = bob.get_note(&fx.root).unwrap();
+    assert!(
+        ghost_root.fields.is_empty(),
+        "ancestor must arrive as a ghost"
+    );
+
+    // Record what the snapshot delivered, as the desktop snapshot command does.
+    let (_, scope) = fx.ws.to_snapshot_json_for(&bob_pk).unwrap();
+    let watermark = fx.ws.get_latest_operation_id().unwrap().unwrap();
+    fx.ws
+        .upsert_sync_peer("dev-bob", &bob_pk, Some(&watermark), None)
+        .unwrap();
+    fx.ws
+        .record_peer_sent_scope("dev-bob", scope.as_ref())
+        .unwrap();
+
+    // ── secret moves into scope ──────────────────────────────────────────
+    // Carol wrote it: relaying it must not make Alice its author on Bob's side.
+    let carol_pk = b64(&SigningKey::from_bytes(&[3u8; 32]));
+    fx.ws
+        .connection()
+        .execute(
+            "UPDATE notes SET created_by = ?1 WHERE id = ?2",
+            [&carol_pk, &fx.secret],
+        )
+        .unwrap();
+    fx.ws.move_note(&fx.secret, Some(&fx.shared), 0.0).unwrap();
+    let bundle = generate_delta(
+        &mut fx.ws,
+        "dev-bob",
+        "Test",
+        &fx.alice_key,
+        "Alice",
+        &fx.cm,
+    )
+    .unwrap();
+    apply_delta(&bundle.bundle_bytes, &mut bob, &fx.bob_key, &mut bob_cm).unwrap();
+
+    let arrived = bob.get_note(&fx.secret).unwrap();
+    assert_eq!(arrived.parent_id.as_deref(), Some(fx.shared.as_str()));
+    assert_eq!(arrived.created_by, carol_pk);
+    assert_eq!(
+        arrived.fields.get("body"),
+        Some(&FieldValue::Text(SECRET_VALUE.into()))
+    );
+    // The attachment is listed once its content has been fetched by hash.
+    assert!(bob.get_attachments(&fx.secret).unwrap().is_empty());
+    let pending = bob.pending_attachments().unwrap();
+    assert_eq!(pending.len(), 1);
+    fx.ws
+        .upsert_sync_peer("dev-bob", &bob_pk, bundle.last_included_op.as_deref(), None)
+        .unwrap();
+    fx.ws
+        .record_peer_sent_scope("dev-bob", bundle.scope.as_ref())
+        .unwrap();
+    request_blobs(&mut fx, &[&pending[0].hash_sha256]);
+    let reply = generate_delta(
+        &mut fx.ws,
+        "dev-bob",
+        "Test",
+        &fx.alice_key,
+        "Alice",
+        &fx.cm,
+    )
+    .unwrap();
+    apply_delta(&reply.bundle_bytes, &mut bob, &fx.bob_key, &mut bob_cm).unwrap();
+    let attachments = bob.get_attachments(&fx.secret).unwrap();
+    assert_eq!(attachments.len(), 1);
+    assert_eq!(
+        bob.get_attachment_bytes(&attachments[0].id).unwrap(),
+        SECRET_BLOB
+    );
+
+    // Boundary ops are applied but never enter Bob's op log.
+    let parsed = parse_delta_bundle(&bundle.bundle_bytes, &fx.bob_key).unwrap();
+    let boundary: Vec<_> = parsed
+        .delta_operations
+        .iter()
+        .filter(|d| d.scope_boundary)
+        .collect();
+    assert!(!boundary.is_empty());
+    for d in &boundary {
+        assert!(!bob.operation_exists(d.op.operation_id()).unwrap());
+    }
+
+    // ── inner moves out of scope ─────────────────────────────────────────
+    fx.ws.move_note(&fx.inner, Some(&fx.root), 5.0).unwrap();
+    fx.ws
+        .update_note(&fx.inner, "Inner".into(), body("edited after leaving"))
+        .unwrap();
+    let bundle = generate_delta(
+        &mut fx.ws,
+        "dev-bob",
+        "Test",
+        &fx.alice_key,
+        "Alice",
+        &fx.cm,
+    )
+    .unwrap();
+    let parsed = parse_delta_bundle(&bundle.bundle_bytes, &fx.bob_key).unwrap();
+    let ops_json = serde_json::to_string(&parsed.delta_operations).unwrap();
+    assert!(!ops_json.contains("edited after leaving"));
+    assert!(parsed.delta_operations.iter().any(|d| d.scope_boundary
+        && matches!(&d.op, Operation::DeleteNote { note_id, .. } if *note_id == fx.inner)));
+
+    apply_delta(&bundle.bundle_bytes, &mut bob, &fx.bob_key, &mut bob_cm).unwrap();
+    assert!(
+        bob.get_note(&fx.inner).is_err(),
+        "inner must leave Bob's replica"
+    );
+    assert!(bob.get_note(&fx.secret).is_ok());
+}
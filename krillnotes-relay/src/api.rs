@@ -0,0 +1,1151 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Request routing and endpoint handlers.
+//!
+//! Transport-independent: [`Api::handle`] takes an [`ApiRequest`] and returns
+//! an [`ApiResponse`], so handlers are testable without a socket. Successful
+//! responses are wrapped as `{"data": ...}`, failures as
+//! `{"error":{"code":"...","message":"..."}}`.
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::config::RelayConfig;
+use crate::crypto;
+use crate::error::{RelayError, Result};
+use crate::store::{AccountRow, InviteRow, NewBundle, Store};
+
+/// Seconds between sweeps of expired rows.
+const PURGE_INTERVAL_SECS: i64 = 60;
+
+/// A parsed HTTP request.
+#[derive(Debug, Clone, Default)]
+pub struct ApiRequest {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    /// Token from an `Authorization: Bearer ...` header.
+    pub bearer: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl ApiRequest {
+    /// Builds a request from a method and a URL (`/path?query`).
+    pub fn new(method: &str, url: &str) -> Self {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        Self {
+            method: method.to_ascii_uppercase(),
+            path: percent_decode(path),
+            query: parse_query(query),
+            ..Self::default()
+        }
+    }
+
+    fn query_param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn json<T: DeserializeOwned>(&self) -> Result<T> {
+        serde_json::from_slice(&self.body)
+            .map_err(|e| RelayError::BadRequest(format!("Invalid request body: {e}")))
+    }
+}
+
+/// A JSON response.
+#[derive(Debug, Clone)]
+pub struct ApiResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+impl ApiResponse {
+    /// Renders `err` in the error envelope.
+    pub fn error(err: &RelayError) -> Self {
+        Self {
+            status: err.status(),
+            body: serde_json::json!({
+                "error": { "code": err.code(), "message": err.client_message() }
+            })
+            .to_string()
+            .into_bytes(),
+        }
+    }
+}
+
+/// A successful handler result before it is wrapped in the envelope.
+struct Reply {
+    status: u16,
+    data: serde_json::Value,
+}
+
+impl Reply {
+    fn ok<T: Serialize>(data: T) -> Result<Self> {
+        Self::with_status(200, data)
+    }
+
+    fn created<T: Serialize>(data: T) -> Result<Self> {
+        Self::with_status(201, data)
+    }
+
+    fn empty() -> Result<Self> {
+        Self::with_status(200, serde_json::Value::Null)
+    }
+
+    fn with_status<T: Serialize>(status: u16, data: T) -> Result<Self> {
+        let data = serde_json::to_value(data)
+            .map_err(|e| RelayError::Internal(format!("response serialisation failed: {e}")))?;
+        Ok(Self { status, data })
+    }
+}
+
+// ── Request bodies ──────────────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+struct RegisterRequest {
+    email: String,
+    password: String,
+    identity_uuid: String,
+    device_public_key: String,
+}
+
+#[derive(Deserialize)]
+struct VerifyRequest {
+    device_public_key: String,
+    nonce: String,
+    #[serde(default)]
+    device_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LoginRequest {
+    email: String,
+    password: String,
+    device_public_key: String,
+}
+
+#[derive(Deserialize)]
+struct ResetPasswordRequest {
+    email: String,
+}
+
+#[derive(Deserialize)]
+struct ResetPasswordConfirmRequest {
+    token: String,
+    new_password: String,
+}
+
+#[derive(Deserialize)]
+struct AddDeviceRequest {
+    device_public_key: String,
+}
+
+#[derive(Deserialize)]
+struct MailboxRequest {
+    workspace_id: String,
+}
+
+#[derive(Deserialize)]
+struct UploadBundleRequest {
+    /// JSON-encoded [`BundleHeader`].
+    header: String,
+    /// base64 bundle bytes.
+    payload: String,
+}
+
+#[derive(Deserialize)]
+struct BundleHeader {
+    workspace_id: String,
+    sender_device_key: String,
+    #[serde(default)]
+    recipient_device_keys: Vec<String>,
+    #[serde(default)]
+    recipient_device_ids: Vec<String>,
+    #[serde(default)]
+    mode: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CreateInviteRequest {
+    payload: String,
+    expires_at: String,
+}
+
+// ── Response bodies ─────────────────────────────────────────────────────────
+
+#[derive(Serialize)]
+struct ChallengeBody {
+    encrypted_nonce: String,
+    server_public_key: String,
+}
+
+#[derive(Serialize)]
+struct RegisterBody {
+    account_id: String,
+    challenge: ChallengeBody,
+}
+
+#[derive(Serialize)]
+struct SessionBody {
+    session_token: String,
+    challenge: Option<ChallengeBody>,
+}
+
+#[derive(Serialize)]
+struct AddDeviceBody {
+    challenge: ChallengeBody,
+}
+
+#[derive(Serialize)]
+struct DeviceKeyBody {
+    device_public_key: String,
+    verified: bool,
+    added_at: String,
+}
+
+#[derive(Serialize)]
+struct AccountBody {
+    account_id: String,
+    email: String,
+    identity_uuid: String,
+    device_keys: Vec<DeviceKeyBody>,
+    role: String,
+    storage_used: u64,
+}
+
+#[derive(Serialize)]
+struct RemoteDeviceBody {
+    device_key: String,
+    device_id: Option<String>,
+}
+
+#[derive(Serialize)]
+struct MailboxBody {
+    workspace_id: String,
+    registered_at: String,
+    pending_bundles: u32,
+    storage_used: u64,
+}
+
+#[derive(Serialize, Default)]
+struct SkippedBody {
+    unknown: Vec<String>,
+    unverified: Vec<String>,
+    quota_exceeded: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct UploadBody {
+    routed_to: u32,
+    bundle_ids: Vec<String>,
+    skipped: SkippedBody,
+}
+
+#[derive(Serialize)]
+struct BundleMetaBody {
+    bundle_id: String,
+    workspace_id: String,
+    sender_device_key: String,
+    mode: String,
+    size_bytes: u64,
+    created_at: String,
+}
+
+#[derive(Serialize)]
+struct BundleBody {
+    bundle_id: String,
+    payload: String,
+}
+
+#[derive(Serialize)]
+struct InviteBody {
+    invite_id: String,
+    token: String,
+    url: String,
+    expires_at: String,
+}
+
+#[derive(Serialize)]
+struct InvitePayloadBody {
+    payload: String,
+    expires_at: String,
+}
+
+// ── Api ─────────────────────────────────────────────────────────────────────
+
+/// The relay's HTTP API over a [`Store`].
+pub struct Api {
+    store: Mutex<Store>,
+    config: RelayConfig,
+    /// Base URL used in invite links, without a trailing slash.
+    base_url: String,
+    last_purge: AtomicI64,
+}
+
+impl Api {
+    pub fn new(store: Store, config: RelayConfig, base_url: String) -> Self {
+        Self {
+            store: Mutex::new(store),
+            config,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            last_purge: AtomicI64::new(0),
+        }
+    }
+
+    /// Handles one request and renders the JSON envelope.
+    pub fn handle(&self, req: &ApiRequest) -> ApiResponse {
+        let now = Utc::now();
+        self.purge_if_due(now);
+        match self.route(req, now) {
+            Ok(reply) => ApiResponse {
+                status: reply.status,
+                body: serde_json::json!({ "data": reply.data })
+                    .to_string()
+                    .into_bytes(),
+            },
+            Err(e) => {
+                if e.status() >= 500 {
+                    log::error!("{} {} failed: {e}", req.method, req.path);
+                } else {
+                    log::debug!("{} {} -> {}: {e}", req.method, req.path, e.status());
+                }
+                ApiResponse::error(&e)
+            }
+        }
+    }
+
+    fn route(&self, req: &ApiRequest, now: DateTime<Utc>) -> Result<Reply> {
+        let segments: Vec<&str> = req.path.split('/').filter(|s| !s.is_empty()).collect();
+        match (req.method.as_str(), segments.as_slice()) {
+            ("GET", ["health"]) => Reply::ok(serde_json::json!({
+                "status": "ok",
+                "version": env!("CARGO_PKG_VERSION"),
+            })),
+            ("POST", ["auth", "register"]) => self.register(req.json()?, now),
+            ("POST", ["auth", "register", "verify"]) => self.register_verify(req.json()?, now),
+            ("POST", ["auth", "login"]) => self.login(req.json()?, now),
+            ("POST", ["auth", "logout"]) => self.logout(req),
+            ("POST", ["auth", "reset-password"]) => self.reset_password(req.json()?, now),
+            ("POST", ["auth", "reset-password", "confirm"]) => {
+                self.reset_password_confirm(req.json()?, now)
+            }
+            ("GET", ["account"]) => self.get_account(req, now),
+            ("GET", ["account", "devices"]) => self.list_devices(req, now),
+            ("POST", ["account", "devices"]) => self.add_device(req, now),
+            ("POST", ["account", "devices", "verify"]) => self.verify_device(req, now),
+            ("DELETE", ["account", "devices", key]) => self.remove_device(req, key, now),
+            ("GET", ["mailboxes"]) => self.list_mailboxes(req, now),
+            ("POST", ["mailboxes"]) => self.ensure_mailbox(req, now),
+            ("GET", ["bundles"]) => self.list_bundles(req, now),
+            ("POST", ["bundles"]) => self.upload_bundle(req, now),
+            ("GET", ["bundles", id]) => self.download_bundle(req, id, now),
+            ("DELETE", ["bundles", id]) => self.delete_bundle(req, id, now),
+            ("GET", ["invites"]) => self.list_invites(req, now),
+            ("POST", ["invites"]) => self.create_invite(req, now),
+            ("GET", ["invites", token]) => self.fetch_invite(token, now),
+            ("DELETE", ["invites", token]) => self.delete_invite(req, token, now),
+            (method, path) if is_known_path(path) => Err(RelayError::MethodNotAllowed(format!(
+                "{method} is not supported on {}",
+                req.path
+            ))),
+            _ => Err(RelayError::NotFound(format!(
+                "No such endpoint: {}",
+                req.path
+            ))),
+        }
+    }
+
+    // ── Helpers ──────────────────────────────────────────────────────────────
+
+    /// Runs `f` inside one SQLite transaction, committing on success.
+    fn tx<T>(&self, f: impl FnOnce(&Store) -> Result<T>) -> Result<T> {
+        let store = self
+            .store
+            .lock()
+            .map_err(|_| RelayError::Internal("store mutex poisoned".to_string()))?;
+        store.begin()?;
+        match f(&store) {
+            Ok(value) => {
+                store.commit()?;
+                Ok(value)
+            }
+            Err(e) => {
+                store.rollback()?;
+                Err(e)
+            }
+        }
+    }
+
+    fn purge_if_due(&self, now: DateTime<Utc>) {
+        let now = now.timestamp();
+        let last = self.last_purge.load(Ordering::Relaxed);
+        if now - last < PURGE_INTERVAL_SECS
+            || self
+                .last_purge
+                .compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed)
+                .is_err()
+        {
+            return;
+        }
+        let cutoff = now - self.config.bundle_ttl_secs;
+        match self.tx(|s| s.purge_expired(now, cutoff)) {
+            Ok(0) => {}
+            Ok(n) => log::info!("purged {n} expired rows"),
+            Err(e) => log::error!("purge failed: {e}"),
+        }
+    }
+
+    /// The account behind the request's bearer token. The session's device
+    /// key must have passed its proof-of-possession challenge.
+    fn authenticate(&self, store: &Store, req: &ApiRequest, now: DateTime<Utc>) -> Result<String> {
+        let (account_id, device_key) = self.session(store, req, now)?;
+        if !store
+            .device_key(&device_key)?
+            .is_some_and(|k| k.verified && k.account_id == account_id)
+        {
+            return Err(RelayError::Unauthorized(
+                "Session device key is not verified".to_string(),
+            ));
+        }
+        Ok(account_id)
+    }
+
+    /// The account and device key behind the request's bearer token, whether
+    /// or not the device key is verified yet.
+    fn session(
+        &self,
+        store: &Store,
+        req: &ApiRequest,
+        now: DateTime<Utc>,
+    ) -> Result<(String, String)> {
+        let token = req
+            .bearer
+            .as_deref()
+            .ok_or_else(|| RelayError::Unauthorized("Missing session token".to_string()))?;
+        store
+            .session(&crypto::token_digest(token), now.timestamp())?
+            .ok_or_else(|| RelayError::Unauthorized("Session expired or invalid".to_string()))
+    }
+
+    fn open_session(
+        &self,
+        store: &Store,
+        account_id: &str,
+        device_key: &str,
+        now: DateTime<Utc>,
+    ) -> Result<String> {
+        let token = crypto::random_token();
+        store.insert_session(
+            &crypto::token_digest(&token),
+            account_id,
+            device_key,
+            now.timestamp() + self.config.session_ttl_secs,
+        )?;
+        Ok(token)
+    }
+
+    fn issue_challenge(
+        &self,
+        store: &Store,
+        device_key: &str,
+        now: DateTime<Utc>,
+    ) -> Result<ChallengeBody> {
+        let challenge = crypto::issue_challenge(device_key)?;
+        store.put_challenge(
+            device_key,
+            &challenge.nonce_hex,
+            now.timestamp() + self.config.challenge_ttl_secs,
+        )?;
+        Ok(ChallengeBody {
+            encrypted_nonce: challenge.encrypted_nonce,
+            server_public_key: challenge.server_public_key,
+        })
+    }
+
+    /// Consumes the outstanding challenge for `device_key` and checks the answer.
+    ///
+    /// Runs in its own transaction so a wrong answer still burns the challenge.
+    fn check_challenge(&self, device_key: &str, nonce_hex: &str, now: DateTime<Utc>) -> Result<()> {
+        let taken = self.tx(|store| store.take_challenge(device_key))?;
+        let (expected, expires_at) = taken.ok_or_else(|| {
+            RelayError::Unauthorized("No outstanding challenge for this device key".to_string())
+        })?;
+        if expires_at <= now.timestamp() {
+            return Err(RelayError::Unauthorized("Challenge expired".to_string()));
+        }
+        if !expected.eq_ignore_ascii_case(nonce_hex) {
+            return Err(RelayError::Unauthorized(
+                "Challenge response does not match".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn check_password(&self, password: &str) -> Result<()> {
+        if password.chars().count() < self.config.min_password_len {
+            return Err(RelayError::BadRequest(format!(
+                "Password must be at least {} characters",
+                self.config.min_password_len
+            )));
+        }
+        Ok(())
+    }
+
+    // ── Auth ─────────────────────────────────────────────────────────────────
+
+    fn register(&self, body: RegisterRequest, now: DateTime<Utc>) -> Result<Reply> {
+        let email = body.email.trim();
+        if !email.contains('@') {
+            return Err(RelayError::BadRequest("Invalid email address".to_string()));
+        }
+        if body.identity_uuid.trim().is_empty() {
+            return Err(RelayError::BadRequest(
+                "identity_uuid is required".to_string(),
+            ));
+        }
+        self.check_password(&body.password)?;
+        let device_key = crypto::normalize_device_key(&body.device_public_key)?;
+        // Hash before taking the store lock — Argon2 is deliberately slow.
+        let password_hash = crypto::hash_password(&body.password)?;
+
+        self.tx(|store| {
+            if store.device_key(&device_key)?.is_some_and(|k| k.verified) {
+                return Err(RelayError::Conflict {
+                    code: "KEY_EXISTS",
+                    message: "Device key is already registered".to_string(),
+                });
+            }
+            if let Some(existing) = store.account_by_email(email)? {
+                if store.has_verified_key(&existing.account_id)? {
+                    return Err(RelayError::Conflict {
+                        code: "EMAIL_EXISTS",
+                        message: "An account with this email already exists".to_string(),
+                    });
+                }
+                // Registration was started but never verified. It holds the
+                // email until its challenge would have expired, so nobody
+                // else can replace the password meanwhile.
+                let pending_until = store
+                    .account_created_at(&existing.account_id)?
+                    .and_then(|t| DateTime::parse_from_rfc3339(&t).ok())
+                    .map_or(0, |t| t.timestamp() + self.config.challenge_ttl_secs);
+                if pending_until > now.timestamp() {
+                    return Err(RelayError::Conflict {
+                        code: "EMAIL_PENDING",
+                        message: "A registration for this email is awaiting verification"
+                            .to_string(),
+                    });
+                }
+                store.delete_account(&existing.account_id)?;
+            }
+
+            let account = AccountRow {
+                account_id: uuid::Uuid::new_v4().to_string(),
+                email: email.to_string(),
+                password_hash,
+                identity_uuid: body.identity_uuid.clone(),
+                role: "user".to_string(),
+            };
+            let stamp = rfc3339(now);
+            store.insert_account(&account, &stamp)?;
+            store.put_unverified_key(&device_key, &account.account_id, &stamp)?;
+            let challenge = self.issue_challenge(store, &device_key, now)?;
+            log::info!(
+                "registered account {} (pending verification)",
+                account.account_id
+            );
+            Reply::created(RegisterBody {
+                account_id: account.account_id,
+                challenge,
+            })
+        })
+    }
+
+    fn register_verify(&self, body: VerifyRequest, now: DateTime<Utc>) -> Result<Reply> {
+        let device_key = crypto::normalize_device_key(&body.device_public_key)?;
+        self.check_challenge(&device_key, &body.nonce, now)?;
+        self.tx(|store| {
+            let key = store.device_key(&device_key)?.ok_or_else(|| {
+                RelayError::Unauthorized("Device key is not registered".to_string())
+            })?;
+            store.mark_key_verified(&device_key, body.device_id.as_deref())?;
+            let session_token = self.open_session(store, &key.account_id, &device_key, now)?;
+            Reply::ok(SessionBody {
+                session_token,
+                challenge: None,
+            })
+        })
+    }
+
+    fn login(&self, body: LoginRequest, now: DateTime<Utc>) -> Result<Reply> {
+        let invalid = || RelayError::Unauthorized("Invalid email or password".to_string());
+        let device_key = crypto::normalize_device_key(&body.device_public_key)?;
+        let account = self
+            .tx(|store| store.account_by_email(body.email.trim()))?
+            .ok_or_else(invalid)?;
+        if !crypto::verify_password(&body.password, &account.password_hash) {
+            return Err(invalid());
+        }
+
+        self.tx(|store| {
+            if store.is_key_revoked(&device_key)? {
+                return Err(RelayError::Forbidden("Device key was revoked".to_string()));
+            }
+            if !store.has_verified_key(&account.account_id)? {
+                return Err(RelayError::Forbidden(
+                    "Account registration was never verified".to_string(),
+                ));
+            }
+            let challenge = match store.device_key(&device_key)? {
+                Some(key) if key.verified && key.account_id == account.account_id => None,
+                Some(key) if key.verified => {
+                    return Err(RelayError::Conflict {
+                        code: "KEY_EXISTS",
+                        message: "Device key belongs to another account".to_string(),
+                    });
+                }
+                _ => {
+                    store.put_unverified_key(&device_key, &account.account_id, &rfc3339(now))?;
+                    Some(self.issue_challenge(store, &device_key, now)?)
+                }
+            };
+            // For an unverified key the session only lets the device answer
+            // its challenge (see `verify_device`).
+            let session_token = self.open_session(store, &account.account_id, &device_key, now)?;
+            Reply::ok(SessionBody {
+                session_token,
+                challenge,
+            })
+        })
+    }
+
+    fn logout(&self, req: &ApiRequest) -> Result<Reply> {
+        let token = req
+            .bearer
+            .as_deref()
+            .ok_or_else(|| RelayError::Unauthorized("Missing session token".to_string()))?;
+        self.tx(|store| store.delete_session(&crypto::token_digest(token)))?;
+        Reply::empty()
+    }
+
+    /// Issues a reset token. The reference server has no mail transport, so
+    /// the token is written to the server log for the operator to pass on.
+    /// The response is the same whether or not the account exists.
+    fn reset_password(&self, body: ResetPasswordRequest, now: DateTime<Utc>) -> Result<Reply> {
+        let email = body.email.trim();
+        self.tx(|store| {
+            if let Some(account) = store.account_by_email(email)? {
+                let token = crypto::random_token();
+                store.insert_password_reset(
+                    &crypto::token_digest(&token),
+                    &account.account_id,
+                    now.timestamp() + self.config.reset_token_ttl_secs,
+                )?;
+                log::warn!("password reset requested for {email}; reset token: {token}");
+            }
+            Ok(())
+        })?;
+        Reply::empty()
+    }
+
+    fn reset_password_confirm(
+        &self,
+        body: ResetPasswordConfirmRequest,
+        now: DateTime<Utc>,
+    ) -> Result<Reply> {
+        self.check_password(&body.new_password)?;
+        let password_hash = crypto::hash_password(&body.new_password)?;
+        self.tx(|store| {
+            let (account_id, expires_at) = store
+                .take_password_reset(&crypto::token_digest(&body.token))?
+                .ok_or_else(|| RelayError::NotFound("Unknown reset token".to_string()))?;
+            if expires_at <= now.timestamp() {
+                return Err(RelayError::Gone("Reset token expired".to_string()));
+            }
+            store.set_password_hash(&account_id, &password_hash)?;
+            store.delete_sessions_for(&account_id)
+        })?;
+        Reply::empty()
+    }
+
+    // ── Account & devices ────────────────────────────────────────────────────
+
+    fn get_account(&self, req: &ApiRequest, now: DateTime<Utc>) -> Result<Reply> {
+        self.tx(|store| {
+            let account_id = self.authenticate(store, req, now)?;
+            let account = store
+                .account_by_id(&account_id)?
+                .ok_or_else(|| RelayError::Unauthorized("Account no longer exists".to_string()))?;
+            let device_keys = store
+                .device_keys_for(&account_id)?
+                .into_iter()
+                .map(|k| DeviceKeyBody {
+                    device_public_key: k.device_key,
+                    verified: k.verified,
+                    added_at: k.added_at,
+                })
+                .collect();
+            Reply::ok(AccountBody {
+                account_id: account.account_id,
+                email: account.email,
+                identity_uuid: account.identity_uuid,
+                device_keys,
+                role: account.role,
+                storage_used: store.storage_used(&account_id)?,
+            })
+        })
+    }
+
+    fn list_devices(&self, req: &ApiRequest, now: DateTime<Utc>) -> Result<Reply> {
+        let exclude = req
+            .query_param("exclude_key")
+            .and_then(|k| crypto::normalize_device_key(k).ok());
+        self.tx(|store| {
+            let account_id = self.authenticate(store, req, now)?;
+            let devices: Vec<_> = store
+                .device_keys_for(&account_id)?
+                .into_iter()
+                .filter(|k| k.verified && exclude.as_deref() != Some(k.device_key.as_str()))
+                .map(|k| RemoteDeviceBody {
+                    device_key: k.device_key,
+                    device_id: k.device_id,
+                })
+                .collect();
+            Reply::ok(devices)
+        })
+    }
+
+    fn add_device(&self, req: &ApiRequest, now: DateTime<Utc>) -> Result<Reply> {
+        let body: AddDeviceRequest = req.json()?;
+        let device_key = crypto::normalize_device_key(&body.device_public_key)?;
+        self.tx(|store| {
+            let account_id = self.authenticate(store, req, now)?;
+            if store.is_key_revoked(&device_key)? {
+                return Err(RelayError::Forbidden("Device key was revoked".to_string()));
+            }
+            if store.device_key(&device_key)?.is_some_and(|k| k.verified) {
+                return Err(RelayError::Conflict {
+                    code: "KEY_EXISTS",
+                    message: "Device key is already registered".to_string(),
+                });
+            }
+            store.put_unverified_key(&device_key, &account_id, &rfc3339(now))?;
+            let challenge = self.issue_challenge(store, &device_key, now)?;
+            Reply::created(AddDeviceBody { challenge })
+        })
+    }
+
+    fn verify_device(&self, req: &ApiRequest, now: DateTime<Utc>) -> Result<Reply> {
+        let body: VerifyRequest = req.json()?;
+        let device_key = crypto::normalize_device_key(&body.device_public_key)?;
+        let check_owner = |store: &Store| {
+            // A session opened for a key still being verified may only
+            // verify that key.
+            let (account_id, session_key) = self.session(store, req, now)?;
+            if session_key != device_key {
+                self.authenticate(store, req, now)?;
+            }
+            if store
+                .device_key(&device_key)?
+                .is_some_and(|k| k.account_id == account_id)
+            {
+                Ok(())
+            } else {
+                Err(RelayError::NotFound(
+                    "Device key is not registered on this account".to_string(),
+                ))
+            }
+        };
+        self.tx(check_owner)?;
+        self.check_challenge(&device_key, &body.nonce, now)?;
+        // Re-check ownership: the key may have been taken over meanwhile.
+        self.tx(|store| {
+            check_owner(store)?;
+            store.mark_key_verified(&device_key, body.device_id.as_deref())
+        })?;
+        Reply::empty()
+    }
+
+    /// Revokes one of the account's device keys. Sessions are per account,
+    /// so every session but the caller's is ended to log the device out.
+    fn remove_device(&self, req: &ApiRequest, key: &str, now: DateTime<Utc>) -> Result<Reply> {
+        let device_key = crypto::normalize_device_key(key)?;
+        self.tx(|store| {
+            let account_id = self.authenticate(store, req, now)?;
+            if store
+                .device_key(&device_key)?
+                .is_none_or(|k| k.account_id != account_id)
+            {
+                return Err(RelayError::NotFound(
+                    "Device key is not registered on this account".to_string(),
+                ));
+            }
+            store.revoke_device_key(&device_key, &account_id, &rfc3339(now))?;
+            let token = req.bearer.as_deref().unwrap_or_default();
+            store.delete_other_sessions(&account_id, &crypto::token_digest(token))
+        })?;
+        Reply::empty()
+    }
+
+    // ── Mailboxes ────────────────────────────────────────────────────────────
+
+    fn list_mailboxes(&self, req: &ApiRequest, now: DateTime<Utc>) -> Result<Reply> {
+        self.tx(|store| {
+            let account_id = self.authenticate(store, req, now)?;
+            let mailboxes: Vec<_> = store
+                .list_mailboxes(&account_id)?
+                .into_iter()
+                .map(|m| MailboxBody {
+                    workspace_id: m.workspace_id,
+                    registered_at: m.registered_at,
+                    pending_bundles: m.pending_bundles,
+                    storage_used: m.storage_used,
+                })
+                .collect();
+            Reply::ok(mailboxes)
+        })
+    }
+
+    fn ensure_mailbox(&self, req: &ApiRequest, now: DateTime<Utc>) -> Result<Reply> {
+        let body: MailboxRequest = req.json()?;
+        if body.workspace_id.trim().is_empty() {
+            return Err(RelayError::BadRequest(
+                "workspace_id is required".to_string(),
+            ));
+        }
+        self.tx(|store| {
+            let account_id = self.authenticate(store, req, now)?;
+            let created = store.ensure_mailbox(&account_id, &body.workspace_id, &rfc3339(now))?;
+            let mailbox = store
+                .list_mailboxes(&account_id)?
+                .into_iter()
+                .find(|m| m.workspace_id == body.workspace_id)
+                .ok_or_else(|| RelayError::Internal("mailbox vanished".to_string()))?;
+            Reply::with_status(
+                if created { 201 } else { 200 },
+                MailboxBody {
+                    workspace_id: mailbox.workspace_id,
+                    registered_at: mailbox.registered_at,
+                    pending_bundles: mailbox.pending_bundles,
+                    storage_used: mailbox.storage_used,
+                },
+            )
+        })
+    }
+
+    // ── Bundles ──────────────────────────────────────────────────────────────
+
+    /// Stores one copy of the bundle per verified recipient key. Recipients
+    /// that are unknown, unverified or over quota are reported, not failed.
+    ///
+    /// The sender key must be a verified key of the caller's account: the
+    /// session's device key or another key it proved possession of, such as
+    /// the identity key.
+    fn upload_bundle(&self, req: &ApiRequest, now: DateTime<Utc>) -> Result<Reply> {
+        let body: UploadBundleRequest = req.json()?;
+        let header: BundleHeader = serde_json::from_str(&body.header)
+            .map_err(|e| RelayError::BadRequest(format!("Invalid bundle header: {e}")))?;
+        let payload = BASE64
+            .decode(&body.payload)
+            .map_err(|e| RelayError::BadRequest(format!("Invalid bundle payload: {e}")))?;
+        if payload.len() as u64 > self.config.max_bundle_bytes {
+            return Err(RelayError::PayloadTooLarge(format!(
+                "Bundle exceeds {} bytes",
+                self.config.max_bundle_bytes
+            )));
+        }
+        let mode = header.mode.as_deref().unwrap_or("delta");
+
+        let sender_key = crypto::normalize_device_key(&header.sender_device_key)?;
+
+        self.tx(|store| {
+            let account_id = self.authenticate(store, req, now)?;
+            if !store
+                .device_key(&sender_key)?
+                .is_some_and(|k| k.verified && k.account_id == account_id)
+            {
+                return Err(RelayError::Forbidden(
+                    "Sender device key does not belong to this account".to_string(),
+                ));
+            }
+            let stamp = rfc3339(now);
+            let mut seen = HashSet::new();
+            let mut bundle_ids = Vec::new();
+            let mut skipped = SkippedBody::default();
+            for (i, raw_key) in header.recipient_device_keys.iter().enumerate() {
+                let Ok(device_key) = crypto::normalize_device_key(raw_key) else {
+                    skipped.unknown.push(raw_key.clone());
+                    continue;
+                };
+                if !seen.insert(device_key.clone()) {
+                    continue;
+                }
+                let Some(recipient) = store.device_key(&device_key)? else {
+                    skipped.unknown.push(raw_key.clone());
+                    continue;
+                };
+                if !recipient.verified {
+                    skipped.unverified.push(raw_key.clone());
+                    continue;
+                }
+                let used = store.storage_used(&recipient.account_id)?;
+                if used + payload.len() as u64 > self.config.account_quota_bytes {
+                    skipped.quota_exceeded.push(raw_key.clone());
+                    continue;
+                }
+                let bundle_id = uuid::Uuid::new_v4().to_string();
+                store.insert_bundle(&NewBundle {
+                    bundle_id: &bundle_id,
+                    account_id: &recipient.account_id,
+                    recipient_device_key: &device_key,
+                    recipient_device_id: header
+                        .recipient_device_ids
+                        .get(i)
+                        .map(String::as_str)
+                        .filter(|id| !id.is_empty()),
+                    workspace_id: &header.workspace_id,
+                    sender_device_key: &sender_key,
+                    mode,
+                    payload: &payload,
+                    created_at: &stamp,
+                    created_unix: now.timestamp(),
+                })?;
+                bundle_ids.push(bundle_id);
+            }
+            log::debug!(
+                "bundle for workspace {} routed to {} recipient(s)",
+                header.workspace_id,
+                bundle_ids.len()
+            );
+            Reply::created(UploadBody {
+                routed_to: bundle_ids.len() as u32,
+                bundle_ids,
+                skipped,
+            })
+        })
+    }
+
+    /// Lists pending bundles. With `?device_id=`, bundles addressed to a
+    /// *different* known device of the same account are left out; bundles
+    /// without a device id, or with one no device has claimed, are always
+    /// listed so nothing is stranded.
+    fn list_bundles(&self, req: &ApiRequest, now: DateTime<Utc>) -> Result<Reply> {
+        let device_id = req.query_param("device_id").filter(|d| !d.is_empty());
+        self.tx(|store| {
+            let account_id = self.authenticate(store, req, now)?;
+            let own_ids: HashSet<String> = store
+                .device_keys_for(&account_id)?
+                .into_iter()
+                .filter_map(|k| k.device_id)
+                .collect();
+            let bundles: Vec<_> = store
+                .list_bundles(&account_id)?
+                .into_iter()
+                .filter(|b| match (device_id, b.recipient_device_id.as_deref()) {
+                    (Some(me), Some(target)) => target == me || !own_ids.contains(target),
+                    _ => true,
+                })
+                .map(|b| BundleMetaBody {
+                    bundle_id: b.bundle_id,
+                    workspace_id: b.workspace_id,
+                    sender_device_key: b.sender_device_key,
+                    mode: b.mode,
+                    size_bytes: b.size_bytes,
+                    created_at: b.created_at,
+                })
+                .collect();
+            Reply::ok(bundles)
+        })
+    }
+
+    fn download_bundle(
+        &self,
+        req: &ApiRequest,
+        bundle_id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Reply> {
+        self.tx(|store| {
+            let account_id = self.authenticate(store, req, now)?;
+            let payload = store
+                .bundle_payload(&account_id, bundle_id)?
+                .ok_or_else(|| RelayError::NotFound(format!("No such bundle: {bundle_id}")))?;
+            Reply::ok(BundleBody {
+                bundle_id: bundle_id.to_string(),
+                payload: BASE64.encode(payload),
+            })
+        })
+    }
+
+    fn delete_bundle(
+        &self,
+        req: &ApiRequest,
+        bundle_id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Reply> {
+        self.tx(|store| {
+            let account_id = self.authenticate(store, req, now)?;
+            if !store.delete_bundle(&account_id, bundle_id)? {
+                return Err(RelayError::NotFound(format!("No such bundle: {bundle_id}")));
+            }
+            Ok(())
+        })?;
+        Reply::empty()
+    }
+
+    // ── Invites ──────────────────────────────────────────────────────────────
+
+    fn invite_body(&self, invite: InviteRow) -> InviteBody {
+        InviteBody {
+            url: format!("{}/invites/{}", self.base_url, invite.token),
+            invite_id: invite.invite_id,
+            token: invite.token,
+            expires_at: invite.expires_at,
+        }
+    }
+
+    fn create_invite(&self, req: &ApiRequest, now: DateTime<Utc>) -> Result<Reply> {
+        let body: CreateInviteRequest = req.json()?;
+        let decoded = BASE64
+            .decode(&body.payload)
+            .map_err(|e| RelayError::BadRequest(format!("Invalid invite payload: {e}")))?;
+        if decoded.len() as u64 > self.config.max_invite_bytes {
+            return Err(RelayError::PayloadTooLarge(format!(
+                "Invite exceeds {} bytes",
+                self.config.max_invite_bytes
+            )));
+        }
+        let expires = DateTime::parse_from_rfc3339(&body.expires_at)
+            .map_err(|e| RelayError::BadRequest(format!("Invalid expires_at: {e}")))?
+            .with_timezone(&Utc);
+        if expires <= now {
+            return Err(RelayError::BadRequest(
+                "expires_at must be in the future".to_string(),
+            ));
+        }
+
+        self.tx(|store| {
+            let account_id = self.authenticate(store, req, now)?;
+            let invite = InviteRow {
+                invite_id: uuid::Uuid::new_v4().to_string(),
+                token: crypto::random_token(),
+                account_id,
+                payload: body.payload.clone(),
+                expires_at: rfc3339(expires),
+                expires_unix: expires.timestamp(),
+            };
+            store.insert_invite(&invite, &rfc3339(now))?;
+            Reply::created(self.invite_body(invite))
+        })
+    }
+
+    fn list_invites(&self, req: &ApiRequest, now: DateTime<Utc>) -> Result<Reply> {
+        self.tx(|store| {
+            let account_id = self.authenticate(store, req, now)?;
+            let invites: Vec<_> = store
+                .list_invites(&account_id, now.timestamp())?
+                .into_iter()
+                .map(|i| self.invite_body(i))
+                .collect();
+            Reply::ok(invites)
+        })
+    }
+
+    /// Public: anyone holding the token may fetch the payload.
+    fn fetch_invite(&self, token: &str, now: DateTime<Utc>) -> Result<Reply> {
+        let invite = self
+            .tx(|store| store.invite_by_token(token))?
+            .ok_or_else(|| RelayError::NotFound("No such invite".to_string()))?;
+        if invite.expires_unix <= now.timestamp() {
+            return Err(RelayError::Gone("Invite has expired".to_string()));
+        }
+        Reply::ok(InvitePayloadBody {
+            payload: invite.payload,
+            expires_at: invite.expires_at,
+        })
+    }
+
+    fn delete_invite(&self, req: &ApiRequest, token: &str, now: DateTime<Utc>) -> Result<Reply> {
+        self.tx(|store| {
+            let account_id = self.authenticate(store, req, now)?;
+            if store
+                .invite_by_token(token)?
+                .is_none_or(|i| i.account_id != account_id)
+            {
+                return Err(RelayError::NotFound("No such invite".to_string()));
+            }
+            store.delete_invite(token)
+        })?;
+        Reply::empty()
+    }
+}
+
+fn is_known_path(segments: &[&str]) -> bool {
+    matches!(
+        segments,
+        ["health"]
+            | ["auth", "register" | "login" | "logout" | "reset-password"]
+            | ["auth", "register" | "reset-password", "verify" | "confirm"]
+            | ["account"]
+            | ["account", "devices"]
+            | ["account", "devices", _]
+            | ["mailboxes"]
+            | ["bundles"]
+            | ["bundles", _]
+            | ["invites"]
+            | ["invites", _]
+    )
+}
+
+fn rfc3339(t: DateTime<Utc>) -> String {
+    t.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+            (
+                percent_decode(&k.replace('+', " ")),
+                percent_decode(&v.replace('+', " ")),
+            )
+        })
+        .collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hi = (bytes[i + 1] as char).to_digit(16);
+            let lo = (bytes[i + 2] as char).to_digit(16);
+            if let (Some(hi), Some(lo)) = (hi, lo) {
+                out.push((hi * 16 + lo) as u8);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+#[path = "api_tests.rs"]
+mod tests;
@@ -0,0 +1,594 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+use super::*;
+use crate::crypto::solve_challenge;
+use ed25519_dalek::SigningKey;
+use serde_json::{json, Value};
+
+fn api() -> Api {
+    Api::new(
+        Store::open(None).unwrap(),
+        RelayConfig::ephemeral(),
+        "https://relay.test/".to_string(),
+    )
+}
+
+fn call(api: &Api, method: &str, url: &str, token: Option<&str>, body: Value) -> (u16, Value) {
+    let mut req = ApiRequest::new(method, url);
+    req.bearer = token.map(str::to_string);
+    if !body.is_null() {
+        req.body = body.to_string().into_bytes();
+    }
+    let resp = api.handle(&req);
+    (resp.status, serde_json::from_slice(&resp.body).unwrap())
+}
+
+fn hex_key(key: &SigningKey) -> String {
+    hex::encode(key.verifying_key().to_bytes())
+}
+
+fn answer(key: &SigningKey, challenge: &Value) -> String {
+    solve_challenge(
+        key,
+        challenge["encrypted_nonce"].as_str().unwrap(),
+        challenge["server_public_key"].as_str().unwrap(),
+    )
+}
+
+/// Registers and verifies an account; returns its session token.
+fn register(api: &Api, key: &SigningKey, email: &str, device_id: &str) -> String {
+    let (status, reg) = call(
+        api,
+        "POST",
+        "/auth/register",
+        None,
+        json!({
+            "email": email,
+            "password": "password-123",
+            "identity_uuid": format!("uuid-{email}"),
+            "device_public_key": hex_key(key),
+        }),
+    );
+    assert_eq!(status, 201, "{reg}");
+    let (status, session) = call(
+        api,
+        "POST",
+        "/auth/register/verify",
+        None,
+        json!({
+            "device_public_key": hex_key(key),
+            "nonce": answer(key, &reg["data"]["challenge"]),
+            "device_id": device_id,
+        }),
+    );
+    assert_eq!(status, 200, "{session}");
+    session["data"]["session_token"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn test_register_verify_and_account() {
+    let api = api();
+    let key = SigningKey::from_bytes(&[1u8; 32]);
+    let token = register(&api, &key, "alice@example.com", "dev-a");
+
+    let (status, account) = call(&api, "GET", "/account", Some(&token), Value::Null);
+    assert_eq!(status, 200);
+    let data = &account["data"];
+    assert_eq!(data["email"], "alice@example.com");
+    assert_eq!(data["identity_uuid"], "uuid-alice@example.com");
+    assert_eq!(data["device_keys"][0]["device_public_key"], hex_key(&key));
+    assert_eq!(data["device_keys"][0]["verified"], true);
+
+    // The email is taken once verified.
+    let other = SigningKey::from_bytes(&[2u8; 32]);
+    let (status, body) = call(
+        &api,
+        "POST",
+        "/auth/register",
+        None,
+        json!({
+            "email": "ALICE@example.com",
+            "password": "password-123",
+            "identity_uuid": "x",
+            "device_public_key": hex_key(&other),
+        }),
+    );
+    assert_eq!(status, 409);
+    assert_eq!(body["error"]["code"], "EMAIL_EXISTS");
+
+    // Logout invalidates the session.
+    assert_eq!(
+        call(&api, "POST", "/auth/logout", Some(&token), Value::Null).0,
+        200
+    );
+    let (status, body) = call(&api, "GET", "/account", Some(&token), Value::Null);
+    assert_eq!(status, 401);
+    assert_eq!(body["error"]["code"], "UNAUTHORIZED");
+}
+
+#[test]
+fn test_wrong_nonce_burns_challenge() {
+    let api = api();
+    let key = SigningKey::from_bytes(&[1u8; 32]);
+    let (_, reg) = call(
+        &api,
+        "POST",
+        "/auth/register",
+        None,
+        json!({
+            "email": "a@example.com",
+            "password": "password-123",
+            "identity_uuid": "u",
+            "device_public_key": hex_key(&key),
+        }),
+    );
+    let nonce = answer(&key, &reg["data"]["challenge"]);
+    let verify = |nonce: &str| {
+        call(
+            &api,
+            "POST",
+            "/auth/register/verify",
+            None,
+            json!({ "device_public_key": hex_key(&key), "nonce": nonce }),
+        )
+        .0
+    };
+    assert_eq!(verify(&"00".repeat(32)), 401);
+    // The right answer no longer works: challenges are single-use.
+    assert_eq!(verify(&nonce), 401);
+}
+
+#[test]
+fn test_pending_registration_holds_email_until_expiry() {
+    let mut config = RelayConfig::ephemeral();
+    config.challenge_ttl_secs = 0;
+    let expired = Api::new(Store::open(None).unwrap(), config, "http://x".to_string());
+    let owner = SigningKey::from_bytes(&[1u8; 32]);
+    let intruder = SigningKey::from_bytes(&[2u8; 32]);
+    let start = |api: &Api, key: &SigningKey| {
+        call(
+            api,
+            "POST",
+            "/auth/register",
+            None,
+            json!({
+                "email": "a@example.com",
+                "password": "password-123",
+                "identity_uuid": "u",
+                "device_public_key": hex_key(key),
+            }),
+        )
+    };
+
+    let api = api();
+    let (_, reg) = start(&api, &owner);
+    let (status, body) = start(&api, &intruder);
+    assert_eq!(status, 409);
+    assert_eq!(body["error"]["code"], "EMAIL_PENDING");
+    // The original registration is untouched and can still be verified.
+    let (status, _) = call(
+        &api,
+        "POST",
+        "/auth/register/verify",
+        None,
+        json!({
+            "device_public_key": hex_key(&owner),
+            "nonce": answer(&owner, &reg["data"]["challenge"]),
+        }),
+    );
+    assert_eq!(status, 200);
+
+    // Once the challenge lifetime has passed, the email is free again.
+    start(&expired, &owner);
+    assert_eq!(start(&expired, &intruder).0, 201);
+}
+
+#[test]
+fn test_login_from_new_device_and_device_list() {
+    let api = api();
+    let first = SigningKey::from_bytes(&[1u8; 32]);
+    register(&api, &first, "a@example.com", "dev-1");
+
+    let (status, body) = call(
+        &api,
+        "POST",
+        "/auth/login",
+        None,
+        json!({ "email": "a@example.com", "password": "wrong-password", "device_public_key": hex_key(&first) }),
+    );
+    assert_eq!(status, 401, "{body}");
+
+    let second = SigningKey::from_bytes(&[2u8; 32]);
+    let (status, session) = call(
+        &api,
+        "POST",
+        "/auth/login",
+        None,
+        json!({ "email": "a@example.com", "password": "password-123", "device_public_key": hex_key(&second) }),
+    );
+    assert_eq!(status, 200);
+    let token = session["data"]["session_token"].as_str().unwrap();
+    let challenge = &session["data"]["challenge"];
+    assert!(challenge.is_object(), "unknown device must be challenged");
+
+    // Until the device answers, the session authenticates nothing else.
+    let (status, _) = call(&api, "GET", "/account", Some(token), Value::Null);
+    assert_eq!(status, 401);
+    let third = SigningKey::from_bytes(&[3u8; 32]);
+    let (status, _) = call(
+        &api,
+        "POST",
+        "/account/devices",
+        Some(token),
+        json!({ "device_public_key": hex_key(&third) }),
+    );
+    assert_eq!(status, 401);
+
+    let (status, _) = call(
+        &api,
+        "POST",
+        "/account/devices/verify",
+        Some(token),
+        json!({
+            "device_public_key": hex_key(&second),
+            "nonce": answer(&second, challenge),
+            "device_id": "dev-2",
+        }),
+    );
+    assert_eq!(status, 200);
+
+    let url = format!("/account/devices?exclude_key={}", hex_key(&second));
+    let (_, devices) = call(&api, "GET", &url, Some(token), Value::Null);
+    assert_eq!(
+        devices["data"],
+        json!([{ "device_key": hex_key(&first), "device_id": "dev-1" }])
+    );
+
+    // A key verified elsewhere cannot be added again.
+    let (status, body) = call(
+        &api,
+        "POST",
+        "/account/devices",
+        Some(token),
+        json!({ "device_public_key": hex_key(&first) }),
+    );
+    assert_eq!(status, 409);
+    assert_eq!(body["error"]["code"], "KEY_EXISTS");
+}
+
+#[test]
+fn test_removed_device_is_logged_out_and_blocked() {
+    let api = api();
+    let first = SigningKey::from_bytes(&[1u8; 32]);
+    let token = register(&api, &first, "a@example.com", "dev-1");
+    let second = SigningKey::from_bytes(&[2u8; 32]);
+    let login = json!({ "email": "a@example.com", "password": "password-123", "device_public_key": hex_key(&second) });
+    let (_, session) = call(&api, "POST", "/auth/login", None, login.clone());
+    let stolen = session["data"]["session_token"].as_str().unwrap();
+    call(
+        &api,
+        "POST",
+        "/account/devices/verify",
+        Some(stolen),
+        json!({
+            "device_public_key": hex_key(&second),
+            "nonce": answer(&second, &session["data"]["challenge"]),
+            "device_id": "dev-2",
+        }),
+    );
+
+    let url = format!("/account/devices/{}", hex_key(&second));
+    assert_eq!(call(&api, "DELETE", &url, Some(&token), Value::Null).0, 200);
+    assert_eq!(call(&api, "DELETE", &url, Some(&token), Value::Null).0, 404);
+
+    // The caller stays logged in; the other session ends.
+    let (_, devices) = call(&api, "GET", "/account/devices", Some(&token), Value::Null);
+    assert_eq!(
+        devices["data"],
+        json!([{ "device_key": hex_key(&first), "device_id": "dev-1" }])
+    );
+    let (status, _) = call(&api, "GET", "/account", Some(stolen), Value::Null);
+    assert_eq!(status, 401);
+
+    // The key can neither log in nor be added back.
+    let (status, _) = call(&api, "POST", "/auth/login", None, login);
+    assert_eq!(status, 403);
+    let (status, _) = call(
+        &api,
+        "POST",
+        "/account/devices",
+        Some(&token),
+        json!({ "device_public_key": hex_key(&second) }),
+    );
+    assert_eq!(status, 403);
+}
+
+#[test]
+fn test_bundle_routing_and_ownership() {
+    let api = api();
+    let alice_key = SigningKey::from_bytes(&[1u8; 32]);
+    let bob_key = SigningKey::from_bytes(&[2u8; 32]);
+    let alice = register(&api, &alice_key, "alice@example.com", "dev-a");
+    let bob = register(&api, &bob_key, "bob@example.com", "dev-b");
+    let stranger = hex_key(&SigningKey::from_bytes(&[3u8; 32]));
+
+    let (status, _) = call(
+        &api,
+        "POST",
+        "/mailboxes",
+        Some(&alice),
+        json!({ "workspace_id": "ws-1" }),
+    );
+    assert_eq!(status, 201);
+    let (status, _) = call(
+        &api,
+        "POST",
+        "/mailboxes",
+        Some(&alice),
+        json!({ "workspace_id": "ws-1" }),
+    );
+    assert_eq!(status, 200);
+
+    // Bob is addressed by his base64 key, as peer records store it.
+    let bob_b64 = BASE64.encode(bob_key.verifying_key().to_bytes());
+    let header = json!({
+        "workspace_id": "ws-1",
+        "sender_device_key": hex_key(&alice_key),
+        "sender_device_id": "dev-a",
+        "recipient_device_keys": [bob_b64, stranger, "garbage"],
+        "recipient_device_ids": ["dev-b", "", ""],
+    });
+    let (status, upload) = call(
+        &api,
+        "POST",
+        "/bundles",
+        Some(&alice),
+        json!({ "header": header.to_string(), "payload": BASE64.encode(b"bundle-bytes") }),
+    );
+    assert_eq!(status, 201, "{upload}");
+    assert_eq!(upload["data"]["routed_to"], 1);
+    assert_eq!(
+        upload["data"]["skipped"]["unknown"],
+        json!([stranger, "garbage"])
+    );
+    let bundle_id = upload["data"]["bundle_ids"][0].as_str().unwrap();
+
+    let (_, listed) = call(
+        &api,
+        "GET",
+        "/bundles?device_id=dev-b",
+        Some(&bob),
+        Value::Null,
+    );
+    assert_eq!(listed["data"][0]["bundle_id"], bundle_id);
+    assert_eq!(listed["data"][0]["mode"], "delta");
+    assert_eq!(listed["data"][0]["size_bytes"], 12);
+
+    // Alice cannot read what she routed to Bob.
+    let url = format!("/bundles/{bundle_id}");
+    assert_eq!(call(&api, "GET", &url, Some(&alice), Value::Null).0, 404);
+
+    let (status, downloaded) = call(&api, "GET", &url, Some(&bob), Value::Null);
+    assert_eq!(status, 200);
+    let payload = BASE64
+        .decode(downloaded["data"]["payload"].as_str().unwrap())
+        .unwrap();
+    assert_eq!(payload, b"bundle-bytes");
+
+    assert_eq!(call(&api, "DELETE", &url, Some(&bob), Value::Null).0, 200);
+    assert_eq!(call(&api, "DELETE", &url, Some(&bob), Value::Null).0, 404);
+
+    // Alice cannot send in Bob's name, nor from a key she never verified.
+    for sender in [hex_key(&bob_key), stranger] {
+        let header = json!({
+            "workspace_id": "ws-1",
+            "sender_device_key": sender,
+            "recipient_device_keys": [bob_b64],
+        });
+        let (status, _) = call(
+            &api,
+            "POST",
+            "/bundles",
+            Some(&alice),
+            json!({ "header": header.to_string(), "payload": "AA==" }),
+        );
+        assert_eq!(status, 403);
+    }
+}
+
+#[test]
+fn test_bundle_list_filters_other_own_devices() {
+    let api = api();
+    let phone = SigningKey::from_bytes(&[1u8; 32]);
+    let laptop = SigningKey::from_bytes(&[2u8; 32]);
+    let token = register(&api, &phone, "a@example.com", "dev-phone");
+    let (_, added) = call(
+        &api,
+        "POST",
+        "/account/devices",
+        Some(&token),
+        json!({ "device_public_key": hex_key(&laptop) }),
+    );
+    call(
+        &api,
+        "POST",
+        "/account/devices/verify",
+        Some(&token),
+        json!({
+            "device_public_key": hex_key(&laptop),
+            "nonce": answer(&laptop, &added["data"]["challenge"]),
+            "device_id": "dev-laptop",
+        }),
+    );
+
+    let upload = |recipient: &SigningKey, device_id: &str| {
+        let header = json!({
+            "workspace_id": "ws-1",
+            "sender_device_key": hex_key(&phone),
+            "recipient_device_keys": [hex_key(recipient)],
+            "recipient_device_ids": [device_id],
+        });
+        call(
+            &api,
+            "POST",
+            "/bundles",
+            Some(&token),
+            json!({ "header": header.to_string(), "payload": "AA==" }),
+        )
+        .1["data"]["bundle_ids"][0]
+            .as_str()
+            .unwrap()
+            .to_string()
+    };
+    let for_phone = upload(&phone, "dev-phone");
+    let for_laptop = upload(&laptop, "dev-laptop");
+    let unclaimed = upload(&phone, "dev-retired");
+
+    let (_, listed) = call(
+        &api,
+        "GET",
+        "/bundles?device_id=dev-phone",
+        Some(&token),
+        Value::Null,
+    );
+    let ids: Vec<_> = listed["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|b| b["bundle_id"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(ids, vec![for_phone, unclaimed]);
+    assert!(!ids.contains(&for_laptop));
+}
+
+#[test]
+fn test_quota_exceeded_is_reported() {
+    let mut config = RelayConfig::ephemeral();
+    config.account_quota_bytes = 10;
+    let api = Api::new(Store::open(None).unwrap(), config, "http://x".to_string());
+    let key = SigningKey::from_bytes(&[1u8; 32]);
+    let token = register(&api, &key, "a@example.com", "dev");
+    let header = json!({
+        "workspace_id": "ws-1",
+        "sender_device_key": hex_key(&key),
+        "recipient_device_keys": [hex_key(&key)],
+    });
+    let (status, upload) = call(
+        &api,
+        "POST",
+        "/bundles",
+        Some(&token),
+        json!({ "header": header.to_string(), "payload": BASE64.encode([0u8; 11]) }),
+    );
+    assert_eq!(status, 201);
+    assert_eq!(upload["data"]["routed_to"], 0);
+    assert_eq!(
+        upload["data"]["skipped"]["quota_exceeded"],
+        json!([hex_key(&key)])
+    );
+}
+
+#[test]
+fn test_invite_lifecycle() {
+    let api = api();
+    let alice = register(
+        &api,
+        &SigningKey::from_bytes(&[1u8; 32]),
+        "a@example.com",
+        "d",
+    );
+    let bob = register(
+        &api,
+        &SigningKey::from_bytes(&[2u8; 32]),
+        "b@example.com",
+        "d",
+    );
+    let expires = rfc3339(Utc::now() + chrono::Duration::days(1));
+
+    let (status, created) = call(
+        &api,
+        "POST",
+        "/invites",
+        Some(&alice),
+        json!({ "payload": BASE64.encode(b"invite"), "expires_at": expires }),
+    );
+    assert_eq!(status, 201);
+    let token = created["data"]["token"].as_str().unwrap();
+    assert_eq!(
+        created["data"]["url"],
+        format!("https://relay.test/invites/{token}")
+    );
+
+    // Fetching needs no session.
+    let url = format!("/invites/{token}");
+    let (status, fetched) = call(&api, "GET", &url, None, Value::Null);
+    assert_eq!(status, 200);
+    assert_eq!(fetched["data"]["payload"], BASE64.encode(b"invite"));
+
+    let (_, listed) = call(&api, "GET", "/invites", Some(&alice), Value::Null);
+    assert_eq!(listed["data"].as_array().unwrap().len(), 1);
+
+    assert_eq!(call(&api, "DELETE", &url, Some(&bob), Value::Null).0, 404);
+    assert_eq!(call(&api, "DELETE", &url, Some(&alice), Value::Null).0, 200);
+    assert_eq!(call(&api, "GET", &url, None, Value::Null).0, 404);
+
+    let past = rfc3339(Utc::now() - chrono::Duration::minutes(1));
+    let (status, _) = call(
+        &api,
+        "POST",
+        "/invites",
+        Some(&alice),
+        json!({ "payload": "AA==", "expires_at": past }),
+    );
+    assert_eq!(status, 400);
+}
+
+#[test]
+fn test_routing_errors() {
+    let api = api();
+    let (status, body) = call(&api, "GET", "/nope", None, Value::Null);
+    assert_eq!(status, 404);
+    assert_eq!(body["error"]["code"], "NOT_FOUND");
+    assert_eq!(call(&api, "PUT", "/bundles", None, Value::Null).0, 405);
+    assert_eq!(call(&api, "GET", "/bundles", None, Value::Null).0, 401);
+    let (status, body) = call(&api, "POST", "/auth/login", None, json!({ "email": 1 }));
+    assert_eq!(status, 400);
+    assert_eq!(body["error"]["code"], "BAD_REQUEST");
+    assert_eq!(call(&api, "GET", "/health", None, Value::Null).0, 200);
+}
+
+#[test]
+fn test_unknown_reset_token() {
+    let api = api();
+    let (status, _) = call(
+        &api,
+        "POST",
+        "/auth/reset-password",
+        None,
+        json!({ "email": "nobody@example.com" }),
+    );
+    assert_eq!(status, 200);
+    let (status, _) = call(
+        &api,
+        "POST",
+        "/auth/reset-password/confirm",
+        None,
+        json!({ "token": "bogus", "new_password": "new-password-1" }),
+    );
+    assert_eq!(status, 404);
+}
+
+#[test]
+fn test_query_decoding() {
+    let req = ApiRequest::new("get", "/bundles?device_id=abc%3Aidentity%3Auuid&x=a+b");
+    assert_eq!(req.method, "GET");
+    assert_eq!(req.query_param("device_id"), Some("abc:identity:uuid"));
+    assert_eq!(req.query_param("x"), Some("a b"));
+}
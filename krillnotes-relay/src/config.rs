@@ -0,0 +1,69 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Relay server configuration.
+
+use std::path::PathBuf;
+
+/// Settings for a [`RelayServer`](crate::RelayServer).
+#[derive(Debug, Clone)]
+pub struct RelayConfig {
+    /// Address to listen on, e.g. `0.0.0.0:8080`. Port 0 picks a free port.
+    pub bind_addr: String,
+    /// SQLite database file. `None` keeps all state in memory.
+    pub database_path: Option<PathBuf>,
+    /// Externally visible base URL, used in invite links. Defaults to
+    /// `http://<bound address>`.
+    pub public_url: Option<String>,
+    /// Number of request worker threads.
+    pub workers: usize,
+    /// Largest accepted bundle, in bytes (before base64 encoding).
+    pub max_bundle_bytes: u64,
+    /// Total bytes of pending bundles an account may hold.
+    pub account_quota_bytes: u64,
+    /// Largest accepted invite payload, in bytes.
+    pub max_invite_bytes: u64,
+    /// Minimum password length for registration and resets.
+    pub min_password_len: usize,
+    /// Session token lifetime, in seconds.
+    pub session_ttl_secs: i64,
+    /// Proof-of-possession challenge lifetime, in seconds.
+    pub challenge_ttl_secs: i64,
+    /// Password reset token lifetime, in seconds.
+    pub reset_token_ttl_secs: i64,
+    /// Undelivered bundles older than this are dropped, in seconds.
+    pub bundle_ttl_secs: i64,
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        Self {
+            bind_addr: "127.0.0.1:8080".to_string(),
+            database_path: None,
+            public_url: None,
+            workers: 4,
+            max_bundle_bytes: 64 * 1024 * 1024,
+            account_quota_bytes: 1024 * 1024 * 1024,
+            max_invite_bytes: 1024 * 1024,
+            min_password_len: 8,
+            session_ttl_secs: 30 * 24 * 3600,
+            challenge_ttl_secs: 10 * 60,
+            reset_token_ttl_secs: 3600,
+            bundle_ttl_secs: 30 * 24 * 3600,
+        }
+    }
+}
+
+impl RelayConfig {
+    /// An in-memory relay on a free loopback port — for tests.
+    pub fn ephemeral() -> Self {
+        Self {
+            bind_addr: "127.0.0.1:0".to_string(),
+            workers: 2,
+            ..Self::default()
+        }
+    }
+}
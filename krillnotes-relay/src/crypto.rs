@@ -0,0 +1,175 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Device key handling, proof-of-possession challenges, password hashing and
+//! opaque tokens.
+
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use crypto_box::aead::{Aead, AeadCore};
+use crypto_box::{PublicKey, SalsaBox, SecretKey};
+use ed25519_dalek::VerifyingKey;
+use rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+
+use crate::error::{RelayError, Result};
+
+/// Normalises a device public key to lowercase hex.
+///
+/// Clients send Ed25519 public keys either hex-encoded (the desktop app) or
+/// base64-encoded (identity keys as stored in peer records); both name the
+/// same device.
+pub fn normalize_device_key(key: &str) -> Result<String> {
+    let bytes = if key.len() == 64 && key.bytes().all(|b| b.is_ascii_hexdigit()) {
+        hex::decode(key).ok()
+    } else {
+        BASE64.decode(key).ok()
+    };
+    let bytes: [u8; 32] = bytes
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| RelayError::BadRequest(format!("Invalid device public key: {key}")))?;
+    VerifyingKey::from_bytes(&bytes)
+        .map_err(|_| RelayError::BadRequest(format!("Invalid device public key: {key}")))?;
+    Ok(hex::encode(bytes))
+}
+
+/// A proof-of-possession challenge for one device key.
+pub struct Challenge {
+    /// Hex-encoded plaintext nonce the client must echo back.
+    pub nonce_hex: String,
+    /// Hex-encoded 24-byte box nonce followed by the ciphertext.
+    pub encrypted_nonce: String,
+    /// Hex-encoded ephemeral X25519 public key of the server.
+    pub server_public_key: String,
+}
+
+/// Encrypts a fresh random nonce to the X25519 form of `device_key_hex`.
+///
+/// Uses NaCl `crypto_box` (X25519 + XSalsa20-Poly1305) with an ephemeral
+/// server key, matching `decrypt_pop_challenge` on the client. Only the
+/// holder of the Ed25519 secret key can recover the nonce.
+pub fn issue_challenge(device_key_hex: &str) -> Result<Challenge> {
+    let bytes: [u8; 32] = hex::decode(device_key_hex)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| RelayError::BadRequest("Invalid device public key".to_string()))?;
+    let verifying_key = VerifyingKey::from_bytes(&bytes)
+        .map_err(|_| RelayError::BadRequest("Invalid device public key".to_string()))?;
+    let client_pk = PublicKey::from(verifying_key.to_montgomery().to_bytes());
+
+    let server_sk = SecretKey::generate(&mut OsRng);
+    let server_pk = server_sk.public_key();
+
+    let mut plaintext = [0u8; 32];
+    OsRng.fill_bytes(&mut plaintext);
+
+    let salsa_box = SalsaBox::new(&client_pk, &server_sk);
+    let nonce = SalsaBox::generate_nonce(&mut OsRng);
+    let ciphertext = salsa_box
+        .encrypt(&nonce, &plaintext[..])
+        .map_err(|e| RelayError::Internal(format!("challenge encryption failed: {e}")))?;
+
+    let mut encrypted = nonce.to_vec();
+    encrypted.extend_from_slice(&ciphertext);
+    Ok(Challenge {
+        nonce_hex: hex::encode(plaintext),
+        encrypted_nonce: hex::encode(encrypted),
+        server_public_key: hex::encode(server_pk.as_bytes()),
+    })
+}
+
+/// Hashes a password into a PHC string (Argon2id, random salt).
+pub fn hash_password(password: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|h| h.to_string())
+        .map_err(|e| RelayError::Internal(format!("password hashing failed: {e}")))
+}
+
+/// Checks `password` against a PHC string produced by [`hash_password`].
+pub fn verify_password(password: &str, phc: &str) -> bool {
+    PasswordHash::new(phc)
+        .map(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+/// A fresh 256-bit random token, hex-encoded.
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// The digest under which a bearer token is stored, so a leaked database
+/// does not hand out live sessions.
+pub fn token_digest(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Client side of a challenge, as `decrypt_pop_challenge` in
+/// `krillnotes-core` does it. Returns the hex nonce to send back.
+#[cfg(test)]
+pub(crate) fn solve_challenge(
+    key: &ed25519_dalek::SigningKey,
+    encrypted_nonce: &str,
+    server_public_key: &str,
+) -> String {
+    let hash = sha2::Sha512::digest(key.to_bytes());
+    let mut scalar = [0u8; 32];
+    scalar.copy_from_slice(&hash[..32]);
+    let client_sk = SecretKey::from(scalar);
+    let server_pk: [u8; 32] = hex::decode(server_public_key).unwrap().try_into().unwrap();
+    let encrypted = hex::decode(encrypted_nonce).unwrap();
+    let (nonce, ciphertext) = encrypted.split_at(24);
+    let plaintext = SalsaBox::new(&PublicKey::from(server_pk), &client_sk)
+        .decrypt(crypto_box::Nonce::from_slice(nonce), ciphertext)
+        .unwrap();
+    hex::encode(plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::SigningKey;
+
+    #[test]
+    fn test_normalize_accepts_hex_and_base64() {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let raw = key.verifying_key().to_bytes();
+        let from_hex = normalize_device_key(&hex::encode(raw)).unwrap();
+        let from_b64 = normalize_device_key(&BASE64.encode(raw)).unwrap();
+        assert_eq!(from_hex, from_b64);
+        assert_eq!(from_hex, hex::encode(raw));
+        assert!(normalize_device_key("not-a-key").is_err());
+    }
+
+    #[test]
+    fn test_challenge_roundtrip() {
+        let key = SigningKey::from_bytes(&[3u8; 32]);
+        let device = hex::encode(key.verifying_key().to_bytes());
+        let challenge = issue_challenge(&device).unwrap();
+        let answer = solve_challenge(
+            &key,
+            &challenge.encrypted_nonce,
+            &challenge.server_public_key,
+        );
+        assert_eq!(answer, challenge.nonce_hex);
+    }
+
+    #[test]
+    fn test_password_hash_roundtrip() {
+        let phc = hash_password("correct horse").unwrap();
+        assert!(verify_password("correct horse", &phc));
+        assert!(!verify_password("wrong horse", &phc));
+        assert!(!verify_password("correct horse", "not a phc string"));
+    }
+}
@@ -0,0 +1,89 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Error type for the relay server.
+//!
+//! Every variant maps onto an HTTP status and a machine-readable code, and is
+//! returned to clients as `{"error":{"code":"...","message":"..."}}` — the
+//! envelope `RelayClient::map_error` in `krillnotes-core` unpacks.
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum RelayError {
+    #[error("{0}")]
+    BadRequest(String),
+
+    #[error("{0}")]
+    Unauthorized(String),
+
+    #[error("{0}")]
+    Forbidden(String),
+
+    #[error("{0}")]
+    NotFound(String),
+
+    #[error("{0}")]
+    MethodNotAllowed(String),
+
+    /// Conflicts carry their own code (`EMAIL_EXISTS`, `KEY_EXISTS`, ...).
+    #[error("{message}")]
+    Conflict { code: &'static str, message: String },
+
+    #[error("{0}")]
+    Gone(String),
+
+    #[error("{0}")]
+    PayloadTooLarge(String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] rusqlite::Error),
+
+    #[error("Internal error: {0}")]
+    Internal(String),
+}
+
+impl RelayError {
+    /// HTTP status code sent for this error.
+    pub fn status(&self) -> u16 {
+        match self {
+            Self::BadRequest(_) => 400,
+            Self::Unauthorized(_) => 401,
+            Self::Forbidden(_) => 403,
+            Self::NotFound(_) => 404,
+            Self::MethodNotAllowed(_) => 405,
+            Self::Conflict { .. } => 409,
+            Self::Gone(_) => 410,
+            Self::PayloadTooLarge(_) => 413,
+            Self::Database(_) | Self::Internal(_) => 500,
+        }
+    }
+
+    /// Machine-readable error code sent alongside the message.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::BadRequest(_) => "BAD_REQUEST",
+            Self::Unauthorized(_) => "UNAUTHORIZED",
+            Self::Forbidden(_) => "FORBIDDEN",
+            Self::NotFound(_) => "NOT_FOUND",
+            Self::MethodNotAllowed(_) => "METHOD_NOT_ALLOWED",
+            Self::Conflict { code, .. } => code,
+            Self::Gone(_) => "GONE",
+            Self::PayloadTooLarge(_) => "PAYLOAD_TOO_LARGE",
+            Self::Database(_) | Self::Internal(_) => "INTERNAL",
+        }
+    }
+
+    /// Message safe to show to clients. Internal details stay in the log.
+    pub fn client_message(&self) -> String {
+        match self {
+            Self::Database(_) | Self::Internal(_) => "Internal server error".to_string(),
+            other => other.to_string(),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, RelayError>;
@@ -0,0 +1,34 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! Reference relay server for Krillnotes sync.
+//!
+//! Implements the HTTP API `RelayClient` in `krillnotes-core` speaks —
+//! registration with proof-of-possession, login sessions, device keys,
+//! mailboxes, bundle store-and-forward and hosted invites — on top of SQLite.
+//! Relays only ever see encrypted `.swarm` bundles and opaque invite payloads.
+//!
+//! Run it as a binary (`krillnotes-relay --help`) to self-host, or start an
+//! in-process instance for tests:
+//!
+//! ```no_run
+//! use krillnotes_relay::{RelayConfig, RelayServer};
+//!
+//! let relay = RelayServer::bind(RelayConfig::ephemeral()).unwrap().spawn();
+//! println!("relay at {}", relay.url());
+//! ```
+
+mod api;
+mod config;
+mod crypto;
+mod error;
+mod server;
+mod store;
+
+pub use api::{Api, ApiRequest, ApiResponse};
+pub use config::RelayConfig;
+pub use error::RelayError;
+pub use server::{RelayHandle, RelayServer};
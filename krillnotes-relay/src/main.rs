@@ -0,0 +1,105 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! `krillnotes-relay` — run the reference relay server.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use krillnotes_relay::{RelayConfig, RelayServer};
+
+const USAGE: &str = "\
+Usage: krillnotes-relay [OPTIONS]
+
+Options:
+  --bind <ADDR>          Address to listen on [default: 127.0.0.1:8080]
+  --db <PATH>            SQLite database file [default: in memory]
+  --public-url <URL>     Base URL used in invite links [default: http://<bind>]
+  --workers <N>          Request worker threads [default: 4]
+  --max-bundle-mb <N>    Largest accepted bundle in MiB [default: 64]
+  --quota-mb <N>         Pending-bundle quota per account in MiB [default: 1024]
+  --log-level <LEVEL>    error, warn, info, debug or trace [default: info]
+  -h, --help             Print this help";
+
+/// Minimal stderr logger; the server has no other logging dependency.
+struct StderrLogger;
+
+impl log::Log for StderrLogger {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        if self.enabled(record.metadata()) {
+            eprintln!(
+                "{} {:<5} {}",
+                chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                record.level(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+fn parse_args() -> Result<(RelayConfig, log::LevelFilter), String> {
+    let mut config = RelayConfig::default();
+    let mut level = log::LevelFilter::Info;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            println!("{USAGE}");
+            std::process::exit(0);
+        }
+        let value = args.next().ok_or_else(|| format!("{arg} needs a value"))?;
+        let number = |v: &str| {
+            v.parse::<u64>()
+                .map_err(|_| format!("{arg}: expected a number, got {v}"))
+        };
+        match arg.as_str() {
+            "--bind" => config.bind_addr = value,
+            "--db" => config.database_path = Some(PathBuf::from(value)),
+            "--public-url" => config.public_url = Some(value),
+            "--workers" => config.workers = number(&value)? as usize,
+            "--max-bundle-mb" => config.max_bundle_bytes = number(&value)? * 1024 * 1024,
+            "--quota-mb" => config.account_quota_bytes = number(&value)? * 1024 * 1024,
+            "--log-level" => {
+                level = value
+                    .parse()
+                    .map_err(|_| format!("--log-level: unknown level {value}"))?
+            }
+            other => return Err(format!("unknown option {other}")),
+        }
+    }
+    Ok((config, level))
+}
+
+fn main() -> ExitCode {
+    let (config, level) = match parse_args() {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    log::set_logger(&StderrLogger).expect("logger already set");
+    log::set_max_level(level);
+
+    if config.database_path.is_none() {
+        log::warn!("no --db given: all relay state is kept in memory");
+    }
+    match RelayServer::bind(config) {
+        Ok(server) => {
+            server.run();
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            log::error!("failed to start relay: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,86 @@
+-- Krillnotes relay server schema.
+-- Device keys are stored hex-encoded; expiries are Unix seconds, display
+-- timestamps RFC 3339.
+
+CREATE TABLE IF NOT EXISTS accounts (
+    account_id TEXT PRIMARY KEY,
+    email TEXT NOT NULL UNIQUE COLLATE NOCASE,
+    password_hash TEXT NOT NULL,
+    identity_uuid TEXT NOT NULL,
+    role TEXT NOT NULL DEFAULT 'user',
+    created_at TEXT NOT NULL
+);
+
+-- A key is usable for routing only once its holder has answered a
+-- proof-of-possession challenge (verified = 1).
+CREATE TABLE IF NOT EXISTS device_keys (
+    device_key TEXT PRIMARY KEY,
+    account_id TEXT NOT NULL,
+    device_id TEXT,
+    verified INTEGER NOT NULL DEFAULT 0,
+    added_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_device_keys_account ON device_keys(account_id);
+
+-- Keys removed from an account; they can never be registered again.
+CREATE TABLE IF NOT EXISTS revoked_device_keys (
+    device_key TEXT PRIMARY KEY,
+    account_id TEXT NOT NULL,
+    revoked_at TEXT NOT NULL
+);
+
+-- Outstanding PoP challenge per device key (plaintext nonce, hex).
+CREATE TABLE IF NOT EXISTS challenges (
+    device_key TEXT PRIMARY KEY,
+    nonce TEXT NOT NULL,
+    expires_at INTEGER NOT NULL
+);
+
+-- Session and password-reset tokens are stored as SHA-256 digests. A session
+-- is bound to the device key it was opened for and only authenticates once
+-- that key is verified.
+CREATE TABLE IF NOT EXISTS sessions (
+    token_hash TEXT PRIMARY KEY,
+    account_id TEXT NOT NULL,
+    device_key TEXT NOT NULL,
+    expires_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS password_resets (
+    token_hash TEXT PRIMARY KEY,
+    account_id TEXT NOT NULL,
+    expires_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS mailboxes (
+    account_id TEXT NOT NULL,
+    workspace_id TEXT NOT NULL,
+    registered_at TEXT NOT NULL,
+    PRIMARY KEY (account_id, workspace_id)
+);
+
+-- One row per routed recipient; account_id is the recipient's account.
+CREATE TABLE IF NOT EXISTS bundles (
+    bundle_id TEXT PRIMARY KEY,
+    account_id TEXT NOT NULL,
+    recipient_device_key TEXT NOT NULL,
+    recipient_device_id TEXT,
+    workspace_id TEXT NOT NULL,
+    sender_device_key TEXT NOT NULL,
+    mode TEXT NOT NULL,
+    payload BLOB NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    created_at TEXT NOT NULL,
+    created_unix INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_bundles_account ON bundles(account_id);
+
+CREATE TABLE IF NOT EXISTS invites (
+    invite_id TEXT PRIMARY KEY,
+    token TEXT NOT NULL UNIQUE,
+    account_id TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+    expires_unix INTEGER NOT NULL,
+    created_at TEXT NOT NULL
+);
@@ -0,0 +1,170 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! HTTP transport: binds a socket and feeds requests to [`Api`] from a small
+//! pool of worker threads.
+
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use crate::api::{Api, ApiRequest, ApiResponse};
+use crate::config::RelayConfig;
+use crate::error::{RelayError, Result};
+use crate::store::Store;
+
+/// Slack on top of the base64-inflated bundle size for the JSON envelope.
+const BODY_OVERHEAD_BYTES: u64 = 64 * 1024;
+
+/// A bound, not yet running relay server.
+pub struct RelayServer {
+    http: Arc<tiny_http::Server>,
+    api: Arc<Api>,
+    url: String,
+    workers: usize,
+    max_body_bytes: u64,
+}
+
+impl RelayServer {
+    /// Opens the database and binds the listening socket.
+    pub fn bind(config: RelayConfig) -> Result<Self> {
+        let store = Store::open(config.database_path.as_deref())?;
+        let http = tiny_http::Server::http(&config.bind_addr)
+            .map_err(|e| RelayError::Internal(format!("bind {}: {e}", config.bind_addr)))?;
+        let addr = http.server_addr().to_ip().ok_or_else(|| {
+            RelayError::Internal(format!("{} is not a TCP address", config.bind_addr))
+        })?;
+        let url = format!("http://{addr}");
+        let base_url = config.public_url.clone().unwrap_or_else(|| url.clone());
+        let workers = config.workers.max(1);
+        let max_body_bytes = config.max_bundle_bytes.div_ceil(3) * 4 + BODY_OVERHEAD_BYTES;
+        Ok(Self {
+            http: Arc::new(http),
+            api: Arc::new(Api::new(store, config, base_url)),
+            url,
+            workers,
+            max_body_bytes,
+        })
+    }
+
+    /// The URL the server is listening on, e.g. `http://127.0.0.1:8080`.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Starts the worker threads and returns a handle that stops them on drop.
+    pub fn spawn(self) -> RelayHandle {
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let threads = (0..self.workers)
+            .map(|i| {
+                let http = Arc::clone(&self.http);
+                let api = Arc::clone(&self.api);
+                let shutdown = Arc::clone(&shutdown);
+                let max_body_bytes = self.max_body_bytes;
+                std::thread::Builder::new()
+                    .name(format!("relay-worker-{i}"))
+                    .spawn(move || worker(&http, &api, &shutdown, max_body_bytes))
+                    .expect("failed to spawn relay worker thread")
+            })
+            .collect();
+        log::info!("relay listening on {}", self.url);
+        RelayHandle {
+            url: self.url,
+            http: self.http,
+            shutdown,
+            threads,
+        }
+    }
+
+    /// Serves requests on the calling thread's behalf until the process exits.
+    pub fn run(self) {
+        let mut handle = self.spawn();
+        for thread in std::mem::take(&mut handle.threads) {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// A running relay server. Dropping it stops the workers.
+pub struct RelayHandle {
+    url: String,
+    http: Arc<tiny_http::Server>,
+    shutdown: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl RelayHandle {
+    /// The URL the server is listening on.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Stops accepting requests and waits for the workers to finish.
+    pub fn shutdown(self) {
+        // Drop does the work.
+    }
+}
+
+impl Drop for RelayHandle {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        for _ in &self.threads {
+            self.http.unblock();
+        }
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn worker(http: &tiny_http::Server, api: &Api, shutdown: &AtomicBool, max_body_bytes: u64) {
+    loop {
+        match http.recv() {
+            Ok(request) => serve(api, request, max_body_bytes),
+            Err(_) if shutdown.load(Ordering::SeqCst) => break,
+            Err(e) => log::warn!("failed to receive request: {e}"),
+        }
+        if shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+    }
+}
+
+fn serve(api: &Api, mut request: tiny_http::Request, max_body_bytes: u64) {
+    let mut req = ApiRequest::new(request.method().as_str(), request.url());
+    req.bearer = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Authorization"))
+        .and_then(|h| h.value.as_str().strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string());
+
+    let read = request
+        .as_reader()
+        .take(max_body_bytes + 1)
+        .read_to_end(&mut req.body);
+    let response = match read {
+        Err(e) => ApiResponse::error(&RelayError::BadRequest(format!(
+            "Failed to read request body: {e}"
+        ))),
+        Ok(_) if req.body.len() as u64 > max_body_bytes => ApiResponse::error(
+            &RelayError::PayloadTooLarge("Request body too large".to_string()),
+        ),
+        Ok(_) => api.handle(&req),
+    };
+    log::debug!("{} {} -> {}", req.method, req.path, response.status);
+
+    let content_type =
+        tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+            .expect("static header is valid");
+    let reply = tiny_http::Response::from_data(response.body)
+        .with_status_code(response.status)
+        .with_header(content_type);
+    if let Err(e) = request.respond(reply) {
+        log::warn!("failed to send response: {e}");
+    }
+}
@@ -0,0 +1,690 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2024-2026 TripleACS Pty Ltd t/a 2pi Software
+
+//! SQLite persistence for accounts, device keys, sessions, mailboxes,
+//! bundles and invites.
+
+use std::path::Path;
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::error::Result;
+
+const SCHEMA: &str = include_str!("schema.sql");
+
+/// A relay account.
+#[derive(Debug, Clone)]
+pub struct AccountRow {
+    pub account_id: String,
+    pub email: String,
+    pub password_hash: String,
+    pub identity_uuid: String,
+    pub role: String,
+}
+
+/// A device public key registered to an account.
+#[derive(Debug, Clone)]
+pub struct DeviceKeyRow {
+    pub device_key: String,
+    pub account_id: String,
+    pub device_id: Option<String>,
+    pub verified: bool,
+    pub added_at: String,
+}
+
+/// Metadata of a stored bundle (payload excluded).
+#[derive(Debug, Clone)]
+pub struct BundleRow {
+    pub bundle_id: String,
+    pub recipient_device_id: Option<String>,
+    pub workspace_id: String,
+    pub sender_device_key: String,
+    pub mode: String,
+    pub size_bytes: u64,
+    pub created_at: String,
+}
+
+/// A new bundle addressed to one recipient device.
+pub struct NewBundle<'a> {
+    pub bundle_id: &'a str,
+    pub account_id: &'a str,
+    pub recipient_device_key: &'a str,
+    pub recipient_device_id: Option<&'a str>,
+    pub workspace_id: &'a str,
+    pub sender_device_key: &'a str,
+    pub mode: &'a str,
+    pub payload: &'a [u8],
+    pub created_at: &'a str,
+    pub created_unix: i64,
+}
+
+/// A mailbox with its pending bundle statistics.
+#[derive(Debug, Clone)]
+pub struct MailboxRow {
+    pub workspace_id: String,
+    pub registered_at: String,
+    pub pending_bundles: u32,
+    pub storage_used: u64,
+}
+
+/// A hosted invite.
+#[derive(Debug, Clone)]
+pub struct InviteRow {
+    pub invite_id: String,
+    pub token: String,
+    pub account_id: String,
+    pub payload: String,
+    pub expires_at: String,
+    pub expires_unix: i64,
+}
+
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    /// Opens (creating if needed) the relay database. `None` keeps it in memory.
+    pub fn open(path: Option<&Path>) -> Result<Self> {
+        let conn = match path {
+            Some(path) => Connection::open(path)?,
+            None => Connection::open_in_memory()?,
+        };
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    pub fn begin(&self) -> Result<()> {
+        self.conn.execute_batch("BEGIN IMMEDIATE")?;
+        Ok(())
+    }
+
+    pub fn commit(&self) -> Result<()> {
+        self.conn.execute_batch("COMMIT")?;
+        Ok(())
+    }
+
+    pub fn rollback(&self) -> Result<()> {
+        self.conn.execute_batch("ROLLBACK")?;
+        Ok(())
+    }
+
+    // ── Accounts ────────────────────────────────────────────────────────────
+
+    fn account_where(&self, clause: &str, value: &str) -> Result<Option<AccountRow>> {
+        let sql = format!(
+            "SELECT account_id, email, password_hash, identity_uuid, role
+             FROM accounts WHERE {clause} = ?1"
+        );
+        Ok(self
+            .conn
+            .query_row(&sql, [value], |row| {
+                Ok(AccountRow {
+                    account_id: row.get(0)?,
+                    email: row.get(1)?,
+                    password_hash: row.get(2)?,
+                    identity_uuid: row.get(3)?,
+                    role: row.get(4)?,
+                })
+            })
+            .optional()?)
+    }
+
+    pub fn account_by_email(&self, email: &str) -> Result<Option<AccountRow>> {
+        self.account_where("email", email)
+    }
+
+    pub fn account_by_id(&self, account_id: &str) -> Result<Option<AccountRow>> {
+        self.account_where("account_id", account_id)
+    }
+
+    pub fn insert_account(&self, account: &AccountRow, created_at: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO accounts (account_id, email, password_hash, identity_uuid, role, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                account.account_id,
+                account.email,
+                account.password_hash,
+                account.identity_uuid,
+                account.role,
+                created_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// When the account was created (RFC 3339).
+    pub fn account_created_at(&self, account_id: &str) -> Result<Option<String>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT created_at FROM accounts WHERE account_id = ?1",
+                [account_id],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    /// Removes an account together with its keys, challenges and sessions.
+    pub fn delete_account(&self, account_id: &str) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM challenges WHERE device_key IN
+                 (SELECT device_key FROM device_keys WHERE account_id = ?1)",
+            [account_id],
+        )?;
+        for table in [
+            "device_keys",
+            "revoked_device_keys",
+            "sessions",
+            "password_resets",
+            "accounts",
+        ] {
+            self.conn.execute(
+                &format!("DELETE FROM {table} WHERE account_id = ?1"),
+                [account_id],
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn set_password_hash(&self, account_id: &str, password_hash: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE accounts SET password_hash = ?1 WHERE account_id = ?2",
+            params![password_hash, account_id],
+        )?;
+        Ok(())
+    }
+
+    // ── Device keys ─────────────────────────────────────────────────────────
+
+    fn map_device_key(row: &rusqlite::Row<'_>) -> rusqlite::Result<DeviceKeyRow> {
+        Ok(DeviceKeyRow {
+            device_key: row.get(0)?,
+            account_id: row.get(1)?,
+            device_id: row.get(2)?,
+            verified: row.get(3)?,
+            added_at: row.get(4)?,
+        })
+    }
+
+    pub fn device_key(&self, device_key: &str) -> Result<Option<DeviceKeyRow>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT device_key, account_id, device_id, verified, added_at
+                 FROM device_keys WHERE device_key = ?1",
+                [device_key],
+                Self::map_device_key,
+            )
+            .optional()?)
+    }
+
+    pub fn device_keys_for(&self, account_id: &str) -> Result<Vec<DeviceKeyRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT device_key, account_id, device_id, verified, added_at
+             FROM device_keys WHERE account_id = ?1 ORDER BY added_at, device_key",
+        )?;
+        let rows = stmt
+            .query_map([account_id], Self::map_device_key)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    pub fn has_verified_key(&self, account_id: &str) -> Result<bool> {
+        Ok(self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM device_keys WHERE account_id = ?1 AND verified = 1)",
+            [account_id],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Records `device_key` as an unverified key of `account_id`, taking it
+    /// over from any other account that never proved possession of it.
+    pub fn put_unverified_key(&self, device_key: &str, account_id: &str, now: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO device_keys (device_key, account_id, device_id, verified, added_at)
+             VALUES (?1, ?2, NULL, 0, ?3)",
+            params![device_key, account_id, now],
+        )?;
+        Ok(())
+    }
+
+    pub fn mark_key_verified(&self, device_key: &str, device_id: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE device_keys SET verified = 1, device_id = COALESCE(?2, device_id)
+             WHERE device_key = ?1",
+            params![device_key, device_id],
+        )?;
+        Ok(())
+    }
+
+    /// Removes `device_key` from its account for good, dropping its
+    /// outstanding challenge, sessions and the bundles waiting for it.
+    pub fn revoke_device_key(&self, device_key: &str, account_id: &str, now: &str) -> Result<()> {
+        for table in ["challenges", "sessions", "device_keys"] {
+            self.conn.execute(
+                &format!("DELETE FROM {table} WHERE device_key = ?1"),
+                [device_key],
+            )?;
+        }
+        self.conn.execute(
+            "DELETE FROM bundles WHERE recipient_device_key = ?1",
+            [device_key],
+        )?;
+        self.conn.execute(
+            "INSERT OR REPLACE INTO revoked_device_keys (device_key, account_id, revoked_at)
+             VALUES (?1, ?2, ?3)",
+            params![device_key, account_id, now],
+        )?;
+        Ok(())
+    }
+
+    pub fn is_key_revoked(&self, device_key: &str) -> Result<bool> {
+        Ok(self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM revoked_device_keys WHERE device_key = ?1)",
+            [device_key],
+            |row| row.get(0),
+        )?)
+    }
+
+    // ── Challenges ──────────────────────────────────────────────────────────
+
+    pub fn put_challenge(&self, device_key: &str, nonce_hex: &str, expires_at: i64) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO challenges (device_key, nonce, expires_at) VALUES (?1, ?2, ?3)",
+            params![device_key, nonce_hex, expires_at],
+        )?;
+        Ok(())
+    }
+
+    /// Removes and returns the outstanding challenge for `device_key`.
+    /// Challenges are single-use whether or not the answer is right.
+    pub fn take_challenge(&self, device_key: &str) -> Result<Option<(String, i64)>> {
+        let row = self
+            .conn
+            .query_row(
+                "SELECT nonce, expires_at FROM challenges WHERE device_key = ?1",
+                [device_key],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        self.conn
+            .execute("DELETE FROM challenges WHERE device_key = ?1", [device_key])?;
+        Ok(row)
+    }
+
+    // ── Sessions and reset tokens ───────────────────────────────────────────
+
+    pub fn insert_session(
+        &self,
+        token_hash: &str,
+        account_id: &str,
+        device_key: &str,
+        expires_at: i64,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO sessions (token_hash, account_id, device_key, expires_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![token_hash, account_id, device_key, expires_at],
+        )?;
+        Ok(())
+    }
+
+    /// The account and device key behind an unexpired session token digest.
+    pub fn session(&self, token_hash: &str, now: i64) -> Result<Option<(String, String)>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT account_id, device_key FROM sessions
+                 WHERE token_hash = ?1 AND expires_at > ?2",
+                params![token_hash, now],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?)
+    }
+
+    pub fn delete_session(&self, token_hash: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM sessions WHERE token_hash = ?1", [token_hash])?;
+        Ok(())
+    }
+
+    pub fn delete_sessions_for(&self, account_id: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM sessions WHERE account_id = ?1", [account_id])?;
+        Ok(())
+    }
+
+    /// Ends every session of `account_id` except the one with `keep_hash`.
+    pub fn delete_other_sessions(&self, account_id: &str, keep_hash: &str) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM sessions WHERE account_id = ?1 AND token_hash != ?2",
+            params![account_id, keep_hash],
+        )?;
+        Ok(())
+    }
+
+    pub fn insert_password_reset(
+        &self,
+        token_hash: &str,
+        account_id: &str,
+        expires_at: i64,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO password_resets (token_hash, account_id, expires_at) VALUES (?1, ?2, ?3)",
+            params![token_hash, account_id, expires_at],
+        )?;
+        Ok(())
+    }
+
+    /// Removes and returns `(account_id, expires_at)` for a reset token digest.
+    pub fn take_password_reset(&self, token_hash: &str) -> Result<Option<(String, i64)>> {
+        let row = self
+            .conn
+            .query_row(
+                "SELECT account_id, expires_at FROM password_resets WHERE token_hash = ?1",
+                [token_hash],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        self.conn.execute(
+            "DELETE FROM password_resets WHERE token_hash = ?1",
+            [token_hash],
+        )?;
+        Ok(row)
+    }
+
+    // ── Mailboxes ───────────────────────────────────────────────────────────
+
+    /// Registers a mailbox; returns `true` if it did not exist before.
+    pub fn ensure_mailbox(&self, account_id: &str, workspace_id: &str, now: &str) -> Result<bool> {
+        let inserted = self.conn.execute(
+            "INSERT OR IGNORE INTO mailboxes (account_id, workspace_id, registered_at)
+             VALUES (?1, ?2, ?3)",
+            params![account_id, workspace_id, now],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    pub fn list_mailboxes(&self, account_id: &str) -> Result<Vec<MailboxRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT m.workspace_id, m.registered_at,
+                    COUNT(b.bundle_id), COALESCE(SUM(b.size_bytes), 0)
+             FROM mailboxes m
+             LEFT JOIN bundles b
+                    ON b.account_id = m.account_id AND b.workspace_id = m.workspace_id
+             WHERE m.account_id = ?1
+             GROUP BY m.workspace_id, m.registered_at
+             ORDER BY m.registered_at, m.workspace_id",
+        )?;
+        let rows = stmt
+            .query_map([account_id], |row| {
+                Ok(MailboxRow {
+                    workspace_id: row.get(0)?,
+                    registered_at: row.get(1)?,
+                    pending_bundles: row.get(2)?,
+                    storage_used: row.get::<_, i64>(3)? as u64,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    // ── Bundles ─────────────────────────────────────────────────────────────
+
+    /// Bytes of pending bundles held for `account_id`.
+    pub fn storage_used(&self, account_id: &str) -> Result<u64> {
+        let used: i64 = self.conn.query_row(
+            "SELECT COALESCE(SUM(size_bytes), 0) FROM bundles WHERE account_id = ?1",
+            [account_id],
+            |row| row.get(0),
+        )?;
+        Ok(used as u64)
+    }
+
+    pub fn insert_bundle(&self, bundle: &NewBundle<'_>) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO bundles (bundle_id, account_id, recipient_device_key, recipient_device_id,
+                                  workspace_id, sender_device_key, mode, payload, size_bytes,
+                                  created_at, created_unix)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                bundle.bundle_id,
+                bundle.account_id,
+                bundle.recipient_device_key,
+                bundle.recipient_device_id,
+                bundle.workspace_id,
+                bundle.sender_device_key,
+                bundle.mode,
+                bundle.payload,
+                bundle.payload.len() as i64,
+                bundle.created_at,
+                bundle.created_unix
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Pending bundles for `account_id`, oldest first.
+    pub fn list_bundles(&self, account_id: &str) -> Result<Vec<BundleRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT bundle_id, recipient_device_id, workspace_id, sender_device_key, mode,
+                    size_bytes, created_at
+             FROM bundles WHERE account_id = ?1 ORDER BY created_unix, rowid",
+        )?;
+        let rows = stmt
+            .query_map([account_id], |row| {
+                Ok(BundleRow {
+                    bundle_id: row.get(0)?,
+                    recipient_device_id: row.get(1)?,
+                    workspace_id: row.get(2)?,
+                    sender_device_key: row.get(3)?,
+                    mode: row.get(4)?,
+                    size_bytes: row.get::<_, i64>(5)? as u64,
+                    created_at: row.get(6)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    pub fn bundle_payload(&self, account_id: &str, bundle_id: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT payload FROM bundles WHERE bundle_id = ?1 AND account_id = ?2",
+                params![bundle_id, account_id],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    /// Deletes a bundle owned by `account_id`; returns `false` if there was none.
+    pub fn delete_bundle(&self, account_id: &str, bundle_id: &str) -> Result<bool> {
+        let deleted = self.conn.execute(
+            "DELETE FROM bundles WHERE bundle_id = ?1 AND account_id = ?2",
+            params![bundle_id, account_id],
+        )?;
+        Ok(deleted > 0)
+    }
+
+    // ── Invites ─────────────────────────────────────────────────────────────
+
+    fn map_invite(row: &rusqlite::Row<'_>) -> rusqlite::Result<InviteRow> {
+        Ok(InviteRow {
+            invite_id: row.get(0)?,
+            token: row.get(1)?,
+            account_id: row.get(2)?,
+            payload: row.get(3)?,
+            expires_at: row.get(4)?,
+            expires_unix: row.get(5)?,
+        })
+    }
+
+    pub fn insert_invite(&self, invite: &InviteRow, created_at: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO invites (invite_id, token, account_id, payload, expires_at, expires_unix,
+                                  created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                invite.invite_id,
+                invite.token,
+                invite.account_id,
+                invite.payload,
+                invite.expires_at,
+                invite.expires_unix,
+                created_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Unexpired invites created by `account_id`, newest first.
+    pub fn list_invites(&self, account_id: &str, now: i64) -> Result<Vec<InviteRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT invite_id, token, account_id, payload, expires_at, expires_unix
+             FROM invites WHERE account_id = ?1 AND expires_unix > ?2
+             ORDER BY created_at DESC, rowid DESC",
+        )?;
+        let rows = stmt
+            .query_map(params![account_id, now], Self::map_invite)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    pub fn invite_by_token(&self, token: &str) -> Result<Option<InviteRow>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT invite_id, token, account_id, payload, expires_at, expires_unix
+                 FROM invites WHERE token = ?1",
+                [token],
+                Self::map_invite,
+            )
+            .optional()?)
+    }
+
+    pub fn delete_invite(&self, token: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM invites WHERE token = ?1", [token])?;
+        Ok(())
+    }
+
+    // ── Housekeeping ────────────────────────────────────────────────────────
+
+    /// Drops expired sessions, challenges, reset tokens and invites, and
+    /// bundles created before `bundle_cutoff`. Returns the rows removed.
+    pub fn purge_expired(&self, now: i64, bundle_cutoff: i64) -> Result<usize> {
+        let mut removed = 0;
+        for table in ["sessions", "challenges", "password_resets"] {
+            removed += self.conn.execute(
+                &format!("DELETE FROM {table} WHERE expires_at <= ?1"),
+                [now],
+            )?;
+        }
+        removed += self
+            .conn
+            .execute("DELETE FROM invites WHERE expires_unix <= ?1", [now])?;
+        removed += self.conn.execute(
+            "DELETE FROM bundles WHERE created_unix < ?1",
+            [bundle_cutoff],
+        )?;
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(id: &str, email: &str) -> AccountRow {
+        AccountRow {
+            account_id: id.to_string(),
+            email: email.to_string(),
+            password_hash: "hash".to_string(),
+            identity_uuid: format!("uuid-{id}"),
+            role: "user".to_string(),
+        }
+    }
+
+    fn bundle<'a>(id: &'a str, account_id: &'a str, created_unix: i64) -> NewBundle<'a> {
+        NewBundle {
+            bundle_id: id,
+            account_id,
+            recipient_device_key: "key",
+            recipient_device_id: None,
+            workspace_id: "ws-1",
+            sender_device_key: "sender",
+            mode: "delta",
+            payload: b"payload",
+            created_at: "2026-01-01T00:00:00Z",
+            created_unix,
+        }
+    }
+
+    #[test]
+    fn test_email_lookup_is_case_insensitive() {
+        let store = Store::open(None).unwrap();
+        store
+            .insert_account(&account("a1", "Alice@Example.com"), "now")
+            .unwrap();
+        let found = store.account_by_email("alice@example.com").unwrap();
+        assert_eq!(found.unwrap().account_id, "a1");
+    }
+
+    #[test]
+    fn test_challenge_is_single_use() {
+        let store = Store::open(None).unwrap();
+        store.put_challenge("key", "nonce", 100).unwrap();
+        assert_eq!(
+            store.take_challenge("key").unwrap(),
+            Some(("nonce".to_string(), 100))
+        );
+        assert_eq!(store.take_challenge("key").unwrap(), None);
+    }
+
+    #[test]
+    fn test_mailbox_stats_and_bundle_ownership() {
+        let store = Store::open(None).unwrap();
+        assert!(store.ensure_mailbox("a1", "ws-1", "now").unwrap());
+        assert!(!store.ensure_mailbox("a1", "ws-1", "now").unwrap());
+        store.insert_bundle(&bundle("b1", "a1", 10)).unwrap();
+        store.insert_bundle(&bundle("b2", "a1", 20)).unwrap();
+
+        let mailboxes = store.list_mailboxes("a1").unwrap();
+        assert_eq!(mailboxes.len(), 1);
+        assert_eq!(mailboxes[0].pending_bundles, 2);
+        assert_eq!(mailboxes[0].storage_used, 14);
+        assert_eq!(store.storage_used("a1").unwrap(), 14);
+
+        assert!(store.bundle_payload("a2", "b1").unwrap().is_none());
+        assert!(!store.delete_bundle("a2", "b1").unwrap());
+        assert!(store.delete_bundle("a1", "b1").unwrap());
+    }
+
+    #[test]
+    fn test_purge_expired() {
+        let store = Store::open(None).unwrap();
+        store.insert_session("s-old", "a1", "k1", 50).unwrap();
+        store.insert_session("s-new", "a1", "k1", 500).unwrap();
+        store.insert_bundle(&bundle("b-old", "a1", 10)).unwrap();
+        store.insert_bundle(&bundle("b-new", "a1", 300)).unwrap();
+
+        assert_eq!(store.purge_expired(100, 200).unwrap(), 2);
+        assert!(store.session("s-old", 0).unwrap().is_none());
+        assert_eq!(
+            store.session("s-new", 100).unwrap(),
+            Some(("a1".to_string(), "k1".to_string()))
+        );
+        let ids: Vec<_> = store
+            .list_bundles("a1")
+            .unwrap()
+            .into_iter()
+            .map(|b| b.bundle_id)
+            .collect();
+        assert_eq!(ids, vec!["b-new"]);
+    }
+}